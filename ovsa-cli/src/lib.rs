@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use sprs::CsVec;
+
+use ovsa::encode::LevelEncoder;
+use ovsa::errors::OVSAError;
+use ovsa::experiments::ExperimentConfig;
+use ovsa::io::{ArtifactKind, Metadata};
+use ovsa::learn::{CentroidClassifier, Samples};
+use ovsa::memory::ItemMemory;
+use ovsa::script::{DEFAULT_TOP_K, Interpreter, Outcome};
+
+
+pub const USAGE: &str = "usage: ovsa-cli <command> [--option value ...]
+
+commands:
+  gen      --out FILE [--labels a,b,c | --count N] [--dimension N] [--n-active N] [--seed N]
+           generate a codebook of random symbols
+  encode   --input FILE --out FILE --codebook FILE [--format text|csv] [--dimension N] [--n-active N] [--seed N]
+           [--ngram N] (text) [--levels N] [--min X] [--max X] (csv)
+           encode 'label<TAB>text' lines or 'value,...,value,label' rows into labelled hypervectors
+  train    --samples FILE --out FILE
+           train a centroid classifier on labelled hypervectors
+  predict  --model FILE --samples FILE
+           classify labelled hypervectors and report the accuracy
+  query    --memory FILE --samples FILE [--k N]
+           find the entries of an item memory most similar to each hypervector
+  migrate  --input FILE
+           upgrade a saved memory, sample list or model to the current file format
+  script   --memory FILE --input FILE [--k N] [--seed N]
+           evaluate statements such as 'x = bind(a, shift(b, 1))' and 'x ~ c' over the symbols of a memory
+  experiment --config FILE [--out DIR]
+           run the experiment described by a JSON config and print its evaluation";
+
+
+/// Errors reported by the command line tool.
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Input(String),
+    Ovsa(OVSAError),
+}
+
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            CliError::Input(message) => write!(f, "invalid input: {}", message),
+            CliError::Ovsa(error) => write!(f, "operation failed: {:?}", error),
+        }
+    }
+}
+
+
+impl From<OVSAError> for CliError {
+    fn from(error: OVSAError) -> Self {
+        CliError::Ovsa(error)
+    }
+}
+
+
+/// The `--name value` options following the command.
+struct Options {
+    values: HashMap<String, String>,
+}
+
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut values = HashMap::new();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let name = flag.strip_prefix("--").ok_or_else(|| CliError::Usage(format!("unexpected argument {}", flag)))?;
+            let value = args.next().ok_or_else(|| CliError::Usage(format!("missing value for {}", flag)))?;
+            values.insert(name.to_string(), value.clone());
+        }
+
+        Ok(Options { values })
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|value| value.as_str())
+    }
+
+    fn required(&self, name: &str) -> Result<&str, CliError> {
+        self.get(name).ok_or_else(|| CliError::Usage(format!("missing required option --{}", name)))
+    }
+
+    fn parse_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, CliError> {
+        match self.get(name) {
+            Some(value) => value.parse().map_err(|_| CliError::Usage(format!("invalid value for --{}: {}", name, value))),
+            None => Ok(default),
+        }
+    }
+
+    /// Reads the dimension and the number of active entries, defaulting to dense codes of half the dimension.
+    fn shape(&self) -> Result<(usize, usize), CliError> {
+        let dimension = self.parse_or("dimension", 10000)?;
+        Ok((dimension, self.parse_or("n-active", dimension / 2)?))
+    }
+
+    fn seed(&self) -> Result<(), CliError> {
+        if let Some(seed) = self.get("seed") {
+            ovsa::rng::set_global_seed(seed.parse().map_err(|_| CliError::Usage(format!("invalid value for --seed: {}", seed)))?);
+        }
+        Ok(())
+    }
+}
+
+
+/// Runs a command given the arguments following the program name.
+/// # Arguments
+/// * `args` - The command name followed by its options.
+/// # Returns
+/// The text to print on standard output.
+pub fn run(args: &[String]) -> Result<String, CliError> {
+    let (command, rest) = args.split_first().ok_or_else(|| CliError::Usage("missing command".to_string()))?;
+    let options = Options::parse(rest)?;
+
+    match command.as_str() {
+        "gen" => generate(&options),
+        "encode" => encode(&options),
+        "train" => train(&options),
+        "predict" => predict(&options),
+        "query" => query(&options),
+        "migrate" => migrate(&options),
+        "script" => script(&options),
+        "experiment" => experiment(&options),
+        _ => Err(CliError::Usage(format!("unknown command {}", command))),
+    }
+}
+
+
+fn generate(options: &Options) -> Result<String, CliError> {
+    let (dimension, n_active) = options.shape()?;
+    options.seed()?;
+
+    let labels: Vec<String> = match options.get("labels") {
+        Some(labels) => labels.split(',').map(|label| label.trim().to_string()).collect(),
+        None => (0..options.parse_or("count", 0usize)?).map(|index| index.to_string()).collect(),
+    };
+    if labels.is_empty() {
+        return Err(CliError::Usage("gen needs --labels or a non-zero --count".to_string()));
+    }
+
+    let mut memory = ItemMemory::new(dimension)?;
+    for label in &labels {
+        memory.symbol(label, n_active)?;
+    }
+    ovsa::io::save_memory(options.required("out")?, &memory)?;
+
+    Ok(format!("generated {} symbols of dimension {}\n", memory.len(), dimension))
+}
+
+
+fn encode(options: &Options) -> Result<String, CliError> {
+    let (dimension, n_active) = options.shape()?;
+    options.seed()?;
+
+    let codebook_path = options.required("codebook")?;
+    let mut codebook = if Path::new(codebook_path).exists() {
+        ovsa::io::load_memory(codebook_path)?
+    } else {
+        ItemMemory::new(dimension)?
+    };
+    if codebook.dimension() != dimension {
+        return Err(CliError::Input(format!("the codebook has dimension {}, not {}", codebook.dimension(), dimension)));
+    }
+
+    let input = fs::read_to_string(options.required("input")?).map_err(|error| CliError::Input(error.to_string()))?;
+    let lines = input.lines().filter(|line| !line.trim().is_empty());
+
+    let format = options.get("format").unwrap_or("text");
+    let mut metadata = Metadata::new(ArtifactKind::Samples, dimension).with_encoder_parameter("format", format);
+    if let Some(seed) = options.get("seed").and_then(|seed| seed.parse().ok()) {
+        metadata = metadata.with_seed(seed);
+    }
+    let samples: Samples = match format {
+        "text" => {
+            let n = options.parse_or("ngram", 3usize)?;
+            metadata = metadata.with_encoder_parameter("ngram", n);
+            lines.map(|line| {
+                let (label, text) = line.split_once('\t').ok_or_else(|| CliError::Input(format!("expected 'label<TAB>text': {}", line)))?;
+                Ok((ovsa::encode::ngrams(text, n, &mut codebook, n_active)?, label.to_string()))
+            }).collect::<Result<_, CliError>>()?
+        }
+        "csv" => {
+            let levels = level_encoder(options, &mut codebook, n_active)?;
+            metadata = metadata
+                .with_encoder_parameter("levels", levels.levels().len())
+                .with_encoder_parameter("min", options.parse_or("min", 0.0)?)
+                .with_encoder_parameter("max", options.parse_or("max", 1.0)?);
+            lines.map(|line| encode_row(line, &levels, &mut codebook, n_active)).collect::<Result<_, CliError>>()?
+        }
+        format => return Err(CliError::Usage(format!("unknown format {}", format))),
+    };
+
+    ovsa::io::save_memory(codebook_path, &codebook)?;
+    ovsa::io::save_samples_with_metadata(options.required("out")?, dimension, &samples, metadata)?;
+
+    Ok(format!("encoded {} samples\n", samples.len()))
+}
+
+
+/// Loads the level vectors from the codebook, generating them on first use so that later runs encode consistently.
+fn level_encoder(options: &Options, codebook: &mut ItemMemory, n_active: usize) -> Result<LevelEncoder, CliError> {
+    let n_levels = options.parse_or("levels", 100usize)?;
+    let label = |level: usize| format!("level:{}", level);
+
+    if codebook.get(&label(0)).is_none() {
+        for (level, vector) in ovsa::encode::levels(codebook.dimension(), n_active, n_levels)?.into_iter().enumerate() {
+            codebook.insert(&label(level), vector)?;
+        }
+    }
+
+    let levels = (0..n_levels)
+        .map(|level| codebook.get(&label(level)).cloned().ok_or_else(|| CliError::Input(format!("the codebook has no {}", label(level)))))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(LevelEncoder::new(options.parse_or("min", 0.0)?, options.parse_or("max", 1.0)?, levels)?)
+}
+
+
+/// Encodes a CSV row of numeric features followed by a label.
+fn encode_row(line: &str, levels: &LevelEncoder, codebook: &mut ItemMemory, n_active: usize) -> Result<(CsVec<i8>, String), CliError> {
+    let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+    let (label, values) = fields.split_last().ok_or_else(|| CliError::Input(format!("empty row: {}", line)))?;
+    let values = values.iter()
+        .map(|value| value.parse().map_err(|_| CliError::Input(format!("not a number: {}", value))))
+        .collect::<Result<Vec<f64>, _>>()?;
+
+    Ok((ovsa::encode::features(&values, levels, codebook, n_active)?, label.to_string()))
+}
+
+
+fn train(options: &Options) -> Result<String, CliError> {
+    let samples_path = options.required("samples")?;
+    let (dimension, samples) = ovsa::io::load_samples(samples_path)?;
+
+    let mut classifier = CentroidClassifier::new(dimension)?;
+    classifier.fit(&samples)?;
+    // the classifier only makes sense for samples encoded the same way, so it records the encoder parameters
+    let mut metadata = Metadata::new(ArtifactKind::Classifier, dimension);
+    if let Some(samples_metadata) = ovsa::io::read_metadata(samples_path)? {
+        metadata.encoder = samples_metadata.encoder;
+    }
+    ovsa::io::save_classifier_with_metadata(options.required("out")?, &classifier, metadata)?;
+
+    Ok(format!("trained {} classes on {} samples\n", classifier.prototypes().len(), samples.len()))
+}
+
+
+fn predict(options: &Options) -> Result<String, CliError> {
+    let (model_path, samples_path) = (options.required("model")?, options.required("samples")?);
+    let classifier = ovsa::io::load_classifier(model_path)?;
+    // the samples must have been encoded like the training samples recorded in the model
+    let model = ovsa::io::read_metadata(model_path)?.unwrap_or_else(|| Metadata::new(ArtifactKind::Classifier, classifier.dimension()));
+    let (_, samples) = ovsa::io::load_samples_expecting(samples_path, &Metadata { kind: ArtifactKind::Samples, ..model })?;
+
+    let mut output = String::new();
+    let mut correct = 0;
+    let vectors: Vec<CsVec<i8>> = samples.iter().map(|(vector, _)| vector.clone()).collect();
+    for ((_, label), prediction) in samples.iter().zip(classifier.predict_batch(&vectors)?) {
+        let (predicted, similarity) = prediction.ok_or_else(|| CliError::Input("the model has no classes".to_string()))?;
+        if predicted == *label {
+            correct += 1;
+        }
+        writeln!(output, "{}\t{}\t{:.4}", label, predicted, similarity).unwrap();
+    }
+    writeln!(output, "accuracy: {:.4}", correct as f64 / samples.len().max(1) as f64).unwrap();
+
+    Ok(output)
+}
+
+
+fn query(options: &Options) -> Result<String, CliError> {
+    let memory = ovsa::io::load_memory(options.required("memory")?)?;
+    let (_, samples) = ovsa::io::load_samples(options.required("samples")?)?;
+    let k = options.parse_or("k", 1usize)?;
+
+    let vectors: Vec<CsVec<i8>> = samples.iter().map(|(vector, _)| vector.clone()).collect();
+    let mut output = String::new();
+    for ((_, label), matches) in samples.iter().zip(memory.query_batch(&vectors, k)?) {
+        write!(output, "{}", label).unwrap();
+        for (matched, similarity) in matches {
+            write!(output, "\t{}:{:.4}", matched, similarity).unwrap();
+        }
+        writeln!(output).unwrap();
+    }
+
+    Ok(output)
+}
+
+
+fn migrate(options: &Options) -> Result<String, CliError> {
+    let version = ovsa::io::migrate_file(options.required("input")?)?;
+    if version == ovsa::io::FORMAT_VERSION {
+        return Ok(format!("already at format version {}\n", version));
+    }
+
+    Ok(format!("migrated from format version {} to {}\n", version, ovsa::io::FORMAT_VERSION))
+}
+
+
+fn script(options: &Options) -> Result<String, CliError> {
+    options.seed()?;
+    let memory = ovsa::io::load_memory(options.required("memory")?)?;
+    let script = fs::read_to_string(options.required("input")?).map_err(|error| CliError::Input(error.to_string()))?;
+
+    let mut interpreter = Interpreter::new(memory).with_top_k(options.parse_or("k", DEFAULT_TOP_K)?);
+    let mut output = String::new();
+    for outcome in interpreter.run(&script)? {
+        if outcome != Outcome::Nothing {
+            writeln!(output, "{}", outcome).unwrap();
+        }
+    }
+
+    Ok(output)
+}
+
+
+fn experiment(options: &Options) -> Result<String, CliError> {
+    let mut config = ExperimentConfig::load(options.required("config")?)?;
+    if let Some(out) = options.get("out") {
+        config.output = Some(out.into());
+    }
+
+    let experiment = ovsa::experiments::run(&config)?;
+    Ok(format!("{}: {} training samples\n{}\n", experiment.report.name, experiment.report.n_train, experiment.evaluation))
+}
@@ -0,0 +1,16 @@
+use std::env;
+use std::process;
+
+use ovsa_cli::{CliError, run};
+
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => print!("{}", output),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(if matches!(error, CliError::Usage(_)) { 2 } else { 1 });
+        }
+    }
+}
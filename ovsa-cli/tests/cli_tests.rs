@@ -0,0 +1,139 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("ovsa-cli-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(args: &[&str]) -> String {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    ovsa_cli::run(&args).unwrap()
+}
+
+
+#[test]
+fn test_gen_and_query() {
+    let dir = temp_dir("gen");
+    let memory = dir.join("memory.json");
+    let memory = memory.to_str().unwrap();
+
+    run(&["gen", "--labels", "red,green,blue", "--dimension", "1000", "--seed", "1", "--out", memory]);
+    // querying a memory with itself finds every entry first
+    let output = run(&["query", "--memory", memory, "--samples", memory]);
+    for line in output.lines() {
+        let (label, best) = line.split_once('\t').unwrap();
+        assert!(best.starts_with(&format!("{}:", label)));
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_text_encode_train_predict() {
+    let dir = temp_dir("text");
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    fs::write(path("train.txt"), "en\tthe quick brown fox jumps over the lazy dog\nde\tder schnelle braune fuchs springt über den faulen hund\n").unwrap();
+    fs::write(path("test.txt"), "en\tthe dog jumps over the fox\nde\tder hund springt über den fuchs\n").unwrap();
+
+    let encode = |input: &str, out: &str| run(&["encode", "--input", &path(input), "--out", &path(out), "--codebook", &path("codebook.json"), "--dimension", "2000", "--seed", "2"]);
+    encode("train.txt", "train.json");
+    encode("test.txt", "test.json");
+    run(&["train", "--samples", &path("train.json"), "--out", &path("model.json")]);
+
+    let output = run(&["predict", "--model", &path("model.json"), "--samples", &path("test.json")]);
+    assert!(output.ends_with("accuracy: 1.0000\n"), "{}", output);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_csv_encode() {
+    let dir = temp_dir("csv");
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    fs::write(path("data.csv"), "0.1,0.9,a\n0.8,0.2,b\n").unwrap();
+
+    let output = run(&["encode", "--format", "csv", "--input", &path("data.csv"), "--out", &path("data.json"), "--codebook", &path("codebook.json"), "--dimension", "1000", "--levels", "10"]);
+    assert_eq!(output, "encoded 2 samples\n");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_usage_errors() {
+    let args: Vec<String> = vec!["nope".to_string()];
+    assert!(matches!(ovsa_cli::run(&args), Err(ovsa_cli::CliError::Usage(_))));
+    let args: Vec<String> = vec!["train".to_string()];
+    assert!(matches!(ovsa_cli::run(&args), Err(ovsa_cli::CliError::Usage(_))));
+}
+
+#[test]
+fn test_predict_rejects_mismatched_encoder() {
+    let dir = temp_dir("mismatch");
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    fs::write(path("data.txt"), "en\tthe quick brown fox\nde\tder schnelle braune fuchs\n").unwrap();
+
+    let encode = |out: &str, ngram: &str| run(&["encode", "--input", &path("data.txt"), "--out", &path(out), "--codebook", &path("codebook.json"), "--dimension", "1000", "--ngram", ngram]);
+    encode("train.json", "3");
+    encode("test.json", "2");
+    run(&["train", "--samples", &path("train.json"), "--out", &path("model.json")]);
+
+    let args: Vec<String> = ["predict", "--model", &path("model.json"), "--samples", &path("test.json")].iter().map(|arg| arg.to_string()).collect();
+    let result = ovsa_cli::run(&args);
+    fs::remove_dir_all(dir).unwrap();
+    assert!(matches!(result, Err(ovsa_cli::CliError::Ovsa(ovsa::errors::OVSAError::IncompatibleArtifact(_)))), "{:?}", result);
+}
+
+#[test]
+fn test_migrate() {
+    let dir = temp_dir("migrate");
+    let memory = dir.join("memory.json");
+    let memory = memory.to_str().unwrap();
+    fs::write(memory, r#"{"dimension": 10, "vectors": [{"label": "a", "indices": [1, 4]}]}"#).unwrap();
+
+    assert_eq!(run(&["migrate", "--input", memory]), format!("migrated from format version 1 to {}\n", ovsa::io::FORMAT_VERSION));
+    assert_eq!(run(&["migrate", "--input", memory]), format!("already at format version {}\n", ovsa::io::FORMAT_VERSION));
+    assert_eq!(ovsa::io::load_memory(memory).unwrap().len(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_script() {
+    let dir = temp_dir("script");
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    run(&["gen", "--labels", "color,shape,red,circle", "--dimension", "2000", "--seed", "3", "--out", &path("memory.json")]);
+    fs::write(path("script.txt"), "# a record of two fields\nrecord = bundle(bind(color, red), bind(shape, circle), random())\n\nunbind(record, color)\nrecord ~ record\n").unwrap();
+
+    let output = run(&["script", "--memory", &path("memory.json"), "--input", &path("script.txt"), "--k", "1", "--seed", "4"]);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "record assigned");
+    assert!(lines[1].starts_with("red:"), "{}", output);
+    assert_eq!(lines[2], "1.0000");
+
+    fs::write(path("bad.txt"), "x = bind(color)\n").unwrap();
+    let args: Vec<String> = ["script", "--memory", &path("memory.json"), "--input", &path("bad.txt")].iter().map(|arg| arg.to_string()).collect();
+    assert!(ovsa_cli::run(&args).is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+
+#[test]
+fn test_experiment() {
+    let dir = temp_dir("experiment");
+    let config = dir.join("config.json");
+    fs::write(&config, r#"{
+        "name": "ranges",
+        "seed": 2,
+        "dataset": {"kind": "numeric",
+            "train": [[[1.0, 2.0], "low"], [[2.0, 1.0], "low"], [[8.0, 9.0], "high"], [[9.0, 8.0], "high"]],
+            "test": [[[1.5, 1.5], "low"], [[8.5, 8.5], "high"]]},
+        "encoder": {"kind": "features", "levels": 4},
+        "model": {"dimension": 1000}
+    }"#).unwrap();
+
+    let out = dir.join("out");
+    let output = run(&["experiment", "--config", config.to_str().unwrap(), "--out", out.to_str().unwrap()]);
+    assert!(output.starts_with("ranges: 4 training samples\naccuracy 1.0000 over 2 samples"), "{}", output);
+    assert!(out.join("report.json").exists() && out.join("classifier.json").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,230 @@
+//! `#[derive(HdEncode)]` and `#[derive(HdDecode)]`: implement `ovsa::encode::schema::HdEncode` and `HdDecode` for
+//! structs with named fields. Categorical fields are encoded with `ToString` and decoded with `FromStr`; skipped fields
+//! are decoded as their `Default`.
+//!
+//! Field attributes:
+//! * `#[hd(min = <f64>, max = <f64>)]` - the range of a numeric field, required for scalars.
+//! * `#[hd(levels = <usize>)]` - the number of levels of a scalar, 16 by default.
+//! * `#[hd(categorical)]` - encodes a numeric field as a category.
+//! * `#[hd(skip)]` - leaves the field out of the record.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitFloat, LitInt, Type, parse_macro_input};
+
+
+const DEFAULT_LEVELS: usize = 16;
+const NUMERIC_TYPES: [&str; 14] = ["f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize"];
+
+
+#[proc_macro_derive(HdEncode, attributes(hd))]
+pub fn derive_hd_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_encode(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+
+#[proc_macro_derive(HdDecode, attributes(hd))]
+pub fn derive_hd_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_decode(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+
+/// The parsed `#[hd(...)]` attributes of a field.
+#[derive(Default)]
+struct FieldAttributes {
+    min: Option<f64>,
+    max: Option<f64>,
+    levels: Option<usize>,
+    categorical: bool,
+    skip: bool,
+}
+
+
+/// How a field is encoded.
+enum Kind {
+    Scalar { min: f64, max: f64, levels: usize },
+    Categorical,
+    Skip,
+}
+
+
+struct ParsedField<'a> {
+    ident: &'a syn::Ident,
+    ty: &'a Type,
+    kind: Kind,
+}
+
+
+fn parse_fields(input: &DeriveInput) -> syn::Result<Vec<ParsedField<'_>>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(input, "HdEncode and HdDecode can only be derived for structs"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(input, "HdEncode and HdDecode require named fields"));
+    };
+
+    let mut parsed = Vec::with_capacity(fields.named.len());
+    for field in &fields.named {
+        let attributes = parse_attributes(field)?;
+        let kind = if attributes.skip {
+            Kind::Skip
+        } else if is_numeric(&field.ty) && !attributes.categorical {
+            let (Some(min), Some(max)) = (attributes.min, attributes.max) else {
+                return Err(syn::Error::new_spanned(field, "numeric fields need #[hd(min = ..., max = ...)] or #[hd(categorical)]"));
+            };
+            Kind::Scalar { min, max, levels: attributes.levels.unwrap_or(DEFAULT_LEVELS) }
+        } else {
+            if attributes.min.is_some() || attributes.max.is_some() || attributes.levels.is_some() {
+                return Err(syn::Error::new_spanned(field, "min, max and levels only apply to numeric fields"));
+            }
+            Kind::Categorical
+        };
+        parsed.push(ParsedField { ident: field.ident.as_ref().expect("Named fields have identifiers."), ty: &field.ty, kind });
+    }
+    Ok(parsed)
+}
+
+
+fn expand_encode(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut specs = Vec::new();
+    let mut values = Vec::new();
+    for field in parse_fields(input)? {
+        let ident = field.ident;
+        let name = ident.to_string();
+        match field.kind {
+            Kind::Scalar { min, max, levels } => {
+                specs.push(quote! {
+                    ::ovsa::encode::schema::FieldSpec { name: #name, kind: ::ovsa::encode::schema::FieldKind::Scalar { min: #min, max: #max, levels: #levels } }
+                });
+                values.push(quote! { ::ovsa::encode::schema::FieldValue::Scalar(self.#ident as f64) });
+            }
+            Kind::Categorical => {
+                specs.push(quote! {
+                    ::ovsa::encode::schema::FieldSpec { name: #name, kind: ::ovsa::encode::schema::FieldKind::Categorical }
+                });
+                values.push(quote! { ::ovsa::encode::schema::FieldValue::Category(::std::string::ToString::to_string(&self.#ident)) });
+            }
+            Kind::Skip => {}
+        }
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::ovsa::encode::schema::HdEncode for #ident #type_generics #where_clause {
+            fn schema() -> ::std::vec::Vec<::ovsa::encode::schema::FieldSpec> {
+                ::std::vec![#(#specs),*]
+            }
+
+            fn field_values(&self) -> ::std::vec::Vec<::ovsa::encode::schema::FieldValue> {
+                ::std::vec![#(#values),*]
+            }
+        }
+    })
+}
+
+
+fn expand_decode(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut initializers = Vec::new();
+    for field in parse_fields(input)? {
+        let ident = field.ident;
+        let ty = field.ty;
+        let name = ident.to_string();
+        let initializer = match field.kind {
+            Kind::Scalar { .. } if is_float(ty) => quote! {
+                match values.next() {
+                    ::std::option::Option::Some(::ovsa::encode::schema::FieldValue::Scalar(value)) => value as #ty,
+                    _ => return ::std::result::Result::Err(::ovsa::encode::schema::mismatched_field(#name)),
+                }
+            },
+            Kind::Scalar { .. } => quote! {
+                match values.next() {
+                    ::std::option::Option::Some(::ovsa::encode::schema::FieldValue::Scalar(value)) => value.round() as #ty,
+                    _ => return ::std::result::Result::Err(::ovsa::encode::schema::mismatched_field(#name)),
+                }
+            },
+            Kind::Categorical => quote! {
+                match values.next() {
+                    ::std::option::Option::Some(::ovsa::encode::schema::FieldValue::Category(value)) => {
+                        value.parse::<#ty>().map_err(|_| ::ovsa::encode::schema::mismatched_field(#name))?
+                    }
+                    _ => return ::std::result::Result::Err(::ovsa::encode::schema::mismatched_field(#name)),
+                }
+            },
+            Kind::Skip => quote! { ::std::default::Default::default() },
+        };
+        initializers.push(quote! { #ident: #initializer });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::ovsa::encode::schema::HdDecode for #ident #type_generics #where_clause {
+            fn from_field_values(values: ::std::vec::Vec<::ovsa::encode::schema::FieldValue>) -> ::std::result::Result<Self, ::ovsa::errors::OVSAError> {
+                let mut values = values.into_iter();
+                ::std::result::Result::Ok(#ident { #(#initializers),* })
+            }
+        }
+    })
+}
+
+
+fn parse_attributes(field: &syn::Field) -> syn::Result<FieldAttributes> {
+    let mut attributes = FieldAttributes::default();
+    for attribute in field.attrs.iter().filter(|attribute| attribute.path().is_ident("hd")) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                attributes.skip = true;
+            } else if meta.path.is_ident("categorical") {
+                attributes.categorical = true;
+            } else if meta.path.is_ident("min") {
+                attributes.min = Some(parse_float(&meta)?);
+            } else if meta.path.is_ident("max") {
+                attributes.max = Some(parse_float(&meta)?);
+            } else if meta.path.is_ident("levels") {
+                attributes.levels = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            } else {
+                return Err(meta.error("unknown hd attribute, expected min, max, levels, categorical or skip"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attributes)
+}
+
+
+/// Parses a float literal, also accepting integers and a leading minus sign.
+fn parse_float(meta: &syn::meta::ParseNestedMeta) -> syn::Result<f64> {
+    let stream = meta.value()?;
+    let negative = stream.peek(syn::Token![-]);
+    if negative {
+        stream.parse::<syn::Token![-]>()?;
+    }
+    let value = if stream.peek(LitInt) {
+        stream.parse::<LitInt>()?.base10_parse::<f64>()?
+    } else {
+        stream.parse::<LitFloat>()?.base10_parse::<f64>()?
+    };
+    Ok(if negative { -value } else { value })
+}
+
+
+fn is_numeric(ty: &Type) -> bool {
+    type_name(ty).is_some_and(|name| NUMERIC_TYPES.contains(&name.as_str()))
+}
+
+
+fn is_float(ty: &Type) -> bool {
+    type_name(ty).is_some_and(|name| name == "f32" || name == "f64")
+}
+
+
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.get_ident().map(ToString::to_string),
+        _ => None,
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+use std::str::FromStr;
+use rand::SeedableRng;
+use ovsa::encode::schema::{FieldKind, FieldValue, HdDecode, HdEncode, SchemaEncoder};
+use ovsa::rng::OvsaRng;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Species {
+    Setosa,
+    Virginica,
+}
+
+
+impl fmt::Display for Species {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+
+impl FromStr for Species {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Setosa" => Ok(Species::Setosa),
+            "Virginica" => Ok(Species::Virginica),
+            _ => Err(()),
+        }
+    }
+}
+
+
+#[derive(Debug, HdEncode, HdDecode)]
+struct Flower {
+    #[hd(min = 0, max = 10.0, levels = 8)]
+    petal_length: f64,
+    #[hd(min = -5, max = 5)]
+    offset: i32,
+    species: Species,
+    #[hd(categorical)]
+    plot: u8,
+    #[hd(skip)]
+    id: u64,
+}
+
+
+fn flower(petal_length: f64, species: Species) -> Flower {
+    Flower { petal_length, offset: 0, species, plot: 3, id: 7 }
+}
+
+
+#[test]
+fn test_derived_schema() {
+    let schema = Flower::schema();
+    let names: Vec<&str> = schema.iter().map(|spec| spec.name).collect();
+    assert_eq!(names, ["petal_length", "offset", "species", "plot"]);
+    assert_eq!(schema[0].kind, FieldKind::Scalar { min: 0.0, max: 10.0, levels: 8 });
+    assert_eq!(schema[1].kind, FieldKind::Scalar { min: -5.0, max: 5.0, levels: 16 });
+    assert_eq!(schema[2].kind, FieldKind::Categorical);
+
+    let values = flower(2.5, Species::Setosa).field_values();
+    assert_eq!(values[0], FieldValue::Scalar(2.5));
+    assert_eq!(values[2], FieldValue::Category("Setosa".to_string()));
+    assert_eq!(values[3], FieldValue::Category("3".to_string()));
+}
+
+#[test]
+fn test_encode_derived_records() {
+    let mut rng = OvsaRng::seed_from_u64(4);
+    let mut encoder = SchemaEncoder::new(2000, 1000).unwrap();
+    let a = encoder.encode_with_rng(&flower(2.0, Species::Setosa), &mut rng).unwrap();
+    let b = encoder.encode_with_rng(&flower(2.2, Species::Setosa), &mut rng).unwrap();
+    let c = encoder.encode_with_rng(&flower(9.0, Species::Virginica), &mut rng).unwrap();
+
+    assert_eq!(a.fields.len(), 4);
+    assert_eq!(a.fields[2].role, "species");
+    assert_eq!(encoder.roles().len(), 4);
+    assert_eq!(encoder.categories().labels(), ["species=Setosa", "plot=3", "species=Virginica"]);
+    let similar = ovsa::binary::similarity(&a.vector, &b.vector).unwrap();
+    let different = ovsa::binary::similarity(&a.vector, &c.vector).unwrap();
+    assert!(similar > different + 0.1, "{similar} {different}");
+}
+
+#[test]
+fn test_decode_derived_records() {
+    let mut rng = OvsaRng::seed_from_u64(5);
+    let mut encoder = SchemaEncoder::new(4000, 2000).unwrap();
+    encoder.encode_with_rng(&flower(9.0, Species::Virginica), &mut rng).unwrap();
+    let record = encoder.encode_with_rng(&Flower { petal_length: 2.9, offset: -4, species: Species::Setosa, plot: 3, id: 7 }, &mut rng).unwrap();
+
+    let decoded = encoder.decode::<Flower>(&record.vector).unwrap();
+    let flower = &decoded.value;
+    assert!((flower.petal_length - 20.0 / 7.0).abs() < 1e-9, "{}", flower.petal_length);
+    assert_eq!(flower.offset, -4);
+    assert_eq!(flower.species, Species::Setosa);
+    assert_eq!(flower.plot, 3);
+    assert_eq!(flower.id, 0);
+
+    let names: Vec<&str> = decoded.fields.iter().map(|field| field.name).collect();
+    assert_eq!(names, ["petal_length", "offset", "species", "plot"]);
+    assert!(decoded.min_confidence() > 0.6, "{:?}", decoded.fields);
+}
+
+#[test]
+fn test_decode_needs_symbols() {
+    let mut rng = OvsaRng::seed_from_u64(6);
+    let record = SchemaEncoder::new(1000, 500).unwrap().encode_with_rng(&flower(1.0, Species::Setosa), &mut rng).unwrap();
+    let encoder = SchemaEncoder::new(1000, 500).unwrap();
+    assert!(matches!(encoder.decode::<Flower>(&record.vector), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+    assert!(Flower::from_field_values(vec![FieldValue::Scalar(1.0)]).is_err());
+}
+
+#[derive(Debug, HdEncode, HdDecode)]
+struct Sample {
+    #[hd(min = 0, max = 100.0, levels = 8)]
+    petal_length: f64,
+}
+
+#[test]
+fn test_same_named_fields_with_different_ranges() {
+    let mut rng = OvsaRng::seed_from_u64(7);
+    let mut encoder = SchemaEncoder::new(4000, 2000).unwrap();
+    encoder.encode_with_rng(&flower(2.5, Species::Setosa), &mut rng).unwrap();
+    let record = encoder.encode_with_rng(&Sample { petal_length: 300.0 / 7.0 }, &mut rng).unwrap();
+
+    // the field keeps its role but gets levels of its own range
+    assert_eq!(encoder.roles().len(), 4);
+    let decoded = encoder.decode::<Sample>(&record.vector).unwrap();
+    assert!((decoded.value.petal_length - 300.0 / 7.0).abs() < 1e-9, "{}", decoded.value.petal_length);
+    let flower = encoder.encode_with_rng(&flower(2.5, Species::Setosa), &mut rng).unwrap();
+    assert!((encoder.decode::<Flower>(&flower.vector).unwrap().value.petal_length - 2.5).abs() < 10.0 / 7.0);
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+use std::io::Read;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use sprs::CsVec;
+
+use ovsa::errors::OVSAError;
+use ovsa::learn::CentroidClassifier;
+use ovsa::memory::ItemMemory;
+use ovsa::memory::concurrent::ConcurrentItemMemory;
+
+
+/// The default limit on the size of a request body, in bytes.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1 << 20;
+
+
+/// Settings of the service: the shape of the generated symbols, where the item memory is persisted,
+/// the classifier answering `/classify`, if any, and the largest accepted request body.
+#[derive(Debug, Clone)]
+pub struct ServiceConfig {
+    pub dimension: usize,
+    pub n_active: usize,
+    pub memory_path: Option<PathBuf>,
+    pub model_path: Option<PathBuf>,
+    pub max_body_bytes: usize,
+}
+
+
+/// Errors returned by the service, each mapped to an HTTP status code.
+#[derive(Debug)]
+pub enum ServiceError {
+    NotFound(String),
+    NotImplemented(String),
+    PayloadTooLarge(usize),
+    BadRequest(String),
+    Ovsa(OVSAError),
+}
+
+
+impl ServiceError {
+    /// Returns the HTTP status code corresponding to the error.
+    pub fn status(&self) -> u16 {
+        match self {
+            ServiceError::NotFound(_) => 404,
+            ServiceError::NotImplemented(_) => 501,
+            ServiceError::PayloadTooLarge(_) => 413,
+            ServiceError::Ovsa(OVSAError::Io(_)) => 500,
+            ServiceError::BadRequest(_) | ServiceError::Ovsa(_) => 400,
+        }
+    }
+}
+
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::NotFound(path) => write!(f, "unknown endpoint {}", path),
+            ServiceError::NotImplemented(message) => write!(f, "not available: {}", message),
+            ServiceError::PayloadTooLarge(limit) => write!(f, "the request body exceeds {} bytes", limit),
+            ServiceError::BadRequest(message) => write!(f, "bad request: {}", message),
+            ServiceError::Ovsa(error) => write!(f, "operation failed: {:?}", error),
+        }
+    }
+}
+
+
+impl From<OVSAError> for ServiceError {
+    fn from(error: OVSAError) -> Self {
+        ServiceError::Ovsa(error)
+    }
+}
+
+
+impl From<serde_json::Error> for ServiceError {
+    fn from(error: serde_json::Error) -> Self {
+        ServiceError::BadRequest(error.to_string())
+    }
+}
+
+
+#[derive(Deserialize)]
+struct LabelRequest {
+    label: String,
+}
+
+#[derive(Deserialize)]
+struct InsertRequest {
+    label: String,
+    vector: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct BindRequest {
+    a: Vec<usize>,
+    b: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct BundleRequest {
+    vectors: Vec<Vec<usize>>,
+}
+
+#[derive(Deserialize)]
+struct ClassifyRequest {
+    vector: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct CleanupRequest {
+    vector: Vec<usize>,
+    #[serde(default = "default_k")]
+    k: usize,
+}
+
+fn default_k() -> usize {
+    1
+}
+
+#[derive(Serialize)]
+struct VectorResponse {
+    vector: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct Match {
+    label: String,
+    similarity: f64,
+}
+
+#[derive(Serialize)]
+struct CleanupResponse {
+    matches: Vec<Match>,
+}
+
+
+/// The request handlers of the server, independent of the HTTP transport.
+/// Binary sparse hypervectors are exchanged as JSON arrays of their active indices.
+pub struct Service {
+    config: ServiceConfig,
+    memory: ConcurrentItemMemory,
+    classifier: Option<CentroidClassifier>,
+}
+
+
+impl Service {
+    /// Creates the service, loading the persisted item memory if its file exists and the classifier if one is configured.
+    /// # Arguments
+    /// * `config` - The service settings.
+    /// # Returns
+    /// A new `Service`.
+    pub fn new(config: ServiceConfig) -> Result<Self, ServiceError> {
+        let memory = match config.memory_path.as_ref().filter(|path| path.exists()) {
+            Some(path) => ovsa::io::load_memory(path)?,
+            None => ItemMemory::new(config.dimension)?,
+        };
+        if memory.dimension() != config.dimension {
+            return Err(ServiceError::Ovsa(OVSAError::IncompatibleArtifact(format!("the stored memory has dimension {}, the service {}", memory.dimension(), config.dimension))));
+        }
+
+        let classifier = config.model_path.as_ref().map(ovsa::io::load_classifier).transpose()?;
+        if let Some(classifier) = &classifier && classifier.dimension() != config.dimension {
+            return Err(ServiceError::Ovsa(OVSAError::IncompatibleArtifact(format!("the model has dimension {}, the service {}", classifier.dimension(), config.dimension))));
+        }
+
+        Ok(Service { config, memory: ConcurrentItemMemory::from_memory(memory), classifier })
+    }
+
+    /// Reads a request body, stopping as soon as it exceeds the configured limit so that an oversized or endless body
+    /// is never buffered in full.
+    /// # Arguments
+    /// * `reader` - The body of the HTTP request.
+    /// # Returns
+    /// The body, `PayloadTooLarge` if it exceeds the limit, or `BadRequest` if it cannot be read or is not UTF-8.
+    pub fn read_body(&self, reader: impl Read) -> Result<String, ServiceError> {
+        let limit = self.config.max_body_bytes;
+        let mut body = Vec::new();
+        reader.take(limit as u64 + 1).read_to_end(&mut body).map_err(|error| ServiceError::BadRequest(error.to_string()))?;
+        if body.len() > limit {
+            return Err(ServiceError::PayloadTooLarge(limit));
+        }
+
+        String::from_utf8(body).map_err(|error| ServiceError::BadRequest(error.to_string()))
+    }
+
+    /// Dispatches a request body to the handler of the given endpoint.
+    /// # Arguments
+    /// * `path` - The endpoint, e.g. `/bind`.
+    /// * `body` - The JSON request body.
+    /// # Returns
+    /// The JSON response body.
+    pub fn handle(&self, path: &str, body: &str) -> Result<String, ServiceError> {
+        match path {
+            "/encode" => self.encode(serde_json::from_str(body)?),
+            "/insert" => self.insert(serde_json::from_str(body)?),
+            "/bind" => self.bind(serde_json::from_str(body)?),
+            "/bundle" => self.bundle(serde_json::from_str(body)?),
+            "/cleanup" => self.cleanup(serde_json::from_str(body)?),
+            "/classify" => self.classify(serde_json::from_str(body)?),
+            _ => Err(ServiceError::NotFound(path.to_string())),
+        }
+    }
+
+    fn encode(&self, request: LabelRequest) -> Result<String, ServiceError> {
+        if let Some(vector) = self.memory.get(&request.label) {
+            return respond(&VectorResponse { vector: vector.indices().to_vec() });
+        }
+
+        let vector = self.memory.try_update(|memory| {
+            // another request may have created the symbol since the lookup above
+            if memory.get(&request.label).is_none() {
+                memory.symbol(&request.label, self.config.n_active)?;
+                self.persist(memory)?;
+            }
+            Ok::<_, ServiceError>(memory.get(&request.label).unwrap().indices().to_vec())
+        })?;
+
+        respond(&VectorResponse { vector })
+    }
+
+    fn insert(&self, request: InsertRequest) -> Result<String, ServiceError> {
+        let vector = to_vector(self.config.dimension, request.vector)?;
+        self.memory.try_update(|memory| {
+            memory.insert(&request.label, vector)?;
+            self.persist(memory)
+        })?;
+
+        Ok("{}".to_string())
+    }
+
+    fn bind(&self, request: BindRequest) -> Result<String, ServiceError> {
+        let a = to_vector(self.config.dimension, request.a)?;
+        let b = to_vector(self.config.dimension, request.b)?;
+
+        respond(&VectorResponse { vector: ovsa::binary::xor(&a, &b)?.indices().to_vec() })
+    }
+
+    fn bundle(&self, request: BundleRequest) -> Result<String, ServiceError> {
+        let vectors = request.vectors.into_iter()
+            .map(|indices| to_vector(self.config.dimension, indices))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        respond(&VectorResponse { vector: ovsa::binary::consensus_sum(&vectors)?.indices().to_vec() })
+    }
+
+    fn cleanup(&self, request: CleanupRequest) -> Result<String, ServiceError> {
+        let query = to_vector(self.config.dimension, request.vector)?;
+        let matches = self.memory.query(&query, request.k)?;
+
+        respond(&CleanupResponse {
+            matches: matches.into_iter().map(|(label, similarity)| Match { label, similarity }).collect(),
+        })
+    }
+
+    fn classify(&self, request: ClassifyRequest) -> Result<String, ServiceError> {
+        let classifier = self.classifier.as_ref().ok_or_else(|| ServiceError::NotImplemented("no classifier model is configured".to_string()))?;
+        let query = to_vector(self.config.dimension, request.vector)?;
+        match classifier.predict(&query)? {
+            Some((label, similarity)) => respond(&Match { label, similarity }),
+            None => Err(ServiceError::BadRequest("the model has no classes".to_string())),
+        }
+    }
+
+    fn persist(&self, memory: &ItemMemory) -> Result<(), ServiceError> {
+        if let Some(path) = &self.config.memory_path {
+            ovsa::io::save_memory(path, memory)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Validates a list of active indices received from a client and converts it to a sparse binary vector.
+fn to_vector(dimension: usize, mut indices: Vec<usize>) -> Result<CsVec<i8>, ServiceError> {
+    indices.sort_unstable();
+    indices.dedup();
+    if indices.last().is_some_and(|&index| index >= dimension) {
+        return Err(ServiceError::BadRequest(format!("indices must be smaller than the dimension {}", dimension)));
+    }
+
+    Ok(ovsa::binary::from_indices(dimension, &indices)?)
+}
+
+
+fn respond<T: Serialize>(response: &T) -> Result<String, ServiceError> {
+    Ok(serde_json::to_string(response)?)
+}
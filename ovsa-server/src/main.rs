@@ -0,0 +1,86 @@
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+use tiny_http::{Header, Method, Response, Server};
+
+use ovsa_server::{DEFAULT_MAX_BODY_BYTES, Service, ServiceConfig};
+
+
+const USAGE: &str = "usage: ovsa-server [--addr HOST:PORT] [--dimension N] [--n-active N] [--memory FILE] [--model FILE] [--max-body-bytes N] [--threads N]";
+
+
+fn main() {
+    let mut addr = "127.0.0.1:8080".to_string();
+    let mut threads: usize = 4;
+    let mut config = ServiceConfig { dimension: 10000, n_active: 100, memory_path: None, model_path: None, max_body_bytes: DEFAULT_MAX_BODY_BYTES };
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().unwrap_or_else(|| exit_with_usage());
+        match flag.as_str() {
+            "--addr" => addr = value,
+            "--dimension" => config.dimension = value.parse().unwrap_or_else(|_| exit_with_usage()),
+            "--n-active" => config.n_active = value.parse().unwrap_or_else(|_| exit_with_usage()),
+            "--memory" => config.memory_path = Some(PathBuf::from(value)),
+            "--model" => config.model_path = Some(PathBuf::from(value)),
+            "--max-body-bytes" => config.max_body_bytes = value.parse().unwrap_or_else(|_| exit_with_usage()),
+            "--threads" => threads = value.parse().unwrap_or_else(|_| exit_with_usage()),
+            _ => exit_with_usage(),
+        }
+    }
+
+    let service = Arc::new(Service::new(config).unwrap_or_else(|error| {
+        eprintln!("failed to start service: {}", error);
+        process::exit(1);
+    }));
+    let server = Arc::new(Server::http(&addr).unwrap_or_else(|error| {
+        eprintln!("failed to bind {}: {}", addr, error);
+        process::exit(1);
+    }));
+    eprintln!("ovsa-server listening on {}", addr);
+
+    let workers: Vec<_> = (0..threads.max(1)).map(|_| {
+        let service = Arc::clone(&service);
+        let server = Arc::clone(&server);
+        thread::spawn(move || serve(&server, &service))
+    }).collect();
+
+    for worker in workers {
+        worker.join().expect("Worker thread panicked.");
+    }
+}
+
+
+/// Answers requests until the server shuts down. Every endpoint expects a JSON body sent with POST.
+fn serve(server: &Server, service: &Service) {
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+
+    for mut request in server.incoming_requests() {
+        let (status, payload) = if *request.method() != Method::Post {
+            (405, error_payload("only POST is supported"))
+        } else {
+            match service.read_body(request.as_reader()).and_then(|body| service.handle(request.url(), &body)) {
+                Ok(payload) => (200, payload),
+                Err(error) => (error.status(), error_payload(&error.to_string())),
+            }
+        };
+
+        let response = Response::from_string(payload).with_status_code(status).with_header(content_type.clone());
+        if let Err(error) = request.respond(response) {
+            eprintln!("failed to send response: {}", error);
+        }
+    }
+}
+
+
+fn error_payload(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
@@ -0,0 +1,106 @@
+use std::env;
+use std::fs;
+use serde_json::Value;
+
+use ovsa_server::{DEFAULT_MAX_BODY_BYTES, Service, ServiceConfig};
+
+
+fn service(memory_path: Option<std::path::PathBuf>) -> Service {
+    Service::new(ServiceConfig { dimension: 1000, n_active: 50, memory_path, model_path: None, max_body_bytes: DEFAULT_MAX_BODY_BYTES }).unwrap()
+}
+
+fn vector(response: &str) -> Value {
+    serde_json::from_str::<Value>(response).unwrap()["vector"].clone()
+}
+
+
+#[test]
+fn test_encode_is_stable() {
+    let service = service(None);
+    let first = service.handle("/encode", r#"{"label": "red"}"#).unwrap();
+    let second = service.handle("/encode", r#"{"label": "red"}"#).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(vector(&first).as_array().unwrap().len(), 50);
+}
+
+#[test]
+fn test_bind_and_cleanup() {
+    let service = service(None);
+    let red = vector(&service.handle("/encode", r#"{"label": "red"}"#).unwrap());
+    let color = vector(&service.handle("/encode", r#"{"label": "color"}"#).unwrap());
+
+    let bound = vector(&service.handle("/bind", &format!(r#"{{"a": {}, "b": {}}}"#, red, color)).unwrap());
+    let unbound = vector(&service.handle("/bind", &format!(r#"{{"a": {}, "b": {}}}"#, bound, color)).unwrap());
+
+    let response: Value = serde_json::from_str(&service.handle("/cleanup", &format!(r#"{{"vector": {}}}"#, unbound)).unwrap()).unwrap();
+    assert_eq!(response["matches"][0]["label"], "red");
+    assert_eq!(response["matches"][0]["similarity"], 1.0);
+}
+
+#[test]
+fn test_classify_uses_the_configured_model() {
+    let samples: Vec<_> = [("low", 0), ("low", 5), ("high", 500), ("high", 505)].iter()
+        .map(|&(label, start)| (ovsa::binary::from_indices(1000, &(start..start + 50).collect::<Vec<_>>()).unwrap(), label.to_string()))
+        .collect();
+    let mut classifier = ovsa::learn::CentroidClassifier::new(1000).unwrap();
+    classifier.fit(&samples).unwrap();
+    let path = env::temp_dir().join(format!("ovsa-server-model-{}.json", std::process::id()));
+    ovsa::io::save_classifier(&path, &classifier).unwrap();
+
+    let service = Service::new(ServiceConfig { dimension: 1000, n_active: 50, memory_path: None, model_path: Some(path.clone()), max_body_bytes: DEFAULT_MAX_BODY_BYTES });
+    let other_dimension = Service::new(ServiceConfig { dimension: 2000, n_active: 50, memory_path: None, model_path: Some(path.clone()), max_body_bytes: DEFAULT_MAX_BODY_BYTES });
+    fs::remove_file(&path).unwrap();
+
+    // the item memory plays no part in classification
+    let service = service.unwrap();
+    service.handle("/insert", r#"{"label": "decoy", "vector": [502, 503, 504]}"#).unwrap();
+    let vector: Vec<usize> = (502..552).collect();
+    let response: Value = serde_json::from_str(&service.handle("/classify", &format!(r#"{{"vector": {:?}}}"#, vector)).unwrap()).unwrap();
+    assert_eq!(response["label"], "high");
+    assert_eq!(other_dimension.err().unwrap().status(), 400);
+}
+
+#[test]
+fn test_classify_without_model_is_not_implemented() {
+    let service = service(None);
+    service.handle("/encode", r#"{"label": "red"}"#).unwrap();
+    assert_eq!(service.handle("/classify", r#"{"vector": [1, 2]}"#).unwrap_err().status(), 501);
+}
+
+#[test]
+fn test_unknown_endpoint_and_bad_request() {
+    let service = service(None);
+    assert_eq!(service.handle("/nope", "{}").unwrap_err().status(), 404);
+    assert_eq!(service.handle("/bind", r#"{"a": [1], "b": [5000]}"#).unwrap_err().status(), 400);
+}
+
+#[test]
+fn test_failed_persist_does_not_change_the_memory() {
+    let path = env::temp_dir().join(format!("ovsa-server-missing-{}", std::process::id())).join("memory.json");
+    let service = service(Some(path));
+    let red = service.handle("/encode", r#"{"label": "red"}"#).unwrap_err();
+    let inserted = service.handle("/insert", r#"{"label": "blue", "vector": [1, 2]}"#).unwrap_err();
+    let matches: Value = serde_json::from_str(&service.handle("/cleanup", r#"{"vector": [1, 2], "k": 5}"#).unwrap()).unwrap();
+
+    assert_eq!((red.status(), inserted.status()), (500, 500));
+    assert_eq!(matches["matches"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn test_request_bodies_are_limited() {
+    let service = Service::new(ServiceConfig { dimension: 1000, n_active: 50, memory_path: None, model_path: None, max_body_bytes: 16 }).unwrap();
+    assert_eq!(service.read_body(&b"{\"label\": \"red\"}"[..]).unwrap(), r#"{"label": "red"}"#);
+    assert_eq!(service.read_body(&b"{\"label\": \"green\"}"[..]).unwrap_err().status(), 413);
+    // an endless body is cut off at the limit instead of being read to the end
+    assert_eq!(service.read_body(std::io::repeat(b' ')).unwrap_err().status(), 413);
+    assert_eq!(service.read_body(&[0xff, 0xfe][..]).unwrap_err().status(), 400);
+}
+
+#[test]
+fn test_memory_is_persisted() {
+    let path = env::temp_dir().join(format!("ovsa-server-test-{}.json", std::process::id()));
+    let first = service(Some(path.clone())).handle("/encode", r#"{"label": "red"}"#).unwrap();
+    let reloaded = service(Some(path.clone())).handle("/encode", r#"{"label": "red"}"#).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(first, reloaded);
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "sparse")]
+
+//! Compares the `RoaringBinary` backend to `CsVec<i8>` on binding, Hamming distances and item memory queries across
+//! densities. Run with `cargo bench -p ovsa --features roaring --bench roaring`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use rand::SeedableRng;
+use sprs::CsVec;
+
+use ovsa::binary::roaring::RoaringBinary;
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+const DIMENSION: usize = 1 << 18;
+
+const DENSITIES: [f64; 5] = [0.001, 0.01, 0.05, 0.1, 0.3];
+
+const N_ITEMS: usize = 200;
+
+
+/// Returns the mean time of a call, repeating it for at least 200 ms after a warm-up.
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    black_box(f());
+    let start = Instant::now();
+    let mut n_calls = 0;
+    while start.elapsed() < Duration::from_millis(200) {
+        black_box(f());
+        n_calls += 1;
+    }
+    start.elapsed() / n_calls
+}
+
+
+fn report(operation: &str, density: f64, sparse: Duration, roaring: Duration) {
+    println!("{:<8} {:>7.3} {:>14.1?} {:>14.1?} {:>8.1}x", operation, density, sparse, roaring, sparse.as_secs_f64() / roaring.as_secs_f64());
+}
+
+
+fn main() {
+    let mut rng = OvsaRng::seed_from_u64(0);
+    println!("dimension {}, {} items per memory", DIMENSION, N_ITEMS);
+    println!("{:<8} {:>7} {:>14} {:>14} {:>9}", "", "density", "CsVec<i8>", "RoaringBinary", "speedup");
+
+    for density in DENSITIES {
+        let n_active = (DIMENSION as f64 * density) as usize;
+        let mut memory = ItemMemory::new(DIMENSION).unwrap();
+        for i in 0..N_ITEMS {
+            memory.insert(&format!("item{}", i), ovsa::binary::sparse_random_with_rng(DIMENSION, n_active, &mut rng).unwrap()).unwrap();
+        }
+        let roaring_memory = memory.roaring().unwrap();
+        let (a, b): (&CsVec<i8>, &CsVec<i8>) = (memory.get("item0").unwrap(), memory.get("item1").unwrap());
+        let (roaring_a, roaring_b) = (RoaringBinary::try_from(a).unwrap(), RoaringBinary::try_from(b).unwrap());
+
+        report("xor", density, time(|| ovsa::binary::xor(a, b).unwrap()), time(|| roaring_a.xor(&roaring_b).unwrap()));
+        report("hamming", density, time(|| ovsa::binary::hamming_distance(a, b)), time(|| roaring_a.hamming_distance(&roaring_b).unwrap()));
+        report("query", density, time(|| memory.query(a, 5).unwrap()), time(|| roaring_memory.query(&roaring_a, 5).unwrap()));
+        println!("{:<8} {:>7.3} {:>13}B {:>13}B", "memory", density, memory.memory_usage().vector_bytes, roaring_memory.memory_usage().vector_bytes);
+    }
+}
@@ -0,0 +1,115 @@
+//! Operation counts and memory traffic of one inference, derived from the model, the dimension and the structure of
+//! the pipeline, to compare representations for hardware without running them. The counts follow the algorithms of
+//! this crate: binary vectors are compared as packed 64-bit words, and dense binding is the direct O(d²) circular
+//! convolution of `dense::bind_accumulate`.
+
+use std::fmt;
+use serde::Serialize;
+
+use crate::analysis::Model;
+use crate::errors::OVSAError;
+
+
+/// The structure of an inference: how an input is encoded and how many prototypes it is compared with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Pipeline {
+    /// The number of role-filler bindings encoding an input, e.g. the fields of a record or the n-grams of a text.
+    pub n_bindings: usize,
+    /// The number of permutations applied while encoding, e.g. the position shifts of n-gram characters.
+    pub n_permutations: usize,
+    /// The number of prototypes the encoded input is compared with.
+    pub n_prototypes: usize,
+}
+
+
+/// The estimated cost of one inference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+pub struct InferenceCost {
+    /// 64-bit XOR operations.
+    pub xors: u64,
+    /// 64-bit population counts.
+    pub popcounts: u64,
+    /// Floating point multiply-accumulates.
+    pub macs: u64,
+    /// Counter increments and floating point additions of bundling.
+    pub additions: u64,
+    /// Bytes read from memory: symbols, counters and prototypes.
+    pub bytes_read: u64,
+    /// Bytes written to memory: bound and permuted vectors and counters.
+    pub bytes_written: u64,
+}
+
+
+impl InferenceCost {
+    /// Returns the total number of operations, counting every kind alike.
+    pub fn operations(&self) -> u64 {
+        self.xors + self.popcounts + self.macs + self.additions
+    }
+
+    /// Returns the total memory traffic in bytes.
+    pub fn traffic(&self) -> u64 {
+        self.bytes_read + self.bytes_written
+    }
+}
+
+
+impl fmt::Display for InferenceCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<14} {:>14}", "xors", self.xors)?;
+        writeln!(f, "{:<14} {:>14}", "popcounts", self.popcounts)?;
+        writeln!(f, "{:<14} {:>14}", "macs", self.macs)?;
+        writeln!(f, "{:<14} {:>14}", "additions", self.additions)?;
+        writeln!(f, "{:<14} {:>14}", "bytes read", self.bytes_read)?;
+        write!(f, "{:<14} {:>14}", "bytes written", self.bytes_written)
+    }
+}
+
+
+/// Estimates the operations and memory traffic of encoding one input and comparing it with the prototypes.
+/// Sparse binary models bind and compare packed words, so their cost does not depend on the number of active entries,
+/// and bundle with one counter per dimension. HRR binds by direct circular convolution, bundles by addition and
+/// compares by cosine against prototypes whose norms are precomputed.
+/// # Arguments
+/// * `model` - The model.
+/// * `dimension` - The dimension of the vectors.
+/// * `pipeline` - The structure of the inference.
+/// # Returns
+/// The `InferenceCost` of one inference.
+pub fn inference_cost(model: Model, dimension: usize, pipeline: &Pipeline) -> Result<InferenceCost, OVSAError> {
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    let d = dimension as u64;
+    let bindings = pipeline.n_bindings as u64;
+    let permutations = pipeline.n_permutations as u64;
+    let prototypes = pipeline.n_prototypes as u64;
+    Ok(match model {
+        Model::SparseBinary { .. } => {
+            let words = dimension.div_ceil(64) as u64;
+            let vector_bytes = 8 * words;
+            // every binding reads a role and a filler and adds the result to 32-bit counters
+            InferenceCost {
+                xors: bindings * words + prototypes * words,
+                popcounts: prototypes * words,
+                macs: 0,
+                additions: bindings * d,
+                bytes_read: 2 * bindings * vector_bytes + permutations * vector_bytes + 4 * d + prototypes * vector_bytes,
+                bytes_written: bindings * vector_bytes + permutations * vector_bytes + 4 * d + vector_bytes,
+            }
+        }
+        #[cfg(feature = "dense")]
+        Model::Hrr => {
+            let vector_bytes = 4 * d;
+            // convolutions accumulate straight into the bundle, and the query norm is computed once
+            InferenceCost {
+                xors: 0,
+                popcounts: 0,
+                macs: bindings * d * d + prototypes * d + d,
+                additions: 0,
+                bytes_read: 2 * bindings * vector_bytes + permutations * vector_bytes + prototypes * vector_bytes,
+                bytes_written: vector_bytes + permutations * vector_bytes,
+            }
+        }
+    })
+}
@@ -0,0 +1,732 @@
+pub mod cost;
+
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::Path;
+#[cfg(feature = "dense")]
+use ndarray::Array1;
+use rand::Rng;
+use serde::Serialize;
+use sprs::CsVec;
+
+use crate::binary;
+#[cfg(feature = "dense")]
+use crate::dense;
+use crate::errors::OVSAError;
+use crate::hypervector::Hypervector;
+use crate::learn::CentroidClassifier;
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+
+
+/// A histogram of similarity values over equal-width bins.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Histogram {
+    /// The bin edges, one more than the number of bins.
+    pub edges: Vec<f64>,
+    /// The number of values falling into each bin.
+    pub counts: Vec<usize>,
+}
+
+
+/// Computes the similarity of every pair of entries of an item memory.
+/// # Arguments
+/// * `memory` - The item memory.
+/// # Returns
+/// The square similarity matrix, rows and columns in the memory's insertion order.
+pub fn similarity_matrix<V: Hypervector>(memory: &ItemMemory<V>) -> Result<Vec<Vec<f64>>, OVSAError> {
+    let vectors: Vec<&V> = memory.iter().map(|(_, vector)| vector).collect();
+    vectors.iter()
+        .map(|a| vectors.iter().map(|b| a.similarity(b)).collect())
+        .collect()
+}
+
+
+/// Collects the similarities of all distinct pairs of entries, e.g. as input to `histogram`.
+/// # Arguments
+/// * `memory` - The item memory.
+/// # Returns
+/// The n * (n - 1) / 2 pairwise similarities.
+pub fn pairwise_similarities<V: Hypervector>(memory: &ItemMemory<V>) -> Result<Vec<f64>, OVSAError> {
+    let vectors: Vec<&V> = memory.iter().map(|(_, vector)| vector).collect();
+    let mut similarities = Vec::with_capacity(vectors.len() * vectors.len().saturating_sub(1) / 2);
+    for (i, a) in vectors.iter().enumerate() {
+        for b in &vectors[i + 1..] {
+            similarities.push(a.similarity(b)?);
+        }
+    }
+
+    Ok(similarities)
+}
+
+
+/// Collects the similarities between every entry of a memory and every given vector,
+/// e.g. random vectors to obtain the baseline distribution of unrelated vectors.
+/// # Arguments
+/// * `memory` - The item memory.
+/// * `others` - The vectors to compare to.
+/// # Returns
+/// The similarities, grouped by memory entry.
+pub fn cross_similarities<V: Hypervector>(memory: &ItemMemory<V>, others: &[V]) -> Result<Vec<f64>, OVSAError> {
+    let mut similarities = Vec::with_capacity(memory.len() * others.len());
+    for (_, vector) in memory.iter() {
+        for other in others {
+            similarities.push(vector.similarity(other)?);
+        }
+    }
+
+    Ok(similarities)
+}
+
+
+/// Counts values into equal-width bins spanning `[min, max]`. Values outside the range are clamped into the outer bins.
+/// # Arguments
+/// * `values` - The values to count.
+/// * `n_bins` - The number of bins.
+/// * `min` - The lower edge of the first bin.
+/// * `max` - The upper edge of the last bin.
+/// # Returns
+/// The `Histogram`.
+pub fn histogram(values: &[f64], n_bins: usize, min: f64, max: f64) -> Result<Histogram, OVSAError> {
+    if n_bins == 0 {
+        return Err(OVSAError::EmptyVectorList);
+    }
+
+    let width = (max - min) / n_bins as f64;
+    let edges = (0..=n_bins).map(|bin| min + bin as f64 * width).collect();
+    let mut counts = vec![0; n_bins];
+    for &value in values {
+        let bin = if width > 0.0 { ((value - min) / width).floor().max(0.0) as usize } else { 0 };
+        counts[bin.min(n_bins - 1)] += 1;
+    }
+
+    Ok(Histogram { edges, counts })
+}
+
+
+/// Writes the similarity matrix of an item memory as CSV, with the labels as header row and first column.
+/// # Arguments
+/// * `path` - The file to write.
+/// * `memory` - The item memory.
+pub fn export_similarity_csv<V: Hypervector>(path: impl AsRef<Path>, memory: &ItemMemory<V>) -> Result<(), OVSAError> {
+    let matrix = similarity_matrix(memory)?;
+
+    let mut csv = String::new();
+    for label in memory.labels() {
+        write!(csv, ",{}", csv_field(label)).unwrap();
+    }
+    csv.push('\n');
+    for (label, row) in memory.labels().iter().zip(matrix) {
+        csv.push_str(&csv_field(label));
+        for similarity in row {
+            write!(csv, ",{}", similarity).unwrap();
+        }
+        csv.push('\n');
+    }
+
+    fs::write(path, csv).map_err(|error| OVSAError::Io(error.to_string()))
+}
+
+
+/// Writes a histogram as JSON with `edges` and `counts` arrays.
+/// # Arguments
+/// * `path` - The file to write.
+/// * `histogram` - The histogram.
+pub fn export_histogram(path: impl AsRef<Path>, histogram: &Histogram) -> Result<(), OVSAError> {
+    let json = serde_json::to_string(histogram).map_err(|error| OVSAError::InvalidFormat(error.to_string()))?;
+    fs::write(path, json).map_err(|error| OVSAError::Io(error.to_string()))
+}
+
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+
+/// Scores every dimension by how well it tells the classes of a trained classifier apart, e.g. to build a
+/// `mask::DimensionMask` keeping the best dimensions. The score is the Fisher ratio of the per-class activation rates:
+/// their variance between classes, weighted by class size, over the mean Bernoulli variance within the classes.
+/// Instead of dropping dimensions, the scores can also weight them in `ItemMemory::query_weighted`.
+/// # Arguments
+/// * `classifier` - The trained classifier.
+/// # Returns
+/// One score per dimension, higher meaning more discriminative; 0 for dimensions that are constant across classes.
+pub fn dimension_scores(classifier: &CentroidClassifier) -> Result<Vec<f64>, OVSAError> {
+    let classes: Vec<(&[u32], usize)> = classifier.prototypes().labels().iter()
+        .filter_map(|label| classifier.counts(label))
+        .filter(|&(_, n)| n > 0)
+        .collect();
+    if classes.is_empty() {
+        return Err(OVSAError::EmptyVectorList);
+    }
+
+    let total: usize = classes.iter().map(|&(_, n)| n).sum();
+    let scores = (0..classifier.dimension())
+        .map(|index| {
+            let overall = classes.iter().map(|(counts, _)| counts[index] as f64).sum::<f64>() / total as f64;
+            let (mut between, mut within) = (0.0, 0.0);
+            for &(counts, n) in &classes {
+                let rate = counts[index] as f64 / n as f64;
+                between += n as f64 * (rate - overall).powi(2);
+                within += n as f64 * rate * (1.0 - rate);
+            }
+            // a small floor keeps perfectly separating dimensions finite
+            between / (within + 1e-9)
+        })
+        .collect();
+    Ok(scores)
+}
+
+
+/// Finds the permutation from a candidate set that maps a vector closest to a target, e.g. to decode which of several
+/// role permutations was applied. See `binary::best_shift` and `dense::best_shift` to search all cyclic shifts at once.
+/// # Arguments
+/// * `vec` - The vector to permute.
+/// * `target` - The vector to align to.
+/// * `candidates` - The candidate permutations.
+/// # Returns
+/// The position of the best candidate, the first one on ties, and the similarity of the permuted vector to the target,
+/// or `None` if there are no candidates.
+pub fn best_permutation<V: Hypervector, F: Fn(&V) -> V>(vec: &V, target: &V, candidates: &[F]) -> Result<Option<(usize, f64)>, OVSAError> {
+    let mut best: Option<(usize, f64)> = None;
+    for (position, permute) in candidates.iter().enumerate() {
+        let similarity = permute(vec).similarity(target)?;
+        if best.is_none_or(|(_, best)| similarity > best) {
+            best = Some((position, similarity));
+        }
+    }
+    Ok(best)
+}
+
+
+/// The largest mean deviation between `sim(bind(a, c), bind(b, c))` and `sim(a, b)` that `self_check` accepts.
+pub const MAX_BIND_DEVIATION: f64 = 0.1;
+
+/// The smallest fraction of recovered factors or constituents that `self_check` accepts.
+pub const MIN_RECOVERY_RATE: f64 = 0.9;
+
+/// The number of random distractors a recovered vector has to be more similar to its source than.
+const N_DISTRACTORS: usize = 8;
+
+/// The number of vectors bundled per `self_check` trial, odd so that the binary majority has no ties.
+const BUNDLE_SIZE: usize = 3;
+
+
+/// A vector symbolic architecture whose algebra `self_check` can verify.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Model {
+    /// Sparse binary vectors with `n_active` active entries, bound by XOR and bundled by consensus sum.
+    SparseBinary { n_active: usize },
+    /// Dense holographic reduced representations, bound by circular convolution, unbound by circular correlation and
+    /// bundled by superposition.
+    #[cfg(feature = "dense")]
+    Hrr,
+}
+
+
+/// The outcome of one property verified by `self_check`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    /// The name of the property.
+    pub name: &'static str,
+    /// The measured value: a mean deviation for `bind_preserves_similarity`, otherwise a recovery rate.
+    pub value: f64,
+    /// The bound the value has to respect.
+    pub threshold: f64,
+    pub passed: bool,
+}
+
+
+/// The report of `self_check`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SelfCheckReport {
+    pub model: Model,
+    pub dimension: usize,
+    pub trials: usize,
+    pub checks: Vec<Check>,
+}
+
+
+impl SelfCheckReport {
+    /// Returns whether every property held.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+}
+
+
+/// Empirically verifies the algebra of a model with random vectors, e.g. as a sanity check of unusual parameters such
+/// as tiny dimensions or extreme sparsity. Every trial checks that:
+/// * `bind_preserves_similarity`: binding two similar vectors with the same key keeps their similarity;
+/// * `unbind_recovers_factor`: unbinding a key from a bound pair is more similar to the other factor than to
+///   random distractors;
+/// * `bundle_preserves_constituents`: every bundled vector is more similar to the bundle than random distractors.
+/// # Arguments
+/// * `model` - The model to verify.
+/// * `dimension` - The dimension of the vectors.
+/// * `trials` - The number of random trials per property.
+/// # Returns
+/// The `SelfCheckReport` with one `Check` per property.
+pub fn self_check(model: Model, dimension: usize, trials: usize) -> Result<SelfCheckReport, OVSAError> {
+    with_global_rng(|rng| self_check_with_rng(model, dimension, trials, rng))
+}
+
+
+/// Empirically verifies the algebra of a model using the provided random number generator.
+/// # Arguments
+/// * `model` - The model to verify.
+/// * `dimension` - The dimension of the vectors.
+/// * `trials` - The number of random trials per property.
+/// * `rng` - The random number generator.
+/// # Returns
+/// The `SelfCheckReport` with one `Check` per property.
+pub fn self_check_with_rng<R: Rng + ?Sized>(model: Model, dimension: usize, trials: usize, rng: &mut R) -> Result<SelfCheckReport, OVSAError> {
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+    if trials == 0 {
+        return Err(OVSAError::InvalidArgument("a self-check needs at least one trial".to_string()));
+    }
+
+    let checks = match model {
+        Model::SparseBinary { n_active } => run_checks(&SparseBinary { dimension, n_active }, trials, rng)?,
+        #[cfg(feature = "dense")]
+        Model::Hrr => run_checks(&Hrr { dimension }, trials, rng)?,
+    };
+
+    Ok(SelfCheckReport { model, dimension, trials, checks })
+}
+
+
+/// The mean similarities to the original vector after a number of nested bind and permute operations.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DepthRow {
+    pub depth: usize,
+    /// The similarity of the vector decoded by undoing every operation, showing how well the original is recovered.
+    pub recovered: f64,
+    /// The similarity of the nested composite itself, which should fall to the baseline.
+    pub encoded: f64,
+}
+
+
+/// The result of `bind_chain_depth`, printable with `{}` as a table with one row per depth.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DepthProfile {
+    pub model: Model,
+    pub dimension: usize,
+    pub trials: usize,
+    /// The mean similarity of two independent random vectors.
+    pub baseline: f64,
+    pub rows: Vec<DepthRow>,
+}
+
+
+impl fmt::Display for DepthProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:?}, dimension {}, {} trials, baseline {:.4}", self.model, self.dimension, self.trials, self.baseline)?;
+        writeln!(f, "{:>5}  {:>9}  {:>9}", "depth", "recovered", "encoded")?;
+        for row in &self.rows {
+            writeln!(f, "{:>5}  {:>9.4}  {:>9.4}", row.depth, row.recovered, row.encoded)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Measures how the similarity to an original vector degrades over nested compositions, e.g. to decide how deep
+/// compositional structures can go. Level `k` of a chain binds the previous level to a random key and shifts the
+/// result by one position; decoding a depth undoes every level in reverse order.
+/// # Arguments
+/// * `model` - The model to analyse.
+/// * `dimension` - The dimension of the vectors.
+/// * `max_depth` - The deepest nesting measured.
+/// * `trials` - The number of random chains averaged per depth.
+/// # Returns
+/// The `DepthProfile` with one row per depth from 1 to `max_depth`.
+pub fn bind_chain_depth(model: Model, dimension: usize, max_depth: usize, trials: usize) -> Result<DepthProfile, OVSAError> {
+    with_global_rng(|rng| bind_chain_depth_with_rng(model, dimension, max_depth, trials, rng))
+}
+
+
+/// Measures the degradation over nested compositions using the provided random number generator.
+/// # Arguments
+/// * `model` - The model to analyse.
+/// * `dimension` - The dimension of the vectors.
+/// * `max_depth` - The deepest nesting measured.
+/// * `trials` - The number of random chains averaged per depth.
+/// * `rng` - The random number generator.
+/// # Returns
+/// The `DepthProfile` with one row per depth from 1 to `max_depth`.
+pub fn bind_chain_depth_with_rng<R: Rng + ?Sized>(model: Model, dimension: usize, max_depth: usize, trials: usize, rng: &mut R) -> Result<DepthProfile, OVSAError> {
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+    if trials == 0 {
+        return Err(OVSAError::InvalidArgument("a depth analysis needs at least one trial".to_string()));
+    }
+
+    let (baseline, rows) = match model {
+        Model::SparseBinary { n_active } => run_chains(&SparseBinary { dimension, n_active }, max_depth, trials, rng)?,
+        #[cfg(feature = "dense")]
+        Model::Hrr => run_chains(&Hrr { dimension }, max_depth, trials, rng)?,
+    };
+
+    Ok(DepthProfile { model, dimension, trials, baseline, rows })
+}
+
+
+fn run_chains<A: Algebra, R: Rng + ?Sized>(algebra: &A, max_depth: usize, trials: usize, rng: &mut R) -> Result<(f64, Vec<DepthRow>), OVSAError> {
+    let mut baseline = 0.0;
+    let mut recovered = vec![0.0; max_depth];
+    let mut encoded = vec![0.0; max_depth];
+
+    for _ in 0..trials {
+        let original = algebra.random(rng)?;
+        baseline += original.similarity(&algebra.random(rng)?)?;
+
+        let mut keys = Vec::with_capacity(max_depth);
+        let mut composite = original.clone();
+        for depth in 0..max_depth {
+            keys.push(algebra.random(rng)?);
+            composite = algebra.permute(&algebra.bind(&composite, &keys[depth])?, 1);
+            encoded[depth] += composite.similarity(&original)?;
+
+            let mut decoded = composite.clone();
+            for key in keys.iter().rev() {
+                decoded = algebra.unbind(&algebra.permute(&decoded, -1), key)?;
+            }
+            recovered[depth] += decoded.similarity(&original)?;
+        }
+    }
+
+    let rows = (0..max_depth)
+        .map(|depth| DepthRow { depth: depth + 1, recovered: recovered[depth] / trials as f64, encoded: encoded[depth] / trials as f64 })
+        .collect();
+    Ok((baseline / trials as f64, rows))
+}
+
+
+/// The operations of a model needed by `self_check`.
+trait Algebra {
+    type Vector: Hypervector;
+
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<Self::Vector, OVSAError>;
+
+    /// Returns a noisy copy that stays similar to the vector.
+    fn perturb<R: Rng + ?Sized>(&self, vector: &Self::Vector, rng: &mut R) -> Result<Self::Vector, OVSAError>;
+
+    fn bind(&self, a: &Self::Vector, b: &Self::Vector) -> Result<Self::Vector, OVSAError>;
+
+    fn unbind(&self, bound: &Self::Vector, key: &Self::Vector) -> Result<Self::Vector, OVSAError>;
+
+    fn bundle<R: Rng + ?Sized>(&self, vectors: &[Self::Vector], rng: &mut R) -> Result<Self::Vector, OVSAError>;
+
+    fn permute(&self, vector: &Self::Vector, shift_by: isize) -> Self::Vector;
+}
+
+
+struct SparseBinary {
+    dimension: usize,
+    n_active: usize,
+}
+
+
+impl Algebra for SparseBinary {
+    type Vector = CsVec<i8>;
+
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        binary::sparse_random_with_rng(self.dimension, self.n_active, rng)
+    }
+
+    fn perturb<R: Rng + ?Sized>(&self, vector: &CsVec<i8>, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        // flipping a few random entries, at least one
+        let noise = binary::sparse_random_with_rng(self.dimension, (self.dimension / 16).max(1), rng)?;
+        binary::xor(vector, &noise)
+    }
+
+    fn bind(&self, a: &CsVec<i8>, b: &CsVec<i8>) -> Result<CsVec<i8>, OVSAError> {
+        binary::xor(a, b)
+    }
+
+    fn unbind(&self, bound: &CsVec<i8>, key: &CsVec<i8>) -> Result<CsVec<i8>, OVSAError> {
+        binary::xor(bound, key)
+    }
+
+    fn bundle<R: Rng + ?Sized>(&self, vectors: &[CsVec<i8>], rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        binary::consensus_sum_with_rng(vectors, rng)
+    }
+
+    fn permute(&self, vector: &CsVec<i8>, shift_by: isize) -> CsVec<i8> {
+        binary::cyclic_shift(vector, shift_by)
+    }
+}
+
+
+#[cfg(feature = "dense")]
+struct Hrr {
+    dimension: usize,
+}
+
+
+#[cfg(feature = "dense")]
+impl Algebra for Hrr {
+    type Vector = Array1<f32>;
+
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<Array1<f32>, OVSAError> {
+        dense::random_uniform_with_rng(self.dimension, -1.0, 1.0, rng)
+    }
+
+    fn perturb<R: Rng + ?Sized>(&self, vector: &Array1<f32>, rng: &mut R) -> Result<Array1<f32>, OVSAError> {
+        let noise = dense::random_uniform_with_rng(self.dimension, -0.5, 0.5, rng)?;
+        Ok(vector + &noise)
+    }
+
+    fn bind(&self, a: &Array1<f32>, b: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+        Ok(dense::circular_convolution(a, b))
+    }
+
+    fn unbind(&self, bound: &Array1<f32>, key: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+        Ok(dense::circular_correlation(bound, key))
+    }
+
+    fn bundle<R: Rng + ?Sized>(&self, vectors: &[Array1<f32>], _rng: &mut R) -> Result<Array1<f32>, OVSAError> {
+        dense::superposition(vectors)
+    }
+
+    fn permute(&self, vector: &Array1<f32>, shift_by: isize) -> Array1<f32> {
+        dense::cyclic_shift(vector, shift_by)
+    }
+}
+
+
+fn run_checks<A: Algebra, R: Rng + ?Sized>(algebra: &A, trials: usize, rng: &mut R) -> Result<Vec<Check>, OVSAError> {
+    let mut deviation = 0.0;
+    let (mut unbound, mut bundled) = (0, 0);
+
+    for _ in 0..trials {
+        let a = algebra.random(rng)?;
+        let b = algebra.perturb(&a, rng)?;
+        let key = algebra.random(rng)?;
+        let bound_similarity = algebra.bind(&a, &key)?.similarity(&algebra.bind(&b, &key)?)?;
+        deviation += (bound_similarity - a.similarity(&b)?).abs();
+
+        let distractors = (0..N_DISTRACTORS).map(|_| algebra.random(rng)).collect::<Result<Vec<_>, _>>()?;
+        let recovered = algebra.unbind(&algebra.bind(&a, &key)?, &key)?;
+        if stands_out(&recovered, &a, &distractors)? {
+            unbound += 1;
+        }
+
+        let constituents = (0..BUNDLE_SIZE).map(|_| algebra.random(rng)).collect::<Result<Vec<_>, _>>()?;
+        let bundle = algebra.bundle(&constituents, rng)?;
+        for constituent in &constituents {
+            if stands_out(&bundle, constituent, &distractors)? {
+                bundled += 1;
+            }
+        }
+    }
+
+    let deviation = deviation / trials as f64;
+    let unbind_rate = unbound as f64 / trials as f64;
+    let bundle_rate = bundled as f64 / (trials * BUNDLE_SIZE) as f64;
+    Ok(vec![
+        Check { name: "bind_preserves_similarity", value: deviation, threshold: MAX_BIND_DEVIATION, passed: deviation <= MAX_BIND_DEVIATION },
+        Check { name: "unbind_recovers_factor", value: unbind_rate, threshold: MIN_RECOVERY_RATE, passed: unbind_rate >= MIN_RECOVERY_RATE },
+        Check { name: "bundle_preserves_constituents", value: bundle_rate, threshold: MIN_RECOVERY_RATE, passed: bundle_rate >= MIN_RECOVERY_RATE },
+    ])
+}
+
+
+/// Returns whether a vector is strictly more similar to its source than to every distractor.
+fn stands_out<V: Hypervector>(vector: &V, source: &V, distractors: &[V]) -> Result<bool, OVSAError> {
+    let similarity = vector.similarity(source)?;
+    for distractor in distractors {
+        if vector.similarity(distractor)? >= similarity {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+
+/// The number of bundles `check_n_active` simulates.
+const SPARSITY_TRIALS: usize = 20;
+
+
+/// The expected and simulated quality of bundling sparse binary vectors with a given number of active entries.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SparsityReport {
+    pub dimension: usize,
+    pub bundle_size: usize,
+    /// The evaluated number of active entries.
+    pub n_active: usize,
+    /// The expected gap between the Hamming distance of a random vector and of a constituent to the bundle, in
+    /// standard deviations of the distances.
+    pub separation: f64,
+    /// The simulated fraction of constituents more similar to their bundle than every one of 8 random distractors.
+    pub recall: f64,
+    /// The number of active entries with the largest expected separation.
+    pub recommended: usize,
+}
+
+
+impl SparsityReport {
+    /// Returns whether the evaluated number of active entries recalls at least `MIN_RECOVERY_RATE` of the constituents.
+    pub fn is_adequate(&self) -> bool {
+        self.recall >= MIN_RECOVERY_RATE
+    }
+}
+
+
+/// Recommends the number of active entries of sparse binary vectors that are bundled by consensus sum.
+/// The expected separation of constituents from random vectors is computed exactly from the binomial distribution of
+/// the per-index counts, following the signal-to-noise capacity analyses of Kanerva (1997) and Frady et al. (2018), and
+/// the best candidate is then checked by simulation. As majority bundling is symmetric under complement, only up to
+/// half of the dimension is considered. Majority bundling favours dense codes: the recommendation is usually close to
+/// half of the dimension, and very sparse codes lose their constituents in bundles of three or more vectors.
+/// # Arguments
+/// * `dimension` - The dimension of the vectors.
+/// * `bundle_size` - The expected number of vectors per bundle.
+/// # Returns
+/// The `SparsityReport` of the recommended number of active entries.
+pub fn recommend_n_active(dimension: usize, bundle_size: usize) -> Result<SparsityReport, OVSAError> {
+    with_global_rng(|rng| recommend_n_active_with_rng(dimension, bundle_size, rng))
+}
+
+
+/// Recommends the number of active entries of sparse binary vectors using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The dimension of the vectors.
+/// * `bundle_size` - The expected number of vectors per bundle.
+/// * `rng` - The random number generator used by the simulation.
+/// # Returns
+/// The `SparsityReport` of the recommended number of active entries.
+pub fn recommend_n_active_with_rng<R: Rng + ?Sized>(dimension: usize, bundle_size: usize, rng: &mut R) -> Result<SparsityReport, OVSAError> {
+    let recommended = best_n_active(dimension, bundle_size)?;
+    check_n_active_with_rng(dimension, bundle_size, recommended, rng)
+}
+
+
+/// Evaluates a chosen number of active entries of sparse binary vectors that are bundled by consensus sum.
+/// # Arguments
+/// * `dimension` - The dimension of the vectors.
+/// * `bundle_size` - The expected number of vectors per bundle.
+/// * `n_active` - The chosen number of active entries.
+/// # Returns
+/// The `SparsityReport`, whose `recommended` field can be compared to the choice.
+pub fn check_n_active(dimension: usize, bundle_size: usize, n_active: usize) -> Result<SparsityReport, OVSAError> {
+    with_global_rng(|rng| check_n_active_with_rng(dimension, bundle_size, n_active, rng))
+}
+
+
+/// Evaluates a chosen number of active entries using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The dimension of the vectors.
+/// * `bundle_size` - The expected number of vectors per bundle.
+/// * `n_active` - The chosen number of active entries.
+/// * `rng` - The random number generator used by the simulation.
+/// # Returns
+/// The `SparsityReport`, whose `recommended` field can be compared to the choice.
+pub fn check_n_active_with_rng<R: Rng + ?Sized>(dimension: usize, bundle_size: usize, n_active: usize, rng: &mut R) -> Result<SparsityReport, OVSAError> {
+    let recommended = best_n_active(dimension, bundle_size)?;
+    if n_active == 0 {
+        return Err(OVSAError::ZeroActiveElements);
+    }
+    if n_active > dimension {
+        return Err(OVSAError::TooManyActiveElements);
+    }
+
+    let algebra = SparseBinary { dimension, n_active };
+    let mut recalled = 0;
+    for _ in 0..SPARSITY_TRIALS {
+        let constituents = (0..bundle_size).map(|_| algebra.random(rng)).collect::<Result<Vec<_>, _>>()?;
+        let distractors = (0..N_DISTRACTORS).map(|_| algebra.random(rng)).collect::<Result<Vec<_>, _>>()?;
+        let bundle = algebra.bundle(&constituents, rng)?;
+        for constituent in &constituents {
+            if stands_out(&bundle, constituent, &distractors)? {
+                recalled += 1;
+            }
+        }
+    }
+
+    Ok(SparsityReport {
+        dimension,
+        bundle_size,
+        n_active,
+        separation: separation(dimension, bundle_size, n_active as f64 / dimension as f64),
+        recall: recalled as f64 / (SPARSITY_TRIALS * bundle_size) as f64,
+        recommended,
+    })
+}
+
+
+/// Returns the number of active entries up to half the dimension with the largest expected separation, searching
+/// every small value and then geometrically growing ones.
+fn best_n_active(dimension: usize, bundle_size: usize) -> Result<usize, OVSAError> {
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+    if bundle_size == 0 {
+        return Err(OVSAError::EmptyVectorList);
+    }
+
+    let half = (dimension / 2).max(1);
+    let mut candidates = Vec::new();
+    let mut n_active = 1;
+    while n_active < half {
+        candidates.push(n_active);
+        n_active = if n_active < 64 { n_active + 1 } else { n_active + n_active / 16 };
+    }
+    candidates.push(half);
+
+    let mut best = (1, f64::NEG_INFINITY);
+    for n_active in candidates {
+        let candidate = separation(dimension, bundle_size, n_active as f64 / dimension as f64);
+        if candidate > best.1 {
+            best = (n_active, candidate);
+        }
+    }
+    Ok(best.0)
+}
+
+
+/// The expected separation of a constituent from a random vector, both of density `density`, in a majority bundle.
+fn separation(dimension: usize, bundle_size: usize, density: f64) -> f64 {
+    let (active, inactive) = majority_probabilities(bundle_size, density);
+    let bundle_density = density * active + (1.0 - density) * inactive;
+    // the per-index probabilities that the bundle differs from a constituent and from an independent random vector
+    let constituent = density * (1.0 - active) + (1.0 - density) * inactive;
+    let random = density * (1.0 - bundle_density) + (1.0 - density) * bundle_density;
+    let deviation = (constituent * (1.0 - constituent) + random * (1.0 - random)).sqrt();
+    if deviation == 0.0 {
+        return if random > constituent { f64::INFINITY } else { 0.0 };
+    }
+    (random - constituent) * (dimension as f64).sqrt() / deviation
+}
+
+
+/// Returns the probabilities that a majority bundle index is active given that a constituent's index is active,
+/// respectively inactive, counting exact ties as half active like the random tie-breaking of `consensus_sum`.
+fn majority_probabilities(bundle_size: usize, density: f64) -> (f64, f64) {
+    let others = bundle_size - 1;
+    let (mut active, mut inactive) = (0.0, 0.0);
+    let majority = |total: usize| match (2 * total).cmp(&bundle_size) {
+        std::cmp::Ordering::Greater => 1.0,
+        std::cmp::Ordering::Equal => 0.5,
+        std::cmp::Ordering::Less => 0.0,
+    };
+
+    // the binomial probabilities of k active indices among the other vectors, computed in log space
+    let mut log_pmf = others as f64 * (1.0 - density).ln();
+    for k in 0..=others {
+        let pmf = log_pmf.exp();
+        active += pmf * majority(k + 1);
+        inactive += pmf * majority(k);
+        log_pmf += ((others - k) as f64 / (k + 1) as f64).ln() + (density / (1.0 - density)).ln();
+    }
+    (active, inactive)
+}
@@ -0,0 +1,259 @@
+use sprs::CsVec;
+
+use crate::binary::{cyclic_shift, distance_limit, from_indices_or_empty, hamming_at_most, kernels};
+use crate::binary::matrix::cyclic_shift_words;
+use crate::errors::OVSAError;
+use crate::hypervector::Hypervector;
+use crate::memory::ItemMemory;
+
+
+/// The storage layout of a compressed binary vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Encoding {
+    /// Gaps between consecutive active indices as LEB128 varints, compact for sparse vectors.
+    Gaps(Vec<u8>),
+    /// One bit per dimension, compact for dense vectors.
+    Packed(Vec<u64>),
+}
+
+
+/// A sparse binary vector stored in whichever of a gap-encoded or bit-packed layout is smaller.
+/// Similarities are computed directly on the compressed form, so an `ItemMemory<CompressedBinary>` can be queried
+/// without decompressing its entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedBinary {
+    dimension: usize,
+    n_active: usize,
+    encoding: Encoding,
+}
+
+
+impl CompressedBinary {
+    /// Compresses a sparse binary vector.
+    /// # Arguments
+    /// * `vec` - The sparse binary vector.
+    /// # Returns
+    /// The `CompressedBinary` using the smaller of the two layouts.
+    pub fn compress(vec: &CsVec<i8>) -> Self {
+        let mut gaps: Vec<u8> = Vec::new();
+        let mut previous = 0;
+        for &index in vec.indices() {
+            write_varint(&mut gaps, index - previous);
+            previous = index;
+        }
+
+        let packed_bytes = vec.dim().div_ceil(64) * 8;
+        let encoding = if gaps.len() <= packed_bytes {
+            gaps.shrink_to_fit();
+            Encoding::Gaps(gaps)
+        } else {
+            let mut words = vec![0u64; vec.dim().div_ceil(64)];
+            for &index in vec.indices() {
+                words[index / 64] |= 1 << (index % 64);
+            }
+            Encoding::Packed(words)
+        };
+
+        CompressedBinary { dimension: vec.dim(), n_active: vec.nnz(), encoding }
+    }
+
+    /// Restores the sparse binary vector.
+    pub fn decompress(&self) -> CsVec<i8> {
+        from_indices_or_empty(self.dimension, self.active_indices().collect())
+    }
+
+    /// Returns the dimension of the vector.
+    pub fn dim(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the number of active entries.
+    pub fn nnz(&self) -> usize {
+        self.n_active
+    }
+
+    /// Returns true if the bit-packed layout was chosen.
+    pub fn is_packed(&self) -> bool {
+        matches!(self.encoding, Encoding::Packed(_))
+    }
+
+    /// Returns the number of bytes used to store the active entries.
+    pub fn size_bytes(&self) -> usize {
+        match &self.encoding {
+            Encoding::Gaps(gaps) => gaps.len(),
+            Encoding::Packed(words) => words.len() * 8,
+        }
+    }
+
+    /// Returns the active indices in increasing order.
+    pub fn active_indices(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        match &self.encoding {
+            Encoding::Gaps(gaps) => {
+                let mut position = 0;
+                let mut index = 0;
+                Box::new(std::iter::from_fn(move || {
+                    if position >= gaps.len() {
+                        return None;
+                    }
+                    index += read_varint(gaps, &mut position);
+                    Some(index)
+                }))
+            }
+            Encoding::Packed(words) => Box::new(words.iter().enumerate().flat_map(|(word_index, &word)| {
+                let mut remaining = word;
+                std::iter::from_fn(move || {
+                    if remaining == 0 {
+                        return None;
+                    }
+                    let bit = remaining.trailing_zeros() as usize;
+                    remaining &= remaining - 1;
+                    Some(word_index * 64 + bit)
+                })
+            })),
+        }
+    }
+
+    /// Returns true if the entry at the index is active.
+    fn contains(&self, index: usize) -> bool {
+        match &self.encoding {
+            Encoding::Packed(words) => words[index / 64] & (1 << (index % 64)) != 0,
+            Encoding::Gaps(_) => self.active_indices().take_while(|&active| active <= index).any(|active| active == index),
+        }
+    }
+
+    /// Counts the entries active in both vectors.
+    fn overlap(&self, other: &Self) -> usize {
+        match (&self.encoding, &other.encoding) {
+            (Encoding::Packed(a), Encoding::Packed(b)) => kernels::and_popcount(a, b),
+            (Encoding::Gaps(_), Encoding::Packed(_)) => self.active_indices().filter(|&index| other.contains(index)).count(),
+            (Encoding::Packed(_), Encoding::Gaps(_)) => other.overlap(self),
+            (Encoding::Gaps(_), Encoding::Gaps(_)) => {
+                let (mut a, mut b) = (self.active_indices().peekable(), other.active_indices().peekable());
+                let mut count = 0;
+                while let (Some(&i), Some(&j)) = (a.peek(), b.peek()) {
+                    if i <= j {
+                        a.next();
+                    }
+                    if j <= i {
+                        b.next();
+                    }
+                    if i == j {
+                        count += 1;
+                    }
+                }
+                count
+            }
+        }
+    }
+
+    /// Computes the Hamming distance to another compressed vector without decompressing either.
+    /// # Arguments
+    /// * `other` - The vector to compare to.
+    /// # Returns
+    /// The number of positions at which the two vectors differ.
+    pub fn hamming_distance(&self, other: &Self) -> Result<usize, OVSAError> {
+        if self.dimension != other.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(self.n_active + other.n_active - 2 * self.overlap(other))
+    }
+
+    /// Computes the Hamming distance to another compressed vector if it is at most a limit. Two bit-packed vectors
+    /// stop the popcount scan as soon as the limit is exceeded; other layouts compute the full distance.
+    /// # Arguments
+    /// * `other` - The vector to compare to.
+    /// * `limit` - The largest distance of interest.
+    /// # Returns
+    /// The distance if it is at most `limit`, otherwise `None`.
+    pub fn hamming_at_most(&self, other: &Self, limit: usize) -> Result<Option<usize>, OVSAError> {
+        if self.dimension != other.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        if self.n_active.abs_diff(other.n_active) > limit {
+            return Ok(None);
+        }
+
+        match (&self.encoding, &other.encoding) {
+            (Encoding::Packed(a), Encoding::Packed(b)) => Ok(hamming_at_most(a, b, limit)),
+            _ => Ok(Some(self.hamming_distance(other)?).filter(|&distance| distance <= limit)),
+        }
+    }
+
+    /// Performs a cyclic shift like `binary::cyclic_shift`. A bit-packed vector is shifted word by word, see
+    /// `matrix::cyclic_shift_words`; a gap-encoded one is shifted as a sparse vector and compressed again.
+    /// # Arguments
+    /// * `shift_by` - The number of positions to shift. Positive values shift to the right, negative values shift to the left.
+    /// # Returns
+    /// The shifted vector.
+    pub fn cyclic_shift(&self, shift_by: isize) -> Self {
+        match &self.encoding {
+            Encoding::Packed(words) => {
+                let words = cyclic_shift_words(self.dimension, words, shift_by).expect("Packed vectors have one word per 64 dimensions and no bits beyond the dimension.");
+                CompressedBinary { dimension: self.dimension, n_active: self.n_active, encoding: Encoding::Packed(words) }
+            }
+            Encoding::Gaps(_) => CompressedBinary::compress(&cyclic_shift(&self.decompress(), shift_by)),
+        }
+    }
+}
+
+
+impl Hypervector for CompressedBinary {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn similarity(&self, other: &Self) -> Result<f64, OVSAError> {
+        Ok(1f64 - self.hamming_distance(other)? as f64 / self.dimension as f64)
+    }
+
+    fn similarity_at_least(&self, other: &Self, minimum: f64) -> Result<Option<f64>, OVSAError> {
+        let distance = self.hamming_at_most(other, distance_limit(minimum, self.dimension))?;
+        Ok(distance.map(|distance| 1f64 - distance as f64 / self.dimension as f64).filter(|&similarity| similarity >= minimum))
+    }
+
+    fn weighted_similarity(&self, other: &Self, weights: &[f64]) -> Result<f64, OVSAError> {
+        crate::binary::weighted_similarity(&self.decompress(), &other.decompress(), weights)
+    }
+
+    fn heap_bytes(&self) -> usize {
+        self.size_bytes()
+    }
+}
+
+
+impl ItemMemory<CsVec<i8>> {
+    /// Copies the memory into one storing compressed vectors, with the same labels in the same order.
+    /// Queries on the copy take compressed query vectors, see `CompressedBinary::compress`.
+    pub fn compressed(&self) -> ItemMemory<CompressedBinary> {
+        let mut compressed = ItemMemory::new(self.dimension()).expect("The dimension was validated on creation.");
+        for (label, vector) in self.iter() {
+            compressed.insert(label, CompressedBinary::compress(vector)).expect("The dimensions match.");
+        }
+        compressed
+    }
+}
+
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*position];
+        *position += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
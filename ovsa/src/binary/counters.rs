@@ -0,0 +1,198 @@
+//! Bundling counters: the intermediate representation of a consensus sum, exposed so that it can be inspected, kept
+//! across updates, or stored in narrow integers on embedded targets.
+
+use std::fmt::Debug;
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::from_indices_or_empty;
+use crate::errors::OVSAError;
+use crate::hypervector::Hardening;
+use crate::rng::with_global_rng;
+
+
+/// An integer type counters are stored in. Narrow types saturate instead of overflowing.
+pub trait Counter: Copy + Debug + PartialEq + Send + Sync {
+    const ZERO: Self;
+
+    /// Adds `delta`, saturating at the bounds of the type.
+    fn saturating_step(self, delta: i32) -> Self;
+
+    /// Returns whether the counter is at a bound of the type.
+    fn is_saturated(self) -> bool;
+
+    fn to_i32(self) -> i32;
+}
+
+
+macro_rules! impl_counter {
+    ($($type:ty),*) => {$(
+        impl Counter for $type {
+            const ZERO: Self = 0;
+
+            fn saturating_step(self, delta: i32) -> Self {
+                (self as i64 + delta as i64).clamp(<$type>::MIN as i64, <$type>::MAX as i64) as $type
+            }
+
+            fn is_saturated(self) -> bool {
+                self == <$type>::MIN || self == <$type>::MAX
+            }
+
+            fn to_i32(self) -> i32 {
+                self as i32
+            }
+        }
+    )*};
+}
+
+impl_counter!(i8, i16, i32);
+
+
+/// Signed bundling counters: every added vector adds 1 at its active entries and subtracts 1 at its inactive ones, so
+/// an entry of the majority vector is active where its counter is positive. Counters of type `i8` or `i16` saturate,
+/// which keeps their sign, and thus the majority, correct until the bundle is dominated by a later minority.
+/// Unlike the `u32` counts of `accumulate`, every update touches all entries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counters<T: Counter = i32> {
+    counts: Vec<T>,
+    n: usize,
+}
+
+
+impl<T: Counter> Counters<T> {
+    /// Creates zeroed counters.
+    /// # Arguments
+    /// * `dimension` - The dimension of the bundled vectors.
+    /// # Returns
+    /// New `Counters` with no vectors added.
+    pub fn new(dimension: usize) -> Result<Self, OVSAError> {
+        if dimension == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+        Ok(Counters { counts: vec![T::ZERO; dimension], n: 0 })
+    }
+
+    /// Converts the active counts used by `accumulate` and `majority` into signed counters, saturating if needed.
+    /// # Arguments
+    /// * `counts` - The number of active entries per dimension.
+    /// * `n` - The number of vectors counted.
+    /// # Returns
+    /// The equivalent `Counters`.
+    pub fn from_active_counts(counts: &[u32], n: usize) -> Result<Self, OVSAError> {
+        if counts.is_empty() {
+            return Err(OVSAError::ZeroDimension);
+        }
+        if counts.iter().any(|&count| count as usize > n) {
+            return Err(OVSAError::CounterUnderflow);
+        }
+
+        let counts = counts.iter()
+            .map(|&count| T::ZERO.saturating_step((2 * count as i64 - n as i64).clamp(i32::MIN as i64, i32::MAX as i64) as i32))
+            .collect();
+        Ok(Counters { counts, n })
+    }
+
+    /// Returns the dimension of the bundled vectors.
+    pub fn dimension(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Returns the counters.
+    pub fn counts(&self) -> &[T] {
+        &self.counts
+    }
+
+    /// Returns the number of vectors added and not removed.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the number of counters at a bound of their type.
+    pub fn n_saturated(&self) -> usize {
+        self.counts.iter().filter(|count| count.is_saturated()).count()
+    }
+
+    /// Adds a vector to the bundle.
+    /// # Arguments
+    /// * `vec` - The sparse binary vector.
+    pub fn add(&mut self, vec: &CsVec<i8>) -> Result<(), OVSAError> {
+        self.step(vec, 1)?;
+        self.n += 1;
+        Ok(())
+    }
+
+    /// Removes a previously added vector from the bundle. Saturated counters do not remember the steps they dropped,
+    /// so removal is only exact while no counter saturated.
+    /// # Arguments
+    /// * `vec` - The sparse binary vector.
+    /// # Returns
+    /// `CounterUnderflow` if no vector was added.
+    pub fn remove(&mut self, vec: &CsVec<i8>) -> Result<(), OVSAError> {
+        if self.n == 0 {
+            return Err(OVSAError::CounterUnderflow);
+        }
+        self.step(vec, -1)?;
+        self.n -= 1;
+        Ok(())
+    }
+
+    /// Returns the majority vector, breaking ties randomly.
+    pub fn majority(&self) -> CsVec<i8> {
+        with_global_rng(|rng| self.majority_with_rng(rng))
+    }
+
+    /// Returns the majority vector: active where the counter is positive, with zero counters drawn at random.
+    /// # Arguments
+    /// * `rng` - The random number generator used to break ties.
+    pub fn majority_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> CsVec<i8> {
+        let mut indices = Vec::new();
+        for (index, count) in self.counts.iter().enumerate() {
+            let count = count.to_i32();
+            if count > 0 || (count == 0 && rng.random_bool(0.5)) {
+                indices.push(index);
+            }
+        }
+        from_indices_or_empty(self.counts.len(), indices)
+    }
+
+    /// Returns the binary vector of the counters with the given hardening.
+    /// # Arguments
+    /// * `hardening` - The hardening.
+    pub fn harden(&self, hardening: Hardening) -> CsVec<i8> {
+        with_global_rng(|rng| self.harden_with_rng(hardening, rng))
+    }
+
+    /// Returns the binary vector of the counters with the given hardening, drawing random decisions with the provided
+    /// random number generator. `Hardening::Stochastic` makes an entry active with probability `(1 + count / n) / 2`,
+    /// the share of the added vectors active there while no counter saturated.
+    /// # Arguments
+    /// * `hardening` - The hardening.
+    /// * `rng` - The random number generator.
+    pub fn harden_with_rng<R: Rng + ?Sized>(&self, hardening: Hardening, rng: &mut R) -> CsVec<i8> {
+        if hardening == Hardening::Majority {
+            return self.majority_with_rng(rng);
+        }
+
+        let mut indices = Vec::new();
+        for (index, count) in self.counts.iter().enumerate() {
+            let probability = if self.n == 0 { 0.5 } else { (0.5 + 0.5 * count.to_i32() as f64 / self.n as f64).clamp(0.0, 1.0) };
+            if rng.random_bool(probability) {
+                indices.push(index);
+            }
+        }
+        from_indices_or_empty(self.counts.len(), indices)
+    }
+
+    fn step(&mut self, vec: &CsVec<i8>, sign: i32) -> Result<(), OVSAError> {
+        if vec.dim() != self.counts.len() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let mut active = vec.indices().iter().peekable();
+        for (index, count) in self.counts.iter_mut().enumerate() {
+            let delta = if active.next_if_eq(&&index).is_some() { sign } else { -sign };
+            *count = count.saturating_step(delta);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+//! Word kernels of the bit-packed representations (popcount, XOR and accumulation into counters), dispatched at
+//! runtime to the widest instruction set the CPU supports, so that one published binary is fast everywhere. Every
+//! instruction set compiles the same portable loops with its target features enabled, which lets the compiler
+//! vectorize them with the wide registers and native popcount instructions; CPUs without any of them use the scalar
+//! build. The choice is made once, on first use, and can be overridden with `set_isa`, e.g. to compare kernels.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::errors::OVSAError;
+
+
+/// An instruction set the kernels are compiled for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Isa {
+    /// Portable code for any CPU.
+    Scalar,
+    /// x86-64 with AVX2 and POPCNT.
+    Avx2,
+    /// x86-64 with AVX-512 and its vector popcount (VPOPCNTDQ).
+    Avx512,
+    /// AArch64 with NEON.
+    Neon,
+}
+
+
+impl Isa {
+    /// Every instruction set, from the narrowest to the widest.
+    pub const ALL: [Isa; 4] = [Isa::Scalar, Isa::Neon, Isa::Avx2, Isa::Avx512];
+
+    /// Returns true if the running CPU supports the instruction set.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Isa::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx2 => is_x86_feature_detected!("avx2") && is_x86_feature_detected!("popcnt"),
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx512 => is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512vpopcntdq") && is_x86_feature_detected!("popcnt"),
+            #[cfg(target_arch = "aarch64")]
+            Isa::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            Isa::Scalar => 0,
+            Isa::Avx2 => 1,
+            Isa::Avx512 => 2,
+            Isa::Neon => 3,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Isa> {
+        Isa::ALL.into_iter().find(|isa| isa.code() == code)
+    }
+}
+
+
+/// The code of the active instruction set, or `UNSET` before the first use.
+static ACTIVE: AtomicU8 = AtomicU8::new(UNSET);
+
+const UNSET: u8 = u8::MAX;
+
+
+/// Returns the widest instruction set the running CPU supports.
+pub fn detected_isa() -> Isa {
+    Isa::ALL.into_iter().rev().find(|isa| isa.is_supported()).unwrap_or(Isa::Scalar)
+}
+
+
+/// Returns the instruction set the kernels currently run on.
+pub fn isa() -> Isa {
+    if let Some(isa) = Isa::from_code(ACTIVE.load(Ordering::Relaxed)) {
+        return isa;
+    }
+
+    let isa = detected_isa();
+    ACTIVE.store(isa.code(), Ordering::Relaxed);
+    isa
+}
+
+
+/// Selects the instruction set of the kernels for the whole process.
+/// # Arguments
+/// * `isa` - The instruction set, or `None` to return to the detected one.
+/// # Returns
+/// `InvalidArgument` if the running CPU does not support the instruction set.
+pub fn set_isa(isa: Option<Isa>) -> Result<(), OVSAError> {
+    let isa = isa.unwrap_or_else(detected_isa);
+    if !isa.is_supported() {
+        return Err(OVSAError::InvalidArgument(format!("the CPU does not support {:?}", isa)));
+    }
+
+    ACTIVE.store(isa.code(), Ordering::Relaxed);
+    Ok(())
+}
+
+
+/// Defines the kernels with the given attributes, so that every instruction set compiles the same code.
+macro_rules! kernels {
+    ($(#[$attribute:meta])*) => {
+        $(#[$attribute])*
+        pub(super) fn popcount(words: &[u64]) -> usize {
+            words.iter().map(|word| word.count_ones() as usize).sum()
+        }
+
+        $(#[$attribute])*
+        pub(super) fn xor_popcount(a: &[u64], b: &[u64]) -> usize {
+            a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones() as usize).sum()
+        }
+
+        $(#[$attribute])*
+        pub(super) fn and_popcount(a: &[u64], b: &[u64]) -> usize {
+            a.iter().zip(b).map(|(a, b)| (a & b).count_ones() as usize).sum()
+        }
+
+        $(#[$attribute])*
+        pub(super) fn xor_into(target: &mut [u64], other: &[u64]) {
+            for (target, other) in target.iter_mut().zip(other) {
+                *target ^= other;
+            }
+        }
+
+        $(#[$attribute])*
+        pub(super) fn accumulate(counts: &mut [u32], words: &[u64]) {
+            // branch-free per bit, which vectorizes, unlike iterating over the set bits
+            for (counts, &word) in counts.chunks_mut(64).zip(words) {
+                for (bit, count) in counts.iter_mut().enumerate() {
+                    *count += ((word >> bit) & 1) as u32;
+                }
+            }
+        }
+    };
+}
+
+
+mod scalar {
+    kernels!();
+}
+
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    kernels!(#[target_feature(enable = "avx2,popcnt")]);
+}
+
+
+#[cfg(target_arch = "x86_64")]
+mod avx512 {
+    kernels!(#[target_feature(enable = "avx512f,avx512vpopcntdq,popcnt")]);
+}
+
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    kernels!(#[target_feature(enable = "neon")]);
+}
+
+
+/// Calls the kernel of the active instruction set.
+macro_rules! dispatch {
+    ($kernel:ident($($argument:expr),*)) => {
+        match isa() {
+            // SAFETY: `isa` only returns instruction sets that `Isa::is_supported` confirmed on this CPU.
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx512 => unsafe { avx512::$kernel($($argument),*) },
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx2 => unsafe { avx2::$kernel($($argument),*) },
+            #[cfg(target_arch = "aarch64")]
+            Isa::Neon => unsafe { neon::$kernel($($argument),*) },
+            _ => scalar::$kernel($($argument),*),
+        }
+    };
+}
+
+
+/// Counts the set bits of packed words.
+/// # Arguments
+/// * `words` - The packed words.
+/// # Returns
+/// The number of set bits.
+pub fn popcount(words: &[u64]) -> usize {
+    dispatch!(popcount(words))
+}
+
+
+/// Counts the bits that differ between packed words, the Hamming distance of two packed vectors.
+/// # Arguments
+/// * `a` - The words of the first vector.
+/// * `b` - The words of the second vector, as many as `a`.
+/// # Returns
+/// The number of differing bits.
+pub fn xor_popcount(a: &[u64], b: &[u64]) -> usize {
+    assert_eq!(a.len(), b.len(), "Packed vectors must have the same number of words.");
+    dispatch!(xor_popcount(a, b))
+}
+
+
+/// Counts the bits set in both of two sets of packed words, the overlap of two packed vectors.
+/// # Arguments
+/// * `a` - The words of the first vector.
+/// * `b` - The words of the second vector, as many as `a`.
+/// # Returns
+/// The number of common set bits.
+pub fn and_popcount(a: &[u64], b: &[u64]) -> usize {
+    assert_eq!(a.len(), b.len(), "Packed vectors must have the same number of words.");
+    dispatch!(and_popcount(a, b))
+}
+
+
+/// XORs packed words into others, binding a packed vector in place.
+/// # Arguments
+/// * `target` - The words to update.
+/// * `other` - The words to XOR in, as many as `target`.
+pub fn xor_into(target: &mut [u64], other: &[u64]) {
+    assert_eq!(target.len(), other.len(), "Packed vectors must have the same number of words.");
+    dispatch!(xor_into(target, other))
+}
+
+
+/// Adds the bits of packed words to per-dimension counters, as when bundling packed vectors.
+/// # Arguments
+/// * `counts` - One counter per dimension.
+/// * `words` - The `counts.len().div_ceil(64)` packed words, with the bits beyond the dimension clear.
+pub fn accumulate(counts: &mut [u32], words: &[u64]) {
+    assert_eq!(counts.len().div_ceil(64), words.len(), "The words must cover the counters.");
+    dispatch!(accumulate(counts, words))
+}
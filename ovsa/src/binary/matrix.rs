@@ -0,0 +1,381 @@
+#[cfg(feature = "sparse")]
+use rand::Rng;
+#[cfg(feature = "sparse")]
+use sprs::CsVec;
+
+use crate::binary::kernels;
+#[cfg(feature = "sparse")]
+use crate::binary::{from_indices_or_empty, majority_with_rng};
+use crate::errors::OVSAError;
+#[cfg(feature = "sparse")]
+use crate::memory::ItemMemory;
+#[cfg(feature = "sparse")]
+use crate::rng::with_global_rng;
+
+
+/// Many binary vectors stored contiguously as bit-packed rows, 64 dimensions per `u64` word in row-major order.
+/// Row operations stream through the words instead of chasing one allocation per vector, which suits bulk workloads
+/// such as comparing many queries to many stored vectors. The methods taking or returning `CsVec<i8>` need the `sparse`
+/// feature; the others work on packed words only. `ItemMemory` keeps its entries as `CsVec<i8>` and only packs the
+/// tiles of a dense enough `query_batch` on the fly, so repeated bulk operations should build a matrix once with
+/// `ItemMemory::to_matrix`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HvMatrix {
+    dimension: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+
+impl HvMatrix {
+    /// Creates an empty matrix for vectors of the given dimension.
+    /// # Arguments
+    /// * `dimension` - The dimension of the rows.
+    /// # Returns
+    /// An empty `HvMatrix`.
+    pub fn new(dimension: usize) -> Result<Self, OVSAError> {
+        if dimension == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+
+        Ok(HvMatrix { dimension, words_per_row: dimension.div_ceil(64), words: Vec::new() })
+    }
+
+    /// Packs sparse binary vectors into the rows of a new matrix.
+    /// # Arguments
+    /// * `dimension` - The dimension of the rows.
+    /// * `rows` - The vectors, all of the given dimension.
+    /// # Returns
+    /// The `HvMatrix` holding one row per vector, in order.
+    #[cfg(feature = "sparse")]
+    pub fn from_rows<'a>(dimension: usize, rows: impl IntoIterator<Item = &'a CsVec<i8>>) -> Result<Self, OVSAError> {
+        let mut matrix = HvMatrix::new(dimension)?;
+        for row in rows {
+            matrix.push(row)?;
+        }
+
+        Ok(matrix)
+    }
+
+    /// Returns the dimension of the rows.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the number of words of a row.
+    pub fn words_per_row(&self) -> usize {
+        self.words_per_row
+    }
+
+    /// Returns the number of rows.
+    pub fn len(&self) -> usize {
+        self.words.len() / self.words_per_row
+    }
+
+    /// Returns true if the matrix has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Appends a sparse binary vector as a new row.
+    /// # Arguments
+    /// * `row` - The vector to pack.
+    #[cfg(feature = "sparse")]
+    pub fn push(&mut self, row: &CsVec<i8>) -> Result<(), OVSAError> {
+        if row.dim() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let start = self.words.len();
+        self.words.resize(start + self.words_per_row, 0);
+        for &index in row.indices() {
+            self.words[start + index / 64] |= 1 << (index % 64);
+        }
+
+        Ok(())
+    }
+
+    /// Appends an already packed row.
+    /// # Arguments
+    /// * `words` - The words of the row.
+    /// # Returns
+    /// `VectorSizeMismatch` for a wrong number of words or bits set beyond the dimension, which the row operations
+    /// would otherwise count.
+    pub fn push_words(&mut self, words: &[u64]) -> Result<(), OVSAError> {
+        check_words(self.dimension, words)?;
+
+        self.words.extend_from_slice(words);
+        Ok(())
+    }
+
+    /// Returns the packed words of a row.
+    /// # Arguments
+    /// * `row` - The index of the row.
+    pub fn row(&self, row: usize) -> &[u64] {
+        &self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    /// Iterates over the packed rows in order.
+    pub fn rows(&self) -> impl Iterator<Item = &[u64]> {
+        self.words.chunks_exact(self.words_per_row)
+    }
+
+    /// Unpacks a row into a sparse binary vector.
+    /// # Arguments
+    /// * `row` - The index of the row.
+    #[cfg(feature = "sparse")]
+    pub fn get(&self, row: usize) -> CsVec<i8> {
+        unpack(self.dimension, self.row(row))
+    }
+
+    /// Counts the active entries of every row.
+    pub fn popcounts(&self) -> Vec<usize> {
+        self.rows().map(kernels::popcount).collect()
+    }
+
+    /// Binds every row with the same vector, e.g. to unbind a role from a whole table at once.
+    /// # Arguments
+    /// * `vector` - The vector to XOR into every row.
+    /// # Returns
+    /// A new matrix whose rows are the XOR of the rows of this one with the vector.
+    #[cfg(feature = "sparse")]
+    pub fn xor_rows(&self, vector: &CsVec<i8>) -> Result<HvMatrix, OVSAError> {
+        self.xor_rows_words(&self.pack(vector)?)
+    }
+
+    /// Binds every row with the same packed vector, like `xor_rows`.
+    /// # Arguments
+    /// * `words` - The packed words of the vector to XOR into every row.
+    /// # Returns
+    /// A new matrix whose rows are the XOR of the rows of this one with the vector, or `VectorSizeMismatch` for a
+    /// wrong number of words or bits set beyond the dimension.
+    pub fn xor_rows_words(&self, words: &[u64]) -> Result<HvMatrix, OVSAError> {
+        check_words(self.dimension, words)?;
+
+        let mut result = self.words.clone();
+        for row in result.chunks_exact_mut(self.words_per_row) {
+            kernels::xor_into(row, words);
+        }
+
+        Ok(HvMatrix { dimension: self.dimension, words_per_row: self.words_per_row, words: result })
+    }
+
+    /// Computes the Hamming distance of every row to a vector.
+    /// # Arguments
+    /// * `vector` - The vector to compare to.
+    /// # Returns
+    /// The distances in row order.
+    #[cfg(feature = "sparse")]
+    pub fn hamming_distances(&self, vector: &CsVec<i8>) -> Result<Vec<usize>, OVSAError> {
+        self.hamming_distances_words(&self.pack(vector)?)
+    }
+
+    /// Computes the Hamming distance of every row to a packed vector, like `hamming_distances`.
+    /// # Arguments
+    /// * `words` - The packed words of the vector to compare to.
+    /// # Returns
+    /// The distances in row order, or `VectorSizeMismatch` for a wrong number of words or bits set beyond the
+    /// dimension.
+    pub fn hamming_distances_words(&self, words: &[u64]) -> Result<Vec<usize>, OVSAError> {
+        check_words(self.dimension, words)?;
+
+        Ok(self.rows().map(|row| kernels::xor_popcount(row, words)).collect())
+    }
+
+    /// Computes the Hamming distance of every row of this matrix to every row of another.
+    /// # Arguments
+    /// * `other` - The matrix to compare to, of the same dimension.
+    /// # Returns
+    /// One vector of distances per row of this matrix, indexed by the rows of `other`.
+    pub fn hamming_block(&self, other: &HvMatrix) -> Result<Vec<Vec<usize>>, OVSAError> {
+        if other.dimension != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(self.rows().map(|row| other.rows().map(|other| kernels::xor_popcount(row, other)).collect()).collect())
+    }
+
+    /// Computes the weighted Hamming distance of every row of this matrix to every row of another: the sum of the
+    /// weights of the dimensions at which the rows differ.
+    /// # Arguments
+    /// * `other` - The matrix to compare to, of the same dimension.
+    /// * `weights` - One weight per dimension.
+    /// # Returns
+    /// One vector of distances per row of this matrix, indexed by the rows of `other`.
+    pub fn weighted_hamming_block(&self, other: &HvMatrix, weights: &[f64]) -> Result<Vec<Vec<f64>>, OVSAError> {
+        if other.dimension != self.dimension || weights.len() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(self.rows().map(|row| other.rows().map(|other| weighted_hamming_words(row, other, weights)).collect()).collect())
+    }
+
+    /// Computes the majority (consensus sum) of all rows, breaking ties randomly.
+    /// # Returns
+    /// The sparse binary vector active where more than half of the rows are.
+    #[cfg(feature = "sparse")]
+    pub fn majority(&self) -> Result<CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.majority_with_rng(rng))
+    }
+
+    /// Computes the majority of all rows, breaking ties with the provided random number generator.
+    /// # Arguments
+    /// * `rng` - The random number generator used to break ties.
+    /// # Returns
+    /// The sparse binary vector active where more than half of the rows are.
+    #[cfg(feature = "sparse")]
+    pub fn majority_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        if self.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let mut counts = vec![0u32; self.dimension];
+        for row in self.rows() {
+            kernels::accumulate(&mut counts, row);
+        }
+
+        Ok(majority_with_rng(&counts, self.len(), rng))
+    }
+
+    /// Packs a vector of the matrix's dimension.
+    #[cfg(feature = "sparse")]
+    fn pack(&self, vector: &CsVec<i8>) -> Result<Vec<u64>, OVSAError> {
+        if vector.dim() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(pack(vector))
+    }
+}
+
+
+#[cfg(feature = "sparse")]
+impl ItemMemory<CsVec<i8>> {
+    /// Packs the stored vectors into the rows of a matrix, in insertion order, e.g. for bulk row operations.
+    pub fn to_matrix(&self) -> HvMatrix {
+        HvMatrix::from_rows(self.dimension(), self.iter().map(|(_, vector)| vector)).expect("The dimensions match.")
+    }
+}
+
+
+/// Packs a sparse binary vector into words, 64 dimensions per word.
+/// # Arguments
+/// * `vector` - The vector to pack.
+/// # Returns
+/// The `dimension.div_ceil(64)` words of the vector.
+#[cfg(feature = "sparse")]
+pub fn pack(vector: &CsVec<i8>) -> Vec<u64> {
+    let mut words = vec![0u64; vector.dim().div_ceil(64)];
+    for &index in vector.indices() {
+        words[index / 64] |= 1 << (index % 64);
+    }
+    words
+}
+
+
+/// Performs a cyclic shift on a packed binary vector with word-level shifts and masks, moving 64 dimensions per step
+/// instead of one active index at a time.
+/// # Arguments
+/// * `dimension` - The dimension of the vector.
+/// * `words` - The `dimension.div_ceil(64)` packed words, with the bits beyond the dimension clear as `pack` leaves them.
+/// * `shift_by` - The number of positions to shift. Positive values shift to the right, negative values shift to the left.
+/// # Returns
+/// The packed words of the shifted vector, or `VectorSizeMismatch` for a wrong number of words or bits set beyond the
+/// dimension.
+pub fn cyclic_shift_words(dimension: usize, words: &[u64], shift_by: isize) -> Result<Vec<u64>, OVSAError> {
+    check_words(dimension, words)?;
+    let n_words = words.len();
+    if dimension == 0 {
+        return Ok(Vec::new());
+    }
+    let shift = shift_by.rem_euclid(dimension as isize) as usize;
+
+    // the entries that stay in range move up by the shift, the ones that wrap around move down by the rest
+    let mut result = shift_words_up(words, shift);
+    if shift > 0 {
+        for (word, wrapped) in result.iter_mut().zip(shift_words_down(words, dimension - shift)) {
+            *word |= wrapped;
+        }
+    }
+    result[n_words - 1] &= last_word_mask(dimension);
+
+    Ok(result)
+}
+
+
+/// Returns the mask of the bits of the last word of a packed row that lie within the dimension.
+fn last_word_mask(dimension: usize) -> u64 {
+    if dimension.is_multiple_of(64) { u64::MAX } else { (1u64 << (dimension % 64)) - 1 }
+}
+
+
+/// Checks that packed words hold a vector of the dimension: `dimension.div_ceil(64)` words with no bit set beyond it.
+fn check_words(dimension: usize, words: &[u64]) -> Result<(), OVSAError> {
+    if words.len() != dimension.div_ceil(64) || words.last().is_some_and(|&last| last & !last_word_mask(dimension) != 0) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    Ok(())
+}
+
+
+/// Moves every bit `shift` positions towards the higher indices, dropping the bits past the last word.
+fn shift_words_up(words: &[u64], shift: usize) -> Vec<u64> {
+    let (word_shift, bit_shift) = (shift / 64, shift % 64);
+    (0..words.len())
+        .map(|index| {
+            let Some(source) = index.checked_sub(word_shift) else {
+                return 0;
+            };
+            let carry = if bit_shift > 0 && source > 0 { words[source - 1] >> (64 - bit_shift) } else { 0 };
+            (words[source] << bit_shift) | carry
+        })
+        .collect()
+}
+
+
+/// Moves every bit `shift` positions towards the lower indices, dropping the bits before the first word.
+fn shift_words_down(words: &[u64], shift: usize) -> Vec<u64> {
+    let (word_shift, bit_shift) = (shift / 64, shift % 64);
+    (0..words.len())
+        .map(|index| {
+            let source = index + word_shift;
+            let low = words.get(source).map_or(0, |&word| word >> bit_shift);
+            let carry = if bit_shift > 0 { words.get(source + 1).map_or(0, |&word| word << (64 - bit_shift)) } else { 0 };
+            low | carry
+        })
+        .collect()
+}
+
+
+/// Unpacks words into a sparse binary vector.
+/// # Arguments
+/// * `dimension` - The dimension of the vector; bits beyond it are ignored.
+/// * `words` - The packed words.
+#[cfg(feature = "sparse")]
+pub fn unpack(dimension: usize, words: &[u64]) -> CsVec<i8> {
+    let mut indices = Vec::new();
+    for (word_index, &word) in words.iter().enumerate() {
+        let mut remaining = word;
+        while remaining != 0 {
+            indices.push(word_index * 64 + remaining.trailing_zeros() as usize);
+            remaining &= remaining - 1;
+        }
+    }
+    indices.retain(|&index| index < dimension);
+
+    from_indices_or_empty(dimension, indices)
+}
+
+
+fn weighted_hamming_words(a: &[u64], b: &[u64], weights: &[f64]) -> f64 {
+    let mut distance = 0.0;
+    for (word_index, (a, b)) in a.iter().zip(b).enumerate() {
+        let mut remaining = a ^ b;
+        while remaining != 0 {
+            distance += weights[word_index * 64 + remaining.trailing_zeros() as usize];
+            remaining &= remaining - 1;
+        }
+    }
+    distance
+}
@@ -1,179 +1,31 @@
-use std::collections::HashMap;
-use sprs::CsVec;
-use rand::seq::index::sample;
-use rand::distr::Uniform;
-use rand::rng;
+//! Binary hypervectors. The bit-packed representation (`kernels` and the word-level API of `matrix`) only needs the
+//! standard library, so it builds without default features; the sparse `CsVec<i8>` vectors and the representations
+//! converting to and from them need the `sparse` feature.
 
-use crate::errors::OVSAError;
+#[cfg(feature = "sparse")]
+mod sparse;
 
+#[cfg(feature = "sparse")]
+pub use sparse::*;
 
+#[cfg(feature = "sparse")]
+pub mod compact;
 
-/// Generates a sparse random binary vector of given size with a specified number of active (1) entries.
-/// This could probably be optimized to use bit fields instead of u8 vectors.
-/// # Arguments
-/// * `dimension` - The size of the vector.
-/// * `n_active` - The number of active (1) entries in the vector.
-/// # Returns
-/// A sparse binary vector represented as `CsVec<i8>`.
-pub fn sparse_random(dimension: usize, n_active: usize) -> Result<CsVec<i8>, OVSAError> {
-    if n_active == 0 {
-        return Err(OVSAError::ZeroActiveElements);
-    }
-    if dimension == 0 {
-        return Err(OVSAError::ZeroDimension);
-    }
-    if n_active > dimension {
-        return Err(OVSAError::TooManyActiveElements);
-    }
-    let mut rng  = rng();
-    let indices: Vec<usize> = sample(&mut rng, dimension, n_active).into_vec();
+#[cfg(feature = "sparse")]
+pub mod compressed;
 
-    let data: Vec<i8> = vec![1i8; n_active];
+#[cfg(feature = "sparse")]
+pub mod counters;
 
-    Ok(CsVec::new_from_unsorted(dimension, indices, data).unwrap())
-}
+pub mod kernels;
 
+pub mod matrix;
 
-/// Creates a sparse binary vector from given indices of active (1) entries.
-/// # Arguments
-/// * `dimension` - The size of the vector.
-/// * `indices` - A slice of indices where the entries are active (1).
-/// # Returns
-/// A sparse binary vector represented as `CsVec<i8>`.
-pub fn from_indices(dimension: usize, indices: &[usize]) -> Result<CsVec<i8>, OVSAError> {
-    if indices.is_empty() {
-        return Err(OVSAError::EmptyIndices);
-    }
-    if dimension == 0 {
-        return Err(OVSAError::ZeroDimension);
-    }
+#[cfg(feature = "sparse")]
+pub mod progressive;
 
-    let n_active = indices.len();
-    let data: Vec<i8> = vec![1i8; n_active];
-
-    Ok(CsVec::new_from_unsorted(dimension, indices.to_vec(), data).unwrap())
-}
-
-
-/// Computes the Hamming distance between two sparse binary vectors.
-/// The Hamming distance is defined as the number of positions at which the corresponding entries are different.
-/// # Arguments
-/// * `vec1` - The first sparse binary vector.
-/// * `vec2` - The second sparse binary vector.
-/// # Returns
-/// The Hamming distance as a usize.
-pub fn hamming_distance(vec1: &CsVec<i8>, vec2: &CsVec<i8>) -> usize {
-    assert_eq!(vec1.dim(), vec2.dim(), "Vectors must be of the same dimension to compute Hamming distance.");
-
-    let bound_vec = xor(vec1, vec2).expect("XOR operation failed in Hamming distance computation.");
-    bound_vec.nnz()
-}
-
-
-/// Computes the consensus sum of a slice of sparse binary vectors.
-/// The consensus sum is determined by taking the majority value at each index across all vectors.
-/// # Arguments
-/// * `vectors` - A slice of sparse binary vectors represented as `CsVec<i8>`.
-/// # Returns
-/// A sparse binary vector representing the consensus sum.
-pub fn consensus_sum(vectors: &[CsVec<i8>]) -> Result<CsVec<i8>, OVSAError> {
-    if vectors.is_empty() {
-        return Err(OVSAError::EmptyVectorList);
-    }
-
-    // todo: optimize this to avoid using a full vector
-    let size: usize = vectors[0].dim();
-    let half_size: i16 = (vectors.len() as i16) / 2;
-    let mut counts: HashMap<usize, i16> = HashMap::new();
-
-    for vec in vectors {
-        if size != vec.dim() {
-            return Err(OVSAError::VectorSizeMismatch);
-        }
-
-        for index in vec.indices() {
-            *counts.entry(*index).or_insert(0) += 1;
-        }
-    }
-
-    let mut rng  = rng();
-    let uniform = Uniform::new(0.0, 1.0).unwrap();
-
-    fn set_active(value: i16, rng: &mut impl rand::Rng, uniform: &Uniform<f64>) -> bool {
-        if value > 0 {
-            true
-        } else if value < 0 {
-            false
-        } else {
-            rng.sample(uniform) > 0.5
-        }
-    }
-
-    let indices: Vec<usize> = counts.iter()
-        .filter_map(|(&index, &value)| if set_active(value - half_size, &mut rng, &uniform) { Some(index) } else { None })
-        .collect();
-
-    Ok(from_indices(size, &indices)?)
-}
-
-
-/// Computes the element-wise XOR of two sparse binary vectors.
-/// # Arguments
-/// * `vec1` - The first sparse binary vector.
-/// * `vec2` - The second sparse binary vector.
-/// # Returns
-/// A sparse binary vector representing the XOR result.
-pub fn xor(vec1: &CsVec<i8>, vec2: &CsVec<i8>) -> Result<CsVec<i8>, OVSAError> {
-    if vec1.dim() != vec2.dim() {
-        return Err(OVSAError::VectorSizeMismatch);
-    }
-
-    let size: usize = vec1.dim();
-    // to simulate an XOR operation, we add the two vectors and keep only the entries where the sum is 1
-    let result: CsVec<i8> = vec1 + vec2;
-    let indices: Vec<usize> = result.iter()
-        // XOR operation: 1 + 1 = 0, so we keep only entries with value 1
-        .filter_map(|(index, &value)| if value == 1 { Some(index) } else { None })
-        .collect();
-
-    Ok(from_indices(size, &indices)?)
-}
-
-
-/// Performs a cyclic shift on a sparse binary vector.
-/// Typically used for implementing permutation operations, or binding/unbinding via shifting (e.g. right/left, respectively).
-/// # Arguments
-/// * `vec` - The sparse binary vector to be shifted.
-/// * `shift_by` - The number of positions to shift. Positive values shift to the right, negative values shift to the left.
-/// # Returns
-/// A new sparse binary vector that has been cyclically shifted.
-pub fn cyclic_shift(vec: &CsVec<i8>, shift_by: isize) -> CsVec<i8> {
-    let size = vec.dim() as isize;
-    let mut new_indices: Vec<usize> = Vec::new();
-
-    for (index, _) in vec.iter() {
-        let new_index = (index as isize + shift_by).rem_euclid(size);
-        new_indices.push(new_index as usize);
-    }
-
-    from_indices(vec.dim(), &new_indices).unwrap()
-}
-
-
-/// Computes the similarity between two sparse binary vectors.
-/// Similarity is defined as 1 - (Hamming distance / dimension).
-/// # Arguments
-/// * `vec1` - The first sparse binary vector.
-/// * `vec2` - The second sparse binary vector.
-/// # Returns
-/// The similarity as a f64 value between 0.0 and 1.0
-pub fn similarity(vec1: &CsVec<i8>, vec2: &CsVec<i8>) -> Result<f64, OVSAError> {
-    if vec1.dim() != vec2.dim() {
-        return Err(OVSAError::VectorSizeMismatch);
-    }
-
-    let sim = hamming_distance(vec1, vec2) as f64 / vec1.dim() as f64;
-
-    Ok(1f64 - sim)
-}
+#[cfg(feature = "roaring")]
+pub mod roaring;
 
+#[cfg(feature = "sparse")]
+pub mod segmented;
@@ -0,0 +1,166 @@
+//! Progressive (bit-serial) similarity search over packed vectors: rows are compared to the query a few words at a
+//! time, every partial comparison bounds the final similarity, and rows whose upper bound falls below the `k`-th best
+//! lower bound are dropped. Most rows of a large memory are thus discarded after a small prefix of their words, and
+//! the scan stops as soon as the ranking of the survivors is decided.
+
+use sprs::CsVec;
+
+use crate::binary::kernels;
+use crate::binary::matrix::{HvMatrix, pack};
+use crate::errors::OVSAError;
+use crate::memory::ItemMemory;
+use crate::trace::span;
+
+
+/// The number of words compared between two refinements of the bounds.
+const PROGRESSIVE_CHUNK_WORDS: usize = 4;
+
+
+/// The similarity of a row to a query as known after comparing a prefix of their words.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimilarityBounds {
+    /// The similarity of the compared prefix, an unbiased estimate of the similarity of random-like vectors.
+    pub estimate: f64,
+    /// The lower bound of the similarity.
+    pub lower: f64,
+    /// The upper bound of the similarity.
+    pub upper: f64,
+    /// The number of words compared.
+    pub n_words: usize,
+}
+
+
+impl SimilarityBounds {
+    /// Bounds the similarity of two vectors from the distance over their first dimensions.
+    /// # Arguments
+    /// * `distance` - The Hamming distance over the compared dimensions.
+    /// * `n_compared` - The number of compared dimensions, at most `dimension`.
+    /// * `dimension` - The dimension of the vectors.
+    /// * `n_words` - The number of words compared.
+    /// * `confidence` - The probability that the similarity lies within the bounds. With 1, the bounds are the hard
+    ///   limits of the distance over the remaining dimensions; below 1, they assume the remaining dimensions differ at
+    ///   about the same rate as the compared ones, as for random-like vectors, and are much tighter.
+    /// # Returns
+    /// The bounds.
+    pub fn from_prefix(distance: usize, n_compared: usize, dimension: usize, n_words: usize, confidence: f64) -> Self {
+        let n_remaining = dimension - n_compared;
+        if n_compared == 0 {
+            return SimilarityBounds { estimate: 0.5, lower: 0.0, upper: 1.0, n_words };
+        }
+
+        let estimate = 1.0 - distance as f64 / n_compared as f64;
+        // the distance over the remaining dimensions is anything from 0 to all of them
+        let mut lower = (n_compared - distance) as f64 / dimension as f64;
+        let mut upper = (n_compared - distance + n_remaining) as f64 / dimension as f64;
+        if confidence < 1.0 && n_remaining > 0 {
+            // Serfling's inequality for sampling the differing dimensions without replacement
+            let finite = 1.0 - (n_compared - 1) as f64 / dimension as f64;
+            let margin = (finite * (2.0 / (1.0 - confidence)).ln() / (2.0 * n_compared as f64)).sqrt();
+            lower = lower.max(estimate - margin);
+            upper = upper.min(estimate + margin);
+        }
+        SimilarityBounds { estimate, lower, upper, n_words }
+    }
+
+    /// Returns true if every word was compared, i.e. the similarity is exact.
+    pub fn is_exact(&self) -> bool {
+        self.lower == self.upper
+    }
+}
+
+
+impl HvMatrix {
+    /// Finds the `k` rows most similar to a vector by progressive comparison.
+    /// # Arguments
+    /// * `vector` - The vector to compare to.
+    /// * `k` - The maximum number of results.
+    /// * `confidence` - The confidence of the bounds, in `(0, 1]`; 1 returns the exact top `k`, lower values stop
+    ///   earlier at the risk of missing a row whose remaining words differ unusually.
+    /// # Returns
+    /// The (row, bounds) pairs of the best rows, sorted by decreasing estimate.
+    pub fn top_k_progressive(&self, vector: &CsVec<i8>, k: usize, confidence: f64) -> Result<Vec<(usize, SimilarityBounds)>, OVSAError> {
+        if vector.dim() != self.dimension() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        if !(confidence > 0.0 && confidence <= 1.0) {
+            return Err(OVSAError::InvalidArgument(format!("confidence must be in (0, 1], got {}", confidence)));
+        }
+        span!(DEBUG, "top_k_progressive", n_rows = self.len(), k);
+
+        let packed = pack(vector);
+        let mut candidates: Vec<(usize, usize)> = (0..self.len()).map(|row| (row, 0)).collect();
+        let mut bounds: Vec<(usize, SimilarityBounds)> = Vec::new();
+        let mut n_words = 0;
+        while n_words < self.words_per_row() && k > 0 {
+            let end = (n_words + PROGRESSIVE_CHUNK_WORDS).min(self.words_per_row());
+            for (row, distance) in &mut candidates {
+                let words = &self.row(*row)[n_words..end];
+                *distance += kernels::xor_popcount(words, &packed[n_words..end]);
+            }
+            n_words = end;
+
+            let n_compared = (n_words * 64).min(self.dimension());
+            bounds = candidates.iter()
+                .map(|&(row, distance)| (row, SimilarityBounds::from_prefix(distance, n_compared, self.dimension(), n_words, confidence)))
+                .collect();
+            if bounds.len() > k {
+                // no row below the k-th best lower bound can make it into the top k
+                let mut lower: Vec<f64> = bounds.iter().map(|(_, bounds)| bounds.lower).collect();
+                lower.select_nth_unstable_by(k - 1, |a, b| b.total_cmp(a));
+                let threshold = lower[k - 1];
+                let kept: Vec<bool> = bounds.iter().map(|(_, bounds)| bounds.upper >= threshold).collect();
+                let mut flags = kept.iter();
+                candidates.retain(|_| *flags.next().unwrap());
+                let mut flags = kept.iter();
+                bounds.retain(|_| *flags.next().unwrap());
+            }
+            if bounds.len() <= k && is_decided(&bounds) {
+                break;
+            }
+        }
+
+        bounds.sort_by(|a, b| b.1.estimate.total_cmp(&a.1.estimate).then_with(|| a.0.cmp(&b.0)));
+        bounds.truncate(k);
+        Ok(bounds)
+    }
+}
+
+
+impl ItemMemory<CsVec<i8>> {
+    /// Finds the `k` stored entries most similar to a query by progressive comparison against the packed rows of the
+    /// memory, which suits large memories queried many times.
+    /// # Arguments
+    /// * `matrix` - The packed rows of this memory, from `to_matrix`.
+    /// * `query` - The query vector.
+    /// * `k` - The maximum number of results.
+    /// * `confidence` - The confidence of the bounds, in `(0, 1]`, see `HvMatrix::top_k_progressive`.
+    /// # Returns
+    /// The (label, bounds) pairs sorted by decreasing estimate.
+    pub fn query_progressive(&self, matrix: &HvMatrix, query: &CsVec<i8>, k: usize, confidence: f64) -> Result<Vec<(String, SimilarityBounds)>, OVSAError> {
+        if matrix.len() != self.len() || matrix.dimension() != self.dimension() {
+            return Err(OVSAError::InvalidArgument("the matrix does not hold the rows of this memory".to_string()));
+        }
+
+        let matches = matrix.top_k_progressive(query, k, confidence)?;
+        Ok(matches.into_iter().map(|(row, bounds)| (self.labels()[row].clone(), bounds)).collect())
+    }
+
+    /// Finds the stored entry most similar to a query by progressive comparison, see `query_progressive`.
+    /// # Arguments
+    /// * `matrix` - The packed rows of this memory, from `to_matrix`.
+    /// * `query` - The query vector.
+    /// * `confidence` - The confidence of the bounds, in `(0, 1]`.
+    /// # Returns
+    /// The label and similarity bounds of the best match, or `None` if the memory is empty.
+    pub fn cleanup_progressive(&self, matrix: &HvMatrix, query: &CsVec<i8>, confidence: f64) -> Result<Option<(String, SimilarityBounds)>, OVSAError> {
+        Ok(self.query_progressive(matrix, query, 1, confidence)?.into_iter().next())
+    }
+}
+
+
+/// Returns true if the intervals of the bounds are disjoint, i.e. the order of the rows is decided.
+fn is_decided(bounds: &[(usize, SimilarityBounds)]) -> bool {
+    let mut sorted: Vec<&SimilarityBounds> = bounds.iter().map(|(_, bounds)| bounds).collect();
+    sorted.sort_by(|a, b| b.estimate.total_cmp(&a.estimate));
+    sorted.windows(2).all(|pair| pair[0].lower > pair[1].upper)
+}
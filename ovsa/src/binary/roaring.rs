@@ -0,0 +1,223 @@
+//! Sparse binary vectors backed by roaring bitmaps, an alternative to `CsVec<i8>` for moderately dense codes. A
+//! roaring bitmap splits the dimensions into chunks of 65536 and stores every chunk as a sorted array of up to 4096
+//! active offsets or, above that density of about 6%, as an 8 KiB bitset. Intersection, union and XOR then combine the
+//! chunks container by container, word-parallel for the bitsets, where `CsVec<i8>` always walks its indices one by
+//! one; the `benches/roaring.rs` benchmark compares the two. Very sparse codes gain little over `CsVec<i8>` or
+//! `compact::CompactBinary`. Like the compact vectors, the dimension is limited to `compact::MAX_DIMENSION`.
+
+use ::roaring::RoaringBitmap;
+use sprs::CsVec;
+
+use crate::binary::compact::check_dimension;
+use crate::errors::OVSAError;
+use crate::hypervector::Hypervector;
+use crate::mask::total_weight;
+use crate::memory::ItemMemory;
+
+
+/// A sparse binary vector stored as a roaring bitmap of its active indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoaringBinary {
+    dimension: usize,
+    bits: RoaringBitmap,
+}
+
+
+impl RoaringBinary {
+    /// Creates a vector from its active indices.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vector, at most `compact::MAX_DIMENSION`.
+    /// * `indices` - The active indices, in any order; duplicates count once.
+    /// # Returns
+    /// The `RoaringBinary`, `InvalidArgument` for a dimension above `compact::MAX_DIMENSION` or `VectorSizeMismatch`
+    /// for an index outside the dimension.
+    pub fn from_indices(dimension: usize, indices: &[usize]) -> Result<Self, OVSAError> {
+        check_dimension(dimension)?;
+        let mut bits = RoaringBitmap::new();
+        for &index in indices {
+            if index >= dimension {
+                return Err(OVSAError::VectorSizeMismatch);
+            }
+            bits.insert(index as u32);
+        }
+
+        Ok(RoaringBinary { dimension, bits })
+    }
+
+    /// Wraps a roaring bitmap of active indices.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vector, at most `compact::MAX_DIMENSION`.
+    /// * `bits` - The active indices.
+    /// # Returns
+    /// The `RoaringBinary`, `InvalidArgument` for a dimension above `compact::MAX_DIMENSION` or `VectorSizeMismatch` if
+    /// the bitmap holds an index outside the dimension.
+    pub fn from_bitmap(dimension: usize, bits: RoaringBitmap) -> Result<Self, OVSAError> {
+        check_dimension(dimension)?;
+        if bits.max().is_some_and(|max| max as usize >= dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(RoaringBinary { dimension, bits })
+    }
+
+    /// Returns the roaring bitmap of the active indices.
+    pub fn bitmap(&self) -> &RoaringBitmap {
+        &self.bits
+    }
+
+    /// Restores the `CsVec<i8>` representation.
+    pub fn to_sparse(&self) -> CsVec<i8> {
+        CsVec::new(self.dimension, self.indices().collect(), vec![1i8; self.nnz()])
+    }
+
+    /// Returns the dimension of the vector.
+    pub fn dim(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the number of active entries.
+    pub fn nnz(&self) -> usize {
+        self.bits.len() as usize
+    }
+
+    /// Returns the active indices in increasing order.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits.iter().map(|index| index as usize)
+    }
+
+    /// Returns true if the entry at the index is active.
+    /// # Arguments
+    /// * `index` - The index, which may lie outside the dimension.
+    pub fn contains(&self, index: usize) -> bool {
+        u32::try_from(index).is_ok_and(|index| self.bits.contains(index))
+    }
+
+    /// Binds the vector to another with XOR, like `binary::xor`.
+    /// # Arguments
+    /// * `other` - The vector to bind with.
+    /// # Returns
+    /// The bound vector.
+    pub fn xor(&self, other: &Self) -> Result<Self, OVSAError> {
+        self.check_dimension(other)?;
+        Ok(RoaringBinary { dimension: self.dimension, bits: &self.bits ^ &other.bits })
+    }
+
+    /// Computes the union with another vector, active wherever either is.
+    /// # Arguments
+    /// * `other` - The other vector.
+    /// # Returns
+    /// The union.
+    pub fn union(&self, other: &Self) -> Result<Self, OVSAError> {
+        self.check_dimension(other)?;
+        Ok(RoaringBinary { dimension: self.dimension, bits: &self.bits | &other.bits })
+    }
+
+    /// Computes the intersection with another vector, active wherever both are.
+    /// # Arguments
+    /// * `other` - The other vector.
+    /// # Returns
+    /// The intersection.
+    pub fn intersection(&self, other: &Self) -> Result<Self, OVSAError> {
+        self.check_dimension(other)?;
+        Ok(RoaringBinary { dimension: self.dimension, bits: &self.bits & &other.bits })
+    }
+
+    /// Counts the entries active in both vectors without building their intersection.
+    /// # Arguments
+    /// * `other` - The other vector.
+    /// # Returns
+    /// The number of common active entries.
+    pub fn overlap(&self, other: &Self) -> Result<usize, OVSAError> {
+        self.check_dimension(other)?;
+        Ok(self.bits.intersection_len(&other.bits) as usize)
+    }
+
+    /// Computes the Hamming distance to another vector without building their XOR.
+    /// # Arguments
+    /// * `other` - The vector to compare to.
+    /// # Returns
+    /// The number of positions at which the two vectors differ.
+    pub fn hamming_distance(&self, other: &Self) -> Result<usize, OVSAError> {
+        self.check_dimension(other)?;
+        Ok(self.bits.symmetric_difference_len(&other.bits) as usize)
+    }
+
+    fn check_dimension(&self, other: &Self) -> Result<(), OVSAError> {
+        if self.dimension != other.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+
+impl TryFrom<&CsVec<i8>> for RoaringBinary {
+    type Error = OVSAError;
+
+    /// Converts a sparse binary vector, failing with `InvalidArgument` for a dimension above `compact::MAX_DIMENSION`.
+    fn try_from(vec: &CsVec<i8>) -> Result<Self, OVSAError> {
+        check_dimension(vec.dim())?;
+        let bits = RoaringBitmap::from_sorted_iter(vec.indices().iter().map(|&index| index as u32))
+            .expect("The indices of a CsVec are sorted.");
+        Ok(RoaringBinary { dimension: vec.dim(), bits })
+    }
+}
+
+
+impl From<&RoaringBinary> for CsVec<i8> {
+    fn from(vec: &RoaringBinary) -> Self {
+        vec.to_sparse()
+    }
+}
+
+
+impl Hypervector for RoaringBinary {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn similarity(&self, other: &Self) -> Result<f64, OVSAError> {
+        Ok(1f64 - self.hamming_distance(other)? as f64 / self.dimension as f64)
+    }
+
+    fn weighted_similarity(&self, other: &Self, weights: &[f64]) -> Result<f64, OVSAError> {
+        self.check_dimension(other)?;
+        let total = total_weight(weights, self.dimension)?;
+        let differing: f64 = (&self.bits ^ &other.bits).iter().map(|index| weights[index as usize]).sum();
+
+        Ok(1f64 - differing / total)
+    }
+
+    fn heap_bytes(&self) -> usize {
+        // the byte counts of the statistics report the bitsets in bits and the 2-byte array values as 4 bytes
+        let statistics = self.bits.statistics();
+        2 * statistics.n_values_array_containers as usize + 8192 * statistics.n_bitset_containers as usize + statistics.n_bytes_run_containers as usize
+    }
+}
+
+
+impl ItemMemory<CsVec<i8>> {
+    /// Copies the memory into one storing `RoaringBinary` vectors, with the same labels in the same order.
+    /// # Returns
+    /// The roaring copy, or `InvalidArgument` if the dimension exceeds `compact::MAX_DIMENSION`.
+    pub fn roaring(&self) -> Result<ItemMemory<RoaringBinary>, OVSAError> {
+        let mut roaring = ItemMemory::new(self.dimension())?;
+        for (label, vector) in self.iter() {
+            roaring.insert(label, RoaringBinary::try_from(vector)?)?;
+        }
+        Ok(roaring)
+    }
+}
+
+
+impl ItemMemory<RoaringBinary> {
+    /// Copies the memory back into one storing `CsVec<i8>` vectors, with the same labels in the same order.
+    pub fn to_sparse(&self) -> ItemMemory<CsVec<i8>> {
+        let mut sparse = ItemMemory::new(self.dimension()).expect("The dimension was validated on creation.");
+        for (label, vector) in self.iter() {
+            sparse.insert(label, vector.to_sparse()).expect("The dimensions match.");
+        }
+        sparse
+    }
+}
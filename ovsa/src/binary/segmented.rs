@@ -0,0 +1,286 @@
+//! Segmented vectors: the dimension is partitioned into named segments, e.g. 2000 dimensions for "color" and 4000 for
+//! "shape", and binding, bundling and similarity apply segment by segment. Fields encoded into different segments thus
+//! never interfere, while the whole vector still converts to a flat sparse binary vector of the summed dimension, the
+//! segments laid out one after the other in order.
+
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::{consensus_sum_with_rng, from_indices_or_empty, similarity, sparse_random_with_rng, xor};
+use crate::errors::OVSAError;
+use crate::hypervector::Hypervector;
+use crate::rng::with_global_rng;
+
+
+/// A sparse binary vector partitioned into named segments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentedHV {
+    names: Vec<String>,
+    segments: Vec<CsVec<i8>>,
+}
+
+
+impl SegmentedHV {
+    /// Creates a segmented vector from its segments.
+    /// # Arguments
+    /// * `segments` - The (name, vector) pairs in layout order; names must be unique and dimensions non-zero.
+    /// # Returns
+    /// A new `SegmentedHV`.
+    pub fn from_segments(segments: Vec<(String, CsVec<i8>)>) -> Result<Self, OVSAError> {
+        if segments.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+        if segments.iter().any(|(_, segment)| segment.dim() == 0) {
+            return Err(OVSAError::ZeroDimension);
+        }
+        for (position, (name, _)) in segments.iter().enumerate() {
+            if segments[..position].iter().any(|(other, _)| other == name) {
+                return Err(OVSAError::InvalidArgument(format!("duplicate segment {:?}", name)));
+            }
+        }
+
+        let (names, segments) = segments.into_iter().unzip();
+        Ok(SegmentedHV { names, segments })
+    }
+
+    /// Creates a segmented vector without active entries.
+    /// # Arguments
+    /// * `layout` - The (name, dimension) pairs of the segments, in order.
+    /// # Returns
+    /// A new `SegmentedHV`.
+    pub fn zeros(layout: &[(&str, usize)]) -> Result<Self, OVSAError> {
+        SegmentedHV::from_segments(layout.iter().map(|&(name, dimension)| (name.to_string(), from_indices_or_empty(dimension, Vec::new()))).collect())
+    }
+
+    /// Creates a random segmented vector.
+    /// # Arguments
+    /// * `layout` - The (name, dimension) pairs of the segments, in order.
+    /// * `density` - The share of active entries of every segment, in `(0, 1]`.
+    /// # Returns
+    /// A new `SegmentedHV`.
+    pub fn random(layout: &[(&str, usize)], density: f64) -> Result<Self, OVSAError> {
+        with_global_rng(|rng| SegmentedHV::random_with_rng(layout, density, rng))
+    }
+
+    /// Creates a random segmented vector using the provided random number generator.
+    /// # Arguments
+    /// * `layout` - The (name, dimension) pairs of the segments, in order.
+    /// * `density` - The share of active entries of every segment, in `(0, 1]`.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// A new `SegmentedHV`.
+    pub fn random_with_rng<R: Rng + ?Sized>(layout: &[(&str, usize)], density: f64, rng: &mut R) -> Result<Self, OVSAError> {
+        if !(density > 0.0 && density <= 1.0) {
+            return Err(OVSAError::InvalidArgument(format!("density must be in (0, 1], got {}", density)));
+        }
+
+        let segments = layout.iter()
+            .map(|&(name, dimension)| Ok((name.to_string(), sparse_random_with_rng(dimension, (density * dimension as f64).round() as usize, rng)?)))
+            .collect::<Result<Vec<_>, OVSAError>>()?;
+        SegmentedHV::from_segments(segments)
+    }
+
+    /// Splits a flat vector into segments.
+    /// # Arguments
+    /// * `flat` - The flat vector, of the summed dimension of the layout.
+    /// * `layout` - The (name, dimension) pairs of the segments, in order.
+    /// # Returns
+    /// A new `SegmentedHV`.
+    pub fn from_flat(flat: &CsVec<i8>, layout: &[(&str, usize)]) -> Result<Self, OVSAError> {
+        if layout.iter().map(|&(_, dimension)| dimension).sum::<usize>() != flat.dim() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let mut segments = Vec::with_capacity(layout.len());
+        let mut start = 0;
+        for &(name, dimension) in layout {
+            let indices = flat.indices().iter().filter(|&&index| (start..start + dimension).contains(&index)).map(|&index| index - start).collect();
+            segments.push((name.to_string(), from_indices_or_empty(dimension, indices)));
+            start += dimension;
+        }
+        SegmentedHV::from_segments(segments)
+    }
+
+    /// Concatenates the segments into a flat vector, e.g. to store the vector in an `ItemMemory` of plain vectors.
+    pub fn to_flat(&self) -> CsVec<i8> {
+        let mut indices = Vec::new();
+        let mut start = 0;
+        for segment in &self.segments {
+            indices.extend(segment.indices().iter().map(|&index| start + index));
+            start += segment.dim();
+        }
+        from_indices_or_empty(start, indices)
+    }
+
+    /// Returns the (name, dimension) pairs of the segments, in order.
+    pub fn layout(&self) -> Vec<(&str, usize)> {
+        self.names.iter().zip(&self.segments).map(|(name, segment)| (name.as_str(), segment.dim())).collect()
+    }
+
+    /// Returns the segment names, in order.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns the range of a segment in the flat vector.
+    /// # Arguments
+    /// * `name` - The name of the segment.
+    pub fn range(&self, name: &str) -> Option<std::ops::Range<usize>> {
+        let position = self.position(name)?;
+        let start: usize = self.segments[..position].iter().map(|segment| segment.dim()).sum();
+        Some(start..start + self.segments[position].dim())
+    }
+
+    /// Returns a segment.
+    /// # Arguments
+    /// * `name` - The name of the segment.
+    pub fn segment(&self, name: &str) -> Option<&CsVec<i8>> {
+        self.position(name).map(|position| &self.segments[position])
+    }
+
+    /// Replaces a segment, e.g. to encode one field.
+    /// # Arguments
+    /// * `name` - The name of the segment.
+    /// * `vector` - The new segment, of the segment's dimension.
+    pub fn set_segment(&mut self, name: &str, vector: CsVec<i8>) -> Result<(), OVSAError> {
+        let position = self.position(name).ok_or_else(|| unknown_segment(name))?;
+        if vector.dim() != self.segments[position].dim() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        self.segments[position] = vector;
+        Ok(())
+    }
+
+    /// Binds two segmented vectors segment by segment.
+    /// # Arguments
+    /// * `other` - A vector of the same layout.
+    /// # Returns
+    /// The segment-wise XOR.
+    pub fn bind(&self, other: &SegmentedHV) -> Result<SegmentedHV, OVSAError> {
+        self.check_layout(other)?;
+        let segments = self.segments.iter().zip(&other.segments).map(|(a, b)| xor(a, b)).collect::<Result<Vec<_>, OVSAError>>()?;
+        Ok(SegmentedHV { names: self.names.clone(), segments })
+    }
+
+    /// Binds one segment with a vector, leaving the other segments unchanged.
+    /// # Arguments
+    /// * `name` - The name of the segment.
+    /// * `vector` - The vector to bind, of the segment's dimension.
+    /// # Returns
+    /// The vector with the bound segment.
+    pub fn bind_segment(&self, name: &str, vector: &CsVec<i8>) -> Result<SegmentedHV, OVSAError> {
+        let position = self.position(name).ok_or_else(|| unknown_segment(name))?;
+        let mut result = self.clone();
+        result.segments[position] = xor(&self.segments[position], vector)?;
+        Ok(result)
+    }
+
+    /// Bundles segmented vectors segment by segment.
+    /// # Arguments
+    /// * `vectors` - The vectors, all of the same layout.
+    /// # Returns
+    /// The segment-wise consensus sum.
+    pub fn bundle(vectors: &[SegmentedHV]) -> Result<SegmentedHV, OVSAError> {
+        with_global_rng(|rng| SegmentedHV::bundle_with_rng(vectors, rng))
+    }
+
+    /// Bundles segmented vectors segment by segment, breaking ties with the provided random number generator.
+    /// # Arguments
+    /// * `vectors` - The vectors, all of the same layout.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// The segment-wise consensus sum.
+    pub fn bundle_with_rng<R: Rng + ?Sized>(vectors: &[SegmentedHV], rng: &mut R) -> Result<SegmentedHV, OVSAError> {
+        let first = vectors.first().ok_or(OVSAError::EmptyVectorList)?;
+        for vector in &vectors[1..] {
+            first.check_layout(vector)?;
+        }
+
+        let segments = (0..first.segments.len())
+            .map(|position| {
+                let segment: Vec<CsVec<i8>> = vectors.iter().map(|vector| vector.segments[position].clone()).collect();
+                consensus_sum_with_rng(&segment, rng)
+            })
+            .collect::<Result<Vec<_>, OVSAError>>()?;
+        Ok(SegmentedHV { names: first.names.clone(), segments })
+    }
+
+    /// Computes the similarity of every segment to the same segment of another vector.
+    /// # Arguments
+    /// * `other` - A vector of the same layout.
+    /// # Returns
+    /// The (name, similarity) pairs in layout order.
+    pub fn segment_similarities(&self, other: &SegmentedHV) -> Result<Vec<(String, f64)>, OVSAError> {
+        self.check_layout(other)?;
+        self.names.iter().zip(self.segments.iter().zip(&other.segments))
+            .map(|(name, (a, b))| Ok((name.clone(), similarity(a, b)?)))
+            .collect()
+    }
+
+    /// Computes the similarity of one segment to the same segment of another vector.
+    /// # Arguments
+    /// * `other` - A vector of the same layout.
+    /// * `name` - The name of the segment.
+    pub fn segment_similarity(&self, other: &SegmentedHV, name: &str) -> Result<f64, OVSAError> {
+        self.check_layout(other)?;
+        let position = self.position(name).ok_or_else(|| unknown_segment(name))?;
+        similarity(&self.segments[position], &other.segments[position])
+    }
+
+    /// Builds per-dimension weights of the flat vector from per-segment weights, for weighted similarities that
+    /// emphasize some fields.
+    /// # Arguments
+    /// * `weights` - The (name, weight) pairs; unnamed segments weigh 1.
+    /// # Returns
+    /// One weight per dimension of the flat vector.
+    pub fn segment_weights(&self, weights: &[(&str, f64)]) -> Result<Vec<f64>, OVSAError> {
+        let segments = weights.iter()
+            .map(|&(name, weight)| Ok((self.range(name).ok_or_else(|| unknown_segment(name))?, weight)))
+            .collect::<Result<Vec<_>, OVSAError>>()?;
+        crate::mask::segment_weights(self.dimension(), &segments, 1.0)
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|other| other == name)
+    }
+
+    fn check_layout(&self, other: &SegmentedHV) -> Result<(), OVSAError> {
+        if self.names != other.names {
+            return Err(OVSAError::InvalidArgument("the segment names differ".to_string()));
+        }
+        if self.segments.iter().zip(&other.segments).any(|(a, b)| a.dim() != b.dim()) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        Ok(())
+    }
+}
+
+
+impl Hypervector for SegmentedHV {
+    fn dimension(&self) -> usize {
+        self.segments.iter().map(|segment| segment.dim()).sum()
+    }
+
+    /// The similarity of the flat vectors, i.e. the segment similarities weighted by segment dimension.
+    fn similarity(&self, other: &Self) -> Result<f64, OVSAError> {
+        let similarities = self.segment_similarities(other)?;
+        let weighted: f64 = similarities.iter().zip(&self.segments).map(|((_, similarity), segment)| similarity * segment.dim() as f64).sum();
+        Ok(weighted / self.dimension() as f64)
+    }
+
+    fn weighted_similarity(&self, other: &Self, weights: &[f64]) -> Result<f64, OVSAError> {
+        self.check_layout(other)?;
+        crate::binary::weighted_similarity(&self.to_flat(), &other.to_flat(), weights)
+    }
+
+    fn heap_bytes(&self) -> usize {
+        let labels: usize = self.names.iter().map(|name| name.capacity()).sum();
+        labels + self.segments.iter().map(|segment| segment.heap_bytes()).sum::<usize>()
+    }
+}
+
+
+fn unknown_segment(name: &str) -> OVSAError {
+    OVSAError::InvalidArgument(format!("unknown segment {:?}", name))
+}
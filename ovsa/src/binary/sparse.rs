@@ -0,0 +1,906 @@
+//! The operations on sparse binary vectors stored as `CsVec<i8>`, re-exported from `binary`.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use sprs::CsVec;
+use rand::{Rng, SeedableRng};
+use rand::seq::index::sample;
+use rand::distr::Uniform;
+
+use crate::errors::OVSAError;
+use crate::hypervector::{BundleStrategy, Hardening};
+use crate::mask::{check_range, total_weight};
+use crate::rng::{OvsaRng, stream_rng, with_global_rng};
+use crate::sketch::splitmix64;
+use crate::trace::span;
+
+use super::kernels;
+
+
+/// Generates a sparse random binary vector of given size with a specified number of active (1) entries.
+/// This could probably be optimized to use bit fields instead of u8 vectors.
+/// # Arguments
+/// * `dimension` - The size of the vector.
+/// * `n_active` - The number of active (1) entries in the vector.
+/// # Returns
+/// A sparse binary vector represented as `CsVec<i8>`.
+pub fn sparse_random(dimension: usize, n_active: usize) -> Result<CsVec<i8>, OVSAError> {
+    with_global_rng(|rng| sparse_random_with_rng(dimension, n_active, rng))
+}
+
+
+/// Generates a sparse random binary vector using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The size of the vector.
+/// * `n_active` - The number of active (1) entries in the vector.
+/// * `rng` - The random number generator to draw the active indices from.
+/// # Returns
+/// A sparse binary vector represented as `CsVec<i8>`.
+pub fn sparse_random_with_rng<R: Rng + ?Sized>(dimension: usize, n_active: usize, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+    if n_active == 0 {
+        return Err(OVSAError::ZeroActiveElements);
+    }
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+    if n_active > dimension {
+        return Err(OVSAError::TooManyActiveElements);
+    }
+    let indices: Vec<usize> = sample(rng, dimension, n_active).into_vec();
+
+    let data: Vec<i8> = vec![1i8; n_active];
+
+    Ok(CsVec::new_from_unsorted(dimension, indices, data).unwrap())
+}
+
+
+/// Creates a sparse binary vector from given indices of active (1) entries.
+/// # Arguments
+/// * `dimension` - The size of the vector.
+/// * `indices` - A slice of indices where the entries are active (1).
+/// # Returns
+/// A sparse binary vector represented as `CsVec<i8>`.
+pub fn from_indices(dimension: usize, indices: &[usize]) -> Result<CsVec<i8>, OVSAError> {
+    if indices.is_empty() {
+        return Err(OVSAError::EmptyIndices);
+    }
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    let n_active = indices.len();
+    let data: Vec<i8> = vec![1i8; n_active];
+
+    Ok(CsVec::new_from_unsorted(dimension, indices.to_vec(), data).unwrap())
+}
+
+
+/// Builds a sparse binary vector from active indices that may be empty, as produced by e.g. XOR of identical vectors.
+pub(crate) fn from_indices_or_empty(dimension: usize, indices: Vec<usize>) -> CsVec<i8> {
+    let data: Vec<i8> = vec![1i8; indices.len()];
+    CsVec::new_from_unsorted(dimension, indices, data).unwrap()
+}
+
+
+/// Computes the Hamming distance between two sparse binary vectors.
+/// The Hamming distance is defined as the number of positions at which the corresponding entries are different.
+/// # Arguments
+/// * `vec1` - The first sparse binary vector.
+/// * `vec2` - The second sparse binary vector.
+/// # Returns
+/// The Hamming distance as a usize.
+pub fn hamming_distance(vec1: &CsVec<i8>, vec2: &CsVec<i8>) -> usize {
+    assert_eq!(vec1.dim(), vec2.dim(), "Vectors must be of the same dimension to compute Hamming distance.");
+
+    hamming_indices(vec1.indices(), vec2.indices())
+}
+
+
+/// Computes the Hamming distance between two binary vectors given by their sorted active indices, without allocating.
+/// Works on the indices of owned vectors and of borrowed views alike.
+pub(crate) fn hamming_indices(indices1: &[usize], indices2: &[usize]) -> usize {
+    let (mut i, mut j, mut overlap) = (0, 0, 0);
+    while i < indices1.len() && j < indices2.len() {
+        if indices1[i] < indices2[j] {
+            i += 1;
+        } else if indices2[j] < indices1[i] {
+            j += 1;
+        } else {
+            overlap += 1;
+            i += 1;
+            j += 1;
+        }
+    }
+
+    indices1.len() + indices2.len() - 2 * overlap
+}
+
+
+/// Computes the Hamming distance between two binary vectors given by their sorted active indices, giving up as soon as
+/// the distance is known to exceed the limit.
+pub(crate) fn hamming_indices_at_most(indices1: &[usize], indices2: &[usize], limit: usize) -> Option<usize> {
+    if indices1.len().abs_diff(indices2.len()) > limit {
+        return None;
+    }
+
+    let (mut i, mut j, mut distance) = (0, 0, 0);
+    while i < indices1.len() && j < indices2.len() {
+        if indices1[i] == indices2[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        if indices1[i] < indices2[j] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+        distance += 1;
+        if distance > limit {
+            return None;
+        }
+    }
+
+    distance += indices1.len() - i + indices2.len() - j;
+    (distance <= limit).then_some(distance)
+}
+
+
+/// The number of words compared between two checks of the limit in `hamming_at_most`.
+const EARLY_EXIT_WORDS: usize = 8;
+
+
+/// Computes the Hamming distance between two bit-packed binary vectors, stopping the popcount scan as soon as the
+/// distance exceeds the limit. Scanning a clearly dissimilar pair thus costs a fraction of a full comparison.
+/// # Arguments
+/// * `a` - The words of the first vector, 64 dimensions per word.
+/// * `b` - The words of the second vector.
+/// * `limit` - The largest distance of interest.
+/// # Returns
+/// The distance if it is at most `limit`, otherwise `None`.
+pub fn hamming_at_most(a: &[u64], b: &[u64], limit: usize) -> Option<usize> {
+    assert_eq!(a.len(), b.len(), "Vectors must be of the same dimension to compute Hamming distance.");
+
+    let mut distance = 0;
+    for (a, b) in a.chunks(EARLY_EXIT_WORDS).zip(b.chunks(EARLY_EXIT_WORDS)) {
+        distance += kernels::xor_popcount(a, b);
+        if distance > limit {
+            return None;
+        }
+    }
+
+    Some(distance)
+}
+
+
+/// Converts a minimum similarity into the largest Hamming distance that can reach it, rounding up so that no
+/// qualifying vector is skipped; callers check the exact similarity afterwards.
+pub(crate) fn distance_limit(minimum: f64, dimension: usize) -> usize {
+    if minimum.is_nan() || minimum <= 0.0 {
+        return dimension;
+    }
+
+    let limit = ((1f64 - minimum) * dimension as f64).ceil();
+    if limit <= 0.0 { 0 } else { (limit as usize).min(dimension) }
+}
+
+
+/// Computes the consensus sum of a slice of sparse binary vectors.
+/// The consensus sum is determined by taking the majority value at each index across all vectors: an index is active
+/// when more than half of the vectors are active there. Only an even number of vectors can tie, and ties are broken
+/// randomly. The counts are 64-bit, so any number of vectors can be bundled.
+/// # Arguments
+/// * `vectors` - A slice of sparse binary vectors represented as `CsVec<i8>`.
+/// # Returns
+/// A sparse binary vector representing the consensus sum.
+pub fn consensus_sum(vectors: &[CsVec<i8>]) -> Result<CsVec<i8>, OVSAError> {
+    with_global_rng(|rng| consensus_sum_with_rng(vectors, rng))
+}
+
+
+/// Computes the consensus sum of a slice of sparse binary vectors, breaking ties with the provided random number generator.
+/// # Arguments
+/// * `vectors` - A slice of sparse binary vectors represented as `CsVec<i8>`.
+/// * `rng` - The random number generator used to break ties.
+/// # Returns
+/// A sparse binary vector representing the consensus sum.
+pub fn consensus_sum_with_rng<R: Rng + ?Sized>(vectors: &[CsVec<i8>], rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+    if vectors.is_empty() {
+        return Err(OVSAError::EmptyVectorList);
+    }
+
+    let size: usize = vectors[0].dim();
+    let n_vectors: i64 = vectors.len() as i64;
+    if vectors.iter().any(|vec| vec.dim() != size) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    span!(DEBUG, "consensus_sum", n_vectors, dimension = size);
+
+    let counts = count_active(vectors);
+
+    let uniform = Uniform::new(0.0, 1.0).unwrap();
+
+    fn set_active<R: Rng + ?Sized>(value: i64, rng: &mut R, uniform: &Uniform<f64>) -> bool {
+        if value > 0 {
+            true
+        } else if value < 0 {
+            false
+        } else {
+            rng.sample(uniform) > 0.5
+        }
+    }
+
+    // iterate in index order so that the tie-breaking draws are reproducible for a seeded generator
+    let indices: Vec<usize> = counts.iter()
+        .filter_map(|&(index, value)| if set_active(2 * value - n_vectors, rng, &uniform) { Some(index) } else { None })
+        .collect();
+
+    Ok(from_indices_or_empty(size, indices))
+}
+
+
+/// Bundles sparse binary vectors with the given hardening.
+/// # Arguments
+/// * `vectors` - A slice of sparse binary vectors.
+/// * `hardening` - The hardening.
+/// # Returns
+/// A sparse binary vector representing the bundle.
+pub fn consensus_sum_with_hardening(vectors: &[CsVec<i8>], hardening: Hardening) -> Result<CsVec<i8>, OVSAError> {
+    with_global_rng(|rng| consensus_sum_with_hardening_and_rng(vectors, hardening, rng))
+}
+
+
+/// Bundles sparse binary vectors with the given hardening, drawing random decisions with the provided random number
+/// generator. `Hardening::Majority` is `consensus_sum_with_rng`; with `Hardening::Stochastic`, an entry is active with
+/// the probability that a random one of the vectors has it active.
+/// # Arguments
+/// * `vectors` - A slice of sparse binary vectors.
+/// * `hardening` - The hardening.
+/// * `rng` - The random number generator.
+/// # Returns
+/// A sparse binary vector representing the bundle.
+pub fn consensus_sum_with_hardening_and_rng<R: Rng + ?Sized>(vectors: &[CsVec<i8>], hardening: Hardening, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+    if hardening == Hardening::Majority {
+        return consensus_sum_with_rng(vectors, rng);
+    }
+    if vectors.is_empty() {
+        return Err(OVSAError::EmptyVectorList);
+    }
+
+    let size = vectors[0].dim();
+    if vectors.iter().any(|vec| vec.dim() != size) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    let n_vectors = vectors.len() as f64;
+    let indices: Vec<usize> = count_active(vectors).into_iter()
+        .filter_map(|(index, count)| rng.random_bool((count as f64 / n_vectors).min(1.0)).then_some(index))
+        .collect();
+    Ok(from_indices_or_empty(size, indices))
+}
+
+
+/// Computes the weighted consensus sum of sparse binary vectors: an entry is active when the vectors holding it carry
+/// more than half of the total weight.
+/// # Arguments
+/// * `vectors` - A slice of sparse binary vectors.
+/// * `weights` - The non-negative weight of every vector.
+/// # Returns
+/// A sparse binary vector representing the weighted consensus sum.
+pub fn weighted_consensus_sum(vectors: &[CsVec<i8>], weights: &[f64]) -> Result<CsVec<i8>, OVSAError> {
+    with_global_rng(|rng| weighted_consensus_sum_with_rng(vectors, weights, rng))
+}
+
+
+/// Computes the weighted consensus sum of sparse binary vectors, breaking exact ties with the provided random number
+/// generator.
+/// # Arguments
+/// * `vectors` - A slice of sparse binary vectors.
+/// * `weights` - The non-negative weight of every vector.
+/// * `rng` - The random number generator used to break ties.
+/// # Returns
+/// A sparse binary vector representing the weighted consensus sum.
+pub fn weighted_consensus_sum_with_rng<R: Rng + ?Sized>(vectors: &[CsVec<i8>], weights: &[f64], rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+    if vectors.is_empty() {
+        return Err(OVSAError::EmptyVectorList);
+    }
+    if weights.len() != vectors.len() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    if weights.iter().any(|weight| !(weight.is_finite() && *weight >= 0.0)) {
+        return Err(OVSAError::InvalidArgument("bundling weights must be finite and non-negative".to_string()));
+    }
+
+    let size: usize = vectors[0].dim();
+    if vectors.iter().any(|vec| vec.dim() != size) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    span!(DEBUG, "weighted_consensus_sum", n_vectors = vectors.len(), dimension = size);
+
+    let mut sums = vec![0f64; size];
+    for (vector, &weight) in vectors.iter().zip(weights) {
+        for &index in vector.indices() {
+            sums[index] += weight;
+        }
+    }
+
+    let half = weights.iter().sum::<f64>() / 2.0;
+    let mut indices = Vec::new();
+    for (index, &sum) in sums.iter().enumerate() {
+        if sum == 0.0 {
+            continue;
+        }
+        if sum > half || (sum == half && rng.random_bool(0.5)) {
+            indices.push(index);
+        }
+    }
+
+    Ok(from_indices_or_empty(size, indices))
+}
+
+
+/// Computes the consensus sum of sparse binary vectors with the given bundling strategy. With `BundleStrategy::Tree`,
+/// the result is a majority of majorities: every chunk breaks its ties with its own random stream, derived from a
+/// single draw of `rng`, so the parallel and serial reductions give identical results.
+/// # Arguments
+/// * `vectors` - A slice of sparse binary vectors.
+/// * `strategy` - The bundling strategy.
+/// # Returns
+/// A sparse binary vector representing the consensus sum.
+pub fn consensus_sum_with_strategy(vectors: &[CsVec<i8>], strategy: BundleStrategy) -> Result<CsVec<i8>, OVSAError> {
+    with_global_rng(|rng| consensus_sum_with_strategy_and_rng(vectors, strategy, rng))
+}
+
+
+/// Computes the consensus sum of sparse binary vectors with the given bundling strategy, breaking ties with the
+/// provided random number generator.
+/// # Arguments
+/// * `vectors` - A slice of sparse binary vectors.
+/// * `strategy` - The bundling strategy.
+/// * `rng` - The random number generator used to break ties.
+/// # Returns
+/// A sparse binary vector representing the consensus sum.
+pub fn consensus_sum_with_strategy_and_rng<R: Rng + ?Sized>(vectors: &[CsVec<i8>], strategy: BundleStrategy, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+    let fan_in = match strategy {
+        BundleStrategy::Flat => return consensus_sum_with_rng(vectors, rng),
+        BundleStrategy::Tree { fan_in } => fan_in,
+    };
+    if fan_in < 2 {
+        return Err(OVSAError::InvalidArgument(format!("a bundling tree needs a fan-in of at least 2, got {}", fan_in)));
+    }
+    if vectors.is_empty() {
+        return Err(OVSAError::EmptyVectorList);
+    }
+
+    let seed: u64 = rng.random();
+    let mut level = majority_level(vectors, fan_in, seed, 0)?;
+    let mut depth = 1;
+    while level.len() > 1 {
+        level = majority_level(&level, fan_in, seed, depth)?;
+        depth += 1;
+    }
+    Ok(level.pop().expect("a reduced level keeps at least one vector"))
+}
+
+
+/// Bundles every chunk of `fan_in` vectors of one tree level, chunk `i` of level `depth` drawing from its own stream.
+fn majority_level(vectors: &[CsVec<i8>], fan_in: usize, seed: u64, depth: u64) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    let reduce = |(chunk, vectors): (usize, &[CsVec<i8>])| consensus_sum_with_rng(vectors, &mut stream_rng(seed, (depth << 32) | chunk as u64));
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        crate::config::install(|| vectors.par_chunks(fan_in).enumerate().map(reduce).collect())
+    }
+    #[cfg(not(feature = "parallel"))]
+    vectors.chunks(fan_in).enumerate().map(reduce).collect()
+}
+
+
+/// Accumulates the binding (XOR) of two sparse binary vectors into per-index counters without materializing the bound vector.
+/// Used to bundle many bound pairs in a single pass, see `majority` to turn the counters into a vector.
+/// # Arguments
+/// * `counts` - The counters, one per dimension.
+/// * `vec1` - The first sparse binary vector.
+/// * `vec2` - The second sparse binary vector.
+pub fn bind_accumulate(counts: &mut [u32], vec1: &CsVec<i8>, vec2: &CsVec<i8>) -> Result<(), OVSAError> {
+    if vec1.dim() != vec2.dim() || counts.len() != vec1.dim() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    // both index lists are sorted, so a merge walk finds the indices active in exactly one of them
+    let (indices1, indices2) = (vec1.indices(), vec2.indices());
+    let (mut i, mut j) = (0, 0);
+    while i < indices1.len() || j < indices2.len() {
+        let index1 = indices1.get(i).copied().unwrap_or(usize::MAX);
+        let index2 = indices2.get(j).copied().unwrap_or(usize::MAX);
+        if index1 < index2 {
+            counts[index1] += 1;
+            i += 1;
+        } else if index2 < index1 {
+            counts[index2] += 1;
+            j += 1;
+        } else {
+            i += 1;
+            j += 1;
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Adds a sparse binary vector to per-index counters, e.g. to build a bundle that can later be edited.
+/// # Arguments
+/// * `counts` - The counters, one per dimension.
+/// * `element` - The sparse binary vector to add.
+pub fn accumulate(counts: &mut [u32], element: &CsVec<i8>) -> Result<(), OVSAError> {
+    if counts.len() != element.dim() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    for &index in element.indices() {
+        counts[index] += 1;
+    }
+
+    Ok(())
+}
+
+
+/// Removes a previously accumulated sparse binary vector from per-index counters, so that an element can be taken out
+/// of a bundle without re-bundling the remaining elements. Call `majority` with one vector less to get the updated bundle.
+/// # Arguments
+/// * `counts` - The counters, one per dimension.
+/// * `element` - The sparse binary vector to remove.
+/// # Returns
+/// `CounterUnderflow` without changing the counters if an active entry of the element has a zero counter,
+/// i.e. the element was never accumulated.
+pub fn bundle_remove(counts: &mut [u32], element: &CsVec<i8>) -> Result<(), OVSAError> {
+    if counts.len() != element.dim() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    if element.indices().iter().any(|&index| counts[index] == 0) {
+        return Err(OVSAError::CounterUnderflow);
+    }
+
+    for &index in element.indices() {
+        counts[index] -= 1;
+    }
+
+    Ok(())
+}
+
+
+/// Binds every pair of vectors and bundles the results in a single pass, without allocating the bound vectors.
+/// Equivalent to the consensus sum of the XOR of every pair.
+/// # Arguments
+/// * `pairs` - The pairs of sparse binary vectors to bind, e.g. (role, filler) fields of a record.
+/// # Returns
+/// A sparse binary vector representing the bundle of the bound pairs.
+pub fn bind_bundle(pairs: &[(&CsVec<i8>, &CsVec<i8>)]) -> Result<CsVec<i8>, OVSAError> {
+    with_global_rng(|rng| bind_bundle_with_rng(pairs, rng))
+}
+
+
+/// Binds and bundles pairs of vectors in a single pass, breaking ties with the provided random number generator.
+/// # Arguments
+/// * `pairs` - The pairs of sparse binary vectors to bind.
+/// * `rng` - The random number generator used to break ties.
+/// # Returns
+/// A sparse binary vector representing the bundle of the bound pairs.
+pub fn bind_bundle_with_rng<R: Rng + ?Sized>(pairs: &[(&CsVec<i8>, &CsVec<i8>)], rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+    let (first, _) = pairs.first().ok_or(OVSAError::EmptyVectorList)?;
+    span!(DEBUG, "bind_bundle", n_pairs = pairs.len(), dimension = first.dim());
+
+    let mut counts = vec![0u32; first.dim()];
+    for (vec1, vec2) in pairs {
+        bind_accumulate(&mut counts, vec1, vec2)?;
+    }
+
+    Ok(majority_with_rng(&counts, pairs.len(), rng))
+}
+
+
+/// Turns per-index counters of `n` bundled vectors into their majority vector, breaking exact ties randomly.
+/// # Arguments
+/// * `counts` - The counters, one per dimension.
+/// * `n` - The number of vectors accumulated into the counters.
+/// # Returns
+/// A sparse binary vector with the indices active in more than half of the vectors.
+pub fn majority(counts: &[u32], n: usize) -> CsVec<i8> {
+    with_global_rng(|rng| majority_with_rng(counts, n, rng))
+}
+
+
+/// Turns per-index counters into their majority vector, breaking ties with the provided random number generator.
+/// # Arguments
+/// * `counts` - The counters, one per dimension.
+/// * `n` - The number of vectors accumulated into the counters.
+/// * `rng` - The random number generator used to break ties.
+/// # Returns
+/// A sparse binary vector with the indices active in more than half of the vectors.
+pub fn majority_with_rng<R: Rng + ?Sized>(counts: &[u32], n: usize, rng: &mut R) -> CsVec<i8> {
+    let mut indices: Vec<usize> = Vec::new();
+    for (index, &count) in counts.iter().enumerate() {
+        let doubled = 2 * count as usize;
+        if doubled > n || (doubled == n && rng.random_bool(0.5)) {
+            indices.push(index);
+        }
+    }
+
+    from_indices_or_empty(counts.len(), indices)
+}
+
+
+/// Turns per-index counters into a binary vector with the given hardening.
+/// # Arguments
+/// * `counts` - The counters, one per dimension.
+/// * `n` - The number of vectors accumulated into the counters.
+/// * `hardening` - The hardening.
+/// # Returns
+/// A sparse binary vector.
+pub fn harden(counts: &[u32], n: usize, hardening: Hardening) -> CsVec<i8> {
+    with_global_rng(|rng| harden_with_rng(counts, n, hardening, rng))
+}
+
+
+/// Turns per-index counters into a binary vector with the given hardening, drawing random decisions with the provided
+/// random number generator. `Hardening::Stochastic` makes an entry active with probability `count / n`.
+/// # Arguments
+/// * `counts` - The counters, one per dimension.
+/// * `n` - The number of vectors accumulated into the counters.
+/// * `hardening` - The hardening.
+/// * `rng` - The random number generator.
+/// # Returns
+/// A sparse binary vector.
+pub fn harden_with_rng<R: Rng + ?Sized>(counts: &[u32], n: usize, hardening: Hardening, rng: &mut R) -> CsVec<i8> {
+    match hardening {
+        Hardening::Majority => majority_with_rng(counts, n, rng),
+        Hardening::Stochastic => {
+            let mut indices: Vec<usize> = Vec::new();
+            for (index, &count) in counts.iter().enumerate() {
+                let probability = if n == 0 { 0.5 } else { (count as f64 / n as f64).min(1.0) };
+                if rng.random_bool(probability) {
+                    indices.push(index);
+                }
+            }
+            from_indices_or_empty(counts.len(), indices)
+        }
+    }
+}
+
+
+/// The dimension from which `consensus_sum` counts active entries, and `dense::superposition` sums, in parallel when
+/// the `parallel` feature is enabled. Both split the work into fixed chunks of dimensions, so their results are
+/// bit-identical to the serial path.
+pub const PARALLEL_DIMENSION_THRESHOLD: usize = 100_000;
+
+
+/// The number of dimensions counted by one parallel task.
+#[cfg(feature = "parallel")]
+const COUNT_CHUNK_SIZE: usize = 16_384;
+
+
+/// Counts how many vectors have each index active.
+/// # Returns
+/// The (index, count) pairs of all indices active in at least one vector, sorted by index.
+fn count_active(vectors: &[CsVec<i8>]) -> Vec<(usize, i64)> {
+    #[cfg(feature = "parallel")]
+    if vectors[0].dim() >= PARALLEL_DIMENSION_THRESHOLD {
+        use rayon::prelude::*;
+
+        let size = vectors[0].dim();
+        let n_chunks = size.div_ceil(COUNT_CHUNK_SIZE);
+        return crate::config::install(|| {
+            (0..n_chunks).into_par_iter()
+                .flat_map_iter(|chunk| count_active_range(vectors, chunk * COUNT_CHUNK_SIZE, ((chunk + 1) * COUNT_CHUNK_SIZE).min(size)))
+                .collect()
+        });
+    }
+
+    let mut counts: HashMap<usize, i64> = HashMap::new();
+    for vec in vectors {
+        for index in vec.indices() {
+            *counts.entry(*index).or_insert(0) += 1;
+        }
+    }
+
+    let mut counts: Vec<(usize, i64)> = counts.into_iter().collect();
+    counts.sort_unstable();
+    counts
+}
+
+
+/// Counts active entries within the index range `[start, end)`, relying on the sorted indices of `CsVec`.
+#[cfg(feature = "parallel")]
+fn count_active_range(vectors: &[CsVec<i8>], start: usize, end: usize) -> Vec<(usize, i64)> {
+    let mut counts = vec![0i64; end - start];
+    for vec in vectors {
+        let indices = vec.indices();
+        let first = indices.partition_point(|&index| index < start);
+        let last = indices.partition_point(|&index| index < end);
+        for &index in &indices[first..last] {
+            counts[index - start] += 1;
+        }
+    }
+
+    counts.into_iter().enumerate()
+        .filter(|&(_, count)| count > 0)
+        .map(|(offset, count)| (start + offset, count))
+        .collect()
+}
+
+
+/// Computes the element-wise XOR of two sparse binary vectors.
+/// # Arguments
+/// * `vec1` - The first sparse binary vector.
+/// * `vec2` - The second sparse binary vector.
+/// # Returns
+/// A sparse binary vector representing the XOR result.
+pub fn xor(vec1: &CsVec<i8>, vec2: &CsVec<i8>) -> Result<CsVec<i8>, OVSAError> {
+    if vec1.dim() != vec2.dim() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    let size: usize = vec1.dim();
+    // to simulate an XOR operation, we add the two vectors and keep only the entries where the sum is 1
+    let result: CsVec<i8> = vec1 + vec2;
+    let indices: Vec<usize> = result.iter()
+        // XOR operation: 1 + 1 = 0, so we keep only entries with value 1
+        .filter_map(|(index, &value)| if value == 1 { Some(index) } else { None })
+        .collect();
+
+    Ok(from_indices_or_empty(size, indices))
+}
+
+
+/// Binds two sparse binary vectors partially, interpolating between the identity (`alpha = 0`) and the full XOR binding
+/// (`alpha = 1`), e.g. for soft role assignment. Only the fraction `alpha` of the active entries of `vec2` is applied,
+/// selected by a fixed hash of their indices: the same `alpha` always selects the same entries, so a graded binding is
+/// undone by repeating it, and a larger `alpha` selects a superset of the entries of a smaller one.
+/// # Arguments
+/// * `vec1` - The vector to bind.
+/// * `vec2` - The key vector.
+/// * `alpha` - The binding strength in `[0, 1]`.
+/// # Returns
+/// A sparse binary vector whose expected similarity to `vec1` falls linearly with `alpha`.
+pub fn bind_graded(vec1: &CsVec<i8>, vec2: &CsVec<i8>, alpha: f64) -> Result<CsVec<i8>, OVSAError> {
+    if !(0.0..=1.0).contains(&alpha) {
+        return Err(OVSAError::InvalidArgument(format!("binding strength must be in [0, 1], got {}", alpha)));
+    }
+
+    // the top 53 bits of the mixed index as a uniform value in [0, 1)
+    let selected: Vec<usize> = vec2.indices().iter().copied()
+        .filter(|&index| ((splitmix64(index as u64) >> 11) as f64 / (1u64 << 53) as f64) < alpha)
+        .collect();
+    xor(vec1, &from_indices_or_empty(vec2.dim(), selected))
+}
+
+
+/// Performs a cyclic shift on a sparse binary vector.
+/// Typically used for implementing permutation operations, or binding/unbinding via shifting (e.g. right/left, respectively).
+/// # Arguments
+/// * `vec` - The sparse binary vector to be shifted.
+/// * `shift_by` - The number of positions to shift. Positive values shift to the right, negative values shift to the left.
+/// # Returns
+/// A new sparse binary vector that has been cyclically shifted.
+pub fn cyclic_shift(vec: &CsVec<i8>, shift_by: isize) -> CsVec<i8> {
+    let size = vec.dim();
+    if size == 0 {
+        return vec.clone();
+    }
+    let shift = shift_by.rem_euclid(size as isize) as usize;
+
+    // the sorted indices are rotated, not re-sorted: those that wrap around come first
+    let indices = vec.indices();
+    let split = indices.partition_point(|&index| index < size - shift);
+    let mut new_indices: Vec<usize> = Vec::with_capacity(indices.len());
+    new_indices.extend(indices[split..].iter().map(|&index| index + shift - size));
+    new_indices.extend(indices[..split].iter().map(|&index| index + shift));
+
+    CsVec::new(size, new_indices, vec![1i8; indices.len()])
+}
+
+
+/// Performs a cyclic shift on a sparse binary vector viewed as a row-major grid, e.g. for toroidal position codes of
+/// spatial encoders. Rows wrap around vertically and columns horizontally, independently of each other.
+/// # Arguments
+/// * `vec` - The sparse binary vector to be shifted.
+/// * `rows` - The number of rows of the grid.
+/// * `cols` - The number of columns of the grid, with `rows * cols` equal to the dimension.
+/// * `dy` - The number of rows to shift down, negative values shifting up.
+/// * `dx` - The number of columns to shift right, negative values shifting left.
+/// # Returns
+/// A new sparse binary vector that has been cyclically shifted along both axes.
+pub fn cyclic_shift_2d(vec: &CsVec<i8>, rows: usize, cols: usize, dy: isize, dx: isize) -> Result<CsVec<i8>, OVSAError> {
+    if rows.checked_mul(cols) != Some(vec.dim()) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    let new_indices = vec.indices().iter()
+        .map(|&index| grid_shift(index, rows, cols, dy, dx))
+        .collect();
+    Ok(from_indices_or_empty(vec.dim(), new_indices))
+}
+
+
+/// Returns the index of the grid cell `index` moves to under a 2D cyclic shift.
+pub(crate) fn grid_shift(index: usize, rows: usize, cols: usize, dy: isize, dx: isize) -> usize {
+    let row = (((index / cols) as isize + dy).rem_euclid(rows as isize)) as usize;
+    let col = (((index % cols) as isize + dx).rem_euclid(cols as isize)) as usize;
+    row * cols + col
+}
+
+
+/// Maps a sparse binary vector to another dimension, e.g. to shrink a trained model for deployment. Every new dimension
+/// copies one original dimension chosen by the seed: when shrinking, a random subset of the original dimensions, and
+/// when growing, all original dimensions followed by random repeats. The fraction of differing entries, and thus the
+/// similarity of two vectors reprojected with the same seed, is preserved in expectation.
+/// # Arguments
+/// * `vec` - The vector to reproject.
+/// * `new_dimension` - The dimension of the result.
+/// * `seed` - The seed of the selection; vectors are only comparable if reprojected with the same seed.
+/// # Returns
+/// The reprojected vector.
+pub fn reproject(vec: &CsVec<i8>, new_dimension: usize, seed: u64) -> Result<CsVec<i8>, OVSAError> {
+    Ok(reproject_with(vec, &reprojection(vec.dim(), new_dimension, seed)?))
+}
+
+
+/// Reprojects a vector with the selection of `reprojection`, e.g. to reuse one selection for many vectors.
+pub(crate) fn reproject_with(vec: &CsVec<i8>, selection: &[usize]) -> CsVec<i8> {
+    let indices = selection.iter().enumerate()
+        .filter(|&(_, &source)| vec.get(source).is_some())
+        .map(|(index, _)| index)
+        .collect();
+    from_indices_or_empty(selection.len(), indices)
+}
+
+
+/// Returns the original dimension every new dimension of `reproject` copies.
+pub(crate) fn reprojection(dimension: usize, new_dimension: usize, seed: u64) -> Result<Vec<usize>, OVSAError> {
+    if dimension == 0 || new_dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    let mut rng = OvsaRng::seed_from_u64(seed);
+    if new_dimension <= dimension {
+        let mut selection = sample(&mut rng, dimension, new_dimension).into_vec();
+        selection.sort_unstable();
+        Ok(selection)
+    } else {
+        Ok((0..dimension).chain((dimension..new_dimension).map(|_| rng.random_range(0..dimension))).collect())
+    }
+}
+
+
+/// Computes the similarity between two sparse binary vectors.
+/// Similarity is defined as 1 - (Hamming distance / dimension).
+/// # Arguments
+/// * `vec1` - The first sparse binary vector.
+/// * `vec2` - The second sparse binary vector.
+/// # Returns
+/// The similarity as a f64 value between 0.0 and 1.0
+pub fn similarity(vec1: &CsVec<i8>, vec2: &CsVec<i8>) -> Result<f64, OVSAError> {
+    if vec1.dim() != vec2.dim() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    let sim = hamming_distance(vec1, vec2) as f64 / vec1.dim() as f64;
+
+    Ok(1f64 - sim)
+}
+
+
+/// Computes the similarity between two sparse binary vectors with per-dimension weights: 1 minus the weight of the
+/// differing dimensions over the total weight. Uniform weights give `similarity`.
+/// # Arguments
+/// * `vec1` - The first sparse binary vector.
+/// * `vec2` - The second sparse binary vector.
+/// * `weights` - One finite, non-negative weight per dimension, e.g. from `mask::segment_weights`.
+/// # Returns
+/// The weighted similarity, between 0.0 and 1.0.
+pub fn weighted_similarity(vec1: &CsVec<i8>, vec2: &CsVec<i8>, weights: &[f64]) -> Result<f64, OVSAError> {
+    if vec1.dim() != vec2.dim() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    let total = total_weight(weights, vec1.dim())?;
+
+    let (indices1, indices2) = (vec1.indices(), vec2.indices());
+    let (mut i, mut j, mut differing) = (0, 0, 0.0);
+    while i < indices1.len() && j < indices2.len() {
+        match indices1[i].cmp(&indices2[j]) {
+            std::cmp::Ordering::Less => {
+                differing += weights[indices1[i]];
+                i += 1;
+            }
+            std::cmp::Ordering::Greater => {
+                differing += weights[indices2[j]];
+                j += 1;
+            }
+            std::cmp::Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    differing += indices1[i..].iter().chain(&indices2[j..]).map(|&index| weights[index]).sum::<f64>();
+
+    Ok(1f64 - differing / total)
+}
+
+
+/// Computes the similarity between two sparse binary vectors over a range of dimensions only, e.g. one field of a
+/// structured vector, without slicing them: 1 minus the Hamming distance within the range over its length. The active
+/// entries in the range are found by binary search, so the cost grows with the active entries inside the range.
+/// # Arguments
+/// * `vec1` - The first sparse binary vector.
+/// * `vec2` - The second sparse binary vector.
+/// * `range` - The non-empty range of dimensions to compare.
+/// # Returns
+/// The similarity within the range, between 0.0 and 1.0.
+pub fn similarity_range(vec1: &CsVec<i8>, vec2: &CsVec<i8>, range: Range<usize>) -> Result<f64, OVSAError> {
+    if vec1.dim() != vec2.dim() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    check_range(vec1.dim(), &range)?;
+
+    let window = |indices: &[usize]| {
+        let start = indices.partition_point(|&index| index < range.start);
+        let end = indices.partition_point(|&index| index < range.end);
+        (start, end)
+    };
+    let ((start1, end1), (start2, end2)) = (window(vec1.indices()), window(vec2.indices()));
+    let distance = hamming_indices(&vec1.indices()[start1..end1], &vec2.indices()[start2..end2]);
+    Ok(1f64 - distance as f64 / range.len() as f64)
+}
+
+
+/// Finds the cyclic shift that maps a vector closest to a target, e.g. to decode a sequence position or estimate a
+/// temporal alignment. All shifts are scored at once by counting, for every pair of active entries, the shift that
+/// aligns them, in O(nnz(vec) * nnz(target) + dimension).
+/// # Arguments
+/// * `vec` - The vector to shift.
+/// * `target` - The vector to align to.
+/// # Returns
+/// The best shift in `[0, dimension)`, the smallest one on ties, and the similarity of `cyclic_shift(vec, shift)` to the
+/// target.
+pub fn best_shift(vec: &CsVec<i8>, target: &CsVec<i8>) -> Result<(isize, f64), OVSAError> {
+    if vec.dim() != target.dim() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    let dimension = vec.dim();
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    let mut overlaps = vec![0usize; dimension];
+    for &i in vec.indices() {
+        for &j in target.indices() {
+            overlaps[(j + dimension - i) % dimension] += 1;
+        }
+    }
+
+    let (shift, overlap) = overlaps.iter().enumerate()
+        .fold((0, 0), |best, (shift, &overlap)| if overlap > best.1 { (shift, overlap) } else { best });
+    let distance = vec.nnz() + target.nnz() - 2 * overlap;
+    Ok((shift as isize, 1f64 - distance as f64 / dimension as f64))
+}
+
+
+/// Computes the similarity between two sparse binary vectors if it reaches a minimum, giving up early otherwise.
+/// # Arguments
+/// * `vec1` - The first sparse binary vector.
+/// * `vec2` - The second sparse binary vector.
+/// * `minimum` - The smallest similarity of interest.
+/// # Returns
+/// The similarity if it is at least `minimum`, otherwise `None`.
+pub fn similarity_at_least(vec1: &CsVec<i8>, vec2: &CsVec<i8>, minimum: f64) -> Result<Option<f64>, OVSAError> {
+    if vec1.dim() != vec2.dim() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    let distance = hamming_indices_at_most(vec1.indices(), vec2.indices(), distance_limit(minimum, vec1.dim()));
+    Ok(distance.map(|distance| 1f64 - distance as f64 / vec1.dim() as f64).filter(|&similarity| similarity >= minimum))
+}
+
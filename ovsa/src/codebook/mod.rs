@@ -0,0 +1,505 @@
+#[cfg(feature = "dense")]
+use ndarray::Array1;
+use rand::Rng;
+use sprs::CsVec;
+
+use std::f64::consts::PI;
+
+use crate::binary::{bind_bundle, cyclic_shift, from_indices_or_empty, hamming_distance, majority_with_rng, sparse_random_with_rng, xor};
+#[cfg(feature = "dense")]
+use crate::dense::{dot, norm, random_uniform_with_rng};
+use crate::encode::LevelEncoder;
+use crate::errors::OVSAError;
+use crate::hypervector::Hypervector;
+use crate::memory::MemoryUsage;
+use crate::rng::{standard_normal, with_global_rng};
+use crate::trace::span;
+
+/// The number of candidates drawn for each vector before a generator gives up.
+pub const MAX_ATTEMPTS: usize = 1000;
+
+
+/// Generates sparse binary vectors whose pairwise similarities are all at most `max_similarity`.
+/// Candidates are drawn at random and rejected while they are too similar to an accepted vector,
+/// which gives guarantees that plain random generation only offers with high probability at large dimensions.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `n_active` - The number of active entries of every vector.
+/// * `n_vectors` - The number of vectors to generate.
+/// * `max_similarity` - The largest allowed similarity between two vectors.
+/// # Returns
+/// The generated vectors, or `GenerationFailed` if a vector could not be found within `MAX_ATTEMPTS` draws.
+pub fn orthogonal_binary(dimension: usize, n_active: usize, n_vectors: usize, max_similarity: f64) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    with_global_rng(|rng| orthogonal_binary_with_rng(dimension, n_active, n_vectors, max_similarity, rng))
+}
+
+
+/// Generates sparse binary vectors with bounded pairwise similarity using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `n_active` - The number of active entries of every vector.
+/// * `n_vectors` - The number of vectors to generate.
+/// * `max_similarity` - The largest allowed similarity between two vectors.
+/// * `rng` - The random number generator.
+/// # Returns
+/// The generated vectors, or `GenerationFailed` if a vector could not be found within `MAX_ATTEMPTS` draws.
+pub fn orthogonal_binary_with_rng<R: Rng + ?Sized>(dimension: usize, n_active: usize, n_vectors: usize, max_similarity: f64, rng: &mut R) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    // similarity is 1 - hamming / dimension, so the bound is a minimum distance
+    let min_distance = (1f64 - max_similarity) * dimension as f64;
+    let mut accepted: Vec<CsVec<i8>> = Vec::with_capacity(n_vectors);
+
+    while accepted.len() < n_vectors {
+        let mut found = false;
+        for _ in 0..MAX_ATTEMPTS {
+            let candidate = sparse_random_with_rng(dimension, n_active, rng)?;
+            if accepted.iter().all(|vector| hamming_distance(&candidate, vector) as f64 >= min_distance) {
+                accepted.push(candidate);
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            return Err(OVSAError::GenerationFailed);
+        }
+    }
+
+    Ok(accepted)
+}
+
+
+/// Generates bipolar (+1/-1) dense vectors whose pairwise cosine similarities are all at most `max_similarity`.
+/// Every candidate is orthogonalised against the previously accepted vectors (Gram–Schmidt) and then quantized to its signs;
+/// quantization loses a little orthogonality, so candidates that exceed the bound are redrawn.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `n_vectors` - The number of vectors to generate, at most `dimension`.
+/// * `max_similarity` - The largest allowed cosine similarity between two vectors.
+/// # Returns
+/// The generated vectors, or `GenerationFailed` if a vector could not be found within `MAX_ATTEMPTS` draws.
+#[cfg(feature = "dense")]
+pub fn orthogonal_dense(dimension: usize, n_vectors: usize, max_similarity: f32) -> Result<Vec<Array1<f32>>, OVSAError> {
+    with_global_rng(|rng| orthogonal_dense_with_rng(dimension, n_vectors, max_similarity, rng))
+}
+
+
+/// Generates bipolar dense vectors with bounded pairwise similarity using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `n_vectors` - The number of vectors to generate, at most `dimension`.
+/// * `max_similarity` - The largest allowed cosine similarity between two vectors.
+/// * `rng` - The random number generator.
+/// # Returns
+/// The generated vectors, or `GenerationFailed` if a vector could not be found within `MAX_ATTEMPTS` draws.
+#[cfg(feature = "dense")]
+pub fn orthogonal_dense_with_rng<R: Rng + ?Sized>(dimension: usize, n_vectors: usize, max_similarity: f32, rng: &mut R) -> Result<Vec<Array1<f32>>, OVSAError> {
+    if n_vectors > dimension {
+        return Err(OVSAError::GenerationFailed);
+    }
+
+    // the orthonormal basis spanned so far, kept unquantized so that later candidates are orthogonalised exactly
+    let mut basis: Vec<Array1<f32>> = Vec::with_capacity(n_vectors);
+    let mut accepted: Vec<Array1<f32>> = Vec::with_capacity(n_vectors);
+
+    while accepted.len() < n_vectors {
+        let mut found = false;
+        for _ in 0..MAX_ATTEMPTS {
+            let mut candidate = random_uniform_with_rng(dimension, -1.0, 1.0, rng)?;
+            for direction in &basis {
+                let projection = dot(candidate.view(), direction.view());
+                candidate.scaled_add(-projection, direction);
+            }
+            let norm = norm(candidate.view());
+            if norm <= f32::EPSILON {
+                continue;
+            }
+
+            let quantized = candidate.mapv(|value| if value >= 0.0 { 1.0 } else { -1.0 });
+            // bipolar vectors all have norm sqrt(dimension), so their cosine is the dot product over the dimension
+            if accepted.iter().all(|vector| dot(quantized.view(), vector.view()) / dimension as f32 <= max_similarity) {
+                basis.push(candidate / norm);
+                accepted.push(quantized);
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            return Err(OVSAError::GenerationFailed);
+        }
+    }
+
+    Ok(accepted)
+}
+
+
+/// Generates dense vectors whose pairwise cosine similarities approximate a target matrix.
+/// The vectors are Gaussian with the target as their correlation matrix, so the error shrinks with the dimension.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `targets` - A symmetric, positive semi-definite matrix with ones on its diagonal.
+/// # Returns
+/// One vector per row of the target matrix, or `GenerationFailed` if the target cannot be a correlation matrix.
+#[cfg(feature = "dense")]
+pub fn correlated_dense(dimension: usize, targets: &[Vec<f64>]) -> Result<Vec<Array1<f32>>, OVSAError> {
+    with_global_rng(|rng| correlated_dense_with_rng(dimension, targets, rng))
+}
+
+
+/// Generates dense vectors with target pairwise similarities using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `targets` - A symmetric, positive semi-definite matrix with ones on its diagonal.
+/// * `rng` - The random number generator.
+/// # Returns
+/// One vector per row of the target matrix, or `GenerationFailed` if the target cannot be a correlation matrix.
+#[cfg(feature = "dense")]
+pub fn correlated_dense_with_rng<R: Rng + ?Sized>(dimension: usize, targets: &[Vec<f64>], rng: &mut R) -> Result<Vec<Array1<f32>>, OVSAError> {
+    let latent = latent_with_rng(dimension, targets, rng)?;
+
+    Ok(latent.into_iter().map(|values| values.into_iter().map(|value| value as f32).collect()).collect())
+}
+
+
+/// Generates half-dense binary vectors (`dimension / 2` active entries) whose pairwise similarities approximate a target matrix.
+/// The similarities use the measure of `binary::similarity`, where independent half-dense vectors are at about 0.5.
+/// Each vector activates the largest entries of a correlated Gaussian vector; two Gaussians with correlation `r`
+/// agree in sign with probability `1 - acos(r) / pi`, which is inverted to find the latent correlations.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `targets` - A symmetric matrix of similarities with ones on its diagonal.
+/// # Returns
+/// One vector per row of the target matrix, or `GenerationFailed` if no correlation matrix produces the targets.
+pub fn correlated_binary(dimension: usize, targets: &[Vec<f64>]) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    with_global_rng(|rng| correlated_binary_with_rng(dimension, targets, rng))
+}
+
+
+/// Generates half-dense binary vectors with target pairwise similarities using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `targets` - A symmetric matrix of similarities with ones on its diagonal.
+/// * `rng` - The random number generator.
+/// # Returns
+/// One vector per row of the target matrix, or `GenerationFailed` if no correlation matrix produces the targets.
+pub fn correlated_binary_with_rng<R: Rng + ?Sized>(dimension: usize, targets: &[Vec<f64>], rng: &mut R) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    let correlations: Vec<Vec<f64>> = targets.iter()
+        .map(|row| row.iter().map(|&similarity| latent_correlation(similarity)).collect())
+        .collect();
+
+    binarize(dimension, latent_with_rng(dimension, &correlations, rng)?)
+}
+
+
+/// Generates half-dense binary vectors for ordinal categories, where similarity decays smoothly with the distance in rank.
+/// Neighbouring vectors have about `neighbour_similarity`, and vectors further apart approach the 0.5 of independent vectors.
+/// Unlike `encode::levels`, no two vectors become independent at a fixed distance, and any number of vectors can be generated.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `n_vectors` - The number of vectors.
+/// * `neighbour_similarity` - The similarity of consecutive vectors, between 0.5 and 1.
+/// # Returns
+/// The vectors ordered by rank.
+pub fn ordinal_binary(dimension: usize, n_vectors: usize, neighbour_similarity: f64) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    with_global_rng(|rng| ordinal_binary_with_rng(dimension, n_vectors, neighbour_similarity, rng))
+}
+
+
+/// Generates ordinal half-dense binary vectors using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `n_vectors` - The number of vectors.
+/// * `neighbour_similarity` - The similarity of consecutive vectors, between 0.5 and 1.
+/// * `rng` - The random number generator.
+/// # Returns
+/// The vectors ordered by rank.
+pub fn ordinal_binary_with_rng<R: Rng + ?Sized>(dimension: usize, n_vectors: usize, neighbour_similarity: f64, rng: &mut R) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    if !(0.5..=1.0).contains(&neighbour_similarity) {
+        return Err(OVSAError::GenerationFailed);
+    }
+
+    // powers of a correlation in [0, 1] form a valid correlation matrix (a first-order autoregressive process)
+    let correlation = latent_correlation(neighbour_similarity);
+    let correlations: Vec<Vec<f64>> = (0..n_vectors)
+        .map(|i| (0..n_vectors).map(|j| correlation.powi(i.abs_diff(j) as i32)).collect())
+        .collect();
+
+    binarize(dimension, latent_with_rng(dimension, &correlations, rng)?)
+}
+
+
+/// A cached table of position vectors, the successive permutation (cyclic shift) powers of a base vector.
+/// Sequence and spatial encoders bind items to positions; the table computes every power once instead of
+/// shifting the base again for every item.
+#[derive(Debug, Clone)]
+pub struct Positions {
+    vectors: Vec<CsVec<i8>>,
+}
+
+
+/// Computes the first `n` permutation powers of a base vector: position `i` is the base shifted by `i`.
+/// # Arguments
+/// * `base` - The sparse binary vector of position 0.
+/// * `n` - The number of positions.
+/// # Returns
+/// The `Positions` table.
+pub fn positions(base: &CsVec<i8>, n: usize) -> Positions {
+    Positions { vectors: (0..n).map(|position| cyclic_shift(base, position as isize)).collect() }
+}
+
+
+impl Positions {
+    /// Returns the number of positions.
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Returns true if the table holds no positions.
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Returns the vector of a position, if it is in the table.
+    /// # Arguments
+    /// * `position` - The position index.
+    pub fn get(&self, position: usize) -> Option<&CsVec<i8>> {
+        self.vectors.get(position)
+    }
+
+    /// Binds (XOR) a vector to a position.
+    /// # Arguments
+    /// * `position` - The position index.
+    /// * `vector` - The sparse binary vector to bind.
+    /// # Returns
+    /// The bound vector, or `InvalidArgument` if the position is not in the table.
+    pub fn bind(&self, position: usize, vector: &CsVec<i8>) -> Result<CsVec<i8>, OVSAError> {
+        let positional = self.get(position)
+            .ok_or_else(|| OVSAError::InvalidArgument(format!("position {} is not in a table of {} positions", position, self.len())))?;
+        xor(positional, vector)
+    }
+
+    /// Encodes a sequence by binding every item to its position and bundling the results in a single pass.
+    /// # Arguments
+    /// * `items` - The sparse binary vectors of the items, at most `len()` of them.
+    /// # Returns
+    /// A sparse binary vector representing the sequence, or `InvalidArgument` if there are more items than positions.
+    pub fn bind_bundle(&self, items: &[&CsVec<i8>]) -> Result<CsVec<i8>, OVSAError> {
+        if items.len() > self.vectors.len() {
+            let n_positions = self.vectors.len();
+            return Err(OVSAError::InvalidArgument(format!("position {} is not in a table of {} positions", n_positions, n_positions)));
+        }
+
+        let pairs: Vec<(&CsVec<i8>, &CsVec<i8>)> = self.vectors.iter().zip(items.iter().copied()).collect();
+        bind_bundle(&pairs)
+    }
+}
+
+
+/// A cached table of every binding (XOR) of a role to a filler, for records over small codebooks such as feature
+/// positions and value levels. Encoding a record then only adds up the cached bindings instead of binding every field
+/// again; the table holds `roles × fillers` vectors, so its size is capped by a memory budget.
+#[derive(Debug, Clone)]
+pub struct BindingTable {
+    n_fillers: usize,
+    dimension: usize,
+    /// The binding of role `r` and filler `f` at position `r * n_fillers + f`.
+    bindings: Vec<CsVec<i8>>,
+}
+
+
+impl BindingTable {
+    /// Binds every role to every filler.
+    /// # Arguments
+    /// * `roles` - The role vectors.
+    /// * `fillers` - The filler vectors.
+    /// * `max_bytes` - The largest number of bytes the bindings may take, as measured by `memory_usage`.
+    /// # Returns
+    /// The `BindingTable`, `EmptyVectorList` without roles or fillers, or `BudgetExceeded` as soon as the bindings
+    /// exceed the budget.
+    pub fn new(roles: &[CsVec<i8>], fillers: &[CsVec<i8>], max_bytes: usize) -> Result<Self, OVSAError> {
+        let dimension = roles.first().ok_or(OVSAError::EmptyVectorList)?.dim();
+        if fillers.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+        span!(DEBUG, "binding_table", n_roles = roles.len(), n_fillers = fillers.len(), dimension);
+
+        let mut bindings = Vec::with_capacity(roles.len() * fillers.len());
+        let mut bytes = 0;
+        for role in roles {
+            for filler in fillers {
+                let binding = xor(role, filler)?;
+                bytes += size_of::<CsVec<i8>>() + binding.heap_bytes();
+                if bytes > max_bytes {
+                    return Err(OVSAError::BudgetExceeded);
+                }
+                bindings.push(binding);
+            }
+        }
+
+        Ok(BindingTable { n_fillers: fillers.len(), dimension, bindings })
+    }
+
+    /// Returns the number of roles.
+    pub fn n_roles(&self) -> usize {
+        self.bindings.len() / self.n_fillers
+    }
+
+    /// Returns the number of fillers.
+    pub fn n_fillers(&self) -> usize {
+        self.n_fillers
+    }
+
+    /// Returns the binding of a role and a filler, if both are in the table.
+    /// # Arguments
+    /// * `role` - The role index.
+    /// * `filler` - The filler index.
+    pub fn get(&self, role: usize, filler: usize) -> Option<&CsVec<i8>> {
+        if filler >= self.n_fillers {
+            return None;
+        }
+        self.bindings.get(role * self.n_fillers + filler)
+    }
+
+    /// Estimates the bytes used by the cached bindings.
+    /// # Returns
+    /// The `MemoryUsage` breakdown, without labels.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let vector_bytes = self.bindings.iter().map(|binding| size_of::<CsVec<i8>>() + binding.heap_bytes()).sum();
+        MemoryUsage { n_vectors: self.bindings.len(), vector_bytes, label_bytes: 0, auxiliary_bytes: 0 }
+    }
+
+    /// Encodes a record like `encode::record` from the cached bindings of its fields.
+    /// # Arguments
+    /// * `fields` - The (role index, filler index) pairs of the record.
+    /// # Returns
+    /// A sparse binary vector representing the record, `EmptyVectorList` without fields, or `InvalidArgument` for a
+    /// field whose role or filler is outside the table.
+    pub fn record(&self, fields: &[(usize, usize)]) -> Result<CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.record_with_rng(fields, rng))
+    }
+
+    /// Encodes a record from the cached bindings, breaking ties with the provided random number generator. The result
+    /// is identical to `encode::record` of the same fields with an identically seeded generator.
+    /// # Arguments
+    /// * `fields` - The (role index, filler index) pairs of the record.
+    /// * `rng` - The random number generator used to break ties.
+    /// # Returns
+    /// A sparse binary vector representing the record, `EmptyVectorList` without fields, or `InvalidArgument` for a
+    /// field whose role or filler is outside the table.
+    pub fn record_with_rng<R: Rng + ?Sized>(&self, fields: &[(usize, usize)], rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        if fields.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let mut counts = vec![0u32; self.dimension];
+        for &(role, filler) in fields {
+            let binding = self.get(role, filler).ok_or_else(|| OVSAError::InvalidArgument(format!(
+                "the field (role {}, filler {}) is outside the table of {} roles and {} fillers", role, filler, self.n_roles(), self.n_fillers,
+            )))?;
+            for &index in binding.indices() {
+                counts[index] += 1;
+            }
+        }
+        Ok(majority_with_rng(&counts, fields.len(), rng))
+    }
+
+    /// Encodes a numeric feature vector like `encode::features`, with the roles of the table as the feature positions
+    /// and the levels of the encoder as the fillers.
+    /// # Arguments
+    /// * `values` - The feature values, at most `n_roles()` of them.
+    /// * `levels` - The level encoder whose levels the table was built from.
+    /// # Returns
+    /// A sparse binary vector representing the feature vector, or `InvalidArgument` if there are more values than roles
+    /// or more levels than fillers.
+    pub fn features(&self, values: &[f64], levels: &LevelEncoder) -> Result<CsVec<i8>, OVSAError> {
+        if values.len() > self.n_roles() {
+            return Err(OVSAError::InvalidArgument(format!("{} feature values do not fit a table of {} roles", values.len(), self.n_roles())));
+        }
+        let fields: Vec<(usize, usize)> = values.iter().enumerate().map(|(position, &value)| (position, levels.level_index(value))).collect();
+        self.record(&fields)
+    }
+}
+
+
+/// Returns the Gaussian correlation whose sign agreement probability is the given binary similarity.
+fn latent_correlation(similarity: f64) -> f64 {
+    (PI * (1f64 - similarity)).cos()
+}
+
+
+/// Activates the largest half of the entries of every latent vector.
+fn binarize(dimension: usize, latent: Vec<Vec<f64>>) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    let n_active = dimension / 2;
+    if n_active == 0 {
+        return Err(OVSAError::ZeroActiveElements);
+    }
+
+    Ok(latent.into_iter()
+        .map(|values| {
+            let mut order: Vec<usize> = (0..dimension).collect();
+            order.select_nth_unstable_by(n_active - 1, |&a, &b| values[b].total_cmp(&values[a]));
+            order.truncate(n_active);
+            from_indices_or_empty(dimension, order)
+        })
+        .collect())
+}
+
+
+/// Draws one Gaussian vector per row of the correlation matrix, correlated as the matrix prescribes.
+fn latent_with_rng<R: Rng + ?Sized>(dimension: usize, correlations: &[Vec<f64>], rng: &mut R) -> Result<Vec<Vec<f64>>, OVSAError> {
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+    if correlations.is_empty() {
+        return Err(OVSAError::EmptyVectorList);
+    }
+    let n = correlations.len();
+    if correlations.iter().any(|row| row.len() != n) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    let factor = cholesky(correlations)?;
+    let mut latent = vec![vec![0f64; dimension]; n];
+    let mut independent = vec![0f64; n];
+    for position in 0..dimension {
+        independent.iter_mut().for_each(|value| *value = standard_normal(rng));
+        for (vector, row) in latent.iter_mut().zip(&factor) {
+            vector[position] = row.iter().zip(&independent).map(|(a, b)| a * b).sum();
+        }
+    }
+
+    Ok(latent)
+}
+
+
+/// Computes the lower triangular Cholesky factor of a symmetric correlation matrix.
+/// Semi-definite matrices are accepted by clamping round-off on the diagonal to zero.
+fn cholesky(matrix: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, OVSAError> {
+    const TOLERANCE: f64 = 1e-9;
+    let n = matrix.len();
+    let mut factor = vec![vec![0f64; n]; n];
+
+    for i in 0..n {
+        if (matrix[i][i] - 1f64).abs() > TOLERANCE {
+            return Err(OVSAError::GenerationFailed);
+        }
+        for j in 0..=i {
+            if (matrix[i][j] - matrix[j][i]).abs() > TOLERANCE {
+                return Err(OVSAError::GenerationFailed);
+            }
+            let sum: f64 = (0..j).map(|k| factor[i][k] * factor[j][k]).sum();
+            if i == j {
+                let residual = matrix[i][i] - sum;
+                if residual < -TOLERANCE {
+                    return Err(OVSAError::GenerationFailed);
+                }
+                factor[i][i] = residual.max(0f64).sqrt();
+            } else if factor[j][j] > TOLERANCE {
+                factor[i][j] = (matrix[i][j] - sum) / factor[j][j];
+            } else if (matrix[i][j] - sum).abs() > TOLERANCE {
+                return Err(OVSAError::GenerationFailed);
+            }
+        }
+    }
+
+    Ok(factor)
+}
+
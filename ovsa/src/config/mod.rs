@@ -0,0 +1,81 @@
+//! Process-wide settings of the crate's parallelism. By default the parallel operations of the `parallel` feature and
+//! the tasks of the `async` feature run on rayon's global thread pool. Applications with their own pools can instead
+//! give the crate a pool of its own with `set_num_threads`, or share one of theirs with `set_thread_pool`, so that the
+//! crate neither competes with them for the global pool nor configures it behind their back.
+
+use std::sync::{Arc, RwLock};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::errors::OVSAError;
+
+
+/// The pool the crate runs on, or `None` for rayon's global pool.
+static POOL: RwLock<Option<Arc<ThreadPool>>> = RwLock::new(None);
+
+
+/// Runs the crate's parallel operations on a new pool of the given number of threads, named `ovsa-<index>`.
+/// Operations already running keep their pool.
+/// # Arguments
+/// * `n_threads` - The number of threads; 1 makes the parallel operations run one task at a time.
+/// # Returns
+/// `InvalidArgument` for zero threads, or `Io` if the threads cannot be started.
+pub fn set_num_threads(n_threads: usize) -> Result<(), OVSAError> {
+    if n_threads == 0 {
+        return Err(OVSAError::InvalidArgument("the number of threads must be positive".to_string()));
+    }
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(n_threads)
+        .thread_name(|index| format!("ovsa-{}", index))
+        .build()
+        .map_err(|error| OVSAError::Io(error.to_string()))?;
+    set_thread_pool(Arc::new(pool));
+    Ok(())
+}
+
+
+/// Runs the crate's parallel operations on the given pool, e.g. one the application also uses.
+/// # Arguments
+/// * `pool` - The thread pool.
+pub fn set_thread_pool(pool: Arc<ThreadPool>) {
+    *POOL.write().expect("Thread pool lock poisoned.") = Some(pool);
+}
+
+
+/// Returns the crate's parallel operations to rayon's global pool.
+pub fn reset_thread_pool() {
+    *POOL.write().expect("Thread pool lock poisoned.") = None;
+}
+
+
+/// Returns the pool set with `set_num_threads` or `set_thread_pool`, or `None` when the global pool is used.
+pub fn thread_pool() -> Option<Arc<ThreadPool>> {
+    POOL.read().expect("Thread pool lock poisoned.").clone()
+}
+
+
+/// Returns the number of threads the crate's parallel operations run on.
+pub fn num_threads() -> usize {
+    thread_pool().map_or_else(rayon::current_num_threads, |pool| pool.current_num_threads())
+}
+
+
+/// Runs a closure on the configured pool, so that the parallel iterators in it, including those of the dependencies,
+/// use its threads.
+#[cfg(feature = "parallel")]
+pub(crate) fn install<R: Send, F: FnOnce() -> R + Send>(f: F) -> R {
+    match thread_pool() {
+        Some(pool) => pool.install(f),
+        None => f(),
+    }
+}
+
+
+/// Runs a closure in the background on the configured pool.
+#[cfg(feature = "async")]
+pub(crate) fn spawn<F: FnOnce() + Send + 'static>(f: F) {
+    match thread_pool() {
+        Some(pool) => pool.spawn(f),
+        None => rayon::spawn(f),
+    }
+}
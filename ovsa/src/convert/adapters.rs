@@ -0,0 +1,131 @@
+//! Adapters between models, so that values encoded with an expressive model can be stored and classified with a cheap
+//! one, and the reverse. An adapter maps vectors of one model to vectors of another such that similarities carry over:
+//!
+//! | adapter | mapping | similarity after adapting |
+//! |---|---|---|
+//! | `PhaseBinarizer` | every FHRR phase becomes a circular thermometer code of `bits_per_phase` bits | `1 - mean(abs(phase difference)) / pi`, a triangular kernel of the phase differences |
+//! | `BinaryPhasors` | bit `b` becomes the phase `b * pi` | FHRR similarity `2 * s - 1` for a binary similarity `s` |
+//!
+//! Both similarities are monotonic in the original ones for random-like vectors, so nearest neighbours are preserved.
+
+use std::f64::consts::PI;
+use ndarray::Array1;
+use sprs::CsVec;
+
+use crate::binary::from_indices_or_empty;
+use crate::errors::OVSAError;
+use crate::fhrr;
+
+
+/// Maps vectors of one model to vectors of another.
+pub trait Adapter {
+    /// The type of the adapted vectors.
+    type Input;
+    /// The type of the resulting vectors.
+    type Output;
+
+    /// Returns the dimension of the resulting vectors for inputs of the given dimension.
+    /// # Arguments
+    /// * `dimension` - The dimension of the inputs.
+    fn output_dimension(&self, dimension: usize) -> usize;
+
+    /// Adapts a vector.
+    /// # Arguments
+    /// * `input` - The vector to adapt.
+    /// # Returns
+    /// The adapted vector.
+    fn adapt(&self, input: &Self::Input) -> Result<Self::Output, OVSAError>;
+
+    /// Adapts many vectors, e.g. a whole training set.
+    /// # Arguments
+    /// * `inputs` - The vectors to adapt.
+    /// # Returns
+    /// The adapted vectors, in order.
+    fn adapt_all(&self, inputs: &[Self::Input]) -> Result<Vec<Self::Output>, OVSAError> {
+        inputs.iter().map(|input| self.adapt(input)).collect()
+    }
+}
+
+
+/// Projects FHRR vectors into binary ones by angle binarization. Bit `i` of the code of a phase `phi` is active when
+/// `phi` lies in the half circle starting at `i * pi / bits_per_phase`, so that two phases differing by `delta` differ in
+/// about `bits_per_phase * abs(delta) / pi` bits: the Hamming distance measures angles, and more bits per phase
+/// resolve them more finely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseBinarizer {
+    bits_per_phase: usize,
+}
+
+
+impl PhaseBinarizer {
+    /// Creates an angle binarizer.
+    /// # Arguments
+    /// * `bits_per_phase` - The number of bits of every phase; 1 keeps the sign of the imaginary part only.
+    /// # Returns
+    /// A new `PhaseBinarizer`.
+    pub fn new(bits_per_phase: usize) -> Result<Self, OVSAError> {
+        if bits_per_phase == 0 {
+            return Err(OVSAError::ZeroActiveElements);
+        }
+        Ok(PhaseBinarizer { bits_per_phase })
+    }
+
+    /// Returns the number of bits of every phase.
+    pub fn bits_per_phase(&self) -> usize {
+        self.bits_per_phase
+    }
+}
+
+
+impl Adapter for PhaseBinarizer {
+    type Input = Array1<f32>;
+    type Output = CsVec<i8>;
+
+    fn output_dimension(&self, dimension: usize) -> usize {
+        dimension / 2 * self.bits_per_phase
+    }
+
+    fn adapt(&self, input: &Array1<f32>) -> Result<CsVec<i8>, OVSAError> {
+        let phases = fhrr::phases(input)?;
+        let sector = PI / self.bits_per_phase as f64;
+        let mut indices = Vec::with_capacity(phases.len() * self.bits_per_phase / 2);
+        for (j, phase) in phases.iter().enumerate() {
+            let phase = phase.rem_euclid(2.0 * PI);
+            for bit in 0..self.bits_per_phase {
+                // the half circle [bit * sector, bit * sector + pi)
+                if (phase - bit as f64 * sector).rem_euclid(2.0 * PI) < PI {
+                    indices.push(j * self.bits_per_phase + bit);
+                }
+            }
+        }
+        Ok(from_indices_or_empty(phases.len() * self.bits_per_phase, indices))
+    }
+}
+
+
+/// Embeds binary vectors into FHRR: bit `b` becomes the phasor `exp(i * b * pi)`, i.e. +1 or -1. XOR binding becomes
+/// FHRR binding, so binary pipelines can feed models that bind or interpolate in the phase domain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BinaryPhasors;
+
+
+impl Adapter for BinaryPhasors {
+    type Input = CsVec<i8>;
+    type Output = Array1<f32>;
+
+    fn output_dimension(&self, dimension: usize) -> usize {
+        2 * dimension
+    }
+
+    fn adapt(&self, input: &CsVec<i8>) -> Result<Array1<f32>, OVSAError> {
+        if input.dim() == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+
+        let mut phases = vec![0.0; input.dim()];
+        for &index in input.indices() {
+            phases[index] = PI;
+        }
+        Ok(fhrr::from_phases(&phases))
+    }
+}
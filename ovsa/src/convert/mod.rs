@@ -0,0 +1,203 @@
+//! Explicit conversions between the hypervector representations of the crate, so that pipelines mixing models agree
+//! on one mapping. Bipolar vectors are dense `Array1<f32>` vectors of +1 and -1, as produced by e.g.
+//! `codebook::orthogonal_dense`.
+//!
+//! | conversion | mapping | loss |
+//! |---|---|---|
+//! | `binary_to_bipolar` | bit `b` becomes `1 - 2b` | none |
+//! | `bipolar_to_binary` | negative entries become 1 | none for bipolar input, otherwise all but the signs |
+//! | `dense_to_bipolar` | the sign of every entry, 0 counting as positive | the magnitudes |
+//! | `dense_to_binary` | `dense_to_bipolar` followed by `bipolar_to_binary` | the magnitudes |
+//! | `harden` | negative entries become 1 and zeros random, or every entry stochastically by its magnitude | the magnitudes, kept in expectation when stochastic |
+//! | `sparse_to_segmented` | the first active entry of every segment | all other active entries |
+//! | `fhrr_to_hrr` | the inverse DFT of the conjugate symmetric phasors | the phasors above `n / 2` and the imaginary parts at 0 and `n / 2` |
+//! | `hrr_to_fhrr` | the phases of the DFT | the magnitudes, none for unitary vectors |
+//!
+//! See `adapters` to move FHRR vectors into binary pipelines and back.
+
+use ndarray::Array1;
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::from_indices_or_empty;
+use crate::dense::fourier::transform;
+use crate::errors::OVSAError;
+use crate::fhrr;
+use crate::hypervector::Hardening;
+use crate::rng::with_global_rng;
+
+pub mod adapters;
+
+
+/// Converts a binary vector into a bipolar one, mapping 0 to +1 and 1 to -1.
+/// The mapping is lossless: XOR binding becomes element-wise multiplication, and a Hamming distance `d` becomes a
+/// cosine similarity of `1 - 2d / dimension`.
+/// # Arguments
+/// * `vec` - The binary vector.
+/// # Returns
+/// The bipolar vector.
+pub fn binary_to_bipolar(vec: &CsVec<i8>) -> Array1<f32> {
+    let mut result = Array1::<f32>::ones(vec.dim());
+    for &index in vec.indices() {
+        result[index] = -1.0;
+    }
+    result
+}
+
+
+/// Converts a bipolar vector into a binary one, mapping negative entries to 1 and all others to 0.
+/// This inverts `binary_to_bipolar`; any other dense vector is reduced to the signs of its entries.
+/// # Arguments
+/// * `vec` - The bipolar vector.
+/// # Returns
+/// The binary vector.
+pub fn bipolar_to_binary(vec: &Array1<f32>) -> Result<CsVec<i8>, OVSAError> {
+    if vec.is_empty() {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    let indices = vec.iter().enumerate().filter(|&(_, &value)| value < 0.0).map(|(index, _)| index).collect();
+    Ok(from_indices_or_empty(vec.len(), indices))
+}
+
+
+/// Quantizes a dense vector to a bipolar one by the signs of its entries, counting 0 as positive.
+/// The magnitudes are lost: for random-like vectors at angle `theta`, the cosine similarity of the quantized vectors
+/// is about `1 - 2 * theta / pi`.
+/// # Arguments
+/// * `vec` - The dense vector.
+/// # Returns
+/// The bipolar vector.
+pub fn dense_to_bipolar(vec: &Array1<f32>) -> Array1<f32> {
+    vec.mapv(|value| if value < 0.0 { -1.0 } else { 1.0 })
+}
+
+
+/// Quantizes a dense vector to a binary one, mapping negative entries to 1. Equivalent to `dense_to_bipolar` followed
+/// by `bipolar_to_binary`, with the same loss.
+/// # Arguments
+/// * `vec` - The dense vector.
+/// # Returns
+/// The binary vector.
+pub fn dense_to_binary(vec: &Array1<f32>) -> Result<CsVec<i8>, OVSAError> {
+    bipolar_to_binary(vec)
+}
+
+
+/// Hardens a dense accumulator, e.g. a superposition of bipolar vectors, into a binary vector with the given hardening.
+/// # Arguments
+/// * `vec` - The dense accumulator.
+/// * `hardening` - The hardening.
+/// # Returns
+/// The binary vector.
+pub fn harden(vec: &Array1<f32>, hardening: Hardening) -> Result<CsVec<i8>, OVSAError> {
+    with_global_rng(|rng| harden_with_rng(vec, hardening, rng))
+}
+
+
+/// Hardens a dense accumulator into a binary vector, drawing random decisions with the provided random number
+/// generator. Negative entries become 1 as in `dense_to_binary`: with `Hardening::Majority` always, zeros at random,
+/// and with `Hardening::Stochastic` with probability `(1 - value / max_abs) / 2`, `max_abs` being the largest
+/// magnitude of the accumulator.
+/// # Arguments
+/// * `vec` - The dense accumulator.
+/// * `hardening` - The hardening.
+/// * `rng` - The random number generator.
+/// # Returns
+/// The binary vector.
+pub fn harden_with_rng<R: Rng + ?Sized>(vec: &Array1<f32>, hardening: Hardening, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+    if vec.is_empty() {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    let max_abs = vec.iter().fold(0f32, |max, value| max.max(value.abs()));
+    let mut indices = Vec::new();
+    for (index, &value) in vec.iter().enumerate() {
+        let active = match hardening {
+            Hardening::Majority => value < 0.0 || (value == 0.0 && rng.random_bool(0.5)),
+            Hardening::Stochastic => {
+                let probability = if max_abs > 0.0 { 0.5 - 0.5 * (value / max_abs) as f64 } else { 0.5 };
+                rng.random_bool(probability.clamp(0.0, 1.0))
+            }
+        };
+        if active {
+            indices.push(index);
+        }
+    }
+    Ok(from_indices_or_empty(vec.len(), indices))
+}
+
+
+/// Converts an FHRR vector of `n` phasors into a real HRR vector of dimension `n`, the inverse discrete Fourier
+/// transform of its phasors after enforcing conjugate symmetry with `fhrr::conjugate_symmetric`. The result is a
+/// unitary HRR vector of unit norm, and FHRR binding becomes circular convolution: binding in the frequency domain and
+/// storing real vectors gives the same result as binding the real vectors.
+/// # Arguments
+/// * `vec` - The FHRR vector.
+/// # Returns
+/// The HRR vector.
+pub fn fhrr_to_hrr(vec: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+    let symmetric = fhrr::conjugate_symmetric(vec)?;
+    let n_phases = symmetric.len() / 2;
+    let mut re: Vec<f64> = symmetric.iter().take(n_phases).map(|&value| value as f64).collect();
+    let mut im: Vec<f64> = symmetric.iter().skip(n_phases).map(|&value| value as f64).collect();
+    transform(&mut re, &mut im, true);
+    Ok(re.into_iter().map(|value| value as f32).collect())
+}
+
+
+/// Converts a real HRR vector of dimension `n` into an FHRR vector of `n` phasors, the phases of its discrete Fourier
+/// transform. The magnitudes of the spectrum are dropped, so the conversion inverts `fhrr_to_hrr` exactly for unitary
+/// vectors, see `dense::make_unitary`, and otherwise keeps the phase structure that binding acts on.
+/// # Arguments
+/// * `vec` - The HRR vector.
+/// # Returns
+/// The conjugate symmetric FHRR vector of dimension `2 * n`.
+pub fn hrr_to_fhrr(vec: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+    if vec.is_empty() {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    let mut re: Vec<f64> = vec.iter().map(|&value| value as f64).collect();
+    let mut im = vec![0.0; vec.len()];
+    transform(&mut re, &mut im, false);
+    let phases: Vec<f64> = re.iter().zip(&im).map(|(re, im)| im.atan2(*re)).collect();
+    Ok(fhrr::from_phases(&phases))
+}
+
+
+/// Converts a sparse binary vector into a segmented (block) code: the dimension is split into `n_segments` equal
+/// segments, each keeping exactly one active entry.
+/// A segment keeps its first active entry; a segment without active entries repeats the offset of the last active
+/// entry before it (or offset 0), so that similar vectors still tend to agree there. The conversion is lossless only
+/// for vectors with exactly one active entry per segment.
+/// # Arguments
+/// * `vec` - The sparse binary vector.
+/// * `n_segments` - The number of segments, which has to divide the dimension.
+/// # Returns
+/// The segmented vector with `n_segments` active entries.
+pub fn sparse_to_segmented(vec: &CsVec<i8>, n_segments: usize) -> Result<CsVec<i8>, OVSAError> {
+    if vec.dim() == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+    if n_segments == 0 || !vec.dim().is_multiple_of(n_segments) {
+        return Err(OVSAError::InvalidArgument(format!("{} segments do not divide the dimension {}", n_segments, vec.dim())));
+    }
+
+    let length = vec.dim() / n_segments;
+    let active = vec.indices();
+    let mut indices = Vec::with_capacity(n_segments);
+    let (mut next, mut last_offset) = (0, 0);
+    for segment in 0..n_segments {
+        let start = segment * length;
+        let first = next;
+        while next < active.len() && active[next] < start + length {
+            last_offset = active[next] - start;
+            next += 1;
+        }
+        let offset = if next > first { active[first] - start } else { last_offset };
+        indices.push(start + offset);
+    }
+
+    Ok(from_indices_or_empty(vec.dim(), indices))
+}
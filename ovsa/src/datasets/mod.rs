@@ -0,0 +1,312 @@
+//! Parsers for the datasets commonly used to benchmark hyperdimensional classifiers.
+//! The files can be fetched with `download`, or downloaded and extracted beforehand from:
+//! * ISOLET: `isolet1+2+3+4.data` and `isolet5.data` from <https://archive.ics.uci.edu/dataset/54/isolet>
+//! * HAR: the `UCI HAR Dataset` folder from <https://archive.ics.uci.edu/dataset/240/human+activity+recognition+using+smartphones>
+//! * European languages: the `training_texts` and `testing_texts` folders from <https://github.com/abbas-rahimi/HDC-Language-Recognition>
+
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::encode::{LevelEncoder, features, ngrams};
+use crate::errors::OVSAError;
+use crate::learn::Samples;
+use crate::memory::ItemMemory;
+use crate::progress::{Progress, ignore, report};
+use crate::trace::span;
+
+
+/// A dataset of labelled numeric feature vectors split into training and test samples.
+#[derive(Debug, Clone)]
+pub struct NumericDataset {
+    pub train: Vec<(Vec<f64>, String)>,
+    pub test: Vec<(Vec<f64>, String)>,
+}
+
+
+impl NumericDataset {
+    /// Returns the smallest and largest feature value of the training samples, e.g. to configure a `LevelEncoder`.
+    pub fn range(&self) -> (f64, f64) {
+        self.train.iter()
+            .flat_map(|(values, _)| values.iter())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| (min.min(value), max.max(value)))
+    }
+
+    /// Encodes the training and test samples with `encode::features`.
+    /// # Arguments
+    /// * `levels` - The level encoder for the feature values.
+    /// * `codebook` - The item memory holding the feature role symbols.
+    /// * `n_active` - The number of active entries of newly generated role symbols.
+    /// # Returns
+    /// The encoded training and test samples.
+    pub fn encode(&self, levels: &LevelEncoder, codebook: &mut ItemMemory, n_active: usize) -> Result<(Samples, Samples), OVSAError> {
+        self.encode_with_progress(levels, codebook, n_active, ignore)
+    }
+
+    /// Encodes the training and test samples, reporting progress after every sample under the stages
+    /// `encode_train` and `encode_test`.
+    /// # Arguments
+    /// * `levels` - The level encoder for the feature values.
+    /// * `codebook` - The item memory holding the feature role symbols.
+    /// * `n_active` - The number of active entries of newly generated role symbols.
+    /// * `on_progress` - The progress callback; stopping it returns `Cancelled`.
+    /// # Returns
+    /// The encoded training and test samples.
+    pub fn encode_with_progress<F: FnMut(Progress) -> ControlFlow<()>>(&self, levels: &LevelEncoder, codebook: &mut ItemMemory, n_active: usize, mut on_progress: F) -> Result<(Samples, Samples), OVSAError> {
+        span!(INFO, "encode_numeric_dataset", n_train = self.train.len(), n_test = self.test.len());
+        let mut encode_all = |samples: &[(Vec<f64>, String)], stage: &'static str| -> Result<Samples, OVSAError> {
+            let mut encoded = Vec::with_capacity(samples.len());
+            for (values, label) in samples {
+                encoded.push((features(values, levels, codebook, n_active)?, label.clone()));
+                report(&mut on_progress, stage, encoded.len(), samples.len(), None)?;
+            }
+            Ok(encoded)
+        };
+
+        Ok((encode_all(&self.train, "encode_train")?, encode_all(&self.test, "encode_test")?))
+    }
+}
+
+
+/// A dataset of labelled texts split into training and test samples.
+#[derive(Debug, Clone)]
+pub struct TextDataset {
+    pub train: Vec<(String, String)>,
+    pub test: Vec<(String, String)>,
+}
+
+
+impl TextDataset {
+    /// Encodes the training and test samples with `encode::ngrams`.
+    /// # Arguments
+    /// * `n` - The n-gram size.
+    /// * `codebook` - The item memory holding the character symbols.
+    /// * `n_active` - The number of active entries of newly generated character symbols.
+    /// # Returns
+    /// The encoded training and test samples.
+    pub fn encode(&self, n: usize, codebook: &mut ItemMemory, n_active: usize) -> Result<(Samples, Samples), OVSAError> {
+        self.encode_with_progress(n, codebook, n_active, ignore)
+    }
+
+    /// Encodes the training and test samples, reporting progress after every sample under the stages
+    /// `encode_train` and `encode_test`.
+    /// # Arguments
+    /// * `n` - The n-gram size.
+    /// * `codebook` - The item memory holding the character symbols.
+    /// * `n_active` - The number of active entries of newly generated character symbols.
+    /// * `on_progress` - The progress callback; stopping it returns `Cancelled`.
+    /// # Returns
+    /// The encoded training and test samples.
+    pub fn encode_with_progress<F: FnMut(Progress) -> ControlFlow<()>>(&self, n: usize, codebook: &mut ItemMemory, n_active: usize, mut on_progress: F) -> Result<(Samples, Samples), OVSAError> {
+        span!(INFO, "encode_text_dataset", n_train = self.train.len(), n_test = self.test.len());
+        let mut encode_all = |samples: &[(String, String)], stage: &'static str| -> Result<Samples, OVSAError> {
+            let mut encoded = Vec::with_capacity(samples.len());
+            for (text, label) in samples {
+                encoded.push((ngrams(text, n, codebook, n_active)?, label.clone()));
+                report(&mut on_progress, stage, encoded.len(), samples.len(), None)?;
+            }
+            Ok(encoded)
+        };
+
+        Ok((encode_all(&self.train, "encode_train")?, encode_all(&self.test, "encode_test")?))
+    }
+}
+
+
+/// Loads the ISOLET spoken letter dataset: 617 features per sample, labels `1` to `26`.
+/// # Arguments
+/// * `dir` - The folder containing `isolet1+2+3+4.data` (training) and `isolet5.data` (test).
+/// # Returns
+/// The parsed `NumericDataset`.
+pub fn load_isolet(dir: impl AsRef<Path>) -> Result<NumericDataset, OVSAError> {
+    let dir = dir.as_ref();
+    Ok(NumericDataset {
+        train: parse_isolet(&read(dir.join("isolet1+2+3+4.data"))?)?,
+        test: parse_isolet(&read(dir.join("isolet5.data"))?)?,
+    })
+}
+
+
+/// Loads the human activity recognition dataset: 561 features per sample, labelled with the activity names.
+/// # Arguments
+/// * `dir` - The `UCI HAR Dataset` folder.
+/// # Returns
+/// The parsed `NumericDataset`, or `InvalidFormat` if a split has not as many labels as feature rows.
+pub fn load_har(dir: impl AsRef<Path>) -> Result<NumericDataset, OVSAError> {
+    let dir = dir.as_ref();
+    // activity_labels.txt maps the numeric labels to names such as WALKING
+    let names: Vec<(String, String)> = read(dir.join("activity_labels.txt"))?.lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .map(|(id, name)| (id.trim().to_string(), name.trim().to_string()))
+        .collect();
+
+    let split = |name: &str| -> Result<Vec<(Vec<f64>, String)>, OVSAError> {
+        let features = read(dir.join(name).join(format!("X_{}.txt", name)))?;
+        let labels = read(dir.join(name).join(format!("y_{}.txt", name)))?;
+        let features: Vec<&str> = features.lines().filter(|line| !line.trim().is_empty()).collect();
+        let labels: Vec<&str> = labels.lines().filter(|line| !line.trim().is_empty()).collect();
+        if features.len() != labels.len() {
+            return Err(OVSAError::InvalidFormat(format!("{} has {} feature rows but {} labels", name, features.len(), labels.len())));
+        }
+
+        features.into_iter().zip(labels)
+            .map(|(line, id)| {
+                let label = names.iter().find(|(known, _)| known == id.trim()).map_or(id.trim(), |(_, name)| name.as_str());
+                Ok((parse_values(line.split_whitespace())?, label.to_string()))
+            })
+            .collect()
+    };
+
+    Ok(NumericDataset { train: split("train")?, test: split("test")? })
+}
+
+
+/// Loads the European languages dataset. Every file is one sample labelled with the part of its name before the first
+/// underscore, e.g. `training_texts/afr.txt` or `testing_texts/afr_12_p.txt` are labelled `afr`.
+/// # Arguments
+/// * `dir` - The folder containing `training_texts` and `testing_texts`.
+/// # Returns
+/// The parsed `TextDataset`.
+pub fn load_languages(dir: impl AsRef<Path>) -> Result<TextDataset, OVSAError> {
+    let dir = dir.as_ref();
+    Ok(TextDataset { train: read_texts(&dir.join("training_texts"))?, test: read_texts(&dir.join("testing_texts"))? })
+}
+
+
+/// A dataset that `download` can fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dataset {
+    /// ISOLET, see `load_isolet`.
+    Isolet,
+    /// Human activity recognition, see `load_har`.
+    Har,
+    /// European languages, see `load_languages`.
+    Languages,
+}
+
+
+impl Dataset {
+    /// Returns the URL of the archive the dataset is published as.
+    pub fn url(&self) -> &'static str {
+        match self {
+            Dataset::Isolet => "https://archive.ics.uci.edu/static/public/54/isolet.zip",
+            Dataset::Har => "https://archive.ics.uci.edu/static/public/240/human+activity+recognition+using+smartphones.zip",
+            Dataset::Languages => "https://github.com/abbas-rahimi/HDC-Language-Recognition/archive/refs/heads/master.zip",
+        }
+    }
+
+    /// Returns the folder of an extracted archive that the loader of the dataset reads.
+    /// # Arguments
+    /// * `dir` - The folder the archive was extracted into.
+    pub fn folder(&self, dir: impl AsRef<Path>) -> PathBuf {
+        match self {
+            Dataset::Isolet => dir.as_ref().to_path_buf(),
+            Dataset::Har => dir.as_ref().join("UCI HAR Dataset"),
+            Dataset::Languages => dir.as_ref().join("HDC-Language-Recognition-master"),
+        }
+    }
+
+    /// Returns true if the folder holds the files the loader reads.
+    fn is_present(&self, folder: &Path) -> bool {
+        match self {
+            Dataset::Isolet => folder.join("isolet1+2+3+4.data").is_file() && folder.join("isolet5.data").is_file(),
+            Dataset::Har => folder.join("train/X_train.txt").is_file() && folder.join("test/X_test.txt").is_file(),
+            Dataset::Languages => folder.join("training_texts").is_dir() && folder.join("testing_texts").is_dir(),
+        }
+    }
+}
+
+
+/// Downloads and extracts a dataset unless it is already present, without adding an HTTP or archive dependency to the
+/// crate: the archive is fetched with `curl` and extracted with `unzip`, and the compressed ISOLET files with `gzip`,
+/// which therefore have to be on the `PATH`.
+/// # Arguments
+/// * `dataset` - The dataset to fetch.
+/// * `dir` - The folder to extract the archive into, created if missing.
+/// # Returns
+/// The folder to pass to the loader of the dataset, or `Io` if a command is missing or fails.
+pub fn download(dataset: Dataset, dir: impl AsRef<Path>) -> Result<PathBuf, OVSAError> {
+    let dir = dir.as_ref();
+    let folder = dataset.folder(dir);
+    if dataset.is_present(&folder) {
+        return Ok(folder);
+    }
+    span!(INFO, "download_dataset", url = dataset.url());
+
+    fs::create_dir_all(dir).map_err(|error| OVSAError::Io(format!("{}: {}", dir.display(), error)))?;
+    let archive = dir.join("download.zip");
+    run(Command::new("curl").args(["--fail", "--silent", "--show-error", "--location", "--output"]).arg(&archive).arg(dataset.url()))?;
+    unzip(&archive, dir)?;
+    // the HAR archive wraps the actual one, and the ISOLET data files are compressed with `compress`, which gzip expands
+    let nested = dir.join("UCI HAR Dataset.zip");
+    if dataset == Dataset::Har && nested.is_file() {
+        unzip(&nested, dir)?;
+    }
+    for name in ["isolet1+2+3+4.data.Z", "isolet5.data.Z"] {
+        if dataset == Dataset::Isolet && dir.join(name).is_file() {
+            run(Command::new("gzip").args(["--decompress", "--force"]).arg(dir.join(name)))?;
+        }
+    }
+
+    if !dataset.is_present(&folder) {
+        return Err(OVSAError::Io(format!("the archive of {:?} did not contain the expected files in {}", dataset, folder.display())));
+    }
+    Ok(folder)
+}
+
+
+fn unzip(archive: &Path, dir: &Path) -> Result<(), OVSAError> {
+    run(Command::new("unzip").args(["-o", "-q"]).arg(archive).arg("-d").arg(dir))?;
+    fs::remove_file(archive).map_err(|error| OVSAError::Io(format!("{}: {}", archive.display(), error)))
+}
+
+
+fn run(command: &mut Command) -> Result<(), OVSAError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command.output().map_err(|error| OVSAError::Io(format!("{}: {}", program, error)))?;
+    if !output.status.success() {
+        return Err(OVSAError::Io(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim())));
+    }
+
+    Ok(())
+}
+
+
+/// Parses ISOLET rows: comma-separated features followed by the class, which is written as a float (e.g. `3.`).
+fn parse_isolet(data: &str) -> Result<Vec<(Vec<f64>, String)>, OVSAError> {
+    data.lines().filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut values = parse_values(line.split(','))?;
+            let class = values.pop().ok_or_else(|| OVSAError::InvalidFormat("empty ISOLET row".to_string()))?;
+            Ok((values, format!("{}", class as u32)))
+        })
+        .collect()
+}
+
+
+fn parse_values<'a>(fields: impl Iterator<Item = &'a str>) -> Result<Vec<f64>, OVSAError> {
+    fields.map(|field| field.trim().parse().map_err(|_| OVSAError::InvalidFormat(format!("not a number: {}", field)))).collect()
+}
+
+
+fn read_texts(dir: &Path) -> Result<Vec<(String, String)>, OVSAError> {
+    let mut paths: Vec<_> = fs::read_dir(dir).map_err(|error| OVSAError::Io(error.to_string()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect();
+    paths.sort();
+
+    paths.iter()
+        .map(|path| {
+            let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            let label = stem.split('_').next().unwrap_or(stem).to_string();
+            Ok((read(path)?.trim().to_string(), label))
+        })
+        .collect()
+}
+
+
+fn read(path: impl AsRef<Path>) -> Result<String, OVSAError> {
+    fs::read_to_string(path.as_ref()).map_err(|error| OVSAError::Io(format!("{}: {}", path.as_ref().display(), error)))
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use ndarray::{Array1, Array2, ArrayView1};
+
+use crate::errors::OVSAError;
+use crate::memory::{ItemMemory, Matches, top_k};
+
+
+/// A bank of labelled dense prototypes stored as the rows of a single matrix, normalized to unit length so that the
+/// cosine similarities to a query are one matrix-vector product, and those to a batch of queries one matrix product.
+/// Rows are contiguous in memory, which suits scanning all prototypes far better than one allocation per prototype.
+/// `ItemMemory<Array1<f32>>::query_batch` compares its entries to the queries through the same matrix products.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DenseBank {
+    labels: Vec<String>,
+    positions: HashMap<String, usize>,
+    rows: Array2<f32>,
+}
+
+
+impl DenseBank {
+    /// Creates an empty bank for vectors of the given dimension.
+    /// # Arguments
+    /// * `dimension` - The dimension of the prototypes.
+    /// # Returns
+    /// An empty `DenseBank`.
+    pub fn new(dimension: usize) -> Result<Self, OVSAError> {
+        if dimension == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+
+        Ok(DenseBank { labels: Vec::new(), positions: HashMap::new(), rows: Array2::zeros((0, dimension)) })
+    }
+
+    /// Returns the dimension of the prototypes.
+    pub fn dimension(&self) -> usize {
+        self.rows.ncols()
+    }
+
+    /// Returns the number of prototypes.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Returns true if the bank holds no prototypes.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Returns the labels in row order.
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// Returns the matrix of normalized prototypes, one row per label.
+    pub fn rows(&self) -> &Array2<f32> {
+        &self.rows
+    }
+
+    /// Returns the normalized prototype of a label.
+    /// # Arguments
+    /// * `label` - The label of the prototype.
+    pub fn get(&self, label: &str) -> Option<ArrayView1<'_, f32>> {
+        self.positions.get(label).map(|&row| self.rows.row(row))
+    }
+
+    /// Stores a prototype under a label, normalized to unit length, replacing the previous prototype of the label.
+    /// A zero vector is stored as is and is equally dissimilar to every query.
+    /// # Arguments
+    /// * `label` - The label of the prototype.
+    /// * `vector` - The prototype.
+    pub fn insert(&mut self, label: &str, vector: &Array1<f32>) -> Result<(), OVSAError> {
+        if vector.len() != self.dimension() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let normalized = normalize(vector.view());
+        match self.positions.get(label) {
+            Some(&row) => self.rows.row_mut(row).assign(&normalized),
+            None => {
+                self.rows.push_row(normalized.view()).expect("The dimensions match.");
+                self.positions.insert(label.to_string(), self.labels.len());
+                self.labels.push(label.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Computes the cosine similarity of the query to every prototype.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// # Returns
+    /// The similarities in row order; all 0 for a zero query.
+    pub fn similarities(&self, query: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+        if query.len() != self.dimension() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(self.rows.dot(&normalize(query.view())))
+    }
+
+    /// Computes the cosine similarity of every query to every prototype.
+    /// # Arguments
+    /// * `queries` - The query vectors.
+    /// # Returns
+    /// The matrix of similarities with one row per query and one column per prototype.
+    pub fn similarities_batch(&self, queries: &[Array1<f32>]) -> Result<Array2<f32>, OVSAError> {
+        if queries.iter().any(|query| query.len() != self.dimension()) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(cosine_block(&normalized_rows(self.dimension(), queries.iter().map(|query| query.view())), &self.rows))
+    }
+
+    /// Finds the `k` prototypes most similar to the query vector.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// * `k` - The maximum number of results.
+    /// # Returns
+    /// The (label, similarity) pairs sorted by decreasing similarity.
+    pub fn query(&self, query: &Array1<f32>, k: usize) -> Result<Matches, OVSAError> {
+        let similarities = self.similarities(query)?;
+        Ok(top_k(self.labels.iter().cloned().zip(similarities.iter().map(|&similarity| similarity as f64)).collect(), k))
+    }
+
+    /// Finds the prototype most similar to the query vector.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// # Returns
+    /// The (label, similarity) pair of the best match, or `None` if the bank is empty.
+    pub fn cleanup(&self, query: &Array1<f32>) -> Result<Option<(String, f64)>, OVSAError> {
+        Ok(self.query(query, 1)?.into_iter().next())
+    }
+}
+
+
+impl ItemMemory<Array1<f32>> {
+    /// Copies the memory into a bank of normalized prototypes, with the same labels in the same order.
+    pub fn to_bank(&self) -> DenseBank {
+        let mut bank = DenseBank::new(self.dimension()).expect("The dimension was validated on creation.");
+        for (label, vector) in self.iter() {
+            bank.insert(label, vector).expect("The dimensions match.");
+        }
+        bank
+    }
+}
+
+
+/// Stacks vectors of the given dimension into the rows of a matrix, each normalized to unit length.
+pub(crate) fn normalized_rows<'a>(dimension: usize, vectors: impl Iterator<Item = ArrayView1<'a, f32>>) -> Array2<f32> {
+    let mut rows = Array2::zeros((0, dimension));
+    for vector in vectors {
+        rows.push_row(normalize(vector).view()).expect("The dimensions match.");
+    }
+    rows
+}
+
+
+/// Computes the dot product of every row of `queries` with every row of `rows`, the cosine similarities for
+/// normalized rows. ndarray multiplies the matrices with the cache-blocked kernels of `matrixmultiply`, without BLAS.
+pub(crate) fn cosine_block(queries: &Array2<f32>, rows: &Array2<f32>) -> Array2<f32> {
+    queries.dot(&rows.t())
+}
+
+
+/// Scales a vector to unit length, leaving a zero vector unchanged.
+pub(crate) fn normalize(vector: ArrayView1<f32>) -> Array1<f32> {
+    let norm = crate::dense::norm(vector.view());
+    if norm > 0.0 { vector.mapv(|value| value / norm) } else { vector.to_owned() }
+}
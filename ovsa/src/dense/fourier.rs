@@ -0,0 +1,82 @@
+//! The discrete Fourier transform of the frequency-domain operations (FHRR conversions, unitary projection), kept
+//! in-crate like the rest of the dense kernels. Power-of-two lengths use an iterative radix-2 FFT in O(n log n), other
+//! lengths the direct O(n²) sum.
+
+use std::f64::consts::PI;
+
+
+/// Transforms a complex sequence in place.
+/// # Arguments
+/// * `re` - The real parts.
+/// * `im` - The imaginary parts, as many as real parts.
+/// * `inverse` - Whether to compute the inverse transform, scaled by `1 / n` so that it inverts the forward one.
+pub(crate) fn transform(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+    assert_eq!(im.len(), n, "Real and imaginary parts must be of the same length.");
+    if n <= 1 {
+        return;
+    }
+
+    if n.is_power_of_two() {
+        radix2(re, im, inverse);
+    } else {
+        direct(re, im, inverse);
+    }
+
+    if inverse {
+        for (re, im) in re.iter_mut().zip(im.iter_mut()) {
+            *re /= n as f64;
+            *im /= n as f64;
+        }
+    }
+}
+
+
+fn radix2(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut length = 2;
+    while length <= n {
+        let angle = sign * 2.0 * PI / length as f64;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (w_re, w_im) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (a, b) = (start + k, start + k + length / 2);
+                let (t_re, t_im) = (re[b] * w_re - im[b] * w_im, re[b] * w_im + im[b] * w_re);
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        length *= 2;
+    }
+}
+
+
+fn direct(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut out_re = vec![0.0; n];
+    let mut out_im = vec![0.0; n];
+    for k in 0..n {
+        for t in 0..n {
+            // reducing k * t modulo n keeps the angle small and accurate
+            let angle = sign * 2.0 * PI * ((k * t) % n) as f64 / n as f64;
+            let (cos, sin) = (angle.cos(), angle.sin());
+            out_re[k] += re[t] * cos - im[t] * sin;
+            out_im[k] += re[t] * sin + im[t] * cos;
+        }
+    }
+    re.copy_from_slice(&out_re);
+    im.copy_from_slice(&out_im);
+}
@@ -1,9 +1,20 @@
-use ndarray::Array1;
-use ndarray_linalg::Norm;
+use std::ops::Range;
+use ndarray::{Array1, ArrayView1, ArrayViewMut1, s};
 use rand::distr::Uniform;
-use rand::{Rng, rng};
+use rand::{Rng, SeedableRng};
+use sprs::CsVec;
 
 use crate::errors::OVSAError;
+use crate::hypervector::BundleStrategy;
+use crate::mask::{check_range, total_weight};
+use crate::binary::{from_indices_or_empty, grid_shift};
+use crate::rng::{OvsaRng, standard_normal, with_global_rng};
+use crate::trace::span;
+
+pub mod bank;
+
+pub(crate) mod fourier;
+
 
 /// Generates a random dense vector of given size with values uniformly distributed between min and max.
 /// # Arguments
@@ -13,16 +24,28 @@ use crate::errors::OVSAError;
 /// # Returns
 /// A dense vector represented as `Array1<f32>`.
 pub fn random_uniform(dimension: usize, min: f32, max: f32) -> Result<Array1<f32>, OVSAError> {
+    with_global_rng(|rng| random_uniform_with_rng(dimension, min, max, rng))
+}
+
+
+/// Generates a random dense vector using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The size of the vector.
+/// * `min` - The minimum value for the uniform distribution.
+/// * `max` - The maximum value for the uniform distribution.
+/// * `rng` - The random number generator to draw the values from.
+/// # Returns
+/// A dense vector represented as `Array1<f32>`.
+pub fn random_uniform_with_rng<R: Rng + ?Sized>(dimension: usize, min: f32, max: f32, rng: &mut R) -> Result<Array1<f32>, OVSAError> {
     if dimension == 0 {
         return Err(OVSAError::ZeroDimension);
     }
 
-    let mut rng = rng();
     let uniform = Uniform::new(min, max).unwrap();
 
     Ok(
         Array1::from(
-        (&mut rng).sample_iter(&uniform).take(dimension).collect::<Vec<f32>>()
+        rng.sample_iter(&uniform).take(dimension).collect::<Vec<f32>>()
         )
     )
 }
@@ -38,14 +61,32 @@ pub fn superposition(array_vec: &[Array1<f32>]) -> Result<Array1<f32>, OVSAError
         return Err(OVSAError::EmptyVectorList);
     }
 
-    let size = array_vec.get(0).expect("Input slice is empty").len();
+    let size = array_vec.first().expect("Input slice is empty").len();
+    if array_vec.iter().any(|array| array.len() != size) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    span!(DEBUG, "superposition", n_vectors = array_vec.len(), dimension = size);
+
+    let mut result = Array1::<f32>::zeros(size);
+    #[cfg(feature = "parallel")]
+    if size >= crate::binary::PARALLEL_DIMENSION_THRESHOLD {
+        use ndarray::Axis;
+        use rayon::prelude::*;
+
+        // every chunk of dimensions sums the vectors in slice order, so each entry is added up exactly like in the
+        // serial loop and the result is bit-identical to it
+        crate::config::install(|| {
+            result.axis_chunks_iter_mut(Axis(0), SUM_CHUNK_SIZE).into_par_iter().enumerate().for_each(|(chunk, mut out)| {
+                let start = chunk * SUM_CHUNK_SIZE;
+                for array in array_vec {
+                    out += &array.slice(s![start..start + out.len()]);
+                }
+            })
+        });
+        return Ok(result);
+    }
 
-    let mut result = Array1::<f32>::zeros(array_vec[0].len());
-    // todo: optimize
     for array in array_vec {
-        if array.len() != size {
-            return Err(OVSAError::VectorSizeMismatch);
-        }
         result += array;
     }
 
@@ -53,6 +94,48 @@ pub fn superposition(array_vec: &[Array1<f32>]) -> Result<Array1<f32>, OVSAError
 }
 
 
+/// Computes the superposition of dense vectors with the given bundling strategy. A tree sums chunks of `fan_in`
+/// vectors first, which can be reduced in parallel and accumulates less rounding error than one long running sum, so
+/// its result may differ from `BundleStrategy::Flat` in the last bits.
+/// # Arguments
+/// * `array_vec` - A slice of dense vectors.
+/// * `strategy` - The bundling strategy.
+/// # Returns
+/// A dense vector representing the superposition result.
+pub fn superposition_with_strategy(array_vec: &[Array1<f32>], strategy: BundleStrategy) -> Result<Array1<f32>, OVSAError> {
+    let fan_in = match strategy {
+        BundleStrategy::Flat => return superposition(array_vec),
+        BundleStrategy::Tree { fan_in } => fan_in,
+    };
+    if fan_in < 2 {
+        return Err(OVSAError::InvalidArgument(format!("a bundling tree needs a fan-in of at least 2, got {}", fan_in)));
+    }
+
+    let mut level = sum_level(array_vec, fan_in)?;
+    while level.len() > 1 {
+        level = sum_level(&level, fan_in)?;
+    }
+    level.pop().ok_or(OVSAError::EmptyVectorList)
+}
+
+
+/// Sums every chunk of `fan_in` vectors of one tree level.
+fn sum_level(array_vec: &[Array1<f32>], fan_in: usize) -> Result<Vec<Array1<f32>>, OVSAError> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        crate::config::install(|| array_vec.par_chunks(fan_in).map(superposition).collect())
+    }
+    #[cfg(not(feature = "parallel"))]
+    array_vec.chunks(fan_in).map(superposition).collect()
+}
+
+
+/// The number of dimensions summed by one parallel task of `superposition`.
+#[cfg(feature = "parallel")]
+const SUM_CHUNK_SIZE: usize = 16_384;
+
+
 /// Computes the circular convolution of two dense vectors.
 /// # Arguments
 /// * `a` - The first dense vector.
@@ -60,21 +143,144 @@ pub fn superposition(array_vec: &[Array1<f32>]) -> Result<Array1<f32>, OVSAError
 /// # Returns
 /// A dense vector representing the circular convolution result.
 pub fn circular_convolution(a: &Array1<f32>, b: &Array1<f32>) -> Array1<f32> {
+    let mut result = Array1::<f32>::zeros(a.len());
+    bind_accumulate(&mut result, a, b).expect("Vectors must be of the same dimension for circular convolution.");
+
+    result
+}
+
+
+/// The number of accumulator entries `bind_accumulate` and `bind_bundle` complete before moving on, 8 KiB of `f32`, so
+/// that the block and the window of `b` it reads stay in the L1 cache.
+const CONVOLUTION_BLOCK: usize = 2048;
+
+
+/// Adds the circular convolution of two dense vectors to an accumulator without allocating the bound vector.
+/// The accumulator is processed in cache-sized blocks, each completed in one pass over `a` while the window of `b` it
+/// reads slides by one entry per step, instead of sweeping the whole accumulator once per entry of `a`.
+/// # Arguments
+/// * `accumulator` - The vector the binding is added to.
+/// * `a` - The first dense vector.
+/// * `b` - The second dense vector.
+pub fn bind_accumulate(accumulator: &mut Array1<f32>, a: &Array1<f32>, b: &Array1<f32>) -> Result<(), OVSAError> {
     let n = a.len();
-    let mut result = Array1::<f32>::zeros(n);
+    if b.len() != n || accumulator.len() != n {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
 
-    // todo: optimize with matmul and slices
+    for start in (0..n).step_by(CONVOLUTION_BLOCK) {
+        let end = (start + CONVOLUTION_BLOCK).min(n);
+        convolve_block(accumulator.slice_mut(s![start..end]), start, a, b);
+    }
+
+    Ok(())
+}
+
+
+/// Adds the entries `start..start + block.len()` of the circular convolution of `a` and `b` to a block of an
+/// accumulator.
+fn convolve_block(mut block: ArrayViewMut1<f32>, start: usize, a: &Array1<f32>, b: &Array1<f32>) {
+    let (n, len) = (a.len(), block.len());
+    // for a fixed i, result[k] += a[i] * b[(k - i) % n] reads a window of b that wraps around at most once, so it
+    // splits into two contiguous slices without a modulo, which lets ndarray vectorize the update
     for i in 0..n {
-        for j in 0..n {
-            let k = (i + j) % n;
-            result[k] += a[i] * b[j];
+        let offset = (start + n - i) % n;
+        let first = len.min(n - offset);
+        block.slice_mut(s![..first]).scaled_add(a[i], &b.slice(s![offset..offset + first]));
+        if first < len {
+            block.slice_mut(s![first..]).scaled_add(a[i], &b.slice(s![..len - first]));
         }
     }
+}
 
-    result
+
+
+/// Removes a previously added vector from a superposition accumulator, e.g. to take an element out of a set or record
+/// without summing the remaining elements again.
+/// # Arguments
+/// * `accumulator` - The superposition the element was added to.
+/// * `element` - The dense vector to remove.
+pub fn bundle_remove(accumulator: &mut Array1<f32>, element: &Array1<f32>) -> Result<(), OVSAError> {
+    if accumulator.len() != element.len() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    accumulator.scaled_add(-1.0, element);
+
+    Ok(())
+}
+
+
+/// Binds two dense vectors partially, interpolating between the identity (`alpha = 0`) and circular convolution
+/// (`alpha = 1`), e.g. for soft role assignment. `vec2` is raised to the fractional power `alpha` by scaling the phase
+/// angles of its Fourier coefficients (Plate's fractional binding), so binding with `alpha` and then with `beta`
+/// approximates binding with `alpha + beta`.
+/// # Arguments
+/// * `a` - The vector to bind.
+/// * `b` - The key vector.
+/// * `alpha` - The binding strength in `[0, 1]`.
+/// # Returns
+/// The partially bound dense vector.
+pub fn bind_graded(a: &Array1<f32>, b: &Array1<f32>, alpha: f64) -> Result<Array1<f32>, OVSAError> {
+    if a.len() != b.len() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    if !(0.0..=1.0).contains(&alpha) {
+        return Err(OVSAError::InvalidArgument(format!("binding strength must be in [0, 1], got {}", alpha)));
+    }
+
+    Ok(circular_convolution(a, &fractional_power(b, alpha)))
 }
 
 
+/// Raises a dense vector to a real power under circular convolution: every Fourier coefficient `r * exp(i * phi)`
+/// becomes `r^exponent * exp(i * exponent * phi)`, and the real part of the inverse transform is kept.
+pub(crate) fn fractional_power(vec: &Array1<f32>, exponent: f64) -> Array1<f32> {
+    map_spectrum(vec, |magnitude, phase| {
+        if magnitude == 0.0 {
+            // 0^0 is the identity's coefficient
+            return if exponent == 0.0 { (1.0, 0.0) } else { (0.0, 0.0) };
+        }
+        (magnitude.powf(exponent), exponent * phase)
+    })
+}
+
+
+/// Projects a dense vector to the nearest unitary vector, whose Fourier coefficients all have magnitude 1: every
+/// coefficient keeps its phase and has its magnitude set to 1, and coefficients of magnitude 0 become 1. Circular
+/// correlation exactly inverts circular convolution with a unitary vector, so unitary keys bind and unbind without
+/// noise, and a unitary vector keeps unit norm under any number of bindings with other unitary vectors.
+/// The projection is idempotent, and `convert::hrr_to_fhrr` and `convert::fhrr_to_hrr` invert each other on its
+/// results. FPE encodings, see `vfa::FractionalPowerEncoder`, and thereby the positions of `encode::OccupancyMap` are
+/// unitary by construction, as vectors of unit phasors in the frequency domain.
+/// # Arguments
+/// * `vec` - The dense vector.
+/// # Returns
+/// The unitary vector, of unit norm.
+pub fn make_unitary(vec: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+    if vec.is_empty() {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    // the coefficients of a real vector are conjugate symmetric, and so are their phases, so the result stays real
+    Ok(map_spectrum(vec, |magnitude, phase| if magnitude == 0.0 { (1.0, 0.0) } else { (1.0, phase) }))
+}
+
+
+/// Transforms a real vector to the frequency domain, maps every coefficient given as `(magnitude, phase)` to a new
+/// `(magnitude, phase)`, and returns the real part of the inverse transform.
+fn map_spectrum<F: Fn(f64, f64) -> (f64, f64)>(vec: &Array1<f32>, map: F) -> Array1<f32> {
+    let mut re: Vec<f64> = vec.iter().map(|&value| value as f64).collect();
+    let mut im = vec![0.0; vec.len()];
+    fourier::transform(&mut re, &mut im, false);
+    for (re, im) in re.iter_mut().zip(im.iter_mut()) {
+        let (magnitude, phase) = map(re.hypot(*im), im.atan2(*re));
+        (*re, *im) = (magnitude * phase.cos(), magnitude * phase.sin());
+    }
+    fourier::transform(&mut re, &mut im, true);
+    re.into_iter().map(|value| value as f32).collect()
+}
+
 
 /// Computes the circular correlation of two dense vectors./// # Arguments
 /// * `a` - The first dense vector.
@@ -103,18 +309,64 @@ pub fn circular_correlation(a: &Array1<f32>, b: &Array1<f32>) -> Array1<f32> {
 /// # Returns
 /// A new dense vector that has been cyclically shifted.
 pub fn cyclic_shift(array: &Array1<f32>, shift_by: isize) -> Array1<f32> {
-    let n = array.len() as isize;
-    let mut result = Array1::<f32>::zeros(array.len());
-
-    for old_index in 0..n {
-        let new_index = (old_index + shift_by).rem_euclid(n);
-        result[new_index as usize] = array[old_index as usize];
+    let n = array.len();
+    if n == 0 {
+        return array.clone();
     }
+    let shift = shift_by.rem_euclid(n as isize) as usize;
 
+    // two slice copies instead of one index computation per entry
+    let mut result = Array1::<f32>::zeros(n);
+    result.slice_mut(s![shift..]).assign(&array.slice(s![..n - shift]));
+    result.slice_mut(s![..shift]).assign(&array.slice(s![n - shift..]));
     result
 }
 
 
+/// Performs a cyclic shift on a dense vector in place, without allocating.
+/// # Arguments
+/// * `array` - The dense vector to be shifted.
+/// * `shift_by` - The number of positions to shift. Positive values shift to the right, negative values shift to the left.
+pub fn cyclic_shift_inplace(array: &mut Array1<f32>, shift_by: isize) {
+    let n = array.len();
+    if n == 0 {
+        return;
+    }
+    let shift = shift_by.rem_euclid(n as isize) as usize;
+
+    match array.as_slice_mut() {
+        Some(values) => values.rotate_right(shift),
+        None => {
+            let shifted = cyclic_shift(array, shift as isize);
+            array.assign(&shifted);
+        }
+    }
+}
+
+
+/// Performs a cyclic shift on a dense vector viewed as a row-major grid, shifting rows and columns independently.
+/// # Arguments
+/// * `array` - The dense vector to be shifted.
+/// * `rows` - The number of rows of the grid.
+/// * `cols` - The number of columns of the grid, with `rows * cols` equal to the dimension.
+/// * `dy` - The number of rows to shift down, negative values shifting up.
+/// * `dx` - The number of columns to shift right, negative values shifting left.
+/// # Returns
+/// A new dense vector that has been cyclically shifted along both axes.
+pub fn cyclic_shift_2d(array: &Array1<f32>, rows: usize, cols: usize, dy: isize, dx: isize) -> Result<Array1<f32>, OVSAError> {
+    if rows.checked_mul(cols) != Some(array.len()) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    let mut result = Array1::<f32>::zeros(array.len());
+    for (old_index, &value) in array.iter().enumerate() {
+        result[grid_shift(old_index, rows, cols, dy, dx)] = value;
+    }
+
+    Ok(result)
+}
+
+
 /// Computes the cosine similarity between two dense vectors.
 /// # Arguments
 /// * `a` - The first dense vector.
@@ -124,5 +376,171 @@ pub fn cyclic_shift(array: &Array1<f32>, shift_by: isize) -> Array1<f32> {
 pub fn similarity(a: &Array1<f32>, b: &Array1<f32>) -> f32 {
     assert_eq!(a.len(), b.len(), "Vectors must be of the same dimension for similarity computation.");
 
-    a.dot(b) / (a.norm_l2() * b.norm_l2())
-}
\ No newline at end of file
+    dot(a.view(), b.view()) / (norm(a.view()) * norm(b.view()))
+}
+
+
+/// Computes the cosine similarity between two dense vectors with per-dimension weights, i.e. the cosine similarity of
+/// the vectors scaled by the square roots of the weights. Uniform weights give `similarity`.
+/// # Arguments
+/// * `a` - The first dense vector.
+/// * `b` - The second dense vector.
+/// * `weights` - One finite, non-negative weight per dimension, e.g. from `mask::segment_weights`.
+/// # Returns
+/// The weighted cosine similarity, between -1.0 and 1.0.
+pub fn weighted_similarity(a: &Array1<f32>, b: &Array1<f32>, weights: &[f64]) -> Result<f32, OVSAError> {
+    if a.len() != b.len() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    total_weight(weights, a.len())?;
+
+    let (mut product, mut norm_a, mut norm_b) = (0f64, 0f64, 0f64);
+    for ((&x, &y), &weight) in a.iter().zip(b.iter()).zip(weights) {
+        product += weight * (x * y) as f64;
+        norm_a += weight * (x * x) as f64;
+        norm_b += weight * (y * y) as f64;
+    }
+    Ok((product / (norm_a * norm_b).sqrt()) as f32)
+}
+
+
+/// Computes the cosine similarity between two dense vectors over a range of dimensions only, e.g. one field of a
+/// structured vector, on views of the range without copying it.
+/// # Arguments
+/// * `a` - The first dense vector.
+/// * `b` - The second dense vector.
+/// * `range` - The non-empty range of dimensions to compare.
+/// # Returns
+/// The cosine similarity of the ranges, between -1.0 and 1.0.
+pub fn similarity_range(a: &Array1<f32>, b: &Array1<f32>, range: Range<usize>) -> Result<f32, OVSAError> {
+    if a.len() != b.len() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    check_range(a.len(), &range)?;
+
+    let (a, b) = (a.slice(s![range.clone()]), b.slice(s![range]));
+    Ok(dot(a, b) / (norm(a) * norm(b)))
+}
+
+
+/// Finds the cyclic shift that maps a vector closest to a target, e.g. to decode a sequence position or estimate a
+/// temporal alignment. The dot products of all shifts are the circular correlation of the target with the vector.
+/// # Arguments
+/// * `vec` - The vector to shift.
+/// * `target` - The vector to align to.
+/// # Returns
+/// The best shift in `[0, dimension)`, the smallest one on ties, and the cosine similarity of `cyclic_shift(vec, shift)`
+/// to the target.
+pub fn best_shift(vec: &Array1<f32>, target: &Array1<f32>) -> Result<(isize, f64), OVSAError> {
+    if vec.len() != target.len() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    if vec.is_empty() {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    let products = circular_correlation(target, vec);
+    let (shift, product) = products.iter().enumerate()
+        .fold((0, f32::NEG_INFINITY), |best, (shift, &product)| if product > best.1 { (shift, product) } else { best });
+    Ok((shift as isize, (product / (norm(vec.view()) * norm(target.view()))) as f64))
+}
+
+
+/// Computes the dot product of two dense vectors.
+/// Implemented without BLAS, so that no BLAS library has to be linked for `ndarray`'s `dot`.
+/// # Arguments
+/// * `a` - The first dense vector.
+/// * `b` - The second dense vector.
+/// # Returns
+/// The dot product.
+pub fn dot(a: ArrayView1<f32>, b: ArrayView1<f32>) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+
+/// Computes the Euclidean (L2) norm of a dense vector.
+/// # Arguments
+/// * `a` - The dense vector.
+/// # Returns
+/// The norm.
+pub fn norm(a: ArrayView1<f32>) -> f32 {
+    dot(a, a).sqrt()
+}
+
+
+/// Binds every pair of vectors by circular convolution and sums the results in a single accumulator. Like
+/// `bind_accumulate`, the accumulator is processed in cache-sized blocks, and every block receives all pairs before the
+/// next one is loaded, so the accumulator passes through the cache once for the whole bundle.
+/// # Arguments
+/// * `pairs` - The pairs of dense vectors to bind, e.g. (role, filler) fields of a record.
+/// # Returns
+/// A dense vector representing the superposition of the bound pairs.
+pub fn bind_bundle(pairs: &[(&Array1<f32>, &Array1<f32>)]) -> Result<Array1<f32>, OVSAError> {
+    let (first, _) = pairs.first().ok_or(OVSAError::EmptyVectorList)?;
+    span!(DEBUG, "bind_bundle", n_pairs = pairs.len(), dimension = first.len());
+
+    let n = first.len();
+    if pairs.iter().any(|(a, b)| a.len() != n || b.len() != n) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    let mut result = Array1::<f32>::zeros(n);
+    for start in (0..n).step_by(CONVOLUTION_BLOCK) {
+        let end = (start + CONVOLUTION_BLOCK).min(n);
+        for (a, b) in pairs {
+            convolve_block(result.slice_mut(s![start..end]), start, a, b);
+        }
+    }
+
+    Ok(result)
+}
+
+
+/// Maps a dense vector to another dimension with a seeded random Gaussian projection, scaled by
+/// `1 / sqrt(new_dimension)` so that norms, and the cosine similarities of vectors reprojected with the same seed, are
+/// preserved approximately (Johnson-Lindenstrauss). The projection is regenerated from the seed row by row.
+/// # Arguments
+/// * `vec` - The vector to reproject.
+/// * `new_dimension` - The dimension of the result.
+/// * `seed` - The seed of the projection; vectors are only comparable if reprojected with the same seed.
+/// # Returns
+/// The reprojected vector.
+pub fn reproject(vec: &Array1<f32>, new_dimension: usize, seed: u64) -> Result<Array1<f32>, OVSAError> {
+    if new_dimension == 0 || vec.is_empty() {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    let mut rng = OvsaRng::seed_from_u64(seed);
+    let scale = 1.0 / (new_dimension as f64).sqrt();
+    Ok((0..new_dimension)
+        .map(|_| (vec.iter().map(|&value| value as f64 * standard_normal(&mut rng)).sum::<f64>() * scale) as f32)
+        .collect())
+}
+
+
+/// Computes a hyperplane LSH signature of a dense vector: bit `j` is set if the vector lies on the positive side of
+/// the `j`-th random Gaussian hyperplane. The Hamming similarity of two signatures approximates `1 - angle / pi`,
+/// which lets dense prototypes be indexed by binary nearest-neighbour systems. Packed with `sketch::to_fingerprints`
+/// and written as little-endian bytes, bit `j` lands in bit `j % 8` of byte `j / 8`, the layout of FAISS binary codes.
+/// # Arguments
+/// * `vec` - The dense vector.
+/// * `n_bits` - The number of hyperplanes, i.e. the size of the signature.
+/// * `seed` - The seed of the hyperplanes; signatures are only comparable if they share it and the vector dimension.
+/// # Returns
+/// The signature as a sparse binary vector of dimension `n_bits`.
+pub fn to_binary_lsh(vec: &Array1<f32>, n_bits: usize, seed: u64) -> Result<CsVec<i8>, OVSAError> {
+    if n_bits == 0 || vec.is_empty() {
+        return Err(OVSAError::ZeroDimension);
+    }
+
+    // the hyperplanes are regenerated from the seed, one at a time, rather than stored
+    let mut rng = OvsaRng::seed_from_u64(seed);
+    let indices: Vec<usize> = (0..n_bits)
+        .filter(|_| {
+            let projection: f64 = vec.iter().map(|&value| value as f64 * standard_normal(&mut rng)).sum();
+            projection > 0.0
+        })
+        .collect();
+
+    Ok(from_indices_or_empty(n_bits, indices))
+}
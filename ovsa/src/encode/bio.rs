@@ -0,0 +1,201 @@
+//! Encoding of DNA and RNA sequences by their k-mers: every k-mer is the XOR of its base symbols, each shifted by its
+//! position in the k-mer, and a sequence is the consensus sum of its k-mers. Sequences sharing many k-mers are thus
+//! similar, which allows classifying sequencing reads against reference sequences by similarity alone.
+
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::{consensus_sum_with_rng, cyclic_shift, sparse_random_with_rng, xor};
+use crate::errors::OVSAError;
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+
+
+/// The nucleotide bases, `U` being read as `T`.
+pub const BASES: [char; 4] = ['A', 'C', 'G', 'T'];
+
+
+/// Encodes nucleotide sequences by their k-mers.
+#[derive(Debug, Clone)]
+pub struct KmerEncoder {
+    k: usize,
+    bases: ItemMemory,
+}
+
+
+impl KmerEncoder {
+    /// Creates a k-mer encoder with random base symbols.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vectors.
+    /// * `n_active` - The number of active entries of every base symbol; about half the dimension suits bundling.
+    /// * `k` - The k-mer length.
+    /// # Returns
+    /// A new `KmerEncoder`.
+    pub fn new(dimension: usize, n_active: usize, k: usize) -> Result<Self, OVSAError> {
+        with_global_rng(|rng| Self::new_with_rng(dimension, n_active, k, rng))
+    }
+
+    /// Creates a k-mer encoder, drawing the base symbols with the provided random number generator.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vectors.
+    /// * `n_active` - The number of active entries of every base symbol; about half the dimension suits bundling.
+    /// * `k` - The k-mer length.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// A new `KmerEncoder`.
+    pub fn new_with_rng<R: Rng + ?Sized>(dimension: usize, n_active: usize, k: usize, rng: &mut R) -> Result<Self, OVSAError> {
+        if k == 0 {
+            return Err(OVSAError::InvalidArgument("the k-mer length must be at least 1, got 0".to_string()));
+        }
+
+        let mut bases = ItemMemory::new(dimension)?;
+        for base in BASES {
+            bases.insert(&base.to_string(), sparse_random_with_rng(dimension, n_active, rng)?)?;
+        }
+        Ok(KmerEncoder { k, bases })
+    }
+
+    /// Returns the k-mer length.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Returns the dimension of the vectors.
+    pub fn dimension(&self) -> usize {
+        self.bases.dimension()
+    }
+
+    /// Returns the base symbols, labelled `A`, `C`, `G` and `T`.
+    pub fn bases(&self) -> &ItemMemory {
+        &self.bases
+    }
+
+    /// Encodes one k-mer.
+    /// # Arguments
+    /// * `kmer` - The k-mer, of length `k`.
+    /// # Returns
+    /// The XOR of the base symbols, the base at position `i` shifted by `i`.
+    pub fn kmer(&self, kmer: &str) -> Result<CsVec<i8>, OVSAError> {
+        let bases = normalize(kmer)?;
+        if bases.len() != self.k {
+            return Err(OVSAError::InvalidArgument(format!("expected a {}-mer, got {} bases", self.k, bases.len())));
+        }
+        self.kmer_of(&bases)
+    }
+
+    /// Encodes a sequence as the consensus sum of its k-mers.
+    /// # Arguments
+    /// * `sequence` - The sequence of `A`, `C`, `G`, `T` or `U`, in either case, at least `k` bases long.
+    /// # Returns
+    /// A sparse binary vector representing the sequence.
+    pub fn encode(&self, sequence: &str) -> Result<CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.encode_with_rng(sequence, rng))
+    }
+
+    /// Encodes a sequence using the provided random number generator to break bundling ties.
+    /// # Arguments
+    /// * `sequence` - The sequence of `A`, `C`, `G`, `T` or `U`, in either case, at least `k` bases long.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// A sparse binary vector representing the sequence.
+    pub fn encode_with_rng<R: Rng + ?Sized>(&self, sequence: &str, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        let bases = normalize(sequence)?;
+        self.encode_bases(&bases, rng)
+    }
+
+    /// Encodes reference sequences into an item memory for `classify_read`.
+    /// # Arguments
+    /// * `references` - The (label, sequence) pairs.
+    /// # Returns
+    /// An `ItemMemory` mapping every label to its sequence encoding.
+    pub fn reference_index(&self, references: &[(&str, &str)]) -> Result<ItemMemory, OVSAError> {
+        with_global_rng(|rng| self.reference_index_with_rng(references, rng))
+    }
+
+    /// Encodes reference sequences into an item memory using the provided random number generator.
+    /// # Arguments
+    /// * `references` - The (label, sequence) pairs.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// An `ItemMemory` mapping every label to its sequence encoding.
+    pub fn reference_index_with_rng<R: Rng + ?Sized>(&self, references: &[(&str, &str)], rng: &mut R) -> Result<ItemMemory, OVSAError> {
+        let mut index = ItemMemory::new(self.dimension())?;
+        for (label, sequence) in references {
+            index.insert(label, self.encode_with_rng(sequence, rng)?)?;
+        }
+        Ok(index)
+    }
+
+    /// Classifies a read by the most similar reference. Reads may come from either strand, so the read and its reverse
+    /// complement are both compared and the better match is kept.
+    /// # Arguments
+    /// * `index` - The references, built by `reference_index` with this encoder.
+    /// * `read` - The read.
+    /// # Returns
+    /// The (label, similarity) pair of the best reference, or `None` if the index is empty.
+    pub fn classify_read(&self, index: &ItemMemory, read: &str) -> Result<Option<(String, f64)>, OVSAError> {
+        with_global_rng(|rng| self.classify_read_with_rng(index, read, rng))
+    }
+
+    /// Classifies a read by the most similar reference using the provided random number generator.
+    /// # Arguments
+    /// * `index` - The references, built by `reference_index` with this encoder.
+    /// * `read` - The read.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// The (label, similarity) pair of the best reference, or `None` if the index is empty.
+    pub fn classify_read_with_rng<R: Rng + ?Sized>(&self, index: &ItemMemory, read: &str, rng: &mut R) -> Result<Option<(String, f64)>, OVSAError> {
+        let forward = normalize(read)?;
+        let reverse: Vec<char> = forward.iter().rev().map(|&base| complement(base)).collect();
+
+        let forward = index.cleanup(&self.encode_bases(&forward, rng)?)?;
+        let reverse = index.cleanup(&self.encode_bases(&reverse, rng)?)?;
+        Ok(match (forward, reverse) {
+            (Some(forward), Some(reverse)) => Some(if reverse.1 > forward.1 { reverse } else { forward }),
+            (forward, reverse) => forward.or(reverse),
+        })
+    }
+
+    fn encode_bases<R: Rng + ?Sized>(&self, bases: &[char], rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        if bases.len() < self.k {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let kmers = bases.windows(self.k).map(|kmer| self.kmer_of(kmer)).collect::<Result<Vec<_>, OVSAError>>()?;
+        consensus_sum_with_rng(&kmers, rng)
+    }
+
+    fn kmer_of(&self, bases: &[char]) -> Result<CsVec<i8>, OVSAError> {
+        let mut kmer = self.base(bases[0]).clone();
+        for (position, &base) in bases.iter().enumerate().skip(1) {
+            kmer = xor(&kmer, &cyclic_shift(self.base(base), position as isize))?;
+        }
+        Ok(kmer)
+    }
+
+    fn base(&self, base: char) -> &CsVec<i8> {
+        self.bases.get(base.encode_utf8(&mut [0; 4])).expect("Normalized bases have symbols.")
+    }
+}
+
+
+/// Upper-cases a sequence and reads `U` as `T`, rejecting any other character.
+fn normalize(sequence: &str) -> Result<Vec<char>, OVSAError> {
+    sequence.chars()
+        .map(|c| match c.to_ascii_uppercase() {
+            'U' => Ok('T'),
+            base if BASES.contains(&base) => Ok(base),
+            other => Err(OVSAError::InvalidArgument(format!("unexpected base {:?}", other))),
+        })
+        .collect()
+}
+
+
+fn complement(base: char) -> char {
+    match base {
+        'A' => 'T',
+        'T' => 'A',
+        'C' => 'G',
+        _ => 'C',
+    }
+}
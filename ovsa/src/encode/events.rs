@@ -0,0 +1,168 @@
+//! Encoding of asynchronous event streams, such as the spikes of a neuromorphic sensor: every event binds its symbol
+//! with the code of its age, and the events of a sliding horizon are bundled, so that the vector describes which
+//! events happened and how long ago.
+
+use std::collections::VecDeque;
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::{consensus_sum_with_rng, sparse_random_with_rng, xor};
+use crate::encode::levels_with_rng;
+use crate::errors::OVSAError;
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+
+
+/// How the age of an event is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeCoding {
+    /// Every time bin has an independent random code, so events only match within the same bin.
+    Bins,
+    /// Time bins are level vectors whose similarity decreases linearly with their distance, so that jittered events
+    /// still match, as with a fractional power encoding of the timestamp.
+    Levels,
+}
+
+
+/// Encodes the events of a sliding time horizon.
+#[derive(Debug, Clone)]
+pub struct EventStreamEncoder {
+    n_active: usize,
+    horizon: f64,
+    bin_width: f64,
+    symbols: ItemMemory,
+    bins: Vec<CsVec<i8>>,
+    events: VecDeque<(f64, String)>,
+}
+
+
+impl EventStreamEncoder {
+    /// Creates an event stream encoder without events.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vectors.
+    /// * `n_active` - The number of active entries of every symbol; about half the dimension suits bundling.
+    /// * `horizon` - How long events are kept, in the unit of the timestamps.
+    /// * `n_bins` - The number of time bins the horizon is split into.
+    /// * `coding` - How the age of an event is encoded.
+    /// # Returns
+    /// A new `EventStreamEncoder`.
+    pub fn new(dimension: usize, n_active: usize, horizon: f64, n_bins: usize, coding: TimeCoding) -> Result<Self, OVSAError> {
+        with_global_rng(|rng| Self::new_with_rng(dimension, n_active, horizon, n_bins, coding, rng))
+    }
+
+    /// Creates an event stream encoder, drawing the time codes with the provided random number generator.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vectors.
+    /// * `n_active` - The number of active entries of every symbol; about half the dimension suits bundling.
+    /// * `horizon` - How long events are kept, in the unit of the timestamps.
+    /// * `n_bins` - The number of time bins the horizon is split into.
+    /// * `coding` - How the age of an event is encoded.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// A new `EventStreamEncoder`.
+    pub fn new_with_rng<R: Rng + ?Sized>(dimension: usize, n_active: usize, horizon: f64, n_bins: usize, coding: TimeCoding, rng: &mut R) -> Result<Self, OVSAError> {
+        if !(horizon.is_finite() && horizon > 0.0) {
+            return Err(OVSAError::InvalidArgument(format!("the horizon must be positive, got {}", horizon)));
+        }
+        if n_bins == 0 {
+            return Err(OVSAError::InvalidArgument("the number of time bins must be at least 1, got 0".to_string()));
+        }
+
+        let bins = match coding {
+            TimeCoding::Bins => (0..n_bins).map(|_| sparse_random_with_rng(dimension, n_active, rng)).collect::<Result<Vec<_>, OVSAError>>()?,
+            TimeCoding::Levels => levels_with_rng(dimension, n_active, n_bins, rng)?,
+        };
+        Ok(EventStreamEncoder {
+            n_active,
+            horizon,
+            bin_width: horizon / n_bins as f64,
+            symbols: ItemMemory::new(dimension)?,
+            bins,
+            events: VecDeque::new(),
+        })
+    }
+
+    /// Returns the dimension of the vectors.
+    pub fn dimension(&self) -> usize {
+        self.symbols.dimension()
+    }
+
+    /// Returns the event symbols created so far.
+    pub fn symbols(&self) -> &ItemMemory {
+        &self.symbols
+    }
+
+    /// Returns the time codes, from the most recent bin to the oldest.
+    pub fn bins(&self) -> &[CsVec<i8>] {
+        &self.bins
+    }
+
+    /// Returns the number of events within the horizon.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns whether no event is within the horizon.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Adds an event and forgets the events that fell out of the horizon.
+    /// # Arguments
+    /// * `time` - The timestamp, not earlier than that of the previous event.
+    /// * `symbol` - The symbol of the event, e.g. a pixel and polarity.
+    pub fn push(&mut self, time: f64, symbol: &str) -> Result<(), OVSAError> {
+        if !time.is_finite() {
+            return Err(OVSAError::InvalidArgument(format!("event timestamps must be finite, got {}", time)));
+        }
+        if self.events.back().is_some_and(|&(last, _)| time < last) {
+            return Err(OVSAError::InvalidArgument(format!("event at {} arrived after a later event", time)));
+        }
+
+        self.events.push_back((time, symbol.to_string()));
+        self.expire(time);
+        Ok(())
+    }
+
+    /// Forgets the events that are older than the horizon at the given time.
+    /// # Arguments
+    /// * `now` - The current time.
+    pub fn expire(&mut self, now: f64) {
+        while self.events.front().is_some_and(|&(time, _)| now - time >= self.horizon) {
+            self.events.pop_front();
+        }
+    }
+
+    /// Encodes the events within the horizon, as seen at the given time.
+    /// # Arguments
+    /// * `now` - The current time, not earlier than the last event.
+    /// # Returns
+    /// A sparse binary vector bundling every event bound to the code of its age.
+    pub fn encode(&mut self, now: f64) -> Result<CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.encode_with_rng(now, rng))
+    }
+
+    /// Encodes the events within the horizon using the provided random number generator.
+    /// # Arguments
+    /// * `now` - The current time, not earlier than the last event.
+    /// * `rng` - The random number generator drawing new symbols and breaking bundling ties.
+    /// # Returns
+    /// A sparse binary vector bundling every event bound to the code of its age.
+    pub fn encode_with_rng<R: Rng + ?Sized>(&mut self, now: f64, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        if self.events.back().is_some_and(|&(last, _)| now < last) {
+            return Err(OVSAError::InvalidArgument(format!("cannot encode at {}, before the last event", now)));
+        }
+        self.expire(now);
+        if self.events.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let mut bound = Vec::with_capacity(self.events.len());
+        for (time, symbol) in &self.events {
+            let bin = (((now - time) / self.bin_width) as usize).min(self.bins.len() - 1);
+            let symbol = self.symbols.symbol_with_rng(symbol, self.n_active, rng)?;
+            bound.push(xor(symbol, &self.bins[bin])?);
+        }
+        consensus_sum_with_rng(&bound, rng)
+    }
+}
@@ -0,0 +1,354 @@
+pub mod bio;
+pub mod events;
+#[cfg(feature = "dense")]
+pub mod occupancy;
+pub mod schema;
+pub mod sparse;
+pub mod string;
+pub mod text;
+
+use rand::Rng;
+use rand::seq::index::sample;
+use sprs::CsVec;
+
+use crate::binary::{bind_bundle, consensus_sum_with_rng, cyclic_shift, from_indices, sparse_random_with_rng, xor};
+use crate::errors::OVSAError;
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+
+
+/// Encodes a text as the consensus sum of its character n-grams.
+/// Each n-gram is the XOR of its character symbols, each shifted by its distance to the end of the n-gram,
+/// so that the same characters in a different order produce a different vector.
+/// Character symbols are taken from (or added to) the codebook.
+/// Sparse codes vanish under majority bundling, so the codebook symbols should be dense (about half the dimension active).
+/// # Arguments
+/// * `text` - The text to encode.
+/// * `n` - The n-gram size.
+/// * `codebook` - The item memory holding the character symbols.
+/// * `n_active` - The number of active entries of newly generated character symbols.
+/// # Returns
+/// A sparse binary vector representing the text.
+pub fn ngrams(text: &str, n: usize, codebook: &mut ItemMemory, n_active: usize) -> Result<CsVec<i8>, OVSAError> {
+    with_global_rng(|rng| ngrams_with_rng(text, n, codebook, n_active, rng))
+}
+
+
+/// Encodes a text as the consensus sum of its character n-grams, generating new character symbols and breaking
+/// bundling ties with the provided random number generator.
+/// # Arguments
+/// * `text` - The text to encode.
+/// * `n` - The n-gram size.
+/// * `codebook` - The item memory holding the character symbols.
+/// * `n_active` - The number of active entries of newly generated character symbols.
+/// * `rng` - The random number generator.
+/// # Returns
+/// A sparse binary vector representing the text.
+pub fn ngrams_with_rng<R: Rng + ?Sized>(text: &str, n: usize, codebook: &mut ItemMemory, n_active: usize, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+    if n == 0 {
+        return Err(OVSAError::InvalidArgument("the n-gram size must be at least 1, got 0".to_string()));
+    }
+    let chars: Vec<String> = text.chars().map(|c| c.to_string()).collect();
+    if chars.len() < n {
+        return Err(OVSAError::EmptyVectorList);
+    }
+
+    let mut grams: Vec<CsVec<i8>> = Vec::with_capacity(chars.len() + 1 - n);
+    for window in chars.windows(n) {
+        let mut gram = cyclic_shift(codebook.symbol_with_rng(&window[0], n_active, rng)?, n as isize - 1);
+        for (offset, symbol) in window.iter().enumerate().skip(1) {
+            let shifted = cyclic_shift(codebook.symbol_with_rng(symbol, n_active, rng)?, (n - 1 - offset) as isize);
+            gram = xor(&gram, &shifted)?;
+        }
+        grams.push(gram);
+    }
+
+    consensus_sum_with_rng(&grams, rng)
+}
+
+
+/// Generates level vectors for encoding scalars, where similarity decreases linearly with the distance between levels.
+/// Level 0 is a random vector; every further level moves an equal share of its active entries to positions that were
+/// inactive in level 0, so that the first and last level share no active entries.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `n_active` - The number of active entries of every level.
+/// * `n_levels` - The number of levels.
+/// # Returns
+/// The level vectors, ordered from the lowest to the highest level.
+pub fn levels(dimension: usize, n_active: usize, n_levels: usize) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    with_global_rng(|rng| levels_with_rng(dimension, n_active, n_levels, rng))
+}
+
+
+/// Generates level vectors using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The size of the vectors.
+/// * `n_active` - The number of active entries of every level.
+/// * `n_levels` - The number of levels.
+/// * `rng` - The random number generator.
+/// # Returns
+/// The level vectors, ordered from the lowest to the highest level.
+pub fn levels_with_rng<R: Rng + ?Sized>(dimension: usize, n_active: usize, n_levels: usize, rng: &mut R) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+    if n_active == 0 {
+        return Err(OVSAError::ZeroActiveElements);
+    }
+    if 2 * n_active > dimension {
+        return Err(OVSAError::TooManyActiveElements);
+    }
+    if n_levels == 0 {
+        return Err(OVSAError::InvalidArgument("the number of levels must be at least 1, got 0".to_string()));
+    }
+
+    // the first half of the sample is the active set of level 0, the second half the positions it moves to
+    let positions: Vec<usize> = sample(rng, dimension, 2 * n_active).into_vec();
+    let (base, pool) = positions.split_at(n_active);
+
+    let mut result = Vec::with_capacity(n_levels);
+    for level in 0..n_levels {
+        let n_moved = if n_levels == 1 { 0 } else { level * n_active / (n_levels - 1) };
+        let indices: Vec<usize> = pool[..n_moved].iter().chain(base[n_moved..].iter()).copied().collect();
+        result.push(from_indices(dimension, &indices)?);
+    }
+
+    Ok(result)
+}
+
+
+/// Maps scalar values in a fixed range onto level vectors.
+#[derive(Debug, Clone)]
+pub struct LevelEncoder {
+    min: f64,
+    max: f64,
+    levels: Vec<CsVec<i8>>,
+}
+
+
+impl LevelEncoder {
+    /// Creates a level encoder for values in `[min, max]`.
+    /// # Arguments
+    /// * `min` - The value mapped to the lowest level.
+    /// * `max` - The value mapped to the highest level.
+    /// * `levels` - The level vectors, e.g. generated by `levels`.
+    /// # Returns
+    /// A new `LevelEncoder`.
+    pub fn new(min: f64, max: f64, levels: Vec<CsVec<i8>>) -> Result<Self, OVSAError> {
+        if levels.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+        if levels.iter().any(|level| level.dim() != levels[0].dim()) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(LevelEncoder { min, max, levels })
+    }
+
+    /// Returns the level vectors, ordered from the lowest to the highest level.
+    pub fn levels(&self) -> &[CsVec<i8>] {
+        &self.levels
+    }
+
+    /// Returns the index of the level a value falls into. Values outside the range are clamped.
+    /// # Arguments
+    /// * `value` - The scalar value.
+    pub fn level_index(&self, value: f64) -> usize {
+        let span = self.max - self.min;
+        let fraction = if span > 0.0 { ((value - self.min) / span).clamp(0.0, 1.0) } else { 0.0 };
+        (fraction * (self.levels.len() - 1) as f64).round() as usize
+    }
+
+    /// Returns the level vector encoding a value.
+    /// # Arguments
+    /// * `value` - The scalar value.
+    pub fn encode(&self, value: f64) -> &CsVec<i8> {
+        &self.levels[self.level_index(value)]
+    }
+}
+
+
+/// Encodes hierarchical categories such as taxonomy paths, so that similarity reflects the distance in the hierarchy.
+/// A top-level category is a random vector, and every child is its parent with a fixed share of the active entries
+/// moved to random inactive positions. Siblings thus keep the bits of their parent, and cousins the bits of their grandparent.
+/// Nodes are created on first use and kept in an item memory labelled by the path joined with `/`.
+#[derive(Debug, Clone)]
+pub struct HierarchyEncoder {
+    n_active: usize,
+    mutation: f64,
+    nodes: ItemMemory,
+}
+
+
+impl HierarchyEncoder {
+    /// Creates a hierarchy encoder.
+    /// # Arguments
+    /// * `dimension` - The size of the vectors.
+    /// * `n_active` - The number of active entries of every node vector.
+    /// * `mutation` - The share of the active entries a child does not inherit from its parent, in `(0, 1]`.
+    /// # Returns
+    /// A new `HierarchyEncoder` without nodes.
+    pub fn new(dimension: usize, n_active: usize, mutation: f64) -> Result<Self, OVSAError> {
+        if n_active == 0 {
+            return Err(OVSAError::ZeroActiveElements);
+        }
+        if n_active > dimension {
+            return Err(OVSAError::TooManyActiveElements);
+        }
+        if !(mutation > 0.0 && mutation <= 1.0) {
+            return Err(OVSAError::InvalidArgument(format!("mutation must be in (0, 1], got {}", mutation)));
+        }
+
+        Ok(HierarchyEncoder { n_active, mutation, nodes: ItemMemory::new(dimension)? })
+    }
+
+    /// Returns the item memory of the nodes created so far, which can be used to clean up a noisy node vector.
+    pub fn nodes(&self) -> &ItemMemory {
+        &self.nodes
+    }
+
+    /// Returns the vector of a node, creating it and its missing ancestors.
+    /// # Arguments
+    /// * `path` - The categories from the top of the hierarchy down to the node.
+    /// # Returns
+    /// The sparse binary vector of the node.
+    pub fn encode(&mut self, path: &[&str]) -> Result<&CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.encode_with_rng(path, rng))
+    }
+
+    /// Returns the vector of a node, creating missing nodes with the provided random number generator.
+    /// # Arguments
+    /// * `path` - The categories from the top of the hierarchy down to the node.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// The sparse binary vector of the node.
+    pub fn encode_with_rng<R: Rng + ?Sized>(&mut self, path: &[&str], rng: &mut R) -> Result<&CsVec<i8>, OVSAError> {
+        if path.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let dimension = self.nodes.dimension();
+        for depth in 1..=path.len() {
+            let label = path[..depth].join("/");
+            if self.nodes.get(&label).is_some() {
+                continue;
+            }
+
+            let vector = if depth == 1 {
+                sparse_random_with_rng(dimension, self.n_active, rng)?
+            } else {
+                let parent = self.nodes.get(&path[..depth - 1].join("/")).unwrap();
+                self.mutate(parent, rng)?
+            };
+            self.nodes.insert(&label, vector)?;
+        }
+
+        Ok(self.nodes.get(&path.join("/")).unwrap())
+    }
+
+    /// Moves the share of active entries given by the mutation rate to positions that are inactive in the parent.
+    fn mutate<R: Rng + ?Sized>(&self, parent: &CsVec<i8>, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        let dimension = parent.dim();
+        let n_moved = ((self.mutation * self.n_active as f64).round() as usize).min(dimension - self.n_active);
+
+        let mut active = vec![false; dimension];
+        parent.indices().iter().for_each(|&index| active[index] = true);
+        let mut indices: Vec<usize> = parent.indices().to_vec();
+        let dropped = sample(rng, indices.len(), n_moved).into_vec();
+        for position in dropped {
+            // the dropped positions stay marked active so that they are not drawn again
+            let mut candidate = rng.random_range(0..dimension);
+            while active[candidate] {
+                candidate = rng.random_range(0..dimension);
+            }
+            active[candidate] = true;
+            indices[position] = candidate;
+        }
+
+        from_indices(dimension, &indices)
+    }
+}
+
+
+/// Encodes a record as the consensus sum of its fields, each field being the XOR of a role (key) and a filler (value) vector.
+/// # Arguments
+/// * `fields` - The (role, filler) pairs of the record.
+/// # Returns
+/// A sparse binary vector representing the record.
+pub fn record(fields: &[(&CsVec<i8>, &CsVec<i8>)]) -> Result<CsVec<i8>, OVSAError> {
+    bind_bundle(fields)
+}
+
+
+/// A record encoded together with its fields, so that decisions on the record can be explained field by field (see
+/// `CentroidClassifier::explain`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedRecord {
+    /// The vector of the record.
+    pub vector: CsVec<i8>,
+    /// The fields of the record, in encoding order.
+    pub fields: Vec<RecordField>,
+}
+
+
+/// A field of an `EncodedRecord`: the labelled role and the filler bound to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordField {
+    pub role: String,
+    pub role_vector: CsVec<i8>,
+    pub filler: CsVec<i8>,
+}
+
+
+/// Encodes a record like `record`, keeping the role labels and the vectors of its fields.
+/// # Arguments
+/// * `fields` - The (role label, role, filler) triples of the record.
+/// # Returns
+/// The `EncodedRecord`.
+pub fn record_with_roles(fields: &[(&str, &CsVec<i8>, &CsVec<i8>)]) -> Result<EncodedRecord, OVSAError> {
+    let pairs: Vec<(&CsVec<i8>, &CsVec<i8>)> = fields.iter().map(|&(_, role, filler)| (role, filler)).collect();
+    let vector = record(&pairs)?;
+    let fields = fields.iter()
+        .map(|&(role, role_vector, filler)| RecordField { role: role.to_string(), role_vector: role_vector.clone(), filler: filler.clone() })
+        .collect();
+
+    Ok(EncodedRecord { vector, fields })
+}
+
+
+/// Encodes a numeric feature vector as a record binding a role symbol per feature position to the level of its value.
+/// Role symbols are labelled `feature:<position>` and taken from (or added to) the codebook.
+/// # Arguments
+/// * `values` - The feature values.
+/// * `levels` - The level encoder for the values.
+/// * `codebook` - The item memory holding the role symbols.
+/// * `n_active` - The number of active entries of newly generated role symbols.
+/// # Returns
+/// A sparse binary vector representing the feature vector.
+pub fn features(values: &[f64], levels: &LevelEncoder, codebook: &mut ItemMemory, n_active: usize) -> Result<CsVec<i8>, OVSAError> {
+    Ok(features_with_roles(values, levels, codebook, n_active)?.vector)
+}
+
+
+/// Encodes a numeric feature vector like `features`, keeping the role labels and the vectors of its fields.
+/// # Arguments
+/// * `values` - The feature values.
+/// * `levels` - The level encoder for the values.
+/// * `codebook` - The item memory holding the role symbols.
+/// * `n_active` - The number of active entries of newly generated role symbols.
+/// # Returns
+/// The `EncodedRecord` with one field per feature position.
+pub fn features_with_roles(values: &[f64], levels: &LevelEncoder, codebook: &mut ItemMemory, n_active: usize) -> Result<EncodedRecord, OVSAError> {
+    let mut roles = Vec::with_capacity(values.len());
+    for position in 0..values.len() {
+        let label = format!("feature:{}", position);
+        let role = codebook.symbol(&label, n_active)?.clone();
+        roles.push((label, role));
+    }
+
+    let fields: Vec<(&str, &CsVec<i8>, &CsVec<i8>)> = roles.iter().zip(values)
+        .map(|((label, role), &value)| (label.as_str(), role, levels.encode(value)))
+        .collect();
+    record_with_roles(&fields)
+}
@@ -0,0 +1,179 @@
+//! Occupancy maps in the style of spatial semantic pointers: a position `(x, y)` is the fractional power encoding
+//! `S(x, y)` of `vfa::FractionalPowerEncoder`, a vector of unit phasors, and a whole map is a superposition of
+//! positions. Occupancy observations add `S(x, y)` weighted by their evidence, so that the inner product of the map
+//! with `S(x, y)` is a kernel-smoothed occupancy estimate. Objects are FHRR symbols bound to their position, so that
+//! unbinding a position recovers the object there and unbinding an object recovers where it is.
+
+use ndarray::Array1;
+use rand::Rng;
+
+use crate::dense::dot;
+use crate::errors::OVSAError;
+use crate::fhrr;
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+use crate::vfa::FractionalPowerEncoder;
+
+
+/// A map of occupancy evidence and object locations over the plane.
+#[derive(Debug, Clone)]
+pub struct OccupancyMap {
+    positions: FractionalPowerEncoder,
+    occupancy: Array1<f32>,
+    objects: Array1<f32>,
+    symbols: ItemMemory<Array1<f32>>,
+}
+
+
+impl OccupancyMap {
+    /// Creates an empty map.
+    /// # Arguments
+    /// * `dimension` - The size of the vectors, an even number.
+    /// * `bandwidth` - The distance over which the position kernel decays to about 0.6, i.e. the map resolution.
+    /// # Returns
+    /// A new `OccupancyMap`.
+    pub fn new(dimension: usize, bandwidth: f64) -> Result<Self, OVSAError> {
+        with_global_rng(|rng| Self::new_with_rng(dimension, bandwidth, rng))
+    }
+
+    /// Creates an empty map, drawing the position frequencies with the provided random number generator.
+    /// # Arguments
+    /// * `dimension` - The size of the vectors, an even number.
+    /// * `bandwidth` - The distance over which the position kernel decays to about 0.6, i.e. the map resolution.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// A new `OccupancyMap`.
+    pub fn new_with_rng<R: Rng + ?Sized>(dimension: usize, bandwidth: f64, rng: &mut R) -> Result<Self, OVSAError> {
+        let positions = FractionalPowerEncoder::new_with_rng(dimension, 2, bandwidth, rng)?;
+        Ok(OccupancyMap {
+            positions,
+            occupancy: Array1::zeros(dimension),
+            objects: Array1::zeros(dimension),
+            symbols: ItemMemory::new(dimension)?,
+        })
+    }
+
+    /// Returns the dimension of the vectors.
+    pub fn dimension(&self) -> usize {
+        self.positions.dimension()
+    }
+
+    /// Returns the position encoder.
+    pub fn positions(&self) -> &FractionalPowerEncoder {
+        &self.positions
+    }
+
+    /// Returns the occupancy map vector.
+    pub fn occupancy_vector(&self) -> &Array1<f32> {
+        &self.occupancy
+    }
+
+    /// Returns the object map vector.
+    pub fn object_vector(&self) -> &Array1<f32> {
+        &self.objects
+    }
+
+    /// Returns the object symbols, random phasor vectors labelled by object name.
+    pub fn symbols(&self) -> &ItemMemory<Array1<f32>> {
+        &self.symbols
+    }
+
+    /// Adds an occupancy observation. Probabilities above 0.5 add evidence for an obstacle, below 0.5 for free space.
+    /// # Arguments
+    /// * `x` - The x coordinate.
+    /// * `y` - The y coordinate.
+    /// * `occupancy` - The observed occupancy probability, in `[0, 1]`.
+    pub fn observe(&mut self, x: f64, y: f64, occupancy: f64) -> Result<(), OVSAError> {
+        if !(0.0..=1.0).contains(&occupancy) {
+            return Err(OVSAError::InvalidArgument(format!("occupancy must be in [0, 1], got {}", occupancy)));
+        }
+
+        let position = self.positions.encode(&[x, y])?;
+        self.occupancy.scaled_add((2.0 * occupancy - 1.0) as f32, &position);
+        Ok(())
+    }
+
+    /// Estimates the occupancy evidence at a position: the observations near it, weighted by the position kernel.
+    /// Positive values mean mostly occupied, negative values mostly free, and values near 0 unobserved.
+    /// # Arguments
+    /// * `x` - The x coordinate.
+    /// * `y` - The y coordinate.
+    pub fn occupancy(&self, x: f64, y: f64) -> Result<f64, OVSAError> {
+        let position = self.positions.encode(&[x, y])?;
+        Ok(dot(self.occupancy.view(), position.view()) as f64 / (self.dimension() / 2) as f64)
+    }
+
+    /// Places an object at a position, creating its symbol on first use.
+    /// # Arguments
+    /// * `label` - The name of the object.
+    /// * `x` - The x coordinate.
+    /// * `y` - The y coordinate.
+    pub fn place(&mut self, label: &str, x: f64, y: f64) -> Result<(), OVSAError> {
+        with_global_rng(|rng| self.place_with_rng(label, x, y, rng))
+    }
+
+    /// Places an object at a position, drawing a new symbol with the provided random number generator.
+    /// # Arguments
+    /// * `label` - The name of the object.
+    /// * `x` - The x coordinate.
+    /// * `y` - The y coordinate.
+    /// * `rng` - The random number generator.
+    pub fn place_with_rng<R: Rng + ?Sized>(&mut self, label: &str, x: f64, y: f64, rng: &mut R) -> Result<(), OVSAError> {
+        if self.symbols.get(label).is_none() {
+            let symbol = fhrr::random_with_rng(self.dimension(), rng)?;
+            self.symbols.insert(label, symbol)?;
+        }
+
+        let position = self.positions.encode(&[x, y])?;
+        let bound = fhrr::bind(self.symbols.get(label).unwrap(), &position)?;
+        self.objects += &bound;
+        Ok(())
+    }
+
+    /// Answers "what is at (x, y)": unbinds the position from the object map and cleans the result up.
+    /// # Arguments
+    /// * `x` - The x coordinate.
+    /// * `y` - The y coordinate.
+    /// # Returns
+    /// The (label, similarity) pair of the most likely object, or `None` if no object was placed.
+    pub fn object_at(&self, x: f64, y: f64) -> Result<Option<(String, f64)>, OVSAError> {
+        let position = self.positions.encode(&[x, y])?;
+        self.symbols.cleanup(&fhrr::unbind(&self.objects, &position)?)
+    }
+
+    /// Answers "where is object O": unbinds the object from the object map and searches a grid for the position whose
+    /// encoding matches the result best.
+    /// # Arguments
+    /// * `label` - The name of the object.
+    /// * `min` - The lower corner of the searched area.
+    /// * `max` - The upper corner of the searched area.
+    /// * `steps` - The number of grid points along each axis, at least 2.
+    /// # Returns
+    /// The best position and its kernel score, about 1 for an object placed once, or `None` for an unknown object.
+    pub fn locate(&self, label: &str, min: [f64; 2], max: [f64; 2], steps: usize) -> Result<Option<([f64; 2], f64)>, OVSAError> {
+        if steps < 2 {
+            return Err(OVSAError::InvalidArgument(format!("a search grid needs at least 2 steps, got {}", steps)));
+        }
+        let Some(symbol) = self.symbols.get(label) else {
+            return Ok(None);
+        };
+
+        let query = fhrr::unbind(&self.objects, symbol)?;
+        let n_phases = (self.dimension() / 2) as f64;
+        let mut best: Option<([f64; 2], f64)> = None;
+        for i in 0..steps {
+            for j in 0..steps {
+                let point = [
+                    min[0] + (max[0] - min[0]) * i as f64 / (steps - 1) as f64,
+                    min[1] + (max[1] - min[1]) * j as f64 / (steps - 1) as f64,
+                ];
+                let score = dot(query.view(), self.positions.encode(&point)?.view()) as f64 / n_phases;
+                if best.is_none_or(|(_, best_score)| score > best_score) {
+                    best = Some((point, score));
+                }
+            }
+        }
+        Ok(best)
+    }
+}
+
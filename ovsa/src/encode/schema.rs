@@ -0,0 +1,283 @@
+//! Record schemas: a type describes its fields once, as scalars or categories, and a `SchemaEncoder` turns its values
+//! into records with one role per field. `#[derive(HdEncode)]` from the `ovsa-derive` crate (re-exported here with the
+//! `derive` feature) implements the description for ordinary structs:
+//!
+//! ```ignore
+//! #[derive(HdEncode)]
+//! struct Flower {
+//!     #[hd(min = 0.0, max = 10.0, levels = 16)]
+//!     petal_length: f64,
+//!     species: String,
+//!     #[hd(skip)]
+//!     id: u64,
+//! }
+//! ```
+//!
+//! Numeric fields are scalars and need a `min` and a `max`; every other field is a category identified by its `Display`
+//! representation. `#[hd(categorical)]` encodes a numeric field as a category instead.
+//!
+//! `#[derive(HdDecode)]` goes the other way: `SchemaEncoder::decode` unbinds every role from a record, cleans the result
+//! up against the field's levels or categories, and rebuilds the struct, parsing categories with `FromStr` and filling
+//! skipped fields with their `Default`.
+
+use std::collections::HashMap;
+use rand::Rng;
+use sprs::CsVec;
+
+#[cfg(feature = "derive")]
+pub use ovsa_derive::{HdDecode, HdEncode};
+
+use crate::binary::{bind_bundle_with_rng, similarity, xor};
+use crate::encode::{EncodedRecord, LevelEncoder, RecordField, levels_with_rng};
+use crate::errors::OVSAError;
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+
+
+/// How a field is encoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    /// A value in `[min, max]` mapped onto one of `levels` level vectors, so that close values get similar vectors.
+    Scalar { min: f64, max: f64, levels: usize },
+    /// A value mapped onto a random symbol of its own.
+    Categorical,
+}
+
+
+/// The description of one field of a schema.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldSpec {
+    /// The name of the field, the label of its role.
+    pub name: &'static str,
+    pub kind: FieldKind,
+}
+
+
+/// The value of one field, in schema order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Scalar(f64),
+    Category(String),
+}
+
+
+/// A type whose values can be encoded as records, usually implemented with `#[derive(HdEncode)]`.
+pub trait HdEncode {
+    /// Returns the fields of the type.
+    fn schema() -> Vec<FieldSpec>;
+
+    /// Returns the values of the fields, in the order of `schema`.
+    fn field_values(&self) -> Vec<FieldValue>;
+}
+
+
+/// A type whose values can be rebuilt from decoded field values, usually implemented with `#[derive(HdDecode)]`.
+pub trait HdDecode: HdEncode + Sized {
+    /// Builds a value from the values of its fields.
+    /// # Arguments
+    /// * `values` - The field values, in the order of `schema`.
+    /// # Returns
+    /// The value, or an error if a value does not fit its field.
+    fn from_field_values(values: Vec<FieldValue>) -> Result<Self, OVSAError>;
+}
+
+
+/// Returns the error of a field value that is missing or does not fit its field, for `HdDecode` implementations.
+/// # Arguments
+/// * `name` - The name of the field.
+pub fn mismatched_field(name: &str) -> OVSAError {
+    OVSAError::InvalidFormat(format!("the decoded value of field {name} does not fit the field"))
+}
+
+
+/// One field recovered from a record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedField {
+    /// The name of the field.
+    pub name: &'static str,
+    /// The recovered value: the centre of the best level for scalars, the best category otherwise.
+    pub value: FieldValue,
+    /// The similarity between the unbound role and the chosen level or category. Fields that were not bound into the
+    /// record score at chance level, about 0.5 for half-dense vectors.
+    pub confidence: f64,
+}
+
+
+/// A value decoded from a record, with the confidence of every field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoded<T> {
+    pub value: T,
+    /// The decoded fields, in schema order.
+    pub fields: Vec<DecodedField>,
+}
+
+
+impl<T> Decoded<T> {
+    /// Returns the lowest field confidence, or 1 for a value without fields.
+    pub fn min_confidence(&self) -> f64 {
+        self.fields.iter().map(|field| field.confidence).fold(1.0, f64::min)
+    }
+}
+
+
+/// Encodes values of `HdEncode` types, generating the role, level and category symbols on first use. Symbols are
+/// shared by all types encoded with the same encoder, so fields of the same name share their role. Scalar fields of
+/// the same name share their levels only if their ranges and numbers of levels agree.
+#[derive(Debug, Clone)]
+pub struct SchemaEncoder {
+    n_active: usize,
+    roles: ItemMemory,
+    categories: ItemMemory,
+    scalars: HashMap<ScalarKey, LevelEncoder>,
+}
+
+
+/// The name, range bounds (as bits) and number of levels of a scalar field.
+type ScalarKey = (&'static str, u64, u64, usize);
+
+
+fn scalar_key(name: &'static str, min: f64, max: f64, levels: usize) -> ScalarKey {
+    (name, min.to_bits(), max.to_bits(), levels)
+}
+
+
+impl SchemaEncoder {
+    /// Creates an encoder without symbols.
+    /// # Arguments
+    /// * `dimension` - The dimension of the records.
+    /// * `n_active` - The number of active entries of every symbol; about half the dimension suits bundling.
+    /// # Returns
+    /// A new `SchemaEncoder`.
+    pub fn new(dimension: usize, n_active: usize) -> Result<Self, OVSAError> {
+        if n_active == 0 {
+            return Err(OVSAError::ZeroActiveElements);
+        }
+        if n_active > dimension {
+            return Err(OVSAError::TooManyActiveElements);
+        }
+        Ok(SchemaEncoder { n_active, roles: ItemMemory::new(dimension)?, categories: ItemMemory::new(dimension)?, scalars: HashMap::new() })
+    }
+
+    /// Returns the dimension of the records.
+    pub fn dimension(&self) -> usize {
+        self.roles.dimension()
+    }
+
+    /// Returns the role symbols, labelled by field name.
+    pub fn roles(&self) -> &ItemMemory {
+        &self.roles
+    }
+
+    /// Returns the category symbols, labelled `<field>=<value>`.
+    pub fn categories(&self) -> &ItemMemory {
+        &self.categories
+    }
+
+    /// Encodes a value as the record of its fields.
+    /// # Arguments
+    /// * `value` - The value to encode.
+    /// # Returns
+    /// The `EncodedRecord` with one field per schema field.
+    pub fn encode<T: HdEncode>(&mut self, value: &T) -> Result<EncodedRecord, OVSAError> {
+        with_global_rng(|rng| self.encode_with_rng(value, rng))
+    }
+
+    /// Encodes a value as the record of its fields using the provided random number generator.
+    /// # Arguments
+    /// * `value` - The value to encode.
+    /// * `rng` - The random number generator drawing new symbols and breaking bundling ties.
+    /// # Returns
+    /// The `EncodedRecord` with one field per schema field.
+    pub fn encode_with_rng<T: HdEncode, R: Rng + ?Sized>(&mut self, value: &T, rng: &mut R) -> Result<EncodedRecord, OVSAError> {
+        let schema = T::schema();
+        let values = value.field_values();
+        if schema.len() != values.len() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        if schema.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let mut fields = Vec::with_capacity(schema.len());
+        for (spec, value) in schema.iter().zip(values) {
+            let role_vector = self.roles.symbol_with_rng(spec.name, self.n_active, rng)?.clone();
+            let filler = self.filler(spec, value, rng)?;
+            fields.push(RecordField { role: spec.name.to_string(), role_vector, filler });
+        }
+
+        let pairs: Vec<(&CsVec<i8>, &CsVec<i8>)> = fields.iter().map(|field| (&field.role_vector, &field.filler)).collect();
+        let vector = bind_bundle_with_rng(&pairs, rng)?;
+        Ok(EncodedRecord { vector, fields })
+    }
+
+    /// Decodes a record back into a value by unbinding every role and cleaning the result up against the field's levels
+    /// or the categories seen for the field. Only symbols created by earlier encodings can be recovered.
+    /// # Arguments
+    /// * `vector` - The record vector, e.g. `EncodedRecord::vector`.
+    /// # Returns
+    /// The `Decoded` value with per-field confidences, or `InvalidArgument` if a field has no symbols yet.
+    pub fn decode<T: HdDecode>(&self, vector: &CsVec<i8>) -> Result<Decoded<T>, OVSAError> {
+        if vector.dim() != self.dimension() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let mut fields = Vec::new();
+        for spec in T::schema() {
+            let role = self.roles.get(spec.name).ok_or_else(|| unknown_field(spec.name))?;
+            let unbound = xor(vector, role)?;
+            let (value, confidence) = match spec.kind {
+                FieldKind::Scalar { min, max, levels } => {
+                    let levels = self.scalars.get(&scalar_key(spec.name, min, max, levels)).ok_or_else(|| unknown_field(spec.name))?.levels();
+                    let (index, confidence) = best_match(levels.iter().enumerate(), &unbound)?.ok_or_else(|| unknown_field(spec.name))?;
+                    let fraction = if levels.len() > 1 { index as f64 / (levels.len() - 1) as f64 } else { 0.0 };
+                    (FieldValue::Scalar(min + fraction * (max - min)), confidence)
+                }
+                FieldKind::Categorical => {
+                    let prefix = format!("{}=", spec.name);
+                    let candidates = self.categories.iter().filter_map(|(label, vector)| label.strip_prefix(prefix.as_str()).map(|value| (value, vector)));
+                    let (value, confidence) = best_match(candidates, &unbound)?.ok_or_else(|| unknown_field(spec.name))?;
+                    (FieldValue::Category(value.to_string()), confidence)
+                }
+            };
+            fields.push(DecodedField { name: spec.name, value, confidence });
+        }
+
+        let value = T::from_field_values(fields.iter().map(|field| field.value.clone()).collect())?;
+        Ok(Decoded { value, fields })
+    }
+
+    fn filler<R: Rng + ?Sized>(&mut self, spec: &FieldSpec, value: FieldValue, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        match (spec.kind, value) {
+            (FieldKind::Scalar { min, max, levels }, FieldValue::Scalar(value)) => {
+                let key = scalar_key(spec.name, min, max, levels);
+                if !self.scalars.contains_key(&key) {
+                    let vectors = levels_with_rng(self.dimension(), self.n_active, levels, rng)?;
+                    self.scalars.insert(key, LevelEncoder::new(min, max, vectors)?);
+                }
+                Ok(self.scalars[&key].encode(value).clone())
+            }
+            (FieldKind::Categorical, FieldValue::Category(value)) => {
+                Ok(self.categories.symbol_with_rng(&format!("{}={}", spec.name, value), self.n_active, rng)?.clone())
+            }
+            _ => Err(OVSAError::InvalidArgument(format!("the value of field {} does not match its kind", spec.name))),
+        }
+    }
+}
+
+
+/// Returns the key of the candidate most similar to the query, ties going to the first.
+fn best_match<'a, K>(candidates: impl Iterator<Item = (K, &'a CsVec<i8>)>, query: &CsVec<i8>) -> Result<Option<(K, f64)>, OVSAError> {
+    let mut best: Option<(K, f64)> = None;
+    for (key, candidate) in candidates {
+        let score = similarity(query, candidate)?;
+        if best.as_ref().is_none_or(|(_, best_score)| score > *best_score) {
+            best = Some((key, score));
+        }
+    }
+    Ok(best)
+}
+
+
+fn unknown_field(name: &str) -> OVSAError {
+    OVSAError::InvalidArgument(format!("field {name} has no symbols, encode a value before decoding"))
+}
@@ -0,0 +1,103 @@
+//! Encoding of sparse feature lists, such as binned mass spectra or bags of features: every (index, intensity) entry
+//! binds the symbol of its index with the level vector of its intensity, and the entries are bundled. Only the listed
+//! entries are touched, so inputs with millions of possible indices are encoded without densifying them.
+
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::bind_bundle_with_rng;
+use crate::encode::{LevelEncoder, levels_with_rng};
+use crate::errors::OVSAError;
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+
+
+/// Encodes (index, intensity) feature lists.
+#[derive(Debug, Clone)]
+pub struct SparseFeatureEncoder {
+    n_active: usize,
+    indices: ItemMemory,
+    intensities: LevelEncoder,
+}
+
+
+impl SparseFeatureEncoder {
+    /// Creates a sparse feature encoder without index symbols.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vectors.
+    /// * `n_active` - The number of active entries of every symbol; about half the dimension suits bundling.
+    /// * `max_intensity` - The intensity mapped to the highest level; intensities are clamped to `[0, max_intensity]`.
+    /// * `n_levels` - The number of intensity levels.
+    /// # Returns
+    /// A new `SparseFeatureEncoder`.
+    pub fn new(dimension: usize, n_active: usize, max_intensity: f64, n_levels: usize) -> Result<Self, OVSAError> {
+        with_global_rng(|rng| Self::new_with_rng(dimension, n_active, max_intensity, n_levels, rng))
+    }
+
+    /// Creates a sparse feature encoder, drawing the intensity levels with the provided random number generator.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vectors.
+    /// * `n_active` - The number of active entries of every symbol; about half the dimension suits bundling.
+    /// * `max_intensity` - The intensity mapped to the highest level; intensities are clamped to `[0, max_intensity]`.
+    /// * `n_levels` - The number of intensity levels.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// A new `SparseFeatureEncoder`.
+    pub fn new_with_rng<R: Rng + ?Sized>(dimension: usize, n_active: usize, max_intensity: f64, n_levels: usize, rng: &mut R) -> Result<Self, OVSAError> {
+        if !(max_intensity.is_finite() && max_intensity > 0.0) {
+            return Err(OVSAError::InvalidArgument(format!("the maximum intensity must be positive, got {}", max_intensity)));
+        }
+
+        let intensities = LevelEncoder::new(0.0, max_intensity, levels_with_rng(dimension, n_active, n_levels, rng)?)?;
+        Ok(SparseFeatureEncoder { n_active, indices: ItemMemory::new(dimension)?, intensities })
+    }
+
+    /// Returns the dimension of the vectors.
+    pub fn dimension(&self) -> usize {
+        self.indices.dimension()
+    }
+
+    /// Returns the index symbols created so far, labelled by the decimal index.
+    pub fn indices(&self) -> &ItemMemory {
+        &self.indices
+    }
+
+    /// Returns the intensity levels.
+    pub fn intensities(&self) -> &LevelEncoder {
+        &self.intensities
+    }
+
+    /// Encodes a feature list.
+    /// # Arguments
+    /// * `features` - The (index, intensity) entries; absent indices are implicitly zero and do not contribute.
+    /// # Returns
+    /// A sparse binary vector bundling every index bound to its intensity level.
+    pub fn encode(&mut self, features: &[(usize, f64)]) -> Result<CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.encode_with_rng(features, rng))
+    }
+
+    /// Encodes a feature list using the provided random number generator.
+    /// # Arguments
+    /// * `features` - The (index, intensity) entries; absent indices are implicitly zero and do not contribute.
+    /// * `rng` - The random number generator drawing new index symbols and breaking bundling ties.
+    /// # Returns
+    /// A sparse binary vector bundling every index bound to its intensity level.
+    pub fn encode_with_rng<R: Rng + ?Sized>(&mut self, features: &[(usize, f64)], rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        if features.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+        if let Some(&(index, intensity)) = features.iter().find(|(_, intensity)| intensity.is_nan()) {
+            return Err(OVSAError::InvalidArgument(format!("intensity {} of feature {} is not a number", intensity, index)));
+        }
+
+        let mut symbols = Vec::with_capacity(features.len());
+        for &(index, _) in features {
+            symbols.push(self.indices.symbol_with_rng(&index.to_string(), self.n_active, rng)?.clone());
+        }
+        let pairs: Vec<(&CsVec<i8>, &CsVec<i8>)> = symbols.iter()
+            .zip(features)
+            .map(|(symbol, &(_, intensity))| (symbol, self.intensities.encode(intensity)))
+            .collect();
+        bind_bundle_with_rng(&pairs, rng)
+    }
+}
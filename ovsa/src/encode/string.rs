@@ -0,0 +1,172 @@
+//! Similarity-preserving encoding of short strings: strings sharing characters at nearby positions get similar vectors,
+//! so a typo, an insertion or a swap of neighbouring characters moves a string only a little. This allows tolerant
+//! lookup of labels, e.g. finding `"colour"` in an item memory indexed with `"color"`.
+//!
+//! Every character is bound to the vector of its position, and position vectors drift slowly: position `i + 1` is
+//! position `i` with a share of its active entries moved, so characters shifted by an insertion still match their
+//! original binding partially. Bigrams, bound without position, keep the local order of the characters.
+
+use rand::Rng;
+use rand::seq::index::sample;
+use sprs::CsVec;
+
+use crate::binary::{consensus_sum_with_rng, cyclic_shift, from_indices, sparse_random_with_rng, xor};
+use crate::errors::OVSAError;
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+
+
+/// Encodes strings so that their similarity follows their character-level similarity.
+#[derive(Debug, Clone)]
+pub struct StringEncoder {
+    n_active: usize,
+    tolerance: usize,
+    characters: ItemMemory,
+    positions: Vec<CsVec<i8>>,
+}
+
+
+impl StringEncoder {
+    /// Creates a string encoder without symbols.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vectors.
+    /// * `n_active` - The number of active entries of every symbol; about half the dimension suits bundling.
+    /// * `tolerance` - The shift, in characters, after which a position vector is unrelated to the original one.
+    /// # Returns
+    /// A new `StringEncoder`.
+    pub fn new(dimension: usize, n_active: usize, tolerance: usize) -> Result<Self, OVSAError> {
+        if n_active == 0 {
+            return Err(OVSAError::ZeroActiveElements);
+        }
+        if n_active > dimension {
+            return Err(OVSAError::TooManyActiveElements);
+        }
+        if tolerance == 0 {
+            return Err(OVSAError::InvalidArgument("the shift tolerance must be at least 1".to_string()));
+        }
+
+        Ok(StringEncoder { n_active, tolerance, characters: ItemMemory::new(dimension)?, positions: Vec::new() })
+    }
+
+    /// Returns the dimension of the vectors.
+    pub fn dimension(&self) -> usize {
+        self.characters.dimension()
+    }
+
+    /// Returns the character symbols created so far.
+    pub fn characters(&self) -> &ItemMemory {
+        &self.characters
+    }
+
+    /// Encodes a string.
+    /// # Arguments
+    /// * `text` - The string to encode.
+    /// # Returns
+    /// A sparse binary vector representing the string.
+    pub fn encode(&mut self, text: &str) -> Result<CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.encode_with_rng(text, rng))
+    }
+
+    /// Encodes a string using the provided random number generator.
+    /// # Arguments
+    /// * `text` - The string to encode.
+    /// * `rng` - The random number generator drawing new symbols and breaking bundling ties.
+    /// # Returns
+    /// A sparse binary vector representing the string.
+    pub fn encode_with_rng<R: Rng + ?Sized>(&mut self, text: &str, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        let chars: Vec<String> = text.chars().map(|c| c.to_string()).collect();
+        if chars.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let mut symbols = Vec::with_capacity(chars.len());
+        for c in &chars {
+            symbols.push(self.characters.symbol_with_rng(c, self.n_active, rng)?.clone());
+        }
+        self.extend_positions(chars.len(), rng)?;
+
+        let mut parts = Vec::with_capacity(2 * chars.len());
+        for (symbol, position) in symbols.iter().zip(&self.positions) {
+            parts.push(xor(symbol, position)?);
+        }
+        for pair in symbols.windows(2) {
+            parts.push(xor(&cyclic_shift(&pair[0], 1), &pair[1])?);
+        }
+
+        consensus_sum_with_rng(&parts, rng)
+    }
+
+    /// Encodes labels into an item memory for tolerant lookup with `find`.
+    /// # Arguments
+    /// * `labels` - The labels to index.
+    /// # Returns
+    /// An `ItemMemory` mapping every label to its string encoding.
+    pub fn index(&mut self, labels: &[&str]) -> Result<ItemMemory, OVSAError> {
+        with_global_rng(|rng| self.index_with_rng(labels, rng))
+    }
+
+    /// Encodes labels into an item memory for tolerant lookup using the provided random number generator.
+    /// # Arguments
+    /// * `labels` - The labels to index.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// An `ItemMemory` mapping every label to its string encoding.
+    pub fn index_with_rng<R: Rng + ?Sized>(&mut self, labels: &[&str], rng: &mut R) -> Result<ItemMemory, OVSAError> {
+        let mut index = ItemMemory::new(self.dimension())?;
+        for label in labels {
+            let vector = self.encode_with_rng(label, rng)?;
+            index.insert(label, vector)?;
+        }
+        Ok(index)
+    }
+
+    /// Finds the labels of an index closest to a possibly misspelt string.
+    /// # Arguments
+    /// * `index` - The index built by `index` with this encoder.
+    /// * `text` - The string to look up.
+    /// * `k` - The maximum number of results.
+    /// # Returns
+    /// The (label, similarity) pairs sorted by decreasing similarity.
+    pub fn find(&mut self, index: &ItemMemory, text: &str, k: usize) -> Result<Vec<(String, f64)>, OVSAError> {
+        with_global_rng(|rng| self.find_with_rng(index, text, k, rng))
+    }
+
+    /// Finds the labels of an index closest to a possibly misspelt string using the provided random number generator.
+    /// # Arguments
+    /// * `index` - The index built by `index` with this encoder.
+    /// * `text` - The string to look up.
+    /// * `k` - The maximum number of results.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// The (label, similarity) pairs sorted by decreasing similarity.
+    pub fn find_with_rng<R: Rng + ?Sized>(&mut self, index: &ItemMemory, text: &str, k: usize, rng: &mut R) -> Result<Vec<(String, f64)>, OVSAError> {
+        let query = self.encode_with_rng(text, rng)?;
+        index.query(&query, k)
+    }
+
+    /// Adds position vectors up to the given length, each moving `n_active / tolerance` entries of the previous one.
+    fn extend_positions<R: Rng + ?Sized>(&mut self, length: usize, rng: &mut R) -> Result<(), OVSAError> {
+        let dimension = self.dimension();
+        if self.positions.is_empty() {
+            self.positions.push(sparse_random_with_rng(dimension, self.n_active, rng)?);
+        }
+        let n_moved = self.n_active.div_ceil(self.tolerance).min(dimension - self.n_active);
+
+        while self.positions.len() < length {
+            let previous = self.positions.last().unwrap();
+            let mut active = vec![false; dimension];
+            previous.indices().iter().for_each(|&index| active[index] = true);
+            let mut indices = previous.indices().to_vec();
+            for position in sample(rng, indices.len(), n_moved).into_vec() {
+                let mut candidate = rng.random_range(0..dimension);
+                while active[candidate] {
+                    candidate = rng.random_range(0..dimension);
+                }
+                active[candidate] = true;
+                indices[position] = candidate;
+            }
+            self.positions.push(from_indices(dimension, &indices)?);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+//! Text encoding with a pluggable tokenizer: a text is split into tokens, consecutive tokens are combined into
+//! n-grams as in `encode::ngrams`, and the n-grams are bundled. Tokens get their own symbols, or, once hashing is
+//! enabled, unseen tokens share a fixed set of bucket symbols so that open-vocabulary text does not grow the codebook.
+//!
+//! Plain bundling counts every occurrence of an n-gram, so frequent but uninformative n-grams dominate a document.
+//! `Weighting` bundles every distinct n-gram once instead, weighted by its damped term frequency and optionally by an
+//! inverse document frequency, e.g. from `TextEncoder::idf_weights`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::{consensus_sum_with_rng, cyclic_shift, weighted_consensus_sum_with_rng, xor};
+use crate::errors::OVSAError;
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+use crate::sketch::fnv1a;
+
+
+/// A user-provided tokenizer.
+pub type TokenizeFn = Arc<dyn Fn(&str) -> Vec<String> + Send + Sync>;
+
+
+/// Splits texts into tokens.
+#[derive(Clone)]
+pub enum Tokenizer {
+    /// One token per byte of the UTF-8 encoding, written as `0x` and two hex digits.
+    Bytes,
+    /// One token per unicode scalar value.
+    Chars,
+    /// One token per run of non-whitespace characters.
+    Whitespace,
+    /// A user-provided function.
+    Custom(TokenizeFn),
+}
+
+
+impl Tokenizer {
+    /// Creates a tokenizer from a closure.
+    /// # Arguments
+    /// * `tokenize` - The function splitting a text into tokens.
+    pub fn custom(tokenize: impl Fn(&str) -> Vec<String> + Send + Sync + 'static) -> Self {
+        Tokenizer::Custom(Arc::new(tokenize))
+    }
+
+    /// Splits a text into tokens.
+    /// # Arguments
+    /// * `text` - The text to split.
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        match self {
+            Tokenizer::Bytes => text.bytes().map(|byte| format!("0x{:02x}", byte)).collect(),
+            Tokenizer::Chars => text.chars().map(|c| c.to_string()).collect(),
+            Tokenizer::Whitespace => text.split_whitespace().map(str::to_string).collect(),
+            Tokenizer::Custom(tokenize) => tokenize(text),
+        }
+    }
+}
+
+
+impl fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tokenizer::Bytes => write!(f, "Bytes"),
+            Tokenizer::Chars => write!(f, "Chars"),
+            Tokenizer::Whitespace => write!(f, "Whitespace"),
+            Tokenizer::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+
+/// How the n-grams of a text are weighted when bundled. N-grams are identified by their tokens joined with spaces,
+/// as returned by `TextEncoder::ngram_keys`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Weighting {
+    /// Every occurrence counts once.
+    #[default]
+    Uniform,
+    /// Every distinct n-gram is weighted by `1 + ln(tf)`, its term frequency `tf` in the text being damped.
+    TermFrequency,
+    /// `TermFrequency` times the given weight of the n-gram, typically its inverse document frequency. N-grams without
+    /// a weight get the default weight.
+    TfIdf { weights: HashMap<String, f64>, default: f64 },
+}
+
+
+/// Encodes texts as the consensus sum of their token n-grams.
+#[derive(Debug, Clone)]
+pub struct TextEncoder {
+    tokenizer: Tokenizer,
+    weighting: Weighting,
+    n: usize,
+    n_active: usize,
+    codebook: ItemMemory,
+    buckets: Option<ItemMemory>,
+    n_buckets: usize,
+}
+
+
+impl TextEncoder {
+    /// Creates a text encoder whose codebook grows with every new token.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vectors.
+    /// * `n` - The n-gram size, in tokens.
+    /// * `n_active` - The number of active entries of every symbol; about half the dimension suits bundling.
+    /// * `tokenizer` - The tokenizer.
+    /// # Returns
+    /// A new `TextEncoder`.
+    pub fn new(dimension: usize, n: usize, n_active: usize, tokenizer: Tokenizer) -> Result<Self, OVSAError> {
+        if n == 0 {
+            return Err(OVSAError::InvalidArgument("the n-gram size must be at least 1, got 0".to_string()));
+        }
+        if n_active == 0 {
+            return Err(OVSAError::ZeroActiveElements);
+        }
+        if n_active > dimension {
+            return Err(OVSAError::TooManyActiveElements);
+        }
+
+        Ok(TextEncoder { tokenizer, weighting: Weighting::Uniform, n, n_active, codebook: ItemMemory::new(dimension)?, buckets: None, n_buckets: 0 })
+    }
+
+    /// Hashes tokens missing from the codebook into a fixed number of bucket symbols instead of adding them. Tokens
+    /// sharing a bucket are indistinguishable, so the number of buckets trades memory for collisions.
+    /// # Arguments
+    /// * `n_buckets` - The number of bucket symbols, at least 1.
+    /// # Returns
+    /// The encoder with hashing enabled.
+    pub fn with_hashing(mut self, n_buckets: usize) -> Result<Self, OVSAError> {
+        if n_buckets == 0 {
+            return Err(OVSAError::InvalidArgument("hashing needs at least one bucket".to_string()));
+        }
+
+        self.buckets = Some(ItemMemory::new(self.dimension())?);
+        self.n_buckets = n_buckets;
+        Ok(self)
+    }
+
+    /// Sets how the n-grams of a text are weighted when bundled.
+    /// # Arguments
+    /// * `weighting` - The weighting.
+    /// # Returns
+    /// The encoder with the weighting.
+    pub fn with_weighting(mut self, weighting: Weighting) -> Self {
+        self.weighting = weighting;
+        self
+    }
+
+    /// Returns the weighting of the n-grams.
+    pub fn weighting(&self) -> &Weighting {
+        &self.weighting
+    }
+
+    /// Returns the dimension of the vectors.
+    pub fn dimension(&self) -> usize {
+        self.codebook.dimension()
+    }
+
+    /// Returns the tokenizer.
+    pub fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    /// Returns the token symbols.
+    pub fn codebook(&self) -> &ItemMemory {
+        &self.codebook
+    }
+
+    /// Returns the bucket symbols created so far, labelled `#<bucket>`, or `None` without hashing.
+    pub fn buckets(&self) -> Option<&ItemMemory> {
+        self.buckets.as_ref()
+    }
+
+    /// Adds tokens to the codebook, e.g. a known vocabulary that should keep symbols of its own when hashing.
+    /// # Arguments
+    /// * `tokens` - The tokens.
+    pub fn add_tokens(&mut self, tokens: &[&str]) -> Result<(), OVSAError> {
+        with_global_rng(|rng| self.add_tokens_with_rng(tokens, rng))
+    }
+
+    /// Adds tokens to the codebook using the provided random number generator.
+    /// # Arguments
+    /// * `tokens` - The tokens.
+    /// * `rng` - The random number generator.
+    pub fn add_tokens_with_rng<R: Rng + ?Sized>(&mut self, tokens: &[&str], rng: &mut R) -> Result<(), OVSAError> {
+        for token in tokens {
+            self.codebook.symbol_with_rng(token, self.n_active, rng)?;
+        }
+        Ok(())
+    }
+
+    /// Encodes a text.
+    /// # Arguments
+    /// * `text` - The text, at least `n` tokens long.
+    /// # Returns
+    /// A sparse binary vector representing the text.
+    pub fn encode(&mut self, text: &str) -> Result<CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.encode_with_rng(text, rng))
+    }
+
+    /// Encodes a text using the provided random number generator.
+    /// # Arguments
+    /// * `text` - The text, at least `n` tokens long.
+    /// * `rng` - The random number generator drawing new symbols and breaking bundling ties.
+    /// # Returns
+    /// A sparse binary vector representing the text.
+    pub fn encode_with_rng<R: Rng + ?Sized>(&mut self, text: &str, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        let grams = self.grams(text, rng)?;
+        if self.weighting == Weighting::Uniform {
+            return consensus_sum_with_rng(&grams, rng);
+        }
+
+        // distinct n-grams in order of first occurrence, with their term frequencies
+        let keys = self.ngram_keys(text);
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        let mut distinct: Vec<(&str, &CsVec<i8>, usize)> = Vec::new();
+        for (key, gram) in keys.iter().zip(&grams) {
+            match positions.get(key.as_str()) {
+                Some(&position) => distinct[position].2 += 1,
+                None => {
+                    positions.insert(key, distinct.len());
+                    distinct.push((key, gram, 1));
+                }
+            }
+        }
+
+        let weights: Vec<f64> = distinct.iter()
+            .map(|&(key, _, tf)| {
+                let damped = 1.0 + (tf as f64).ln();
+                match &self.weighting {
+                    Weighting::TfIdf { weights, default } => damped * weights.get(key).copied().unwrap_or(*default),
+                    _ => damped,
+                }
+            })
+            .collect();
+        let vectors: Vec<CsVec<i8>> = distinct.into_iter().map(|(_, gram, _)| gram.clone()).collect();
+        weighted_consensus_sum_with_rng(&vectors, &weights, rng)
+    }
+
+    /// Returns the keys of the n-grams of a text, their tokens joined with spaces, in order of occurrence.
+    /// # Arguments
+    /// * `text` - The text.
+    pub fn ngram_keys(&self, text: &str) -> Vec<String> {
+        let tokens = self.tokenizer.tokenize(text);
+        tokens.windows(self.n).map(|window| window.join(" ")).collect()
+    }
+
+    /// Computes smoothed inverse document frequencies, `1 + ln((1 + N) / (1 + df))`, of the n-grams of a corpus of
+    /// `N` documents, for `Weighting::TfIdf`.
+    /// # Arguments
+    /// * `corpus` - The documents.
+    /// # Returns
+    /// The weight of every n-gram occurring in the corpus.
+    pub fn idf_weights(&self, corpus: &[&str]) -> HashMap<String, f64> {
+        let mut frequencies: HashMap<String, usize> = HashMap::new();
+        for document in corpus {
+            let mut keys = self.ngram_keys(document);
+            keys.sort_unstable();
+            keys.dedup();
+            for key in keys {
+                *frequencies.entry(key).or_insert(0) += 1;
+            }
+        }
+
+        let n_documents = corpus.len() as f64;
+        frequencies.into_iter().map(|(key, df)| (key, 1.0 + ((1.0 + n_documents) / (1.0 + df as f64)).ln())).collect()
+    }
+
+    /// Returns the n-gram vectors of a text, each the XOR of its token symbols shifted by their distance to the end.
+    fn grams<R: Rng + ?Sized>(&mut self, text: &str, rng: &mut R) -> Result<Vec<CsVec<i8>>, OVSAError> {
+        let tokens = self.tokenizer.tokenize(text);
+        if tokens.len() < self.n {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let symbols = tokens.iter().map(|token| self.symbol(token, rng)).collect::<Result<Vec<_>, OVSAError>>()?;
+        let mut grams = Vec::with_capacity(symbols.len() + 1 - self.n);
+        for window in symbols.windows(self.n) {
+            let mut gram = cyclic_shift(&window[0], self.n as isize - 1);
+            for (offset, symbol) in window.iter().enumerate().skip(1) {
+                gram = xor(&gram, &cyclic_shift(symbol, (self.n - 1 - offset) as isize))?;
+            }
+            grams.push(gram);
+        }
+        Ok(grams)
+    }
+
+    /// Returns the symbol of a token, from the codebook, its bucket, or a new codebook entry.
+    fn symbol<R: Rng + ?Sized>(&mut self, token: &str, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        if let Some(symbol) = self.codebook.get(token) {
+            return Ok(symbol.clone());
+        }
+        match &mut self.buckets {
+            Some(buckets) => {
+                let bucket = fnv1a(token.as_bytes()) % self.n_buckets as u64;
+                Ok(buckets.symbol_with_rng(&format!("#{}", bucket), self.n_active, rng)?.clone())
+            }
+            None => Ok(self.codebook.symbol_with_rng(token, self.n_active, rng)?.clone()),
+        }
+    }
+}
@@ -7,4 +7,14 @@ pub enum OVSAError {
     ZeroActiveElements,
     ZeroDimension,
     TooManyActiveElements,
+    ZeroShards,
+    GenerationFailed,
+    CounterUnderflow,
+    CounterOverflow,
+    BudgetExceeded,
+    Cancelled,
+    Io(String),
+    InvalidArgument(String),
+    InvalidFormat(String),
+    IncompatibleArtifact(String),
 }
@@ -0,0 +1,332 @@
+//! Reproducible experiments described by a JSON config, so that a result can be shared as the file that produced it
+//! and rerun to check it against later versions of the crate:
+//!
+//! ```json
+//! {
+//!     "name": "iris",
+//!     "seed": 7,
+//!     "dataset": {"kind": "numeric", "train": [[[5.1, 3.5, 1.4, 0.2], "setosa"], ...]},
+//!     "encoder": {"kind": "features", "levels": 16},
+//!     "model": {"dimension": 10000, "density": 0.5},
+//!     "training": {"epochs": 5, "test_fraction": 0.2},
+//!     "output": "results/iris"
+//! }
+//! ```
+//!
+//! `run` loads the dataset, splits it if it has no test samples, encodes it, fits a `CentroidClassifier`, retrains it
+//! for the configured number of epochs and evaluates it on the test samples. Every stage draws from its own random
+//! stream of the seed, so a config always produces the same report. With an `output` folder, the config, the report,
+//! the codebook and the classifier are written to `config.json`, `report.json`, `codebook.json` and
+//! `classifier.json`; the encoder parameters are recorded in the metadata of the artifacts.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sprs::CsVec;
+
+use crate::binary::bind_bundle_with_rng;
+use crate::encode::{LevelEncoder, levels_with_rng, ngrams_with_rng};
+use crate::errors::OVSAError;
+use crate::io::{ArtifactKind, Metadata, save_classifier_with_metadata, save_memory_with_metadata};
+use crate::learn::evaluation::{ClassMetrics, Evaluation, Split, evaluate, train_test_split_with_rng};
+use crate::learn::{CentroidClassifier, Samples};
+use crate::memory::ItemMemory;
+use crate::rng::stream_rng;
+use crate::trace::span;
+
+
+/// The description of an experiment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentConfig {
+    /// The name of the experiment, copied to the report.
+    pub name: String,
+    /// The seed of all random decisions.
+    #[serde(default)]
+    pub seed: u64,
+    pub dataset: DatasetConfig,
+    pub encoder: EncoderConfig,
+    #[serde(default)]
+    pub model: ModelConfig,
+    #[serde(default)]
+    pub training: TrainingConfig,
+    /// The folder the artifacts are written to, none to only return them.
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+}
+
+
+impl ExperimentConfig {
+    /// Reads a config from JSON.
+    /// # Arguments
+    /// * `json` - The JSON text.
+    /// # Returns
+    /// The config, or `InvalidFormat` if the JSON does not describe one.
+    pub fn from_json(json: &str) -> Result<Self, OVSAError> {
+        serde_json::from_str(json).map_err(|error| OVSAError::InvalidFormat(error.to_string()))
+    }
+
+    /// Reads a config from a JSON file.
+    /// # Arguments
+    /// * `path` - The file to read.
+    /// # Returns
+    /// The config, `Io` if the file cannot be read or `InvalidFormat` if it does not describe one.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, OVSAError> {
+        Self::from_json(&fs::read_to_string(path).map_err(|error| OVSAError::Io(error.to_string()))?)
+    }
+
+    /// Writes the config as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("A config serializes to JSON.")
+    }
+}
+
+
+/// The samples of an experiment, given inline, read from a file or loaded from a benchmark folder.
+/// An empty test set is split off the training samples.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DatasetConfig {
+    /// Labelled numeric feature vectors, encoded with `EncoderConfig::Features`.
+    Numeric {
+        train: Vec<(Vec<f64>, String)>,
+        #[serde(default)]
+        test: Vec<(Vec<f64>, String)>,
+    },
+    /// Labelled texts, encoded with `EncoderConfig::Ngrams`.
+    Text {
+        train: Vec<(String, String)>,
+        #[serde(default)]
+        test: Vec<(String, String)>,
+    },
+    /// A JSON file holding a `numeric` or `text` dataset.
+    File { path: PathBuf },
+    /// The ISOLET folder, see `datasets::load_isolet`.
+    #[cfg(feature = "datasets")]
+    Isolet { dir: PathBuf },
+    /// The HAR folder, see `datasets::load_har`.
+    #[cfg(feature = "datasets")]
+    Har { dir: PathBuf },
+    /// The European languages folder, see `datasets::load_languages`.
+    #[cfg(feature = "datasets")]
+    Languages { dir: PathBuf },
+}
+
+
+impl DatasetConfig {
+    /// Loads the samples of a file or benchmark dataset, returning a `Numeric` or `Text` dataset.
+    fn resolve(&self) -> Result<DatasetConfig, OVSAError> {
+        match self {
+            DatasetConfig::Numeric { .. } | DatasetConfig::Text { .. } => Ok(self.clone()),
+            DatasetConfig::File { path } => {
+                let json = fs::read_to_string(path).map_err(|error| OVSAError::Io(error.to_string()))?;
+                match serde_json::from_str(&json).map_err(|error| OVSAError::InvalidFormat(error.to_string()))? {
+                    dataset @ (DatasetConfig::Numeric { .. } | DatasetConfig::Text { .. }) => Ok(dataset),
+                    _ => Err(OVSAError::InvalidFormat(format!("{} does not hold a numeric or text dataset", path.display()))),
+                }
+            }
+            #[cfg(feature = "datasets")]
+            DatasetConfig::Isolet { dir } => {
+                let dataset = crate::datasets::load_isolet(dir)?;
+                Ok(DatasetConfig::Numeric { train: dataset.train, test: dataset.test })
+            }
+            #[cfg(feature = "datasets")]
+            DatasetConfig::Har { dir } => {
+                let dataset = crate::datasets::load_har(dir)?;
+                Ok(DatasetConfig::Numeric { train: dataset.train, test: dataset.test })
+            }
+            #[cfg(feature = "datasets")]
+            DatasetConfig::Languages { dir } => {
+                let dataset = crate::datasets::load_languages(dir)?;
+                Ok(DatasetConfig::Text { train: dataset.train, test: dataset.test })
+            }
+        }
+    }
+}
+
+
+/// The encoding of the samples.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EncoderConfig {
+    /// Every feature position bound to a role symbol `feature:<position>` and its value to one of `levels` level
+    /// vectors spanning the range of the training values, as `encode::features`.
+    Features { levels: usize },
+    /// The consensus sum of the character n-grams, as `encode::ngrams`.
+    Ngrams { n: usize },
+}
+
+
+/// The vectors of the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelConfig {
+    /// The dimension of the vectors.
+    pub dimension: usize,
+    /// The share of active entries of the symbols, in `(0, 1]`.
+    pub density: f64,
+}
+
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        ModelConfig { dimension: 10000, density: 0.5 }
+    }
+}
+
+
+/// The training of the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrainingConfig {
+    /// The number of retraining epochs after the initial fit, 0 for the plain centroid classifier.
+    pub epochs: usize,
+    /// The share of the training samples held out for testing if the dataset has no test samples.
+    pub test_fraction: f64,
+}
+
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        TrainingConfig { epochs: 0, test_fraction: 0.2 }
+    }
+}
+
+
+/// The metrics of an experiment, written to `report.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentReport {
+    pub name: String,
+    pub seed: u64,
+    /// The version of the crate that ran the experiment.
+    pub crate_version: String,
+    pub n_train: usize,
+    pub n_test: usize,
+    /// The number of corrections of every retraining epoch that ran.
+    pub corrections: Vec<usize>,
+    /// The accuracy on the test samples.
+    pub accuracy: f64,
+    /// The macro F1 score on the test samples.
+    pub macro_f1: f64,
+    /// The metrics of every class, in label order.
+    pub classes: Vec<ClassMetrics>,
+}
+
+
+/// The outcome of `run`: the report and the trained artifacts.
+#[derive(Debug, Clone)]
+pub struct Experiment {
+    pub report: ExperimentReport,
+    pub evaluation: Evaluation,
+    /// The symbols of the encoder: the feature roles and levels, or the characters.
+    pub codebook: ItemMemory,
+    pub classifier: CentroidClassifier,
+}
+
+
+/// Runs an experiment.
+/// # Arguments
+/// * `config` - The description of the experiment.
+/// # Returns
+/// The `Experiment`, `InvalidArgument` if the encoder does not fit the dataset or a parameter is out of range, or `Io`
+/// if the dataset or the artifacts cannot be read or written.
+pub fn run(config: &ExperimentConfig) -> Result<Experiment, OVSAError> {
+    let ModelConfig { dimension, density } = config.model;
+    if !(density > 0.0 && density <= 1.0) {
+        return Err(OVSAError::InvalidArgument(format!("the density must be in (0, 1], got {}", density)));
+    }
+    let n_active = ((density * dimension as f64).round() as usize).max(1);
+    span!(INFO, "experiment", name = config.name.as_str(), dimension);
+
+    let mut codebook = ItemMemory::new(dimension)?;
+    let mut metadata = Metadata::new(ArtifactKind::Memory, dimension).with_seed(config.seed);
+    let (train, test) = match (config.dataset.resolve()?, &config.encoder) {
+        (DatasetConfig::Numeric { train, test }, &EncoderConfig::Features { levels }) => {
+            let (train, test) = split(train, test, config)?;
+            let n_features = train.first().map(|(features, _)| features.len()).ok_or(OVSAError::EmptyVectorList)?;
+            if train.iter().chain(&test).any(|(features, _)| features.len() != n_features) {
+                return Err(OVSAError::VectorSizeMismatch);
+            }
+            let values = train.iter().flat_map(|(features, _)| features.iter().copied());
+            let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)));
+
+            let rng = &mut stream_rng(config.seed, 1);
+            let encoder = LevelEncoder::new(min, max, levels_with_rng(dimension, n_active, levels, rng)?)?;
+            for position in 0..n_features {
+                codebook.symbol_with_rng(&format!("feature:{}", position), n_active, rng)?;
+            }
+            for (index, level) in encoder.levels().iter().enumerate() {
+                codebook.insert(&format!("level:{}", index), level.clone())?;
+            }
+            metadata = metadata.with_encoder_parameter("encoder", "features")
+                .with_encoder_parameter("levels", levels)
+                .with_encoder_parameter("min", min)
+                .with_encoder_parameter("max", max);
+
+            let rng = &mut stream_rng(config.seed, 2);
+            let roles: Vec<&CsVec<i8>> = (0..n_features).filter_map(|position| codebook.get(&format!("feature:{}", position))).collect();
+            let mut encode = |samples: &[(Vec<f64>, String)]| -> Result<Samples, OVSAError> {
+                samples.iter()
+                    .map(|(features, label)| {
+                        let pairs: Vec<(&CsVec<i8>, &CsVec<i8>)> = roles.iter().zip(features).map(|(&role, &value)| (role, encoder.encode(value))).collect();
+                        Ok((bind_bundle_with_rng(&pairs, rng)?, label.clone()))
+                    })
+                    .collect()
+            };
+            (encode(&train)?, encode(&test)?)
+        }
+        (DatasetConfig::Text { train, test }, &EncoderConfig::Ngrams { n }) => {
+            let (train, test) = split(train, test, config)?;
+            metadata = metadata.with_encoder_parameter("encoder", "ngrams").with_encoder_parameter("n", n);
+
+            let rng = &mut stream_rng(config.seed, 2);
+            let mut encode = |samples: &[(String, String)]| -> Result<Samples, OVSAError> {
+                samples.iter()
+                    .map(|(text, label)| Ok((ngrams_with_rng(text, n, &mut codebook, n_active, rng)?, label.clone())))
+                    .collect()
+            };
+            (encode(&train)?, encode(&test)?)
+        }
+        (_, encoder) => return Err(OVSAError::InvalidArgument(format!("the encoder {:?} does not fit the dataset", encoder))),
+    };
+    metadata = metadata.with_encoder_parameter("n_active", n_active);
+
+    let rng = &mut stream_rng(config.seed, 3);
+    let mut classifier = CentroidClassifier::new(dimension)?;
+    classifier.fit_with_rng(&train, rng)?;
+    let corrections = classifier.retrain_with_rng(&train, config.training.epochs, rng)?;
+    let evaluation = evaluate(&classifier, &test)?;
+
+    let report = ExperimentReport {
+        name: config.name.clone(),
+        seed: config.seed,
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        n_train: train.len(),
+        n_test: test.len(),
+        corrections,
+        accuracy: evaluation.accuracy(),
+        macro_f1: evaluation.macro_f1(),
+        classes: evaluation.per_class(),
+    };
+
+    if let Some(output) = &config.output {
+        let write = |name: &str, json: String| fs::write(output.join(name), json).map_err(|error| OVSAError::Io(error.to_string()));
+        fs::create_dir_all(output).map_err(|error| OVSAError::Io(error.to_string()))?;
+        write("config.json", config.to_json())?;
+        write("report.json", serde_json::to_string_pretty(&report).expect("A report serializes to JSON."))?;
+        save_memory_with_metadata(output.join("codebook.json"), &codebook, metadata.clone())?;
+        metadata.kind = ArtifactKind::Classifier;
+        save_classifier_with_metadata(output.join("classifier.json"), &classifier, metadata)?;
+    }
+
+    Ok(Experiment { report, evaluation, codebook, classifier })
+}
+
+
+/// Returns the training and test samples, splitting off the test samples if there are none.
+fn split<T: Clone>(train: Vec<(T, String)>, test: Vec<(T, String)>, config: &ExperimentConfig) -> Result<Split<T>, OVSAError> {
+    if !test.is_empty() {
+        return Ok((train, test));
+    }
+
+    train_test_split_with_rng(&train, config.training.test_fraction, &mut stream_rng(config.seed, 0))
+}
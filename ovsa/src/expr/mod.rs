@@ -0,0 +1,184 @@
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::{from_indices_or_empty, majority_with_rng};
+use crate::errors::OVSAError;
+use crate::rng::with_global_rng;
+
+pub mod structure;
+
+
+/// A deferred computation over sparse binary vectors, built with `expr` and evaluated with `Expr::eval`.
+/// Building the expression only records the operations; evaluation fuses them:
+/// * chained binds become a single n-ary XOR,
+/// * permutations are pushed down to the leaves and applied while reading their indices,
+/// * chained bundles become a single n-ary majority accumulated in one counter pass.
+///
+/// `a.bundle(b).bundle(c)` is the majority of all three vectors: `bundle` appends to a bundle that was itself started
+/// by `bundle`. Any other expression, including a bundle built with `Expr::bundle_all`, is bundled as a single term,
+/// so `Expr::bundle_all([a, b, c]).bundle(d)` is the majority of a bundle and a vector.
+#[derive(Debug, Clone)]
+pub struct Expr<'a> {
+    node: Node<'a>,
+}
+
+
+/// The operations recorded by an `Expr`.
+#[derive(Debug, Clone)]
+enum Node<'a> {
+    /// A leaf vector.
+    Vector(&'a CsVec<i8>),
+    /// The XOR of the terms.
+    Bind(Vec<Expr<'a>>),
+    /// The term cyclically shifted by the given amount.
+    Permute(Box<Expr<'a>>, isize),
+    /// The majority of the terms; `chained` if the bundle was started by `Expr::bundle`, which may append to it.
+    Bundle { terms: Vec<Expr<'a>>, chained: bool },
+}
+
+
+/// Starts an expression from a vector.
+/// # Arguments
+/// * `vec` - The sparse binary vector.
+/// # Returns
+/// An `Expr` evaluating to the vector itself.
+pub fn expr(vec: &CsVec<i8>) -> Expr<'_> {
+    Expr { node: Node::Vector(vec) }
+}
+
+
+impl<'a> From<&'a CsVec<i8>> for Expr<'a> {
+    fn from(vec: &'a CsVec<i8>) -> Self {
+        expr(vec)
+    }
+}
+
+
+impl<'a> Expr<'a> {
+    /// Binds (XOR) all the terms at once.
+    /// # Arguments
+    /// * `terms` - The expressions or vectors to bind.
+    /// # Returns
+    /// An `Expr` whose evaluation fails with `EmptyVectorList` if there are no terms.
+    pub fn bind_all<T: Into<Expr<'a>>>(terms: impl IntoIterator<Item = T>) -> Self {
+        Expr { node: Node::Bind(terms.into_iter().map(Into::into).collect()) }
+    }
+
+    /// Bundles (majority) all the terms at once, as one bundle that later calls to `bundle` do not extend.
+    /// # Arguments
+    /// * `terms` - The expressions or vectors to bundle.
+    /// # Returns
+    /// An `Expr` whose evaluation fails with `EmptyVectorList` if there are no terms.
+    pub fn bundle_all<T: Into<Expr<'a>>>(terms: impl IntoIterator<Item = T>) -> Self {
+        Expr { node: Node::Bundle { terms: terms.into_iter().map(Into::into).collect(), chained: false } }
+    }
+
+    /// Binds (XOR) the expression with another one.
+    pub fn bind(self, other: impl Into<Expr<'a>>) -> Self {
+        match self.node {
+            Node::Bind(mut terms) => {
+                terms.push(other.into());
+                Expr { node: Node::Bind(terms) }
+            }
+            node => Expr { node: Node::Bind(vec![Expr { node }, other.into()]) },
+        }
+    }
+
+    /// Cyclically shifts the expression. Positive values shift to the right, negative values shift to the left.
+    pub fn permute(self, shift_by: isize) -> Self {
+        match self.node {
+            Node::Permute(inner, shift) => Expr { node: Node::Permute(inner, shift + shift_by) },
+            node => Expr { node: Node::Permute(Box::new(Expr { node }), shift_by) },
+        }
+    }
+
+    /// Bundles (majority) the expression with another one. If the expression is a bundle started by `bundle`, the
+    /// other one is added to its terms; otherwise both become the terms of a new bundle.
+    pub fn bundle(self, other: impl Into<Expr<'a>>) -> Self {
+        match self.node {
+            Node::Bundle { mut terms, chained: true } => {
+                terms.push(other.into());
+                Expr { node: Node::Bundle { terms, chained: true } }
+            }
+            node => Expr { node: Node::Bundle { terms: vec![Expr { node }, other.into()], chained: true } },
+        }
+    }
+
+    /// Returns the dimension shared by all vectors of the expression.
+    /// # Returns
+    /// The dimension, `VectorSizeMismatch` if the vectors differ, or `EmptyVectorList` for a bind or bundle without terms.
+    pub fn dimension(&self) -> Result<usize, OVSAError> {
+        match &self.node {
+            Node::Vector(vec) => Ok(vec.dim()),
+            Node::Permute(inner, _) => inner.dimension(),
+            Node::Bind(terms) | Node::Bundle { terms, .. } => {
+                let (first, rest) = terms.split_first().ok_or(OVSAError::EmptyVectorList)?;
+                let dimension = first.dimension()?;
+                for term in rest {
+                    if term.dimension()? != dimension {
+                        return Err(OVSAError::VectorSizeMismatch);
+                    }
+                }
+                Ok(dimension)
+            }
+        }
+    }
+
+    /// Evaluates the expression.
+    /// # Returns
+    /// The resulting sparse binary vector.
+    pub fn eval(&self) -> Result<CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.eval_with_rng(rng))
+    }
+
+    /// Evaluates the expression, breaking bundling ties with the provided random number generator.
+    /// # Arguments
+    /// * `rng` - The random number generator used to break ties.
+    /// # Returns
+    /// The resulting sparse binary vector.
+    pub fn eval_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        let dimension = self.dimension()?;
+        Ok(from_indices_or_empty(dimension, self.active_indices(dimension, 0, rng)))
+    }
+
+    /// Computes the sorted active indices of the expression shifted by `shift`.
+    fn active_indices<R: Rng + ?Sized>(&self, dimension: usize, shift: isize, rng: &mut R) -> Vec<usize> {
+        match &self.node {
+            Node::Vector(vec) => shifted(vec.indices(), dimension, shift),
+            Node::Permute(inner, shift_by) => inner.active_indices(dimension, shift + shift_by, rng),
+            Node::Bind(terms) => {
+                let mut indices: Vec<usize> = terms.iter().flat_map(|term| term.active_indices(dimension, shift, rng)).collect();
+                indices.sort_unstable();
+
+                // an index is active in the XOR of all terms if it is active in an odd number of them
+                let mut result = Vec::with_capacity(indices.len());
+                for run in indices.chunk_by(|a, b| a == b) {
+                    if run.len() % 2 == 1 {
+                        result.push(run[0]);
+                    }
+                }
+                result
+            }
+            Node::Bundle { terms, .. } => {
+                let mut counts = vec![0u32; dimension];
+                for term in terms {
+                    for index in term.active_indices(dimension, shift, rng) {
+                        counts[index] += 1;
+                    }
+                }
+                majority_with_rng(&counts, terms.len(), rng).indices().to_vec()
+            }
+        }
+    }
+}
+
+
+/// Shifts sorted indices cyclically while keeping them sorted: the indices wrapping around come first.
+fn shifted(indices: &[usize], dimension: usize, shift: isize) -> Vec<usize> {
+    let shift = shift.rem_euclid(dimension as isize) as usize;
+    let wrap = indices.partition_point(|&index| index + shift < dimension);
+
+    indices[wrap..].iter().map(|&index| index + shift - dimension)
+        .chain(indices[..wrap].iter().map(|&index| index + shift))
+        .collect()
+}
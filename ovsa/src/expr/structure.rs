@@ -0,0 +1,218 @@
+//! Serializable descriptions of composite structures, so that experiments can store what was encoded and not only the
+//! resulting vector. A `Structure` is an expression tree over the labels of an item memory, built like an `Expr` and
+//! written as JSON with `describe`:
+//!
+//! ```json
+//! {"op": "record", "fields": [
+//!     {"role": "color", "filler": {"op": "symbol", "label": "red"}},
+//!     {"role": "path", "filler": {"op": "sequence", "items": [{"op": "symbol", "label": "a"}, {"op": "symbol", "label": "b"}]}}
+//! ]}
+//! ```
+//!
+//! `materialize` looks the labels up in an item memory and evaluates the tree as one fused `Expr`:
+//!
+//! | structure | vector |
+//! |---|---|
+//! | `symbol` | the vector stored under the label |
+//! | `bind`, `bundle`, `permute` | as `Expr::bind_all`, `Expr::bundle_all` and `Expr::permute` |
+//! | `record` | the bundle of every filler bound to the symbol of its role, as `encode::record` |
+//! | `sequence` | the bundle of every item shifted by its position |
+//! | `tree` | the bundle of the value and every child shifted by its position plus one, so shifts add up along a path |
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sprs::CsVec;
+
+use crate::errors::OVSAError;
+use crate::expr::{Expr, expr};
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+
+
+/// A composite structure over labelled symbols.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Structure {
+    /// A symbol of the item memory.
+    Symbol { label: String },
+    /// The binding (XOR) of the terms.
+    Bind { terms: Vec<Structure> },
+    /// The bundle (majority) of the terms.
+    Bundle { terms: Vec<Structure> },
+    /// The term cyclically shifted.
+    Permute { term: Box<Structure>, shift: isize },
+    /// Fillers bound to role symbols.
+    Record { fields: Vec<Field> },
+    /// Items bound to their positions by shifting.
+    Sequence { items: Vec<Structure> },
+    /// A value with ordered children, each a tree or any other structure.
+    Tree { value: Box<Structure>, children: Vec<Structure> },
+}
+
+
+/// A field of a `Structure::Record`: the label of the role symbol and the filler bound to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    pub role: String,
+    pub filler: Structure,
+}
+
+
+impl Structure {
+    /// Starts a structure from a symbol.
+    /// # Arguments
+    /// * `label` - The label of the symbol in the item memory.
+    pub fn symbol(label: &str) -> Self {
+        Structure::Symbol { label: label.to_string() }
+    }
+
+    /// Builds a record from (role label, filler) pairs.
+    pub fn record(fields: impl IntoIterator<Item = (impl Into<String>, Structure)>) -> Self {
+        Structure::Record { fields: fields.into_iter().map(|(role, filler)| Field { role: role.into(), filler }).collect() }
+    }
+
+    /// Builds a sequence of items.
+    pub fn sequence(items: impl IntoIterator<Item = Structure>) -> Self {
+        Structure::Sequence { items: items.into_iter().collect() }
+    }
+
+    /// Builds a tree node from its value and its children.
+    pub fn tree(value: Structure, children: impl IntoIterator<Item = Structure>) -> Self {
+        Structure::Tree { value: Box::new(value), children: children.into_iter().collect() }
+    }
+
+    /// Binds the structure with another one, flattening chained binds like `Expr::bind`.
+    pub fn bind(self, other: Structure) -> Self {
+        match self {
+            Structure::Bind { mut terms } => {
+                terms.push(other);
+                Structure::Bind { terms }
+            }
+            term => Structure::Bind { terms: vec![term, other] },
+        }
+    }
+
+    /// Cyclically shifts the structure. Positive values shift to the right, negative values shift to the left.
+    pub fn permute(self, shift_by: isize) -> Self {
+        match self {
+            Structure::Permute { term, shift } => Structure::Permute { term, shift: shift + shift_by },
+            term => Structure::Permute { term: Box::new(term), shift: shift_by },
+        }
+    }
+
+    /// Bundles the structure with another one, appending to the terms if the structure is already a bundle.
+    /// A nested bundle is written explicitly as `Structure::Bundle { terms }`, which materializes as `Expr::bundle_all`.
+    pub fn bundle(self, other: Structure) -> Self {
+        match self {
+            Structure::Bundle { mut terms } => {
+                terms.push(other);
+                Structure::Bundle { terms }
+            }
+            term => Structure::Bundle { terms: vec![term, other] },
+        }
+    }
+
+    /// Returns the labels of all symbols of the structure, roles included, in order of first appearance.
+    pub fn labels(&self) -> Vec<&str> {
+        let mut labels = Vec::new();
+        self.collect_labels(&mut labels);
+        labels
+    }
+
+    /// Describes the structure as JSON, e.g. to store it next to the vector it encodes.
+    pub fn describe(&self) -> String {
+        serde_json::to_string(self).expect("A structure serializes to JSON.")
+    }
+
+    /// Reads a structure from its JSON description.
+    /// # Arguments
+    /// * `json` - The description written by `describe`.
+    /// # Returns
+    /// The structure, or `InvalidFormat` if the JSON does not describe one.
+    pub fn from_description(json: &str) -> Result<Self, OVSAError> {
+        serde_json::from_str(json).map_err(|error| OVSAError::InvalidFormat(error.to_string()))
+    }
+
+    /// Builds the vector of the structure from the symbols of an item memory.
+    /// # Arguments
+    /// * `memory` - The memory holding the symbols.
+    /// # Returns
+    /// The sparse binary vector, `InvalidFormat` for a label missing from the memory, or `EmptyVectorList` for a
+    /// bundle, bind, record or sequence without terms.
+    pub fn materialize(&self, memory: &ItemMemory) -> Result<CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.materialize_with_rng(memory, rng))
+    }
+
+    /// Builds the vector of the structure, breaking bundling ties with the provided random number generator.
+    /// # Arguments
+    /// * `memory` - The memory holding the symbols.
+    /// * `rng` - The random number generator used to break ties.
+    /// # Returns
+    /// The sparse binary vector.
+    pub fn materialize_with_rng<R: Rng + ?Sized>(&self, memory: &ItemMemory, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        self.to_expr(memory)?.eval_with_rng(rng)
+    }
+
+    /// Lowers the structure to an `Expr` over the vectors of the memory.
+    fn to_expr<'a>(&self, memory: &'a ItemMemory) -> Result<Expr<'a>, OVSAError> {
+        let lookup = |label: &str| memory.get(label).map(expr).ok_or_else(|| OVSAError::InvalidFormat(format!("unknown symbol {}", label)));
+        let all = |terms: &[Structure]| {
+            if terms.is_empty() {
+                return Err(OVSAError::EmptyVectorList);
+            }
+            terms.iter().map(|term| term.to_expr(memory)).collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(match self {
+            Structure::Symbol { label } => lookup(label)?,
+            Structure::Bind { terms } => Expr::bind_all(all(terms)?),
+            Structure::Bundle { terms } => Expr::bundle_all(all(terms)?),
+            Structure::Permute { term, shift } => term.to_expr(memory)?.permute(*shift),
+            Structure::Record { fields } => {
+                if fields.is_empty() {
+                    return Err(OVSAError::EmptyVectorList);
+                }
+                let bound = fields.iter()
+                    .map(|field| Ok(Expr::bind_all([lookup(&field.role)?, field.filler.to_expr(memory)?])))
+                    .collect::<Result<Vec<_>, OVSAError>>()?;
+                Expr::bundle_all(bound)
+            }
+            Structure::Sequence { items } => {
+                let shifted = all(items)?.into_iter().enumerate().map(|(position, item)| item.permute(position as isize));
+                Expr::bundle_all(shifted)
+            }
+            Structure::Tree { value, children } => {
+                let children = children.iter().enumerate()
+                    .map(|(position, child)| Ok(child.to_expr(memory)?.permute(position as isize + 1)));
+                Expr::bundle_all(std::iter::once(value.to_expr(memory)).chain(children).collect::<Result<Vec<_>, OVSAError>>()?)
+            }
+        })
+    }
+
+    fn collect_labels<'a>(&'a self, labels: &mut Vec<&'a str>) {
+        match self {
+            Structure::Symbol { label } => push_label(labels, label),
+            Structure::Bind { terms } | Structure::Bundle { terms } | Structure::Sequence { items: terms } => {
+                terms.iter().for_each(|term| term.collect_labels(labels));
+            }
+            Structure::Permute { term, .. } => term.collect_labels(labels),
+            Structure::Record { fields } => {
+                for field in fields {
+                    push_label(labels, &field.role);
+                    field.filler.collect_labels(labels);
+                }
+            }
+            Structure::Tree { value, children } => {
+                value.collect_labels(labels);
+                children.iter().for_each(|child| child.collect_labels(labels));
+            }
+        }
+    }
+}
+
+
+fn push_label<'a>(labels: &mut Vec<&'a str>, label: &'a str) {
+    if !labels.contains(&label) {
+        labels.push(label);
+    }
+}
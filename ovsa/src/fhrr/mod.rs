@@ -0,0 +1,193 @@
+//! Fourier holographic reduced representations (FHRR): vectors of unit phasors `exp(i * phi_j)`, bound by multiplying
+//! them phasor-wise, i.e. adding their phases. A vector of `n` phasors is stored as a dense vector of `2n` entries, the
+//! real parts in the first half and the imaginary parts in the second half, the layout of
+//! `vfa::FractionalPowerEncoder`, so that FPE encodings are FHRR vectors and the real inner product of two vectors is
+//! the real part of their complex inner product.
+
+use std::f64::consts::PI;
+use ndarray::Array1;
+use rand::Rng;
+
+use crate::dense::dot;
+use crate::errors::OVSAError;
+use crate::rng::with_global_rng;
+
+
+/// Draws a random FHRR vector with uniformly distributed phases.
+/// # Arguments
+/// * `dimension` - The size of the vector, an even number.
+/// # Returns
+/// The FHRR vector.
+pub fn random(dimension: usize) -> Result<Array1<f32>, OVSAError> {
+    with_global_rng(|rng| random_with_rng(dimension, rng))
+}
+
+
+/// Draws a random FHRR vector using the provided random number generator.
+/// # Arguments
+/// * `dimension` - The size of the vector, an even number.
+/// * `rng` - The random number generator.
+/// # Returns
+/// The FHRR vector.
+pub fn random_with_rng<R: Rng + ?Sized>(dimension: usize, rng: &mut R) -> Result<Array1<f32>, OVSAError> {
+    check_dimension(dimension)?;
+    let phases: Vec<f64> = (0..dimension / 2).map(|_| rng.random_range(-PI..PI)).collect();
+    Ok(from_phases(&phases))
+}
+
+
+/// Builds an FHRR vector from its phases.
+/// # Arguments
+/// * `phases` - The phases in radians.
+/// # Returns
+/// The FHRR vector of `2 * phases.len()` entries.
+pub fn from_phases(phases: &[f64]) -> Array1<f32> {
+    let n_phases = phases.len();
+    let mut result = Array1::<f32>::zeros(2 * n_phases);
+    for (j, phase) in phases.iter().enumerate() {
+        result[j] = phase.cos() as f32;
+        result[n_phases + j] = phase.sin() as f32;
+    }
+    result
+}
+
+
+/// Returns the phases of an FHRR vector, in `(-pi, pi]`. Entries that are not unit phasors, e.g. of a bundle, are
+/// reduced to their angle.
+/// # Arguments
+/// * `vec` - The FHRR vector.
+pub fn phases(vec: &Array1<f32>) -> Result<Vec<f64>, OVSAError> {
+    check_dimension(vec.len())?;
+    let n_phases = vec.len() / 2;
+    Ok((0..n_phases).map(|j| (vec[n_phases + j] as f64).atan2(vec[j] as f64)).collect())
+}
+
+
+/// Enforces conjugate symmetry on the phasors of an FHRR vector, viewed as the discrete Fourier transform of a real
+/// vector: phasor `n - k` becomes the conjugate of phasor `k`, and the phasors at `k = 0` and, for an even number of
+/// phasors, `k = n / 2` are rounded to the nearer of +1 and -1. Binding preserves the symmetry, so vectors bound in the
+/// frequency domain keep converting exactly with `convert::fhrr_to_hrr`.
+/// # Arguments
+/// * `vec` - The FHRR vector.
+/// # Returns
+/// The conjugate symmetric FHRR vector.
+pub fn conjugate_symmetric(vec: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+    let mut phases = phases(vec)?;
+    let n_phases = phases.len();
+    let real = |phase: f64| if phase.cos() < 0.0 { PI } else { 0.0 };
+    phases[0] = real(phases[0]);
+    if n_phases.is_multiple_of(2) {
+        phases[n_phases / 2] = real(phases[n_phases / 2]);
+    }
+    for k in 1..n_phases.div_ceil(2) {
+        phases[n_phases - k] = -phases[k];
+    }
+    Ok(from_phases(&phases))
+}
+
+
+/// Binds two FHRR vectors by multiplying their phasors, adding their phases.
+/// # Arguments
+/// * `a` - The first vector.
+/// * `b` - The second vector.
+/// # Returns
+/// The bound vector.
+pub fn bind(a: &Array1<f32>, b: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+    multiply(a, b, false)
+}
+
+
+/// Unbinds an FHRR vector by multiplying with the conjugate of the other one, subtracting its phases. Unbinding is the
+/// exact inverse of binding for unit phasors.
+/// # Arguments
+/// * `a` - The bound vector.
+/// * `b` - The vector to unbind.
+/// # Returns
+/// The unbound vector.
+pub fn unbind(a: &Array1<f32>, b: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+    multiply(a, b, true)
+}
+
+
+/// Raises an FHRR vector to a real power by multiplying its phases, the engine of fractional binding: integer
+/// exponents repeat the binding, fractional ones interpolate it, and `pow(pow(vec, a), b)` is `pow(vec, a * b)` up to
+/// phase wrapping. Phases are taken in `(-pi, pi]`, so that `pow(base, x)` for a base of frequencies within that range
+/// is the fractional power encoding of `x`.
+/// # Arguments
+/// * `vec` - The FHRR vector.
+/// * `exponent` - The exponent, e.g. 0.5 for a square root: `bind(pow(vec, 0.5), pow(vec, 0.5))` is `vec`.
+/// # Returns
+/// The FHRR vector with every phase multiplied by the exponent.
+pub fn pow(vec: &Array1<f32>, exponent: f64) -> Result<Array1<f32>, OVSAError> {
+    if !exponent.is_finite() {
+        return Err(OVSAError::InvalidArgument(format!("the exponent must be finite, got {}", exponent)));
+    }
+
+    let phases: Vec<f64> = phases(vec)?.into_iter().map(|phase| phase * exponent).collect();
+    Ok(from_phases(&phases))
+}
+
+
+/// Interpolates between two FHRR vectors along the shorter arc of every phasor, i.e. `bind(a, pow(unbind(b, a), t))`,
+/// e.g. to blend two concepts smoothly. The similarity to `a` decreases and the similarity to `b` increases
+/// monotonically with `t`.
+/// # Arguments
+/// * `a` - The vector at `t = 0`.
+/// * `b` - The vector at `t = 1`.
+/// * `t` - The interpolation parameter; values outside `[0, 1]` extrapolate.
+/// # Returns
+/// The interpolated FHRR vector.
+pub fn slerp(a: &Array1<f32>, b: &Array1<f32>, t: f64) -> Result<Array1<f32>, OVSAError> {
+    if a.len() != b.len() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    pow(&unbind(b, a)?, t).and_then(|step| bind(a, &step))
+}
+
+
+/// Computes the similarity of two FHRR vectors: the real part of their complex inner product over the number of
+/// phasors, i.e. the mean cosine of their phase differences for unit phasors.
+/// # Arguments
+/// * `a` - The first vector.
+/// * `b` - The second vector.
+/// # Returns
+/// The similarity, 1 for identical unit phasor vectors and about 0 for unrelated ones.
+pub fn similarity(a: &Array1<f32>, b: &Array1<f32>) -> Result<f64, OVSAError> {
+    if a.len() != b.len() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    check_dimension(a.len())?;
+
+    Ok(dot(a.view(), b.view()) as f64 / (a.len() / 2) as f64)
+}
+
+
+fn multiply(a: &Array1<f32>, b: &Array1<f32>, conjugate: bool) -> Result<Array1<f32>, OVSAError> {
+    if a.len() != b.len() {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    check_dimension(a.len())?;
+
+    let n_phases = a.len() / 2;
+    let sign = if conjugate { -1.0 } else { 1.0 };
+    let mut result = Array1::<f32>::zeros(a.len());
+    for j in 0..n_phases {
+        let (a_re, a_im) = (a[j], a[n_phases + j]);
+        let (b_re, b_im) = (b[j], sign * b[n_phases + j]);
+        result[j] = a_re * b_re - a_im * b_im;
+        result[n_phases + j] = a_re * b_im + a_im * b_re;
+    }
+    Ok(result)
+}
+
+
+fn check_dimension(dimension: usize) -> Result<(), OVSAError> {
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+    if !dimension.is_multiple_of(2) {
+        return Err(OVSAError::InvalidArgument(format!("an FHRR vector has an even dimension, got {}", dimension)));
+    }
+    Ok(())
+}
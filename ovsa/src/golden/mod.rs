@@ -0,0 +1,200 @@
+//! Golden test vectors: the expected results of seeded generation for every random number generator and model, so that
+//! downstream users can verify that their platform, toolchain and dependency versions reproduce the same codebooks.
+//! A mismatch means that seeded results, and thus models saved elsewhere, cannot be reproduced on this build.
+
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::binary;
+use crate::errors::OVSAError;
+use crate::rng::OvsaRng;
+use crate::rng::algorithms::{Pcg64, Xoshiro256PlusPlus};
+
+
+/// The seed of every golden vector.
+pub const SEED: u64 = 42;
+/// The number of values drawn by every golden vector.
+pub const LENGTH: usize = 8;
+/// The dimension of the sparse binary golden vectors.
+pub const DIMENSION: usize = 1024;
+
+
+/// A random number generator covered by the golden vectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Generator {
+    ChaCha12,
+    Xoshiro256PlusPlus,
+    Pcg64,
+    /// The generator selected by the features, expected to match its pinned algorithm.
+    OvsaRng,
+}
+
+
+impl Generator {
+    /// The generators whose outputs are pinned by the golden vectors.
+    pub const PINNED: [Generator; 3] = [Generator::ChaCha12, Generator::Xoshiro256PlusPlus, Generator::Pcg64];
+
+    /// Returns the pinned algorithm of the generator: the generator itself, or the one behind `OvsaRng`.
+    pub fn pinned(self) -> Generator {
+        match self {
+            Generator::OvsaRng => match OvsaRng::algorithm() {
+                "pcg64" => Generator::Pcg64,
+                "xoshiro256++" => Generator::Xoshiro256PlusPlus,
+                _ => Generator::ChaCha12,
+            },
+            generator => generator,
+        }
+    }
+}
+
+
+/// What a golden vector draws from a generator seeded with `seed_from_u64(SEED)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Draw {
+    /// The first `LENGTH` raw 64-bit outputs.
+    Words,
+    /// The sorted active indices of a sparse binary vector of dimension `DIMENSION` with `LENGTH` active entries.
+    SparseBinary,
+    /// A dense HRR vector of dimension `LENGTH` drawn uniformly from [-1, 1).
+    #[cfg(feature = "dense")]
+    Hrr,
+}
+
+
+impl Draw {
+    /// Every draw available with the enabled features.
+    pub fn all() -> Vec<Draw> {
+        vec![
+            Draw::Words,
+            Draw::SparseBinary,
+            #[cfg(feature = "dense")]
+            Draw::Hrr,
+        ]
+    }
+}
+
+
+/// The result of a draw. Values are compared bit for bit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Output {
+    Words(Vec<u64>),
+    Indices(Vec<usize>),
+    Values(Vec<f32>),
+}
+
+
+/// The comparison of one draw with its golden vector.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GoldenCheck {
+    pub generator: Generator,
+    pub draw: Draw,
+    pub expected: Output,
+    pub actual: Output,
+}
+
+
+impl GoldenCheck {
+    /// Returns whether the draw reproduced its golden vector.
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+
+/// The outcome of `verify`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GoldenReport {
+    pub checks: Vec<GoldenCheck>,
+}
+
+
+impl GoldenReport {
+    /// Returns whether every draw reproduced its golden vector.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(GoldenCheck::passed)
+    }
+
+    /// Returns the checks that did not reproduce their golden vector.
+    pub fn failures(&self) -> Vec<&GoldenCheck> {
+        self.checks.iter().filter(|check| !check.passed()).collect()
+    }
+}
+
+
+/// Returns the golden vector of a draw.
+/// # Arguments
+/// * `generator` - The generator; `OvsaRng` shares the golden vectors of its pinned algorithm.
+/// * `draw` - What is drawn.
+/// # Returns
+/// The expected `Output`.
+pub fn expected(generator: Generator, draw: Draw) -> Output {
+    match (generator.pinned(), draw) {
+        (Generator::ChaCha12, Draw::Words) => Output::Words(vec![
+            0x86cc7763222724a2, 0x8af00a133fad517d, 0xa2ef6071de5134d1, 0x67e92d78fd7630b2,
+            0x08cab0dff8119fea, 0x6a3a9ca39e0f81a8, 0xbcc7d8e8590878fb, 0xd9688d9b2f8eb737,
+        ]),
+        (Generator::Xoshiro256PlusPlus, Draw::Words) => Output::Words(vec![
+            0xd0764d4f4476689f, 0x519e4174576f3791, 0xfbe07cfb0c24ed8c, 0xb37d9f600cd835b8,
+            0xcb231c3874846a73, 0x968d9f004e50de7d, 0x201718ff221a3556, 0x9ae94e070ed8cb46,
+        ]),
+        (_, Draw::Words) => Output::Words(vec![
+            0x08aa216468999186, 0x1ee798985d75f72e, 0xd299b3dff42362f7, 0x4506c2c4000fa978,
+            0x0ecb7107a983bcf5, 0xf202ad2237be2d68, 0xf36d676c4456fbbd, 0x50d7efa2e55fff62,
+        ]),
+        (Generator::ChaCha12, Draw::SparseBinary) => Output::Indices(vec![135, 253, 415, 536, 553, 650, 886, 1012]),
+        (Generator::Xoshiro256PlusPlus, Draw::SparseBinary) => Output::Indices(vec![128, 324, 601, 619, 715, 810, 828, 1002]),
+        (_, Draw::SparseBinary) => Output::Indices(vec![0, 222, 273, 371, 415, 676, 917, 971]),
+        #[cfg(feature = "dense")]
+        (Generator::ChaCha12, Draw::Hrr) => Output::Values(vec![
+            -0.7331805, 0.053114653, -0.5025234, 0.08545041, 0.7368531, 0.27293015, 0.9801693, -0.18819666,
+        ]),
+        #[cfg(feature = "dense")]
+        (Generator::Xoshiro256PlusPlus, Draw::Hrr) => Output::Values(vec![
+            0.62861013, -0.3623581, 0.9677882, 0.40227103, 0.58700895, 0.17619681, -0.74929523, 0.2102449,
+        ]),
+        #[cfg(feature = "dense")]
+        (_, Draw::Hrr) => Output::Values(vec![
+            -0.18281364, -0.26983762, 0.9073298, -0.9995222, 0.3243327, -0.5645089, -0.4660957, 0.79199195,
+        ]),
+    }
+}
+
+
+/// Performs a draw on this build.
+/// # Arguments
+/// * `generator` - The generator, seeded with `seed_from_u64(SEED)`.
+/// * `draw` - What is drawn.
+/// # Returns
+/// The actual `Output`.
+pub fn generate(generator: Generator, draw: Draw) -> Result<Output, OVSAError> {
+    match generator {
+        Generator::ChaCha12 => run(draw, &mut rand_chacha::ChaCha12Rng::seed_from_u64(SEED)),
+        Generator::Xoshiro256PlusPlus => run(draw, &mut Xoshiro256PlusPlus::seed_from_u64(SEED)),
+        Generator::Pcg64 => run(draw, &mut Pcg64::seed_from_u64(SEED)),
+        Generator::OvsaRng => run(draw, &mut OvsaRng::seed_from_u64(SEED)),
+    }
+}
+
+
+/// Compares every draw of every generator, including `OvsaRng`, with its golden vector.
+/// # Returns
+/// The `GoldenReport` with one check per generator and draw.
+pub fn verify() -> Result<GoldenReport, OVSAError> {
+    let mut checks = Vec::new();
+    for generator in Generator::PINNED.into_iter().chain([Generator::OvsaRng]) {
+        for draw in Draw::all() {
+            checks.push(GoldenCheck { generator, draw, expected: expected(generator, draw), actual: generate(generator, draw)? });
+        }
+    }
+    Ok(GoldenReport { checks })
+}
+
+
+fn run<R: Rng>(draw: Draw, rng: &mut R) -> Result<Output, OVSAError> {
+    Ok(match draw {
+        Draw::Words => Output::Words((0..LENGTH).map(|_| rng.next_u64()).collect()),
+        Draw::SparseBinary => Output::Indices(binary::sparse_random_with_rng(DIMENSION, LENGTH, rng)?.indices().to_vec()),
+        #[cfg(feature = "dense")]
+        Draw::Hrr => Output::Values(crate::dense::random_uniform_with_rng(LENGTH, -1.0, 1.0, rng)?.to_vec()),
+    })
+}
@@ -0,0 +1,255 @@
+use std::ops::Range;
+#[cfg(feature = "dense")]
+use ndarray::{Array1, s};
+use sprs::CsVec;
+
+use crate::binary::matrix::HvMatrix;
+#[cfg(feature = "dense")]
+use crate::dense::bank::{cosine_block, normalized_rows};
+use crate::errors::OVSAError;
+use crate::mask::{range_weights, total_weight};
+
+
+/// How a bundling operation combines many vectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BundleStrategy {
+    /// All vectors at once.
+    #[default]
+    Flat,
+    /// In a tree: consecutive chunks of `fan_in` vectors are bundled first, then chunks of the chunk results, until one
+    /// vector is left. The chunks of a level are independent and bundled in parallel with the `parallel` feature.
+    Tree { fan_in: usize },
+}
+
+
+/// How bundling counters are turned back into a binary vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Hardening {
+    /// An entry is active where more than half of the bundled vectors are active, ties being broken at random.
+    #[default]
+    Majority,
+    /// An entry is active with a probability growing linearly with its counter, e.g. the share of the bundled vectors
+    /// active there. Small bundles, whose majority mostly consists of ties and near ties, keep their minority entries
+    /// in proportion instead of losing them, so that the expected similarity to every bundled vector is preserved.
+    Stochastic,
+}
+
+
+/// Common interface over the hypervector representations of the crate.
+/// Used by generic containers such as the item memory to store and compare vectors of either representation.
+pub trait Hypervector: Clone + Send + Sync {
+    /// Returns the dimension of the hypervector.
+    fn dimension(&self) -> usize;
+
+    /// Computes the similarity to another hypervector of the same representation.
+    /// # Arguments
+    /// * `other` - The hypervector to compare to.
+    /// # Returns
+    /// The similarity as defined by the representation, higher meaning more similar.
+    fn similarity(&self, other: &Self) -> Result<f64, OVSAError>;
+
+    /// Computes the similarity to another hypervector if it reaches a minimum. Representations that can bound the
+    /// similarity before finishing the comparison override this to skip clearly dissimilar vectors early.
+    /// # Arguments
+    /// * `other` - The hypervector to compare to.
+    /// * `minimum` - The smallest similarity of interest.
+    /// # Returns
+    /// The similarity if it is at least `minimum`, otherwise `None`.
+    fn similarity_at_least(&self, other: &Self, minimum: f64) -> Result<Option<f64>, OVSAError> {
+        Ok(Some(self.similarity(other)?).filter(|&similarity| similarity >= minimum))
+    }
+
+    /// Computes the similarity of every query to every vector, e.g. for a tile of a batched cleanup. Representations
+    /// with a faster bulk kernel than pairwise comparisons override this.
+    /// # Arguments
+    /// * `queries` - The query hypervectors.
+    /// * `vectors` - The hypervectors to compare them to.
+    /// # Returns
+    /// One row of similarities per query, indexed like `vectors`.
+    fn similarities(queries: &[Self], vectors: &[&Self]) -> Result<Vec<Vec<f64>>, OVSAError> {
+        queries.iter().map(|query| vectors.iter().map(|vector| query.similarity(vector)).collect()).collect()
+    }
+
+    /// Computes the similarity to another hypervector with per-dimension weights scaling the contribution of every
+    /// dimension, e.g. the scores of `analysis::dimension_scores` or weights from `mask::segment_weights`.
+    /// # Arguments
+    /// * `other` - The hypervector to compare to.
+    /// * `weights` - One finite, non-negative weight per dimension, at least one of them positive.
+    /// # Returns
+    /// The weighted similarity, equal to `similarity` for uniform weights.
+    fn weighted_similarity(&self, other: &Self, weights: &[f64]) -> Result<f64, OVSAError>;
+
+    /// Computes the weighted similarity of every query to every vector. Representations with a faster bulk kernel than
+    /// pairwise comparisons override this.
+    /// # Arguments
+    /// * `queries` - The query hypervectors.
+    /// * `vectors` - The hypervectors to compare them to.
+    /// * `weights` - One weight per dimension.
+    /// # Returns
+    /// One row of weighted similarities per query, indexed like `vectors`.
+    fn weighted_similarities(queries: &[Self], vectors: &[&Self], weights: &[f64]) -> Result<Vec<Vec<f64>>, OVSAError> {
+        queries.iter().map(|query| vectors.iter().map(|vector| query.weighted_similarity(vector, weights)).collect()).collect()
+    }
+
+    /// Computes the similarity to another hypervector over a range of dimensions only, e.g. one field of a structured
+    /// vector. Defaults to the weighted similarity with weight 1 inside the range and 0 outside; representations that
+    /// can compare the range in place override this.
+    /// # Arguments
+    /// * `other` - The hypervector to compare to.
+    /// * `range` - The non-empty range of dimensions to compare.
+    /// # Returns
+    /// The similarity within the range, as defined by the representation.
+    fn similarity_range(&self, other: &Self, range: Range<usize>) -> Result<f64, OVSAError> {
+        self.weighted_similarity(other, &range_weights(self.dimension(), range)?)
+    }
+
+    /// Computes the similarity of every query to every vector over a range of dimensions. Representations with a faster
+    /// bulk kernel than pairwise comparisons override this.
+    /// # Arguments
+    /// * `queries` - The query hypervectors.
+    /// * `vectors` - The hypervectors to compare them to.
+    /// * `range` - The non-empty range of dimensions to compare.
+    /// # Returns
+    /// One row of similarities per query, indexed like `vectors`.
+    fn similarities_range(queries: &[Self], vectors: &[&Self], range: Range<usize>) -> Result<Vec<Vec<f64>>, OVSAError> {
+        queries.iter().map(|query| vectors.iter().map(|vector| query.similarity_range(vector, range.clone())).collect()).collect()
+    }
+
+    /// Returns the number of heap bytes holding the vector's entries, excluding the inline size of the value itself.
+    fn heap_bytes(&self) -> usize;
+}
+
+
+impl Hypervector for CsVec<i8> {
+    fn dimension(&self) -> usize {
+        self.dim()
+    }
+
+    fn similarity(&self, other: &Self) -> Result<f64, OVSAError> {
+        crate::binary::similarity(self, other)
+    }
+
+    fn similarity_at_least(&self, other: &Self, minimum: f64) -> Result<Option<f64>, OVSAError> {
+        crate::binary::similarity_at_least(self, other, minimum)
+    }
+
+    fn similarities(queries: &[Self], vectors: &[&Self]) -> Result<Vec<Vec<f64>>, OVSAError> {
+        let Some(dimension) = queries.first().map(|query| query.dim()) else {
+            return Ok(Vec::new());
+        };
+        // merging sorted indices costs about nnz steps and a packed comparison dimension / 64 words, so packing only
+        // pays off when the vectors are dense enough
+        let active: usize = vectors.iter().map(|vector| vector.nnz()).sum();
+        if vectors.is_empty() || active / vectors.len() < dimension / 64 {
+            return queries.iter().map(|query| vectors.iter().map(|vector| query.similarity(vector)).collect()).collect();
+        }
+
+        let queries = HvMatrix::from_rows(dimension, queries)?;
+        let vectors = HvMatrix::from_rows(dimension, vectors.iter().copied())?;
+        let distances = queries.hamming_block(&vectors)?;
+        Ok(distances.into_iter().map(|row| row.into_iter().map(|distance| 1f64 - distance as f64 / dimension as f64).collect()).collect())
+    }
+
+    fn weighted_similarity(&self, other: &Self, weights: &[f64]) -> Result<f64, OVSAError> {
+        crate::binary::weighted_similarity(self, other, weights)
+    }
+
+    fn similarity_range(&self, other: &Self, range: Range<usize>) -> Result<f64, OVSAError> {
+        crate::binary::similarity_range(self, other, range)
+    }
+
+    fn weighted_similarities(queries: &[Self], vectors: &[&Self], weights: &[f64]) -> Result<Vec<Vec<f64>>, OVSAError> {
+        let Some(dimension) = queries.first().map(|query| query.dim()) else {
+            return Ok(Vec::new());
+        };
+        // the packed kernel only visits the differing dimensions, like the merge of sorted indices, but without the
+        // branches, so the same density rule as for `similarities` applies
+        let active: usize = vectors.iter().map(|vector| vector.nnz()).sum();
+        if vectors.is_empty() || active / vectors.len() < dimension / 64 {
+            return queries.iter().map(|query| vectors.iter().map(|vector| query.weighted_similarity(vector, weights)).collect()).collect();
+        }
+
+        let total = total_weight(weights, dimension)?;
+        let queries = HvMatrix::from_rows(dimension, queries)?;
+        let vectors = HvMatrix::from_rows(dimension, vectors.iter().copied())?;
+        let distances = queries.weighted_hamming_block(&vectors, weights)?;
+        Ok(distances.into_iter().map(|row| row.into_iter().map(|distance| 1f64 - distance / total).collect()).collect())
+    }
+
+    fn heap_bytes(&self) -> usize {
+        self.nnz() * (size_of::<usize>() + size_of::<i8>())
+    }
+}
+
+
+#[cfg(feature = "dense")]
+impl Hypervector for Array1<f32> {
+    fn dimension(&self) -> usize {
+        self.len()
+    }
+
+    fn similarity(&self, other: &Self) -> Result<f64, OVSAError> {
+        if self.len() != other.len() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(crate::dense::similarity(self, other) as f64)
+    }
+
+    fn similarities(queries: &[Self], vectors: &[&Self]) -> Result<Vec<Vec<f64>>, OVSAError> {
+        let Some(dimension) = queries.first().map(|query| query.len()) else {
+            return Ok(Vec::new());
+        };
+        if queries.iter().map(|query| query.len()).chain(vectors.iter().map(|vector| vector.len())).any(|len| len != dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        // stacking the normalized vectors like a `DenseBank` turns the cosine similarities into one matrix product
+        let queries = normalized_rows(dimension, queries.iter().map(|query| query.view()));
+        let vectors = normalized_rows(dimension, vectors.iter().map(|vector| vector.view()));
+        Ok(cosine_block(&queries, &vectors).rows().into_iter().map(|row| row.iter().map(|&similarity| similarity as f64).collect()).collect())
+    }
+
+    fn weighted_similarity(&self, other: &Self, weights: &[f64]) -> Result<f64, OVSAError> {
+        Ok(crate::dense::weighted_similarity(self, other, weights)? as f64)
+    }
+
+    fn similarity_range(&self, other: &Self, range: Range<usize>) -> Result<f64, OVSAError> {
+        Ok(crate::dense::similarity_range(self, other, range)? as f64)
+    }
+
+    fn similarities_range(queries: &[Self], vectors: &[&Self], range: Range<usize>) -> Result<Vec<Vec<f64>>, OVSAError> {
+        let Some(dimension) = queries.first().map(|query| query.len()) else {
+            return Ok(Vec::new());
+        };
+        if queries.iter().map(|query| query.len()).chain(vectors.iter().map(|vector| vector.len())).any(|len| len != dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        crate::mask::check_range(dimension, &range)?;
+
+        // the ranges are copied and normalized once, so that the batch runs on the bulk kernel of `similarities`
+        let queries: Vec<Array1<f32>> = queries.iter().map(|query| query.slice(s![range.clone()]).to_owned()).collect();
+        let vectors: Vec<Array1<f32>> = vectors.iter().map(|vector| vector.slice(s![range.clone()]).to_owned()).collect();
+        Self::similarities(&queries, &vectors.iter().collect::<Vec<_>>())
+    }
+
+    fn weighted_similarities(queries: &[Self], vectors: &[&Self], weights: &[f64]) -> Result<Vec<Vec<f64>>, OVSAError> {
+        let Some(dimension) = queries.first().map(|query| query.len()) else {
+            return Ok(Vec::new());
+        };
+        if queries.iter().map(|query| query.len()).chain(vectors.iter().map(|vector| vector.len())).any(|len| len != dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        total_weight(weights, dimension)?;
+
+        // scaling every dimension by the square root of its weight turns the weighted similarities into plain ones
+        let scale: Array1<f32> = weights.iter().map(|weight| weight.sqrt() as f32).collect();
+        let queries: Vec<Array1<f32>> = queries.iter().map(|query| query * &scale).collect();
+        let vectors: Vec<Array1<f32>> = vectors.iter().map(|vector| *vector * &scale).collect();
+        Self::similarities(&queries, &vectors.iter().collect::<Vec<_>>())
+    }
+
+    fn heap_bytes(&self) -> usize {
+        self.len() * size_of::<f32>()
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+#[cfg(feature = "dense")]
+use ndarray::Array1;
+use sprs::CsVec;
+
+use crate::errors::OVSAError;
+
+
+/// The number of entries shown by summaries and diffs unless specified otherwise.
+pub const DEFAULT_SHOWN: usize = 8;
+
+
+/// A short description of a hypervector, printable with `{}` where the raw value would print thousands of entries.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Summary {
+    /// A sparse binary vector: its active count and its first active indices.
+    Binary { dimension: usize, n_active: usize, first_active: Vec<usize> },
+    /// A dense vector: its L2 norm and its components of largest magnitude as (index, value) pairs.
+    Dense { dimension: usize, norm: f32, largest: Vec<(usize, f32)> },
+}
+
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Summary::Binary { dimension, n_active, first_active } => {
+                write!(f, "binary hypervector: dimension {}, {} active (density {:.4}), first active {:?}",
+                    dimension, n_active, *n_active as f64 / *dimension as f64, first_active)?;
+                if first_active.len() < *n_active {
+                    write!(f, " ...")?;
+                }
+                Ok(())
+            }
+            Summary::Dense { dimension, norm, largest } => {
+                write!(f, "dense hypervector: dimension {}, norm {:.4}, largest", dimension, norm)?;
+                for (index, value) in largest {
+                    write!(f, " [{}]={:.4}", index, value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+
+/// The positions at which two hypervectors differ, as (index, first value, second value) triples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diff {
+    pub dimension: usize,
+    pub n_different: usize,
+    pub shown: Vec<(usize, f64, f64)>,
+}
+
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} positions differ", self.n_different, self.dimension)?;
+        for (index, a, b) in &self.shown {
+            write!(f, " [{}]: {} != {}", index, a, b)?;
+        }
+        if self.shown.len() < self.n_different {
+            write!(f, " ...")?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Debug inspection of hypervectors.
+pub trait Inspect {
+    /// Summarizes the hypervector, showing at most `shown` entries.
+    fn summary_with(&self, shown: usize) -> Summary;
+
+    /// Lists the positions at which two hypervectors differ, showing at most `shown` of them.
+    fn diff_with(&self, other: &Self, shown: usize) -> Result<Diff, OVSAError>;
+
+    /// Summarizes the hypervector, showing at most `DEFAULT_SHOWN` entries.
+    fn summary(&self) -> Summary {
+        self.summary_with(DEFAULT_SHOWN)
+    }
+
+    /// Lists the positions at which two hypervectors differ, showing at most `DEFAULT_SHOWN` of them.
+    fn diff(&self, other: &Self) -> Result<Diff, OVSAError> {
+        self.diff_with(other, DEFAULT_SHOWN)
+    }
+}
+
+
+impl Inspect for CsVec<i8> {
+    fn summary_with(&self, shown: usize) -> Summary {
+        Summary::Binary { dimension: self.dim(), n_active: self.nnz(), first_active: self.indices().iter().take(shown).copied().collect() }
+    }
+
+    fn diff_with(&self, other: &Self, shown: usize) -> Result<Diff, OVSAError> {
+        let different = crate::binary::xor(self, other)?;
+        let shown = different.indices().iter()
+            .take(shown)
+            .map(|&index| (index, self.get(index).map_or(0.0, |&value| value as f64), other.get(index).map_or(0.0, |&value| value as f64)))
+            .collect();
+
+        Ok(Diff { dimension: self.dim(), n_different: different.nnz(), shown })
+    }
+}
+
+
+#[cfg(feature = "dense")]
+impl Inspect for Array1<f32> {
+    fn summary_with(&self, shown: usize) -> Summary {
+        let mut largest: Vec<(usize, f32)> = self.iter().copied().enumerate().collect();
+        largest.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()).then(a.0.cmp(&b.0)));
+        largest.truncate(shown);
+
+        Summary::Dense { dimension: self.len(), norm: crate::dense::norm(self.view()), largest }
+    }
+
+    fn diff_with(&self, other: &Self, shown: usize) -> Result<Diff, OVSAError> {
+        if self.len() != other.len() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let different: Vec<(usize, f64, f64)> = self.iter().zip(other.iter()).enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(index, (&a, &b))| (index, a as f64, b as f64))
+            .collect();
+
+        Ok(Diff { dimension: self.len(), n_different: different.len(), shown: different.into_iter().take(shown).collect() })
+    }
+}
@@ -0,0 +1,547 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sprs::CsVec;
+
+use crate::binary::from_indices_or_empty;
+use crate::errors::OVSAError;
+use crate::learn::{CentroidClassifier, Samples};
+use crate::learn::calibration::Calibration;
+use crate::memory::ItemMemory;
+
+
+/// The kind of object a saved artifact holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    Memory,
+    Samples,
+    Classifier,
+}
+
+
+/// The representation of the sparse binary vectors written by this module.
+pub const SPARSE_BINARY: &str = "sparse_binary";
+
+
+/// The version of the on-disk format written by this release. Older versions are upgraded by `migrate` when loaded.
+///
+/// * 1 - the vectors or classes with their dimension, without a version field; later releases added optional metadata.
+/// * 2 - adds the `format_version` field and stores the class counts of classifiers as (index, count) pairs of their
+///   nonzero entries instead of dense arrays of `dimension` counts.
+pub const FORMAT_VERSION: u32 = 2;
+
+
+/// A description of a saved artifact, embedded in the file so that it can be validated before use.
+/// Files written before metadata existed load without it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// The kind of object saved.
+    pub kind: ArtifactKind,
+    /// The vector representation, e.g. `sparse_binary`.
+    pub representation: String,
+    /// The dimension of the vectors.
+    pub dimension: usize,
+    /// The mean fraction of active entries of the saved vectors, if there are any.
+    pub density: Option<f64>,
+    /// The seed the vectors were generated with, if known.
+    pub seed: Option<u64>,
+    /// Free-form parameters of the encoder that produced the vectors, e.g. the n-gram size.
+    pub encoder: BTreeMap<String, String>,
+    /// The version of the crate that wrote the artifact.
+    pub crate_version: String,
+}
+
+
+impl Metadata {
+    /// Creates the metadata of a sparse binary artifact written by this version of the crate.
+    /// # Arguments
+    /// * `kind` - The kind of object saved.
+    /// * `dimension` - The dimension of the vectors.
+    pub fn new(kind: ArtifactKind, dimension: usize) -> Self {
+        Metadata {
+            kind,
+            representation: SPARSE_BINARY.to_string(),
+            dimension,
+            density: None,
+            seed: None,
+            encoder: BTreeMap::new(),
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Records the seed the vectors were generated with.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Records a parameter of the encoder that produced the vectors.
+    pub fn with_encoder_parameter(mut self, name: &str, value: impl ToString) -> Self {
+        self.encoder.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Checks that an artifact described by this metadata can be used where `expected` is required:
+    /// the kind, representation and dimension must be equal, and so must the seed and every encoder parameter
+    /// recorded by both. The density and crate version are informative only.
+    /// # Arguments
+    /// * `expected` - The metadata the artifact must match.
+    /// # Returns
+    /// `IncompatibleArtifact` describing the first mismatch.
+    pub fn ensure_compatible(&self, expected: &Metadata) -> Result<(), OVSAError> {
+        let mismatch = |field: &str, found: &dyn std::fmt::Debug, wanted: &dyn std::fmt::Debug| {
+            Err(OVSAError::IncompatibleArtifact(format!("{} is {:?}, expected {:?}", field, found, wanted)))
+        };
+
+        if self.kind != expected.kind {
+            return mismatch("kind", &self.kind, &expected.kind);
+        }
+        if self.representation != expected.representation {
+            return mismatch("representation", &self.representation, &expected.representation);
+        }
+        if self.dimension != expected.dimension {
+            return mismatch("dimension", &self.dimension, &expected.dimension);
+        }
+        if let (Some(seed), Some(wanted)) = (self.seed, expected.seed) && seed != wanted {
+            return mismatch("seed", &seed, &wanted);
+        }
+        for (name, wanted) in &expected.encoder {
+            if let Some(value) = self.encoder.get(name) && value != wanted {
+                return mismatch(&format!("encoder parameter {}", name), value, wanted);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// A labelled sparse binary vector as stored on disk: the vector is given by its active indices.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredVector {
+    label: String,
+    indices: Vec<usize>,
+}
+
+/// The on-disk format shared by item memories and sample lists.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredVectors {
+    format_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<Metadata>,
+    dimension: usize,
+    vectors: Vec<StoredVector>,
+}
+
+/// A class of a saved classifier: its number of training vectors and the (index, count) pairs of its nonzero counts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredClass {
+    label: String,
+    n: usize,
+    counts: Vec<(usize, u32)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredClassifier {
+    format_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<Metadata>,
+    dimension: usize,
+    classes: Vec<StoredClass>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    calibration: Option<Calibration>,
+}
+
+
+/// Writes an item memory of sparse binary vectors as JSON, with default metadata.
+/// # Arguments
+/// * `path` - The file to write.
+/// * `memory` - The item memory to save.
+pub fn save_memory(path: impl AsRef<Path>, memory: &ItemMemory) -> Result<(), OVSAError> {
+    save_memory_with_metadata(path, memory, Metadata::new(ArtifactKind::Memory, memory.dimension()))
+}
+
+
+/// Writes an item memory of sparse binary vectors as JSON, embedding the given metadata.
+/// The density is filled in from the vectors.
+/// # Arguments
+/// * `path` - The file to write.
+/// * `memory` - The item memory to save.
+/// * `metadata` - The metadata, e.g. with the seed and encoder parameters of the symbols.
+pub fn save_memory_with_metadata(path: impl AsRef<Path>, memory: &ItemMemory, metadata: Metadata) -> Result<(), OVSAError> {
+    let samples: Samples = memory.iter().map(|(label, vector)| (vector.clone(), label.to_string())).collect();
+    write_vectors(path, memory.dimension(), &samples, metadata, ArtifactKind::Memory)
+}
+
+
+/// Reads an item memory written by `save_memory`.
+/// # Arguments
+/// * `path` - The file to read.
+/// # Returns
+/// The loaded `ItemMemory`, or `IncompatibleArtifact` if the file holds another kind of artifact.
+pub fn load_memory(path: impl AsRef<Path>) -> Result<ItemMemory, OVSAError> {
+    let (dimension, samples, _) = read_vectors(path, &[ArtifactKind::Memory])?;
+    to_memory(dimension, samples)
+}
+
+
+/// Reads an item memory written by `save_memory` and checks it against the metadata the caller requires,
+/// e.g. the dimension, seed and encoder parameters of the symbols it will be combined with.
+/// # Arguments
+/// * `path` - The file to read.
+/// * `expected` - The metadata the artifact must be compatible with, see `Metadata::ensure_compatible`.
+/// # Returns
+/// The loaded `ItemMemory`, or `IncompatibleArtifact` describing the first mismatch.
+pub fn load_memory_expecting(path: impl AsRef<Path>, expected: &Metadata) -> Result<ItemMemory, OVSAError> {
+    let (dimension, samples, metadata) = read_vectors(path, &[ArtifactKind::Memory])?;
+    check_expected(metadata, dimension, expected)?;
+    to_memory(dimension, samples)
+}
+
+
+/// Writes labelled sparse binary vectors, e.g. an encoded dataset, as JSON with default metadata. Labels may repeat.
+/// # Arguments
+/// * `path` - The file to write.
+/// * `dimension` - The dimension of the vectors.
+/// * `samples` - The (vector, label) pairs to save.
+pub fn save_samples(path: impl AsRef<Path>, dimension: usize, samples: &[(CsVec<i8>, String)]) -> Result<(), OVSAError> {
+    save_samples_with_metadata(path, dimension, samples, Metadata::new(ArtifactKind::Samples, dimension))
+}
+
+
+/// Writes labelled sparse binary vectors as JSON, embedding the given metadata.
+/// # Arguments
+/// * `path` - The file to write.
+/// * `dimension` - The dimension of the vectors.
+/// * `samples` - The (vector, label) pairs to save.
+/// * `metadata` - The metadata, e.g. with the parameters of the encoder that produced the samples.
+pub fn save_samples_with_metadata(path: impl AsRef<Path>, dimension: usize, samples: &[(CsVec<i8>, String)], metadata: Metadata) -> Result<(), OVSAError> {
+    write_vectors(path, dimension, samples, metadata, ArtifactKind::Samples)
+}
+
+
+/// Reads labelled sparse binary vectors written by `save_samples` or `save_memory`.
+/// # Arguments
+/// * `path` - The file to read.
+/// # Returns
+/// The dimension of the vectors and the (vector, label) pairs.
+pub fn load_samples(path: impl AsRef<Path>) -> Result<(usize, Samples), OVSAError> {
+    let (dimension, samples, _) = read_vectors(path, &[ArtifactKind::Samples, ArtifactKind::Memory])?;
+    Ok((dimension, samples))
+}
+
+
+/// Reads labelled sparse binary vectors and checks them against the metadata the caller requires,
+/// e.g. the encoder parameters a classifier was trained with.
+/// # Arguments
+/// * `path` - The file to read.
+/// * `expected` - The metadata the artifact must be compatible with, see `Metadata::ensure_compatible`.
+/// # Returns
+/// The dimension of the vectors and the (vector, label) pairs, or `IncompatibleArtifact` describing the first mismatch.
+pub fn load_samples_expecting(path: impl AsRef<Path>, expected: &Metadata) -> Result<(usize, Samples), OVSAError> {
+    let (dimension, samples, metadata) = read_vectors(path, &[ArtifactKind::Samples, ArtifactKind::Memory])?;
+    check_expected(metadata, dimension, expected)?;
+    Ok((dimension, samples))
+}
+
+
+/// Writes a centroid classifier as JSON, including its per-class counts so that training can be resumed, and its
+/// calibration.
+/// # Arguments
+/// * `path` - The file to write.
+/// * `classifier` - The classifier to save.
+pub fn save_classifier(path: impl AsRef<Path>, classifier: &CentroidClassifier) -> Result<(), OVSAError> {
+    save_classifier_with_metadata(path, classifier, Metadata::new(ArtifactKind::Classifier, classifier.dimension()))
+}
+
+
+/// Writes a centroid classifier as JSON, embedding the given metadata.
+/// # Arguments
+/// * `path` - The file to write.
+/// * `classifier` - The classifier to save.
+/// * `metadata` - The metadata, e.g. with the parameters of the encoder the classifier was trained on.
+pub fn save_classifier_with_metadata(path: impl AsRef<Path>, classifier: &CentroidClassifier, mut metadata: Metadata) -> Result<(), OVSAError> {
+    metadata.ensure_compatible(&Metadata::new(ArtifactKind::Classifier, classifier.dimension()))?;
+    let classes: Vec<StoredClass> = classifier.prototypes().labels().iter()
+        .map(|label| {
+            let (counts, n) = classifier.counts(label).expect("Every prototype has counts.");
+            let counts = counts.iter().enumerate().filter(|&(_, &count)| count > 0).map(|(index, &count)| (index, count)).collect();
+            StoredClass { label: label.clone(), n, counts }
+        })
+        .collect();
+    let prototypes = classifier.prototypes();
+    metadata.density = density(prototypes.iter().map(|(_, vector)| vector), classifier.dimension());
+
+    write_json(path, &StoredClassifier {
+        format_version: FORMAT_VERSION,
+        metadata: Some(metadata),
+        dimension: classifier.dimension(),
+        classes,
+        calibration: classifier.calibration(),
+    })
+}
+
+
+/// Reads a centroid classifier written by `save_classifier`.
+/// # Arguments
+/// * `path` - The file to read.
+/// # Returns
+/// The loaded `CentroidClassifier`, or `IncompatibleArtifact` if the file holds another kind of artifact.
+pub fn load_classifier(path: impl AsRef<Path>) -> Result<CentroidClassifier, OVSAError> {
+    let stored: StoredClassifier = read_artifact(path, &[ArtifactKind::Classifier])?;
+    validate(stored.metadata.as_ref(), stored.dimension)?;
+    to_classifier(stored)
+}
+
+
+/// Reads a centroid classifier and checks it against the metadata the caller requires,
+/// e.g. the dimension and encoder parameters of the vectors it will classify.
+/// # Arguments
+/// * `path` - The file to read.
+/// * `expected` - The metadata the artifact must be compatible with, see `Metadata::ensure_compatible`.
+/// # Returns
+/// The loaded `CentroidClassifier`, or `IncompatibleArtifact` describing the first mismatch.
+pub fn load_classifier_expecting(path: impl AsRef<Path>, expected: &Metadata) -> Result<CentroidClassifier, OVSAError> {
+    let mut stored: StoredClassifier = read_artifact(path, &[ArtifactKind::Classifier])?;
+    validate(stored.metadata.as_ref(), stored.dimension)?;
+    check_expected(stored.metadata.take(), stored.dimension, expected)?;
+    to_classifier(stored)
+}
+
+
+fn to_classifier(stored: StoredClassifier) -> Result<CentroidClassifier, OVSAError> {
+    let classes = stored.classes.into_iter()
+        .map(|class| Ok((class.label, to_counts(stored.dimension, &class.counts)?, class.n)))
+        .collect::<Result<Vec<_>, OVSAError>>()?;
+    let mut classifier = CentroidClassifier::from_counts(stored.dimension, classes)?;
+    classifier.set_calibration(stored.calibration);
+    Ok(classifier)
+}
+
+
+/// Reads the metadata of a saved artifact without loading its vectors.
+/// # Arguments
+/// * `path` - The file to read.
+/// # Returns
+/// The metadata, or `None` for files written before metadata existed.
+pub fn read_metadata(path: impl AsRef<Path>) -> Result<Option<Metadata>, OVSAError> {
+    #[derive(Deserialize)]
+    struct Header {
+        #[serde(default)]
+        metadata: Option<Metadata>,
+    }
+
+    Ok(read_json::<Header>(path)?.metadata)
+}
+
+
+fn write_vectors(path: impl AsRef<Path>, dimension: usize, samples: &[(CsVec<i8>, String)], mut metadata: Metadata, kind: ArtifactKind) -> Result<(), OVSAError> {
+    metadata.ensure_compatible(&Metadata::new(kind, dimension))?;
+    metadata.density = density(samples.iter().map(|(vector, _)| vector), dimension);
+
+    let stored = StoredVectors {
+        format_version: FORMAT_VERSION,
+        metadata: Some(metadata),
+        dimension,
+        vectors: samples.iter().map(|(vector, label)| StoredVector { label: label.clone(), indices: vector.indices().to_vec() }).collect(),
+    };
+    write_json(path, &stored)
+}
+
+
+fn read_vectors(path: impl AsRef<Path>, kinds: &[ArtifactKind]) -> Result<(usize, Samples, Option<Metadata>), OVSAError> {
+    let stored: StoredVectors = read_artifact(path, kinds)?;
+    validate(stored.metadata.as_ref(), stored.dimension)?;
+
+    let mut samples = Vec::with_capacity(stored.vectors.len());
+    for vector in stored.vectors {
+        samples.push((to_vector(stored.dimension, vector.indices)?, vector.label));
+    }
+
+    Ok((stored.dimension, samples, stored.metadata))
+}
+
+
+fn to_memory(dimension: usize, samples: Samples) -> Result<ItemMemory, OVSAError> {
+    let mut memory = ItemMemory::new(dimension)?;
+    for (vector, label) in samples {
+        memory.insert(&label, vector)?;
+    }
+
+    Ok(memory)
+}
+
+
+/// Reads an artifact, checking the kind recorded in its metadata, if any, before parsing the rest of the file so that
+/// a file of another kind is reported as such rather than as malformed.
+fn read_artifact<T: DeserializeOwned>(path: impl AsRef<Path>, kinds: &[ArtifactKind]) -> Result<T, OVSAError> {
+    let json = migrate(read_json(path)?)?;
+    if let Some(header) = json.get("metadata") {
+        let metadata: Metadata = serde_json::from_value(header.clone()).map_err(|error| OVSAError::InvalidFormat(error.to_string()))?;
+        if !kinds.contains(&metadata.kind) {
+            return Err(OVSAError::IncompatibleArtifact(format!("kind is {:?}, expected one of {:?}", metadata.kind, kinds)));
+        }
+    }
+
+    serde_json::from_value(json).map_err(|error| OVSAError::InvalidFormat(error.to_string()))
+}
+
+
+/// Returns the format version of a saved artifact; files written before versions were recorded are version 1.
+/// # Arguments
+/// * `path` - The file to read.
+pub fn format_version(path: impl AsRef<Path>) -> Result<u32, OVSAError> {
+    version_of(&read_json(path)?)
+}
+
+
+/// Upgrades a parsed artifact to the current format version, one version at a time.
+/// Loading functions call this automatically, so it is only needed to inspect or rewrite old files.
+/// # Arguments
+/// * `json` - The parsed content of an artifact file of any version.
+/// # Returns
+/// The content in the current format, or `IncompatibleArtifact` if it was written by a newer release.
+pub fn migrate(mut json: serde_json::Value) -> Result<serde_json::Value, OVSAError> {
+    let mut version = version_of(&json)?;
+    if version > FORMAT_VERSION {
+        return Err(OVSAError::IncompatibleArtifact(format!("format version {} is newer than the supported version {}", version, FORMAT_VERSION)));
+    }
+
+    while version < FORMAT_VERSION {
+        json = match version {
+            1 => migrate_v1(json)?,
+            _ => unreachable!("every version below the current one has a migration"),
+        };
+        version += 1;
+    }
+
+    Ok(json)
+}
+
+
+/// Rewrites a saved artifact in the current format version.
+/// # Arguments
+/// * `path` - The file to upgrade in place.
+/// # Returns
+/// The version the file had before the upgrade.
+pub fn migrate_file(path: impl AsRef<Path>) -> Result<u32, OVSAError> {
+    let json: serde_json::Value = read_json(&path)?;
+    let version = version_of(&json)?;
+    if version != FORMAT_VERSION {
+        write_json(&path, &migrate(json)?)?;
+    }
+
+    Ok(version)
+}
+
+
+fn version_of(json: &serde_json::Value) -> Result<u32, OVSAError> {
+    if !json.is_object() {
+        return Err(OVSAError::InvalidFormat("an artifact must be a JSON object".to_string()));
+    }
+    match json.get("format_version") {
+        None => Ok(1),
+        Some(version) => version.as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .filter(|&version| version >= 1)
+            .ok_or_else(|| OVSAError::InvalidFormat(format!("invalid format version {}", version))),
+    }
+}
+
+
+/// Version 2 adds the version field and replaces the dense class counts of classifiers by (index, count) pairs;
+/// item memories and samples are unchanged.
+fn migrate_v1(mut json: serde_json::Value) -> Result<serde_json::Value, OVSAError> {
+    if let Some(classes) = json.get_mut("classes").and_then(serde_json::Value::as_array_mut) {
+        for class in classes {
+            let counts = class.get("counts").and_then(serde_json::Value::as_array)
+                .ok_or_else(|| OVSAError::InvalidFormat("every class of a version 1 classifier must have a counts array".to_string()))?;
+            let pairs: Vec<serde_json::Value> = counts.iter().enumerate()
+                .filter(|(_, count)| count.as_u64() != Some(0))
+                .map(|(index, count)| serde_json::json!([index, count]))
+                .collect();
+            class["counts"] = serde_json::Value::from(pairs);
+        }
+    }
+    json["format_version"] = serde_json::Value::from(2);
+    Ok(json)
+}
+
+
+/// Checks that the embedded metadata, if any, agrees with the contents of the artifact.
+fn validate(metadata: Option<&Metadata>, dimension: usize) -> Result<(), OVSAError> {
+    match metadata {
+        Some(metadata) => metadata.ensure_compatible(&Metadata::new(metadata.kind, dimension)),
+        None => Ok(()),
+    }
+}
+
+
+/// Checks an artifact against the metadata required by the caller. Files written before metadata existed only record
+/// their dimension, so that is all that can be checked for them.
+fn check_expected(metadata: Option<Metadata>, dimension: usize, expected: &Metadata) -> Result<(), OVSAError> {
+    metadata.unwrap_or_else(|| Metadata::new(expected.kind, dimension)).ensure_compatible(expected)
+}
+
+
+/// Returns the mean fraction of active entries of the vectors, or `None` if there are none.
+fn density<'a>(vectors: impl Iterator<Item = &'a CsVec<i8>>, dimension: usize) -> Option<f64> {
+    let (count, active) = vectors.fold((0usize, 0usize), |(count, active), vector| (count + 1, active + vector.nnz()));
+    (count > 0 && dimension > 0).then(|| active as f64 / (count * dimension) as f64)
+}
+
+
+/// Expands the stored (index, count) pairs of a class to one count per dimension.
+fn to_counts(dimension: usize, pairs: &[(usize, u32)]) -> Result<Vec<u32>, OVSAError> {
+    let mut counts = vec![0; dimension];
+    for &(index, count) in pairs {
+        *counts.get_mut(index).ok_or_else(|| OVSAError::InvalidFormat(format!("count index {} out of range for dimension {}", index, dimension)))? = count;
+    }
+
+    Ok(counts)
+}
+
+
+/// Checks stored indices before building a vector from them, since they come from an untrusted file.
+fn to_vector(dimension: usize, mut indices: Vec<usize>) -> Result<CsVec<i8>, OVSAError> {
+    indices.sort_unstable();
+    indices.dedup();
+    if dimension == 0 {
+        return Err(OVSAError::ZeroDimension);
+    }
+    if indices.last().is_some_and(|&index| index >= dimension) {
+        return Err(OVSAError::InvalidFormat(format!("index out of range for dimension {}", dimension)));
+    }
+
+    Ok(from_indices_or_empty(dimension, indices))
+}
+
+
+/// Writes the JSON to a temporary file next to the target and renames it over the target once it is on disk,
+/// so that a crash or a failed write never leaves a truncated artifact behind.
+fn write_json<T: Serialize>(path: impl AsRef<Path>, value: &T) -> Result<(), OVSAError> {
+    let path = path.as_ref();
+    let json = serde_json::to_string(value).map_err(|error| OVSAError::InvalidFormat(error.to_string()))?;
+    let file_name = path.file_name().ok_or_else(|| OVSAError::Io(format!("{} is not a file path", path.display())))?;
+    let temporary = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    let written = File::create(&temporary)
+        .and_then(|mut file| file.write_all(json.as_bytes()).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&temporary, path));
+    if let Err(error) = written {
+        let _ = fs::remove_file(&temporary);
+        return Err(OVSAError::Io(error.to_string()));
+    }
+
+    Ok(())
+}
+
+
+fn read_json<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, OVSAError> {
+    let json = fs::read_to_string(path).map_err(|error| OVSAError::Io(error.to_string()))?;
+    serde_json::from_str(&json).map_err(|error| OVSAError::InvalidFormat(error.to_string()))
+}
@@ -0,0 +1,180 @@
+//! Anomaly detection from normal samples only: the samples are bundled into one or a few prototypes, and a new sample
+//! scores by its dissimilarity to the closest prototype. The decision threshold is calibrated on held-out normal
+//! samples for a target false positive rate.
+
+use rand::Rng;
+use rand::seq::index::sample;
+use sprs::CsVec;
+
+use crate::binary::{consensus_sum_with_rng, similarity};
+use crate::errors::OVSAError;
+use crate::memory::{ItemMemory, Recognition};
+use crate::rng::with_global_rng;
+
+
+/// The number of assignment rounds when fitting several prototypes.
+const CLUSTER_ROUNDS: usize = 10;
+
+
+/// Scores samples by their dissimilarity to prototypes of normal behaviour.
+#[derive(Debug, Clone)]
+pub struct AnomalyDetector {
+    prototypes: ItemMemory,
+    threshold: Option<f64>,
+}
+
+
+impl AnomalyDetector {
+    /// Fits prototypes to normal samples.
+    /// # Arguments
+    /// * `normal` - The normal samples.
+    /// * `n_prototypes` - The number of prototypes; more than one covers normal behaviour made of several modes.
+    /// # Returns
+    /// A new, uncalibrated `AnomalyDetector`.
+    pub fn fit(normal: &[CsVec<i8>], n_prototypes: usize) -> Result<Self, OVSAError> {
+        with_global_rng(|rng| Self::fit_with_rng(normal, n_prototypes, rng))
+    }
+
+    /// Fits prototypes to normal samples using the provided random number generator. Several prototypes are found by
+    /// k-means clustering: prototypes start as random samples, and every round assigns the samples to their most
+    /// similar prototype and bundles every cluster anew.
+    /// # Arguments
+    /// * `normal` - The normal samples.
+    /// * `n_prototypes` - The number of prototypes, at most the number of samples.
+    /// * `rng` - The random number generator choosing the initial prototypes and breaking bundling ties.
+    /// # Returns
+    /// A new, uncalibrated `AnomalyDetector`.
+    pub fn fit_with_rng<R: Rng + ?Sized>(normal: &[CsVec<i8>], n_prototypes: usize, rng: &mut R) -> Result<Self, OVSAError> {
+        if normal.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+        if n_prototypes == 0 {
+            return Err(OVSAError::InvalidArgument("the number of prototypes must be at least 1, got 0".to_string()));
+        }
+        if n_prototypes > normal.len() {
+            return Err(OVSAError::InvalidArgument(format!("cannot fit {} prototypes to {} samples", n_prototypes, normal.len())));
+        }
+
+        let centers = cluster_with_rng(normal, n_prototypes, rng)?;
+        let mut prototypes = ItemMemory::new(normal[0].dim())?;
+        for (index, center) in centers.into_iter().enumerate() {
+            prototypes.insert(&format!("normal{}", index), center)?;
+        }
+        Ok(AnomalyDetector { prototypes, threshold: None })
+    }
+
+    /// Returns the prototypes of normal behaviour, labelled `normal0`, `normal1`, ...
+    pub fn prototypes(&self) -> &ItemMemory {
+        &self.prototypes
+    }
+
+    /// Returns the calibrated threshold, if any.
+    pub fn threshold(&self) -> Option<f64> {
+        self.threshold
+    }
+
+    /// Sets the threshold directly, e.g. one calibrated earlier.
+    /// # Arguments
+    /// * `threshold` - The score above which a sample is anomalous.
+    pub fn set_threshold(&mut self, threshold: f64) {
+        self.threshold = Some(threshold);
+    }
+
+    /// Scores a sample: 1 minus its similarity to the closest prototype, so that higher scores are more anomalous.
+    /// # Arguments
+    /// * `vector` - The sample.
+    pub fn score(&self, vector: &CsVec<i8>) -> Result<f64, OVSAError> {
+        let (_, best) = self.prototypes.cleanup(vector)?.ok_or(OVSAError::EmptyVectorList)?;
+        Ok(1.0 - best)
+    }
+
+    /// Sets the threshold so that about the given share of held-out normal samples is flagged.
+    /// # Arguments
+    /// * `validation` - Normal samples not used for fitting.
+    /// * `false_positive_rate` - The target share of normal samples flagged, in `[0, 1)`.
+    /// # Returns
+    /// The threshold, the `1 - false_positive_rate` quantile of the validation scores.
+    pub fn calibrate(&mut self, validation: &[CsVec<i8>], false_positive_rate: f64) -> Result<f64, OVSAError> {
+        if validation.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+        if !(0.0..1.0).contains(&false_positive_rate) {
+            return Err(OVSAError::InvalidArgument(format!("false positive rate must be in [0, 1), got {}", false_positive_rate)));
+        }
+
+        let mut scores = validation.iter().map(|vector| self.score(vector)).collect::<Result<Vec<_>, OVSAError>>()?;
+        scores.sort_by(f64::total_cmp);
+        // the smallest score exceeded by at most the target share of the validation samples
+        let n_flagged = (false_positive_rate * scores.len() as f64).floor() as usize;
+        let threshold = scores[scores.len() - 1 - n_flagged];
+        self.threshold = Some(threshold);
+        Ok(threshold)
+    }
+
+    /// Scores a sample and, if it is anomalous, adopts it as a new prototype of normal behaviour, so that a recurring
+    /// novelty is only flagged once. The threshold is kept, so later samples of the novelty score as normal.
+    /// # Arguments
+    /// * `vector` - The sample.
+    /// # Returns
+    /// `Recognition::Known` with the closest prototype, or `Recognition::Novel` with the label of the new prototype.
+    pub fn absorb(&mut self, vector: &CsVec<i8>) -> Result<Recognition, OVSAError> {
+        let threshold = self.threshold.ok_or_else(|| OVSAError::InvalidArgument("the anomaly detector is not calibrated".to_string()))?;
+        // a score of at most the threshold is a similarity of at least 1 - threshold
+        self.prototypes.recognize_or_insert(vector, 1.0 - threshold, "normal")
+    }
+
+    /// Decides whether a sample is anomalous, i.e. scores above the calibrated threshold.
+    /// # Arguments
+    /// * `vector` - The sample.
+    /// # Returns
+    /// Whether the sample is anomalous, or `InvalidArgument` if the detector was not calibrated.
+    pub fn is_anomaly(&self, vector: &CsVec<i8>) -> Result<bool, OVSAError> {
+        let threshold = self.threshold.ok_or_else(|| OVSAError::InvalidArgument("the anomaly detector is not calibrated".to_string()))?;
+        Ok(self.score(vector)? > threshold)
+    }
+}
+
+
+/// Clusters vectors by k-means: a single cluster is the bundle of all vectors; otherwise centers start as random
+/// vectors, and every round assigns the vectors to their most similar center and bundles every cluster anew.
+/// # Arguments
+/// * `vectors` - The vectors, at least `k`.
+/// * `k` - The number of clusters, at least 1.
+/// * `rng` - The random number generator choosing the initial centers and breaking bundling ties.
+/// # Returns
+/// The centers of the clusters.
+pub(crate) fn cluster_with_rng<R: Rng + ?Sized>(vectors: &[CsVec<i8>], k: usize, rng: &mut R) -> Result<Vec<CsVec<i8>>, OVSAError> {
+    if k == 1 {
+        return Ok(vec![consensus_sum_with_rng(vectors, rng)?]);
+    }
+
+    let mut centers: Vec<CsVec<i8>> = sample(rng, vectors.len(), k).into_iter().map(|index| vectors[index].clone()).collect();
+    for _ in 0..CLUSTER_ROUNDS {
+        let mut clusters: Vec<Vec<CsVec<i8>>> = vec![Vec::new(); k];
+        for vector in vectors {
+            clusters[closest(&centers, vector)?.0].push(vector.clone());
+        }
+        let updated = clusters.iter().zip(&centers)
+            .map(|(cluster, center)| if cluster.is_empty() { Ok(center.clone()) } else { consensus_sum_with_rng(cluster, rng) })
+            .collect::<Result<Vec<_>, OVSAError>>()?;
+        if updated == centers {
+            break;
+        }
+        centers = updated;
+    }
+
+    Ok(centers)
+}
+
+
+/// Returns the position and similarity of the center most similar to the vector.
+pub(crate) fn closest(centers: &[CsVec<i8>], vector: &CsVec<i8>) -> Result<(usize, f64), OVSAError> {
+    let mut best = (0, f64::NEG_INFINITY);
+    for (position, center) in centers.iter().enumerate() {
+        let score = similarity(center, vector)?;
+        if score > best.1 {
+            best = (position, score);
+        }
+    }
+    Ok(best)
+}
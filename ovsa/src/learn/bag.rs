@@ -0,0 +1,203 @@
+//! Multi-instance classification, where a sample is a bag of encoded instances of any size. Every bag is pooled into
+//! one vector, either the majority of its instances or a majority weighted by attention: an instance weighs in with the
+//! softmax of its similarity to the closest class prototype, so the instances the model recognizes dominate the bag.
+//! The pooled vectors are what the underlying `CentroidClassifier` counts, and retraining pools every bag again with
+//! the current prototypes before predicting it, so a correction adds and subtracts the very vector it predicted.
+
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::{consensus_sum_with_rng, similarity, weighted_consensus_sum_with_rng};
+use crate::errors::OVSAError;
+use crate::learn::CentroidClassifier;
+use crate::rng::with_global_rng;
+use crate::trace::span;
+
+
+/// How a `BagClassifier` pools the instances of a bag.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Pooling {
+    /// The majority of the instances.
+    #[default]
+    Majority,
+    /// The majority of the instances weighted by the softmax of their similarity to the closest prototype divided by
+    /// the temperature; a low temperature approaches the single best recognized instance. Instances weigh the same
+    /// until the classifier is trained.
+    Attention { temperature: f64 },
+}
+
+
+/// A centroid classifier of bags of instances.
+#[derive(Debug, Clone)]
+pub struct BagClassifier {
+    classifier: CentroidClassifier,
+    pooling: Pooling,
+}
+
+
+impl BagClassifier {
+    /// Creates an untrained classifier.
+    /// # Arguments
+    /// * `dimension` - The dimension of the instances.
+    /// * `pooling` - How the instances of a bag are pooled.
+    /// # Returns
+    /// A new `BagClassifier`, or `InvalidArgument` for an attention temperature that is not finite and positive.
+    pub fn new(dimension: usize, pooling: Pooling) -> Result<Self, OVSAError> {
+        if let Pooling::Attention { temperature } = pooling && (!temperature.is_finite() || temperature <= 0.0) {
+            return Err(OVSAError::InvalidArgument(format!("invalid attention temperature {}", temperature)));
+        }
+
+        Ok(BagClassifier { classifier: CentroidClassifier::new(dimension)?, pooling })
+    }
+
+    /// Returns how the instances of a bag are pooled.
+    pub fn pooling(&self) -> Pooling {
+        self.pooling
+    }
+
+    /// Returns the underlying classifier of pooled vectors.
+    pub fn classifier(&self) -> &CentroidClassifier {
+        &self.classifier
+    }
+
+    /// Computes the weight of every instance of a bag in its pooled vector.
+    /// # Arguments
+    /// * `bag` - The instances.
+    /// # Returns
+    /// The weights of the instances, which sum to 1.
+    pub fn weights(&self, bag: &[CsVec<i8>]) -> Result<Vec<f64>, OVSAError> {
+        self.validate(bag)?;
+        let uniform = vec![1.0 / bag.len() as f64; bag.len()];
+        let Pooling::Attention { temperature } = self.pooling else {
+            return Ok(uniform);
+        };
+        if self.classifier.prototypes().is_empty() {
+            return Ok(uniform);
+        }
+
+        let mut scores = Vec::with_capacity(bag.len());
+        for instance in bag {
+            let mut closest = f64::NEG_INFINITY;
+            for (_, prototype) in self.classifier.prototypes().iter() {
+                closest = closest.max(similarity(prototype, instance)?);
+            }
+            scores.push(closest);
+        }
+        let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let exps: Vec<f64> = scores.iter().map(|score| ((score - max) / temperature).exp()).collect();
+        let sum: f64 = exps.iter().sum();
+        Ok(exps.into_iter().map(|value| value / sum).collect())
+    }
+
+    /// Pools the instances of a bag into one vector.
+    /// # Arguments
+    /// * `bag` - The instances.
+    /// # Returns
+    /// The pooled vector.
+    pub fn pool(&self, bag: &[CsVec<i8>]) -> Result<CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.pool_with_rng(bag, rng))
+    }
+
+    /// Pools the instances of a bag, breaking ties with the provided random number generator.
+    /// # Arguments
+    /// * `bag` - The instances.
+    /// * `rng` - The random number generator used to break ties.
+    /// # Returns
+    /// The pooled vector.
+    pub fn pool_with_rng<R: Rng + ?Sized>(&self, bag: &[CsVec<i8>], rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        match self.pooling {
+            Pooling::Majority => {
+                self.validate(bag)?;
+                consensus_sum_with_rng(bag, rng)
+            }
+            Pooling::Attention { .. } => weighted_consensus_sum_with_rng(bag, &self.weights(bag)?, rng),
+        }
+    }
+
+    /// Adds training bags and updates the prototypes of the affected classes.
+    /// # Arguments
+    /// * `bags` - The (instances, label) training pairs.
+    pub fn fit(&mut self, bags: &[(Vec<CsVec<i8>>, String)]) -> Result<(), OVSAError> {
+        with_global_rng(|rng| self.fit_with_rng(bags, rng))
+    }
+
+    /// Adds training bags, breaking ties with the provided random number generator. The bags are pooled with the
+    /// prototypes from before the call.
+    /// # Arguments
+    /// * `bags` - The (instances, label) training pairs.
+    /// * `rng` - The random number generator used to break ties.
+    pub fn fit_with_rng<R: Rng + ?Sized>(&mut self, bags: &[(Vec<CsVec<i8>>, String)], rng: &mut R) -> Result<(), OVSAError> {
+        span!(INFO, "fit_bags", n_bags = bags.len());
+        let pooled = self.pool_all_with_rng(bags, rng)?;
+        self.classifier.fit_with_rng(&pooled, rng)
+    }
+
+    /// Retrains the classifier perceptron-style like `CentroidClassifier::retrain`. Every epoch pools the bags with
+    /// the current prototypes, and a misclassified bag moves its pooled vector from the predicted class to its own.
+    /// # Arguments
+    /// * `bags` - The (instances, label) training pairs, usually those of the initial `fit`.
+    /// * `epochs` - The maximum number of passes over the bags.
+    /// # Returns
+    /// The number of corrections of every epoch run.
+    pub fn retrain(&mut self, bags: &[(Vec<CsVec<i8>>, String)], epochs: usize) -> Result<Vec<usize>, OVSAError> {
+        with_global_rng(|rng| self.retrain_with_rng(bags, epochs, rng))
+    }
+
+    /// Retrains the classifier perceptron-style, breaking ties with the provided random number generator.
+    /// # Arguments
+    /// * `bags` - The (instances, label) training pairs.
+    /// * `epochs` - The maximum number of passes over the bags.
+    /// * `rng` - The random number generator used to break ties.
+    /// # Returns
+    /// The number of corrections of every epoch run.
+    pub fn retrain_with_rng<R: Rng + ?Sized>(&mut self, bags: &[(Vec<CsVec<i8>>, String)], epochs: usize, rng: &mut R) -> Result<Vec<usize>, OVSAError> {
+        span!(INFO, "retrain_bags", n_bags = bags.len(), epochs);
+        let mut corrections = Vec::with_capacity(epochs);
+        for _ in 0..epochs {
+            let pooled = self.pool_all_with_rng(bags, rng)?;
+            let samples: Vec<&(CsVec<i8>, String)> = pooled.iter().collect();
+            corrections.push(self.classifier.correct_with_rng(&samples, rng)?);
+            if corrections.last() == Some(&0) {
+                break;
+            }
+        }
+
+        Ok(corrections)
+    }
+
+    /// Predicts the class of a bag.
+    /// # Arguments
+    /// * `bag` - The instances.
+    /// # Returns
+    /// The (label, similarity) pair of the best class for the pooled vector, or `None` if the classifier was not
+    /// trained.
+    pub fn predict(&self, bag: &[CsVec<i8>]) -> Result<Option<(String, f64)>, OVSAError> {
+        with_global_rng(|rng| self.predict_with_rng(bag, rng))
+    }
+
+    /// Predicts the class of a bag, breaking pooling ties with the provided random number generator.
+    /// # Arguments
+    /// * `bag` - The instances.
+    /// * `rng` - The random number generator used to break ties.
+    /// # Returns
+    /// The (label, similarity) pair of the best class, or `None` if the classifier was not trained.
+    pub fn predict_with_rng<R: Rng + ?Sized>(&self, bag: &[CsVec<i8>], rng: &mut R) -> Result<Option<(String, f64)>, OVSAError> {
+        self.classifier.predict(&self.pool_with_rng(bag, rng)?)
+    }
+
+    fn pool_all_with_rng<R: Rng + ?Sized>(&self, bags: &[(Vec<CsVec<i8>>, String)], rng: &mut R) -> Result<Vec<(CsVec<i8>, String)>, OVSAError> {
+        bags.iter().map(|(bag, label)| Ok((self.pool_with_rng(bag, rng)?, label.clone()))).collect()
+    }
+
+    /// Checks that a bag is not empty and holds instances of the classifier's dimension.
+    fn validate(&self, bag: &[CsVec<i8>]) -> Result<(), OVSAError> {
+        if bag.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+        if bag.iter().any(|instance| instance.dim() != self.classifier.dimension()) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(())
+    }
+}
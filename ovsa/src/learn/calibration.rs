@@ -0,0 +1,175 @@
+//! Calibration of classifier similarities to probabilities, fitted on held-out samples that played no part in training:
+//! * temperature scaling - the softmax of the class similarities divided by a temperature, chosen to minimize the
+//!   negative log-likelihood of the true classes;
+//! * Platt scaling - the probability that the predicted class is right, a logistic function of the margin between the
+//!   best and the second best similarity, fitted by Newton's method on Platt's smoothed targets. The remaining
+//!   probability is shared evenly by the other classes.
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::OVSAError;
+
+
+/// The number of golden-section steps of the temperature search.
+const TEMPERATURE_STEPS: usize = 100;
+
+/// The range of temperatures searched, as powers of ten.
+const LOG_TEMPERATURE_RANGE: (f64, f64) = (-4.0, 1.0);
+
+/// The maximum number of Newton steps of the Platt fit.
+const PLATT_STEPS: usize = 100;
+
+
+/// The method `CentroidClassifier::calibrate` fits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationMethod {
+    Temperature,
+    Platt,
+}
+
+
+/// A fitted map from class similarities to probabilities.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum Calibration {
+    /// The softmax of the similarities divided by the temperature.
+    Temperature { temperature: f64 },
+    /// The probability `1 / (1 + exp(-(a * margin + b)))` of the best class.
+    Platt { a: f64, b: f64 },
+}
+
+
+impl Calibration {
+    /// Fits a calibration to the similarities of held-out samples.
+    /// # Arguments
+    /// * `method` - The calibration method.
+    /// * `scores` - The similarity to every class of every sample, all with the same classes in the same order, and
+    ///   the position of the true class.
+    /// # Returns
+    /// The fitted `Calibration`, `EmptyVectorList` without samples or `InvalidArgument` for fewer than two classes.
+    pub fn fit(method: CalibrationMethod, scores: &[(Vec<f64>, usize)]) -> Result<Self, OVSAError> {
+        let n_classes = scores.first().map(|(similarities, _)| similarities.len()).ok_or(OVSAError::EmptyVectorList)?;
+        if n_classes < 2 {
+            return Err(OVSAError::InvalidArgument("calibration needs at least two classes".to_string()));
+        }
+        if scores.iter().any(|(similarities, truth)| similarities.len() != n_classes || *truth >= n_classes) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(match method {
+            CalibrationMethod::Temperature => Calibration::Temperature { temperature: fit_temperature(scores) },
+            CalibrationMethod::Platt => {
+                let (a, b) = fit_platt(scores);
+                Calibration::Platt { a, b }
+            }
+        })
+    }
+
+    /// Maps class similarities to probabilities that sum to 1.
+    /// # Arguments
+    /// * `similarities` - The similarity to every class.
+    /// # Returns
+    /// The probability of every class, in the order of the similarities.
+    pub fn probabilities(&self, similarities: &[f64]) -> Vec<f64> {
+        match *self {
+            Calibration::Temperature { temperature } => softmax(similarities, temperature),
+            Calibration::Platt { a, b } => {
+                let Some((best, margin)) = best_and_margin(similarities) else {
+                    return vec![1.0; similarities.len()];
+                };
+                let confidence = sigmoid(a * margin + b);
+                let rest = (1.0 - confidence) / (similarities.len() - 1) as f64;
+                (0..similarities.len()).map(|class| if class == best { confidence } else { rest }).collect()
+            }
+        }
+    }
+}
+
+
+fn softmax(similarities: &[f64], temperature: f64) -> Vec<f64> {
+    let max = similarities.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let exps: Vec<f64> = similarities.iter().map(|similarity| ((similarity - max) / temperature).exp()).collect();
+    let sum: f64 = exps.iter().sum();
+    exps.into_iter().map(|value| value / sum).collect()
+}
+
+
+fn sigmoid(value: f64) -> f64 {
+    1.0 / (1.0 + (-value).exp())
+}
+
+
+/// Returns the position of the highest similarity, the first one on ties, and its margin over the second highest, or
+/// `None` for fewer than two classes.
+fn best_and_margin(similarities: &[f64]) -> Option<(usize, f64)> {
+    if similarities.len() < 2 {
+        return None;
+    }
+
+    let best = (0..similarities.len()).fold(0, |best, class| if similarities[class] > similarities[best] { class } else { best });
+    let second = (0..similarities.len()).filter(|&class| class != best).map(|class| similarities[class]).fold(f64::NEG_INFINITY, f64::max);
+    Some((best, similarities[best] - second))
+}
+
+
+/// Finds the temperature of the lowest negative log-likelihood by golden-section search over its logarithm.
+fn fit_temperature(scores: &[(Vec<f64>, usize)]) -> f64 {
+    let loss = |log_temperature: f64| -> f64 {
+        let temperature = 10f64.powf(log_temperature);
+        scores.iter().map(|(similarities, truth)| -softmax(similarities, temperature)[*truth].max(f64::MIN_POSITIVE).ln()).sum()
+    };
+
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = LOG_TEMPERATURE_RANGE;
+    for _ in 0..TEMPERATURE_STEPS {
+        let (left, right) = (high - ratio * (high - low), low + ratio * (high - low));
+        if loss(left) <= loss(right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+
+    10f64.powf((low + high) / 2.0)
+}
+
+
+/// Fits the logistic function of the margin to whether the best class is the true one, by Newton's method on the
+/// cross-entropy with Platt's smoothed targets, which keep the fit finite when all predictions are right.
+fn fit_platt(scores: &[(Vec<f64>, usize)]) -> (f64, f64) {
+    let samples: Vec<(f64, bool)> = scores.iter()
+        .filter_map(|(similarities, truth)| best_and_margin(similarities).map(|(best, margin)| (margin, best == *truth)))
+        .collect();
+    let n_right = samples.iter().filter(|(_, right)| *right).count() as f64;
+    let n_wrong = samples.len() as f64 - n_right;
+    let (target_right, target_wrong) = ((n_right + 1.0) / (n_right + 2.0), 1.0 / (n_wrong + 2.0));
+
+    let (mut a, mut b) = (1.0, ((n_right + 1.0) / (n_wrong + 1.0)).ln());
+    for _ in 0..PLATT_STEPS {
+        // gradient and Hessian of the cross-entropy, with a small ridge keeping the Hessian invertible
+        let (mut ga, mut gb, mut haa, mut hab, mut hbb) = (0.0, 0.0, 1e-9, 0.0, 1e-9);
+        for &(margin, right) in &samples {
+            let p = sigmoid(a * margin + b);
+            let error = p - if right { target_right } else { target_wrong };
+            let weight = p * (1.0 - p);
+            ga += error * margin;
+            gb += error;
+            haa += weight * margin * margin;
+            hab += weight * margin;
+            hbb += weight;
+        }
+
+        let determinant = haa * hbb - hab * hab;
+        if !determinant.is_finite() || determinant <= 0.0 {
+            break;
+        }
+        let (da, db) = ((hbb * ga - hab * gb) / determinant, (haa * gb - hab * ga) / determinant);
+        a -= da;
+        b -= db;
+        if da.abs() < 1e-10 && db.abs() < 1e-10 {
+            break;
+        }
+    }
+
+    (a, b)
+}
@@ -0,0 +1,301 @@
+//! Compression of trained classifiers for deployment: the training counters are dropped, quantizing every class to its
+//! one-bit prototype, the prototypes are kept in the smaller of a gap-encoded or bit-packed layout, the least
+//! discriminative dimensions are pruned and, if allowed, the most similar classes are merged until the model fits a
+//! memory budget.
+
+use std::collections::HashMap;
+use sprs::CsVec;
+
+use crate::analysis::dimension_scores;
+use crate::binary::compressed::CompressedBinary;
+use crate::binary::{from_indices_or_empty, similarity};
+use crate::errors::OVSAError;
+use crate::learn::CentroidClassifier;
+use crate::mask::DimensionMask;
+use crate::memory::{ItemMemory, MemoryUsage};
+use crate::trace::span;
+
+
+/// The settings of `CentroidClassifier::compress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressParams {
+    /// The smallest number of dimensions pruning may keep.
+    pub min_dimension: usize,
+    /// Whether the two most similar classes may be merged, repeatedly, when pruning alone does not fit the budget.
+    pub merge: bool,
+}
+
+
+impl Default for CompressParams {
+    fn default() -> Self {
+        CompressParams { min_dimension: 64, merge: false }
+    }
+}
+
+
+/// The outcome of `CentroidClassifier::compress`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressionReport {
+    /// The bytes of the original classifier, training counters included.
+    pub original_bytes: usize,
+    /// The bytes of the compressed classifier.
+    pub compressed_bytes: usize,
+    /// The number of dimensions kept.
+    pub n_kept: usize,
+    /// The number of merges performed.
+    pub n_merged: usize,
+    /// The accuracy of the original classifier on the validation set.
+    pub original_accuracy: f64,
+    /// The accuracy of the compressed classifier on the validation set, a prediction of a merged class counting as
+    /// correct if the class contains the true label.
+    pub compressed_accuracy: f64,
+}
+
+
+/// A classifier ready for deployment: compressed prototypes over the kept dimensions of a mask. It cannot be trained
+/// further.
+#[derive(Debug, Clone)]
+pub struct CompressedClassifier {
+    dimension: usize,
+    kept: Option<KeptBits>,
+    prototypes: ItemMemory<CompressedBinary>,
+    members: HashMap<String, Vec<String>>,
+}
+
+
+/// The kept dimensions as a bit set, with the number of kept dimensions before every word so that a kept dimension is
+/// mapped to its projected position with one popcount.
+#[derive(Debug, Clone)]
+struct KeptBits {
+    words: Vec<u64>,
+    ranks: Vec<u32>,
+}
+
+
+impl KeptBits {
+    fn new(mask: &DimensionMask) -> Self {
+        let mut words = vec![0u64; mask.dimension().div_ceil(64)];
+        for &index in mask.kept() {
+            words[index / 64] |= 1 << (index % 64);
+        }
+        let ranks = words.iter()
+            .scan(0u32, |rank, word| {
+                let before = *rank;
+                *rank += word.count_ones();
+                Some(before)
+            })
+            .collect();
+        KeptBits { words, ranks }
+    }
+
+    fn position(&self, index: usize) -> Option<usize> {
+        let (word, bit) = (self.words[index / 64], index % 64);
+        (word >> bit & 1 == 1).then(|| self.ranks[index / 64] as usize + (word & ((1 << bit) - 1)).count_ones() as usize)
+    }
+
+    fn bytes(&self) -> usize {
+        self.words.len() * size_of::<u64>() + self.ranks.len() * size_of::<u32>()
+    }
+}
+
+
+impl CompressedClassifier {
+    /// Returns the dimension of the classified vectors.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the number of kept dimensions, the dimension of the prototypes.
+    pub fn n_kept(&self) -> usize {
+        self.prototypes.dimension()
+    }
+
+    /// Returns the mask selecting the kept dimensions, or `None` if no dimension was pruned.
+    pub fn mask(&self) -> Option<DimensionMask> {
+        self.kept.as_ref().map(|kept| {
+            let indices = (0..self.dimension).filter(|&index| kept.position(index).is_some()).collect();
+            DimensionMask::new(self.dimension, indices).expect("The kept dimensions are valid.")
+        })
+    }
+
+    /// Returns the compressed prototypes, over the kept dimensions.
+    pub fn prototypes(&self) -> &ItemMemory<CompressedBinary> {
+        &self.prototypes
+    }
+
+    /// Returns the original labels of a class: the label itself, or the labels merged into it.
+    /// # Arguments
+    /// * `label` - A label returned by `predict`.
+    pub fn members(&self, label: &str) -> Option<&[String]> {
+        self.members.get(label).map(Vec::as_slice)
+    }
+
+    /// Estimates the bytes used by the classifier: the prototypes, with the kept dimensions and the merged labels
+    /// reported as auxiliary bytes.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = self.prototypes.memory_usage();
+        usage.auxiliary_bytes += self.kept.as_ref().map_or(0, KeptBits::bytes);
+        usage.auxiliary_bytes += self.members.iter()
+            .filter(|(_, members)| members.len() > 1)
+            .map(|(_, members)| members.iter().map(|member| member.len() + size_of::<String>()).sum::<usize>())
+            .sum::<usize>();
+        usage
+    }
+
+    /// Predicts the class whose prototype is most similar to the vector.
+    /// # Arguments
+    /// * `vector` - The vector to classify, of the dimension of the original classifier.
+    /// # Returns
+    /// The (label, similarity) pair of the best class; merged classes are labelled by their members joined with `+`.
+    pub fn predict(&self, vector: &CsVec<i8>) -> Result<Option<(String, f64)>, OVSAError> {
+        if vector.dim() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        let projected = match &self.kept {
+            Some(kept) => {
+                let indices = vector.indices().iter().filter_map(|&index| kept.position(index)).collect();
+                from_indices_or_empty(self.n_kept(), indices)
+            }
+            None => vector.clone(),
+        };
+        self.prototypes.cleanup(&CompressedBinary::compress(&projected))
+    }
+}
+
+
+impl CentroidClassifier {
+    /// Compresses the classifier to fit a memory budget, as measured by `CompressedClassifier::memory_usage`.
+    /// The training counters are dropped and the prototypes stored compressed. If that does not fit, the dimensions
+    /// with the lowest `analysis::dimension_scores` are pruned, keeping as many as fit and at least `min_dimension`.
+    /// If that still does not fit and merging is allowed, the two classes with the most similar prototypes are merged
+    /// and pruning is tried again.
+    /// # Arguments
+    /// * `target_bytes` - The memory budget.
+    /// * `validation` - The (vector, label) pairs the accuracy impact is measured on.
+    /// * `params` - The smallest dimension and whether classes may be merged.
+    /// # Returns
+    /// The `CompressedClassifier` and the `CompressionReport`, or `BudgetExceeded` if the budget cannot be met.
+    pub fn compress(&self, target_bytes: usize, validation: &[(CsVec<i8>, String)], params: CompressParams) -> Result<(CompressedClassifier, CompressionReport), OVSAError> {
+        span!(INFO, "compress", n_classes = self.prototypes().len(), target_bytes = target_bytes);
+        if self.prototypes().is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let mut classifier = self.clone();
+        let mut members: HashMap<String, Vec<String>> = self.prototypes().labels().iter()
+            .map(|label| (label.clone(), vec![label.clone()]))
+            .collect();
+        let mut n_merged = 0;
+        let compressed = loop {
+            if let Some(compressed) = fit_budget(&classifier, &members, target_bytes, params.min_dimension)? {
+                break compressed;
+            }
+            if !params.merge || classifier.prototypes().len() < 2 {
+                return Err(OVSAError::BudgetExceeded);
+            }
+            classifier = merge_closest(&classifier, &mut members)?;
+            n_merged += 1;
+        };
+
+        let original = self.predict_batch(&validation.iter().map(|(vector, _)| vector.clone()).collect::<Vec<_>>())?;
+        let original_accuracy = accuracy(validation, original.iter().map(|prediction| prediction.as_ref().map(|(label, _)| vec![label.clone()])));
+        let predictions = validation.iter()
+            .map(|(vector, _)| Ok(compressed.predict(vector)?.and_then(|(label, _)| compressed.members(&label).map(<[String]>::to_vec))))
+            .collect::<Result<Vec<_>, OVSAError>>()?;
+        let compressed_accuracy = accuracy(validation, predictions.into_iter());
+
+        let report = CompressionReport {
+            original_bytes: self.memory_usage().total_bytes(),
+            compressed_bytes: compressed.memory_usage().total_bytes(),
+            n_kept: compressed.n_kept(),
+            n_merged,
+            original_accuracy,
+            compressed_accuracy,
+        };
+        Ok((compressed, report))
+    }
+}
+
+
+/// Finds the largest number of kept dimensions, at least `min_dimension`, whose compressed classifier fits the budget.
+fn fit_budget(classifier: &CentroidClassifier, members: &HashMap<String, Vec<String>>, target_bytes: usize, min_dimension: usize) -> Result<Option<CompressedClassifier>, OVSAError> {
+    let dimension = classifier.dimension();
+    let scores = dimension_scores(classifier)?;
+    let build = |n_kept: usize| -> Result<CompressedClassifier, OVSAError> {
+        let mask = DimensionMask::from_scores(&scores, n_kept)?;
+        let prototypes = classifier.masked(&mask)?.prototypes().compressed();
+        Ok(CompressedClassifier { dimension, kept: Some(KeptBits::new(&mask)), prototypes, members: members.clone() })
+    };
+
+    let full = CompressedClassifier { dimension, kept: None, prototypes: classifier.prototypes().compressed(), members: members.clone() };
+    if full.memory_usage().total_bytes() <= target_bytes {
+        return Ok(Some(full));
+    }
+    let mut low = min_dimension.clamp(1, dimension);
+    let smallest = build(low)?;
+    if smallest.memory_usage().total_bytes() > target_bytes {
+        return Ok(None);
+    }
+
+    // the size grows with the number of kept dimensions, so the largest fitting number is found by bisection
+    let mut best = smallest;
+    let mut high = dimension;
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        let candidate = build(middle)?;
+        if candidate.memory_usage().total_bytes() <= target_bytes {
+            low = middle;
+            best = candidate;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(Some(best))
+}
+
+
+/// Merges the two classes with the most similar prototypes into one class labelled by their members joined with `+`.
+fn merge_closest(classifier: &CentroidClassifier, members: &mut HashMap<String, Vec<String>>) -> Result<CentroidClassifier, OVSAError> {
+    let labels = classifier.prototypes().labels();
+    let mut closest = (0, 1, f64::NEG_INFINITY);
+    for i in 0..labels.len() {
+        for j in i + 1..labels.len() {
+            let (a, b) = (classifier.prototypes().get(&labels[i]), classifier.prototypes().get(&labels[j]));
+            if let (Some(a), Some(b)) = (a, b) {
+                let value = similarity(a, b)?;
+                if value > closest.2 {
+                    closest = (i, j, value);
+                }
+            }
+        }
+    }
+
+    let (first, second) = (&labels[closest.0], &labels[closest.1]);
+    let mut merged_members = members.remove(first).unwrap_or_default();
+    merged_members.extend(members.remove(second).unwrap_or_default());
+    let merged = merged_members.join("+");
+
+    let mut classes = Vec::with_capacity(labels.len() - 1);
+    let (first_counts, first_n) = classifier.counts(first).ok_or(OVSAError::EmptyVectorList)?;
+    let (second_counts, second_n) = classifier.counts(second).ok_or(OVSAError::EmptyVectorList)?;
+    classes.push((merged.clone(), first_counts.iter().zip(second_counts).map(|(a, b)| a + b).collect(), first_n + second_n));
+    for label in labels.iter().filter(|&label| label != first && label != second) {
+        let (counts, n) = classifier.counts(label).ok_or(OVSAError::EmptyVectorList)?;
+        classes.push((label.clone(), counts.to_vec(), n));
+    }
+    members.insert(merged, merged_members);
+
+    CentroidClassifier::from_counts_with_hardening(classifier.dimension(), classes, classifier.hardening())
+}
+
+
+/// The share of validation samples whose label is among the predicted labels.
+fn accuracy(validation: &[(CsVec<i8>, String)], predictions: impl Iterator<Item = Option<Vec<String>>>) -> f64 {
+    if validation.is_empty() {
+        return 0.0;
+    }
+    let correct = validation.iter().zip(predictions)
+        .filter(|((_, label), predicted)| predicted.as_ref().is_some_and(|labels| labels.contains(label)))
+        .count();
+    correct as f64 / validation.len() as f64
+}
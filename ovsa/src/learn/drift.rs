@@ -0,0 +1,202 @@
+//! Lightweight drift detection for deployed models. Every incoming encoded sample yields two statistics:
+//! * its similarity to the closest class prototype, which falls when the model no longer recognizes the inputs;
+//! * its fit to the reference distribution, the mean share of reference samples that have its active dimensions,
+//!   which changes when the encoded data itself shifts, whatever the classes.
+//!
+//! The monitor keeps the running means of both over a sliding window and compares them to their means over the
+//! reference samples, flagging drift once either deviates by more than a threshold number of standard errors.
+
+use std::collections::VecDeque;
+use sprs::CsVec;
+
+use crate::binary::similarity;
+use crate::errors::OVSAError;
+use crate::learn::CentroidClassifier;
+use crate::memory::ItemMemory;
+
+
+/// The default number of standard errors a window mean may deviate from its reference before drift is flagged.
+pub const DEFAULT_THRESHOLD: f64 = 3.0;
+
+
+/// The mean and standard deviation of a statistic over the reference samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Baseline {
+    pub mean: f64,
+    pub std: f64,
+}
+
+
+impl Baseline {
+    fn of(values: &[f64]) -> Self {
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / values.len() as f64;
+        Baseline { mean, std: variance.sqrt() }
+    }
+
+    /// Returns the deviation of a mean of `n` values from the baseline mean, in standard errors.
+    fn z_score(&self, mean: f64, n: usize) -> f64 {
+        (mean - self.mean) / (self.std / (n as f64).sqrt()).max(f64::EPSILON)
+    }
+}
+
+
+/// The state of a `DriftMonitor` after an observation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriftStatus {
+    /// The number of samples observed since the monitor was created or reset.
+    pub n_observed: usize,
+    /// The mean similarity to the closest prototype over the window.
+    pub prototype_similarity: f64,
+    /// The mean fit to the reference distribution over the window.
+    pub reference_fit: f64,
+    /// The deviation of `prototype_similarity` from the reference, in standard errors.
+    pub prototype_z: f64,
+    /// The deviation of `reference_fit` from the reference, in standard errors.
+    pub reference_z: f64,
+    /// Whether the window is full and either deviation exceeds the threshold.
+    pub drift: bool,
+}
+
+
+/// Tracks the statistics of incoming samples against a classifier and reference samples.
+#[derive(Debug, Clone)]
+pub struct DriftMonitor {
+    prototypes: ItemMemory,
+    /// The share of reference samples that have every dimension active.
+    frequencies: Vec<f64>,
+    prototype_baseline: Baseline,
+    reference_baseline: Baseline,
+    window: usize,
+    threshold: f64,
+    recent: VecDeque<(f64, f64)>,
+    sums: (f64, f64),
+    n_observed: usize,
+}
+
+
+impl DriftMonitor {
+    /// Creates a monitor from a trained classifier and reference samples of the data it was trained for, ideally held
+    /// out from training so that the reference similarities are not inflated. The frequencies of the reference
+    /// distribution are estimated from these samples, so they should be many times the window.
+    /// # Arguments
+    /// * `classifier` - The trained classifier, whose prototypes are copied.
+    /// * `reference` - The reference samples.
+    /// * `window` - The number of most recent samples the running means cover.
+    /// # Returns
+    /// A new `DriftMonitor`, `EmptyVectorList` for an untrained classifier or no reference samples, or
+    /// `InvalidArgument` for a zero window.
+    pub fn new(classifier: &CentroidClassifier, reference: &[CsVec<i8>], window: usize) -> Result<Self, OVSAError> {
+        if classifier.prototypes().is_empty() || reference.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+        if window == 0 {
+            return Err(OVSAError::InvalidArgument("the window must hold at least 1 sample, got 0".to_string()));
+        }
+        let dimension = classifier.dimension();
+        if reference.iter().any(|vector| vector.dim() != dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let mut frequencies = vec![0.0; dimension];
+        for vector in reference {
+            for &index in vector.indices() {
+                frequencies[index] += 1.0 / reference.len() as f64;
+            }
+        }
+        let mut monitor = DriftMonitor {
+            prototypes: classifier.prototypes().snapshot(),
+            frequencies,
+            prototype_baseline: Baseline { mean: 0.0, std: 0.0 },
+            reference_baseline: Baseline { mean: 0.0, std: 0.0 },
+            window,
+            threshold: DEFAULT_THRESHOLD,
+            recent: VecDeque::with_capacity(window),
+            sums: (0.0, 0.0),
+            n_observed: 0,
+        };
+
+        let statistics = reference.iter().map(|vector| monitor.statistics(vector)).collect::<Result<Vec<_>, _>>()?;
+        monitor.prototype_baseline = Baseline::of(&statistics.iter().map(|&(prototype, _)| prototype).collect::<Vec<_>>());
+        // a reference sample shares all its active dimensions with itself, so its fit leaves it out of the frequencies
+        let n = reference.len() as f64;
+        let fits: Vec<f64> = statistics.iter().map(|&(_, fit)| if n > 1.0 { (fit * n - 1.0) / (n - 1.0) } else { fit }).collect();
+        monitor.reference_baseline = Baseline::of(&fits);
+        Ok(monitor)
+    }
+
+    /// Sets the number of standard errors a window mean may deviate from its reference before drift is flagged.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Returns the reference statistics of the similarity to the closest prototype.
+    pub fn prototype_baseline(&self) -> Baseline {
+        self.prototype_baseline
+    }
+
+    /// Returns the reference statistics of the fit to the reference distribution.
+    pub fn reference_baseline(&self) -> Baseline {
+        self.reference_baseline
+    }
+
+    /// Observes an incoming sample.
+    /// # Arguments
+    /// * `vector` - The encoded sample.
+    /// # Returns
+    /// The `DriftStatus` including the sample.
+    pub fn observe(&mut self, vector: &CsVec<i8>) -> Result<DriftStatus, OVSAError> {
+        let (prototype, reference) = self.statistics(vector)?;
+        if self.recent.len() == self.window {
+            let (old_prototype, old_reference) = self.recent.pop_front().expect("The window is full.");
+            self.sums.0 -= old_prototype;
+            self.sums.1 -= old_reference;
+        }
+        self.recent.push_back((prototype, reference));
+        self.sums.0 += prototype;
+        self.sums.1 += reference;
+        self.n_observed += 1;
+
+        Ok(self.status().expect("A sample was observed."))
+    }
+
+    /// Returns the current `DriftStatus`, or `None` before the first observation.
+    pub fn status(&self) -> Option<DriftStatus> {
+        if self.recent.is_empty() {
+            return None;
+        }
+
+        let n = self.recent.len();
+        let (prototype_similarity, reference_fit) = (self.sums.0 / n as f64, self.sums.1 / n as f64);
+        let prototype_z = self.prototype_baseline.z_score(prototype_similarity, n);
+        let reference_z = self.reference_baseline.z_score(reference_fit, n);
+        let drift = n == self.window && (prototype_z.abs() > self.threshold || reference_z.abs() > self.threshold);
+        Some(DriftStatus { n_observed: self.n_observed, prototype_similarity, reference_fit, prototype_z, reference_z, drift })
+    }
+
+    /// Forgets the observed samples, e.g. after retraining on recent data; the reference is kept.
+    pub fn reset(&mut self) {
+        self.recent.clear();
+        self.sums = (0.0, 0.0);
+        self.n_observed = 0;
+    }
+
+    /// Returns the similarity of a vector to the closest prototype and its fit to the reference distribution.
+    fn statistics(&self, vector: &CsVec<i8>) -> Result<(f64, f64), OVSAError> {
+        if vector.dim() != self.frequencies.len() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let mut closest = f64::NEG_INFINITY;
+        for (_, prototype) in self.prototypes.iter() {
+            closest = closest.max(similarity(prototype, vector)?);
+        }
+        let fit = if vector.nnz() == 0 {
+            0.0
+        } else {
+            vector.indices().iter().map(|&index| self.frequencies[index]).sum::<f64>() / vector.nnz() as f64
+        };
+        Ok((closest, fit))
+    }
+}
@@ -0,0 +1,145 @@
+//! Ensembles of centroid classifiers. Every member sees its own random reprojection of the input vectors, chosen by a
+//! seed derived from the ensemble seed, so the members make partly independent errors, and their predictions are
+//! combined by averaging the class similarities or by majority vote. Members are trained from their own random streams
+//! of the seed, so training is reproducible and identical with and without the `parallel` feature, which trains the
+//! members in parallel.
+
+use std::collections::BTreeMap;
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::{reproject_with, reprojection};
+use crate::errors::OVSAError;
+use crate::learn::CentroidClassifier;
+use crate::rng::stream_rng;
+use crate::trace::span;
+
+
+/// How an `Ensemble` combines the predictions of its members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Combination {
+    /// The class of the highest mean similarity over the members, scored by that mean.
+    #[default]
+    Average,
+    /// The class predicted by most members, ties broken by the mean similarity, scored by its share of the votes.
+    Vote,
+}
+
+
+/// Centroid classifiers over random reprojections of the input vectors.
+#[derive(Debug, Clone)]
+pub struct Ensemble {
+    dimension: usize,
+    combination: Combination,
+    /// The input dimension every dimension of a member copies, see `binary::reproject`.
+    selections: Vec<Vec<usize>>,
+    members: Vec<CentroidClassifier>,
+    seed: u64,
+    /// The number of calls of `fit`, which select the random streams of the next call.
+    n_fits: u64,
+}
+
+
+impl Ensemble {
+    /// Creates an untrained ensemble.
+    /// # Arguments
+    /// * `dimension` - The dimension of the input vectors.
+    /// * `n_members` - The number of member classifiers.
+    /// * `member_dimension` - The dimension every member reprojects the inputs to; below `dimension`, every member
+    ///   sees a different random subset of the input dimensions.
+    /// * `seed` - The seed of the reprojections and of the training of the members.
+    /// # Returns
+    /// A new `Ensemble`, `ZeroDimension` for a zero dimension or `InvalidArgument` for zero members.
+    pub fn new(dimension: usize, n_members: usize, member_dimension: usize, seed: u64) -> Result<Self, OVSAError> {
+        if n_members == 0 {
+            return Err(OVSAError::InvalidArgument("the number of members must be at least 1, got 0".to_string()));
+        }
+
+        let selections = (0..n_members)
+            .map(|member| reprojection(dimension, member_dimension, stream_rng(seed, member as u64).random()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let members = (0..n_members).map(|_| CentroidClassifier::new(member_dimension)).collect::<Result<Vec<_>, _>>()?;
+        Ok(Ensemble { dimension, combination: Combination::default(), selections, members, seed, n_fits: 0 })
+    }
+
+    /// Sets how the predictions of the members are combined.
+    pub fn with_combination(mut self, combination: Combination) -> Self {
+        self.combination = combination;
+        self
+    }
+
+    /// Returns how the predictions of the members are combined.
+    pub fn combination(&self) -> Combination {
+        self.combination
+    }
+
+    /// Returns the dimension of the input vectors.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the member classifiers, which classify reprojected vectors.
+    pub fn members(&self) -> &[CentroidClassifier] {
+        &self.members
+    }
+
+    /// Adds training vectors to every member. Training can continue with further calls.
+    /// # Arguments
+    /// * `samples` - The (vector, label) training pairs.
+    pub fn fit(&mut self, samples: &[(CsVec<i8>, String)]) -> Result<(), OVSAError> {
+        span!(INFO, "fit_ensemble", n_samples = samples.len(), n_members = self.members.len());
+        if samples.iter().any(|(vector, _)| vector.dim() != self.dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        // every call draws from fresh streams, after the ones of the reprojections
+        self.n_fits += 1;
+        let (seed, first_stream) = (self.seed, self.n_fits * self.members.len() as u64);
+        let fit = |(member, (classifier, selection)): (usize, (&mut CentroidClassifier, &Vec<usize>))| {
+            let projected: Vec<(CsVec<i8>, String)> = samples.iter().map(|(vector, label)| (reproject_with(vector, selection), label.clone())).collect();
+            classifier.fit_with_rng(&projected, &mut stream_rng(seed, first_stream + member as u64))
+        };
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            crate::config::install(|| self.members.par_iter_mut().zip(self.selections.par_iter()).enumerate().try_for_each(fit))
+        }
+        #[cfg(not(feature = "parallel"))]
+        self.members.iter_mut().zip(self.selections.iter()).enumerate().try_for_each(fit)
+    }
+
+    /// Predicts the class of a vector by combining the predictions of the members.
+    /// # Arguments
+    /// * `vector` - The vector to classify.
+    /// # Returns
+    /// The (label, score) pair of the predicted class, or `None` if the ensemble was not trained.
+    pub fn predict(&self, vector: &CsVec<i8>) -> Result<Option<(String, f64)>, OVSAError> {
+        if vector.dim() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        // label -> (votes, summed similarity)
+        let mut scores: BTreeMap<String, (usize, f64)> = BTreeMap::new();
+        for (classifier, selection) in self.members.iter().zip(&self.selections) {
+            let prototypes = classifier.prototypes();
+            let matches = prototypes.query(&reproject_with(vector, selection), prototypes.len())?;
+            for (rank, (label, similarity)) in matches.into_iter().enumerate() {
+                let (votes, sum) = scores.entry(label).or_default();
+                *votes += usize::from(rank == 0);
+                *sum += similarity;
+            }
+        }
+
+        let n_members = self.members.len() as f64;
+        let key = |&(votes, sum): &(usize, f64)| match self.combination {
+            Combination::Average => (0, sum),
+            Combination::Vote => (votes, sum),
+        };
+        let best = scores.into_iter().reduce(|best, candidate| if key(&candidate.1) > key(&best.1) { candidate } else { best });
+        Ok(best.map(|(label, (votes, sum))| match self.combination {
+            Combination::Average => (label, sum / n_members),
+            Combination::Vote => (label, votes as f64 / n_members),
+        }))
+    }
+}
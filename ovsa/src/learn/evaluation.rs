@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use sprs::CsVec;
+
+use crate::errors::OVSAError;
+use crate::learn::CentroidClassifier;
+use crate::learn::compress::CompressedClassifier;
+use crate::learn::ensemble::Ensemble;
+use crate::learn::onlinehd::OnlineHDClassifier;
+use crate::rng::with_global_rng;
+
+
+/// The training and the test samples of a split, each a list of (sample, label) pairs.
+pub type Split<T> = (Vec<(T, String)>, Vec<(T, String)>);
+
+
+/// A model that assigns a class label to a vector, evaluated with `evaluate`.
+pub trait Classifier {
+    /// Predicts the class of a vector.
+    /// # Arguments
+    /// * `vector` - The vector to classify.
+    /// # Returns
+    /// The (label, score) pair of the predicted class, or `None` if the classifier knows no class.
+    fn predict(&self, vector: &CsVec<i8>) -> Result<Option<(String, f64)>, OVSAError>;
+
+    /// Predicts the classes of many vectors. Classifiers with a faster bulk kernel than single predictions override
+    /// this.
+    /// # Arguments
+    /// * `vectors` - The vectors to classify.
+    /// # Returns
+    /// The prediction of every vector, in input order.
+    fn predict_batch(&self, vectors: &[CsVec<i8>]) -> Result<Vec<Option<(String, f64)>>, OVSAError> {
+        vectors.iter().map(|vector| self.predict(vector)).collect()
+    }
+}
+
+
+impl Classifier for CentroidClassifier {
+    fn predict(&self, vector: &CsVec<i8>) -> Result<Option<(String, f64)>, OVSAError> {
+        CentroidClassifier::predict(self, vector)
+    }
+
+    fn predict_batch(&self, vectors: &[CsVec<i8>]) -> Result<Vec<Option<(String, f64)>>, OVSAError> {
+        CentroidClassifier::predict_batch(self, vectors)
+    }
+}
+
+
+impl Classifier for CompressedClassifier {
+    fn predict(&self, vector: &CsVec<i8>) -> Result<Option<(String, f64)>, OVSAError> {
+        CompressedClassifier::predict(self, vector)
+    }
+}
+
+
+impl Classifier for Ensemble {
+    fn predict(&self, vector: &CsVec<i8>) -> Result<Option<(String, f64)>, OVSAError> {
+        Ensemble::predict(self, vector)
+    }
+}
+
+
+impl Classifier for OnlineHDClassifier {
+    fn predict(&self, vector: &CsVec<i8>) -> Result<Option<(String, f64)>, OVSAError> {
+        OnlineHDClassifier::predict(self, vector)
+    }
+}
+
+/// The precision, recall and F1 score of one class.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassMetrics {
+    pub label: String,
+    /// The share of the predictions of the class that were right, 0 if the class was never predicted.
+    pub precision: f64,
+    /// The share of the samples of the class that were predicted as such, 0 if the class has no samples.
+    pub recall: f64,
+    /// The harmonic mean of precision and recall, 0 if both are 0.
+    pub f1: f64,
+    /// The number of samples of the class.
+    pub support: usize,
+}
+
+
+/// The predictions of a classifier on a labelled test set, summarized as a confusion matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    /// The labels of the test set and of the predictions, sorted.
+    pub labels: Vec<String>,
+    /// The number of samples of every true label (row) predicted as every label (column), indexed like `labels`.
+    pub confusion: Vec<Vec<usize>>,
+    /// The number of samples of every true label the classifier made no prediction for, counted as errors.
+    pub unpredicted: Vec<usize>,
+}
+
+
+impl Evaluation {
+    /// Returns the number of evaluated samples.
+    pub fn n_samples(&self) -> usize {
+        self.confusion.iter().flatten().sum::<usize>() + self.unpredicted.iter().sum::<usize>()
+    }
+
+    /// Returns the share of correctly classified samples, 0 for an empty test set.
+    pub fn accuracy(&self) -> f64 {
+        let correct: usize = (0..self.labels.len()).map(|i| self.confusion[i][i]).sum();
+        if self.n_samples() == 0 { 0.0 } else { correct as f64 / self.n_samples() as f64 }
+    }
+
+    /// Returns the number of samples of a true label predicted as another label.
+    /// # Arguments
+    /// * `actual` - The true label.
+    /// * `predicted` - The predicted label.
+    pub fn count(&self, actual: &str, predicted: &str) -> usize {
+        match (self.position(actual), self.position(predicted)) {
+            (Some(actual), Some(predicted)) => self.confusion[actual][predicted],
+            _ => 0,
+        }
+    }
+
+    /// Returns the precision, recall and F1 score of a class.
+    /// # Arguments
+    /// * `label` - The label of the class.
+    /// # Returns
+    /// The metrics, or `None` if the label neither occurs in the test set nor was predicted.
+    pub fn class_metrics(&self, label: &str) -> Option<ClassMetrics> {
+        let position = self.position(label)?;
+        let correct = self.confusion[position][position];
+        let predicted: usize = self.confusion.iter().map(|row| row[position]).sum();
+        // samples of the class without a prediction count against its recall
+        let support = self.confusion[position].iter().sum::<usize>() + self.unpredicted[position];
+        let ratio = |count: usize, total: usize| if total == 0 { 0.0 } else { count as f64 / total as f64 };
+        let (precision, recall) = (ratio(correct, predicted), ratio(correct, support));
+        let f1 = if precision + recall == 0.0 { 0.0 } else { 2.0 * precision * recall / (precision + recall) };
+
+        Some(ClassMetrics { label: label.to_string(), precision, recall, f1, support })
+    }
+
+    /// Returns the metrics of every class, in the order of `labels`.
+    pub fn per_class(&self) -> Vec<ClassMetrics> {
+        self.labels.iter().map(|label| self.class_metrics(label).expect("The label is known.")).collect()
+    }
+
+    /// Returns the unweighted mean F1 score over the classes of the test set, 0 for an empty test set.
+    pub fn macro_f1(&self) -> f64 {
+        let scores: Vec<f64> = self.per_class().into_iter().filter(|metrics| metrics.support > 0).map(|metrics| metrics.f1).collect();
+        if scores.is_empty() { 0.0 } else { scores.iter().sum::<f64>() / scores.len() as f64 }
+    }
+
+    fn position(&self, label: &str) -> Option<usize> {
+        self.labels.binary_search_by(|other| other.as_str().cmp(label)).ok()
+    }
+}
+
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "accuracy {:.4} over {} samples, macro F1 {:.4}", self.accuracy(), self.n_samples(), self.macro_f1())?;
+        for metrics in self.per_class() {
+            write!(f, "\n{}: precision {:.4}, recall {:.4}, F1 {:.4}, support {}", metrics.label, metrics.precision, metrics.recall, metrics.f1, metrics.support)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Evaluates a classifier on a labelled test set.
+/// # Arguments
+/// * `classifier` - The trained classifier.
+/// * `test_set` - The (vector, label) pairs.
+/// # Returns
+/// The `Evaluation` with the confusion matrix of the predictions.
+pub fn evaluate<C: Classifier + ?Sized>(classifier: &C, test_set: &[(CsVec<i8>, String)]) -> Result<Evaluation, OVSAError> {
+    let vectors: Vec<CsVec<i8>> = test_set.iter().map(|(vector, _)| vector.clone()).collect();
+    let predictions = classifier.predict_batch(&vectors)?;
+
+    let mut labels: Vec<String> = test_set.iter().map(|(_, label)| label.clone())
+        .chain(predictions.iter().flatten().map(|(label, _)| label.clone()))
+        .collect();
+    labels.sort_unstable();
+    labels.dedup();
+
+    let mut evaluation = Evaluation { confusion: vec![vec![0; labels.len()]; labels.len()], unpredicted: vec![0; labels.len()], labels };
+    for ((_, label), prediction) in test_set.iter().zip(&predictions) {
+        let actual = evaluation.position(label).expect("Every label is listed.");
+        match prediction {
+            Some((predicted, _)) => {
+                let predicted = evaluation.position(predicted).expect("Every label is listed.");
+                evaluation.confusion[actual][predicted] += 1;
+            }
+            None => evaluation.unpredicted[actual] += 1,
+        }
+    }
+
+    Ok(evaluation)
+}
+
+
+/// Splits labelled samples into a training and a test set, stratified so that every class keeps its share in both.
+/// # Arguments
+/// * `samples` - The (sample, label) pairs, e.g. encoded vectors or raw records.
+/// * `test_fraction` - The share of every class moved to the test set, in `(0, 1)`, rounded per class.
+/// # Returns
+/// The training and the test samples.
+pub fn train_test_split<T: Clone>(samples: &[(T, String)], test_fraction: f64) -> Result<Split<T>, OVSAError> {
+    with_global_rng(|rng| train_test_split_with_rng(samples, test_fraction, rng))
+}
+
+
+/// Splits labelled samples into a stratified training and test set using the provided random number generator.
+/// # Arguments
+/// * `samples` - The (sample, label) pairs.
+/// * `test_fraction` - The share of every class moved to the test set, in `(0, 1)`, rounded per class.
+/// * `rng` - The random number generator shuffling every class.
+/// # Returns
+/// The training and the test samples.
+pub fn train_test_split_with_rng<T: Clone, R: Rng + ?Sized>(samples: &[(T, String)], test_fraction: f64, rng: &mut R) -> Result<Split<T>, OVSAError> {
+    if !(test_fraction > 0.0 && test_fraction < 1.0) {
+        return Err(OVSAError::InvalidArgument(format!("the test fraction must be in (0, 1), got {}", test_fraction)));
+    }
+
+    let (mut train, mut test) = (Vec::new(), Vec::new());
+    for mut class in shuffled_classes(samples, rng) {
+        let n_test = (class.len() as f64 * test_fraction).round() as usize;
+        let rest = class.split_off(n_test);
+        test.extend(class.into_iter().map(|index| samples[index].clone()));
+        train.extend(rest.into_iter().map(|index| samples[index].clone()));
+    }
+
+    Ok((train, test))
+}
+
+
+/// Splits labelled samples into `k` stratified folds for cross-validation: the samples of every class are dealt to the
+/// folds in turn, continuing where the previous class stopped, so that fold sizes differ by at most one.
+/// # Arguments
+/// * `samples` - The (sample, label) pairs.
+/// * `k` - The number of folds, at least 2 and at most the number of samples.
+/// # Returns
+/// The `k` (training, test) pairs, every sample being in exactly one test set.
+pub fn k_fold<T: Clone>(samples: &[(T, String)], k: usize) -> Result<Vec<Split<T>>, OVSAError> {
+    with_global_rng(|rng| k_fold_with_rng(samples, k, rng))
+}
+
+
+/// Splits labelled samples into `k` stratified folds using the provided random number generator.
+/// # Arguments
+/// * `samples` - The (sample, label) pairs.
+/// * `k` - The number of folds, at least 2 and at most the number of samples.
+/// * `rng` - The random number generator shuffling every class.
+/// # Returns
+/// The `k` (training, test) pairs, every sample being in exactly one test set.
+pub fn k_fold_with_rng<T: Clone, R: Rng + ?Sized>(samples: &[(T, String)], k: usize, rng: &mut R) -> Result<Vec<Split<T>>, OVSAError> {
+    if k < 2 || k > samples.len() {
+        return Err(OVSAError::InvalidArgument(format!("need between 2 and {} folds, got {}", samples.len(), k)));
+    }
+
+    let mut folds = vec![Vec::new(); k];
+    let mut next = 0;
+    for index in shuffled_classes(samples, rng).into_iter().flatten() {
+        folds[next].push(index);
+        next = (next + 1) % k;
+    }
+
+    Ok((0..k)
+        .map(|fold| {
+            let train = folds.iter().enumerate().filter(|&(other, _)| other != fold).flat_map(|(_, indices)| indices).map(|&index| samples[index].clone()).collect();
+            let test = folds[fold].iter().map(|&index| samples[index].clone()).collect();
+            (train, test)
+        })
+        .collect())
+}
+
+
+/// Cross-validates a training procedure: trains on the training set of every stratified fold and evaluates on its
+/// test set. The global generator is only locked to split the folds, so the procedure may use it.
+/// # Arguments
+/// * `samples` - The (vector, label) pairs.
+/// * `k` - The number of folds.
+/// * `train` - Trains a classifier on a training set.
+/// # Returns
+/// The `Evaluation` of every fold.
+pub fn cross_validate<C: Classifier, F: FnMut(&[(CsVec<i8>, String)]) -> Result<C, OVSAError>>(samples: &[(CsVec<i8>, String)], k: usize, train: F) -> Result<Vec<Evaluation>, OVSAError> {
+    evaluate_folds(k_fold(samples, k)?, train)
+}
+
+
+/// Cross-validates a training procedure, splitting the folds with the provided random number generator.
+/// # Arguments
+/// * `samples` - The (vector, label) pairs.
+/// * `k` - The number of folds.
+/// * `train` - Trains a classifier on a training set.
+/// * `rng` - The random number generator shuffling every class.
+/// # Returns
+/// The `Evaluation` of every fold.
+pub fn cross_validate_with_rng<C, F, R>(samples: &[(CsVec<i8>, String)], k: usize, train: F, rng: &mut R) -> Result<Vec<Evaluation>, OVSAError>
+where
+    C: Classifier,
+    F: FnMut(&[(CsVec<i8>, String)]) -> Result<C, OVSAError>,
+    R: Rng + ?Sized,
+{
+    evaluate_folds(k_fold_with_rng(samples, k, rng)?, train)
+}
+
+
+fn evaluate_folds<C, F>(folds: Vec<Split<CsVec<i8>>>, mut train: F) -> Result<Vec<Evaluation>, OVSAError>
+where
+    C: Classifier,
+    F: FnMut(&[(CsVec<i8>, String)]) -> Result<C, OVSAError>,
+{
+    folds.into_iter()
+        .map(|(training, test)| evaluate(&train(&training)?, &test))
+        .collect()
+}
+
+
+/// Groups the indices of the samples by label, in label order, and shuffles every group.
+fn shuffled_classes<T, R: Rng + ?Sized>(samples: &[(T, String)], rng: &mut R) -> Vec<Vec<usize>> {
+    let mut classes: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (index, (_, label)) in samples.iter().enumerate() {
+        classes.entry(label).or_default().push(index);
+    }
+
+    classes.into_values()
+        .map(|mut indices| {
+            indices.shuffle(rng);
+            indices
+        })
+        .collect()
+}
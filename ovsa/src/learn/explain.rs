@@ -0,0 +1,82 @@
+//! Explanations of classifier decisions on records: every field's role is unbound from the winning prototype and the
+//! result compared with the field's filler, which tells how strongly the prototype holds that field.
+
+use sprs::CsVec;
+
+use crate::binary::{similarity, xor};
+use crate::encode::EncodedRecord;
+use crate::errors::OVSAError;
+use crate::learn::CentroidClassifier;
+
+
+/// How strongly one field of a record is represented in the class prototypes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contribution {
+    /// The role label of the field.
+    pub role: String,
+    /// The similarity between the filler and the role unbound from the predicted prototype. Fields absent from the
+    /// prototype align at chance level, about 0.5 for half-dense vectors.
+    pub alignment: f64,
+    /// The alignment with the runner-up prototype, if there is one.
+    pub runner_up_alignment: Option<f64>,
+}
+
+
+impl Contribution {
+    /// Returns how much more the predicted class holds the field than the runner-up, or 0 without a runner-up.
+    pub fn margin(&self) -> f64 {
+        self.runner_up_alignment.map_or(0.0, |runner_up| self.alignment - runner_up)
+    }
+}
+
+
+/// The explanation of a prediction on a record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    /// The predicted label.
+    pub label: String,
+    /// The similarity of the record to the predicted prototype.
+    pub similarity: f64,
+    /// The second most similar label, if there is one.
+    pub runner_up: Option<String>,
+    /// One contribution per field, by decreasing alignment.
+    pub contributions: Vec<Contribution>,
+}
+
+
+impl CentroidClassifier {
+    /// Predicts the class of a record and reports which of its fields contributed most to the similarity with the
+    /// predicted prototype.
+    /// # Arguments
+    /// * `record` - The record, encoded with its roles, e.g. by `encode::features_with_roles`.
+    /// # Returns
+    /// The `Explanation`, or `None` if the classifier was not trained.
+    pub fn explain(&self, record: &EncodedRecord) -> Result<Option<Explanation>, OVSAError> {
+        let matches = self.prototypes().query(&record.vector, 2)?;
+        let Some((label, best)) = matches.first().cloned() else {
+            return Ok(None);
+        };
+        let runner_up = matches.get(1).map(|(label, _)| label.clone());
+
+        let prototype = self.prototypes().get(&label).ok_or(OVSAError::EmptyVectorList)?;
+        let runner_up_prototype = runner_up.as_deref().and_then(|label| self.prototypes().get(label));
+        let mut contributions = record.fields.iter()
+            .map(|field| {
+                Ok(Contribution {
+                    role: field.role.clone(),
+                    alignment: alignment(prototype, &field.role_vector, &field.filler)?,
+                    runner_up_alignment: runner_up_prototype.map(|prototype| alignment(prototype, &field.role_vector, &field.filler)).transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>, OVSAError>>()?;
+        contributions.sort_by(|a, b| b.alignment.total_cmp(&a.alignment));
+
+        Ok(Some(Explanation { label, similarity: best, runner_up, contributions }))
+    }
+}
+
+
+/// Unbinds the role from the prototype and compares the result with the filler.
+fn alignment(prototype: &CsVec<i8>, role: &CsVec<i8>, filler: &CsVec<i8>) -> Result<f64, OVSAError> {
+    similarity(&xor(prototype, role)?, filler)
+}
@@ -0,0 +1,587 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::{harden_with_rng, reprojection, similarity};
+use crate::errors::OVSAError;
+use crate::hypervector::Hardening;
+use crate::mask::DimensionMask;
+use crate::learn::anomaly::{closest, cluster_with_rng};
+use crate::memory::{ItemMemory, MemoryUsage, scoped};
+use crate::progress::{Progress, ignore, report};
+use crate::rng::with_global_rng;
+use crate::trace::span;
+
+pub mod anomaly;
+pub mod bag;
+pub mod calibration;
+pub mod compress;
+pub mod drift;
+pub mod ensemble;
+pub mod evaluation;
+pub mod explain;
+pub mod online;
+pub mod onlinehd;
+pub mod qmemory;
+pub mod refine;
+pub mod sweep;
+
+pub use anomaly::AnomalyDetector;
+pub use bag::{BagClassifier, Pooling};
+pub use calibration::{Calibration, CalibrationMethod};
+pub use drift::{DriftMonitor, DriftStatus};
+pub use ensemble::{Combination, Ensemble};
+pub use evaluation::{Classifier, Evaluation, cross_validate, evaluate, k_fold, train_test_split};
+pub use online::{TrainingParams, TrainingReport, train_online};
+pub use onlinehd::{LearningRate, OnlineHDClassifier};
+pub use qmemory::QMemory;
+pub use sweep::{SweepGrid, SweepTable, sweep};
+
+
+/// Labelled sparse binary vectors, e.g. an encoded dataset.
+pub type Samples = Vec<(CsVec<i8>, String)>;
+
+
+/// A classifier representing every class by the consensus sum (majority) of its training vectors.
+/// Per-class counts of active entries are kept so that training can continue after predictions were made.
+#[derive(Debug, Clone)]
+pub struct CentroidClassifier {
+    dimension: usize,
+    counts: HashMap<String, (Vec<u32>, usize)>,
+    prototypes: ItemMemory,
+    hardening: Hardening,
+    calibration: Option<Calibration>,
+}
+
+
+impl CentroidClassifier {
+    /// Creates an untrained classifier for vectors of the given dimension.
+    /// # Arguments
+    /// * `dimension` - The dimension of the classified vectors.
+    /// # Returns
+    /// A new `CentroidClassifier`.
+    pub fn new(dimension: usize) -> Result<Self, OVSAError> {
+        Ok(CentroidClassifier { dimension, counts: HashMap::new(), prototypes: ItemMemory::new(dimension)?, hardening: Hardening::Majority, calibration: None })
+    }
+
+    /// Sets how prototypes are hardened from the class counts, from the next training on. Stochastic hardening suits
+    /// classes with few training vectors, whose majority would lose most of their minority entries.
+    /// # Arguments
+    /// * `hardening` - The hardening.
+    /// # Returns
+    /// The classifier with the hardening.
+    pub fn with_hardening(mut self, hardening: Hardening) -> Self {
+        self.hardening = hardening;
+        self
+    }
+
+    /// Returns how prototypes are hardened from the class counts.
+    pub fn hardening(&self) -> Hardening {
+        self.hardening
+    }
+
+    /// Returns the dimension of the classified vectors.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the class prototypes.
+    pub fn prototypes(&self) -> &ItemMemory {
+        &self.prototypes
+    }
+
+    /// Returns the per-index active counts and the number of training vectors of a class.
+    /// # Arguments
+    /// * `label` - The class label.
+    pub fn counts(&self, label: &str) -> Option<(&[u32], usize)> {
+        self.counts.get(label).map(|(counts, n)| (counts.as_slice(), *n))
+    }
+
+    /// Estimates the bytes used by the classifier: the prototypes plus the per-class training counters.
+    /// # Returns
+    /// The `MemoryUsage` breakdown, with the counters reported as auxiliary bytes.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = self.prototypes.memory_usage();
+        let counter_bytes: usize = self.counts.iter()
+            .map(|(label, (counts, _))| label.len() + size_of::<String>() + counts.len() * size_of::<u32>() + size_of::<(Vec<u32>, usize)>())
+            .sum();
+        usage.auxiliary_bytes += counter_bytes;
+        usage
+    }
+
+    /// Adds training vectors and updates the prototypes of the affected classes.
+    /// # Arguments
+    /// * `samples` - The (vector, label) training pairs.
+    pub fn fit(&mut self, samples: &[(CsVec<i8>, String)]) -> Result<(), OVSAError> {
+        self.fit_with_progress(samples, ignore)
+    }
+
+    /// Adds training vectors, breaking prototype ties with the provided random number generator.
+    /// # Arguments
+    /// * `samples` - The (vector, label) training pairs.
+    /// * `rng` - The random number generator used to break ties.
+    pub fn fit_with_rng<R: Rng + ?Sized>(&mut self, samples: &[(CsVec<i8>, String)], rng: &mut R) -> Result<(), OVSAError> {
+        self.fit_with_progress_and_rng(samples, rng, ignore)
+    }
+
+    /// Adds training vectors, reporting progress after every sample under the stage `fit`.
+    /// If the callback stops the training, the samples added so far are kept and `Cancelled` is returned.
+    /// The global generator is only locked to draw the prototypes after the samples are counted, so the callback
+    /// may use it.
+    /// # Arguments
+    /// * `samples` - The (vector, label) training pairs.
+    /// * `on_progress` - The progress callback.
+    pub fn fit_with_progress<F: FnMut(Progress) -> ControlFlow<()>>(&mut self, samples: &[(CsVec<i8>, String)], on_progress: F) -> Result<(), OVSAError> {
+        span!(INFO, "fit", n_samples = samples.len(), dimension = self.dimension);
+        let (touched, outcome) = self.count(samples, on_progress);
+        with_global_rng(|rng| self.harden_classes(&touched, rng))?;
+        outcome
+    }
+
+    /// Adds training vectors with a progress callback, breaking prototype ties with the provided random number generator.
+    /// # Arguments
+    /// * `samples` - The (vector, label) training pairs.
+    /// * `rng` - The random number generator used to break ties.
+    /// * `on_progress` - The progress callback.
+    pub fn fit_with_progress_and_rng<R: Rng + ?Sized, F: FnMut(Progress) -> ControlFlow<()>>(&mut self, samples: &[(CsVec<i8>, String)], rng: &mut R, on_progress: F) -> Result<(), OVSAError> {
+        span!(INFO, "fit", n_samples = samples.len(), dimension = self.dimension);
+        let (touched, outcome) = self.count(samples, on_progress);
+        self.harden_classes(&touched, rng)?;
+        outcome
+    }
+
+    /// Adds the samples to the counters of their classes, reporting progress after every sample.
+    /// # Returns
+    /// The labels of the classes counted into, in order of first appearance, and the outcome of the counting.
+    fn count<'a, F: FnMut(Progress) -> ControlFlow<()>>(&mut self, samples: &'a [(CsVec<i8>, String)], mut on_progress: F) -> (Vec<&'a str>, Result<(), OVSAError>) {
+        let mut touched: Vec<&str> = Vec::new();
+        let mut outcome = Ok(());
+
+        for (completed, (vector, label)) in samples.iter().enumerate() {
+            if vector.dim() != self.dimension {
+                outcome = Err(OVSAError::VectorSizeMismatch);
+                break;
+            }
+
+            let (counts, n) = self.counts.entry(label.clone()).or_insert_with(|| (vec![0; self.dimension], 0));
+            for &index in vector.indices() {
+                counts[index] += 1;
+            }
+            *n += 1;
+
+            if !touched.contains(&label.as_str()) {
+                touched.push(label);
+            }
+
+            outcome = report(&mut on_progress, "fit", completed + 1, samples.len(), None);
+            if outcome.is_err() {
+                break;
+            }
+        }
+
+        (touched, outcome)
+    }
+
+    /// Redraws the prototypes of the given classes from their counters. They are updated even when the counting
+    /// stopped early, so that they always agree with the counts.
+    fn harden_classes<R: Rng + ?Sized>(&mut self, labels: &[&str], rng: &mut R) -> Result<(), OVSAError> {
+        for &label in labels {
+            let (counts, n) = &self.counts[label];
+            self.prototypes.insert(label, harden_with_rng(counts, *n, self.hardening, rng))?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds training vectors like `fit_with_rng`, counting the vectors of every class in parallel. Classes are counted
+    /// independently and their prototypes are then drawn serially in the same order, so the result is bit-identical
+    /// to `fit_with_rng` with an identically seeded generator.
+    /// # Arguments
+    /// * `samples` - The (vector, label) training pairs.
+    /// * `rng` - The random number generator used to break ties.
+    #[cfg(feature = "parallel")]
+    pub fn par_fit_with_rng<R: Rng + ?Sized>(&mut self, samples: &[(CsVec<i8>, String)], rng: &mut R) -> Result<(), OVSAError> {
+        use rayon::prelude::*;
+
+        span!(INFO, "par_fit", n_samples = samples.len(), dimension = self.dimension);
+        if samples.iter().any(|(vector, _)| vector.dim() != self.dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        // the classes in order of first appearance, which is the order `fit_with_rng` breaks ties in
+        let mut classes: Vec<(&str, Vec<&CsVec<i8>>)> = Vec::new();
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        for (vector, label) in samples {
+            let position = *positions.entry(label).or_insert_with(|| {
+                classes.push((label, Vec::new()));
+                classes.len() - 1
+            });
+            classes[position].1.push(vector);
+        }
+
+        let dimension = self.dimension;
+        let class_counts: Vec<Vec<u32>> = crate::config::install(|| {
+            classes.par_iter()
+                .map(|(_, vectors)| {
+                    let mut counts = vec![0u32; dimension];
+                    for vector in vectors {
+                        for &index in vector.indices() {
+                            counts[index] += 1;
+                        }
+                    }
+                    counts
+                })
+                .collect()
+        });
+
+        for ((label, vectors), added) in classes.into_iter().zip(class_counts) {
+            let (counts, n) = self.counts.entry(label.to_string()).or_insert_with(|| (vec![0; self.dimension], 0));
+            for (count, added) in counts.iter_mut().zip(added) {
+                *count += added;
+            }
+            *n += vectors.len();
+            self.prototypes.insert(label, harden_with_rng(counts, *n, self.hardening, rng))?;
+        }
+
+        Ok(())
+    }
+
+    /// Retrains the classifier perceptron-style: every misclassified training vector is added to the counts of its
+    /// class and subtracted from those of the class it was mistaken for, and the prototypes of the corrected classes
+    /// are hardened again after every epoch. Retraining stops early after an epoch without corrections. A subtraction
+    /// leaves counters that are already zero at zero.
+    /// # Arguments
+    /// * `samples` - The (vector, label) training pairs, usually those of the initial `fit`.
+    /// * `epochs` - The maximum number of passes over the samples.
+    /// # Returns
+    /// The number of corrections of every epoch run.
+    pub fn retrain(&mut self, samples: &[(CsVec<i8>, String)], epochs: usize) -> Result<Vec<usize>, OVSAError> {
+        with_global_rng(|rng| self.retrain_with_rng(samples, epochs, rng))
+    }
+
+    /// Retrains the classifier perceptron-style, breaking prototype ties with the provided random number generator.
+    /// # Arguments
+    /// * `samples` - The (vector, label) training pairs.
+    /// * `epochs` - The maximum number of passes over the samples.
+    /// * `rng` - The random number generator used to break ties.
+    /// # Returns
+    /// The number of corrections of every epoch run.
+    pub fn retrain_with_rng<R: Rng + ?Sized>(&mut self, samples: &[(CsVec<i8>, String)], epochs: usize, rng: &mut R) -> Result<Vec<usize>, OVSAError> {
+        span!(INFO, "retrain", n_samples = samples.len(), epochs);
+        if samples.iter().any(|(vector, _)| vector.dim() != self.dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let samples: Vec<&(CsVec<i8>, String)> = samples.iter().collect();
+        let mut corrections = Vec::with_capacity(epochs);
+        for _ in 0..epochs {
+            corrections.push(self.correct_with_rng(&samples, rng)?);
+            if corrections.last() == Some(&0) {
+                break;
+            }
+        }
+
+        Ok(corrections)
+    }
+
+    /// Makes one perceptron-style pass over samples of the classifier's dimension: every misclassified vector is
+    /// added to the counts of its class and subtracted from those of the predicted class, then the prototypes of the
+    /// corrected classes are hardened again.
+    /// # Returns
+    /// The number of corrections.
+    pub(crate) fn correct_with_rng<R: Rng + ?Sized>(&mut self, samples: &[&(CsVec<i8>, String)], rng: &mut R) -> Result<usize, OVSAError> {
+        let vectors: Vec<CsVec<i8>> = samples.iter().map(|(vector, _)| vector.clone()).collect();
+        let predictions = self.predict_batch(&vectors)?;
+        let (mut touched, mut n_corrections): (Vec<&str>, usize) = (Vec::new(), 0);
+        for (&(vector, label), prediction) in samples.iter().zip(&predictions) {
+            let predicted = prediction.as_ref().map(|(predicted, _)| predicted.as_str());
+            if predicted == Some(label.as_str()) {
+                continue;
+            }
+            n_corrections += 1;
+
+            let (counts, n) = self.counts.entry(label.clone()).or_insert_with(|| (vec![0; self.dimension], 0));
+            for &index in vector.indices() {
+                counts[index] += 1;
+            }
+            *n += 1;
+            touched.push(label);
+            if let Some(predicted) = predicted {
+                let (counts, n) = self.counts.get_mut(predicted).expect("Predicted classes have counts.");
+                for &index in vector.indices() {
+                    counts[index] = counts[index].saturating_sub(1);
+                }
+                *n = n.saturating_sub(1);
+                touched.push(predicted);
+            }
+        }
+
+        touched.sort_unstable();
+        touched.dedup();
+        for label in touched {
+            let (counts, n) = &self.counts[label];
+            self.prototypes.insert(label, harden_with_rng(counts, *n, self.hardening, rng))?;
+        }
+
+        Ok(n_corrections)
+    }
+
+    /// Predicts the class whose prototype is most similar to the vector.
+    /// # Arguments
+    /// * `vector` - The vector to classify.
+    /// # Returns
+    /// The (label, similarity) pair of the best class, or `None` if the classifier was not trained.
+    pub fn predict(&self, vector: &CsVec<i8>) -> Result<Option<(String, f64)>, OVSAError> {
+        self.prototypes.cleanup(vector)
+    }
+
+    /// Predicts the classes of many vectors at once, e.g. to evaluate the classifier on a test set.
+    /// # Arguments
+    /// * `vectors` - The vectors to classify.
+    /// # Returns
+    /// The (label, similarity) pair of the best class of every vector, in input order.
+    pub fn predict_batch(&self, vectors: &[CsVec<i8>]) -> Result<Vec<Option<(String, f64)>>, OVSAError> {
+        Ok(self.prototypes.query_batch(vectors, 1)?.into_iter().map(|matches| matches.into_iter().next()).collect())
+    }
+
+    /// Fits a calibration of the similarities to probabilities on held-out samples and stores it with the classifier,
+    /// replacing any previous one. The samples must not have been used for training.
+    /// # Arguments
+    /// * `held_out` - The (vector, label) pairs, all of known classes.
+    /// * `method` - Temperature or Platt scaling.
+    /// # Returns
+    /// The fitted `Calibration`, `InvalidArgument` for a sample of an unknown class or a classifier of fewer than two
+    /// classes.
+    pub fn calibrate(&mut self, held_out: &[(CsVec<i8>, String)], method: CalibrationMethod) -> Result<Calibration, OVSAError> {
+        let labels = self.prototypes.labels();
+        let scores = held_out.iter()
+            .map(|(vector, label)| {
+                let truth = labels.iter().position(|known| known == label).ok_or_else(|| OVSAError::InvalidArgument(format!("unknown class {}", label)))?;
+                Ok((self.similarities(vector)?, truth))
+            })
+            .collect::<Result<Vec<_>, OVSAError>>()?;
+
+        let calibration = Calibration::fit(method, &scores)?;
+        self.calibration = Some(calibration);
+        Ok(calibration)
+    }
+
+    /// Returns the calibration of the classifier, if one was fitted or set.
+    pub fn calibration(&self) -> Option<Calibration> {
+        self.calibration
+    }
+
+    /// Sets or clears the calibration, e.g. when restoring a saved model.
+    pub fn set_calibration(&mut self, calibration: Option<Calibration>) {
+        self.calibration = calibration;
+    }
+
+    /// Computes the calibrated probability of every class.
+    /// # Arguments
+    /// * `vector` - The vector to classify.
+    /// # Returns
+    /// The (label, probability) pairs from the most to the least probable class, or `InvalidArgument` if the
+    /// classifier is not calibrated.
+    pub fn predict_proba(&self, vector: &CsVec<i8>) -> Result<Vec<(String, f64)>, OVSAError> {
+        let calibration = self.calibration.ok_or_else(|| OVSAError::InvalidArgument("the classifier is not calibrated, call calibrate first".to_string()))?;
+        let probabilities = calibration.probabilities(&self.similarities(vector)?);
+        let mut classes: Vec<(String, f64)> = self.prototypes.labels().iter().cloned().zip(probabilities).collect();
+        classes.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(classes)
+    }
+
+    /// Predicts the most probable class with its calibrated probability.
+    /// # Arguments
+    /// * `vector` - The vector to classify.
+    /// # Returns
+    /// The (label, probability) pair, `None` if the classifier was not trained, or `InvalidArgument` if it is not
+    /// calibrated.
+    pub fn predict_calibrated(&self, vector: &CsVec<i8>) -> Result<Option<(String, f64)>, OVSAError> {
+        Ok(self.predict_proba(vector)?.into_iter().next())
+    }
+
+    /// Returns the similarity of a vector to every prototype, in label order.
+    fn similarities(&self, vector: &CsVec<i8>) -> Result<Vec<f64>, OVSAError> {
+        if vector.dim() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        self.prototypes.iter().map(|(_, prototype)| similarity(prototype, vector)).collect()
+    }
+
+    /// Projects the classifier onto the kept dimensions of a mask, e.g. to drop dimensions that do not help telling the
+    /// classes apart. The projected classifier classifies vectors projected with the same mask.
+    /// # Arguments
+    /// * `mask` - The dimension mask.
+    /// # Returns
+    /// A `CentroidClassifier` of dimension `mask.n_kept()` with the prototypes recomputed from the projected counts.
+    pub fn masked(&self, mask: &DimensionMask) -> Result<Self, OVSAError> {
+        if mask.dimension() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let classes = self.prototypes.labels().iter()
+            .map(|label| {
+                let (counts, n) = &self.counts[label];
+                Ok((label.clone(), mask.project_counts(counts)?, *n))
+            })
+            .collect::<Result<Vec<_>, OVSAError>>()?;
+        CentroidClassifier::from_counts_with_hardening(mask.n_kept(), classes, self.hardening)
+    }
+
+    /// Maps the classifier to another dimension like `binary::reproject`, e.g. to shrink a trained model for deployment
+    /// without retraining. The reprojected classifier classifies vectors reprojected with the same seed.
+    /// # Arguments
+    /// * `new_dimension` - The dimension of the result.
+    /// * `seed` - The seed of the reprojection.
+    /// # Returns
+    /// A `CentroidClassifier` with the counts copied to the new dimensions and the prototypes recomputed.
+    pub fn reproject(&self, new_dimension: usize, seed: u64) -> Result<Self, OVSAError> {
+        let selection = reprojection(self.dimension, new_dimension, seed)?;
+        let classes = self.prototypes.labels().iter()
+            .map(|label| {
+                let (counts, n) = &self.counts[label];
+                (label.clone(), selection.iter().map(|&source| counts[source]).collect(), *n)
+            })
+            .collect();
+        CentroidClassifier::from_counts_with_hardening(new_dimension, classes, self.hardening)
+    }
+
+    /// Merges two classes into one whose counts are the sums of theirs, e.g. when two labels turn out to name the same
+    /// concept. The merged prototype is the prototype the classes' training vectors would have produced together.
+    /// # Arguments
+    /// * `first` - The label of a class.
+    /// * `second` - The label of another class.
+    /// * `merged` - The label of the merged class, either of the two or a new one.
+    /// # Returns
+    /// `InvalidArgument` if a class is unknown, the two are the same, or `merged` names a third class.
+    pub fn merge_classes(&mut self, first: &str, second: &str, merged: &str) -> Result<(), OVSAError> {
+        with_global_rng(|rng| self.merge_classes_with_rng(first, second, merged, rng))
+    }
+
+    /// Merges two classes, breaking prototype ties with the provided random number generator.
+    /// # Arguments
+    /// * `first` - The label of a class.
+    /// * `second` - The label of another class.
+    /// * `merged` - The label of the merged class.
+    /// * `rng` - The random number generator used to break ties.
+    pub fn merge_classes_with_rng<R: Rng + ?Sized>(&mut self, first: &str, second: &str, merged: &str, rng: &mut R) -> Result<(), OVSAError> {
+        if first == second {
+            return Err(OVSAError::InvalidArgument(format!("cannot merge class {} with itself", first)));
+        }
+        for label in [first, second] {
+            if !self.counts.contains_key(label) {
+                return Err(OVSAError::InvalidArgument(format!("unknown class {}", label)));
+            }
+        }
+        if merged != first && merged != second && self.counts.contains_key(merged) {
+            return Err(OVSAError::InvalidArgument(format!("class {} already exists", merged)));
+        }
+
+        let (mut counts, mut n) = self.remove_class(first).expect("The class exists.");
+        let (other_counts, other_n) = self.remove_class(second).expect("The class exists.");
+        for (count, other) in counts.iter_mut().zip(other_counts) {
+            *count += other;
+        }
+        n += other_n;
+        self.prototypes.insert(merged, harden_with_rng(&counts, n, self.hardening, rng))?;
+        self.counts.insert(merged.to_string(), (counts, n));
+
+        Ok(())
+    }
+
+    /// Splits a class into sub-classes by clustering its training vectors with k-means, e.g. when a label covers
+    /// several distinct modes. The sub-classes are labelled `<label>/0`, `<label>/1`, ... (see `memory::scoped`) and
+    /// counted from the given vectors alone, so corrections of `retrain` to the class are discarded.
+    /// # Arguments
+    /// * `label` - The label of the class.
+    /// * `vectors` - The training vectors of the class.
+    /// * `n_parts` - The number of sub-classes, at least 2 and at most the number of vectors.
+    /// # Returns
+    /// The labels of the sub-classes, `InvalidArgument` for an unknown class or an invalid number of parts.
+    pub fn split_class(&mut self, label: &str, vectors: &[CsVec<i8>], n_parts: usize) -> Result<Vec<String>, OVSAError> {
+        with_global_rng(|rng| self.split_class_with_rng(label, vectors, n_parts, rng))
+    }
+
+    /// Splits a class into sub-classes, clustering and breaking ties with the provided random number generator.
+    /// # Arguments
+    /// * `label` - The label of the class.
+    /// * `vectors` - The training vectors of the class.
+    /// * `n_parts` - The number of sub-classes.
+    /// * `rng` - The random number generator choosing the initial centers and breaking ties.
+    /// # Returns
+    /// The labels of the sub-classes.
+    pub fn split_class_with_rng<R: Rng + ?Sized>(&mut self, label: &str, vectors: &[CsVec<i8>], n_parts: usize, rng: &mut R) -> Result<Vec<String>, OVSAError> {
+        if !self.counts.contains_key(label) {
+            return Err(OVSAError::InvalidArgument(format!("unknown class {}", label)));
+        }
+        if n_parts < 2 || n_parts > vectors.len() {
+            return Err(OVSAError::InvalidArgument(format!("cannot split {} vectors into {} classes", vectors.len(), n_parts)));
+        }
+        if vectors.iter().any(|vector| vector.dim() != self.dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        let labels: Vec<String> = (0..n_parts).map(|part| scoped(label, &part.to_string())).collect();
+        if let Some(existing) = labels.iter().find(|part| self.counts.contains_key(part.as_str())) {
+            return Err(OVSAError::InvalidArgument(format!("class {} already exists", existing)));
+        }
+
+        let centers = cluster_with_rng(vectors, n_parts, rng)?;
+        let mut parts = vec![(vec![0; self.dimension], 0); n_parts];
+        for vector in vectors {
+            let (counts, n) = &mut parts[closest(&centers, vector)?.0];
+            for &index in vector.indices() {
+                counts[index] += 1;
+            }
+            *n += 1;
+        }
+
+        self.remove_class(label);
+        for (part, (counts, n)) in labels.iter().zip(parts) {
+            self.prototypes.insert(part, harden_with_rng(&counts, n, self.hardening, rng))?;
+            self.counts.insert(part.clone(), (counts, n));
+        }
+
+        Ok(labels)
+    }
+
+    /// Removes a class, returning its counts.
+    fn remove_class(&mut self, label: &str) -> Option<(Vec<u32>, usize)> {
+        self.prototypes.remove(label);
+        self.counts.remove(label)
+    }
+
+    /// Restores a classifier from per-class counts, e.g. when loading a saved model.
+    /// # Arguments
+    /// * `dimension` - The dimension of the classified vectors.
+    /// * `classes` - The (label, counts, number of training vectors) of every class.
+    /// # Returns
+    /// A `CentroidClassifier` with the prototypes recomputed from the counts.
+    pub fn from_counts(dimension: usize, classes: Vec<(String, Vec<u32>, usize)>) -> Result<Self, OVSAError> {
+        CentroidClassifier::from_counts_with_hardening(dimension, classes, Hardening::Majority)
+    }
+
+    /// Restores a classifier from per-class counts, hardening the prototypes with the given hardening.
+    /// # Arguments
+    /// * `dimension` - The dimension of the classified vectors.
+    /// * `classes` - The (label, counts, number of training vectors) of every class.
+    /// * `hardening` - The hardening of the prototypes, kept for later training.
+    /// # Returns
+    /// A `CentroidClassifier` with the prototypes recomputed from the counts.
+    pub fn from_counts_with_hardening(dimension: usize, classes: Vec<(String, Vec<u32>, usize)>, hardening: Hardening) -> Result<Self, OVSAError> {
+        let mut classifier = CentroidClassifier::new(dimension)?.with_hardening(hardening);
+        with_global_rng(|rng| {
+            for (label, counts, n) in classes {
+                if counts.len() != dimension {
+                    return Err(OVSAError::VectorSizeMismatch);
+                }
+                classifier.prototypes.insert(&label, harden_with_rng(&counts, n, hardening, rng))?;
+                classifier.counts.insert(label, (counts, n));
+            }
+            Ok(())
+        })?;
+
+        Ok(classifier)
+    }
+}
+
@@ -0,0 +1,184 @@
+//! Online training of a `CentroidClassifier` in shuffled, stratified mini-batches with per-epoch validation and early
+//! stopping. Mistake-driven training depends on the order of the samples: a batch of one class corrects the others
+//! away from it, so every batch holds the classes in the proportions of the whole training set.
+
+use std::collections::BTreeMap;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use sprs::CsVec;
+
+use crate::errors::OVSAError;
+use crate::learn::CentroidClassifier;
+use crate::learn::evaluation::evaluate;
+use crate::rng::with_global_rng;
+use crate::trace::span;
+
+
+/// The settings of `train_online`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrainingParams {
+    /// The number of samples of a mini-batch.
+    pub batch_size: usize,
+    /// The maximum number of epochs.
+    pub epochs: usize,
+    /// The number of epochs without an improvement of the validation accuracy after which training stops.
+    pub patience: usize,
+    /// The smallest increase of the validation accuracy that counts as an improvement.
+    pub min_delta: f64,
+}
+
+
+impl Default for TrainingParams {
+    fn default() -> Self {
+        TrainingParams { batch_size: 32, epochs: 20, patience: 3, min_delta: 0.0 }
+    }
+}
+
+
+/// The outcome of one epoch of `train_online`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EpochReport {
+    /// The number of training samples added to their class: all of them in the first epoch, the misclassified ones
+    /// afterwards.
+    pub updates: usize,
+    /// The accuracy on the validation samples after the epoch.
+    pub validation_accuracy: f64,
+}
+
+
+/// The outcome of `train_online`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingReport {
+    /// The report of every epoch run, in order.
+    pub epochs: Vec<EpochReport>,
+    /// The index of the epoch with the best validation accuracy, whose classifier is kept.
+    pub best_epoch: usize,
+    /// Whether training stopped before the maximum number of epochs, because the validation accuracy stopped
+    /// improving or an epoch made no corrections.
+    pub stopped_early: bool,
+}
+
+
+impl TrainingReport {
+    /// Returns the validation accuracy of the kept classifier.
+    pub fn best_accuracy(&self) -> f64 {
+        self.epochs[self.best_epoch].validation_accuracy
+    }
+}
+
+
+/// Splits samples into shuffled mini-batches that hold every class in its overall proportion. The samples of every
+/// class are shuffled and spread evenly over the sequence of all samples, which is then cut into batches.
+/// # Arguments
+/// * `samples` - The (sample, label) pairs.
+/// * `batch_size` - The number of samples of a batch; the last batch may be smaller.
+/// # Returns
+/// The indices of the samples of every batch.
+pub fn stratified_batches<T>(samples: &[(T, String)], batch_size: usize) -> Result<Vec<Vec<usize>>, OVSAError> {
+    with_global_rng(|rng| stratified_batches_with_rng(samples, batch_size, rng))
+}
+
+
+/// Splits samples into stratified mini-batches, shuffling with the provided random number generator.
+/// # Arguments
+/// * `samples` - The (sample, label) pairs.
+/// * `batch_size` - The number of samples of a batch; the last batch may be smaller.
+/// * `rng` - The random number generator.
+/// # Returns
+/// The indices of the samples of every batch.
+pub fn stratified_batches_with_rng<T, R: Rng + ?Sized>(samples: &[(T, String)], batch_size: usize, rng: &mut R) -> Result<Vec<Vec<usize>>, OVSAError> {
+    if batch_size == 0 {
+        return Err(OVSAError::InvalidArgument("the batch size must be positive".to_string()));
+    }
+
+    let mut classes: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (index, (_, label)) in samples.iter().enumerate() {
+        classes.entry(label).or_default().push(index);
+    }
+
+    // the j-th of the n samples of a class is placed at (j + u) / n for a random offset u of the class
+    let mut positions: Vec<(f64, usize)> = Vec::with_capacity(samples.len());
+    for indices in classes.values_mut() {
+        indices.shuffle(rng);
+        let offset: f64 = rng.random();
+        positions.extend(indices.iter().enumerate().map(|(j, &index)| ((j as f64 + offset) / indices.len() as f64, index)));
+    }
+    positions.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    Ok(positions.chunks(batch_size).map(|batch| batch.iter().map(|&(_, index)| index).collect()).collect())
+}
+
+
+/// Trains a classifier online in stratified mini-batches. The first epoch adds every sample to its class, batch by
+/// batch; later epochs correct the classifier perceptron-style on the misclassified samples of every batch, as
+/// `CentroidClassifier::retrain`. After every epoch the classifier is evaluated on the validation samples; training
+/// stops once the accuracy has not improved for `patience` epochs or an epoch makes no corrections, and the classifier
+/// of the best epoch is kept.
+/// # Arguments
+/// * `classifier` - The classifier, usually new.
+/// * `train` - The (vector, label) training pairs.
+/// * `validation` - The (vector, label) validation pairs.
+/// * `params` - The batch size, number of epochs and early stopping settings.
+/// # Returns
+/// The `TrainingReport`, `EmptyVectorList` if either set of samples is empty, or `InvalidArgument` for zero epochs or a
+/// zero batch size.
+pub fn train_online(classifier: &mut CentroidClassifier, train: &[(CsVec<i8>, String)], validation: &[(CsVec<i8>, String)], params: &TrainingParams) -> Result<TrainingReport, OVSAError> {
+    with_global_rng(|rng| train_online_with_rng(classifier, train, validation, params, rng))
+}
+
+
+/// Trains a classifier online, shuffling and breaking prototype ties with the provided random number generator.
+/// # Arguments
+/// * `classifier` - The classifier, usually new.
+/// * `train` - The (vector, label) training pairs.
+/// * `validation` - The (vector, label) validation pairs.
+/// * `params` - The batch size, number of epochs and early stopping settings.
+/// * `rng` - The random number generator.
+/// # Returns
+/// The `TrainingReport`.
+pub fn train_online_with_rng<R: Rng + ?Sized>(classifier: &mut CentroidClassifier, train: &[(CsVec<i8>, String)], validation: &[(CsVec<i8>, String)], params: &TrainingParams, rng: &mut R) -> Result<TrainingReport, OVSAError> {
+    if train.is_empty() || validation.is_empty() {
+        return Err(OVSAError::EmptyVectorList);
+    }
+    if train.iter().any(|(vector, _)| vector.dim() != classifier.dimension()) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    if params.epochs == 0 {
+        return Err(OVSAError::InvalidArgument("training needs at least one epoch".to_string()));
+    }
+    span!(INFO, "train_online", n_train = train.len(), n_validation = validation.len(), batch_size = params.batch_size);
+
+    let mut epochs = Vec::with_capacity(params.epochs);
+    let mut best: Option<(usize, f64, CentroidClassifier)> = None;
+    let mut stopped_early = false;
+    for epoch in 0..params.epochs {
+        let mut updates = 0;
+        for batch in stratified_batches_with_rng(train, params.batch_size, rng)? {
+            let batch: Vec<&(CsVec<i8>, String)> = batch.into_iter().map(|index| &train[index]).collect();
+            if epoch == 0 {
+                let batch: Vec<(CsVec<i8>, String)> = batch.into_iter().cloned().collect();
+                classifier.fit_with_rng(&batch, rng)?;
+                updates += batch.len();
+            } else {
+                updates += classifier.correct_with_rng(&batch, rng)?;
+            }
+        }
+
+        let validation_accuracy = evaluate(classifier, validation)?.accuracy();
+        epochs.push(EpochReport { updates, validation_accuracy });
+        match &best {
+            Some((_, accuracy, _)) if validation_accuracy <= accuracy + params.min_delta => {}
+            _ => best = Some((epoch, validation_accuracy, classifier.clone())),
+        }
+
+        let best_epoch = best.as_ref().map_or(0, |(best_epoch, _, _)| *best_epoch);
+        if epoch + 1 < params.epochs && (updates == 0 || epoch - best_epoch >= params.patience) {
+            stopped_early = true;
+            break;
+        }
+    }
+
+    let (best_epoch, _, best_classifier) = best.expect("At least one epoch ran.");
+    *classifier = best_classifier;
+    Ok(TrainingReport { epochs, best_epoch, stopped_early })
+}
@@ -0,0 +1,220 @@
+//! OnlineHD-style training (Hernández-Cano et al., 2021): every class keeps a real-valued accumulator, and a sample
+//! moves it by an amount that shrinks as the class already recognizes the sample. A sample of similarity `δ` to its
+//! class is added with the weight `η (1 − δ)`; when another class wins, the sample is also subtracted from the winner
+//! with the weight `η (1 − δ_winner)`. Samples that are already well represented barely change the model, so the
+//! accumulators do not saturate on common patterns the way majority counts do.
+
+use sprs::CsVec;
+
+use crate::errors::OVSAError;
+use crate::trace::span;
+
+
+/// The learning rate `η` of every epoch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LearningRate {
+    /// The same rate in every epoch.
+    Constant(f64),
+    /// The initial rate multiplied by `factor` after every `every` epochs.
+    Step { initial: f64, factor: f64, every: usize },
+    /// The initial rate divided by `1 + decay * epoch`.
+    InverseTime { initial: f64, decay: f64 },
+}
+
+
+impl Default for LearningRate {
+    fn default() -> Self {
+        LearningRate::Constant(0.035)
+    }
+}
+
+
+impl LearningRate {
+    /// Returns the learning rate of an epoch, counted from 0.
+    pub fn at(&self, epoch: usize) -> f64 {
+        match *self {
+            LearningRate::Constant(rate) => rate,
+            LearningRate::Step { initial, factor, every } => initial * factor.powi((epoch / every.max(1)) as i32),
+            LearningRate::InverseTime { initial, decay } => initial / (1.0 + decay * epoch as f64),
+        }
+    }
+
+    /// Checks that the schedule yields finite, positive rates.
+    fn validate(&self) -> Result<(), OVSAError> {
+        let valid = match *self {
+            LearningRate::Constant(rate) => rate.is_finite() && rate > 0.0,
+            LearningRate::Step { initial, factor, every } => initial.is_finite() && initial > 0.0 && factor.is_finite() && factor > 0.0 && every > 0,
+            LearningRate::InverseTime { initial, decay } => initial.is_finite() && initial > 0.0 && decay.is_finite() && decay >= 0.0,
+        };
+        if !valid {
+            return Err(OVSAError::InvalidArgument(format!("invalid learning rate schedule {:?}", self)));
+        }
+
+        Ok(())
+    }
+}
+
+
+/// A classifier with one real-valued accumulator per class, trained with margin-scaled updates and predicting the
+/// class of the highest cosine similarity.
+#[derive(Debug, Clone)]
+pub struct OnlineHDClassifier {
+    dimension: usize,
+    schedule: LearningRate,
+    labels: Vec<String>,
+    accumulators: Vec<Vec<f64>>,
+    /// The squared norm of every accumulator, kept up to date by the updates.
+    squared_norms: Vec<f64>,
+}
+
+
+impl OnlineHDClassifier {
+    /// Creates an empty classifier.
+    /// # Arguments
+    /// * `dimension` - The dimension of the vectors to classify.
+    /// * `schedule` - The learning rate of every epoch of `fit`.
+    /// # Returns
+    /// A new `OnlineHDClassifier`, `ZeroDimension` or `InvalidArgument` for a schedule with a non-positive rate.
+    pub fn new(dimension: usize, schedule: LearningRate) -> Result<Self, OVSAError> {
+        if dimension == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+        schedule.validate()?;
+
+        Ok(OnlineHDClassifier { dimension, schedule, labels: Vec::new(), accumulators: Vec::new(), squared_norms: Vec::new() })
+    }
+
+    /// Returns the dimension of the vectors.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the learning rate schedule.
+    pub fn schedule(&self) -> LearningRate {
+        self.schedule
+    }
+
+    /// Returns the labels of the classes, in order of first appearance.
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// Returns the accumulator of a class.
+    pub fn accumulator(&self, label: &str) -> Option<&[f64]> {
+        self.class_index(label).map(|class| self.accumulators[class].as_slice())
+    }
+
+    /// Computes the cosine similarity of a vector to every class.
+    /// # Arguments
+    /// * `vector` - The vector to compare.
+    /// # Returns
+    /// The similarity of every class in label order, 0 for a class whose accumulator is zero.
+    pub fn similarities(&self, vector: &CsVec<i8>) -> Result<Vec<f64>, OVSAError> {
+        if vector.dim() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let vector_norm = (vector.nnz() as f64).sqrt();
+        Ok(self.accumulators.iter().zip(&self.squared_norms)
+            .map(|(accumulator, &squared_norm)| {
+                let dot: f64 = vector.indices().iter().map(|&index| accumulator[index]).sum();
+                let norm = squared_norm.sqrt() * vector_norm;
+                if norm > 0.0 { dot / norm } else { 0.0 }
+            })
+            .collect())
+    }
+
+    /// Predicts the class of the highest similarity, the first one on ties.
+    /// # Arguments
+    /// * `vector` - The vector to classify.
+    /// # Returns
+    /// The (label, similarity) pair of the best class, or `None` if the classifier was not trained.
+    pub fn predict(&self, vector: &CsVec<i8>) -> Result<Option<(String, f64)>, OVSAError> {
+        let similarities = self.similarities(vector)?;
+        Ok(best(&similarities).map(|class| (self.labels[class].clone(), similarities[class])))
+    }
+
+    /// Trains the classifier on one sample: the sample is added to its class with the weight
+    /// `learning_rate * (1 - similarity)` and, if another class has the highest similarity, subtracted from that class
+    /// with the weight `learning_rate * (1 - its similarity)`.
+    /// # Arguments
+    /// * `vector` - The training vector.
+    /// * `label` - Its class.
+    /// * `learning_rate` - The learning rate `η`.
+    /// # Returns
+    /// Whether another class had the highest similarity before the update.
+    pub fn update(&mut self, vector: &CsVec<i8>, label: &str, learning_rate: f64) -> Result<bool, OVSAError> {
+        let mut similarities = self.similarities(vector)?;
+        let predicted = best(&similarities);
+        let class = match self.class_index(label) {
+            Some(class) => class,
+            None => {
+                self.labels.push(label.to_string());
+                self.accumulators.push(vec![0.0; self.dimension]);
+                self.squared_norms.push(0.0);
+                similarities.push(0.0);
+                self.labels.len() - 1
+            }
+        };
+
+        self.add(class, vector, learning_rate * (1.0 - similarities[class]));
+        match predicted {
+            Some(predicted) if predicted != class => {
+                self.add(predicted, vector, -learning_rate * (1.0 - similarities[predicted]));
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Trains the classifier with one pass over the samples per epoch, in order, at the learning rate of the
+    /// schedule. Training can continue with further calls.
+    /// # Arguments
+    /// * `samples` - The (vector, label) training pairs.
+    /// * `epochs` - The number of passes; the schedule counts them from 0 in every call.
+    /// # Returns
+    /// The number of samples of every epoch that another class had claimed before their update.
+    pub fn fit(&mut self, samples: &[(CsVec<i8>, String)], epochs: usize) -> Result<Vec<usize>, OVSAError> {
+        span!(INFO, "fit_onlinehd", n_samples = samples.len(), epochs);
+        if samples.iter().any(|(vector, _)| vector.dim() != self.dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let mut mistakes = Vec::with_capacity(epochs);
+        for epoch in 0..epochs {
+            let learning_rate = self.schedule.at(epoch);
+            let mut n_mistakes = 0;
+            for (vector, label) in samples {
+                n_mistakes += usize::from(self.update(vector, label, learning_rate)?);
+            }
+            mistakes.push(n_mistakes);
+        }
+
+        Ok(mistakes)
+    }
+
+    fn class_index(&self, label: &str) -> Option<usize> {
+        self.labels.iter().position(|known| known == label)
+    }
+
+    /// Adds a weighted binary vector to the accumulator of a class.
+    fn add(&mut self, class: usize, vector: &CsVec<i8>, weight: f64) {
+        let accumulator = &mut self.accumulators[class];
+        for &index in vector.indices() {
+            let old = accumulator[index];
+            accumulator[index] += weight;
+            self.squared_norms[class] += accumulator[index] * accumulator[index] - old * old;
+        }
+        // rounding must not leave a negative squared norm
+        self.squared_norms[class] = self.squared_norms[class].max(0.0);
+    }
+}
+
+
+/// Returns the index of the highest value, the first one on ties.
+fn best(values: &[f64]) -> Option<usize> {
+    values.iter().enumerate().fold(None, |best, (index, &value)| match best {
+        Some((_, best_value)) if best_value >= value => best,
+        _ => Some((index, value)),
+    }).map(|(index, _)| index)
+}
@@ -0,0 +1,255 @@
+//! State-action value memory for reinforcement learning: every stored value is the binding `state ^ action ^ level`
+//! of an encoded state, an action symbol and the level vector of the value, and all bindings are superposed in signed
+//! counters. Reading `Q(s, a)` unbinds `state ^ action` and finds the level that best matches what remains.
+
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::counters::Counters;
+use crate::binary::xor;
+use crate::encode::levels_with_rng;
+use crate::errors::OVSAError;
+use crate::memory::ItemMemory;
+use crate::rng::with_global_rng;
+
+
+/// The score below which a state-action pair counts as never stored: a stored binding scores about 1, an unrelated
+/// query about 0.
+const PRESENCE_THRESHOLD: f64 = 0.5;
+
+
+/// One step of interaction with an environment.
+#[derive(Debug, Clone, Copy)]
+pub struct Transition<'a> {
+    /// The encoded state the action was taken in.
+    pub state: &'a CsVec<i8>,
+    /// The action taken.
+    pub action: &'a str,
+    /// The reward received.
+    pub reward: f64,
+    /// The encoded state reached, or `None` at the end of an episode.
+    pub next_state: Option<&'a CsVec<i8>>,
+}
+
+
+/// A superposition of state-action values.
+#[derive(Debug, Clone)]
+pub struct QMemory {
+    n_active: usize,
+    min_value: f64,
+    max_value: f64,
+    actions: ItemMemory,
+    levels: Vec<CsVec<i8>>,
+    counters: Counters<i32>,
+}
+
+
+impl QMemory {
+    /// Creates an empty memory.
+    /// # Arguments
+    /// * `dimension` - The dimension of the state vectors.
+    /// * `n_active` - The number of active entries of action symbols and levels; about half the dimension.
+    /// * `min_value` - The lowest representable value.
+    /// * `max_value` - The highest representable value.
+    /// * `n_levels` - The number of value levels, i.e. the value resolution.
+    /// # Returns
+    /// A new `QMemory`.
+    pub fn new(dimension: usize, n_active: usize, min_value: f64, max_value: f64, n_levels: usize) -> Result<Self, OVSAError> {
+        with_global_rng(|rng| Self::new_with_rng(dimension, n_active, min_value, max_value, n_levels, rng))
+    }
+
+    /// Creates an empty memory, drawing the value levels with the provided random number generator.
+    /// # Arguments
+    /// * `dimension` - The dimension of the state vectors.
+    /// * `n_active` - The number of active entries of action symbols and levels; about half the dimension.
+    /// * `min_value` - The lowest representable value.
+    /// * `max_value` - The highest representable value.
+    /// * `n_levels` - The number of value levels, at least 2.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// A new `QMemory`.
+    pub fn new_with_rng<R: Rng + ?Sized>(dimension: usize, n_active: usize, min_value: f64, max_value: f64, n_levels: usize, rng: &mut R) -> Result<Self, OVSAError> {
+        if min_value.is_nan() || max_value.is_nan() || min_value >= max_value {
+            return Err(OVSAError::InvalidArgument(format!("the value range [{}, {}] is empty", min_value, max_value)));
+        }
+        if n_levels < 2 {
+            return Err(OVSAError::InvalidArgument(format!("a value memory needs at least 2 levels, got {}", n_levels)));
+        }
+
+        Ok(QMemory {
+            n_active,
+            min_value,
+            max_value,
+            actions: ItemMemory::new(dimension)?,
+            levels: levels_with_rng(dimension, n_active, n_levels, rng)?,
+            counters: Counters::new(dimension)?,
+        })
+    }
+
+    /// Returns the dimension of the state vectors.
+    pub fn dimension(&self) -> usize {
+        self.actions.dimension()
+    }
+
+    /// Returns the action symbols created so far.
+    pub fn actions(&self) -> &ItemMemory {
+        &self.actions
+    }
+
+    /// Returns the number of bindings in the superposition.
+    pub fn len(&self) -> usize {
+        self.counters.n()
+    }
+
+    /// Returns whether no value was stored.
+    pub fn is_empty(&self) -> bool {
+        self.counters.n() == 0
+    }
+
+    /// Reads the value of an action in a state.
+    /// # Arguments
+    /// * `state` - The encoded state.
+    /// * `action` - The action label.
+    /// # Returns
+    /// The value, quantized to the levels, or `None` if the pair was never stored.
+    pub fn value(&self, state: &CsVec<i8>, action: &str) -> Result<Option<f64>, OVSAError> {
+        let Some(action) = self.actions.get(action) else {
+            return Ok(None);
+        };
+        let key = xor(state, action)?;
+        Ok(self.read(&key)?.map(|level| self.level_value(level)))
+    }
+
+    /// Moves the value of an action in a state towards a target, or stores the target for a new pair. Values are
+    /// quantized to the levels, so steps smaller than half a level are rounded away; with a small learning rate,
+    /// use correspondingly many levels.
+    /// # Arguments
+    /// * `state` - The encoded state.
+    /// * `action` - The action label.
+    /// * `target` - The target value, clamped to the value range.
+    /// * `learning_rate` - The share of the difference to the target applied, in `(0, 1]`.
+    /// # Returns
+    /// The new value.
+    pub fn update(&mut self, state: &CsVec<i8>, action: &str, target: f64, learning_rate: f64) -> Result<f64, OVSAError> {
+        with_global_rng(|rng| self.update_with_rng(state, action, target, learning_rate, rng))
+    }
+
+    /// Moves the value of an action in a state towards a target, drawing new action symbols with the provided random
+    /// number generator.
+    /// # Arguments
+    /// * `state` - The encoded state.
+    /// * `action` - The action label.
+    /// * `target` - The target value, clamped to the value range.
+    /// * `learning_rate` - The share of the difference to the target applied, in `(0, 1]`.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// The new value.
+    pub fn update_with_rng<R: Rng + ?Sized>(&mut self, state: &CsVec<i8>, action: &str, target: f64, learning_rate: f64, rng: &mut R) -> Result<f64, OVSAError> {
+        if !(learning_rate > 0.0 && learning_rate <= 1.0) {
+            return Err(OVSAError::InvalidArgument(format!("learning rate must be in (0, 1], got {}", learning_rate)));
+        }
+        if target.is_nan() {
+            return Err(OVSAError::InvalidArgument("the target value is not a number".to_string()));
+        }
+
+        let action = self.actions.symbol_with_rng(action, self.n_active, rng)?;
+        let key = xor(state, action)?;
+        let value = match self.read(&key)? {
+            Some(level) => {
+                self.counters.remove(&xor(&key, &self.levels[level])?)?;
+                let current = self.level_value(level);
+                current + learning_rate * (target - current)
+            }
+            None => target,
+        };
+
+        let level = self.level_index(value);
+        self.counters.add(&xor(&key, &self.levels[level])?)?;
+        Ok(self.level_value(level))
+    }
+
+    /// Returns the action with the highest stored value in a state.
+    /// # Arguments
+    /// * `state` - The encoded state.
+    /// * `actions` - The candidate actions.
+    /// # Returns
+    /// The (action, value) pair of the best action, or `None` if no candidate was stored for the state.
+    pub fn greedy_action(&self, state: &CsVec<i8>, actions: &[&str]) -> Result<Option<(String, f64)>, OVSAError> {
+        let mut best: Option<(String, f64)> = None;
+        for action in actions {
+            if let Some(value) = self.value(state, action)? && best.as_ref().is_none_or(|(_, best_value)| value > *best_value) {
+                best = Some((action.to_string(), value));
+            }
+        }
+        Ok(best)
+    }
+
+    /// Applies one Q-learning step: the value of the action taken moves towards the reward plus the discounted value
+    /// of the best action in the next state. Next states without stored actions count as 0.
+    /// # Arguments
+    /// * `transition` - The observed transition.
+    /// * `actions` - The actions available in the next state.
+    /// * `discount` - The discount factor, in `[0, 1]`.
+    /// * `learning_rate` - The share of the temporal difference applied, in `(0, 1]`.
+    /// # Returns
+    /// The new value of the action.
+    pub fn q_learning_step(&mut self, transition: &Transition<'_>, actions: &[&str], discount: f64, learning_rate: f64) -> Result<f64, OVSAError> {
+        with_global_rng(|rng| self.q_learning_step_with_rng(transition, actions, discount, learning_rate, rng))
+    }
+
+    /// Applies one Q-learning step, drawing new action symbols with the provided random number generator.
+    /// # Arguments
+    /// * `transition` - The observed transition.
+    /// * `actions` - The actions available in the next state.
+    /// * `discount` - The discount factor, in `[0, 1]`.
+    /// * `learning_rate` - The share of the temporal difference applied, in `(0, 1]`.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// The new value of the action.
+    pub fn q_learning_step_with_rng<R: Rng + ?Sized>(&mut self, transition: &Transition<'_>, actions: &[&str], discount: f64, learning_rate: f64, rng: &mut R) -> Result<f64, OVSAError> {
+        if !(0.0..=1.0).contains(&discount) {
+            return Err(OVSAError::InvalidArgument(format!("discount must be in [0, 1], got {}", discount)));
+        }
+
+        let future = match transition.next_state {
+            Some(next_state) => self.greedy_action(next_state, actions)?.map_or(0.0, |(_, value)| value),
+            None => 0.0,
+        };
+        self.update_with_rng(transition.state, transition.action, transition.reward + discount * future, learning_rate, rng)
+    }
+
+    /// Finds the level best matching the unbound key, if the key was stored.
+    fn read(&self, key: &CsVec<i8>) -> Result<Option<usize>, OVSAError> {
+        if self.counters.n() == 0 {
+            return Ok(None);
+        }
+
+        // unbind the key from the counters once: flipping the counters at the key's active entries turns the
+        // bipolar inner product with `key ^ level` into one with `level`
+        let mut unbound: Vec<i64> = self.counters.counts().iter().map(|&count| count as i64).collect();
+        for &index in key.indices() {
+            unbound[index] = -unbound[index];
+        }
+        let total: i64 = unbound.iter().sum();
+
+        let mut best: Option<(usize, f64)> = None;
+        for (level, vector) in self.levels.iter().enumerate() {
+            // 1 per exact copy of the binding stored
+            let active: i64 = vector.indices().iter().map(|&index| unbound[index]).sum();
+            let score = (2 * active - total) as f64 / self.dimension() as f64;
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((level, score));
+            }
+        }
+        Ok(best.filter(|&(_, score)| score >= PRESENCE_THRESHOLD).map(|(level, _)| level))
+    }
+
+    fn level_index(&self, value: f64) -> usize {
+        let fraction = ((value - self.min_value) / (self.max_value - self.min_value)).clamp(0.0, 1.0);
+        (fraction * (self.levels.len() - 1) as f64).round() as usize
+    }
+
+    fn level_value(&self, level: usize) -> f64 {
+        self.min_value + (self.max_value - self.min_value) * level as f64 / (self.levels.len() - 1) as f64
+    }
+}
@@ -0,0 +1,149 @@
+//! Gradient-free refinement of codebooks: symbols are perturbed by bit swaps and a perturbation is kept when it widens
+//! the margin of a centroid classifier on a validation set.
+
+use rand::{Rng, SeedableRng};
+use rand::seq::index::sample;
+use sprs::CsVec;
+
+use crate::binary::from_indices_or_empty;
+use crate::errors::OVSAError;
+use crate::learn::CentroidClassifier;
+use crate::memory::ItemMemory;
+use crate::rng::{OvsaRng, with_global_rng};
+use crate::trace::span;
+
+
+/// The settings of `refine_codebook`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefineParams {
+    /// The number of perturbations tried.
+    pub iterations: usize,
+    /// The number of active entries a perturbation moves to inactive positions.
+    pub swaps: usize,
+}
+
+
+impl Default for RefineParams {
+    fn default() -> Self {
+        RefineParams { iterations: 100, swaps: 8 }
+    }
+}
+
+
+/// The outcome of `refine_codebook`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RefineReport {
+    /// The mean validation margin of the original codebook.
+    pub initial_margin: f64,
+    /// The mean validation margin of the refined codebook.
+    pub final_margin: f64,
+    /// The number of perturbations that were kept.
+    pub accepted: usize,
+}
+
+
+/// Refines the symbols of a codebook by hill climbing. Every iteration moves a few active entries of one random symbol
+/// to inactive positions, encodes the samples with the perturbed codebook, retrains a `CentroidClassifier` and keeps the
+/// perturbation only if the mean validation margin grows. The margin of a sample is the similarity to its class
+/// prototype minus the largest similarity to another prototype.
+/// Every iteration encodes all samples again, so the training and validation sets should be small samples.
+/// The refinement runs on a generator seeded from the global one, which is not locked while encoding, so `encode` may
+/// use it.
+/// # Arguments
+/// * `codebook` - The codebook to refine, e.g. the role or level symbols of an encoder.
+/// * `train` - The (input, label) pairs the classifier is trained on.
+/// * `validation` - The (input, label) pairs the margin is measured on.
+/// * `encode` - Encodes an input with the given codebook.
+/// * `params` - The number of iterations and swaps.
+/// # Returns
+/// The `RefineReport` with the margins before and after the refinement.
+pub fn refine_codebook<T, E: FnMut(&ItemMemory, &T) -> Result<CsVec<i8>, OVSAError>>(codebook: &mut ItemMemory, train: &[(T, String)], validation: &[(T, String)], encode: E, params: RefineParams) -> Result<RefineReport, OVSAError> {
+    let mut rng = OvsaRng::seed_from_u64(with_global_rng(|rng| rng.random()));
+    refine_codebook_with_rng(codebook, train, validation, encode, params, &mut rng)
+}
+
+
+/// Refines the symbols of a codebook by hill climbing, using the provided random number generator.
+/// # Arguments
+/// * `codebook` - The codebook to refine.
+/// * `train` - The (input, label) pairs the classifier is trained on.
+/// * `validation` - The (input, label) pairs the margin is measured on.
+/// * `encode` - Encodes an input with the given codebook.
+/// * `params` - The number of iterations and swaps.
+/// * `rng` - The random number generator drawing the perturbations and breaking prototype ties.
+/// # Returns
+/// The `RefineReport` with the margins before and after the refinement.
+pub fn refine_codebook_with_rng<T, E, R>(codebook: &mut ItemMemory, train: &[(T, String)], validation: &[(T, String)], mut encode: E, params: RefineParams, rng: &mut R) -> Result<RefineReport, OVSAError>
+where
+    E: FnMut(&ItemMemory, &T) -> Result<CsVec<i8>, OVSAError>,
+    R: Rng + ?Sized,
+{
+    span!(INFO, "refine_codebook", n_symbols = codebook.len(), iterations = params.iterations);
+    if codebook.is_empty() {
+        return Err(OVSAError::EmptyVectorList);
+    }
+
+    let initial_margin = margin(codebook, train, validation, &mut encode, rng)?;
+    let mut current = initial_margin;
+    let mut accepted = 0;
+    for _ in 0..params.iterations {
+        let label = codebook.labels()[rng.random_range(0..codebook.len())].clone();
+        let original = codebook.get(&label).cloned().ok_or(OVSAError::EmptyVectorList)?;
+        codebook.insert(&label, swap_entries(&original, params.swaps, rng))?;
+
+        let candidate = margin(codebook, train, validation, &mut encode, rng)?;
+        if candidate > current {
+            current = candidate;
+            accepted += 1;
+        } else {
+            codebook.insert(&label, original)?;
+        }
+    }
+
+    Ok(RefineReport { initial_margin, final_margin: current, accepted })
+}
+
+
+/// Trains a classifier on the encoded training samples and returns its mean margin on the validation samples whose
+/// class was trained.
+fn margin<T, E, R>(codebook: &ItemMemory, train: &[(T, String)], validation: &[(T, String)], encode: &mut E, rng: &mut R) -> Result<f64, OVSAError>
+where
+    E: FnMut(&ItemMemory, &T) -> Result<CsVec<i8>, OVSAError>,
+    R: Rng + ?Sized,
+{
+    let samples = train.iter().map(|(input, label)| Ok((encode(codebook, input)?, label.clone()))).collect::<Result<Vec<_>, OVSAError>>()?;
+    let mut classifier = CentroidClassifier::new(codebook.dimension())?;
+    classifier.fit_with_rng(&samples, rng)?;
+
+    let vectors = validation.iter().map(|(input, _)| encode(codebook, input)).collect::<Result<Vec<_>, _>>()?;
+    let matches = classifier.prototypes().query_batch(&vectors, usize::MAX)?;
+    let margins: Vec<f64> = matches.iter().zip(validation)
+        .filter_map(|(matches, (_, label))| {
+            let own = matches.iter().find(|(other, _)| other == label)?.1;
+            let best_other = matches.iter().find(|(other, _)| other != label).map_or(0.0, |(_, similarity)| *similarity);
+            Some(own - best_other)
+        })
+        .collect();
+    if margins.is_empty() {
+        return Err(OVSAError::EmptyVectorList);
+    }
+
+    Ok(margins.iter().sum::<f64>() / margins.len() as f64)
+}
+
+
+/// Moves `swaps` random active entries of a vector to random inactive positions, keeping its number of active entries.
+fn swap_entries<R: Rng + ?Sized>(vector: &CsVec<i8>, swaps: usize, rng: &mut R) -> CsVec<i8> {
+    let active = vector.indices();
+    let inactive: Vec<usize> = (0..vector.dim()).filter(|index| active.binary_search(index).is_err()).collect();
+    let swaps = swaps.min(active.len()).min(inactive.len());
+
+    let removed = sample(rng, active.len(), swaps).into_vec();
+    let mut indices: Vec<usize> = active.iter().enumerate()
+        .filter(|(position, _)| !removed.contains(position))
+        .map(|(_, &index)| index)
+        .collect();
+    indices.extend(sample(rng, inactive.len(), swaps).into_iter().map(|position| inactive[position]));
+
+    from_indices_or_empty(vector.dim(), indices)
+}
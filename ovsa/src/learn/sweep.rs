@@ -0,0 +1,177 @@
+//! Hyperparameter sweeps over numeric datasets: every combination of dimension, density and number of levels encodes
+//! the data anew, trains a `CentroidClassifier`, retrains it and evaluates it after every requested number of epochs,
+//! e.g. to reproduce dimension-accuracy curves. Every combination draws from its own random stream of the seed, so the
+//! results are reproducible and identical with and without the `parallel` feature, which evaluates the combinations
+//! in parallel.
+
+use std::fmt;
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::{bind_bundle_with_rng, sparse_random_with_rng};
+use crate::encode::{LevelEncoder, levels_with_rng};
+use crate::errors::OVSAError;
+use crate::learn::CentroidClassifier;
+use crate::learn::evaluation::evaluate;
+use crate::rng::stream_rng;
+use crate::trace::span;
+
+
+/// The values of the swept hyperparameters; the sweep runs every combination.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepGrid {
+    /// The dimensions of the vectors.
+    pub dimensions: Vec<usize>,
+    /// The densities of the level and role vectors, the share of active entries in `(0, 1]`.
+    pub densities: Vec<f64>,
+    /// The numbers of levels of the value encoder.
+    pub levels: Vec<usize>,
+    /// The numbers of retraining epochs after the initial fit, 0 for the plain centroid classifier. Retraining stops
+    /// early once an epoch makes no corrections.
+    pub epochs: Vec<usize>,
+}
+
+
+impl Default for SweepGrid {
+    fn default() -> Self {
+        SweepGrid { dimensions: vec![1000, 2000, 5000, 10000], densities: vec![0.5], levels: vec![16], epochs: vec![0, 5] }
+    }
+}
+
+
+/// The scores of one combination of hyperparameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepRow {
+    pub dimension: usize,
+    pub density: f64,
+    pub levels: usize,
+    pub epochs: usize,
+    /// The accuracy on the test set.
+    pub accuracy: f64,
+    /// The macro F1 score on the test set.
+    pub macro_f1: f64,
+}
+
+
+/// The results of a sweep, one row per combination in grid order, the epochs varying fastest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepTable {
+    pub rows: Vec<SweepRow>,
+}
+
+
+impl SweepTable {
+    /// Returns the row of the highest accuracy, the first one on ties, or `None` for an empty table.
+    pub fn best(&self) -> Option<&SweepRow> {
+        self.rows.iter().reduce(|best, row| if row.accuracy > best.accuracy { row } else { best })
+    }
+}
+
+
+impl fmt::Display for SweepTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dimension\tdensity\tlevels\tepochs\taccuracy\tmacro_f1")?;
+        for row in &self.rows {
+            write!(f, "\n{}\t{}\t{}\t{}\t{:.4}\t{:.4}", row.dimension, row.density, row.levels, row.epochs, row.accuracy, row.macro_f1)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Sweeps the hyperparameters of a record encoding of numeric features: every feature position is bound to a random
+/// role and its value to one of `levels` level vectors spanning the range of the training values.
+/// # Arguments
+/// * `train` - The (features, label) training samples, all with the same number of features.
+/// * `test` - The (features, label) test samples.
+/// * `grid` - The hyperparameter values.
+/// * `seed` - The seed of the random vectors.
+/// # Returns
+/// The `SweepTable` with one row per combination.
+pub fn sweep(train: &[(Vec<f64>, String)], test: &[(Vec<f64>, String)], grid: &SweepGrid, seed: u64) -> Result<SweepTable, OVSAError> {
+    let n_features = train.first().map(|(features, _)| features.len()).ok_or(OVSAError::EmptyVectorList)?;
+    if train.iter().chain(test).any(|(features, _)| features.len() != n_features) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    if [grid.dimensions.len(), grid.densities.len(), grid.levels.len(), grid.epochs.len()].contains(&0) {
+        return Err(OVSAError::EmptyVectorList);
+    }
+    if let Some(density) = grid.densities.iter().find(|density| !(**density > 0.0 && **density <= 1.0)) {
+        return Err(OVSAError::InvalidArgument(format!("densities must be in (0, 1], got {}", density)));
+    }
+    span!(INFO, "sweep", n_train = train.len(), n_test = test.len());
+
+    let values = train.iter().flat_map(|(features, _)| features.iter().copied());
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)));
+    let mut epochs = grid.epochs.clone();
+    epochs.sort_unstable();
+    epochs.dedup();
+
+    let combinations: Vec<(usize, f64, usize)> = grid.dimensions.iter()
+        .flat_map(|&dimension| grid.densities.iter().flat_map(move |&density| grid.levels.iter().map(move |&levels| (dimension, density, levels))))
+        .collect();
+    let run = |(stream, &(dimension, density, levels)): (usize, &(usize, f64, usize))| {
+        let setting = Setting { dimension, density, levels, min, max };
+        setting.run(train, test, &epochs, &mut stream_rng(seed, stream as u64))
+    };
+
+    #[cfg(feature = "parallel")]
+    let tables: Vec<Vec<SweepRow>> = {
+        use rayon::prelude::*;
+        crate::config::install(|| combinations.par_iter().enumerate().map(run).collect::<Result<_, _>>())?
+    };
+    #[cfg(not(feature = "parallel"))]
+    let tables: Vec<Vec<SweepRow>> = combinations.iter().enumerate().map(run).collect::<Result<_, _>>()?;
+
+    Ok(SweepTable { rows: tables.into_iter().flatten().collect() })
+}
+
+
+/// One combination of the encoding hyperparameters.
+struct Setting {
+    dimension: usize,
+    density: f64,
+    levels: usize,
+    min: f64,
+    max: f64,
+}
+
+
+impl Setting {
+    /// Encodes the data, trains and evaluates after every number of epochs, in increasing order.
+    fn run<R: Rng + ?Sized>(&self, train: &[(Vec<f64>, String)], test: &[(Vec<f64>, String)], epochs: &[usize], rng: &mut R) -> Result<Vec<SweepRow>, OVSAError> {
+        let n_active = ((self.density * self.dimension as f64).round() as usize).max(1);
+        let levels = LevelEncoder::new(self.min, self.max, levels_with_rng(self.dimension, n_active, self.levels, rng)?)?;
+        let n_features = train[0].0.len();
+        let roles = (0..n_features).map(|_| sparse_random_with_rng(self.dimension, n_active, rng)).collect::<Result<Vec<_>, _>>()?;
+        let mut encode = |samples: &[(Vec<f64>, String)]| {
+            samples.iter()
+                .map(|(features, label)| {
+                    let pairs: Vec<(&CsVec<i8>, &CsVec<i8>)> = roles.iter().zip(features).map(|(role, &value)| (role, levels.encode(value))).collect();
+                    Ok((bind_bundle_with_rng(&pairs, rng)?, label.clone()))
+                })
+                .collect::<Result<Vec<_>, OVSAError>>()
+        };
+        let (train, test) = (encode(train)?, encode(test)?);
+
+        let mut classifier = CentroidClassifier::new(self.dimension)?;
+        classifier.fit_with_rng(&train, rng)?;
+        let mut trained = 0;
+        let mut rows = Vec::with_capacity(epochs.len());
+        for &epoch in epochs {
+            classifier.retrain_with_rng(&train, epoch - trained, rng)?;
+            trained = epoch;
+            let evaluation = evaluate(&classifier, &test)?;
+            rows.push(SweepRow {
+                dimension: self.dimension,
+                density: self.density,
+                levels: self.levels,
+                epochs: epoch,
+                accuracy: evaluation.accuracy(),
+                macro_f1: evaluation.macro_f1(),
+            });
+        }
+
+        Ok(rows)
+    }
+}
@@ -1,6 +1,81 @@
 
+#[cfg(feature = "sparse")]
+pub mod analysis;
+
 pub mod binary;
 
+#[cfg(feature = "sparse")]
+pub mod codebook;
+
+#[cfg(any(feature = "parallel", feature = "async"))]
+pub mod config;
+
+#[cfg(feature = "dense")]
+pub mod convert;
+
+#[cfg(feature = "datasets")]
+pub mod datasets;
+
+#[cfg(feature = "dense")]
 pub mod dense;
 
-pub mod errors;
\ No newline at end of file
+#[cfg(feature = "sparse")]
+pub mod encode;
+
+pub mod errors;
+
+#[cfg(feature = "sparse")]
+pub mod experiments;
+
+#[cfg(feature = "sparse")]
+pub mod expr;
+
+#[cfg(feature = "dense")]
+pub mod fhrr;
+
+#[cfg(feature = "sparse")]
+pub mod golden;
+
+#[cfg(feature = "sparse")]
+pub mod hypervector;
+
+#[cfg(feature = "sparse")]
+pub mod inspect;
+
+#[cfg(feature = "sparse")]
+pub mod io;
+
+#[cfg(feature = "sparse")]
+pub mod learn;
+
+#[cfg(feature = "sparse")]
+pub mod mask;
+
+#[cfg(feature = "sparse")]
+pub mod memory;
+
+#[cfg(feature = "async")]
+pub mod nonblocking;
+
+#[cfg(feature = "sparse")]
+pub mod pipelines;
+
+pub mod progress;
+
+#[cfg(feature = "sparse")]
+pub mod rng;
+
+#[cfg(feature = "sparse")]
+pub mod script;
+
+#[cfg(feature = "sparse")]
+pub mod sketch;
+
+#[cfg(feature = "sparse")]
+pub mod structures;
+
+#[cfg(feature = "sparse")]
+mod trace;
+
+#[cfg(feature = "dense")]
+pub mod vfa;
@@ -0,0 +1,238 @@
+//! Dimension masks: a subset of dimensions kept across a model, e.g. to prune dimensions that do not help telling
+//! classes apart. Vectors can be masked by zeroing the dropped dimensions, keeping their dimension, or projected onto the
+//! kept dimensions, shrinking them. See `analysis::dimension_scores` to rank dimensions for a mask.
+//!
+//! Per-dimension weights soften a mask: weighted similarities such as `binary::weighted_similarity` scale the
+//! contribution of every dimension, so that the scores of `analysis::dimension_scores` can be used as weights directly,
+//! and encoders can emphasize some segments of their vectors over others.
+
+#[cfg(feature = "dense")]
+use ndarray::Array1;
+use std::ops::Range;
+use sprs::CsVec;
+
+use crate::binary::from_indices_or_empty;
+use crate::errors::OVSAError;
+use crate::memory::ItemMemory;
+
+
+/// The dimensions kept out of a vector space, in increasing order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DimensionMask {
+    dimension: usize,
+    kept: Vec<usize>,
+}
+
+
+impl DimensionMask {
+    /// Creates a mask keeping the given dimensions.
+    /// # Arguments
+    /// * `dimension` - The dimension of the masked vectors.
+    /// * `kept` - The kept dimensions, in any order; duplicates are ignored.
+    /// # Returns
+    /// A new `DimensionMask`.
+    pub fn new(dimension: usize, mut kept: Vec<usize>) -> Result<Self, OVSAError> {
+        if dimension == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+        kept.sort_unstable();
+        kept.dedup();
+        if kept.is_empty() {
+            return Err(OVSAError::EmptyIndices);
+        }
+        if kept.last().is_some_and(|&index| index >= dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        Ok(DimensionMask { dimension, kept })
+    }
+
+    /// Creates a mask keeping the dimensions with the highest scores, the lower dimension first on ties.
+    /// # Arguments
+    /// * `scores` - One score per dimension, e.g. from `analysis::dimension_scores`.
+    /// * `n_kept` - The number of dimensions to keep.
+    /// # Returns
+    /// A new `DimensionMask`.
+    pub fn from_scores(scores: &[f64], n_kept: usize) -> Result<Self, OVSAError> {
+        if n_kept > scores.len() {
+            return Err(OVSAError::TooManyActiveElements);
+        }
+
+        let mut ranking: Vec<usize> = (0..scores.len()).collect();
+        ranking.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then(a.cmp(&b)));
+        ranking.truncate(n_kept);
+        DimensionMask::new(scores.len(), ranking)
+    }
+
+    /// Returns the dimension of the masked vectors.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the number of kept dimensions, the dimension of projected vectors.
+    pub fn n_kept(&self) -> usize {
+        self.kept.len()
+    }
+
+    /// Returns the kept dimensions in increasing order.
+    pub fn kept(&self) -> &[usize] {
+        &self.kept
+    }
+
+    /// Zeroes the dropped dimensions of a sparse binary vector.
+    /// # Arguments
+    /// * `vec` - The vector to mask.
+    /// # Returns
+    /// The vector with only the active entries at kept dimensions, of the same dimension.
+    pub fn zero(&self, vec: &CsVec<i8>) -> Result<CsVec<i8>, OVSAError> {
+        self.check(vec.dim())?;
+        let indices = vec.indices().iter().copied().filter(|index| self.kept.binary_search(index).is_ok()).collect();
+        Ok(from_indices_or_empty(self.dimension, indices))
+    }
+
+    /// Projects a sparse binary vector onto the kept dimensions: the `i`-th kept dimension becomes dimension `i`.
+    /// Similarities of projected vectors only count the kept dimensions.
+    /// # Arguments
+    /// * `vec` - The vector to project.
+    /// # Returns
+    /// The projected vector of dimension `n_kept`.
+    pub fn project(&self, vec: &CsVec<i8>) -> Result<CsVec<i8>, OVSAError> {
+        self.check(vec.dim())?;
+        let indices = vec.indices().iter().filter_map(|index| self.kept.binary_search(index).ok()).collect();
+        Ok(from_indices_or_empty(self.kept.len(), indices))
+    }
+
+    /// Zeroes the dropped dimensions of a dense vector.
+    /// # Arguments
+    /// * `vec` - The vector to mask.
+    /// # Returns
+    /// The masked vector of the same dimension.
+    #[cfg(feature = "dense")]
+    pub fn zero_dense(&self, vec: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+        self.check(vec.len())?;
+        let mut result = Array1::<f32>::zeros(self.dimension);
+        for &index in &self.kept {
+            result[index] = vec[index];
+        }
+        Ok(result)
+    }
+
+    /// Projects a dense vector onto the kept dimensions.
+    /// # Arguments
+    /// * `vec` - The vector to project.
+    /// # Returns
+    /// The projected vector of dimension `n_kept`.
+    #[cfg(feature = "dense")]
+    pub fn project_dense(&self, vec: &Array1<f32>) -> Result<Array1<f32>, OVSAError> {
+        self.check(vec.len())?;
+        Ok(self.kept.iter().map(|&index| vec[index]).collect())
+    }
+
+    /// Projects every entry of an item memory, so that it can be queried with projected vectors.
+    /// # Arguments
+    /// * `memory` - The item memory.
+    /// # Returns
+    /// A new item memory of dimension `n_kept` with the same labels.
+    pub fn project_memory(&self, memory: &ItemMemory) -> Result<ItemMemory, OVSAError> {
+        self.check(memory.dimension())?;
+        let mut projected = ItemMemory::new(self.kept.len())?;
+        for label in memory.labels() {
+            if let Some(vector) = memory.get(label) {
+                projected.insert(label, self.project(vector)?)?;
+            }
+        }
+        Ok(projected)
+    }
+
+    /// Projects per-dimension counters, e.g. the training counts of a classifier.
+    /// # Arguments
+    /// * `counts` - One counter per dimension.
+    /// # Returns
+    /// The counters of the kept dimensions.
+    pub fn project_counts(&self, counts: &[u32]) -> Result<Vec<u32>, OVSAError> {
+        self.check(counts.len())?;
+        Ok(self.kept.iter().map(|&index| counts[index]).collect())
+    }
+
+    /// Returns the mask as per-dimension weights, 1 for the kept dimensions and 0 for the dropped ones. Weighted
+    /// similarities with these weights equal the similarities of the projected vectors.
+    pub fn weights(&self) -> Vec<f64> {
+        let mut weights = vec![0.0; self.dimension];
+        for &index in &self.kept {
+            weights[index] = 1.0;
+        }
+        weights
+    }
+
+    fn check(&self, dimension: usize) -> Result<(), OVSAError> {
+        if dimension != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        Ok(())
+    }
+}
+
+
+/// Builds per-dimension weights from weighted segments, e.g. to emphasize one field of a segmented encoding.
+/// # Arguments
+/// * `dimension` - The dimension of the weighted vectors.
+/// * `segments` - The (range of dimensions, weight) pairs; later segments override earlier ones where they overlap.
+/// * `default` - The weight of the dimensions outside every segment.
+/// # Returns
+/// One weight per dimension.
+pub fn segment_weights(dimension: usize, segments: &[(Range<usize>, f64)], default: f64) -> Result<Vec<f64>, OVSAError> {
+    let mut weights = vec![default; dimension];
+    for (range, weight) in segments {
+        if range.end > dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        weights[range.clone()].fill(*weight);
+    }
+    total_weight(&weights, dimension)?;
+    Ok(weights)
+}
+
+
+/// Builds per-dimension weights selecting a range of dimensions: 1 inside the range and 0 outside, so that weighted
+/// similarities compare the range only.
+/// # Arguments
+/// * `dimension` - The dimension of the weighted vectors.
+/// * `range` - The non-empty range of dimensions.
+pub(crate) fn range_weights(dimension: usize, range: Range<usize>) -> Result<Vec<f64>, OVSAError> {
+    check_range(dimension, &range)?;
+    let mut weights = vec![0.0; dimension];
+    weights[range].fill(1.0);
+    Ok(weights)
+}
+
+
+/// Checks that a range of dimensions is non-empty and lies within the dimension.
+pub(crate) fn check_range(dimension: usize, range: &Range<usize>) -> Result<(), OVSAError> {
+    if range.end > dimension {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    if range.is_empty() {
+        return Err(OVSAError::ZeroDimension);
+    }
+    Ok(())
+}
+
+
+/// Checks per-dimension weights and returns their sum.
+/// # Arguments
+/// * `weights` - The weights, finite and non-negative, at least one of them positive.
+/// * `dimension` - The dimension of the weighted vectors.
+pub(crate) fn total_weight(weights: &[f64], dimension: usize) -> Result<f64, OVSAError> {
+    if weights.len() != dimension {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+    if let Some(weight) = weights.iter().find(|weight| !(weight.is_finite() && **weight >= 0.0)) {
+        return Err(OVSAError::InvalidArgument(format!("weights must be finite and non-negative, got {}", weight)));
+    }
+
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return Err(OVSAError::InvalidArgument("at least one weight must be positive".to_string()));
+    }
+    Ok(total)
+}
@@ -0,0 +1,135 @@
+use std::sync::{Arc, RwLock};
+use sprs::CsVec;
+
+use crate::errors::OVSAError;
+use crate::hypervector::Hypervector;
+use crate::memory::{ItemMemory, Matches};
+
+
+/// An item memory that can be shared across threads, e.g. by the request handlers of a server.
+/// Readers take a snapshot of the memory (an `Arc` clone under a briefly held read lock) and run their query without
+/// holding any lock, so long cleanup queries never block writers and writers never block running queries.
+/// Writers edit the memory in place, or copy it first if a reader still holds the previous snapshot (copy-on-write).
+#[derive(Debug)]
+pub struct ConcurrentItemMemory<V: Hypervector = CsVec<i8>> {
+    current: RwLock<Arc<ItemMemory<V>>>,
+}
+
+
+impl<V: Hypervector> ConcurrentItemMemory<V> {
+    /// Creates an empty shared item memory for vectors of the given dimension.
+    /// # Arguments
+    /// * `dimension` - The dimension of the stored vectors.
+    /// # Returns
+    /// An empty `ConcurrentItemMemory`.
+    pub fn new(dimension: usize) -> Result<Self, OVSAError> {
+        Ok(Self::from_memory(ItemMemory::new(dimension)?))
+    }
+
+    /// Shares an existing item memory.
+    /// # Arguments
+    /// * `memory` - The item memory, e.g. loaded with `io::load_memory`.
+    pub fn from_memory(memory: ItemMemory<V>) -> Self {
+        ConcurrentItemMemory { current: RwLock::new(Arc::new(memory)) }
+    }
+
+    /// Returns the current state of the memory. The snapshot is not affected by later writes,
+    /// which makes it suitable for several reads that must see the same entries.
+    pub fn snapshot(&self) -> Arc<ItemMemory<V>> {
+        self.current.read().expect("Memory lock poisoned.").clone()
+    }
+
+    /// Applies a change to the memory while holding the write lock, so that the change and any side effect
+    /// (such as persisting the memory) are not interleaved with other writes.
+    /// # Arguments
+    /// * `f` - The change, receiving the memory mutably.
+    /// # Returns
+    /// The value returned by the change.
+    pub fn update<T>(&self, f: impl FnOnce(&mut ItemMemory<V>) -> T) -> T {
+        let mut current = self.current.write().expect("Memory lock poisoned.");
+        f(Arc::make_mut(&mut current))
+    }
+
+    /// Applies a fallible change to a copy of the memory while holding the write lock, and replaces the memory with
+    /// the copy only if the change succeeds. A change that fails part way, e.g. because persisting the changed memory
+    /// failed, therefore leaves the memory as it was.
+    /// # Arguments
+    /// * `f` - The change, receiving the copy mutably.
+    /// # Returns
+    /// The value returned by the change, or its error.
+    pub fn try_update<T, E>(&self, f: impl FnOnce(&mut ItemMemory<V>) -> Result<T, E>) -> Result<T, E> {
+        let mut current = self.current.write().expect("Memory lock poisoned.");
+        let mut updated = ItemMemory::clone(&current);
+        let value = f(&mut updated)?;
+        *current = Arc::new(updated);
+        Ok(value)
+    }
+
+    /// Returns the dimension of the stored vectors.
+    pub fn dimension(&self) -> usize {
+        self.snapshot().dimension()
+    }
+
+    /// Returns the number of stored entries.
+    pub fn len(&self) -> usize {
+        self.snapshot().len()
+    }
+
+    /// Returns true if the memory holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.snapshot().is_empty()
+    }
+
+    /// Returns a copy of the vector stored under the given label, if any.
+    /// # Arguments
+    /// * `label` - The label of the entry.
+    pub fn get(&self, label: &str) -> Option<V> {
+        self.snapshot().get(label).cloned()
+    }
+
+    /// Inserts a vector under the given label, replacing any vector previously stored under it.
+    /// # Arguments
+    /// * `label` - The label of the entry.
+    /// * `vector` - The vector to store.
+    pub fn insert(&self, label: &str, vector: V) -> Result<(), OVSAError> {
+        self.update(|memory| memory.insert(label, vector))
+    }
+
+    /// Finds the `k` stored entries most similar to the query vector, without blocking writers.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// * `k` - The maximum number of results.
+    /// # Returns
+    /// The (label, similarity) pairs sorted by decreasing similarity.
+    pub fn query(&self, query: &V, k: usize) -> Result<Matches, OVSAError> {
+        self.snapshot().query(query, k)
+    }
+
+    /// Finds the single stored entry most similar to the query vector, without blocking writers.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// # Returns
+    /// The (label, similarity) pair of the best match, or `None` if the memory is empty.
+    pub fn cleanup(&self, query: &V) -> Result<Option<(String, f64)>, OVSAError> {
+        self.snapshot().cleanup(query)
+    }
+}
+
+
+impl ConcurrentItemMemory<CsVec<i8>> {
+    /// Returns the vector stored under the given label, generating and inserting a random one if absent.
+    /// Existing symbols are read without taking the write lock.
+    /// # Arguments
+    /// * `label` - The label of the symbol.
+    /// * `n_active` - The number of active entries of a newly generated symbol.
+    /// # Returns
+    /// A copy of the symbol's sparse binary vector.
+    pub fn symbol(&self, label: &str, n_active: usize) -> Result<CsVec<i8>, OVSAError> {
+        if let Some(vector) = self.get(label) {
+            return Ok(vector);
+        }
+
+        // another thread may create the symbol first, in which case `symbol` returns the stored one
+        self.update(|memory| memory.symbol(label, n_active).cloned())
+    }
+}
@@ -0,0 +1,304 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use rand::{Rng, SeedableRng};
+use sprs::CsVec;
+
+use crate::errors::OVSAError;
+use crate::hypervector::Hypervector;
+use crate::memory::{ItemMemory, Matches, top_k};
+use crate::rng::OvsaRng;
+use crate::trace::span;
+
+
+/// The settings of a hierarchical navigable small world (HNSW) graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HnswParams {
+    /// The number of links a new entry makes per layer; layer 0 keeps up to twice as many.
+    pub m: usize,
+    /// The number of candidates considered when linking a new entry; larger values build a better graph more slowly.
+    pub ef_construction: usize,
+    /// The number of candidates considered by a query; larger values trade speed for recall.
+    pub ef_search: usize,
+    /// The seed drawing the layers of the entries, which makes the graph reproducible.
+    pub seed: u64,
+}
+
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        HnswParams { m: 16, ef_construction: 100, ef_search: 64, seed: 0 }
+    }
+}
+
+
+/// A visited entry of the graph together with its distance (one minus similarity) to the query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f64,
+    position: usize,
+}
+
+
+impl Eq for Candidate {}
+
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.position.cmp(&other.position))
+    }
+}
+
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+
+/// An item memory indexed by an HNSW graph, so that queries visit a small fraction of the entries instead of scanning
+/// them all. Results are approximate: the true nearest entries are found with high probability, which `ef_search`
+/// controls. Every insertion links the new entry into the graph, so the index never needs to be rebuilt.
+#[derive(Debug, Clone)]
+pub struct HnswItemMemory<V: Hypervector = CsVec<i8>> {
+    memory: ItemMemory<V>,
+    params: HnswParams,
+    // links[position][layer] lists the neighbours of an entry on one layer of the graph
+    links: Vec<Vec<Vec<usize>>>,
+    entry: Option<usize>,
+    rng: OvsaRng,
+}
+
+
+impl<V: Hypervector> HnswItemMemory<V> {
+    /// Creates an empty indexed item memory.
+    /// # Arguments
+    /// * `dimension` - The dimension of the stored vectors.
+    /// * `params` - The settings of the graph.
+    /// # Returns
+    /// An empty `HnswItemMemory`.
+    pub fn new(dimension: usize, params: HnswParams) -> Result<Self, OVSAError> {
+        Self::from_memory(ItemMemory::new(dimension)?, params)
+    }
+
+    /// Indexes an existing item memory, linking its entries in insertion order.
+    /// # Arguments
+    /// * `memory` - The item memory.
+    /// * `params` - The settings of the graph.
+    /// # Returns
+    /// The indexed memory.
+    pub fn from_memory(memory: ItemMemory<V>, params: HnswParams) -> Result<Self, OVSAError> {
+        if params.m < 2 || params.ef_construction == 0 || params.ef_search == 0 {
+            return Err(OVSAError::InvalidArgument("HNSW needs m >= 2 and positive ef values".to_string()));
+        }
+
+        let n_entries = memory.len();
+        let mut indexed = HnswItemMemory { memory, params, links: Vec::with_capacity(n_entries), entry: None, rng: OvsaRng::seed_from_u64(params.seed) };
+        for position in 0..n_entries {
+            indexed.link(position)?;
+        }
+
+        Ok(indexed)
+    }
+
+    /// Returns the underlying item memory.
+    pub fn memory(&self) -> &ItemMemory<V> {
+        &self.memory
+    }
+
+    /// Returns the settings of the graph.
+    pub fn params(&self) -> HnswParams {
+        self.params
+    }
+
+    /// Changes the number of candidates considered by queries.
+    /// # Arguments
+    /// * `ef_search` - The new value, at least 1.
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.params.ef_search = ef_search.max(1);
+    }
+
+    /// Returns the number of stored entries.
+    pub fn len(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Returns true if the memory holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_empty()
+    }
+
+    /// Inserts a vector under the given label and links it into the graph.
+    /// Replacing the vector of a label relinks its entry to the neighbours of the new vector.
+    /// # Arguments
+    /// * `label` - The label of the entry.
+    /// * `vector` - The vector to store.
+    pub fn insert(&mut self, label: &str, vector: V) -> Result<(), OVSAError> {
+        let existing = self.memory.own_position(label);
+        self.memory.insert(label, vector)?;
+
+        match existing {
+            Some(position) => {
+                let level = self.links[position].len() - 1;
+                self.connect(position, level)
+            }
+            None => self.link(self.memory.len() - 1),
+        }
+    }
+
+    /// Finds (approximately) the `k` stored entries most similar to the query vector.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// * `k` - The maximum number of results.
+    /// # Returns
+    /// The (label, similarity) pairs sorted by decreasing similarity.
+    pub fn query(&self, query: &V, k: usize) -> Result<Matches, OVSAError> {
+        if query.dimension() != self.memory.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        let Some(entry) = self.entry else {
+            return Ok(Vec::new());
+        };
+        span!(DEBUG, "hnsw_query", n_entries = self.memory.len(), k, ef_search = self.params.ef_search);
+
+        let mut nearest = Candidate { distance: self.distance(query, entry)?, position: entry };
+        for layer in (1..self.links[entry].len()).rev() {
+            nearest = self.greedy(query, nearest, layer)?;
+        }
+        let found = self.search_layer(query, vec![nearest], self.params.ef_search.max(k), 0)?;
+
+        let results = found.into_iter().map(|candidate| (self.memory.labels[candidate.position].clone(), 1f64 - candidate.distance)).collect();
+        Ok(top_k(results, k))
+    }
+
+    /// Finds (approximately) the single stored entry most similar to the query vector.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// # Returns
+    /// The (label, similarity) pair of the best match, or `None` if the memory is empty.
+    pub fn cleanup(&self, query: &V) -> Result<Option<(String, f64)>, OVSAError> {
+        Ok(self.query(query, 1)?.into_iter().next())
+    }
+
+    /// Adds the entry at a position to the graph on a randomly drawn number of layers.
+    fn link(&mut self, position: usize) -> Result<(), OVSAError> {
+        // layers are geometrically distributed so that every layer holds about 1 / m of the entries of the one below
+        let uniform: f64 = 1f64 - self.rng.random::<f64>();
+        let level = (-uniform.ln() / (self.params.m as f64).ln()) as usize;
+        self.links.push(vec![Vec::new(); level + 1]);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(position);
+            return Ok(());
+        };
+
+        self.connect(position, level)?;
+        if level >= self.links[entry].len() {
+            self.entry = Some(position);
+        }
+
+        Ok(())
+    }
+
+    /// Links an entry to its nearest neighbours on every layer up to `level`, replacing its previous links.
+    fn connect(&mut self, position: usize, level: usize) -> Result<(), OVSAError> {
+        let entry = self.entry.unwrap();
+        let vector = self.memory.vectors[position].clone();
+        let top = self.links[entry].len() - 1;
+
+        let mut nearest = Candidate { distance: self.distance(&vector, entry)?, position: entry };
+        for layer in (level + 1..=top).rev() {
+            nearest = self.greedy(&vector, nearest, layer)?;
+        }
+
+        let mut entry_points = vec![nearest];
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&vector, entry_points, self.params.ef_construction, layer)?;
+            let selected: Vec<usize> = found.iter().map(|candidate| candidate.position).filter(|&neighbour| neighbour != position).take(self.params.m).collect();
+            for &neighbour in &selected {
+                self.add_link(neighbour, position, layer)?;
+            }
+            self.links[position][layer] = selected;
+            entry_points = found;
+        }
+
+        Ok(())
+    }
+
+    /// Links `from` to `to` on a layer, dropping the furthest link of `from` if it has too many.
+    fn add_link(&mut self, from: usize, to: usize, layer: usize) -> Result<(), OVSAError> {
+        if self.links[from][layer].contains(&to) {
+            return Ok(());
+        }
+        self.links[from][layer].push(to);
+
+        let max_links = if layer == 0 { 2 * self.params.m } else { self.params.m };
+        if self.links[from][layer].len() > max_links {
+            let vector = self.memory.vectors[from].clone();
+            let mut neighbours = self.links[from][layer].iter()
+                .map(|&neighbour| Ok(Candidate { distance: self.distance(&vector, neighbour)?, position: neighbour }))
+                .collect::<Result<Vec<Candidate>, OVSAError>>()?;
+            neighbours.sort();
+            neighbours.truncate(max_links);
+            self.links[from][layer] = neighbours.into_iter().map(|candidate| candidate.position).collect();
+        }
+
+        Ok(())
+    }
+
+    /// Moves to closer neighbours on a layer until none is closer to the query.
+    fn greedy(&self, query: &V, mut nearest: Candidate, layer: usize) -> Result<Candidate, OVSAError> {
+        loop {
+            let mut improved = false;
+            for &neighbour in &self.links[nearest.position][layer] {
+                let distance = self.distance(query, neighbour)?;
+                if distance < nearest.distance {
+                    nearest = Candidate { distance, position: neighbour };
+                    improved = true;
+                }
+            }
+            if !improved {
+                return Ok(nearest);
+            }
+        }
+    }
+
+    /// Finds the `ef` entries of a layer nearest to the query by best-first search from the entry points.
+    /// # Returns
+    /// The candidates sorted by increasing distance.
+    fn search_layer(&self, query: &V, entry_points: Vec<Candidate>, ef: usize, layer: usize) -> Result<Vec<Candidate>, OVSAError> {
+        let mut visited: HashSet<usize> = entry_points.iter().map(|candidate| candidate.position).collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate>> = entry_points.iter().copied().map(Reverse).collect();
+        let mut found: BinaryHeap<Candidate> = entry_points.into_iter().collect();
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(Reverse(nearest)) = candidates.pop() {
+            if found.len() >= ef && nearest.distance > found.peek().unwrap().distance {
+                break;
+            }
+            for &neighbour in &self.links[nearest.position][layer] {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let distance = self.distance(query, neighbour)?;
+                if found.len() < ef || distance < found.peek().unwrap().distance {
+                    let candidate = Candidate { distance, position: neighbour };
+                    candidates.push(Reverse(candidate));
+                    found.push(candidate);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        Ok(found.into_sorted_vec())
+    }
+
+    /// Returns one minus the similarity of a vector to a stored entry.
+    fn distance(&self, vector: &V, position: usize) -> Result<f64, OVSAError> {
+        Ok(1f64 - vector.similarity(&self.memory.vectors[position])?)
+    }
+}
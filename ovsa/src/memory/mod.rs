@@ -0,0 +1,831 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+#[cfg(feature = "dense")]
+use ndarray::{Array1, ArrayView1};
+use rand::Rng;
+use sprs::{CsVec, CsVecView};
+
+use crate::errors::OVSAError;
+use crate::hypervector::Hypervector;
+use crate::rng::{stream_rng, with_global_rng};
+use crate::trace::span;
+
+pub mod concurrent;
+
+#[cfg(feature = "hnsw")]
+pub mod hnsw;
+
+pub mod sharded;
+
+
+/// The (label, similarity) pairs returned by a cleanup query, sorted by decreasing similarity.
+pub type Matches = Vec<(String, f64)>;
+
+
+/// A breakdown of the bytes used by an item memory or a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryUsage {
+    /// The number of stored vectors.
+    pub n_vectors: usize,
+    /// The bytes of the stored vectors, inline and on the heap.
+    pub vector_bytes: usize,
+    /// The bytes of the label strings, counting every copy kept for lookups.
+    pub label_bytes: usize,
+    /// The bytes of auxiliary state such as lookup tables or training counters.
+    pub auxiliary_bytes: usize,
+}
+
+
+impl MemoryUsage {
+    /// Returns the total number of bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.vector_bytes + self.label_bytes + self.auxiliary_bytes
+    }
+
+    /// Returns the average number of bytes per stored vector, or 0 if there are none.
+    pub fn bytes_per_vector(&self) -> usize {
+        self.vector_bytes.checked_div(self.n_vectors).unwrap_or(0)
+    }
+}
+
+
+/// How `ItemMemory::insert_checked` handles a new entry that is nearly identical to a stored one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Stores the entry anyway and only reports the collisions.
+    Allow,
+    /// Leaves the memory unchanged.
+    Reject,
+    /// Makes the new label an alias of the most similar stored entry instead of storing the vector.
+    Merge,
+}
+
+
+/// What `ItemMemory::insert_checked` did with the new entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InsertOutcome {
+    /// The vector was stored under its label.
+    Inserted,
+    /// The vector was not stored because it collided with a stored entry.
+    Rejected,
+    /// The label now resolves to the stored entry with the given label.
+    Merged(String),
+}
+
+
+/// The result of a checked insertion together with the stored entries it collided with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertReport {
+    /// What happened to the new entry.
+    pub outcome: InsertOutcome,
+    /// The (label, similarity) pairs of the stored entries above the threshold, sorted by decreasing similarity.
+    pub collisions: Matches,
+}
+
+
+/// How `ItemMemory::merge` resolves a label stored with different vectors in both memories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keeps the vector of the memory being merged into.
+    KeepOurs,
+    /// Takes the vector of the merged memory.
+    TakeTheirs,
+}
+
+
+/// What `ItemMemory::recognize_or_insert` did with a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recognition {
+    /// The query matched a stored entry.
+    Known { label: String, similarity: f64 },
+    /// The query matched no stored entry and was stored under a generated label. `closest` is the best match it
+    /// had before, if the memory was not empty.
+    Novel { label: String, closest: Option<(String, f64)> },
+}
+
+
+/// The labels that differ between two versions of an item memory, see `ItemMemory::diff`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryDiff {
+    /// Labels only stored in the newer version, in its insertion order.
+    pub added: Vec<String>,
+    /// Labels only stored in the older version, in its insertion order.
+    pub removed: Vec<String>,
+    /// Labels stored in both versions with a vector that was replaced, in the newer version's insertion order.
+    pub changed: Vec<String>,
+}
+
+
+impl MemoryDiff {
+    /// Returns true if the two versions hold the same entries.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+
+/// A labelled collection of hypervectors supporting cleanup queries.
+/// Queries return the stored entries most similar to a (possibly noisy) query vector.
+#[derive(Debug, Clone)]
+pub struct ItemMemory<V: Hypervector = CsVec<i8>> {
+    dimension: usize,
+    labels: Vec<String>,
+    vectors: Vec<Arc<V>>,
+    positions: HashMap<String, usize>,
+}
+
+
+impl<V: Hypervector> ItemMemory<V> {
+    /// Creates an empty item memory for vectors of the given dimension.
+    /// # Arguments
+    /// * `dimension` - The dimension of the stored vectors.
+    /// # Returns
+    /// An empty `ItemMemory`.
+    pub fn new(dimension: usize) -> Result<Self, OVSAError> {
+        if dimension == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+
+        Ok(ItemMemory { dimension, labels: Vec::new(), vectors: Vec::new(), positions: HashMap::new() })
+    }
+
+    /// Returns the dimension of the stored vectors.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the number of stored entries.
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Returns true if the memory holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Returns the labels of the stored entries in insertion order.
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// Inserts a vector under the given label, replacing any vector previously stored under it.
+    /// # Arguments
+    /// * `label` - The label of the entry.
+    /// * `vector` - The vector to store.
+    pub fn insert(&mut self, label: &str, vector: V) -> Result<(), OVSAError> {
+        if vector.dimension() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        // a merged label only aliases another entry, so storing a vector under it creates its own entry
+        match self.own_position(label) {
+            Some(position) => self.vectors[position] = Arc::new(vector),
+            None => {
+                self.positions.insert(label.to_string(), self.vectors.len());
+                self.labels.push(label.to_string());
+                self.vectors.push(Arc::new(vector));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the entry stored under a label together with the labels merged into it, or only the label if it
+    /// aliases another entry. The remaining entries keep their order.
+    /// # Arguments
+    /// * `label` - The label to remove.
+    /// # Returns
+    /// True if the label was stored.
+    pub fn remove(&mut self, label: &str) -> bool {
+        let Some(position) = self.positions.get(label).copied() else {
+            return false;
+        };
+        if self.labels[position] != label {
+            self.positions.remove(label);
+            return true;
+        }
+
+        self.labels.remove(position);
+        self.vectors.remove(position);
+        self.positions.retain(|_, stored| *stored != position);
+        for stored in self.positions.values_mut().filter(|stored| **stored > position) {
+            *stored -= 1;
+        }
+        true
+    }
+
+    /// Reserves capacity for at least `additional` more entries, avoiding reallocations during bulk insertion.
+    /// # Arguments
+    /// * `additional` - The number of entries about to be inserted.
+    pub fn reserve(&mut self, additional: usize) {
+        self.labels.reserve(additional);
+        self.vectors.reserve(additional);
+        self.positions.reserve(additional);
+    }
+
+    /// Inserts many (label, vector) pairs, reserving storage up front from the iterator's size hint.
+    /// Labels already stored have their vector replaced, as with `insert`.
+    /// # Arguments
+    /// * `entries` - The (label, vector) pairs.
+    /// # Returns
+    /// `VectorSizeMismatch` at the first vector of the wrong dimension; the entries before it stay inserted.
+    pub fn extend_from_iter<L: AsRef<str>>(&mut self, entries: impl IntoIterator<Item = (L, V)>) -> Result<(), OVSAError> {
+        let entries = entries.into_iter();
+        self.reserve(entries.size_hint().0);
+        for (label, vector) in entries {
+            self.insert(label.as_ref(), vector)?;
+        }
+
+        Ok(())
+    }
+
+    /// Inserts a vector unless it is a near-duplicate of a stored entry, whose presence would make cleanup ambiguous.
+    /// An entry already stored under the same label is not counted as a collision, so replacing a vector is always possible.
+    /// Merged labels resolve through `get` but are not listed by `labels` or `iter`, and are therefore not saved by `io::save_memory`.
+    /// # Arguments
+    /// * `label` - The label of the entry.
+    /// * `vector` - The vector to store.
+    /// * `threshold` - The similarity above which a stored entry counts as a collision.
+    /// * `policy` - What to do when there is at least one collision.
+    /// # Returns
+    /// An `InsertReport` with the outcome and the collisions.
+    pub fn insert_checked(&mut self, label: &str, vector: V, threshold: f64, policy: DuplicatePolicy) -> Result<InsertReport, OVSAError> {
+        if vector.dimension() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let own_position = self.own_position(label);
+        let mut collisions = Vec::new();
+        for (position, stored) in self.vectors.iter().enumerate() {
+            if Some(position) == own_position {
+                continue;
+            }
+            let similarity = vector.similarity(stored)?;
+            if similarity > threshold {
+                collisions.push((self.labels[position].clone(), similarity));
+            }
+        }
+        let collisions = top_k(collisions, usize::MAX);
+
+        let outcome = match (collisions.first(), policy) {
+            (None, _) | (Some(_), DuplicatePolicy::Allow) => {
+                self.insert(label, vector)?;
+                InsertOutcome::Inserted
+            }
+            (Some(_), DuplicatePolicy::Reject) => InsertOutcome::Rejected,
+            (Some((target, _)), DuplicatePolicy::Merge) => {
+                let position = self.positions[target];
+                match own_position {
+                    // the label names a stored entry, so it cannot become an alias without orphaning that entry
+                    Some(_) => InsertOutcome::Rejected,
+                    None => {
+                        self.positions.insert(label.to_string(), position);
+                        InsertOutcome::Merged(target.clone())
+                    }
+                }
+            }
+        };
+
+        Ok(InsertReport { outcome, collisions })
+    }
+
+    /// Returns the vector stored under the given label, if any.
+    /// # Arguments
+    /// * `label` - The label of the entry.
+    pub fn get(&self, label: &str) -> Option<&V> {
+        self.positions.get(label).map(|&position| self.vectors[position].as_ref())
+    }
+
+    /// Returns an iterator over the stored (label, vector) pairs in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &V)> {
+        self.labels.iter().map(|label| label.as_str()).zip(self.vectors.iter().map(|vector| vector.as_ref()))
+    }
+
+    /// Branches the memory. The snapshot shares every vector with the original instead of copying it,
+    /// so branching a large codebook only copies its labels; later insertions into either version do not affect the other.
+    /// # Returns
+    /// An independent `ItemMemory` with the same entries.
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    /// Lists the entries added, removed or replaced in this memory relative to an older version.
+    /// Vectors are compared by identity, so the diff is meaningful for versions branched with `snapshot`:
+    /// an entry counts as changed when it was inserted again in either version after branching.
+    /// # Arguments
+    /// * `base` - The older version.
+    /// # Returns
+    /// The `MemoryDiff` from `base` to this memory.
+    pub fn diff(&self, base: &ItemMemory<V>) -> MemoryDiff {
+        let mut diff = MemoryDiff::default();
+        for (position, label) in self.labels.iter().enumerate() {
+            match base.own_position(label) {
+                None => diff.added.push(label.clone()),
+                Some(base_position) if !Arc::ptr_eq(&self.vectors[position], &base.vectors[base_position]) => diff.changed.push(label.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = base.labels.iter().filter(|label| self.own_position(label).is_none()).cloned().collect();
+
+        diff
+    }
+
+    /// Merges the entries of another version into this memory. Labels missing here are added (sharing their vectors),
+    /// and labels stored here with a different vector are resolved by the policy.
+    /// # Arguments
+    /// * `other` - The version to merge.
+    /// * `policy` - How to resolve conflicting labels.
+    /// # Returns
+    /// The conflicting labels, in the other version's insertion order.
+    pub fn merge(&mut self, other: &ItemMemory<V>, policy: MergePolicy) -> Result<Vec<String>, OVSAError> {
+        if other.dimension != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let mut conflicts = Vec::new();
+        for (label, vector) in other.labels.iter().zip(&other.vectors) {
+            match self.own_position(label) {
+                None => {
+                    self.positions.insert(label.clone(), self.vectors.len());
+                    self.labels.push(label.clone());
+                    self.vectors.push(Arc::clone(vector));
+                }
+                Some(position) if !Arc::ptr_eq(&self.vectors[position], vector) => {
+                    conflicts.push(label.clone());
+                    if policy == MergePolicy::TakeTheirs {
+                        self.vectors[position] = Arc::clone(vector);
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(conflicts)
+    }
+
+    /// Returns the position of the entry stored under the label itself, ignoring labels merged into another entry.
+    fn own_position(&self, label: &str) -> Option<usize> {
+        self.positions.get(label).copied().filter(|&position| self.labels[position] == label)
+    }
+
+    /// Estimates the bytes used by the memory. Allocator overhead and unused capacity are not included.
+    /// Vectors shared with snapshots are counted in full by every memory holding them.
+    /// # Returns
+    /// The `MemoryUsage` breakdown.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let label_heap: usize = self.labels.iter().map(|label| label.len()).sum();
+        // a label is stored both in the ordered list and as a key of the position map
+        let label_bytes = 2 * (label_heap + self.labels.len() * size_of::<String>());
+        // every vector sits behind a shared pointer with a strong and a weak reference count
+        let auxiliary_bytes = self.positions.len() * size_of::<usize>() + self.vectors.len() * (size_of::<Arc<V>>() + 2 * size_of::<usize>());
+        let vector_bytes = self.vectors.iter().map(|vector| size_of::<V>() + vector.heap_bytes()).sum();
+
+        MemoryUsage { n_vectors: self.vectors.len(), vector_bytes, label_bytes, auxiliary_bytes }
+    }
+
+    /// Finds the `k` stored entries most similar to the query vector.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// * `k` - The maximum number of results.
+    /// # Returns
+    /// The (label, similarity) pairs sorted by decreasing similarity.
+    pub fn query(&self, query: &V, k: usize) -> Result<Vec<(String, f64)>, OVSAError> {
+        if query.dimension() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        span!(DEBUG, "query", n_entries = self.vectors.len(), k);
+
+        let scores = self.vectors.iter().map(|vector| query.similarity(vector)).collect::<Result<Vec<f64>, _>>()?;
+
+        Ok(self.rank(&scores, k))
+    }
+
+    /// Finds the `k` stored entries most similar to the query vector by weighted similarity, e.g. to emphasize the
+    /// dimensions that best tell classes apart or one segment of the vectors.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// * `k` - The maximum number of results.
+    /// * `weights` - One finite, non-negative weight per dimension.
+    /// # Returns
+    /// The (label, weighted similarity) pairs sorted by decreasing similarity.
+    pub fn query_weighted(&self, query: &V, k: usize, weights: &[f64]) -> Result<Matches, OVSAError> {
+        if query.dimension() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        span!(DEBUG, "query_weighted", n_entries = self.vectors.len(), k);
+
+        let entries: Vec<&V> = self.vectors.iter().map(|entry| entry.as_ref()).collect();
+        let scores = V::weighted_similarities(std::slice::from_ref(query), &entries, weights)?.pop().unwrap_or_default();
+
+        Ok(self.rank(&scores, k))
+    }
+
+    /// Finds the `k` stored entries most similar to the query vector over a range of dimensions, e.g. to look up
+    /// structured vectors by one of their fields.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// * `k` - The maximum number of results.
+    /// * `range` - The non-empty range of dimensions to compare.
+    /// # Returns
+    /// The (label, similarity within the range) pairs sorted by decreasing similarity.
+    pub fn query_range(&self, query: &V, k: usize, range: Range<usize>) -> Result<Matches, OVSAError> {
+        if query.dimension() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        span!(DEBUG, "query_range", n_entries = self.vectors.len(), k);
+
+        let entries: Vec<&V> = self.vectors.iter().map(|entry| entry.as_ref()).collect();
+        let scores = V::similarities_range(std::slice::from_ref(query), &entries, range)?.pop().unwrap_or_default();
+
+        Ok(self.rank(&scores, k))
+    }
+
+    /// Finds the `k` stored entries most similar to each of many query vectors. The similarities are computed in tiles
+    /// of queries and entries, so that a tile of entries is compared to many queries while it is in cache, and tiles of
+    /// queries are processed in parallel when the `parallel` feature is enabled.
+    /// # Arguments
+    /// * `queries` - The query vectors.
+    /// * `k` - The maximum number of results per query.
+    /// # Returns
+    /// The (label, similarity) pairs of every query, in query order, each ranked like `query`. Dense similarities are
+    /// computed on normalized vectors, so they may differ from those of `query` by rounding.
+    pub fn query_batch(&self, queries: &[V], k: usize) -> Result<Vec<Matches>, OVSAError> {
+        if queries.iter().any(|query| query.dimension() != self.dimension) {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        span!(DEBUG, "query_batch", n_queries = queries.len(), n_entries = self.vectors.len(), k);
+
+        #[cfg(feature = "parallel")]
+        let tiles: Vec<Vec<Matches>> = {
+            use rayon::prelude::*;
+            crate::config::install(|| queries.par_chunks(QUERY_TILE).map(|tile| self.query_tile(tile, k)).collect::<Result<_, _>>())?
+        };
+        #[cfg(not(feature = "parallel"))]
+        let tiles: Vec<Vec<Matches>> = queries.chunks(QUERY_TILE).map(|tile| self.query_tile(tile, k)).collect::<Result<_, _>>()?;
+
+        Ok(tiles.into_iter().flatten().collect())
+    }
+
+    /// Ranks the entries for a tile of queries, keeping only the `k` best candidates of every query between tiles of
+    /// entries.
+    fn query_tile(&self, queries: &[V], k: usize) -> Result<Vec<Matches>, OVSAError> {
+        let order = |a: &(usize, f64), b: &(usize, f64)| b.1.total_cmp(&a.1).then_with(|| self.labels[a.0].cmp(&self.labels[b.0]));
+        let mut best: Vec<Vec<(usize, f64)>> = vec![Vec::with_capacity(k.min(self.vectors.len()) + ENTRY_TILE); queries.len()];
+
+        for start in (0..self.vectors.len()).step_by(ENTRY_TILE) {
+            let entries: Vec<&V> = self.vectors[start..(start + ENTRY_TILE).min(self.vectors.len())].iter().map(|entry| entry.as_ref()).collect();
+            let block = V::similarities(queries, &entries)?;
+            for (row, candidates) in block.into_iter().zip(&mut best) {
+                candidates.extend(row.into_iter().enumerate().map(|(offset, similarity)| (start + offset, similarity)));
+                candidates.sort_by(order);
+                candidates.truncate(k);
+            }
+        }
+
+        Ok(best.into_iter()
+            .map(|candidates| candidates.into_iter().map(|(position, similarity)| (self.labels[position].clone(), similarity)).collect())
+            .collect())
+    }
+
+    /// Finds the stored entry most similar to the query vector and returns it by reference, avoiding any copy.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// # Returns
+    /// The label, stored vector and similarity of the best match, or `None` if the memory is empty.
+    pub fn cleanup_ref(&self, query: &V) -> Result<Option<(&str, &V, f64)>, OVSAError> {
+        if query.dimension() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        span!(DEBUG, "cleanup", n_entries = self.vectors.len());
+
+        let mut best: Option<(usize, f64)> = None;
+        for (position, vector) in self.vectors.iter().enumerate() {
+            // candidates below the best similarity so far cannot win, so their comparison may stop early
+            let minimum = best.map_or(f64::NEG_INFINITY, |(_, similarity)| similarity);
+            let Some(similarity) = query.similarity_at_least(vector, minimum)? else {
+                continue;
+            };
+            let better = match best {
+                None => true,
+                Some((best_position, best_similarity)) => similarity > best_similarity
+                    || (similarity == best_similarity && self.labels[position] < self.labels[best_position]),
+            };
+            if better {
+                best = Some((position, similarity));
+            }
+        }
+
+        Ok(best.map(|(position, similarity)| (self.labels[position].as_str(), self.vectors[position].as_ref(), similarity)))
+    }
+
+    /// Ranks the entries by their score, cloning only the labels of the `k` best entries.
+    fn rank(&self, scores: &[f64], k: usize) -> Matches {
+        self.rank_positions((0..scores.len()).collect(), scores, k)
+    }
+
+    /// Ranks a subset of the entries by their score, indexed by position.
+    fn rank_positions(&self, mut positions: Vec<usize>, scores: &[f64], k: usize) -> Matches {
+        positions.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then_with(|| self.labels[a].cmp(&self.labels[b])));
+        positions.truncate(k);
+
+        positions.into_iter().map(|position| (self.labels[position].clone(), scores[position])).collect()
+    }
+
+    /// Finds the single stored entry most similar to the query vector.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// # Returns
+    /// The (label, similarity) pair of the best match, or `None` if the memory is empty.
+    pub fn cleanup(&self, query: &V) -> Result<Option<(String, f64)>, OVSAError> {
+        Ok(self.cleanup_ref(query)?.map(|(label, _, similarity)| (label.to_string(), similarity)))
+    }
+
+    /// Recognizes a query or, if it is novel, stores it as a new entry, so that the memory grows with the concepts it
+    /// encounters, e.g. for open-set recognition or an online vocabulary. New labels are the prefix followed by a
+    /// number, starting at the current number of entries and skipping labels in use.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// * `threshold` - The similarity a stored entry must reach for the query to be known.
+    /// * `prefix` - The prefix of generated labels, e.g. `unknown`.
+    /// # Returns
+    /// The `Recognition`: the matched entry, or the label the query was stored under.
+    pub fn recognize_or_insert(&mut self, query: &V, threshold: f64, prefix: &str) -> Result<Recognition, OVSAError>
+    where
+        V: Clone,
+    {
+        let closest = self.cleanup(query)?;
+        if let Some((label, similarity)) = &closest && *similarity >= threshold {
+            return Ok(Recognition::Known { label: label.clone(), similarity: *similarity });
+        }
+
+        let mut number = self.len();
+        while self.positions.contains_key(&format!("{}{}", prefix, number)) {
+            number += 1;
+        }
+        let label = format!("{}{}", prefix, number);
+        self.insert(&label, query.clone())?;
+        Ok(Recognition::Novel { label, closest })
+    }
+
+    /// Returns the labels stored in a namespace, i.e. starting with the namespace followed by `/`, in insertion order.
+    /// Namespaces nest, so `colors` also contains `colors/warm/red`.
+    /// # Arguments
+    /// * `namespace` - The namespace, e.g. `colors`.
+    pub fn labels_in<'a>(&'a self, namespace: &'a str) -> impl Iterator<Item = &'a str> {
+        self.labels.iter().map(|label| label.as_str()).filter(move |label| in_namespace(label, namespace))
+    }
+
+    /// Finds the `k` entries of a namespace most similar to the query vector, ignoring the rest of the memory.
+    /// This lets several encoders share one memory without their symbols competing during cleanup.
+    /// # Arguments
+    /// * `namespace` - The namespace, e.g. `colors`.
+    /// * `query` - The query vector.
+    /// * `k` - The maximum number of results.
+    /// # Returns
+    /// The (full label, similarity) pairs sorted by decreasing similarity.
+    pub fn query_in(&self, namespace: &str, query: &V, k: usize) -> Result<Matches, OVSAError> {
+        if query.dimension() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let positions: Vec<usize> = (0..self.labels.len()).filter(|&position| in_namespace(&self.labels[position], namespace)).collect();
+        let mut scores = vec![0f64; self.vectors.len()];
+        for &position in &positions {
+            scores[position] = query.similarity(&self.vectors[position])?;
+        }
+
+        Ok(self.rank_positions(positions, &scores, k))
+    }
+
+    /// Finds the single entry of a namespace most similar to the query vector.
+    /// # Arguments
+    /// * `namespace` - The namespace, e.g. `colors`.
+    /// * `query` - The query vector.
+    /// # Returns
+    /// The (full label, similarity) pair of the best match, or `None` if the namespace is empty.
+    pub fn cleanup_in(&self, namespace: &str, query: &V) -> Result<Option<(String, f64)>, OVSAError> {
+        Ok(self.query_in(namespace, query, 1)?.into_iter().next())
+    }
+
+    /// Extracts a namespace as a memory of its own, with the namespace prefix removed from the labels.
+    /// The vectors are shared with this memory, as for `snapshot`.
+    /// # Arguments
+    /// * `namespace` - The namespace, e.g. `colors`.
+    /// # Returns
+    /// An `ItemMemory` holding the entries of the namespace.
+    pub fn namespace(&self, namespace: &str) -> ItemMemory<V> {
+        let mut scoped = ItemMemory { dimension: self.dimension, labels: Vec::new(), vectors: Vec::new(), positions: HashMap::new() };
+        for (label, vector) in self.labels.iter().zip(&self.vectors) {
+            if in_namespace(label, namespace) {
+                let name = &label[namespace.len() + 1..];
+                scoped.positions.insert(name.to_string(), scoped.vectors.len());
+                scoped.labels.push(name.to_string());
+                scoped.vectors.push(Arc::clone(vector));
+            }
+        }
+
+        scoped
+    }
+}
+
+
+/// Joins a namespace and a name into a scoped label, e.g. `colors/red`.
+/// # Arguments
+/// * `namespace` - The namespace.
+/// * `name` - The name within the namespace.
+pub fn scoped(namespace: &str, name: &str) -> String {
+    format!("{}/{}", namespace, name)
+}
+
+
+/// Returns true if the label lies in the namespace.
+fn in_namespace(label: &str, namespace: &str) -> bool {
+    label.strip_prefix(namespace).is_some_and(|rest| rest.starts_with('/'))
+}
+
+
+impl ItemMemory<CsVec<i8>> {
+    /// Creates a codebook holding a random symbol for every label, drawn from the global generator in label order.
+    /// Repeated labels get a single symbol.
+    /// # Arguments
+    /// * `dimension` - The size of the symbols.
+    /// * `labels` - The labels of the symbols.
+    /// * `n_active` - The number of active entries of every symbol.
+    /// # Returns
+    /// The new `ItemMemory`.
+    pub fn build_from_labels(dimension: usize, labels: &[&str], n_active: usize) -> Result<Self, OVSAError> {
+        with_global_rng(|rng| Self::build_from_labels_with_rng(dimension, labels, n_active, rng))
+    }
+
+    /// Creates a codebook of random symbols drawn from a single stream of the provided random number generator.
+    /// # Arguments
+    /// * `dimension` - The size of the symbols.
+    /// * `labels` - The labels of the symbols.
+    /// * `n_active` - The number of active entries of every symbol.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// The new `ItemMemory`.
+    pub fn build_from_labels_with_rng<R: Rng + ?Sized>(dimension: usize, labels: &[&str], n_active: usize, rng: &mut R) -> Result<Self, OVSAError> {
+        let mut memory = ItemMemory::new(dimension)?;
+        memory.reserve(labels.len());
+        for label in labels {
+            memory.symbol_with_rng(label, n_active, rng)?;
+        }
+
+        Ok(memory)
+    }
+
+    /// Creates a codebook of random symbols, drawing symbol `i` from the stream `i` of the seed (see `rng::stream_rng`).
+    /// The result is bit-identical to `par_build_from_labels` with the same seed.
+    /// # Arguments
+    /// * `dimension` - The size of the symbols.
+    /// * `labels` - The labels of the symbols.
+    /// * `n_active` - The number of active entries of every symbol.
+    /// * `seed` - The seed of the symbols.
+    /// # Returns
+    /// The new `ItemMemory`; a repeated label keeps the symbol of its first occurrence.
+    pub fn build_from_labels_seeded(dimension: usize, labels: &[&str], n_active: usize, seed: u64) -> Result<Self, OVSAError> {
+        let vectors = (0..labels.len())
+            .map(|i| crate::binary::sparse_random_with_rng(dimension, n_active, &mut stream_rng(seed, i as u64)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::from_symbols(dimension, labels, vectors)
+    }
+
+    /// Creates a codebook of random symbols generated in parallel. Symbol `i` is drawn from the stream `i` of the seed,
+    /// so the result depends on the seed but not on the number of threads, and is bit-identical to
+    /// `build_from_labels_seeded`.
+    /// # Arguments
+    /// * `dimension` - The size of the symbols.
+    /// * `labels` - The labels of the symbols.
+    /// * `n_active` - The number of active entries of every symbol.
+    /// * `seed` - The seed of the symbols.
+    /// # Returns
+    /// The new `ItemMemory`; a repeated label keeps the symbol of its first occurrence.
+    #[cfg(feature = "parallel")]
+    pub fn par_build_from_labels(dimension: usize, labels: &[&str], n_active: usize, seed: u64) -> Result<Self, OVSAError> {
+        use rayon::prelude::*;
+
+        let vectors = crate::config::install(|| {
+            (0..labels.len()).into_par_iter()
+                .map(|i| crate::binary::sparse_random_with_rng(dimension, n_active, &mut stream_rng(seed, i as u64)))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        Self::from_symbols(dimension, labels, vectors)
+    }
+
+    /// Stores generated symbols under their labels, keeping the first symbol of a repeated label.
+    fn from_symbols(dimension: usize, labels: &[&str], vectors: Vec<CsVec<i8>>) -> Result<Self, OVSAError> {
+        let mut memory = ItemMemory::new(dimension)?;
+        memory.reserve(labels.len());
+        for (label, vector) in labels.iter().zip(vectors) {
+            if memory.own_position(label).is_none() {
+                memory.insert(label, vector)?;
+            }
+        }
+
+        Ok(memory)
+    }
+
+    /// Returns the vector stored under the given label, generating and inserting a random one if absent.
+    /// This makes the memory usable as a codebook of atomic symbols.
+    /// # Arguments
+    /// * `label` - The label of the symbol.
+    /// * `n_active` - The number of active entries of a newly generated symbol.
+    /// # Returns
+    /// The symbol's sparse binary vector.
+    pub fn symbol(&mut self, label: &str, n_active: usize) -> Result<&CsVec<i8>, OVSAError> {
+        with_global_rng(|rng| self.symbol_with_rng(label, n_active, rng))
+    }
+
+    /// Returns the vector stored under the given label, generating a missing one with the provided random number generator.
+    /// # Arguments
+    /// * `label` - The label of the symbol.
+    /// * `n_active` - The number of active entries of a newly generated symbol.
+    /// * `rng` - The random number generator used for new symbols.
+    /// # Returns
+    /// The symbol's sparse binary vector.
+    pub fn symbol_with_rng<R: Rng + ?Sized>(&mut self, label: &str, n_active: usize, rng: &mut R) -> Result<&CsVec<i8>, OVSAError> {
+        if !self.positions.contains_key(label) {
+            let vector = crate::binary::sparse_random_with_rng(self.dimension, n_active, rng)?;
+            self.insert(label, vector)?;
+        }
+
+        Ok(self.vectors[self.positions[label]].as_ref())
+    }
+
+    /// Returns a borrowed view of the vector stored under the given label, if any.
+    /// # Arguments
+    /// * `label` - The label of the entry.
+    pub fn get_view(&self, label: &str) -> Option<CsVecView<'_, i8>> {
+        self.get(label).map(|vector| vector.view())
+    }
+
+    /// Finds the `k` stored entries most similar to a borrowed query vector, e.g. a view into another memory.
+    /// # Arguments
+    /// * `query` - The query vector view.
+    /// * `k` - The maximum number of results.
+    /// # Returns
+    /// The (label, similarity) pairs sorted by decreasing similarity.
+    pub fn query_view(&self, query: CsVecView<'_, i8>, k: usize) -> Result<Matches, OVSAError> {
+        if query.dim() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let scores: Vec<f64> = self.vectors.iter()
+            .map(|vector| 1f64 - crate::binary::hamming_indices(query.indices(), vector.indices()) as f64 / self.dimension as f64)
+            .collect();
+
+        Ok(self.rank(&scores, k))
+    }
+}
+
+
+#[cfg(feature = "dense")]
+impl ItemMemory<Array1<f32>> {
+    /// Returns a borrowed view of the vector stored under the given label, if any.
+    /// # Arguments
+    /// * `label` - The label of the entry.
+    pub fn get_view(&self, label: &str) -> Option<ArrayView1<'_, f32>> {
+        self.get(label).map(|vector| vector.view())
+    }
+
+    /// Finds the `k` stored entries most similar (cosine) to a borrowed query vector, e.g. a row of a matrix.
+    /// # Arguments
+    /// * `query` - The query vector view.
+    /// * `k` - The maximum number of results.
+    /// # Returns
+    /// The (label, similarity) pairs sorted by decreasing similarity.
+    pub fn query_view(&self, query: ArrayView1<'_, f32>, k: usize) -> Result<Matches, OVSAError> {
+        if query.len() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let query_norm = crate::dense::norm(query);
+        let scores: Vec<f64> = self.vectors.iter()
+            .map(|vector| (crate::dense::dot(query, vector.view()) / (query_norm * crate::dense::norm(vector.view()))) as f64)
+            .collect();
+
+        Ok(self.rank(&scores, k))
+    }
+}
+
+
+/// The number of queries compared together by `query_batch`.
+const QUERY_TILE: usize = 32;
+
+
+/// The number of entries compared to a tile of queries before their candidates are trimmed to the best `k`.
+const ENTRY_TILE: usize = 256;
+
+
+/// Sorts (label, similarity) pairs by decreasing similarity and keeps the first `k`.
+/// Ties are ordered by label so that results do not depend on storage order.
+pub(crate) fn top_k(mut results: Vec<(String, f64)>, k: usize) -> Vec<(String, f64)> {
+    results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    results.truncate(k);
+    results
+}
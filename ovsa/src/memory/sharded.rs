@@ -0,0 +1,159 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use sprs::CsVec;
+
+use crate::errors::OVSAError;
+use crate::hypervector::Hypervector;
+use crate::memory::{ItemMemory, top_k};
+use crate::sketch::fnv1a;
+
+
+/// A partition of an item memory that can answer top-k cleanup queries on its own.
+/// Implemented for the in-process `ItemMemory`; shards living in other processes can implement it
+/// by forwarding the calls to their remote counterpart.
+pub trait MemoryShard<V: Hypervector>: Send + Sync {
+    /// Returns the number of entries held by the shard.
+    fn len(&self) -> usize;
+
+    /// Returns true if the shard holds no entries.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts a vector under the given label, replacing any vector previously stored under it.
+    fn insert(&mut self, label: &str, vector: V) -> Result<(), OVSAError>;
+
+    /// Finds the `k` entries of the shard most similar to the query vector, sorted by decreasing similarity.
+    fn query(&self, query: &V, k: usize) -> Result<Vec<(String, f64)>, OVSAError>;
+}
+
+
+impl<V: Hypervector> MemoryShard<V> for ItemMemory<V> {
+    fn len(&self) -> usize {
+        ItemMemory::len(self)
+    }
+
+    fn insert(&mut self, label: &str, vector: V) -> Result<(), OVSAError> {
+        ItemMemory::insert(self, label, vector)
+    }
+
+    fn query(&self, query: &V, k: usize) -> Result<Vec<(String, f64)>, OVSAError> {
+        ItemMemory::query(self, query, k)
+    }
+}
+
+
+/// An item memory split across several shards.
+/// Entries are routed to shards by a stable hash of their label, and queries are fanned out to all shards
+/// in parallel before merging the per-shard top-k results.
+pub struct ShardedItemMemory<V: Hypervector = CsVec<i8>> {
+    dimension: usize,
+    shards: Vec<Box<dyn MemoryShard<V>>>,
+}
+
+
+impl<V: Hypervector + 'static> ShardedItemMemory<V> {
+    /// Creates an empty sharded memory backed by in-process item memories.
+    /// # Arguments
+    /// * `dimension` - The dimension of the stored vectors.
+    /// * `n_shards` - The number of shards.
+    /// # Returns
+    /// An empty `ShardedItemMemory`.
+    pub fn new(dimension: usize, n_shards: usize) -> Result<Self, OVSAError> {
+        if n_shards == 0 {
+            return Err(OVSAError::ZeroShards);
+        }
+
+        let mut shards: Vec<Box<dyn MemoryShard<V>>> = Vec::with_capacity(n_shards);
+        for _ in 0..n_shards {
+            shards.push(Box::new(ItemMemory::<V>::new(dimension)?));
+        }
+
+        Ok(ShardedItemMemory { dimension, shards })
+    }
+
+    /// Creates a sharded memory from existing shards, e.g. handles to shards in other processes.
+    /// # Arguments
+    /// * `dimension` - The dimension of the stored vectors.
+    /// * `shards` - The shards making up the memory.
+    /// # Returns
+    /// A `ShardedItemMemory` over the given shards.
+    pub fn from_shards(dimension: usize, shards: Vec<Box<dyn MemoryShard<V>>>) -> Result<Self, OVSAError> {
+        if dimension == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+        if shards.is_empty() {
+            return Err(OVSAError::ZeroShards);
+        }
+
+        Ok(ShardedItemMemory { dimension, shards })
+    }
+
+    /// Returns the dimension of the stored vectors.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the number of shards.
+    pub fn n_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the total number of entries across all shards.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+
+    /// Returns true if no shard holds any entry.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.is_empty())
+    }
+
+    /// Inserts a vector into the shard selected by the label's hash.
+    /// # Arguments
+    /// * `label` - The label of the entry.
+    /// * `vector` - The vector to store.
+    pub fn insert(&mut self, label: &str, vector: V) -> Result<(), OVSAError> {
+        if vector.dimension() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let shard = shard_index(label, self.shards.len());
+        self.shards[shard].insert(label, vector)
+    }
+
+    /// Finds the `k` entries most similar to the query vector across all shards, querying the shards in parallel when
+    /// the `parallel` feature is enabled.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// * `k` - The maximum number of results.
+    /// # Returns
+    /// The (label, similarity) pairs sorted by decreasing similarity.
+    pub fn query(&self, query: &V, k: usize) -> Result<Vec<(String, f64)>, OVSAError> {
+        if query.dimension() != self.dimension {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        #[cfg(feature = "parallel")]
+        let per_shard: Vec<Vec<(String, f64)>> = crate::config::install(|| self.shards.par_iter().map(|shard| shard.query(query, k)).collect::<Result<_, _>>())?;
+        #[cfg(not(feature = "parallel"))]
+        let per_shard: Vec<Vec<(String, f64)>> = self.shards.iter().map(|shard| shard.query(query, k)).collect::<Result<_, _>>()?;
+
+        Ok(top_k(per_shard.into_iter().flatten().collect(), k))
+    }
+
+    /// Finds the single entry most similar to the query vector across all shards.
+    /// # Arguments
+    /// * `query` - The query vector.
+    /// # Returns
+    /// The (label, similarity) pair of the best match, or `None` if the memory is empty.
+    pub fn cleanup(&self, query: &V) -> Result<Option<(String, f64)>, OVSAError> {
+        Ok(self.query(query, 1)?.into_iter().next())
+    }
+}
+
+
+/// Maps a label to a shard using FNV-1a, which unlike the std hasher is stable across processes and releases.
+fn shard_index(label: &str, n_shards: usize) -> usize {
+    (fnv1a(label.as_bytes()) % n_shards as u64) as usize
+}
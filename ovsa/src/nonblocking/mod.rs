@@ -0,0 +1,125 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use rayon::prelude::*;
+
+use crate::errors::OVSAError;
+use crate::hypervector::Hypervector;
+use crate::memory::{ItemMemory, Matches};
+use crate::progress::CancellationToken;
+
+
+struct TaskState<T> {
+    result: Option<T>,
+    waker: Option<Waker>,
+}
+
+
+/// A future resolving to the result of a computation running on the crate's thread pool.
+/// Awaiting it never blocks the executor thread, which makes it safe to use from async services.
+pub struct Task<T> {
+    state: Arc<Mutex<TaskState<T>>>,
+}
+
+
+impl<T> Future for Task<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().expect("Task state lock poisoned.");
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+
+/// Runs a closure on the crate's thread pool (see `config`) and returns a future resolving to its result.
+/// # Arguments
+/// * `f` - The computation to run.
+/// # Returns
+/// A `Task` resolving to the closure's return value.
+pub fn spawn<T, F>(f: F) -> Task<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let state = Arc::new(Mutex::new(TaskState { result: None, waker: None }));
+    let task_state = Arc::clone(&state);
+
+    crate::config::spawn(move || {
+        let result = f();
+        let mut state = task_state.lock().expect("Task state lock poisoned.");
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    });
+
+    Task { state }
+}
+
+
+/// Applies a fallible function to every input in parallel without blocking the caller, e.g. to encode a dataset.
+/// # Arguments
+/// * `inputs` - The inputs to process.
+/// * `f` - The function applied to each input.
+/// # Returns
+/// A `Task` resolving to the outputs in input order, or the first error encountered.
+pub fn map_batch<I, O, F>(inputs: Vec<I>, f: F) -> Task<Result<Vec<O>, OVSAError>>
+where
+    I: Send + Sync + 'static,
+    O: Send + 'static,
+    F: Fn(&I) -> Result<O, OVSAError> + Send + Sync + 'static,
+{
+    map_batch_cancellable(inputs, f, CancellationToken::new())
+}
+
+
+/// Applies a fallible function to every input in parallel without blocking the caller, stopping once the token is
+/// cancelled. Inputs not yet started when the token is cancelled are skipped.
+/// # Arguments
+/// * `inputs` - The inputs to process.
+/// * `f` - The function applied to each input.
+/// * `token` - The cancellation token.
+/// # Returns
+/// A `Task` resolving to the outputs in input order, `Cancelled`, or the first error encountered.
+pub fn map_batch_cancellable<I, O, F>(inputs: Vec<I>, f: F, token: CancellationToken) -> Task<Result<Vec<O>, OVSAError>>
+where
+    I: Send + Sync + 'static,
+    O: Send + 'static,
+    F: Fn(&I) -> Result<O, OVSAError> + Send + Sync + 'static,
+{
+    spawn(move || inputs.par_iter().map(|input| token.check().and_then(|_| f(input))).collect())
+}
+
+
+/// Runs a cleanup query for every query vector in parallel without blocking the caller.
+/// # Arguments
+/// * `memory` - The item memory to query.
+/// * `queries` - The query vectors.
+/// * `k` - The maximum number of results per query.
+/// # Returns
+/// A `Task` resolving to the top-k (label, similarity) pairs of each query, in query order.
+pub fn query_batch<V: Hypervector + 'static>(memory: Arc<ItemMemory<V>>, queries: Vec<V>, k: usize) -> Task<Result<Vec<Matches>, OVSAError>> {
+    spawn(move || memory.query_batch(&queries, k))
+}
+
+
+/// Runs a cleanup query for every query vector in parallel without blocking the caller, stopping once the token is
+/// cancelled.
+/// # Arguments
+/// * `memory` - The item memory to query.
+/// * `queries` - The query vectors.
+/// * `k` - The maximum number of results per query.
+/// * `token` - The cancellation token.
+/// # Returns
+/// A `Task` resolving to the top-k (label, similarity) pairs of each query, in query order, or `Cancelled`.
+pub fn query_batch_cancellable<V: Hypervector + 'static>(memory: Arc<ItemMemory<V>>, queries: Vec<V>, k: usize, token: CancellationToken) -> Task<Result<Vec<Matches>, OVSAError>> {
+    map_batch_cancellable(queries, move |query| memory.query(query, k), token)
+}
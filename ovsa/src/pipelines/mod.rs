@@ -0,0 +1,75 @@
+use crate::encode::ngrams;
+use crate::errors::OVSAError;
+use crate::learn::{CentroidClassifier, Samples};
+use crate::memory::ItemMemory;
+use crate::trace::span;
+
+
+/// Language identification from character n-gram statistics.
+/// Texts are encoded with `encode::ngrams`, bundled per language into class prototypes, and identified by the most
+/// similar prototype.
+#[derive(Debug, Clone)]
+pub struct LanguageId {
+    n: usize,
+    n_active: usize,
+    codebook: ItemMemory,
+    classifier: CentroidClassifier,
+}
+
+
+impl LanguageId {
+    /// Creates an untrained language identifier using dense character symbols (half of the entries active).
+    /// # Arguments
+    /// * `dimension` - The dimension of the hypervectors.
+    /// * `n` - The n-gram size, typically 3.
+    /// # Returns
+    /// A new `LanguageId`.
+    pub fn new(dimension: usize, n: usize) -> Result<Self, OVSAError> {
+        if n == 0 {
+            return Err(OVSAError::InvalidArgument("the n-gram size must be at least 1, got 0".to_string()));
+        }
+
+        Ok(LanguageId {
+            n,
+            n_active: dimension / 2,
+            codebook: ItemMemory::new(dimension)?,
+            classifier: CentroidClassifier::new(dimension)?,
+        })
+    }
+
+    /// Returns the character symbols.
+    pub fn codebook(&self) -> &ItemMemory {
+        &self.codebook
+    }
+
+    /// Returns the underlying classifier holding one prototype per language.
+    pub fn classifier(&self) -> &CentroidClassifier {
+        &self.classifier
+    }
+
+    /// Adds texts to the prototypes of their languages.
+    /// # Arguments
+    /// * `corpus` - The (text, language) pairs.
+    pub fn train(&mut self, corpus: &[(String, String)]) -> Result<(), OVSAError> {
+        span!(INFO, "train_language_identifier", n_texts = corpus.len());
+        let samples: Samples = corpus.iter()
+            .map(|(text, language)| Ok((self.encode(text)?, language.clone())))
+            .collect::<Result<_, OVSAError>>()?;
+
+        self.classifier.fit(&samples)
+    }
+
+    /// Identifies the language of a text. Characters never seen in training get a fresh random symbol.
+    /// # Arguments
+    /// * `text` - The text to identify, at least `n` characters long.
+    /// # Returns
+    /// The (language, similarity) pair of the best matching language, or `None` if nothing was trained.
+    pub fn identify(&mut self, text: &str) -> Result<Option<(String, f64)>, OVSAError> {
+        let vector = self.encode(text)?;
+        self.classifier.predict(&vector)
+    }
+
+    fn encode(&mut self, text: &str) -> Result<sprs::CsVec<i8>, OVSAError> {
+        ngrams(text, self.n, &mut self.codebook, self.n_active)
+    }
+}
@@ -0,0 +1,94 @@
+//! Progress reporting for long-running operations such as dataset encoding and training.
+//!
+//! Operations taking an `on_progress` callback call it after every completed unit of work. The callback returns
+//! `ControlFlow::Continue(())` to go on or `ControlFlow::Break(())` to stop early, in which case the operation returns
+//! `OVSAError::Cancelled`. A `CancellationToken` stops operations from another thread, e.g. when a request deadline
+//! passes.
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::errors::OVSAError;
+
+
+/// A snapshot of the progress of an operation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// The current stage of the operation, e.g. `encode_train` or `fit`.
+    pub stage: &'static str,
+    /// The number of completed units (samples or epochs) of the stage.
+    pub completed: usize,
+    /// The total number of units of the stage.
+    pub total: usize,
+    /// The current training loss, for operations that track one.
+    pub loss: Option<f64>,
+}
+
+
+impl Progress {
+    /// Returns the completed share of the stage, between 0 and 1.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 { 1.0 } else { self.completed as f64 / self.total as f64 }
+    }
+}
+
+
+/// A shared flag asking operations to stop. Clones share the flag, so one clone can be handed to the operation and
+/// another kept to cancel it.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every operation holding a clone of the token to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true once the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns `Cancelled` if the token was cancelled.
+    pub fn check(&self) -> Result<(), OVSAError> {
+        if self.is_cancelled() { Err(OVSAError::Cancelled) } else { Ok(()) }
+    }
+
+    /// Returns a progress callback that stops the operation once the token is cancelled,
+    /// for the operations taking an `on_progress` callback.
+    pub fn callback(&self) -> impl FnMut(Progress) -> ControlFlow<()> + use<> {
+        self.guard(ignore)
+    }
+
+    /// Wraps a progress callback so that the operation also stops once the token is cancelled.
+    /// # Arguments
+    /// * `on_progress` - The callback called while the token is not cancelled.
+    pub fn guard<F: FnMut(Progress) -> ControlFlow<()>>(&self, mut on_progress: F) -> impl FnMut(Progress) -> ControlFlow<()> + use<F> {
+        let token = self.clone();
+        move |progress| if token.is_cancelled() { ControlFlow::Break(()) } else { on_progress(progress) }
+    }
+}
+
+
+/// A callback that ignores the progress and never stops the operation.
+pub fn ignore(_: Progress) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+}
+
+
+/// Reports progress to a callback, turning a request to stop into `Cancelled`.
+#[cfg(feature = "sparse")]
+pub(crate) fn report<F: FnMut(Progress) -> ControlFlow<()>>(on_progress: &mut F, stage: &'static str, completed: usize, total: usize, loss: Option<f64>) -> Result<(), OVSAError> {
+    match on_progress(Progress { stage, completed, total, loss }) {
+        ControlFlow::Continue(()) => Ok(()),
+        ControlFlow::Break(()) => Err(OVSAError::Cancelled),
+    }
+}
@@ -0,0 +1,129 @@
+//! Small non-cryptographic generators with fixed, documented algorithms, so that seeded results can be reproduced on
+//! any platform and with any version of `rand`. Select one for `OvsaRng` with the `rng-xoshiro` or `rng-pcg` feature, or
+//! pass one directly to any `*_with_rng` function.
+
+use rand::SeedableRng;
+use rand::rand_core::RngCore;
+use rand::rand_core::impls::fill_bytes_via_next;
+
+use crate::sketch::splitmix64;
+
+
+/// The xoshiro256++ generator of Blackman and Vigna: 256 bits of state, fast and statistically strong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xoshiro256PlusPlus {
+    state: [u64; 4],
+}
+
+
+impl RngCore for Xoshiro256PlusPlus {
+    fn next_u32(&mut self) -> u32 {
+        // the upper bits are the strongest
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s0.wrapping_add(*s3).rotate_left(23).wrapping_add(*s0);
+        let t = *s1 << 17;
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+        result
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        fill_bytes_via_next(self, dst)
+    }
+}
+
+
+impl SeedableRng for Xoshiro256PlusPlus {
+    type Seed = [u8; 32];
+
+    /// Creates a generator from the four little-endian state words of the seed. The all-zero state is a fixed point of
+    /// the generator, so an all-zero seed is replaced by `seed_from_u64(0)`.
+    fn from_seed(seed: Self::Seed) -> Self {
+        if seed.iter().all(|&byte| byte == 0) {
+            return Self::seed_from_u64(0);
+        }
+        let mut state = [0u64; 4];
+        for (word, bytes) in state.iter_mut().zip(seed.chunks_exact(8)) {
+            *word = u64::from_le_bytes(bytes.try_into().expect("Chunks have 8 bytes."));
+        }
+        Xoshiro256PlusPlus { state }
+    }
+
+    /// Expands a 64-bit seed with SplitMix64, as recommended by the authors of xoshiro.
+    fn seed_from_u64(seed: u64) -> Self {
+        let mut state = [0u64; 4];
+        for (i, word) in state.iter_mut().enumerate() {
+            *word = splitmix64(seed.wrapping_add((i as u64).wrapping_mul(0x9e3779b97f4a7c15)));
+        }
+        Xoshiro256PlusPlus { state }
+    }
+}
+
+
+/// The PCG64 generator of O'Neill (XSL RR 128/64): a 128-bit linear congruential generator with a permuted output,
+/// supporting 2^127 independent streams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pcg64 {
+    state: u128,
+    increment: u128,
+}
+
+
+impl Pcg64 {
+    const MULTIPLIER: u128 = 0x2360_ed05_1fc6_5da4_4385_df64_9fcc_f645;
+
+    /// Creates a generator with the initialisation of the reference implementation.
+    /// # Arguments
+    /// * `state` - The initial state.
+    /// * `stream` - The stream; generators on different streams give unrelated sequences.
+    /// # Returns
+    /// A new `Pcg64`.
+    pub fn new(state: u128, stream: u128) -> Self {
+        let increment = (stream << 1) | 1;
+        let mut pcg = Pcg64 { state: state.wrapping_add(increment), increment };
+        pcg.step();
+        pcg
+    }
+
+    fn step(&mut self) {
+        self.state = self.state.wrapping_mul(Self::MULTIPLIER).wrapping_add(self.increment);
+    }
+}
+
+
+impl RngCore for Pcg64 {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.step();
+        let state = self.state;
+        let rotation = (state >> 122) as u32;
+        (((state >> 64) as u64) ^ (state as u64)).rotate_right(rotation)
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        fill_bytes_via_next(self, dst)
+    }
+}
+
+
+impl SeedableRng for Pcg64 {
+    type Seed = [u8; 32];
+
+    /// Creates a generator from the little-endian state (first 16 bytes) and stream (last 16 bytes) of the seed.
+    fn from_seed(seed: Self::Seed) -> Self {
+        let state = u128::from_le_bytes(seed[..16].try_into().expect("The seed has 32 bytes."));
+        let stream = u128::from_le_bytes(seed[16..].try_into().expect("The seed has 32 bytes."));
+        Pcg64::new(state, stream)
+    }
+}
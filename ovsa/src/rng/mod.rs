@@ -0,0 +1,124 @@
+pub mod algorithms;
+
+use std::f64::consts::PI;
+use std::sync::Mutex;
+use rand::{Rng, RngCore, SeedableRng};
+
+
+#[cfg(feature = "rng-pcg")]
+use algorithms::Pcg64 as Selected;
+#[cfg(all(feature = "rng-xoshiro", not(feature = "rng-pcg")))]
+use algorithms::Xoshiro256PlusPlus as Selected;
+#[cfg(not(any(feature = "rng-xoshiro", feature = "rng-pcg")))]
+use rand_chacha::ChaCha12Rng as Selected;
+
+
+/// The algorithm behind `OvsaRng`, chosen at compile time: ChaCha12 by default, xoshiro256++ with the `rng-xoshiro`
+/// feature and PCG64 with the `rng-pcg` feature, which takes precedence if both are enabled.
+/// Unlike `rand`'s `StdRng`, whose algorithm may change between releases, each of them is pinned, so that seeded
+/// results are reproducible across platforms and versions.
+pub type Algorithm = Selected;
+
+
+/// The random number generator used throughout the crate.
+/// Wraps a seedable generator so that every random operation (vector generation, bundling tie-breaks)
+/// can be made reproducible by seeding it explicitly. Functions taking an explicit generator accept any `Rng`, e.g. one
+/// of `algorithms`, to pin an algorithm for a single computation regardless of the features.
+#[derive(Debug, Clone)]
+pub struct OvsaRng(Algorithm);
+
+
+impl OvsaRng {
+    /// Creates a generator seeded from the operating system's entropy source.
+    /// # Returns
+    /// A non-deterministic `OvsaRng`.
+    pub fn from_entropy() -> Self {
+        OvsaRng(Algorithm::from_os_rng())
+    }
+
+    /// Returns the name of the algorithm selected by the features, to be recorded next to seeds of reproducible runs.
+    pub fn algorithm() -> &'static str {
+        if cfg!(feature = "rng-pcg") {
+            "pcg64"
+        } else if cfg!(feature = "rng-xoshiro") {
+            "xoshiro256++"
+        } else {
+            "chacha12"
+        }
+    }
+}
+
+
+impl RngCore for OvsaRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        self.0.fill_bytes(dst)
+    }
+}
+
+
+impl SeedableRng for OvsaRng {
+    type Seed = <Algorithm as SeedableRng>::Seed;
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        OvsaRng(Algorithm::from_seed(seed))
+    }
+
+    fn seed_from_u64(seed: u64) -> Self {
+        OvsaRng(Algorithm::seed_from_u64(seed))
+    }
+}
+
+
+static GLOBAL_RNG: Mutex<Option<OvsaRng>> = Mutex::new(None);
+
+
+/// Seeds the global generator used by functions that do not take an explicit RNG.
+/// After calling this, the sequence of results produced by those functions is reproducible.
+/// # Arguments
+/// * `seed` - The seed for the global generator.
+pub fn set_global_seed(seed: u64) {
+    let mut global = GLOBAL_RNG.lock().expect("Global RNG lock poisoned.");
+    *global = Some(OvsaRng::seed_from_u64(seed));
+}
+
+
+/// Runs a closure with exclusive access to the global generator.
+/// The generator is seeded from entropy on first use unless `set_global_seed` was called before.
+/// # Arguments
+/// * `f` - The closure receiving the global generator.
+/// # Returns
+/// The value returned by the closure.
+pub fn with_global_rng<T>(f: impl FnOnce(&mut OvsaRng) -> T) -> T {
+    let mut global = GLOBAL_RNG.lock().expect("Global RNG lock poisoned.");
+    f(global.get_or_insert_with(OvsaRng::from_entropy))
+}
+
+
+/// Creates the generator of one stream of a seeded computation, e.g. the symbol at position `stream` of a codebook.
+/// Parallel code draws each chunk of work from its own stream, so that its results depend on the seed and the
+/// chunking but not on the number of threads or their scheduling, and the serial code uses the same streams so that
+/// both paths give bit-identical results.
+/// # Arguments
+/// * `seed` - The seed of the whole computation.
+/// * `stream` - The index of the stream.
+/// # Returns
+/// A seeded `OvsaRng` independent of the other streams.
+pub fn stream_rng(seed: u64, stream: u64) -> OvsaRng {
+    OvsaRng::seed_from_u64(crate::sketch::splitmix64(seed ^ crate::sketch::splitmix64(stream)))
+}
+
+
+/// Draws a standard normal value with the Box–Muller transform.
+pub(crate) fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    let u1: f64 = 1f64 - rng.random::<f64>();
+    let u2: f64 = rng.random::<f64>();
+    (-2f64 * u1.ln()).sqrt() * (2f64 * PI * u2).cos()
+}
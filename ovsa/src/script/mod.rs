@@ -0,0 +1,339 @@
+//! A small interpreter of hypervector algebra over the symbols of an item memory, for exploring encodings without
+//! recompiling. A script holds one statement per line:
+//!
+//! | statement | effect |
+//! |---|---|
+//! | `name = expression` | evaluates the expression and stores it under `name`, shadowing a symbol of that name |
+//! | `expression ~ expression` | prints the similarity of the two vectors |
+//! | `expression` | prints the symbols of the memory most similar to the vector |
+//!
+//! Expressions are names of variables or memory symbols, quoted labels such as `"new york"`, and the calls
+//! `bind(a, b, ...)`, `unbind(a, b, ...)` (the same as `bind` for XOR binding), `bundle(a, b, ...)`, `shift(a, n)` and
+//! `random()`, a fresh random vector of half density. For example `bind(A, shift(B, 1)) ~ C`. Expressions are evaluated
+//! as one fused `expr::Expr`, and `#` starts a comment. Every `bundle` call is its own majority, so
+//! `bundle(bundle(a, b, c), d, e)` is a majority of majorities rather than the majority of all five vectors.
+
+use std::collections::HashMap;
+use std::fmt;
+use rand::Rng;
+use sprs::CsVec;
+
+use crate::binary::{similarity, sparse_random_with_rng};
+use crate::errors::OVSAError;
+use crate::expr::{Expr, expr};
+use crate::memory::{ItemMemory, Matches};
+use crate::rng::with_global_rng;
+
+
+/// The number of matches printed for an expression unless specified otherwise.
+pub const DEFAULT_TOP_K: usize = 3;
+
+
+/// What executing a statement produced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The line was blank or a comment.
+    Nothing,
+    /// A variable was assigned.
+    Assigned(String),
+    /// The similarity of a comparison.
+    Similarity(f64),
+    /// The (label, similarity) pairs of the memory symbols most similar to an expression.
+    Matches(Matches),
+}
+
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Nothing => Ok(()),
+            Outcome::Assigned(name) => write!(f, "{} assigned", name),
+            Outcome::Similarity(similarity) => write!(f, "{:.4}", similarity),
+            Outcome::Matches(matches) => {
+                let matches: Vec<String> = matches.iter().map(|(label, similarity)| format!("{}:{:.4}", label, similarity)).collect();
+                write!(f, "{}", matches.join("\t"))
+            }
+        }
+    }
+}
+
+
+/// Evaluates statements over the symbols of an item memory and the variables assigned by earlier statements.
+#[derive(Debug, Clone)]
+pub struct Interpreter {
+    memory: ItemMemory,
+    variables: HashMap<String, CsVec<i8>>,
+    top_k: usize,
+}
+
+
+impl Interpreter {
+    /// Creates an interpreter over the symbols of an item memory.
+    /// # Arguments
+    /// * `memory` - The memory whose labels name the symbols, and which expressions are cleaned up against.
+    /// # Returns
+    /// A new `Interpreter` without variables.
+    pub fn new(memory: ItemMemory) -> Self {
+        Interpreter { memory, variables: HashMap::new(), top_k: DEFAULT_TOP_K }
+    }
+
+    /// Sets the number of matches reported for an expression.
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    /// Returns the memory of the symbols.
+    pub fn memory(&self) -> &ItemMemory {
+        &self.memory
+    }
+
+    /// Returns the value of a variable or, failing that, of a memory symbol.
+    /// # Arguments
+    /// * `name` - The name of the variable or the label of the symbol.
+    pub fn get(&self, name: &str) -> Option<&CsVec<i8>> {
+        self.variables.get(name).or_else(|| self.memory.get(name))
+    }
+
+    /// Executes a statement.
+    /// # Arguments
+    /// * `line` - The statement.
+    /// # Returns
+    /// What the statement produced, or `InvalidFormat` describing a syntax error or an unknown name.
+    pub fn execute(&mut self, line: &str) -> Result<Outcome, OVSAError> {
+        with_global_rng(|rng| self.execute_with_rng(line, rng))
+    }
+
+    /// Executes a statement, drawing the vectors of `random()` and breaking bundling ties with the provided random
+    /// number generator.
+    /// # Arguments
+    /// * `line` - The statement.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// What the statement produced, or `InvalidFormat` describing a syntax error or an unknown name.
+    pub fn execute_with_rng<R: Rng + ?Sized>(&mut self, line: &str, rng: &mut R) -> Result<Outcome, OVSAError> {
+        let Some(statement) = Parser::new(line)?.statement()? else {
+            return Ok(Outcome::Nothing);
+        };
+
+        match statement {
+            Statement::Assign(name, node) => {
+                let value = self.evaluate(&node, rng)?;
+                self.variables.insert(name.clone(), value);
+                Ok(Outcome::Assigned(name))
+            }
+            Statement::Compare(a, b) => {
+                let (a, b) = (self.evaluate(&a, rng)?, self.evaluate(&b, rng)?);
+                Ok(Outcome::Similarity(similarity(&a, &b)?))
+            }
+            Statement::Query(node) => {
+                let value = self.evaluate(&node, rng)?;
+                Ok(Outcome::Matches(self.memory.query(&value, self.top_k)?))
+            }
+        }
+    }
+
+    /// Executes a script line by line, stopping at the first failing statement.
+    /// # Arguments
+    /// * `script` - The statements, one per line.
+    /// # Returns
+    /// The outcome of every line, or the error of the first failing line prefixed with its number.
+    pub fn run(&mut self, script: &str) -> Result<Vec<Outcome>, OVSAError> {
+        with_global_rng(|rng| self.run_with_rng(script, rng))
+    }
+
+    /// Executes a script line by line with the provided random number generator.
+    /// # Arguments
+    /// * `script` - The statements, one per line.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// The outcome of every line, or the error of the first failing line prefixed with its number.
+    pub fn run_with_rng<R: Rng + ?Sized>(&mut self, script: &str, rng: &mut R) -> Result<Vec<Outcome>, OVSAError> {
+        script.lines().enumerate()
+            .map(|(number, line)| self.execute_with_rng(line, rng).map_err(|error| match error {
+                OVSAError::InvalidFormat(message) => OVSAError::InvalidFormat(format!("line {}: {}", number + 1, message)),
+                error => error,
+            }))
+            .collect()
+    }
+
+    /// Evaluates an expression as one fused `Expr`, after drawing the vectors of its `random()` calls.
+    fn evaluate<R: Rng + ?Sized>(&self, node: &Node, rng: &mut R) -> Result<CsVec<i8>, OVSAError> {
+        let dimension = self.memory.dimension();
+        let randoms = (0..node.n_randoms())
+            .map(|_| sparse_random_with_rng(dimension, dimension.div_ceil(2), rng))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let expr = self.build(node, &mut randoms.iter())?;
+        expr.eval_with_rng(rng)
+    }
+
+    fn build<'a>(&'a self, node: &Node, randoms: &mut std::slice::Iter<'a, CsVec<i8>>) -> Result<Expr<'a>, OVSAError> {
+        match node {
+            Node::Name(name) => self.get(name).map(expr).ok_or_else(|| syntax_error(format!("unknown name {}", name))),
+            Node::Integer(value) => Err(syntax_error(format!("expected a vector, got the number {}", value))),
+            Node::Call(function, arguments) => match (function.as_str(), arguments.as_slice()) {
+                ("random", []) => Ok(expr(randoms.next().expect("One vector is drawn per call."))),
+                ("bind" | "unbind", [first, rest @ ..]) if !rest.is_empty() => {
+                    rest.iter().try_fold(self.build(first, randoms)?, |expr, argument| Ok(expr.bind(self.build(argument, randoms)?)))
+                }
+                // one majority per call, so that a nested call stays a single term of this one
+                ("bundle", [_, rest @ ..]) if !rest.is_empty() => {
+                    Ok(Expr::bundle_all(arguments.iter().map(|argument| self.build(argument, randoms)).collect::<Result<Vec<_>, _>>()?))
+                }
+                ("shift", [vector, Node::Integer(shift)]) => Ok(self.build(vector, randoms)?.permute(*shift)),
+                ("random" | "bind" | "unbind" | "bundle" | "shift", _) => Err(syntax_error(format!("wrong arguments for {}: {}", function, usage(function)))),
+                _ => Err(syntax_error(format!("unknown function {}", function))),
+            },
+        }
+    }
+}
+
+
+/// A parsed statement.
+enum Statement {
+    Assign(String, Node),
+    Compare(Node, Node),
+    Query(Node),
+}
+
+
+/// A parsed expression.
+enum Node {
+    Name(String),
+    Integer(isize),
+    Call(String, Vec<Node>),
+}
+
+
+impl Node {
+    fn n_randoms(&self) -> usize {
+        match self {
+            Node::Call(function, arguments) => {
+                usize::from(function == "random") + arguments.iter().map(Node::n_randoms).sum::<usize>()
+            }
+            _ => 0,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Integer(isize),
+    Symbol(char),
+}
+
+
+/// A recursive descent parser over the tokens of one line.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+
+impl Parser {
+    fn new(line: &str) -> Result<Self, OVSAError> {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = Vec::new();
+        let mut chars = line.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c == '"' {
+                chars.next();
+                let label: String = chars.by_ref().take_while(|&c| c != '"').collect();
+                tokens.push(Token::Name(label));
+            } else if c.is_alphabetic() || c == '_' {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek().filter(|&&c| c.is_alphanumeric() || "_./:-".contains(c)) {
+                    name.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Name(name));
+            } else if c.is_ascii_digit() || c == '-' {
+                let mut number = String::from(c);
+                chars.next();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    number.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Integer(number.parse().map_err(|_| syntax_error(format!("invalid number {}", number)))?));
+            } else if "(),=~".contains(c) {
+                tokens.push(Token::Symbol(c));
+                chars.next();
+            } else {
+                return Err(syntax_error(format!("unexpected character {:?}", c)));
+            }
+        }
+
+        Ok(Parser { tokens, position: 0 })
+    }
+
+    fn statement(&mut self) -> Result<Option<Statement>, OVSAError> {
+        if self.tokens.is_empty() {
+            return Ok(None);
+        }
+
+        let statement = if let [Token::Name(name), Token::Symbol('='), ..] = self.tokens.as_slice() {
+            let name = name.clone();
+            self.position = 2;
+            Statement::Assign(name, self.expression()?)
+        } else {
+            let node = self.expression()?;
+            if self.accept('~') { Statement::Compare(node, self.expression()?) } else { Statement::Query(node) }
+        };
+        match self.tokens.get(self.position) {
+            None => Ok(Some(statement)),
+            Some(token) => Err(syntax_error(format!("unexpected {:?} after the statement", token))),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node, OVSAError> {
+        let token = self.tokens.get(self.position).cloned().ok_or_else(|| syntax_error("unexpected end of line".to_string()))?;
+        self.position += 1;
+        match token {
+            Token::Integer(value) => Ok(Node::Integer(value)),
+            Token::Name(name) if self.accept('(') => {
+                let mut arguments = Vec::new();
+                if !self.accept(')') {
+                    loop {
+                        arguments.push(self.expression()?);
+                        if self.accept(')') {
+                            break;
+                        }
+                        if !self.accept(',') {
+                            return Err(syntax_error(format!("expected ',' or ')' in the arguments of {}", name)));
+                        }
+                    }
+                }
+                Ok(Node::Call(name, arguments))
+            }
+            Token::Name(name) => Ok(Node::Name(name)),
+            Token::Symbol(symbol) => Err(syntax_error(format!("unexpected {:?}", symbol))),
+        }
+    }
+
+    fn accept(&mut self, symbol: char) -> bool {
+        let accepted = self.tokens.get(self.position) == Some(&Token::Symbol(symbol));
+        if accepted {
+            self.position += 1;
+        }
+        accepted
+    }
+}
+
+
+fn usage(function: &str) -> &'static str {
+    match function {
+        "random" => "random()",
+        "shift" => "shift(vector, integer)",
+        _ => "at least two vectors",
+    }
+}
+
+
+fn syntax_error(message: String) -> OVSAError {
+    OVSAError::InvalidFormat(message)
+}
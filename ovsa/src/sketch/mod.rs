@@ -0,0 +1,265 @@
+//! Encoders bridging classical similarity sketches and sparse binary hypervectors.
+//!
+//! The sketches are plain binary vectors, so they can be stored in an `ItemMemory`, cleaned up and classified
+//! like any other hypervector, while their Hamming similarity estimates a similarity of the original inputs.
+use sprs::CsVec;
+
+use crate::binary::from_indices_or_empty;
+use crate::errors::OVSAError;
+
+
+/// Hashes bytes with 64-bit FNV-1a, which unlike the std hasher is stable across processes and releases.
+/// # Arguments
+/// * `bytes` - The bytes to hash.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+
+/// Mixes a 64-bit value with the SplitMix64 finalizer, so that related inputs give unrelated outputs.
+/// # Arguments
+/// * `value` - The value to mix.
+pub fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+
+/// A SimHash (Charikar) encoder of weighted features into binary vectors whose Hamming similarity
+/// approximates `1 - angle / pi`, the angle being that between the feature vectors.
+///
+/// Bit layout: the vector is split into 64-bit blocks, and index `64 * b + k` is bit `k` (least significant first)
+/// of the feature hash for block `b`, `splitmix64(fnv1a(feature) ^ seed ^ splitmix64(b))`. Every feature adds its
+/// weight to the indices whose hash bit is set and subtracts it from the others, and the indices with a positive sum
+/// are active. With 64 dimensions this is the classic 64-bit SimHash fingerprint, see `to_fingerprints`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimHash {
+    dimension: usize,
+    seed: u64,
+}
+
+
+impl SimHash {
+    /// Creates a SimHash encoder.
+    /// # Arguments
+    /// * `dimension` - The size of the vectors, i.e. the number of fingerprint bits.
+    /// * `seed` - The seed of the hash family; sketches are only comparable if they share it.
+    /// # Returns
+    /// A new `SimHash`.
+    pub fn new(dimension: usize, seed: u64) -> Result<Self, OVSAError> {
+        if dimension == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+
+        Ok(SimHash { dimension, seed })
+    }
+
+    /// Returns the size of the vectors.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the 64 hash bits of a feature for a block of the vector.
+    /// # Arguments
+    /// * `feature` - The bytes identifying the feature, e.g. a token.
+    /// * `block` - The index of the 64-bit block.
+    pub fn feature_hash(&self, feature: &[u8], block: usize) -> u64 {
+        splitmix64(fnv1a(feature) ^ self.seed ^ splitmix64(block as u64))
+    }
+
+    /// Encodes weighted features, e.g. tokens with their counts or tf-idf weights.
+    /// # Arguments
+    /// * `features` - The (feature, weight) pairs.
+    /// # Returns
+    /// The SimHash of the features as a sparse binary vector.
+    pub fn encode_weighted<F: AsRef<[u8]>>(&self, features: &[(F, f64)]) -> Result<CsVec<i8>, OVSAError> {
+        if features.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let n_blocks = self.dimension.div_ceil(64);
+        let mut sums = vec![0f64; self.dimension];
+        for (feature, weight) in features {
+            for block in 0..n_blocks {
+                let hash = self.feature_hash(feature.as_ref(), block);
+                for (bit, sum) in sums[64 * block..self.dimension.min(64 * block + 64)].iter_mut().enumerate() {
+                    if hash >> bit & 1 == 1 { *sum += weight } else { *sum -= weight }
+                }
+            }
+        }
+
+        Ok(positive(&sums))
+    }
+
+    /// Encodes a real-valued feature vector, using the little-endian bytes of each position as its feature.
+    /// # Arguments
+    /// * `values` - The feature values.
+    /// # Returns
+    /// The SimHash of the values as a sparse binary vector.
+    pub fn encode_dense(&self, values: &[f64]) -> Result<CsVec<i8>, OVSAError> {
+        let features: Vec<([u8; 8], f64)> = values.iter().enumerate().map(|(position, &value)| ((position as u64).to_le_bytes(), value)).collect();
+        self.encode_weighted(&features)
+    }
+}
+
+
+/// A MinHash encoder of sets into sparse binary vectors whose overlap estimates the Jaccard similarity of the sets.
+///
+/// The vector has one block of `bucket_size` indices per hash function. Hash function `i` maps an item to
+/// `splitmix64(fnv1a(item) ^ seed ^ splitmix64(i))`, and the block activates the index given by the minimum hash over
+/// the set modulo `bucket_size` (b-bit minwise hashing). Two sets agree on a block with probability about
+/// `J + (1 - J) / bucket_size`, where `J` is their Jaccard similarity, see `estimate_jaccard`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinHash {
+    n_hashes: usize,
+    bucket_size: usize,
+    seed: u64,
+}
+
+
+impl MinHash {
+    /// Creates a MinHash encoder.
+    /// # Arguments
+    /// * `n_hashes` - The number of hash functions, i.e. active entries per vector; more hashes give finer estimates.
+    /// * `bucket_size` - The number of indices per hash function; larger buckets give fewer accidental agreements.
+    /// * `seed` - The seed of the hash family; sketches are only comparable if they share it.
+    /// # Returns
+    /// A new `MinHash` producing vectors of dimension `n_hashes * bucket_size`, `ZeroDimension` for no hash functions or
+    /// `InvalidArgument` for a bucket size below 2.
+    pub fn new(n_hashes: usize, bucket_size: usize, seed: u64) -> Result<Self, OVSAError> {
+        if n_hashes == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+        if bucket_size < 2 {
+            return Err(OVSAError::InvalidArgument(format!("bucket_size must be at least 2, got {}", bucket_size)));
+        }
+
+        Ok(MinHash { n_hashes, bucket_size, seed })
+    }
+
+    /// Returns the size of the vectors.
+    pub fn dimension(&self) -> usize {
+        self.n_hashes * self.bucket_size
+    }
+
+    /// Encodes a set of items, e.g. the shingles of a document. Repeated items are counted once.
+    /// # Arguments
+    /// * `items` - The items of the set.
+    /// # Returns
+    /// The MinHash signature as a sparse binary vector with one active entry per hash function.
+    pub fn encode<T: AsRef<[u8]>>(&self, items: &[T]) -> Result<CsVec<i8>, OVSAError> {
+        if items.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let item_hashes: Vec<u64> = items.iter().map(|item| fnv1a(item.as_ref()) ^ self.seed).collect();
+        let indices: Vec<usize> = (0..self.n_hashes)
+            .map(|i| {
+                let salt = splitmix64(i as u64);
+                let minimum = item_hashes.iter().map(|&hash| splitmix64(hash ^ salt)).min().unwrap();
+                i * self.bucket_size + (minimum % self.bucket_size as u64) as usize
+            })
+            .collect();
+
+        Ok(from_indices_or_empty(self.dimension(), indices))
+    }
+
+    /// Estimates the Jaccard similarity of two sets from their signatures, correcting for accidental agreements.
+    /// # Arguments
+    /// * `a` - The signature of the first set.
+    /// * `b` - The signature of the second set.
+    /// # Returns
+    /// The estimate, clamped to `[0, 1]`.
+    pub fn estimate_jaccard(&self, a: &CsVec<i8>, b: &CsVec<i8>) -> Result<f64, OVSAError> {
+        if a.dim() != self.dimension() || b.dim() != self.dimension() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        // signatures have one entry per block, so the shared entries are the agreeing blocks
+        let distance = crate::binary::hamming_distance(a, b);
+        let agreement = (a.nnz() + b.nnz() - distance) as f64 / 2.0 / self.n_hashes as f64;
+        let chance = 1.0 / self.bucket_size as f64;
+
+        Ok(((agreement - chance) / (1.0 - chance)).clamp(0.0, 1.0))
+    }
+}
+
+
+/// Splits a text into its overlapping character shingles of the given width, the usual set representation
+/// of documents for near-duplicate detection.
+/// # Arguments
+/// * `text` - The text.
+/// * `width` - The number of characters per shingle.
+/// # Returns
+/// The shingles in order of appearance, possibly repeated; texts shorter than `width` give a single shingle.
+pub fn shingles(text: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if width == 0 || chars.is_empty() {
+        return Vec::new();
+    }
+    if chars.len() <= width {
+        return vec![text.to_string()];
+    }
+
+    chars.windows(width).map(|window| window.iter().collect()).collect()
+}
+
+
+/// Computes a classic 64-bit SimHash from features hashed by any 64-bit hash function, as a 64-dimensional vector.
+/// This reproduces the fingerprints of existing SimHash implementations given the same feature hashes.
+/// # Arguments
+/// * `hashes` - The (feature hash, weight) pairs.
+/// # Returns
+/// The fingerprint as a sparse binary vector, index `k` being bit `k`.
+pub fn simhash64(hashes: &[(u64, f64)]) -> CsVec<i8> {
+    let mut sums = [0f64; 64];
+    for &(hash, weight) in hashes {
+        for (bit, sum) in sums.iter_mut().enumerate() {
+            if hash >> bit & 1 == 1 { *sum += weight } else { *sum -= weight }
+        }
+    }
+
+    positive(&sums)
+}
+
+
+/// Packs a binary vector into 64-bit fingerprints, index `64 * b + k` becoming bit `k` of word `b`.
+/// # Arguments
+/// * `vector` - The sparse binary vector.
+pub fn to_fingerprints(vector: &CsVec<i8>) -> Vec<u64> {
+    let mut words = vec![0u64; vector.dim().div_ceil(64)];
+    for &index in vector.indices() {
+        words[index / 64] |= 1 << (index % 64);
+    }
+
+    words
+}
+
+
+/// Unpacks 64-bit fingerprints into a binary vector, the inverse of `to_fingerprints`.
+/// # Arguments
+/// * `dimension` - The size of the vector, at most 64 bits per word.
+/// * `words` - The fingerprint words.
+pub fn from_fingerprints(dimension: usize, words: &[u64]) -> Result<CsVec<i8>, OVSAError> {
+    if words.len() != dimension.div_ceil(64) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    let indices: Vec<usize> = (0..dimension).filter(|&index| words[index / 64] >> (index % 64) & 1 == 1).collect();
+    Ok(from_indices_or_empty(dimension, indices))
+}
+
+
+/// Activates the indices with a positive sum.
+fn positive(sums: &[f64]) -> CsVec<i8> {
+    let indices: Vec<usize> = (0..sums.len()).filter(|&index| sums[index] > 0.0).collect();
+    from_indices_or_empty(sums.len(), indices)
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use sprs::CsVec;
+
+use crate::binary::bundle_remove;
+use crate::errors::OVSAError;
+
+
+/// A linear correction applied to the estimates of a `HdMultiset`, fitted on elements with known counts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    /// The factor applied to the analytic estimate.
+    pub scale: f64,
+    /// The value added after scaling.
+    pub offset: f64,
+}
+
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration { scale: 1.0, offset: 0.0 }
+    }
+}
+
+
+/// A multiset of sparse binary vectors stored as a counting superposition, similar to a counting Bloom filter.
+/// Every insertion adds one to the counters of the element's active entries, and the multiplicity of an element
+/// is estimated from the mean of its counters after removing the expected contribution of the other elements.
+/// Estimates are unbiased for random elements and their noise grows with the sum of the squared multiplicities.
+#[derive(Debug, Clone)]
+pub struct HdMultiset {
+    counts: Vec<u32>,
+    total: usize,
+    total_active: usize,
+    /// The multiplicity of every distinct element, keyed by a hash of its active indices.
+    multiplicities: HashMap<u64, usize>,
+    calibration: Calibration,
+}
+
+
+impl HdMultiset {
+    /// Creates an empty multiset for vectors of the given dimension.
+    /// # Arguments
+    /// * `dimension` - The size of the vectors.
+    /// # Returns
+    /// An empty `HdMultiset`.
+    pub fn new(dimension: usize) -> Result<Self, OVSAError> {
+        if dimension == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+
+        Ok(HdMultiset {
+            counts: vec![0; dimension],
+            total: 0,
+            total_active: 0,
+            multiplicities: HashMap::new(),
+            calibration: Calibration::default(),
+        })
+    }
+
+    /// Returns the dimension of the vectors.
+    pub fn dimension(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Returns the total number of insertions.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the counters of the superposition.
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// Returns the calibration applied to the estimates.
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+
+    /// Adds an element to the multiset.
+    /// # Arguments
+    /// * `element` - The sparse binary vector of the element.
+    pub fn insert(&mut self, element: &CsVec<i8>) -> Result<(), OVSAError> {
+        self.insert_n(element, 1)
+    }
+
+    /// Adds an element to the multiset several times.
+    /// # Arguments
+    /// * `element` - The sparse binary vector of the element.
+    /// * `n` - The multiplicity to add.
+    /// # Returns
+    /// `CounterOverflow` without changing the multiset if a counter would exceed `u32::MAX`.
+    pub fn insert_n(&mut self, element: &CsVec<i8>, n: usize) -> Result<(), OVSAError> {
+        if element.dim() != self.counts.len() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        let step = u32::try_from(n).map_err(|_| OVSAError::CounterOverflow)?;
+        if element.indices().iter().any(|&index| self.counts[index].checked_add(step).is_none()) {
+            return Err(OVSAError::CounterOverflow);
+        }
+        if n == 0 {
+            return Ok(());
+        }
+
+        for &index in element.indices() {
+            self.counts[index] += step;
+        }
+        self.total += n;
+        self.total_active += n * element.nnz();
+        *self.multiplicities.entry(fingerprint(element)).or_insert(0) += n;
+
+        Ok(())
+    }
+
+    /// Removes one insertion of an element from the multiset.
+    /// # Arguments
+    /// * `element` - The sparse binary vector of the element.
+    /// # Returns
+    /// `CounterUnderflow` if the element was not inserted.
+    pub fn remove(&mut self, element: &CsVec<i8>) -> Result<(), OVSAError> {
+        let key = fingerprint(element);
+        if !self.multiplicities.contains_key(&key) {
+            return Err(OVSAError::CounterUnderflow);
+        }
+        bundle_remove(&mut self.counts, element)?;
+        self.total -= 1;
+        self.total_active -= element.nnz();
+        let multiplicity = self.multiplicities.get_mut(&key).expect("The element was inserted.");
+        *multiplicity -= 1;
+        if *multiplicity == 0 {
+            self.multiplicities.remove(&key);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the mean counter over the active entries of an element, i.e. its dot product with the superposition
+    /// scaled by its number of active entries.
+    /// # Arguments
+    /// * `element` - The sparse binary vector of the element.
+    pub fn raw(&self, element: &CsVec<i8>) -> Result<f64, OVSAError> {
+        if element.dim() != self.counts.len() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+        if element.nnz() == 0 {
+            return Err(OVSAError::ZeroActiveElements);
+        }
+
+        let dot: u64 = element.indices().iter().map(|&index| self.counts[index] as u64).sum();
+        Ok(dot as f64 / element.nnz() as f64)
+    }
+
+    /// Estimates how many times an element was inserted, applying the calibration.
+    /// # Arguments
+    /// * `element` - The sparse binary vector of the element.
+    /// # Returns
+    /// The estimated multiplicity, which may be fractional or slightly negative for absent elements.
+    pub fn estimate(&self, element: &CsVec<i8>) -> Result<f64, OVSAError> {
+        let estimate = self.uncalibrated(element)?;
+        Ok(self.calibration.scale * estimate + self.calibration.offset)
+    }
+
+    /// Returns the expected standard deviation of the estimate of an element with `n_active` active entries.
+    /// Every other element shares a binomial number of the element's entries and adds its multiplicity to each of
+    /// them, so the crosstalk variance grows with the sum of the squared multiplicities, bounded here by including
+    /// the element's own. Counts differing by several deviations can be told apart reliably.
+    /// # Arguments
+    /// * `n_active` - The number of active entries of the element.
+    pub fn noise_std(&self, n_active: usize) -> f64 {
+        let density = self.density();
+        if n_active == 0 || density >= 1.0 {
+            return f64::INFINITY;
+        }
+
+        let sum_squares: f64 = self.multiplicities.values().map(|&multiplicity| (multiplicity as f64).powi(2)).sum();
+        (sum_squares * density * (1.0 - density) / n_active as f64).sqrt() / (1.0 - density) * self.calibration.scale.abs()
+    }
+
+    /// Fits the calibration by least squares so that the estimates of elements with known counts match those counts.
+    /// With fewer than two distinct estimates only the offset is fitted.
+    /// # Arguments
+    /// * `known` - (element, true multiplicity) pairs.
+    /// # Returns
+    /// The fitted calibration, which is also stored.
+    pub fn calibrate(&mut self, known: &[(&CsVec<i8>, usize)]) -> Result<Calibration, OVSAError> {
+        if known.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let estimates = known.iter().map(|(element, _)| self.uncalibrated(element)).collect::<Result<Vec<f64>, _>>()?;
+        let counts: Vec<f64> = known.iter().map(|&(_, count)| count as f64).collect();
+        let n = known.len() as f64;
+        let mean_estimate = estimates.iter().sum::<f64>() / n;
+        let mean_count = counts.iter().sum::<f64>() / n;
+        let covariance: f64 = estimates.iter().zip(&counts).map(|(e, c)| (e - mean_estimate) * (c - mean_count)).sum();
+        let variance: f64 = estimates.iter().map(|e| (e - mean_estimate).powi(2)).sum();
+
+        let scale = if variance > f64::EPSILON { covariance / variance } else { 1.0 };
+        self.calibration = Calibration { scale, offset: mean_count - scale * mean_estimate };
+
+        Ok(self.calibration)
+    }
+
+    /// Returns the mean density of the inserted elements.
+    fn density(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.total_active as f64 / (self.total as f64 * self.counts.len() as f64)
+    }
+
+    /// Removes the expected contribution of the other elements from the raw mean counter.
+    /// Each insertion of a random element activates an entry with probability equal to the mean density `p`,
+    /// so the raw value of an element inserted `c` times is `c + (total - c) * p` in expectation.
+    fn uncalibrated(&self, element: &CsVec<i8>) -> Result<f64, OVSAError> {
+        let raw = self.raw(element)?;
+        let density = self.density();
+        if density >= 1.0 {
+            return Ok(raw);
+        }
+
+        Ok((raw - self.total as f64 * density) / (1.0 - density))
+    }
+}
+
+
+/// Identifies an element by a hash of its active indices.
+fn fingerprint(element: &CsVec<i8>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    element.indices().hash(&mut hasher);
+    hasher.finish()
+}
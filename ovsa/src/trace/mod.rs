@@ -0,0 +1,44 @@
+//! Optional `tracing` instrumentation of expensive operations, enabled with the `trace` feature.
+//!
+//! Spans carry the size of the work (e.g. the number of bundled vectors or scanned entries) as fields, and emit a
+//! `finished` event with the elapsed time in microseconds when they close. Without the feature the spans compile to
+//! nothing.
+#[cfg(feature = "trace")]
+use std::time::Instant;
+
+
+/// An entered span that reports its duration when dropped.
+#[cfg(feature = "trace")]
+pub(crate) struct Timed {
+    _span: tracing::span::EnteredSpan,
+    start: Instant,
+}
+
+
+#[cfg(feature = "trace")]
+impl Timed {
+    pub(crate) fn enter(span: tracing::Span) -> Self {
+        Timed { _span: span.entered(), start: Instant::now() }
+    }
+}
+
+
+#[cfg(feature = "trace")]
+impl Drop for Timed {
+    fn drop(&mut self) {
+        // the span is still entered here, so the event is recorded inside it
+        tracing::debug!(elapsed_us = self.start.elapsed().as_micros() as u64, "finished");
+    }
+}
+
+
+/// Opens a timed span until the end of the enclosing block, e.g. `span!(INFO, "fit", n_samples = samples.len())`.
+macro_rules! span {
+    ($level:ident, $name:expr $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "trace")]
+        let _timed = $crate::trace::Timed::enter(tracing::span!(tracing::Level::$level, $name $(, $($fields)*)?));
+    };
+}
+
+
+pub(crate) use span;
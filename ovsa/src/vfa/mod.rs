@@ -0,0 +1,336 @@
+//! Vector function approximation: real-valued functions represented as a single dense hypervector.
+//!
+//! Points are encoded with fractional power encoding (FPE): a point `x` becomes the vector of the unit phasors
+//! `exp(i * theta_j . x)`, with random frequencies `theta_j` (random Fourier features). The inner product of two
+//! encodings approximates a shift-invariant kernel of the distance between the points, chosen by the distribution of the
+//! frequencies, so a superposition of weighted encodings is a kernel regression model that is evaluated anywhere with a
+//! single inner product. Encodings are FHRR vectors, see `fhrr`: binding the encodings of two points encodes
+//! their sum, and `fhrr::pow` and `fhrr::slerp` scale and interpolate encodings.
+use std::f64::consts::PI;
+use std::ops::ControlFlow;
+use ndarray::Array1;
+use rand::Rng;
+
+use crate::dense::dot;
+use crate::errors::OVSAError;
+use crate::fhrr;
+use crate::progress::{Progress, ignore, report};
+use crate::rng::{standard_normal, with_global_rng};
+
+
+/// Encodes points of `n_inputs` dimensions as FPE vectors.
+/// A vector stores the real parts of its phasors in its first half and the imaginary parts in its second half,
+/// so that the real inner product of two encodings equals the real part of their complex inner product.
+#[derive(Debug, Clone)]
+pub struct FractionalPowerEncoder {
+    n_inputs: usize,
+    kernel: Kernel,
+    bandwidth: f64,
+    frequencies: Vec<Vec<f64>>,
+}
+
+
+/// A shift-invariant kernel approximated by a `FractionalPowerEncoder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    /// `exp(-|a - b|^2 / (2 * bandwidth^2))`, smooth; frequencies are normally distributed.
+    Gaussian,
+    /// `exp(-|a - b|_1 / bandwidth)` over the L1 distance, sharply peaked; frequencies are Cauchy distributed.
+    Laplacian,
+}
+
+
+impl Kernel {
+    /// Computes the exact kernel value between two points, e.g. to check the approximation of an encoder.
+    /// # Arguments
+    /// * `a` - The first point.
+    /// * `b` - The second point.
+    /// * `bandwidth` - The bandwidth of the kernel.
+    /// # Returns
+    /// The kernel value, 1 for identical points.
+    pub fn evaluate(&self, a: &[f64], b: &[f64], bandwidth: f64) -> Result<f64, OVSAError> {
+        if a.len() != b.len() {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        let distance = self.distance(a, b);
+        Ok(match self {
+            Kernel::Gaussian => (-distance * distance / (2.0 * bandwidth * bandwidth)).exp(),
+            Kernel::Laplacian => (-distance / bandwidth).exp(),
+        })
+    }
+
+    /// The distance the kernel decays over: Euclidean for `Gaussian`, L1 for `Laplacian`.
+    fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+        let differences = a.iter().zip(b).map(|(x, y)| x - y);
+        match self {
+            Kernel::Gaussian => differences.map(|difference| difference * difference).sum::<f64>().sqrt(),
+            Kernel::Laplacian => differences.map(f64::abs).sum(),
+        }
+    }
+
+    /// Draws a frequency of a single coordinate from the spectral density of the kernel (Bochner's theorem).
+    fn frequency<R: Rng + ?Sized>(&self, bandwidth: f64, rng: &mut R) -> f64 {
+        match self {
+            Kernel::Gaussian => standard_normal(rng) / bandwidth,
+            Kernel::Laplacian => (PI * (rng.random::<f64>() - 0.5)).tan() / bandwidth,
+        }
+    }
+}
+
+
+/// Picks a bandwidth by the median heuristic: the median distance between pairs of points, measured as the kernel
+/// does. Compares all pairs, so large sets should be subsampled first.
+/// # Arguments
+/// * `points` - The points, e.g. a sample of the training inputs.
+/// * `kernel` - The kernel the bandwidth is for.
+/// # Returns
+/// The median pairwise distance.
+pub fn median_bandwidth(points: &[Vec<f64>], kernel: Kernel) -> Result<f64, OVSAError> {
+    if points.len() < 2 {
+        return Err(OVSAError::EmptyVectorList);
+    }
+    if points.iter().any(|point| point.len() != points[0].len()) {
+        return Err(OVSAError::VectorSizeMismatch);
+    }
+
+    let mut distances: Vec<f64> = points.iter().enumerate()
+        .flat_map(|(i, a)| points[i + 1..].iter().map(move |b| kernel.distance(a, b)))
+        .collect();
+    distances.sort_by(f64::total_cmp);
+    let median = distances[distances.len() / 2];
+    if median <= 0.0 {
+        return Err(OVSAError::InvalidArgument("the median distance between the points is 0".to_string()));
+    }
+    Ok(median)
+}
+
+
+/// Picks the bandwidth at which points at a given distance have a given kernel value, e.g. so that inputs one unit
+/// apart are encoded with similarity 0.5.
+/// # Arguments
+/// * `kernel` - The kernel the bandwidth is for.
+/// * `distance` - The distance, measured as the kernel does.
+/// * `similarity` - The kernel value at that distance, in `(0, 1)`.
+/// # Returns
+/// The bandwidth.
+pub fn bandwidth_for_similarity(kernel: Kernel, distance: f64, similarity: f64) -> Result<f64, OVSAError> {
+    if distance <= 0.0 || similarity <= 0.0 || similarity >= 1.0 {
+        return Err(OVSAError::InvalidArgument(format!("need a positive distance and a similarity in (0, 1), got {} and {}", distance, similarity)));
+    }
+
+    Ok(match kernel {
+        Kernel::Gaussian => distance / (-2.0 * similarity.ln()).sqrt(),
+        Kernel::Laplacian => distance / -similarity.ln(),
+    })
+}
+
+
+impl FractionalPowerEncoder {
+    /// Creates an encoder whose kernel is a Gaussian of the given bandwidth.
+    /// # Arguments
+    /// * `dimension` - The size of the encodings, an even number.
+    /// * `n_inputs` - The number of coordinates of a point.
+    /// * `bandwidth` - The distance over which the kernel decays to about 0.6; smaller values fit finer details.
+    /// # Returns
+    /// A new `FractionalPowerEncoder`.
+    pub fn new(dimension: usize, n_inputs: usize, bandwidth: f64) -> Result<Self, OVSAError> {
+        with_global_rng(|rng| Self::new_with_rng(dimension, n_inputs, bandwidth, rng))
+    }
+
+    /// Creates an encoder drawing its frequencies with the provided random number generator.
+    /// # Arguments
+    /// * `dimension` - The size of the encodings, an even number.
+    /// * `n_inputs` - The number of coordinates of a point.
+    /// * `bandwidth` - The distance over which the kernel decays to about 0.6.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// A new `FractionalPowerEncoder`.
+    pub fn new_with_rng<R: Rng + ?Sized>(dimension: usize, n_inputs: usize, bandwidth: f64, rng: &mut R) -> Result<Self, OVSAError> {
+        Self::with_kernel_and_rng(dimension, n_inputs, Kernel::Gaussian, bandwidth, rng)
+    }
+
+    /// Creates an encoder approximating the given kernel, see `median_bandwidth` and `bandwidth_for_similarity` to
+    /// choose the bandwidth.
+    /// # Arguments
+    /// * `dimension` - The size of the encodings, an even number.
+    /// * `n_inputs` - The number of coordinates of a point.
+    /// * `kernel` - The kernel to approximate.
+    /// * `bandwidth` - The bandwidth of the kernel.
+    /// # Returns
+    /// A new `FractionalPowerEncoder`.
+    pub fn with_kernel(dimension: usize, n_inputs: usize, kernel: Kernel, bandwidth: f64) -> Result<Self, OVSAError> {
+        with_global_rng(|rng| Self::with_kernel_and_rng(dimension, n_inputs, kernel, bandwidth, rng))
+    }
+
+    /// Creates an encoder approximating the given kernel, drawing its frequencies with the provided random number
+    /// generator.
+    /// # Arguments
+    /// * `dimension` - The size of the encodings, an even number.
+    /// * `n_inputs` - The number of coordinates of a point.
+    /// * `kernel` - The kernel to approximate.
+    /// * `bandwidth` - The bandwidth of the kernel.
+    /// * `rng` - The random number generator.
+    /// # Returns
+    /// A new `FractionalPowerEncoder`.
+    pub fn with_kernel_and_rng<R: Rng + ?Sized>(dimension: usize, n_inputs: usize, kernel: Kernel, bandwidth: f64, rng: &mut R) -> Result<Self, OVSAError> {
+        if dimension == 0 {
+            return Err(OVSAError::ZeroDimension);
+        }
+        if !dimension.is_multiple_of(2) {
+            return Err(OVSAError::InvalidArgument(format!("dimension must be even, got {}", dimension)));
+        }
+        if n_inputs == 0 {
+            return Err(OVSAError::EmptyIndices);
+        }
+        if bandwidth <= 0.0 {
+            return Err(OVSAError::InvalidArgument(format!("bandwidth must be positive, got {}", bandwidth)));
+        }
+
+        let frequencies = (0..dimension / 2)
+            .map(|_| (0..n_inputs).map(|_| kernel.frequency(bandwidth, rng)).collect())
+            .collect();
+
+        Ok(FractionalPowerEncoder { n_inputs, kernel, bandwidth, frequencies })
+    }
+
+    /// Returns the size of the encodings.
+    pub fn dimension(&self) -> usize {
+        2 * self.frequencies.len()
+    }
+
+    /// Returns the number of coordinates of a point.
+    pub fn n_inputs(&self) -> usize {
+        self.n_inputs
+    }
+
+    /// Returns the approximated kernel.
+    pub fn kernel_type(&self) -> Kernel {
+        self.kernel
+    }
+
+    /// Returns the bandwidth of the approximated kernel.
+    pub fn bandwidth(&self) -> f64 {
+        self.bandwidth
+    }
+
+    /// Encodes a point.
+    /// # Arguments
+    /// * `point` - The coordinates of the point.
+    /// # Returns
+    /// The FPE vector of the point.
+    pub fn encode(&self, point: &[f64]) -> Result<Array1<f32>, OVSAError> {
+        if point.len() != self.n_inputs {
+            return Err(OVSAError::VectorSizeMismatch);
+        }
+
+        // the phases are computed from the unwrapped frequencies rather than with `fhrr::pow` of a base vector, whose
+        // phases wrap to (-pi, pi] and would alias frequencies beyond pi
+        let phases: Vec<f64> = self.frequencies.iter().map(|frequency| frequency.iter().zip(point).map(|(theta, x)| theta * x).sum()).collect();
+        Ok(fhrr::from_phases(&phases))
+    }
+
+    /// Returns the kernel between two points as estimated by their encodings, 1 for identical points.
+    /// # Arguments
+    /// * `a` - The first point.
+    /// * `b` - The second point.
+    pub fn kernel(&self, a: &[f64], b: &[f64]) -> Result<f64, OVSAError> {
+        let (a, b) = (self.encode(a)?, self.encode(b)?);
+        Ok(dot(a.view(), b.view()) as f64 / self.frequencies.len() as f64)
+    }
+}
+
+
+/// A real-valued function stored as a weighted superposition of FPE vectors.
+#[derive(Debug, Clone)]
+pub struct VectorFunction {
+    encoder: FractionalPowerEncoder,
+    weights: Array1<f32>,
+}
+
+
+impl VectorFunction {
+    /// Creates the zero function over the points of an encoder.
+    /// # Arguments
+    /// * `encoder` - The encoder of the function's inputs.
+    pub fn new(encoder: FractionalPowerEncoder) -> Self {
+        let weights = Array1::zeros(encoder.dimension());
+        VectorFunction { encoder, weights }
+    }
+
+    /// Returns the encoder of the function's inputs.
+    pub fn encoder(&self) -> &FractionalPowerEncoder {
+        &self.encoder
+    }
+
+    /// Returns the hypervector representing the function.
+    pub fn weights(&self) -> &Array1<f32> {
+        &self.weights
+    }
+
+    /// Evaluates the function at a point with a single inner product.
+    /// # Arguments
+    /// * `point` - The coordinates of the point.
+    pub fn evaluate(&self, point: &[f64]) -> Result<f64, OVSAError> {
+        let encoding = self.encoder.encode(point)?;
+        Ok(self.value(&encoding))
+    }
+
+    /// Adds a weighted kernel centred on a point to the function, i.e. bundles the point's encoding scaled by `weight`.
+    /// # Arguments
+    /// * `point` - The coordinates of the point.
+    /// * `weight` - The height of the added kernel.
+    pub fn add(&mut self, point: &[f64], weight: f64) -> Result<(), OVSAError> {
+        let encoding = self.encoder.encode(point)?;
+        self.weights.scaled_add(weight as f32, &encoding);
+        Ok(())
+    }
+
+    /// Fits the function to (point, value) samples by iteratively bundling each sample's residual,
+    /// which converges to the kernel regression of the samples.
+    /// # Arguments
+    /// * `samples` - The (point, value) pairs.
+    /// * `epochs` - The number of passes over the samples.
+    /// * `learning_rate` - The share of the residual corrected per update, in `(0, 1]`.
+    /// # Returns
+    /// The root mean squared error on the samples after the last epoch.
+    pub fn fit(&mut self, samples: &[(Vec<f64>, f64)], epochs: usize, learning_rate: f64) -> Result<f64, OVSAError> {
+        self.fit_with_progress(samples, epochs, learning_rate, ignore)
+    }
+
+    /// Fits the function like `fit`, reporting progress after every epoch under the stage `epoch`. The reported loss
+    /// is the root mean squared residual seen during the epoch, before each sample's correction.
+    /// If the callback stops the training, the updates made so far are kept and `Cancelled` is returned.
+    /// # Arguments
+    /// * `samples` - The (point, value) pairs.
+    /// * `epochs` - The number of passes over the samples.
+    /// * `learning_rate` - The share of the residual corrected per update, in `(0, 1]`.
+    /// * `on_progress` - The progress callback.
+    /// # Returns
+    /// The root mean squared error on the samples after the last epoch.
+    pub fn fit_with_progress<F: FnMut(Progress) -> ControlFlow<()>>(&mut self, samples: &[(Vec<f64>, f64)], epochs: usize, learning_rate: f64, mut on_progress: F) -> Result<f64, OVSAError> {
+        if samples.is_empty() {
+            return Err(OVSAError::EmptyVectorList);
+        }
+
+        let encodings = samples.iter().map(|(point, _)| self.encoder.encode(point)).collect::<Result<Vec<_>, _>>()?;
+        for epoch in 0..epochs {
+            let mut squared = 0f64;
+            for (encoding, (_, value)) in encodings.iter().zip(samples) {
+                // the encoding has a squared norm of dimension / 2, so a rate of 1 makes the function exact at the sample
+                let residual = value - self.value(encoding);
+                squared += residual * residual;
+                self.weights.scaled_add((learning_rate * residual) as f32, encoding);
+            }
+            report(&mut on_progress, "epoch", epoch + 1, epochs, Some((squared / samples.len() as f64).sqrt()))?;
+        }
+
+        let squared: f64 = encodings.iter().zip(samples).map(|(encoding, (_, value))| (value - self.value(encoding)).powi(2)).sum();
+        Ok((squared / samples.len() as f64).sqrt())
+    }
+
+    /// Evaluates the function at an encoded point.
+    fn value(&self, encoding: &Array1<f32>) -> f64 {
+        dot(self.weights.view(), encoding.view()) as f64 / (encoding.len() / 2) as f64
+    }
+}
@@ -0,0 +1,168 @@
+#![cfg(feature = "sparse")]
+
+use std::env;
+use std::fs;
+use rand::SeedableRng;
+use sprs::CsVec;
+use ovsa::analysis::{Model, best_permutation, check_n_active_with_rng, recommend_n_active_with_rng};
+#[cfg(feature = "dense")]
+use ovsa::analysis::{bind_chain_depth_with_rng, self_check_with_rng};
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+fn memory() -> ItemMemory {
+    let mut memory = ItemMemory::new(10).unwrap();
+    memory.insert("a", ovsa::binary::from_indices(10, &[1, 3, 5]).unwrap()).unwrap();
+    memory.insert("b", ovsa::binary::from_indices(10, &[3, 4, 5]).unwrap()).unwrap();
+    memory.insert("c,d", ovsa::binary::from_indices(10, &[0]).unwrap()).unwrap();
+    memory
+}
+
+
+#[test]
+fn test_similarity_matrix() {
+    let matrix = ovsa::analysis::similarity_matrix(&memory()).unwrap();
+    assert_eq!(matrix[0][0], 1.0);
+    assert_eq!(matrix[0][1], 0.8);
+    assert_eq!(matrix[1][0], 0.8);
+    assert_eq!(ovsa::analysis::pairwise_similarities(&memory()).unwrap().len(), 3);
+}
+
+#[test]
+fn test_histogram() {
+    let histogram = ovsa::analysis::histogram(&[0.0, 0.1, 0.5, 0.99, 1.0, 2.0], 2, 0.0, 1.0).unwrap();
+    assert_eq!(histogram.edges, vec![0.0, 0.5, 1.0]);
+    assert_eq!(histogram.counts, vec![2, 4]);
+}
+
+#[test]
+fn test_export() {
+    let csv_path = env::temp_dir().join(format!("ovsa-similarity-{}.csv", std::process::id()));
+    ovsa::analysis::export_similarity_csv(&csv_path, &memory()).unwrap();
+    let csv = fs::read_to_string(&csv_path).unwrap();
+    fs::remove_file(&csv_path).unwrap();
+    assert_eq!(csv.lines().next().unwrap(), ",a,b,\"c,d\"");
+    assert_eq!(csv.lines().nth(1).unwrap(), "a,1,0.8,0.6");
+
+    let json_path = env::temp_dir().join(format!("ovsa-histogram-{}.json", std::process::id()));
+    let histogram = ovsa::analysis::histogram(&[0.25], 1, 0.0, 1.0).unwrap();
+    ovsa::analysis::export_histogram(&json_path, &histogram).unwrap();
+    let json = fs::read_to_string(&json_path).unwrap();
+    fs::remove_file(&json_path).unwrap();
+    assert_eq!(json, r#"{"edges":[0.0,1.0],"counts":[1]}"#);
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_self_check_passes_for_usual_parameters() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    for model in [Model::SparseBinary { n_active: 500 }, Model::Hrr] {
+        let report = self_check_with_rng(model, 1000, 10, &mut rng).unwrap();
+        assert!(report.passed(), "{:?}", report);
+        assert_eq!(report.checks.len(), 3);
+    }
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_self_check_flags_extreme_parameters() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    // the majority of three vectors with 5 of 1000 active entries is almost empty
+    let report = self_check_with_rng(Model::SparseBinary { n_active: 5 }, 1000, 10, &mut rng).unwrap();
+    assert!(!report.passed());
+    let bundle = report.checks.iter().find(|check| check.name == "bundle_preserves_constituents").unwrap();
+    assert!(!bundle.passed);
+
+    assert!(!self_check_with_rng(Model::Hrr, 8, 20, &mut rng).unwrap().passed());
+    assert!(self_check_with_rng(Model::Hrr, 0, 10, &mut rng).is_err());
+    assert!(self_check_with_rng(Model::Hrr, 100, 0, &mut rng).is_err());
+}
+
+#[test]
+fn test_recommend_n_active() {
+    let mut rng = OvsaRng::seed_from_u64(5);
+    let report = recommend_n_active_with_rng(1000, 5, &mut rng).unwrap();
+    assert_eq!(report.n_active, report.recommended);
+    assert_eq!(report.recommended, 500);
+    assert!(report.is_adequate());
+    assert!(recommend_n_active_with_rng(0, 5, &mut rng).is_err());
+    assert!(recommend_n_active_with_rng(1000, 0, &mut rng).is_err());
+}
+
+#[test]
+fn test_check_n_active_flags_sparse_choices() {
+    let mut rng = OvsaRng::seed_from_u64(5);
+    let sparse = check_n_active_with_rng(1000, 5, 10, &mut rng).unwrap();
+    let dense = check_n_active_with_rng(1000, 5, 500, &mut rng).unwrap();
+    assert!(!sparse.is_adequate());
+    assert!(sparse.separation < dense.separation);
+    assert_eq!(sparse.recommended, 500);
+    assert!(check_n_active_with_rng(1000, 5, 0, &mut rng).is_err());
+    assert!(check_n_active_with_rng(1000, 5, 1001, &mut rng).is_err());
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_bind_chain_depth() {
+    let mut rng = OvsaRng::seed_from_u64(5);
+    let hrr = bind_chain_depth_with_rng(Model::Hrr, 256, 4, 5, &mut rng).unwrap();
+    assert_eq!(hrr.rows.len(), 4);
+    assert_eq!(hrr.rows[0].depth, 1);
+    // correlation only approximately inverts convolution, so every level loses similarity
+    assert!(hrr.rows.windows(2).all(|pair| pair[1].recovered < pair[0].recovered));
+    assert!(hrr.rows[3].recovered > hrr.baseline);
+
+    // XOR and shifts are exact inverses
+    let binary = bind_chain_depth_with_rng(Model::SparseBinary { n_active: 128 }, 256, 3, 5, &mut rng).unwrap();
+    assert!(binary.rows.iter().all(|row| row.recovered == 1.0 && row.encoded < 0.6));
+
+    let table = binary.to_string();
+    assert_eq!(table.lines().count(), 5);
+    assert_eq!(table.lines().nth(1).unwrap(), "depth  recovered    encoded");
+    assert!(table.lines().nth(2).unwrap().starts_with("    1     1.0000"));
+    assert!(bind_chain_depth_with_rng(Model::Hrr, 256, 3, 0, &mut rng).is_err());
+}
+
+type Permutation = dyn Fn(&CsVec<i8>) -> CsVec<i8>;
+
+#[test]
+fn test_best_permutation() {
+    use ovsa::binary::{cyclic_shift, from_indices};
+
+    let vec = from_indices(10, &[0, 1, 4]).unwrap();
+    let target = cyclic_shift(&vec, 2);
+    let candidates: Vec<Box<Permutation>> = vec![
+        Box::new(|vec| cyclic_shift(vec, 1)),
+        Box::new(|vec| cyclic_shift(vec, 2)),
+        Box::new(|vec| vec.clone()),
+    ];
+    assert_eq!(best_permutation(&vec, &target, &candidates).unwrap(), Some((1, 1.0)));
+
+    let none: [Box<Permutation>; 0] = [];
+    assert_eq!(best_permutation(&vec, &target, &none).unwrap(), None);
+}
+
+#[test]
+fn test_inference_cost() {
+    use ovsa::analysis::cost::{Pipeline, inference_cost};
+
+    let pipeline = Pipeline { n_bindings: 10, n_permutations: 0, n_prototypes: 5 };
+    let binary = inference_cost(Model::SparseBinary { n_active: 5000 }, 10000, &pipeline).unwrap();
+    // 157 words per vector
+    assert_eq!(binary.xors, 15 * 157);
+    assert_eq!(binary.popcounts, 5 * 157);
+    assert_eq!(binary.macs, 0);
+    assert_eq!(binary.additions, 100000);
+    assert_eq!(inference_cost(Model::SparseBinary { n_active: 50 }, 10000, &pipeline).unwrap(), binary);
+    assert_eq!(binary.to_string().lines().count(), 6);
+    assert!(inference_cost(Model::SparseBinary { n_active: 50 }, 0, &pipeline).is_err());
+
+    #[cfg(feature = "dense")]
+    {
+        let hrr = inference_cost(Model::Hrr, 10000, &pipeline).unwrap();
+        assert_eq!(hrr.macs, 10 * 10000 * 10000 + 6 * 10000);
+        assert!(hrr.operations() > binary.operations());
+        assert!(hrr.traffic() > binary.traffic());
+    }
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "dense")]
+
+use ndarray::{Array1, array};
+use rand::SeedableRng;
+use ovsa::dense::bank::DenseBank;
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_bank_normalizes_rows() {
+    let mut bank = DenseBank::new(2).unwrap();
+    bank.insert("x", &array![3.0f32, 0.0]).unwrap();
+    bank.insert("y", &array![0.0f32, 2.0]).unwrap();
+    bank.insert("zero", &array![0.0f32, 0.0]).unwrap();
+    assert_eq!(bank.len(), 3);
+    assert_eq!(bank.get("x").unwrap(), array![1.0f32, 0.0]);
+
+    let similarities = bank.similarities(&array![1.0f32, 1.0]).unwrap();
+    assert!((similarities[0] - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+    assert_eq!(similarities[2], 0.0);
+
+    // replacing a prototype keeps its row
+    bank.insert("x", &array![-1.0f32, 0.0]).unwrap();
+    assert_eq!(bank.len(), 3);
+    assert_eq!(bank.cleanup(&array![-5.0f32, 0.1]).unwrap().unwrap().0, "x");
+    assert!(bank.insert("bad", &array![1.0f32]).is_err());
+}
+
+#[test]
+fn test_bank_matches_item_memory() {
+    let mut rng = OvsaRng::seed_from_u64(5);
+    let mut memory: ItemMemory<Array1<f32>> = ItemMemory::new(256).unwrap();
+    for i in 0..20 {
+        memory.insert(&i.to_string(), ovsa::dense::random_uniform_with_rng(256, -1.0, 1.0, &mut rng).unwrap()).unwrap();
+    }
+    let bank = memory.to_bank();
+    assert_eq!(bank.labels(), memory.labels());
+
+    let query = ovsa::dense::random_uniform_with_rng(256, -1.0, 1.0, &mut rng).unwrap();
+    let expected = memory.query(&query, 5).unwrap();
+    let found = bank.query(&query, 5).unwrap();
+    for ((label, similarity), (expected_label, expected_similarity)) in found.iter().zip(&expected) {
+        assert_eq!(label, expected_label);
+        assert!((similarity - expected_similarity).abs() < 1e-5);
+    }
+
+    let other = ovsa::dense::random_uniform_with_rng(256, -1.0, 1.0, &mut rng).unwrap();
+    let block = bank.similarities_batch(&[query.clone(), other.clone()]).unwrap();
+    assert_eq!(block.dim(), (2, 20));
+    for (row, query) in block.rows().into_iter().zip([&query, &other]) {
+        for (similarity, expected) in row.iter().zip(bank.similarities(query).unwrap()) {
+            assert!((similarity - expected).abs() < 1e-5);
+        }
+    }
+    assert!(bank.similarities_batch(&[Array1::zeros(3)]).is_err());
+
+    let batch = memory.query_batch(&[query], 5).unwrap();
+    assert_eq!(batch[0].iter().map(|(label, _)| label).collect::<Vec<_>>(), expected.iter().map(|(label, _)| label).collect::<Vec<_>>());
+    for ((_, similarity), (_, expected_similarity)) in batch[0].iter().zip(&expected) {
+        assert!((similarity - expected_similarity).abs() < 1e-5);
+    }
+}
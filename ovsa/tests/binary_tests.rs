@@ -1,3 +1,5 @@
+#![cfg(feature = "sparse")]
+
 use rand::seq::index::sample;
 use rand::rng;
 
@@ -129,3 +131,343 @@ fn test_similarity() {
     assert_eq!(similarity, expected_similarity); // 2 common active out of 4 total active
 }
 
+
+#[test]
+fn test_similarity_identical() {
+    let vec = ovsa::binary::from_indices(10, &[1, 3, 5]).unwrap();
+    let similarity = ovsa::binary::similarity(&vec, &vec).expect("Failed to compute similarity");
+    assert_eq!(similarity, 1.0);
+}
+
+#[test]
+fn test_consensus_sum_even_split_ties() {
+    use rand::SeedableRng;
+
+    // two disjoint vectors split every active index evenly, so each of them is kept at random
+    let vec1 = ovsa::binary::from_indices(1000, &(0..500).collect::<Vec<_>>()).unwrap();
+    let vec2 = ovsa::binary::from_indices(1000, &(500..1000).collect::<Vec<_>>()).unwrap();
+    let consensus = ovsa::binary::consensus_sum_with_rng(&[vec1, vec2], &mut ovsa::rng::OvsaRng::seed_from_u64(3)).unwrap();
+    assert!((400..600).contains(&consensus.nnz()), "{}", consensus.nnz());
+}
+
+#[test]
+fn test_consensus_sum_odd_has_no_ties() {
+    // an index active in one of three vectors is a minority; comparing the count with n / 2 = 1 used to treat it as a
+    // tie and keep it at random
+    let dimension = 10;
+    let vec1 = ovsa::binary::from_indices(dimension, &[1, 3]).unwrap();
+    let vec2 = ovsa::binary::from_indices(dimension, &[1, 4]).unwrap();
+    let vec3 = ovsa::binary::from_indices(dimension, &[2, 5]).unwrap();
+    for _ in 0..20 {
+        let consensus = ovsa::binary::consensus_sum(&[vec1.clone(), vec2.clone(), vec3.clone()]).unwrap();
+        assert_eq!(consensus.indices(), &[1]);
+    }
+}
+
+#[test]
+fn test_consensus_sum_counts_beyond_i16() {
+    use rand::SeedableRng;
+
+    // 20001 of 40000 vectors are active at index 0 and 19999 at index 1, counts a 16-bit counter could not hold
+    let active = ovsa::binary::from_indices(4, &[0, 2]).unwrap();
+    let inactive = ovsa::binary::from_indices(4, &[1, 3]).unwrap();
+    let mut vectors = vec![active; 20_001];
+    vectors.extend(vec![inactive; 19_999]);
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(109);
+    let consensus = ovsa::binary::consensus_sum_with_rng(&vectors, &mut rng).unwrap();
+    assert_eq!(consensus.indices(), &[0, 2]);
+
+    // with one more inactive vector every index ties, and the ties are broken by the generator
+    vectors.push(ovsa::binary::from_indices(4, &[1, 3]).unwrap());
+    let first = ovsa::binary::consensus_sum_with_rng(&vectors, &mut ovsa::rng::OvsaRng::seed_from_u64(1)).unwrap();
+    let second = ovsa::binary::consensus_sum_with_rng(&vectors, &mut ovsa::rng::OvsaRng::seed_from_u64(1)).unwrap();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_consensus_sum_large_dimension() {
+    use rand::SeedableRng;
+
+    let dimension = 200_000;
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(8);
+    let vectors: Vec<_> = (0..4).map(|_| ovsa::binary::sparse_random_with_rng(dimension, dimension / 2, &mut rng).unwrap()).collect();
+
+    let consensus = ovsa::binary::consensus_sum_with_rng(&vectors, &mut ovsa::rng::OvsaRng::seed_from_u64(9)).unwrap();
+    // indices active in at least three of the four vectors are always set, indices active in at most one never
+    for index in 0..dimension {
+        let count = vectors.iter().filter(|vec| vec.get(index).is_some()).count();
+        if count >= 3 {
+            assert_eq!(consensus.get(index), Some(&1));
+        } else if count <= 1 {
+            assert_eq!(consensus.get(index), None);
+        }
+    }
+}
+
+#[test]
+fn test_bind_accumulate() {
+    let dimension = 10;
+    let vec1 = ovsa::binary::from_indices(dimension, &[1, 3, 5]).unwrap();
+    let vec2 = ovsa::binary::from_indices(dimension, &[3, 4, 5]).unwrap();
+    let vec3 = ovsa::binary::from_indices(dimension, &[1, 9]).unwrap();
+
+    let mut counts = vec![0u32; dimension];
+    ovsa::binary::bind_accumulate(&mut counts, &vec1, &vec2).unwrap();
+    ovsa::binary::bind_accumulate(&mut counts, &vec1, &vec3).unwrap();
+    assert_eq!(counts, vec![0, 1, 0, 1, 1, 1, 0, 0, 0, 1]);
+
+    let bundled = ovsa::binary::majority(&[2, 1, 0, 2], 2);
+    assert_eq!(bundled.get(0), Some(&1));
+    assert_eq!(bundled.get(2), None);
+    assert_eq!(bundled.get(3), Some(&1));
+}
+
+#[test]
+fn test_bind_bundle_matches_consensus_of_xors() {
+    use rand::SeedableRng;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(10);
+    let vectors: Vec<_> = (0..6).map(|_| ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).collect();
+    let pairs = [(&vectors[0], &vectors[1]), (&vectors[2], &vectors[3]), (&vectors[4], &vectors[5])];
+
+    // with an odd number of pairs there are no ties, so the result is deterministic
+    let fused = ovsa::binary::bind_bundle(&pairs).unwrap();
+    let bound: Vec<_> = pairs.iter().map(|(a, b)| ovsa::binary::xor(a, b).unwrap()).collect();
+    assert_eq!(fused, ovsa::binary::consensus_sum(&bound).unwrap());
+}
+
+#[test]
+fn test_bundle_remove_matches_rebundling() {
+    use rand::SeedableRng;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(7);
+    let vectors: Vec<_> = (0..6).map(|_| ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).collect();
+    let mut counts = vec![0u32; 1000];
+    for vector in &vectors {
+        ovsa::binary::accumulate(&mut counts, vector).unwrap();
+    }
+    ovsa::binary::bundle_remove(&mut counts, &vectors[5]).unwrap();
+
+    let mut expected = vec![0u32; 1000];
+    for vector in &vectors[..5] {
+        ovsa::binary::accumulate(&mut expected, vector).unwrap();
+    }
+    assert_eq!(counts, expected);
+    assert_eq!(ovsa::binary::majority(&counts, 5), ovsa::binary::consensus_sum(&vectors[..5]).unwrap());
+}
+
+#[test]
+fn test_bundle_remove_rejects_absent_element() {
+    let mut counts = vec![0u32; 10];
+    ovsa::binary::accumulate(&mut counts, &ovsa::binary::from_indices(10, &[1, 2]).unwrap()).unwrap();
+    let result = ovsa::binary::bundle_remove(&mut counts, &ovsa::binary::from_indices(10, &[2, 3]).unwrap());
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::CounterUnderflow)));
+    assert_eq!(counts[2], 1);
+}
+
+#[test]
+fn test_large_consensus_sum_matches_serial_reference() {
+    use rand::{Rng, SeedableRng};
+
+    let dimension = ovsa::binary::PARALLEL_DIMENSION_THRESHOLD;
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(15);
+    let vectors: Vec<_> = (0..4).map(|_| ovsa::binary::sparse_random_with_rng(dimension, dimension / 2, &mut rng).unwrap()).collect();
+
+    // the serial definition: ties among the active indices are broken in index order, one draw per tie
+    let mut counts = vec![0u32; dimension];
+    for vector in &vectors {
+        ovsa::binary::accumulate(&mut counts, vector).unwrap();
+    }
+    let mut tie_breaker = ovsa::rng::OvsaRng::seed_from_u64(16);
+    let uniform = rand::distr::Uniform::new(0.0, 1.0).unwrap();
+    let indices: Vec<usize> = (0..dimension)
+        .filter(|&index| counts[index] > 2 || (counts[index] == 2 && tie_breaker.sample(uniform) > 0.5))
+        .collect();
+
+    let expected = ovsa::binary::from_indices(dimension, &indices).unwrap();
+    assert_eq!(ovsa::binary::consensus_sum_with_rng(&vectors, &mut ovsa::rng::OvsaRng::seed_from_u64(16)).unwrap(), expected);
+}
+
+#[test]
+fn test_hamming_at_most() {
+    let a = [0b1011u64, 0, u64::MAX];
+    let b = [0b0001u64, 0, 0];
+    assert_eq!(ovsa::binary::hamming_at_most(&a, &b, 100), Some(66));
+    assert_eq!(ovsa::binary::hamming_at_most(&a, &b, 66), Some(66));
+    assert_eq!(ovsa::binary::hamming_at_most(&a, &b, 65), None);
+    assert_eq!(ovsa::binary::hamming_at_most(&a, &a, 0), Some(0));
+}
+
+#[test]
+fn test_similarity_at_least() {
+    let vec1 = ovsa::binary::from_indices(10, &[1, 3, 5]).unwrap();
+    let vec2 = ovsa::binary::from_indices(10, &[3, 4, 5]).unwrap();
+    let similarity = ovsa::binary::similarity(&vec1, &vec2).unwrap();
+    assert_eq!(ovsa::binary::similarity_at_least(&vec1, &vec2, similarity).unwrap(), Some(similarity));
+    assert_eq!(ovsa::binary::similarity_at_least(&vec1, &vec2, 0.0).unwrap(), Some(similarity));
+    assert_eq!(ovsa::binary::similarity_at_least(&vec1, &vec2, similarity + 1e-9).unwrap(), None);
+}
+
+
+#[test]
+fn test_bind_graded() {
+    use rand::SeedableRng;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(4);
+    let a = ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap();
+    let b = ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap();
+
+    assert_eq!(ovsa::binary::bind_graded(&a, &b, 0.0).unwrap(), a);
+    assert_eq!(ovsa::binary::bind_graded(&a, &b, 1.0).unwrap(), ovsa::binary::xor(&a, &b).unwrap());
+
+    let half = ovsa::binary::bind_graded(&a, &b, 0.5).unwrap();
+    let similarity = ovsa::binary::similarity(&a, &half).unwrap();
+    assert!((similarity - 0.75).abs() < 0.05, "{}", similarity);
+    // the same strength selects the same entries of the key, so repeating the binding undoes it
+    assert_eq!(ovsa::binary::bind_graded(&half, &b, 0.5).unwrap(), a);
+
+    assert!(ovsa::binary::bind_graded(&a, &b, 1.5).is_err());
+    assert!(ovsa::binary::bind_graded(&a, &ovsa::binary::from_indices(10, &[1]).unwrap(), 0.5).is_err());
+}
+
+
+#[test]
+fn test_best_shift() {
+    use rand::SeedableRng;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(6);
+    let vec = ovsa::binary::sparse_random_with_rng(500, 50, &mut rng).unwrap();
+    let target = ovsa::binary::cyclic_shift(&vec, 137);
+    assert_eq!(ovsa::binary::best_shift(&vec, &target).unwrap(), (137, 1.0));
+
+    // a noisy target still aligns at the true shift, with the similarity of the shifted vector
+    let noise = ovsa::binary::sparse_random_with_rng(500, 10, &mut rng).unwrap();
+    let noisy = ovsa::binary::xor(&ovsa::binary::cyclic_shift(&vec, -3), &noise).unwrap();
+    let (shift, similarity) = ovsa::binary::best_shift(&vec, &noisy).unwrap();
+    assert_eq!(shift, 497);
+    assert_eq!(similarity, ovsa::binary::similarity(&ovsa::binary::cyclic_shift(&vec, shift), &noisy).unwrap());
+
+    assert!(ovsa::binary::best_shift(&vec, &ovsa::binary::from_indices(10, &[1]).unwrap()).is_err());
+}
+
+
+#[test]
+fn test_cyclic_shift_2d() {
+    // a 3 x 4 grid with cells (0, 0), (1, 3) and (2, 1) active
+    let vec = ovsa::binary::from_indices(12, &[0, 7, 9]).unwrap();
+    let shifted = ovsa::binary::cyclic_shift_2d(&vec, 3, 4, 1, 1).unwrap();
+    // (1, 1), (2, 0) and (0, 2)
+    assert_eq!(shifted.indices(), &[2, 5, 8]);
+    assert_eq!(ovsa::binary::cyclic_shift_2d(&shifted, 3, 4, -1, -1).unwrap(), vec);
+    assert_eq!(ovsa::binary::cyclic_shift_2d(&vec, 3, 4, 3, -8).unwrap(), vec);
+
+    assert!(ovsa::binary::cyclic_shift_2d(&vec, 3, 5, 1, 1).is_err());
+}
+
+
+#[test]
+fn test_reproject() {
+    use rand::SeedableRng;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(10);
+    let a = ovsa::binary::sparse_random_with_rng(4000, 2000, &mut rng).unwrap();
+    let noise = ovsa::binary::sparse_random_with_rng(4000, 400, &mut rng).unwrap();
+    let b = ovsa::binary::xor(&a, &noise).unwrap();
+    let original = ovsa::binary::similarity(&a, &b).unwrap();
+
+    for new_dimension in [1000, 6000] {
+        let (a2, b2) = (ovsa::binary::reproject(&a, new_dimension, 3).unwrap(), ovsa::binary::reproject(&b, new_dimension, 3).unwrap());
+        assert_eq!(a2.dim(), new_dimension);
+        assert_eq!(a2, ovsa::binary::reproject(&a, new_dimension, 3).unwrap());
+        let similarity = ovsa::binary::similarity(&a2, &b2).unwrap();
+        assert!((similarity - original).abs() < 0.03, "{} vs {}", similarity, original);
+    }
+    assert!(ovsa::binary::reproject(&a, 0, 3).is_err());
+}
+
+
+#[test]
+fn test_tree_consensus_sum() {
+    use rand::SeedableRng;
+    use ovsa::hypervector::BundleStrategy;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(13);
+    let vectors: Vec<_> = (0..9).map(|_| ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).collect();
+
+    // chunks of three vectors have no ties, so the tree is the majority of the three chunk majorities
+    let chunks: Vec<_> = vectors.chunks(3).map(|chunk| ovsa::binary::consensus_sum(chunk).unwrap()).collect();
+    let expected = ovsa::binary::consensus_sum(&chunks).unwrap();
+    let tree = ovsa::binary::consensus_sum_with_strategy_and_rng(&vectors, BundleStrategy::Tree { fan_in: 3 }, &mut rng).unwrap();
+    assert_eq!(tree, expected);
+
+    // ties are broken reproducibly for a seeded generator
+    let mut first = ovsa::rng::OvsaRng::seed_from_u64(1);
+    let mut second = ovsa::rng::OvsaRng::seed_from_u64(1);
+    let strategy = BundleStrategy::Tree { fan_in: 2 };
+    assert_eq!(
+        ovsa::binary::consensus_sum_with_strategy_and_rng(&vectors, strategy, &mut first).unwrap(),
+        ovsa::binary::consensus_sum_with_strategy_and_rng(&vectors, strategy, &mut second).unwrap(),
+    );
+
+    assert_eq!(ovsa::binary::consensus_sum_with_strategy(&vectors[..1], strategy).unwrap(), vectors[0]);
+    assert!(ovsa::binary::consensus_sum_with_strategy(&vectors, BundleStrategy::Tree { fan_in: 1 }).is_err());
+    assert!(ovsa::binary::consensus_sum_with_strategy(&[], strategy).is_err());
+}
+
+#[test]
+fn test_weighted_consensus_sum() {
+    let dimension = 10;
+    let heavy = ovsa::binary::from_indices(dimension, &[1, 2]).unwrap();
+    let light1 = ovsa::binary::from_indices(dimension, &[3, 4]).unwrap();
+    let light2 = ovsa::binary::from_indices(dimension, &[3, 5]).unwrap();
+    let vectors = [heavy, light1, light2];
+
+    let consensus = ovsa::binary::weighted_consensus_sum(&vectors, &[3.0, 1.0, 1.0]).unwrap();
+    assert_eq!(consensus.indices(), &[1, 2]);
+    let consensus = ovsa::binary::weighted_consensus_sum(&vectors, &[0.5, 1.0, 1.0]).unwrap();
+    assert_eq!(consensus.indices(), &[3]);
+
+    assert!(ovsa::binary::weighted_consensus_sum(&vectors, &[1.0, 1.0]).is_err());
+    assert!(ovsa::binary::weighted_consensus_sum(&vectors, &[1.0, -1.0, 1.0]).is_err());
+    assert!(ovsa::binary::weighted_consensus_sum(&[], &[]).is_err());
+}
+
+
+#[test]
+fn test_stochastic_hardening_keeps_minority_entries() {
+    use rand::SeedableRng;
+    use ovsa::hypervector::Hardening;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(21);
+    let counts = vec![3u32; 1000].into_iter().chain(vec![1u32; 1000]).chain(vec![0u32; 1000]).collect::<Vec<_>>();
+    assert_eq!(ovsa::binary::harden_with_rng(&counts, 3, Hardening::Majority, &mut rng), ovsa::binary::majority(&counts, 3));
+
+    let hardened = ovsa::binary::harden_with_rng(&counts, 3, Hardening::Stochastic, &mut rng);
+    let ones = hardened.indices().iter().filter(|&&index| index < 1000).count();
+    let minority = hardened.indices().iter().filter(|&&index| (1000..2000).contains(&index)).count();
+    assert_eq!(ones, 1000);
+    assert!((280..390).contains(&minority), "{}", minority);
+    assert!(hardened.indices().iter().all(|&index| index < 2000));
+
+    // every entry copies one of the three vectors at random, so it agrees with each of them with probability 2/3
+    let vectors: Vec<_> = (0..3).map(|_| ovsa::binary::sparse_random_with_rng(4000, 2000, &mut rng).unwrap()).collect();
+    let bundle = ovsa::binary::consensus_sum_with_hardening_and_rng(&vectors, Hardening::Stochastic, &mut rng).unwrap();
+    for vector in &vectors {
+        let similarity = ovsa::binary::similarity(&bundle, vector).unwrap();
+        assert!(similarity > 0.62 && similarity < 0.72, "{}", similarity);
+    }
+    assert!(ovsa::binary::consensus_sum_with_hardening(&[], Hardening::Stochastic).is_err());
+}
+
+#[test]
+fn test_cyclic_shift_matches_index_remapping() {
+    let mut rng = <ovsa::rng::OvsaRng as rand::SeedableRng>::seed_from_u64(12);
+    for dimension in [1, 7, 64, 100] {
+        let vec = ovsa::binary::sparse_random_with_rng(dimension, dimension.div_ceil(2), &mut rng).unwrap();
+        for shift in [-(dimension as isize) - 3, -1, 0, 1, 5, dimension as isize, 2 * dimension as isize + 1] {
+            let expected: Vec<usize> = vec.indices().iter().map(|&index| (index as isize + shift).rem_euclid(dimension as isize) as usize).collect();
+            let shifted = ovsa::binary::cyclic_shift(&vec, shift);
+            assert_eq!(shifted, ovsa::binary::from_indices(dimension, &expected).unwrap(), "{dimension} {shift}");
+        }
+    }
+}
@@ -0,0 +1,170 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::codebook::orthogonal_binary_with_rng;
+#[cfg(feature = "dense")]
+use ovsa::codebook::orthogonal_dense_with_rng;
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_orthogonal_binary_bounds_similarity() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let vectors = orthogonal_binary_with_rng(100, 50, 20, 0.56, &mut rng).unwrap();
+    assert_eq!(vectors.len(), 20);
+    for (i, a) in vectors.iter().enumerate() {
+        assert_eq!(a.nnz(), 50);
+        for b in &vectors[i + 1..] {
+            assert!(ovsa::binary::similarity(a, b).unwrap() <= 0.56);
+        }
+    }
+}
+
+#[test]
+fn test_orthogonal_binary_impossible_bound_fails() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let result = orthogonal_binary_with_rng(10, 5, 3, 0.0, &mut rng);
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::GenerationFailed)));
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_orthogonal_dense_bounds_similarity() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let vectors = orthogonal_dense_with_rng(64, 32, 0.3, &mut rng).unwrap();
+    assert_eq!(vectors.len(), 32);
+    for (i, a) in vectors.iter().enumerate() {
+        assert!(a.iter().all(|&value| value == 1.0 || value == -1.0));
+        for b in &vectors[i + 1..] {
+            assert!(ovsa::dense::similarity(a, b) <= 0.3);
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_orthogonal_dense_rejects_too_many_vectors() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let result = orthogonal_dense_with_rng(8, 9, 1.0, &mut rng);
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::GenerationFailed)));
+}
+
+#[test]
+fn test_correlated_binary_matches_targets() {
+    let mut rng = OvsaRng::seed_from_u64(4);
+    let targets = vec![
+        vec![1.0, 0.8, 0.6],
+        vec![0.8, 1.0, 0.7],
+        vec![0.6, 0.7, 1.0],
+    ];
+    let vectors = ovsa::codebook::correlated_binary_with_rng(10000, &targets, &mut rng).unwrap();
+    for i in 0..3 {
+        assert_eq!(vectors[i].nnz(), 5000);
+        for j in 0..3 {
+            let similarity = ovsa::binary::similarity(&vectors[i], &vectors[j]).unwrap();
+            assert!((similarity - targets[i][j]).abs() < 0.03, "{i} {j} {similarity}");
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_correlated_dense_matches_targets() {
+    let mut rng = OvsaRng::seed_from_u64(4);
+    let targets = vec![vec![1.0, -0.5], vec![-0.5, 1.0]];
+    let vectors = ovsa::codebook::correlated_dense_with_rng(10000, &targets, &mut rng).unwrap();
+    assert!((ovsa::dense::similarity(&vectors[0], &vectors[1]) + 0.5).abs() < 0.03);
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_correlated_rejects_invalid_targets() {
+    let mut rng = OvsaRng::seed_from_u64(4);
+    // three vectors cannot be pairwise perfectly anti-correlated
+    let targets = vec![
+        vec![1.0, -1.0, -1.0],
+        vec![-1.0, 1.0, -1.0],
+        vec![-1.0, -1.0, 1.0],
+    ];
+    let result = ovsa::codebook::correlated_dense_with_rng(100, &targets, &mut rng);
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::GenerationFailed)));
+}
+
+#[test]
+fn test_ordinal_binary_similarity_decays_with_rank() {
+    let mut rng = OvsaRng::seed_from_u64(4);
+    let vectors = ovsa::codebook::ordinal_binary_with_rng(10000, 6, 0.9, &mut rng).unwrap();
+    let similarities: Vec<f64> = vectors.iter().map(|vector| ovsa::binary::similarity(&vectors[0], vector).unwrap()).collect();
+    assert!((similarities[1] - 0.9).abs() < 0.03);
+    assert!(similarities.windows(2).all(|pair| pair[0] > pair[1]));
+    assert!(similarities[5] > 0.5);
+}
+
+#[test]
+fn test_positions_are_shift_powers() {
+    let base = ovsa::binary::from_indices(10, &[0, 3, 9]).unwrap();
+    let table = ovsa::codebook::positions(&base, 4);
+    assert_eq!(table.len(), 4);
+    assert_eq!(table.get(0), Some(&base));
+    assert_eq!(table.get(2), Some(&ovsa::binary::cyclic_shift(&base, 2)));
+    assert!(table.get(4).is_none());
+
+    let item = ovsa::binary::from_indices(10, &[1]).unwrap();
+    assert_eq!(table.bind(3, &item).unwrap(), ovsa::binary::xor(&ovsa::binary::cyclic_shift(&base, 3), &item).unwrap());
+    let error = table.bind(4, &item).unwrap_err();
+    assert!(matches!(&error, ovsa::errors::OVSAError::InvalidArgument(message) if message.contains("position 4")), "{:?}", error);
+}
+
+#[test]
+fn test_positions_bind_bundle_encodes_sequence() {
+    let mut rng = OvsaRng::seed_from_u64(8);
+    let base = ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap();
+    let items: Vec<_> = (0..3).map(|_| ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).collect();
+    let table = ovsa::codebook::positions(&base, 3);
+    let sequence = table.bind_bundle(&items.iter().collect::<Vec<_>>()).unwrap();
+
+    // unbinding position 1 recovers the second item
+    let recovered = table.bind(1, &sequence).unwrap();
+    assert!(ovsa::binary::similarity(&recovered, &items[1]).unwrap() > 0.7);
+    let error = table.bind_bundle(&[&items[0], &items[1], &items[2], &items[0]]).unwrap_err();
+    assert!(matches!(&error, ovsa::errors::OVSAError::InvalidArgument(message) if message.contains("position 3")), "{:?}", error);
+}
+
+#[test]
+fn test_binding_table_matches_record() {
+    use ovsa::codebook::BindingTable;
+    let mut rng = OvsaRng::seed_from_u64(9);
+    let roles: Vec<_> = (0..3).map(|_| ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).collect();
+    let fillers = ovsa::encode::levels_with_rng(1000, 500, 4, &mut rng).unwrap();
+    let table = BindingTable::new(&roles, &fillers, usize::MAX).unwrap();
+    assert_eq!((table.n_roles(), table.n_fillers()), (3, 4));
+    assert_eq!(table.get(2, 1), Some(&ovsa::binary::xor(&roles[2], &fillers[1]).unwrap()));
+    assert!(table.get(1, 4).is_none());
+    assert_eq!(table.memory_usage().n_vectors, 12);
+
+    let fields = [(0, 3), (1, 0), (2, 3), (0, 1)];
+    let pairs: Vec<_> = fields.iter().map(|&(role, filler)| (&roles[role], &fillers[filler])).collect();
+    let expected = ovsa::binary::bind_bundle_with_rng(&pairs, &mut OvsaRng::seed_from_u64(10)).unwrap();
+    assert_eq!(table.record_with_rng(&fields, &mut OvsaRng::seed_from_u64(10)).unwrap(), expected);
+    assert!(matches!(table.record(&[(3, 0)]), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+    assert!(matches!(table.record(&[(0, 4)]), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+    assert!(matches!(table.record(&[]), Err(ovsa::errors::OVSAError::EmptyVectorList)));
+
+    let levels = ovsa::encode::LevelEncoder::new(0.0, 1.0, fillers.clone()).unwrap();
+    let encoded = table.features(&[0.0, 1.0, 0.4], &levels).unwrap();
+    let pairs: Vec<_> = [0.0, 1.0, 0.4].iter().enumerate().map(|(role, &value)| (&roles[role], levels.encode(value))).collect();
+    assert!(ovsa::binary::similarity(&encoded, &ovsa::binary::bind_bundle(&pairs).unwrap()).unwrap() > 0.9);
+    assert!(matches!(table.features(&[0.0, 1.0, 0.4, 0.2], &levels), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+}
+
+#[test]
+fn test_binding_table_budget() {
+    use ovsa::codebook::BindingTable;
+    let mut rng = OvsaRng::seed_from_u64(11);
+    let vectors: Vec<_> = (0..4).map(|_| ovsa::binary::sparse_random_with_rng(1000, 100, &mut rng).unwrap()).collect();
+    let table = BindingTable::new(&vectors, &vectors, usize::MAX).unwrap();
+    let bytes = table.memory_usage().total_bytes();
+    assert!(BindingTable::new(&vectors, &vectors, bytes).is_ok());
+    assert!(matches!(BindingTable::new(&vectors, &vectors, bytes - 1), Err(ovsa::errors::OVSAError::BudgetExceeded)));
+    assert!(BindingTable::new(&[], &vectors, usize::MAX).is_err());
+}
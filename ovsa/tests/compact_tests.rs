@@ -0,0 +1,68 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::binary::compact::{CompactBinary, MAX_DIMENSION};
+use ovsa::hypervector::Hypervector;
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_checked_conversions() {
+    let mut rng = OvsaRng::seed_from_u64(1);
+    let sparse = ovsa::binary::sparse_random_with_rng(10000, 300, &mut rng).unwrap();
+    let compact = CompactBinary::try_from(&sparse).unwrap();
+    assert_eq!((compact.dim(), compact.nnz()), (10000, 300));
+    assert!(compact.indices().eq(sparse.indices().iter().copied()));
+    assert_eq!(compact.to_sparse(), sparse);
+    assert_eq!(sprs::CsVec::from(&compact), sparse);
+    assert_eq!(compact.heap_bytes(), 300 * 4);
+    assert!(compact.heap_bytes() * 2 < sparse.heap_bytes());
+
+    let built = CompactBinary::from_indices(10, &[7, 2, 7, 0]).unwrap();
+    assert!(built.indices().eq([0, 2, 7]));
+    assert!(built.contains(2) && !built.contains(3) && !built.contains(usize::MAX));
+    assert!(CompactBinary::from_indices(10, &[10]).is_err());
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn test_dimensions_beyond_u32_are_rejected() {
+    let large = CompactBinary::from_indices(MAX_DIMENSION, &[0, MAX_DIMENSION - 1]).unwrap();
+    assert!(large.indices().eq([0, MAX_DIMENSION - 1]));
+    assert!(matches!(CompactBinary::from_indices(MAX_DIMENSION + 1, &[0]), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+    let too_large = sprs::CsVec::new(MAX_DIMENSION + 1, vec![MAX_DIMENSION], vec![1i8]);
+    assert!(CompactBinary::try_from(&too_large).is_err());
+}
+
+#[test]
+fn test_similarities_match_sparse() {
+    let mut rng = OvsaRng::seed_from_u64(2);
+    let vectors: Vec<_> = [10, 300, 2500].iter().map(|&n_active| ovsa::binary::sparse_random_with_rng(5000, n_active, &mut rng).unwrap()).collect();
+    let weights: Vec<f64> = (0..5000).map(|index| (index % 7) as f64).collect();
+    for a in &vectors {
+        for b in &vectors {
+            let (compact_a, compact_b) = (CompactBinary::try_from(a).unwrap(), CompactBinary::try_from(b).unwrap());
+            assert_eq!(compact_a.hamming_distance(&compact_b).unwrap(), ovsa::binary::hamming_distance(a, b));
+            assert_eq!(compact_a.similarity(&compact_b).unwrap(), a.similarity(b).unwrap());
+            assert!((compact_a.weighted_similarity(&compact_b, &weights).unwrap() - a.weighted_similarity(b, &weights).unwrap()).abs() < 1e-12);
+        }
+    }
+    let other = CompactBinary::from_indices(100, &[1]).unwrap();
+    assert!(CompactBinary::try_from(&vectors[0]).unwrap().hamming_distance(&other).is_err());
+}
+
+#[test]
+fn test_compact_memory() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let mut memory = ItemMemory::new(20000).unwrap();
+    for i in 0..50 {
+        memory.insert(&format!("symbol{}", i), ovsa::binary::sparse_random_with_rng(20000, 200, &mut rng).unwrap()).unwrap();
+    }
+    let query = ovsa::binary::sparse_random_with_rng(20000, 200, &mut rng).unwrap();
+
+    let compact = memory.compact().unwrap();
+    assert_eq!(compact.query(&CompactBinary::try_from(&query).unwrap(), 5).unwrap(), memory.query(&query, 5).unwrap());
+    assert!(compact.memory_usage().vector_bytes * 2 < memory.memory_usage().vector_bytes);
+    assert_eq!(compact.to_sparse().get("symbol7"), memory.get("symbol7"));
+}
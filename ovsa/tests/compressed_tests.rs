@@ -0,0 +1,95 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::binary::compressed::CompressedBinary;
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_round_trip_both_layouts() {
+    let mut rng = OvsaRng::seed_from_u64(1);
+    let sparse = ovsa::binary::sparse_random_with_rng(10000, 20, &mut rng).unwrap();
+    let dense = ovsa::binary::sparse_random_with_rng(10000, 5000, &mut rng).unwrap();
+
+    let compressed_sparse = CompressedBinary::compress(&sparse);
+    let compressed_dense = CompressedBinary::compress(&dense);
+    assert!(!compressed_sparse.is_packed());
+    assert!(compressed_dense.is_packed());
+    assert!(compressed_sparse.size_bytes() < 20 * 8);
+    assert_eq!(compressed_dense.size_bytes(), 10000usize.div_ceil(64) * 8);
+
+    assert_eq!(compressed_sparse.decompress(), sparse);
+    assert_eq!(compressed_dense.decompress(), dense);
+}
+
+#[test]
+fn test_hamming_distance_across_layouts() {
+    let mut rng = OvsaRng::seed_from_u64(2);
+    let vectors: Vec<_> = [10, 30, 3000, 5000].iter().map(|&n_active| ovsa::binary::sparse_random_with_rng(5000, n_active, &mut rng).unwrap()).collect();
+    for a in &vectors {
+        for b in &vectors {
+            let expected = ovsa::binary::hamming_distance(a, b);
+            let actual = CompressedBinary::compress(a).hamming_distance(&CompressedBinary::compress(b)).unwrap();
+            assert_eq!(actual, expected);
+        }
+    }
+}
+
+#[test]
+fn test_compressed_memory_query() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let mut memory = ItemMemory::new(2000).unwrap();
+    for label in ["a", "b", "c"] {
+        memory.insert(label, ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap()).unwrap();
+    }
+    let query = ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap();
+
+    let compressed = memory.compressed();
+    assert_eq!(compressed.query(&CompressedBinary::compress(&query), 3).unwrap(), memory.query(&query, 3).unwrap());
+}
+
+#[test]
+fn test_hamming_at_most_across_layouts() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let vectors: Vec<_> = [10, 2500, 5000].iter().map(|&n_active| ovsa::binary::sparse_random_with_rng(5000, n_active, &mut rng).unwrap()).collect();
+    for a in &vectors {
+        for b in &vectors {
+            let expected = ovsa::binary::hamming_distance(a, b);
+            let (a, b) = (CompressedBinary::compress(a), CompressedBinary::compress(b));
+            assert_eq!(a.hamming_at_most(&b, expected).unwrap(), Some(expected));
+            if expected > 0 {
+                assert_eq!(a.hamming_at_most(&b, expected - 1).unwrap(), None);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_compressed_cleanup_matches_full_scan() {
+    let mut rng = OvsaRng::seed_from_u64(4);
+    let mut memory = ItemMemory::new(4096).unwrap();
+    for i in 0..50 {
+        memory.insert(&i.to_string(), ovsa::binary::sparse_random_with_rng(4096, 2048, &mut rng).unwrap()).unwrap();
+    }
+    let compressed = memory.compressed();
+    for _ in 0..10 {
+        let query = ovsa::binary::sparse_random_with_rng(4096, 2048, &mut rng).unwrap();
+        let expected = memory.query(&query, 1).unwrap().into_iter().next();
+        assert_eq!(memory.cleanup(&query).unwrap(), expected);
+        assert_eq!(compressed.cleanup(&CompressedBinary::compress(&query)).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_cyclic_shift_both_layouts() {
+    let mut rng = OvsaRng::seed_from_u64(13);
+    for n_active in [5, 500] {
+        let vector = ovsa::binary::sparse_random_with_rng(1000, n_active, &mut rng).unwrap();
+        let compressed = CompressedBinary::compress(&vector);
+        assert_eq!(compressed.is_packed(), n_active == 500);
+        for shift in [-70, 3, 64, 999] {
+            assert_eq!(compressed.cyclic_shift(shift).decompress(), ovsa::binary::cyclic_shift(&vector, shift));
+        }
+    }
+}
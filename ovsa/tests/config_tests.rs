@@ -0,0 +1,63 @@
+#![cfg(any(feature = "parallel", feature = "async"))]
+
+use std::sync::Arc;
+use ovsa::config;
+
+
+// a single test, since the pool is set for the whole process
+#[test]
+fn test_thread_pool_configuration() {
+    assert!(config::thread_pool().is_none());
+    assert_eq!(config::num_threads(), rayon::current_num_threads());
+    assert!(config::set_num_threads(0).is_err());
+
+    config::set_num_threads(2).unwrap();
+    assert_eq!(config::num_threads(), 2);
+    let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(3).thread_name(|i| format!("app-{}", i)).build().unwrap());
+    config::set_thread_pool(Arc::clone(&pool));
+    assert_eq!(config::num_threads(), 3);
+
+    #[cfg(feature = "async")]
+    {
+        // background tasks run on the crate's pool
+        let names = ovsa::nonblocking::map_batch(vec![0, 1, 2], |_| Ok(std::thread::current().name().unwrap_or_default().to_string()));
+        let names = block_on(names).unwrap();
+        assert!(names.iter().all(|name| name.starts_with("app-")), "{names:?}");
+    }
+
+    #[cfg(feature = "parallel")]
+    {
+        // the results do not depend on the pool
+        let labels: Vec<String> = (0..50).map(|i| format!("symbol{}", i)).collect();
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        let memory = ovsa::memory::ItemMemory::par_build_from_labels(1000, &labels, 50, 3).unwrap();
+        config::set_num_threads(1).unwrap();
+        assert_eq!(memory.get("symbol7"), ovsa::memory::ItemMemory::par_build_from_labels(1000, &labels, 50, 3).unwrap().get("symbol7"));
+    }
+
+    config::reset_thread_pool();
+    assert!(config::thread_pool().is_none());
+}
+
+
+#[cfg(feature = "async")]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct ThreadWaker(std::thread::Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+#![cfg(feature = "dense")]
+
+use ndarray::{Array1, array};
+use rand::SeedableRng;
+use ovsa::binary::{from_indices, hamming_distance, sparse_random_with_rng, xor};
+use ovsa::convert::{binary_to_bipolar, bipolar_to_binary, dense_to_binary, dense_to_bipolar, sparse_to_segmented};
+use ovsa::dense::{random_uniform_with_rng, similarity};
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_binary_bipolar_round_trip() {
+    let vec = from_indices(6, &[1, 4]).unwrap();
+    let bipolar = binary_to_bipolar(&vec);
+    assert_eq!(bipolar, array![1.0f32, -1.0, 1.0, 1.0, -1.0, 1.0]);
+    assert_eq!(bipolar_to_binary(&bipolar).unwrap(), vec);
+}
+
+
+#[test]
+fn test_bipolar_preserves_binding_and_distance() {
+    let mut rng = OvsaRng::seed_from_u64(7);
+    let a = sparse_random_with_rng(1000, 300, &mut rng).unwrap();
+    let b = sparse_random_with_rng(1000, 300, &mut rng).unwrap();
+
+    let product: Array1<f32> = binary_to_bipolar(&a) * binary_to_bipolar(&b);
+    assert_eq!(bipolar_to_binary(&product).unwrap(), xor(&a, &b).unwrap());
+
+    let expected = 1.0 - 2.0 * hamming_distance(&a, &b) as f32 / 1000.0;
+    assert!((similarity(&binary_to_bipolar(&a), &binary_to_bipolar(&b)) - expected).abs() < 1e-5);
+}
+
+
+#[test]
+fn test_dense_quantization_uses_signs() {
+    let vec = array![0.5f32, -2.0, 0.0, -0.1];
+    assert_eq!(dense_to_bipolar(&vec), array![1.0f32, -1.0, 1.0, -1.0]);
+    assert_eq!(dense_to_binary(&vec).unwrap().indices(), &[1, 3]);
+    assert!(bipolar_to_binary(&Array1::zeros(0)).is_err());
+}
+
+
+#[test]
+fn test_dense_quantization_keeps_similarity_order() {
+    let mut rng = OvsaRng::seed_from_u64(11);
+    let a = random_uniform_with_rng(4096, -1.0, 1.0, &mut rng).unwrap();
+    let noise = random_uniform_with_rng(4096, -1.0, 1.0, &mut rng).unwrap();
+    let near = &a + &(&noise * 0.3);
+    assert!(similarity(&dense_to_bipolar(&a), &dense_to_bipolar(&near)) > similarity(&dense_to_bipolar(&a), &dense_to_bipolar(&noise)));
+}
+
+
+#[test]
+fn test_sparse_to_segmented() {
+    // segments of length 4: [0, 4), [4, 8), [8, 12)
+    let vec = from_indices(12, &[1, 3, 10]).unwrap();
+    let segmented = sparse_to_segmented(&vec, 3).unwrap();
+    // the empty middle segment repeats the offset 3 of the last active entry before it
+    assert_eq!(segmented.indices(), &[1, 7, 10]);
+    assert_eq!(sparse_to_segmented(&segmented, 3).unwrap(), segmented);
+
+    let empty = from_indices(12, &[0]).unwrap();
+    assert_eq!(sparse_to_segmented(&empty, 4).unwrap().indices(), &[0, 3, 6, 9]);
+    assert!(sparse_to_segmented(&vec, 5).is_err());
+    assert!(sparse_to_segmented(&vec, 0).is_err());
+}
+
+
+#[test]
+fn test_harden_dense_accumulator() {
+    use ovsa::hypervector::Hardening;
+
+    let mut rng = OvsaRng::seed_from_u64(8);
+    let accumulator: Array1<f32> = Array1::from_iter((0..3000).map(|index| [-3.0, 1.0, 3.0][index % 3]));
+    let majority = ovsa::convert::harden_with_rng(&accumulator, Hardening::Majority, &mut rng).unwrap();
+    assert_eq!(majority, dense_to_binary(&accumulator).unwrap());
+    assert!(ovsa::convert::harden(&Array1::zeros(0), Hardening::Stochastic).is_err());
+
+    // negative entries become 1 with probability (1 - value / max_abs) / 2: always for -3, with 1/3 for 1, never for 3
+    let hardened = ovsa::convert::harden_with_rng(&accumulator, Hardening::Stochastic, &mut rng).unwrap();
+    assert!((0..3000).step_by(3).all(|index| hardened.get(index).is_some()));
+    assert!((2..3000).step_by(3).all(|index| hardened.get(index).is_none()));
+    let weak = (1..3000).step_by(3).filter(|&index| hardened.get(index).is_some()).count();
+    assert!((280..390).contains(&weak), "{}", weak);
+}
+
+
+#[test]
+fn test_phase_binarizer_measures_angles() {
+    use ovsa::convert::adapters::{Adapter, PhaseBinarizer};
+
+    let binarizer = PhaseBinarizer::new(4).unwrap();
+    let a = ovsa::fhrr::from_phases(&[0.1, 3.0, -2.0]);
+    let b = ovsa::fhrr::from_phases(&[0.1 + std::f64::consts::PI / 2.0, 3.0, -2.0]);
+    let (a, b) = (binarizer.adapt(&a).unwrap(), binarizer.adapt(&b).unwrap());
+    assert_eq!(a.dim(), binarizer.output_dimension(6));
+    // a quarter turn flips half of the 4 bits of the first phase
+    assert_eq!(hamming_distance(&a, &b), 2);
+    assert!(PhaseBinarizer::new(0).is_err());
+
+    // nearest neighbours of FPE encodings survive binarization
+    let mut rng = OvsaRng::seed_from_u64(10);
+    let encoder = ovsa::vfa::FractionalPowerEncoder::new_with_rng(2000, 1, 1.0, &mut rng).unwrap();
+    let points: Vec<_> = [0.0, 0.3, 1.0, 3.0].iter().map(|&x| binarizer.adapt(&encoder.encode(&[x]).unwrap()).unwrap()).collect();
+    let similarities: Vec<f64> = points.iter().map(|point| ovsa::binary::similarity(&points[0], point).unwrap()).collect();
+    assert_eq!(similarities[0], 1.0);
+    assert!(similarities.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", similarities);
+}
+
+
+#[test]
+fn test_binary_phasors_preserve_binding_and_similarity() {
+    use ovsa::convert::adapters::{Adapter, BinaryPhasors};
+
+    let mut rng = OvsaRng::seed_from_u64(11);
+    let a = sparse_random_with_rng(500, 250, &mut rng).unwrap();
+    let b = sparse_random_with_rng(500, 250, &mut rng).unwrap();
+    let adapted = BinaryPhasors.adapt_all(&[a.clone(), b.clone()]).unwrap();
+    assert_eq!(adapted[0].len(), BinaryPhasors.output_dimension(500));
+
+    let similarity = ovsa::fhrr::similarity(&adapted[0], &adapted[1]).unwrap();
+    let expected = 2.0 * ovsa::binary::similarity(&a, &b).unwrap() - 1.0;
+    assert!((similarity - expected).abs() < 1e-5);
+    let bound = BinaryPhasors.adapt(&xor(&a, &b).unwrap()).unwrap();
+    let fhrr_bound = ovsa::fhrr::bind(&adapted[0], &adapted[1]).unwrap();
+    assert!((ovsa::fhrr::similarity(&bound, &fhrr_bound).unwrap() - 1.0).abs() < 1e-5);
+}
+
+
+#[test]
+fn test_fhrr_hrr_round_trip() {
+    use ovsa::convert::{fhrr_to_hrr, hrr_to_fhrr};
+
+    let mut rng = OvsaRng::seed_from_u64(12);
+    // a power of two uses the FFT, other sizes the direct transform
+    for n_phases in [256, 101] {
+        let fhrr = ovsa::fhrr::conjugate_symmetric(&ovsa::fhrr::random_with_rng(2 * n_phases, &mut rng).unwrap()).unwrap();
+        let hrr = fhrr_to_hrr(&fhrr).unwrap();
+        assert_eq!(hrr.len(), n_phases);
+        assert!((ovsa::dense::norm(hrr.view()) - 1.0).abs() < 1e-4);
+        assert!((ovsa::fhrr::similarity(&hrr_to_fhrr(&hrr).unwrap(), &fhrr).unwrap() - 1.0).abs() < 1e-4);
+    }
+    assert!(hrr_to_fhrr(&Array1::zeros(0)).is_err());
+}
+
+
+#[test]
+fn test_fhrr_binding_is_hrr_convolution() {
+    use ovsa::convert::{fhrr_to_hrr, hrr_to_fhrr};
+
+    let mut rng = OvsaRng::seed_from_u64(13);
+    let a = ovsa::fhrr::conjugate_symmetric(&ovsa::fhrr::random_with_rng(128, &mut rng).unwrap()).unwrap();
+    // real vectors have conjugate symmetric spectra
+    let b = hrr_to_fhrr(&random_uniform_with_rng(64, -1.0, 1.0, &mut rng).unwrap()).unwrap();
+    assert!((ovsa::fhrr::similarity(&ovsa::fhrr::conjugate_symmetric(&b).unwrap(), &b).unwrap() - 1.0).abs() < 1e-4);
+
+    let bound = fhrr_to_hrr(&ovsa::fhrr::bind(&a, &b).unwrap()).unwrap();
+    let convolved = ovsa::dense::circular_convolution(&fhrr_to_hrr(&a).unwrap(), &fhrr_to_hrr(&b).unwrap());
+    assert!(bound.iter().zip(convolved.iter()).all(|(x, y)| (x - y).abs() < 1e-4));
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::binary::counters::Counters;
+use ovsa::binary::{from_indices, sparse_random_with_rng};
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_counters_match_consensus_sum() {
+    let mut rng = OvsaRng::seed_from_u64(2);
+    let vectors: Vec<_> = (0..5).map(|_| sparse_random_with_rng(500, 250, &mut rng).unwrap()).collect();
+
+    let mut counters: Counters = Counters::new(500).unwrap();
+    let mut active = vec![0u32; 500];
+    for vector in &vectors {
+        counters.add(vector).unwrap();
+        ovsa::binary::accumulate(&mut active, vector).unwrap();
+    }
+    assert_eq!(counters.n(), 5);
+    assert_eq!(counters, Counters::from_active_counts(&active, 5).unwrap());
+    // five vectors never tie
+    assert_eq!(counters.majority(), ovsa::binary::consensus_sum(&vectors).unwrap());
+
+    counters.remove(&vectors[4]).unwrap();
+    assert_eq!(counters.n(), 4);
+    assert!(counters.counts().iter().all(|count| count % 2 == 0));
+}
+
+
+#[test]
+fn test_counters_layout() {
+    let mut counters: Counters<i16> = Counters::new(4).unwrap();
+    counters.add(&from_indices(4, &[0, 2]).unwrap()).unwrap();
+    counters.add(&from_indices(4, &[2]).unwrap()).unwrap();
+    assert_eq!(counters.counts(), &[0, -2, 2, -2]);
+    assert_eq!(counters.dimension(), 4);
+
+    assert!(counters.add(&from_indices(5, &[0]).unwrap()).is_err());
+    let mut empty: Counters<i8> = Counters::new(4).unwrap();
+    assert!(empty.remove(&from_indices(4, &[0]).unwrap()).is_err());
+    assert!(Counters::<i32>::new(0).is_err());
+}
+
+
+#[test]
+fn test_narrow_counters_saturate() {
+    let vector = from_indices(3, &[1]).unwrap();
+    let mut counters: Counters<i8> = Counters::new(3).unwrap();
+    for _ in 0..200 {
+        counters.add(&vector).unwrap();
+    }
+    assert_eq!(counters.counts(), &[-128, 127, -128]);
+    assert_eq!(counters.n_saturated(), 3);
+    assert_eq!(counters.n(), 200);
+    assert_eq!(counters.majority(), vector);
+
+    let wide: Counters<i8> = Counters::from_active_counts(&[1000, 0], 1000).unwrap();
+    assert_eq!(wide.counts(), &[127, -128]);
+    assert!(Counters::<i8>::from_active_counts(&[3], 2).is_err());
+}
+
+
+#[test]
+fn test_counters_stochastic_hardening() {
+    use ovsa::hypervector::Hardening;
+
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let mut counters: Counters<i16> = Counters::new(2000).unwrap();
+    let always: Vec<usize> = (0..1000).collect();
+    counters.add(&from_indices(2000, &always).unwrap()).unwrap();
+    counters.add(&from_indices(2000, &always).unwrap()).unwrap();
+    counters.add(&from_indices(2000, &(0..1500).collect::<Vec<_>>()).unwrap()).unwrap();
+    counters.add(&from_indices(2000, &always).unwrap()).unwrap();
+
+    assert_eq!(counters.harden_with_rng(Hardening::Majority, &mut rng), counters.majority_with_rng(&mut OvsaRng::seed_from_u64(3)));
+    let hardened = counters.harden_with_rng(Hardening::Stochastic, &mut rng);
+    // entries of 1000..1500 are active in a quarter of the vectors, entries from 1500 in none
+    let quarter = hardened.indices().iter().filter(|&&index| (1000..1500).contains(&index)).count();
+    assert_eq!(hardened.indices().iter().filter(|&&index| index < 1000).count(), 1000);
+    assert!((80..170).contains(&quarter), "{}", quarter);
+    assert!(hardened.indices().iter().all(|&index| index < 1500));
+    assert_eq!(Counters::<i8>::new(10).unwrap().harden_with_rng(Hardening::Stochastic, &mut rng).dim(), 10);
+}
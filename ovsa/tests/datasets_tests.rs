@@ -0,0 +1,131 @@
+#![cfg(feature = "datasets")]
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use ovsa::datasets::Dataset;
+use ovsa::encode::LevelEncoder;
+use ovsa::memory::ItemMemory;
+
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("ovsa-datasets-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+
+#[test]
+fn test_load_isolet_and_encode() {
+    let dir = temp_dir("isolet");
+    fs::write(dir.join("isolet1+2+3+4.data"), "0.1, -0.5, 0.3, 1.\n-0.2, 0.4, 0.9, 2.\n").unwrap();
+    fs::write(dir.join("isolet5.data"), "0.1, -0.4, 0.3, 1.\n").unwrap();
+
+    let dataset = ovsa::datasets::load_isolet(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(dataset.train.len(), 2);
+    assert_eq!(dataset.train[1], (vec![-0.2, 0.4, 0.9], "2".to_string()));
+    assert_eq!(dataset.range(), (-0.5, 0.9));
+
+    let (min, max) = dataset.range();
+    let levels = LevelEncoder::new(min, max, ovsa::encode::levels(1000, 500, 10).unwrap()).unwrap();
+    let mut codebook = ItemMemory::new(1000).unwrap();
+    let (train, test) = dataset.encode(&levels, &mut codebook, 500).unwrap();
+    assert_eq!((train.len(), test.len()), (2, 1));
+    assert_eq!(codebook.len(), 3);
+}
+
+#[test]
+fn test_load_har() {
+    let dir = temp_dir("har");
+    fs::create_dir_all(dir.join("train")).unwrap();
+    fs::create_dir_all(dir.join("test")).unwrap();
+    fs::write(dir.join("activity_labels.txt"), "1 WALKING\n2 SITTING\n").unwrap();
+    fs::write(dir.join("train/X_train.txt"), "  2.5e-001 -1.0e-001\n  1.0e-001  3.0e-001\n").unwrap();
+    fs::write(dir.join("train/y_train.txt"), "1\n2\n").unwrap();
+    fs::write(dir.join("test/X_test.txt"), "  2.0e-001 -1.0e-001\n").unwrap();
+    fs::write(dir.join("test/y_test.txt"), "2\n").unwrap();
+
+    let dataset = ovsa::datasets::load_har(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(dataset.train[0], (vec![0.25, -0.1], "WALKING".to_string()));
+    assert_eq!(dataset.test[0].1, "SITTING");
+}
+
+#[test]
+fn test_load_har_rejects_missing_labels() {
+    let dir = temp_dir("har-truncated");
+    fs::create_dir_all(dir.join("train")).unwrap();
+    fs::create_dir_all(dir.join("test")).unwrap();
+    fs::write(dir.join("activity_labels.txt"), "1 WALKING\n").unwrap();
+    fs::write(dir.join("train/X_train.txt"), "1.0 2.0\n3.0 4.0\n").unwrap();
+    fs::write(dir.join("train/y_train.txt"), "1\n").unwrap();
+    fs::write(dir.join("test/X_test.txt"), "1.0 2.0\n").unwrap();
+    fs::write(dir.join("test/y_test.txt"), "1\n").unwrap();
+
+    let result = ovsa::datasets::load_har(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::InvalidFormat(_))));
+}
+
+#[test]
+fn test_download_skips_present_datasets() {
+    // the files are in place, so nothing is fetched
+    let dir = temp_dir("download");
+    let folder = Dataset::Har.folder(&dir);
+    fs::create_dir_all(folder.join("train")).unwrap();
+    fs::create_dir_all(folder.join("test")).unwrap();
+    fs::write(folder.join("train/X_train.txt"), "").unwrap();
+    fs::write(folder.join("test/X_test.txt"), "").unwrap();
+
+    let result = ovsa::datasets::download(Dataset::Har, &dir);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(result.unwrap(), folder);
+    assert_eq!(Dataset::Isolet.folder(&dir), dir);
+}
+
+#[test]
+fn test_load_languages() {
+    let dir = temp_dir("languages");
+    fs::create_dir_all(dir.join("training_texts")).unwrap();
+    fs::create_dir_all(dir.join("testing_texts")).unwrap();
+    fs::write(dir.join("training_texts/eng.txt"), "the quick brown fox\n").unwrap();
+    fs::write(dir.join("training_texts/deu.txt"), "der schnelle braune fuchs\n").unwrap();
+    fs::write(dir.join("testing_texts/eng_1_p.txt"), "the fox\n").unwrap();
+
+    let dataset = ovsa::datasets::load_languages(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(dataset.train[0], ("der schnelle braune fuchs".to_string(), "deu".to_string()));
+    assert_eq!(dataset.test[0], ("the fox".to_string(), "eng".to_string()));
+
+    let mut codebook = ItemMemory::new(1000).unwrap();
+    let (train, _) = dataset.encode(3, &mut codebook, 500).unwrap();
+    assert_eq!(train.len(), 2);
+}
+
+#[test]
+fn test_missing_files() {
+    let result = ovsa::datasets::load_isolet(temp_dir("missing"));
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::Io(_))));
+}
+
+#[test]
+fn test_encode_reports_progress_per_stage() {
+    use std::ops::ControlFlow;
+
+    let dataset = ovsa::datasets::TextDataset {
+        train: vec![("hello".to_string(), "en".to_string()), ("hallo".to_string(), "de".to_string())],
+        test: vec![("help".to_string(), "en".to_string())],
+    };
+    let mut codebook = ItemMemory::new(1000).unwrap();
+    let mut stages = Vec::new();
+    let (train, test) = dataset.encode_with_progress(3, &mut codebook, 500, |progress| {
+        stages.push((progress.stage, progress.completed, progress.total));
+        ControlFlow::Continue(())
+    }).unwrap();
+    assert_eq!((train.len(), test.len()), (2, 1));
+    assert_eq!(stages, vec![("encode_train", 1, 2), ("encode_train", 2, 2), ("encode_test", 1, 1)]);
+
+    let result = dataset.encode_with_progress(3, &mut codebook, 500, |_| ControlFlow::Break(()));
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::Cancelled)));
+}
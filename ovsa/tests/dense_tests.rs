@@ -0,0 +1,237 @@
+#![cfg(feature = "dense")]
+
+use ndarray::array;
+
+
+#[test]
+fn test_circular_convolution() {
+    let a = array![1.0f32, 2.0, 3.0];
+    let b = array![0.0f32, 1.0, 0.5];
+    // result[k] = sum over i + j = k (mod 3) of a[i] * b[j]
+    let expected = array![2.0f32 * 0.5 + 3.0 * 1.0, 1.0 * 1.0 + 3.0 * 0.5, 1.0 * 0.5 + 2.0 * 1.0];
+    assert_eq!(ovsa::dense::circular_convolution(&a, &b), expected);
+}
+
+#[test]
+fn test_bind_accumulate() {
+    let a = ovsa::dense::random_uniform(64, -1.0, 1.0).unwrap();
+    let b = ovsa::dense::random_uniform(64, -1.0, 1.0).unwrap();
+    let c = ovsa::dense::random_uniform(64, -1.0, 1.0).unwrap();
+
+    let mut accumulator = ndarray::Array1::<f32>::zeros(64);
+    ovsa::dense::bind_accumulate(&mut accumulator, &a, &b).unwrap();
+    ovsa::dense::bind_accumulate(&mut accumulator, &a, &c).unwrap();
+
+    let expected = ovsa::dense::circular_convolution(&a, &b) + ovsa::dense::circular_convolution(&a, &c);
+    assert!((accumulator - expected).iter().all(|difference| difference.abs() < 1e-5));
+}
+
+#[test]
+fn test_blocked_convolution_matches_definition() {
+    // a dimension spanning several blocks of the accumulator, the last one partial
+    let n = 2500;
+    let a = ovsa::dense::random_uniform(n, -1.0, 1.0).unwrap();
+    let b = ovsa::dense::random_uniform(n, -1.0, 1.0).unwrap();
+    let c = ovsa::dense::random_uniform(n, -1.0, 1.0).unwrap();
+    let convolve = |x: &ndarray::Array1<f32>, y: &ndarray::Array1<f32>| {
+        let mut result = ndarray::Array1::<f64>::zeros(n);
+        for i in 0..n {
+            for j in 0..n {
+                result[(i + j) % n] += x[i] as f64 * y[j] as f64;
+            }
+        }
+        result
+    };
+    let expected = convolve(&a, &b) + convolve(&b, &c);
+
+    let mut accumulator = ndarray::Array1::<f32>::zeros(n);
+    ovsa::dense::bind_accumulate(&mut accumulator, &a, &b).unwrap();
+    ovsa::dense::bind_accumulate(&mut accumulator, &b, &c).unwrap();
+    let bundled = ovsa::dense::bind_bundle(&[(&a, &b), (&b, &c)]).unwrap();
+    for k in 0..n {
+        assert!((accumulator[k] as f64 - expected[k]).abs() < 1e-2);
+        assert!((bundled[k] as f64 - expected[k]).abs() < 1e-2);
+    }
+}
+
+#[test]
+fn test_bind_accumulate_size_mismatch() {
+    let mut accumulator = ndarray::Array1::<f32>::zeros(3);
+    assert!(ovsa::dense::bind_accumulate(&mut accumulator, &array![1.0f32, 2.0], &array![1.0f32, 2.0]).is_err());
+}
+
+#[test]
+fn test_bind_bundle() {
+    let a = array![1.0f32, 0.0, 0.0];
+    let b = array![0.0f32, 1.0, 0.0];
+    let bundled = ovsa::dense::bind_bundle(&[(&a, &b), (&b, &b)]).unwrap();
+    assert_eq!(bundled, array![0.0f32, 1.0, 1.0]);
+    assert!(ovsa::dense::bind_bundle(&[]).is_err());
+    assert!(ovsa::dense::bind_bundle(&[(&a, &b), (&a, &array![1.0f32])]).is_err());
+}
+
+#[test]
+fn test_similarity() {
+    let a = array![1.0f32, 0.0, 1.0];
+    let b = array![1.0f32, 1.0, 0.0];
+    assert!((ovsa::dense::similarity(&a, &b) - 0.5).abs() < 1e-6);
+    assert_eq!(ovsa::dense::dot(a.view(), b.view()), 1.0);
+}
+
+#[test]
+fn test_dense_bundle_remove() {
+    let a = array![1.0, 2.0, 3.0];
+    let b = array![0.5, -1.0, 2.0];
+    let mut accumulator = ovsa::dense::superposition(&[a.clone(), b.clone()]).unwrap();
+    ovsa::dense::bundle_remove(&mut accumulator, &b).unwrap();
+    assert_eq!(accumulator, a);
+}
+
+#[test]
+fn test_to_binary_lsh_preserves_angles() {
+    let a = ovsa::dense::random_uniform(200, -1.0, 1.0).unwrap();
+    let noise = ovsa::dense::random_uniform(200, -1.0, 1.0).unwrap();
+    let b = &a + &(&noise * 0.5);
+
+    let expected = 1.0 - (ovsa::dense::similarity(&a, &b) as f64).acos() / std::f64::consts::PI;
+    let (sa, sb) = (ovsa::dense::to_binary_lsh(&a, 2048, 11).unwrap(), ovsa::dense::to_binary_lsh(&b, 2048, 11).unwrap());
+    assert_eq!(sa.dim(), 2048);
+    assert!((ovsa::binary::similarity(&sa, &sb).unwrap() - expected).abs() < 0.05);
+    assert_eq!(ovsa::dense::to_binary_lsh(&a, 2048, 11).unwrap(), sa);
+    assert_ne!(ovsa::dense::to_binary_lsh(&a, 2048, 12).unwrap(), sa);
+}
+
+#[test]
+fn test_large_superposition_is_bit_identical_to_serial_sum() {
+    use rand::SeedableRng;
+
+    let dimension = ovsa::binary::PARALLEL_DIMENSION_THRESHOLD + 123;
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(12);
+    let vectors: Vec<_> = (0..5).map(|_| ovsa::dense::random_uniform_with_rng(dimension, -1.0, 1.0, &mut rng).unwrap()).collect();
+
+    let mut expected = ndarray::Array1::<f32>::zeros(dimension);
+    for vector in &vectors {
+        expected += vector;
+    }
+    assert_eq!(ovsa::dense::superposition(&vectors).unwrap(), expected);
+}
+
+
+#[test]
+fn test_bind_graded() {
+    use rand::SeedableRng;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(4);
+    let a = ovsa::dense::random_uniform_with_rng(128, -1.0, 1.0, &mut rng).unwrap();
+    let b = ovsa::dense::random_uniform_with_rng(128, -1.0, 1.0, &mut rng).unwrap();
+    let close = |x: &ndarray::Array1<f32>, y: &ndarray::Array1<f32>| x.iter().zip(y).all(|(x, y)| (x - y).abs() < 1e-3);
+
+    assert!(close(&ovsa::dense::bind_graded(&a, &b, 0.0).unwrap(), &a));
+    assert!(close(&ovsa::dense::bind_graded(&a, &b, 1.0).unwrap(), &ovsa::dense::circular_convolution(&a, &b)));
+
+    // the similarity to the unbound vector falls as the strength grows
+    let similarities: Vec<f32> = [0.1, 0.5, 0.9].iter()
+        .map(|&alpha| ovsa::dense::similarity(&a, &ovsa::dense::bind_graded(&a, &b, alpha).unwrap()))
+        .collect();
+    assert!(similarities[0] > similarities[1] && similarities[1] > similarities[2], "{:?}", similarities);
+
+    assert!(ovsa::dense::bind_graded(&a, &b, -0.1).is_err());
+    assert!(ovsa::dense::bind_graded(&a, &array![1.0f32], 0.5).is_err());
+}
+
+
+#[test]
+fn test_make_unitary() {
+    use rand::SeedableRng;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(5);
+    let close = |x: &ndarray::Array1<f32>, y: &ndarray::Array1<f32>| x.iter().zip(y).all(|(x, y)| (x - y).abs() < 1e-4);
+    for dimension in [128, 100] {
+        let a = ovsa::dense::random_uniform_with_rng(dimension, -1.0, 1.0, &mut rng).unwrap();
+        let key = ovsa::dense::make_unitary(&ovsa::dense::random_uniform_with_rng(dimension, -1.0, 1.0, &mut rng).unwrap()).unwrap();
+
+        assert!((ovsa::dense::norm(key.view()) - 1.0).abs() < 1e-4);
+        assert!(close(&ovsa::dense::make_unitary(&key).unwrap(), &key));
+        // unbinding a unitary key is exact
+        let bound = ovsa::dense::circular_convolution(&a, &key);
+        assert!(close(&ovsa::dense::circular_correlation(&bound, &key), &a));
+        assert!((ovsa::dense::norm(bound.view()) - ovsa::dense::norm(a.view())).abs() < 1e-3);
+        // and the conversions to and from FHRR invert each other
+        let round_trip = ovsa::convert::fhrr_to_hrr(&ovsa::convert::hrr_to_fhrr(&key).unwrap()).unwrap();
+        assert!(close(&round_trip, &key));
+    }
+
+    assert!(ovsa::dense::make_unitary(&array![0.0f32, 0.0, 0.0, 0.0]).unwrap().iter().zip([1.0, 0.0, 0.0, 0.0]).all(|(x, y)| (x - y).abs() < 1e-6));
+    assert!(ovsa::dense::make_unitary(&ndarray::Array1::<f32>::zeros(0)).is_err());
+}
+
+
+#[test]
+fn test_best_shift() {
+    use rand::SeedableRng;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(6);
+    let vec = ovsa::dense::random_uniform_with_rng(64, -1.0, 1.0, &mut rng).unwrap();
+    let (shift, similarity) = ovsa::dense::best_shift(&vec, &ovsa::dense::cyclic_shift(&vec, -5)).unwrap();
+    assert_eq!(shift, 59);
+    assert!((similarity - 1.0).abs() < 1e-5);
+    assert!(ovsa::dense::best_shift(&vec, &array![1.0f32]).is_err());
+}
+
+
+#[test]
+fn test_cyclic_shift_2d() {
+    let grid = array![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0];
+    // two rows of three columns: shifting one column right and one row down
+    assert_eq!(ovsa::dense::cyclic_shift_2d(&grid, 2, 3, 1, 1).unwrap(), array![6.0f32, 4.0, 5.0, 3.0, 1.0, 2.0]);
+    assert_eq!(ovsa::dense::cyclic_shift_2d(&grid, 1, 6, 0, 2).unwrap(), ovsa::dense::cyclic_shift(&grid, 2));
+    assert!(ovsa::dense::cyclic_shift_2d(&grid, 4, 2, 1, 1).is_err());
+}
+
+
+#[test]
+fn test_reproject() {
+    use rand::SeedableRng;
+
+    let mut rng = ovsa::rng::OvsaRng::seed_from_u64(10);
+    let a = ovsa::dense::random_uniform_with_rng(1000, -1.0, 1.0, &mut rng).unwrap();
+    let noise = ovsa::dense::random_uniform_with_rng(1000, -1.0, 1.0, &mut rng).unwrap();
+    let b = &a + &noise;
+    let original = ovsa::dense::similarity(&a, &b);
+
+    let (a2, b2) = (ovsa::dense::reproject(&a, 500, 7).unwrap(), ovsa::dense::reproject(&b, 500, 7).unwrap());
+    assert_eq!(a2.len(), 500);
+    assert!((ovsa::dense::similarity(&a2, &b2) - original).abs() < 0.05);
+    assert_eq!(a2, ovsa::dense::reproject(&a, 500, 7).unwrap());
+    assert!(ovsa::dense::reproject(&a, 0, 7).is_err());
+}
+
+
+#[test]
+fn test_tree_superposition() {
+    use ovsa::hypervector::BundleStrategy;
+
+    let vectors: Vec<_> = (0..7).map(|i| array![i as f32, 1.0, -(i as f32)]).collect();
+    let flat = ovsa::dense::superposition_with_strategy(&vectors, BundleStrategy::Flat).unwrap();
+    assert_eq!(flat, array![21.0f32, 7.0, -21.0]);
+    assert_eq!(ovsa::dense::superposition_with_strategy(&vectors, BundleStrategy::Tree { fan_in: 2 }).unwrap(), flat);
+    assert!(ovsa::dense::superposition_with_strategy(&vectors, BundleStrategy::Tree { fan_in: 0 }).is_err());
+    assert!(ovsa::dense::superposition_with_strategy(&[], BundleStrategy::Tree { fan_in: 2 }).is_err());
+}
+
+#[test]
+fn test_cyclic_shift_inplace() {
+    let vec = array![1.0f32, 2.0, 3.0, 4.0, 5.0];
+    assert_eq!(ovsa::dense::cyclic_shift(&vec, 2), array![4.0f32, 5.0, 1.0, 2.0, 3.0]);
+    assert_eq!(ovsa::dense::cyclic_shift(&vec, -6), array![2.0f32, 3.0, 4.0, 5.0, 1.0]);
+    for shift in [-7, -1, 0, 3, 5, 12] {
+        let mut shifted = vec.clone();
+        ovsa::dense::cyclic_shift_inplace(&mut shifted, shift);
+        assert_eq!(shifted, ovsa::dense::cyclic_shift(&vec, shift));
+    }
+
+    // views that are not contiguous fall back to a copy
+    let mut strided = array![[1.0f32, 2.0], [3.0, 4.0], [5.0, 6.0]].column(0).to_owned();
+    ovsa::dense::cyclic_shift_inplace(&mut strided, 1);
+    assert_eq!(strided, array![5.0f32, 1.0, 3.0]);
+}
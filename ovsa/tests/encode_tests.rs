@@ -0,0 +1,304 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::encode::LevelEncoder;
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_ngrams_order_sensitive() {
+    let mut codebook = ItemMemory::new(1000).unwrap();
+    let abc = ovsa::encode::ngrams("abc", 3, &mut codebook, 500).unwrap();
+    let cba = ovsa::encode::ngrams("cba", 3, &mut codebook, 500).unwrap();
+    assert_eq!(codebook.len(), 3);
+    assert!(ovsa::binary::similarity(&abc, &cba).unwrap() < 0.6);
+}
+
+#[test]
+fn test_ngrams_with_rng_is_reproducible() {
+    let encode = |seed| {
+        let mut codebook = ItemMemory::new(1000).unwrap();
+        ovsa::encode::ngrams_with_rng("abcab", 2, &mut codebook, 500, &mut OvsaRng::seed_from_u64(seed)).unwrap()
+    };
+    assert_eq!(encode(4), encode(4));
+    assert_ne!(encode(4), encode(5));
+}
+
+#[test]
+fn test_ngrams_too_short() {
+    let mut codebook = ItemMemory::new(1000).unwrap();
+    assert!(matches!(ovsa::encode::ngrams("ab", 3, &mut codebook, 500), Err(ovsa::errors::OVSAError::EmptyVectorList)));
+    assert!(matches!(ovsa::encode::ngrams("ab", 0, &mut codebook, 500), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+    assert!(matches!(ovsa::encode::levels(100, 10, 0), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+}
+
+#[test]
+fn test_levels_similarity_decreases() {
+    let levels = ovsa::encode::levels_with_rng(1000, 100, 5, &mut OvsaRng::seed_from_u64(1)).unwrap();
+    for level in &levels {
+        assert_eq!(level.nnz(), 100);
+    }
+    let similarities: Vec<f64> = levels.iter().map(|level| ovsa::binary::similarity(&levels[0], level).unwrap()).collect();
+    assert!(similarities.windows(2).all(|pair| pair[0] > pair[1]));
+    // the extreme levels share no active entries
+    assert_eq!(ovsa::binary::hamming_distance(&levels[0], &levels[4]), 200);
+}
+
+#[test]
+fn test_level_encoder_clamps() {
+    let levels = ovsa::encode::levels(100, 10, 11).unwrap();
+    let encoder = LevelEncoder::new(0.0, 1.0, levels).unwrap();
+    assert_eq!(encoder.level_index(0.5), 5);
+    assert_eq!(encoder.level_index(-3.0), 0);
+    assert_eq!(encoder.level_index(3.0), 10);
+}
+
+#[test]
+fn test_record_recovers_field() {
+    let mut rng = OvsaRng::seed_from_u64(2);
+    let vectors: Vec<_> = (0..6).map(|_| ovsa::binary::sparse_random_with_rng(10000, 5000, &mut rng).unwrap()).collect();
+    let record = ovsa::encode::record(&[(&vectors[0], &vectors[1]), (&vectors[2], &vectors[3]), (&vectors[4], &vectors[5])]).unwrap();
+
+    let unbound = ovsa::binary::xor(&record, &vectors[2]).unwrap();
+    assert!(ovsa::binary::similarity(&unbound, &vectors[3]).unwrap() > 0.7);
+}
+
+#[test]
+fn test_hierarchy_encoder_reflects_taxonomy() {
+    let mut rng = OvsaRng::seed_from_u64(5);
+    let mut encoder = ovsa::encode::HierarchyEncoder::new(10000, 500, 0.3).unwrap();
+    let dog = encoder.encode_with_rng(&["animal", "mammal", "dog"], &mut rng).unwrap().clone();
+    let cat = encoder.encode_with_rng(&["animal", "mammal", "cat"], &mut rng).unwrap().clone();
+    let eagle = encoder.encode_with_rng(&["animal", "bird", "eagle"], &mut rng).unwrap().clone();
+    let oak = encoder.encode_with_rng(&["plant", "tree", "oak"], &mut rng).unwrap().clone();
+
+    assert_eq!(dog.nnz(), 500);
+    assert_eq!(encoder.nodes().len(), 9);
+    let siblings = ovsa::binary::similarity(&dog, &cat).unwrap();
+    let cousins = ovsa::binary::similarity(&dog, &eagle).unwrap();
+    let unrelated = ovsa::binary::similarity(&dog, &oak).unwrap();
+    assert!(siblings > cousins && cousins > unrelated, "{siblings} {cousins} {unrelated}");
+
+    let parent = encoder.nodes().get("animal/mammal").unwrap();
+    assert_eq!(parent.indices().iter().filter(|index| dog.indices().contains(index)).count(), 350);
+    assert!(matches!(ovsa::encode::HierarchyEncoder::new(10000, 500, 1.5), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+}
+
+#[test]
+fn test_features_with_roles_keeps_fields() {
+    let mut rng = OvsaRng::seed_from_u64(2);
+    let levels = LevelEncoder::new(0.0, 1.0, ovsa::encode::levels_with_rng(1000, 500, 4, &mut rng).unwrap()).unwrap();
+    let mut codebook = ItemMemory::new(1000).unwrap();
+    let record = ovsa::encode::features_with_roles(&[0.1, 0.9], &levels, &mut codebook, 500).unwrap();
+
+    assert_eq!(record.vector.dim(), 1000);
+    assert_eq!(record.fields.len(), 2);
+    assert_eq!(record.fields[1].role, "feature:1");
+    assert_eq!(&record.fields[1].role_vector, codebook.get("feature:1").unwrap());
+    assert_eq!(&record.fields[1].filler, levels.encode(0.9));
+}
+
+#[test]
+fn test_string_similarity_follows_edits() {
+    let mut rng = OvsaRng::seed_from_u64(11);
+    let mut encoder = ovsa::encode::string::StringEncoder::new(4000, 2000, 3).unwrap();
+    let color = encoder.encode_with_rng("color", &mut rng).unwrap();
+    let colour = encoder.encode_with_rng("colour", &mut rng).unwrap();
+    let colr = encoder.encode_with_rng("colr", &mut rng).unwrap();
+    let table = encoder.encode_with_rng("table", &mut rng).unwrap();
+
+    let insertion = ovsa::binary::similarity(&color, &colour).unwrap();
+    let deletion = ovsa::binary::similarity(&color, &colr).unwrap();
+    let unrelated = ovsa::binary::similarity(&color, &table).unwrap();
+    assert!(insertion > unrelated + 0.1, "{insertion} {unrelated}");
+    assert!(deletion > unrelated + 0.1, "{deletion} {unrelated}");
+    assert!(encoder.encode_with_rng("", &mut rng).is_err());
+}
+
+#[test]
+fn test_string_find_tolerates_typos() {
+    let mut rng = OvsaRng::seed_from_u64(12);
+    let mut encoder = ovsa::encode::string::StringEncoder::new(4000, 2000, 3).unwrap();
+    let index = encoder.index_with_rng(&["apple", "banana", "cherry", "grape", "lemon"], &mut rng).unwrap();
+    for (typo, label) in [("aple", "apple"), ("bananna", "banana"), ("chery", "cherry"), ("graep", "grape"), ("lemmon", "lemon")] {
+        let matches = encoder.find_with_rng(&index, typo, 1, &mut rng).unwrap();
+        assert_eq!(matches[0].0, label, "{typo}");
+    }
+}
+
+#[test]
+fn test_tokenizers() {
+    use ovsa::encode::text::Tokenizer;
+    assert_eq!(Tokenizer::Bytes.tokenize("hé"), ["0x68", "0xc3", "0xa9"]);
+    assert_eq!(Tokenizer::Chars.tokenize("hé"), ["h", "é"]);
+    assert_eq!(Tokenizer::Whitespace.tokenize(" the  cat sat "), ["the", "cat", "sat"]);
+    let comma = Tokenizer::custom(|text| text.split(',').map(str::to_string).collect());
+    assert_eq!(comma.tokenize("a,b"), ["a", "b"]);
+}
+
+#[test]
+fn test_text_encoder_hashes_unseen_tokens() {
+    use ovsa::encode::text::{TextEncoder, Tokenizer};
+    let mut rng = OvsaRng::seed_from_u64(13);
+    let mut encoder = TextEncoder::new(2000, 2, 1000, Tokenizer::Whitespace).unwrap().with_hashing(8).unwrap();
+    encoder.add_tokens_with_rng(&["the", "cat"], &mut rng).unwrap();
+    let a = encoder.encode_with_rng("the cat sat on the mat", &mut rng).unwrap();
+    encoder.encode_with_rng("a completely different sentence with many unseen words", &mut rng).unwrap();
+    let b = encoder.encode_with_rng("the cat sat on the mat", &mut rng).unwrap();
+
+    assert_eq!(encoder.codebook().len(), 2);
+    assert!(encoder.buckets().unwrap().len() <= 8);
+    assert!(ovsa::binary::similarity(&a, &b).unwrap() > 0.9);
+    assert!(encoder.encode_with_rng("cat", &mut rng).is_err());
+    assert!(TextEncoder::new(2000, 2, 1000, Tokenizer::Chars).unwrap().with_hashing(0).is_err());
+}
+
+#[test]
+fn test_text_encoder_grows_without_hashing() {
+    use ovsa::encode::text::{TextEncoder, Tokenizer};
+    let mut rng = OvsaRng::seed_from_u64(14);
+    let mut encoder = TextEncoder::new(2000, 3, 1000, Tokenizer::Chars).unwrap();
+    encoder.encode_with_rng("abcd", &mut rng).unwrap();
+    assert_eq!(encoder.codebook().len(), 4);
+    assert!(encoder.buckets().is_none());
+}
+
+#[test]
+fn test_text_encoder_tf_idf_weighting() {
+    use ovsa::encode::text::{TextEncoder, Tokenizer, Weighting};
+    let corpus = ["the cat sat", "the dog ran", "the bird flew"];
+    let encoder = TextEncoder::new(4000, 1, 2000, Tokenizer::Whitespace).unwrap();
+    let idf = encoder.idf_weights(&corpus);
+    assert!(idf["the"] < idf["cat"]);
+    assert_eq!(encoder.ngram_keys("the cat sat"), ["the", "cat", "sat"]);
+
+    // a document repeating an uninformative word is dominated by it under plain bundling
+    let document = "the the the the the cat sat";
+    let mut rng = OvsaRng::seed_from_u64(15);
+    let mut uniform = TextEncoder::new(4000, 1, 2000, Tokenizer::Whitespace).unwrap();
+    let plain = uniform.encode_with_rng(document, &mut rng).unwrap();
+    let cat = uniform.encode_with_rng("cat", &mut rng).unwrap();
+    let mut weighted = uniform.clone().with_weighting(Weighting::TfIdf { weights: idf, default: 1.0 });
+    let tf_idf = weighted.encode_with_rng(document, &mut rng).unwrap();
+
+    let plain_cat = ovsa::binary::similarity(&plain, &cat).unwrap();
+    let weighted_cat = ovsa::binary::similarity(&tf_idf, &cat).unwrap();
+    assert!(weighted_cat > plain_cat + 0.1, "{weighted_cat} {plain_cat}");
+}
+
+#[test]
+fn test_event_stream_horizon() {
+    use ovsa::encode::events::{EventStreamEncoder, TimeCoding};
+    let mut rng = OvsaRng::seed_from_u64(16);
+    let mut encoder = EventStreamEncoder::new_with_rng(2000, 1000, 10.0, 10, TimeCoding::Bins, &mut rng).unwrap();
+    encoder.push(0.0, "a").unwrap();
+    encoder.push(4.0, "b").unwrap();
+    encoder.push(12.0, "c").unwrap();
+    assert_eq!(encoder.len(), 2);
+    assert!(encoder.push(11.0, "d").is_err());
+    assert!(encoder.encode_with_rng(11.0, &mut rng).is_err());
+    encoder.encode_with_rng(30.0, &mut rng).unwrap_err();
+    assert!(encoder.is_empty());
+}
+
+#[test]
+fn test_event_stream_levels_tolerate_jitter() {
+    use ovsa::encode::events::{EventStreamEncoder, TimeCoding};
+    let mut rng = OvsaRng::seed_from_u64(17);
+    let mut encoder = EventStreamEncoder::new_with_rng(4000, 2000, 10.0, 20, TimeCoding::Levels, &mut rng).unwrap();
+    let mut encode = |events: &[(f64, &str)], now: f64| {
+        for &(time, symbol) in events {
+            encoder.push(time, symbol).unwrap();
+        }
+        let vector = encoder.encode_with_rng(now, &mut rng).unwrap();
+        encoder.expire(f64::INFINITY);
+        vector
+    };
+    let pattern = encode(&[(1.0, "x"), (3.0, "y"), (5.0, "z")], 6.0);
+    let jittered = encode(&[(1.5, "x"), (3.0, "y"), (5.5, "z")], 6.0);
+    let reordered = encode(&[(1.0, "z"), (3.0, "y"), (5.0, "x")], 6.0);
+
+    let close = ovsa::binary::similarity(&pattern, &jittered).unwrap();
+    let far = ovsa::binary::similarity(&pattern, &reordered).unwrap();
+    assert!(close > far + 0.1, "{close} {far}");
+}
+
+#[test]
+fn test_kmer_encoding() {
+    use ovsa::encode::bio::KmerEncoder;
+    let mut rng = OvsaRng::seed_from_u64(18);
+    let encoder = KmerEncoder::new_with_rng(2000, 1000, 3, &mut rng).unwrap();
+    assert!(matches!(KmerEncoder::new_with_rng(2000, 1000, 0, &mut rng), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+    assert_eq!(encoder.kmer("ACG").unwrap(), encoder.kmer("acg").unwrap());
+    assert_ne!(encoder.kmer("ACG").unwrap(), encoder.kmer("GCA").unwrap());
+    assert_eq!(encoder.kmer("UUU").unwrap(), encoder.kmer("TTT").unwrap());
+    assert!(encoder.kmer("ACGT").is_err());
+    assert!(encoder.encode_with_rng("ACNGT", &mut rng).is_err());
+    assert!(encoder.encode_with_rng("AC", &mut rng).is_err());
+}
+
+#[test]
+fn test_classify_reads() {
+    use rand::Rng;
+    use ovsa::encode::bio::{BASES, KmerEncoder};
+    let mut rng = OvsaRng::seed_from_u64(19);
+    let genomes: Vec<String> = (0..4).map(|_| (0..200).map(|_| BASES[rng.random_range(0..4)]).collect()).collect();
+    let references: Vec<(String, &str)> = genomes.iter().enumerate().map(|(i, genome)| (format!("genome{i}"), genome.as_str())).collect();
+    let references: Vec<(&str, &str)> = references.iter().map(|(label, genome)| (label.as_str(), *genome)).collect();
+
+    let encoder = KmerEncoder::new_with_rng(2000, 1000, 5, &mut rng).unwrap();
+    let index = encoder.reference_index_with_rng(&references, &mut rng).unwrap();
+    for (i, genome) in genomes.iter().enumerate() {
+        let read = &genome[60..140];
+        let reverse: String = read.chars().rev().map(|base| match base { 'A' => 'T', 'T' => 'A', 'C' => 'G', _ => 'C' }).collect();
+        for read in [read.to_string(), reverse] {
+            let (label, _) = encoder.classify_read_with_rng(&index, &read, &mut rng).unwrap().unwrap();
+            assert_eq!(label, format!("genome{i}"));
+        }
+    }
+}
+
+#[test]
+fn test_sparse_feature_encoder() {
+    use ovsa::encode::sparse::SparseFeatureEncoder;
+    let mut rng = OvsaRng::seed_from_u64(20);
+    let mut encoder = SparseFeatureEncoder::new_with_rng(4000, 2000, 100.0, 16, &mut rng).unwrap();
+    let spectrum = encoder.encode_with_rng(&[(1_000_000, 80.0), (1_200_345, 20.0), (7, 55.0)], &mut rng).unwrap();
+    let noisy = encoder.encode_with_rng(&[(1_000_000, 75.0), (1_200_345, 25.0), (7, 55.0)], &mut rng).unwrap();
+    let shifted = encoder.encode_with_rng(&[(1_000_001, 80.0), (1_200_346, 20.0), (8, 55.0)], &mut rng).unwrap();
+
+    assert_eq!(encoder.indices().len(), 6);
+    let close = ovsa::binary::similarity(&spectrum, &noisy).unwrap();
+    let far = ovsa::binary::similarity(&spectrum, &shifted).unwrap();
+    assert!(close > far + 0.2, "{close} {far}");
+    assert!(encoder.encode_with_rng(&[], &mut rng).is_err());
+    assert!(encoder.encode_with_rng(&[(1, f64::NAN)], &mut rng).is_err());
+    assert!(SparseFeatureEncoder::new_with_rng(100, 50, 0.0, 4, &mut rng).is_err());
+}
+
+#[cfg(feature = "dense")]
+#[test]
+fn test_occupancy_map() {
+    use ovsa::encode::occupancy::OccupancyMap;
+    let mut rng = OvsaRng::seed_from_u64(21);
+    let mut map = OccupancyMap::new_with_rng(2048, 1.0, &mut rng).unwrap();
+    for x in 0..5 {
+        map.observe(x as f64, 0.0, 0.9).unwrap();
+        map.observe(x as f64, 4.0, 0.1).unwrap();
+    }
+    assert!(map.occupancy(2.0, 0.0).unwrap() > 0.5);
+    assert!(map.occupancy(2.0, 4.0).unwrap() < -0.5);
+    assert!(map.occupancy(20.0, 20.0).unwrap().abs() < 0.2);
+    assert!(map.observe(0.0, 0.0, 1.5).is_err());
+
+    map.place_with_rng("cup", 1.0, 3.0, &mut rng).unwrap();
+    map.place_with_rng("key", 4.0, 1.0, &mut rng).unwrap();
+    assert_eq!(map.object_at(1.0, 3.0).unwrap().unwrap().0, "cup");
+    assert_eq!(map.object_at(4.0, 1.0).unwrap().unwrap().0, "key");
+
+    let (point, score) = map.locate("key", [0.0, 0.0], [5.0, 5.0], 11).unwrap().unwrap();
+    assert_eq!(point, [4.0, 1.0]);
+    assert!(score > 0.8, "{score}");
+    assert!(map.locate("lamp", [0.0, 0.0], [5.0, 5.0], 11).unwrap().is_none());
+}
@@ -0,0 +1,101 @@
+#![cfg(feature = "sparse")]
+
+use std::env;
+use std::fs;
+use rand::{Rng, SeedableRng};
+use ovsa::experiments::{DatasetConfig, EncoderConfig, ExperimentConfig, ModelConfig, TrainingConfig};
+use ovsa::rng::OvsaRng;
+
+
+fn numeric_config() -> ExperimentConfig {
+    let mut rng = OvsaRng::seed_from_u64(5);
+    let mut sample = |label: &str, center: f64| {
+        let features: Vec<f64> = (0..4).map(|_| center + rng.random_range(-1.0..1.0)).collect();
+        (features, label.to_string())
+    };
+    let train: Vec<_> = (0..20).flat_map(|_| [sample("low", 2.0), sample("high", 8.0)]).collect();
+
+    ExperimentConfig {
+        name: "numeric".to_string(),
+        seed: 3,
+        dataset: DatasetConfig::Numeric { train, test: vec![] },
+        encoder: EncoderConfig::Features { levels: 8 },
+        model: ModelConfig { dimension: 1000, density: 0.5 },
+        training: TrainingConfig { epochs: 2, test_fraction: 0.25 },
+        output: None,
+    }
+}
+
+
+#[test]
+fn test_run_numeric() {
+    let config = numeric_config();
+    let experiment = ovsa::experiments::run(&config).unwrap();
+    assert_eq!((experiment.report.n_train, experiment.report.n_test), (30, 10));
+    assert!(experiment.report.accuracy > 0.9);
+    assert_eq!(experiment.report.classes.len(), 2);
+    assert!(experiment.codebook.get("feature:3").is_some());
+    assert!(experiment.codebook.get("level:7").is_some());
+    assert_eq!(experiment.classifier.dimension(), 1000);
+
+    // the same config reproduces the same report
+    assert_eq!(ovsa::experiments::run(&config).unwrap().report, experiment.report);
+}
+
+
+#[test]
+fn test_run_text_from_json() {
+    let json = r#"{
+        "name": "languages",
+        "seed": 1,
+        "dataset": {"kind": "text",
+            "train": [["the cat sat on the mat", "en"], ["where is the dog", "en"], ["der hund ist hier", "de"], ["wo ist die katze", "de"]],
+            "test": [["the dog sat", "en"], ["die katze ist hier", "de"]]},
+        "encoder": {"kind": "ngrams", "n": 3},
+        "model": {"dimension": 2000}
+    }"#;
+    let config = ExperimentConfig::from_json(json).unwrap();
+    assert_eq!(config.model.density, 0.5);
+    assert_eq!(config.training, TrainingConfig::default());
+
+    let experiment = ovsa::experiments::run(&config).unwrap();
+    assert_eq!((experiment.report.n_train, experiment.report.n_test), (4, 2));
+    assert!(experiment.report.corrections.is_empty());
+    assert!(experiment.codebook.get("t").is_some());
+    assert_eq!(ExperimentConfig::from_json(&config.to_json()).unwrap(), config);
+}
+
+
+#[test]
+fn test_run_writes_artifacts() {
+    let dir = env::temp_dir().join(format!("ovsa-experiments-{}", std::process::id()));
+    let mut config = numeric_config();
+    config.output = Some(dir.clone());
+    let experiment = ovsa::experiments::run(&config).unwrap();
+
+    assert_eq!(ExperimentConfig::load(dir.join("config.json")).unwrap(), config);
+    let report: ovsa::experiments::ExperimentReport = serde_json::from_str(&fs::read_to_string(dir.join("report.json")).unwrap()).unwrap();
+    assert_eq!(report, experiment.report);
+    assert_eq!(ovsa::io::load_memory(dir.join("codebook.json")).unwrap().len(), experiment.codebook.len());
+    let metadata = ovsa::io::read_metadata(dir.join("classifier.json")).unwrap().unwrap();
+    assert_eq!(metadata.seed, Some(3));
+    assert_eq!(metadata.encoder["levels"], "8");
+    assert_eq!(ovsa::io::load_classifier(dir.join("classifier.json")).unwrap().prototypes().len(), 2);
+
+    // a dataset file is resolved like an inline dataset
+    fs::write(dir.join("dataset.json"), serde_json::to_string(&config.dataset).unwrap()).unwrap();
+    config.dataset = DatasetConfig::File { path: dir.join("dataset.json") };
+    config.output = None;
+    assert_eq!(ovsa::experiments::run(&config).unwrap().report, experiment.report);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+
+#[test]
+fn test_run_rejects_invalid_configs() {
+    let config = numeric_config();
+    assert!(ovsa::experiments::run(&ExperimentConfig { encoder: EncoderConfig::Ngrams { n: 3 }, ..config.clone() }).is_err());
+    assert!(ovsa::experiments::run(&ExperimentConfig { model: ModelConfig { dimension: 1000, density: 0.0 }, ..config.clone() }).is_err());
+    assert!(ovsa::experiments::run(&ExperimentConfig { dataset: DatasetConfig::File { path: "missing.json".into() }, ..config }).is_err());
+    assert!(ExperimentConfig::from_json(r#"{"name": "x"}"#).is_err());
+}
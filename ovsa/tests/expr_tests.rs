@@ -0,0 +1,107 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::expr::{Expr, expr};
+use ovsa::rng::OvsaRng;
+
+
+fn vectors(n: usize) -> Vec<sprs::CsVec<i8>> {
+    let mut rng = OvsaRng::seed_from_u64(6);
+    (0..n).map(|_| ovsa::binary::sparse_random_with_rng(500, 250, &mut rng).unwrap()).collect()
+}
+
+
+#[test]
+fn test_bind_permute_matches_eager() {
+    let v = vectors(3);
+    let lazy = expr(&v[0]).bind(&v[1]).permute(3).permute(-5).bind(&v[2]).eval().unwrap();
+
+    let bound = ovsa::binary::xor(&v[0], &v[1]).unwrap();
+    let eager = ovsa::binary::xor(&ovsa::binary::cyclic_shift(&bound, -2), &v[2]).unwrap();
+    assert_eq!(lazy, eager);
+}
+
+#[test]
+fn test_bundle_matches_eager() {
+    let v = vectors(4);
+    let lazy = expr(&v[0]).bundle(expr(&v[1]).bind(&v[2]).permute(7)).bundle(&v[3]).eval().unwrap();
+
+    let term = ovsa::binary::cyclic_shift(&ovsa::binary::xor(&v[1], &v[2]).unwrap(), 7);
+    let eager = ovsa::binary::consensus_sum(&[v[0].clone(), term, v[3].clone()]).unwrap();
+    assert_eq!(lazy, eager);
+}
+
+#[test]
+fn test_self_bind_cancels() {
+    let v = vectors(1);
+    assert_eq!(expr(&v[0]).bind(&v[0]).eval().unwrap().nnz(), 0);
+}
+
+#[test]
+fn test_bundle_extends_only_chained_bundles() {
+    let v = vectors(5);
+    let consensus = |vectors: &[&sprs::CsVec<i8>]| ovsa::binary::consensus_sum(&vectors.iter().map(|&vector| vector.clone()).collect::<Vec<_>>()).unwrap();
+
+    let chained = expr(&v[0]).bundle(&v[1]).bundle(&v[2]).bundle(&v[3]).bundle(&v[4]).eval().unwrap();
+    let nested = Expr::bundle_all(&v[..3]).bundle(&v[3]).bundle(&v[4]).eval().unwrap();
+    let flat = Expr::bundle_all(&v).eval().unwrap();
+
+    let inner = consensus(&[&v[0], &v[1], &v[2]]);
+    assert_eq!(chained, consensus(&[&v[0], &v[1], &v[2], &v[3], &v[4]]));
+    assert_eq!(flat, chained);
+    assert_eq!(nested, consensus(&[&inner, &v[3], &v[4]]));
+    assert_ne!(nested, chained);
+}
+
+#[test]
+fn test_empty_bind_and_bundle() {
+    let v = vectors(1);
+    let none: [&sprs::CsVec<i8>; 0] = [];
+    assert!(matches!(Expr::bundle_all(none).eval(), Err(ovsa::errors::OVSAError::EmptyVectorList)));
+    assert!(matches!(expr(&v[0]).bind(Expr::bind_all(none)).eval(), Err(ovsa::errors::OVSAError::EmptyVectorList)));
+    assert_eq!(Expr::bind_all([&v[0]]).eval().unwrap(), v[0]);
+}
+
+#[test]
+fn test_dimension_mismatch() {
+    let a = ovsa::binary::from_indices(10, &[1]).unwrap();
+    let b = ovsa::binary::from_indices(20, &[1]).unwrap();
+    assert!(matches!(expr(&a).bind(&b).eval(), Err(ovsa::errors::OVSAError::VectorSizeMismatch)));
+}
+
+
+#[test]
+fn test_structure_round_trips_and_materializes() {
+    use ovsa::expr::structure::Structure;
+
+    let labels = ["color", "shape", "size", "red", "circle", "big", "a", "b", "c"];
+    let memory = ovsa::memory::ItemMemory::build_from_labels_seeded(1000, &labels, 500, 7).unwrap();
+    let symbol = |label: &str| memory.get(label).unwrap();
+
+    let record = Structure::record([
+        ("color", Structure::symbol("red")),
+        ("shape", Structure::symbol("circle")),
+        ("size", Structure::symbol("big")),
+    ]);
+    let description = record.describe();
+    assert!(description.starts_with(r#"{"op":"record","fields":[{"role":"color","filler":{"op":"symbol","label":"red"}}"#), "{}", description);
+    assert_eq!(Structure::from_description(&description).unwrap(), record);
+    // an odd number of fields leaves no ties to break
+    let expected = ovsa::encode::record(&[(symbol("color"), symbol("red")), (symbol("shape"), symbol("circle")), (symbol("size"), symbol("big"))]).unwrap();
+    assert_eq!(record.materialize(&memory).unwrap(), expected);
+
+    let sequence = Structure::sequence(["a", "b", "c"].map(Structure::symbol));
+    let shifted: Vec<_> = ["a", "b", "c"].iter().enumerate().map(|(position, label)| ovsa::binary::cyclic_shift(symbol(label), position as isize)).collect();
+    assert_eq!(sequence.materialize(&memory).unwrap(), expr(&shifted[0]).bundle(&shifted[1]).bundle(&shifted[2]).eval().unwrap());
+
+    let tree = Structure::tree(Structure::symbol("a"), [Structure::symbol("b"), Structure::tree(Structure::symbol("c"), [Structure::symbol("a").bind(Structure::symbol("b"))])]);
+    assert_eq!(Structure::from_description(&tree.describe()).unwrap(), tree);
+    assert_eq!(tree.labels(), ["a", "b", "c"]);
+    let vector = tree.materialize(&memory).unwrap();
+    assert!(ovsa::binary::similarity(&vector, symbol("a")).unwrap() > 0.7);
+    assert!(ovsa::binary::similarity(&vector, &ovsa::binary::cyclic_shift(symbol("b"), 1)).unwrap() > 0.7);
+
+    assert!(matches!(Structure::symbol("missing").materialize(&memory), Err(ovsa::errors::OVSAError::InvalidFormat(_))));
+    assert!(matches!(Structure::sequence([]).materialize(&memory), Err(ovsa::errors::OVSAError::EmptyVectorList)));
+    assert!(Structure::from_description(r#"{"op":"unknown"}"#).is_err());
+}
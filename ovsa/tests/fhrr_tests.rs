@@ -0,0 +1,75 @@
+#![cfg(feature = "dense")]
+
+use std::f64::consts::PI;
+use rand::SeedableRng;
+use ovsa::fhrr;
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_phases_round_trip() {
+    let phases = vec![0.0, PI / 2.0, -PI / 3.0, PI];
+    let vector = fhrr::from_phases(&phases);
+    assert_eq!(vector.len(), 8);
+    for (a, b) in fhrr::phases(&vector).unwrap().iter().zip(&phases) {
+        assert!((a - b).abs() < 1e-6 || (a.abs() - PI).abs() < 1e-6 && (b.abs() - PI).abs() < 1e-6);
+    }
+    assert!(fhrr::phases(&ndarray::Array1::zeros(3)).is_err());
+    assert!(fhrr::random(7).is_err());
+}
+
+
+#[test]
+fn test_bind_unbind_is_exact() {
+    let mut rng = OvsaRng::seed_from_u64(1);
+    let a = fhrr::random_with_rng(2000, &mut rng).unwrap();
+    let b = fhrr::random_with_rng(2000, &mut rng).unwrap();
+
+    let bound = fhrr::bind(&a, &b).unwrap();
+    assert!(fhrr::similarity(&bound, &a).unwrap().abs() < 0.1);
+    assert!((fhrr::similarity(&fhrr::unbind(&bound, &b).unwrap(), &a).unwrap() - 1.0).abs() < 1e-4);
+    assert!((fhrr::similarity(&a, &a).unwrap() - 1.0).abs() < 1e-4);
+    assert!(fhrr::similarity(&a, &b).unwrap().abs() < 0.1);
+    assert!(fhrr::bind(&a, &fhrr::random_with_rng(20, &mut rng).unwrap()).is_err());
+}
+
+
+#[test]
+fn test_pow_is_fractional_binding() {
+    let mut rng = OvsaRng::seed_from_u64(2);
+    let base = fhrr::random_with_rng(1000, &mut rng).unwrap();
+    let root = fhrr::pow(&base, 0.5).unwrap();
+    assert!((fhrr::similarity(&fhrr::bind(&root, &root).unwrap(), &base).unwrap() - 1.0).abs() < 1e-4);
+    let cube = fhrr::pow(&base, 3.0).unwrap();
+    let repeated = fhrr::bind(&fhrr::bind(&base, &base).unwrap(), &base).unwrap();
+    assert!((fhrr::similarity(&cube, &repeated).unwrap() - 1.0).abs() < 1e-4);
+    assert!((fhrr::similarity(&fhrr::pow(&base, 0.0).unwrap(), &fhrr::from_phases(&[0.0; 500])).unwrap() - 1.0).abs() < 1e-6);
+    assert!(fhrr::pow(&base, f64::NAN).is_err());
+
+    // with frequencies within (-pi, pi], powers of a base vector are fractional power encodings
+    let frequencies: Vec<f64> = (0..500).map(|j| -3.0 + 6.0 * j as f64 / 500.0).collect();
+    let axis = fhrr::from_phases(&frequencies);
+    let encoded = fhrr::from_phases(&frequencies.iter().map(|theta| theta * 2.7).collect::<Vec<_>>());
+    assert!((fhrr::similarity(&fhrr::pow(&axis, 2.7).unwrap(), &encoded).unwrap() - 1.0).abs() < 1e-4);
+}
+
+
+#[test]
+fn test_slerp_blends_monotonically() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let a = fhrr::random_with_rng(2000, &mut rng).unwrap();
+    let b = fhrr::random_with_rng(2000, &mut rng).unwrap();
+
+    assert!((fhrr::similarity(&fhrr::slerp(&a, &b, 0.0).unwrap(), &a).unwrap() - 1.0).abs() < 1e-4);
+    assert!((fhrr::similarity(&fhrr::slerp(&a, &b, 1.0).unwrap(), &b).unwrap() - 1.0).abs() < 1e-4);
+    let steps: Vec<(f64, f64)> = [0.0, 0.25, 0.5, 0.75, 1.0].iter()
+        .map(|&t| {
+            let blend = fhrr::slerp(&a, &b, t).unwrap();
+            (fhrr::similarity(&blend, &a).unwrap(), fhrr::similarity(&blend, &b).unwrap())
+        })
+        .collect();
+    assert!(steps.windows(2).all(|pair| pair[1].0 < pair[0].0 && pair[1].1 > pair[0].1), "{:?}", steps);
+    // the midpoint is equally similar to both ends
+    assert!((steps[2].0 - steps[2].1).abs() < 0.02);
+    assert!(fhrr::slerp(&a, &fhrr::random_with_rng(20, &mut rng).unwrap(), 0.5).is_err());
+}
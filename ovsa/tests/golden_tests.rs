@@ -0,0 +1,29 @@
+#![cfg(feature = "sparse")]
+
+use ovsa::golden::{Draw, Generator, Output, expected, generate, verify};
+
+
+#[test]
+fn test_golden_vectors_reproduce() {
+    let report = verify().unwrap();
+    assert!(report.passed(), "{:?}", report.failures());
+    assert_eq!(report.checks.len(), 4 * Draw::all().len());
+}
+
+#[test]
+fn test_ovsa_rng_matches_its_algorithm() {
+    let pinned = Generator::OvsaRng.pinned();
+    assert!(Generator::PINNED.contains(&pinned));
+    for draw in Draw::all() {
+        assert_eq!(expected(Generator::OvsaRng, draw), expected(pinned, draw));
+        assert_eq!(generate(Generator::OvsaRng, draw).unwrap(), generate(pinned, draw).unwrap());
+    }
+}
+
+#[test]
+fn test_generators_differ() {
+    let words: Vec<Output> = Generator::PINNED.into_iter().map(|generator| expected(generator, Draw::Words)).collect();
+    assert_ne!(words[0], words[1]);
+    assert_ne!(words[1], words[2]);
+    assert_ne!(words[0], words[2]);
+}
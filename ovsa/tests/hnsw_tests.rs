@@ -0,0 +1,68 @@
+#![cfg(feature = "hnsw")]
+
+use rand::SeedableRng;
+use ovsa::memory::ItemMemory;
+use ovsa::memory::hnsw::{HnswItemMemory, HnswParams};
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_hnsw_recall_matches_exhaustive_search() {
+    let dimension = 512;
+    let mut rng = OvsaRng::seed_from_u64(12);
+    let mut exact = ItemMemory::new(dimension).unwrap();
+    let mut indexed = HnswItemMemory::new(dimension, HnswParams::default()).unwrap();
+    for i in 0..1000 {
+        let vector = ovsa::binary::sparse_random_with_rng(dimension, 256, &mut rng).unwrap();
+        exact.insert(&i.to_string(), vector.clone()).unwrap();
+        indexed.insert(&i.to_string(), vector).unwrap();
+    }
+    assert_eq!(indexed.len(), 1000);
+
+    // noisy copies of stored vectors are cleaned up to their source
+    for i in (0..1000).step_by(50) {
+        let noise = ovsa::binary::sparse_random_with_rng(dimension, 60, &mut rng).unwrap();
+        let query = ovsa::binary::xor(exact.get(&i.to_string()).unwrap(), &noise).unwrap();
+        assert_eq!(indexed.cleanup(&query).unwrap().unwrap().0, i.to_string());
+    }
+
+    // unrelated queries have no clear neighbours, which is the hardest case for the graph
+    indexed.set_ef_search(200);
+    let mut hits = 0;
+    for _ in 0..20 {
+        let query = ovsa::binary::sparse_random_with_rng(dimension, 256, &mut rng).unwrap();
+        let expected: Vec<String> = exact.query(&query, 10).unwrap().into_iter().map(|(label, _)| label).collect();
+        let found = indexed.query(&query, 10).unwrap();
+        assert_eq!(found.len(), 10);
+        hits += found.iter().filter(|(label, _)| expected.contains(label)).count();
+    }
+    assert!(hits as f64 / 200.0 > 0.9, "{hits}");
+}
+
+#[test]
+fn test_hnsw_finds_stored_vectors_and_replacements() {
+    let dimension = 256;
+    let mut rng = OvsaRng::seed_from_u64(13);
+    let mut memory = ItemMemory::new(dimension).unwrap();
+    for i in 0..300 {
+        memory.insert(&i.to_string(), ovsa::binary::sparse_random_with_rng(dimension, 128, &mut rng).unwrap()).unwrap();
+    }
+    let mut indexed = HnswItemMemory::from_memory(memory, HnswParams { m: 8, ..HnswParams::default() }).unwrap();
+
+    let replacement = ovsa::binary::sparse_random_with_rng(dimension, 128, &mut rng).unwrap();
+    indexed.insert("42", replacement.clone()).unwrap();
+    assert_eq!(indexed.len(), 300);
+    assert_eq!(indexed.cleanup(&replacement).unwrap().unwrap(), ("42".to_string(), 1.0));
+
+    for i in [0, 150, 299] {
+        let stored = indexed.memory().get(&i.to_string()).unwrap().clone();
+        assert_eq!(indexed.cleanup(&stored).unwrap().unwrap().0, i.to_string());
+    }
+}
+
+#[test]
+fn test_hnsw_empty_and_invalid() {
+    let indexed: HnswItemMemory = HnswItemMemory::new(10, HnswParams::default()).unwrap();
+    assert!(indexed.cleanup(&ovsa::binary::from_indices(10, &[1]).unwrap()).unwrap().is_none());
+    assert!(HnswItemMemory::<sprs::CsVec<i8>>::new(10, HnswParams { m: 1, ..HnswParams::default() }).is_err());
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "sparse")]
+
+#[cfg(feature = "dense")]
+use ndarray::array;
+use ovsa::inspect::{Inspect, Summary};
+
+
+#[test]
+fn test_binary_summary() {
+    let vec = ovsa::binary::from_indices(10, &[1, 3, 5]).unwrap();
+    assert_eq!(vec.summary_with(2), Summary::Binary { dimension: 10, n_active: 3, first_active: vec![1, 3] });
+    assert_eq!(vec.summary_with(2).to_string(), "binary hypervector: dimension 10, 3 active (density 0.3000), first active [1, 3] ...");
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_dense_summary() {
+    let vec = array![0.0f32, -4.0, 3.0, 1.0];
+    assert_eq!(vec.summary_with(2).to_string(), "dense hypervector: dimension 4, norm 5.0990, largest [1]=-4.0000 [2]=3.0000");
+}
+
+#[test]
+fn test_binary_diff() {
+    let vec1 = ovsa::binary::from_indices(10, &[1, 3, 5]).unwrap();
+    let vec2 = ovsa::binary::from_indices(10, &[3, 4, 5]).unwrap();
+    let diff = vec1.diff(&vec2).unwrap();
+    assert_eq!(diff.n_different, 2);
+    assert_eq!(diff.to_string(), "2 of 10 positions differ [1]: 1 != 0 [4]: 0 != 1");
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_dense_diff_truncated() {
+    let diff = array![1.0f32, 2.0, 3.0].diff_with(&array![0.0f32, 2.0, 0.0], 1).unwrap();
+    assert_eq!(diff.to_string(), "2 of 3 positions differ [0]: 1 != 0 ...");
+}
@@ -0,0 +1,186 @@
+#![cfg(feature = "sparse")]
+
+use std::env;
+use std::fs;
+use ovsa::errors::OVSAError;
+use ovsa::io::{ArtifactKind, Metadata};
+use ovsa::learn::CentroidClassifier;
+
+
+#[test]
+fn test_load_rejects_out_of_range_indices() {
+    let path = env::temp_dir().join(format!("ovsa-bad-memory-{}.json", std::process::id()));
+    fs::write(&path, r#"{"dimension": 10, "vectors": [{"label": "a", "indices": [12]}]}"#).unwrap();
+    let result = ovsa::io::load_memory(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(OVSAError::InvalidFormat(_))));
+}
+
+#[test]
+fn test_metadata_round_trip() {
+    let path = env::temp_dir().join(format!("ovsa-metadata-{}.json", std::process::id()));
+    let samples = vec![(ovsa::binary::from_indices(10, &[1, 2]).unwrap(), "a".to_string())];
+    let metadata = Metadata::new(ArtifactKind::Samples, 10).with_seed(5).with_encoder_parameter("ngram", 3);
+    ovsa::io::save_samples_with_metadata(&path, 10, &samples, metadata).unwrap();
+
+    let loaded = ovsa::io::read_metadata(&path).unwrap().unwrap();
+    let (_, loaded_samples) = ovsa::io::load_samples(&path).unwrap();
+    let as_memory = ovsa::io::load_memory(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.seed, Some(5));
+    assert_eq!(loaded.density, Some(0.2));
+    assert_eq!(loaded.encoder.get("ngram").map(String::as_str), Some("3"));
+    assert_eq!(loaded_samples, samples);
+    assert!(matches!(as_memory, Err(OVSAError::IncompatibleArtifact(_))));
+}
+
+#[test]
+fn test_files_without_metadata_still_load() {
+    let path = env::temp_dir().join(format!("ovsa-legacy-memory-{}.json", std::process::id()));
+    fs::write(&path, r#"{"dimension": 10, "vectors": [{"label": "a", "indices": [1, 4]}]}"#).unwrap();
+    let metadata = ovsa::io::read_metadata(&path).unwrap();
+    let memory = ovsa::io::load_memory(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(metadata.is_none());
+    assert_eq!(memory.len(), 1);
+}
+
+#[test]
+fn test_metadata_compatibility() {
+    let samples = Metadata::new(ArtifactKind::Samples, 100).with_encoder_parameter("ngram", 3);
+    assert!(samples.ensure_compatible(&Metadata::new(ArtifactKind::Samples, 100)).is_ok());
+    assert!(samples.ensure_compatible(&Metadata::new(ArtifactKind::Samples, 100).with_encoder_parameter("ngram", 3).with_seed(1)).is_ok());
+    for expected in [
+        Metadata::new(ArtifactKind::Memory, 100),
+        Metadata::new(ArtifactKind::Samples, 200),
+        Metadata::new(ArtifactKind::Samples, 100).with_encoder_parameter("ngram", 4),
+    ] {
+        assert!(matches!(samples.ensure_compatible(&expected), Err(OVSAError::IncompatibleArtifact(_))));
+    }
+}
+
+#[test]
+fn test_loaders_check_expected_metadata() {
+    let path = env::temp_dir().join(format!("ovsa-expecting-{}.json", std::process::id()));
+    let samples = vec![(ovsa::binary::from_indices(10, &[1, 2]).unwrap(), "a".to_string())];
+    ovsa::io::save_samples_with_metadata(&path, 10, &samples, Metadata::new(ArtifactKind::Samples, 10).with_seed(5).with_encoder_parameter("ngram", 3)).unwrap();
+
+    let matching = ovsa::io::load_samples_expecting(&path, &Metadata::new(ArtifactKind::Samples, 10).with_encoder_parameter("ngram", 3));
+    let other_encoder = ovsa::io::load_samples_expecting(&path, &Metadata::new(ArtifactKind::Samples, 10).with_encoder_parameter("ngram", 4));
+    let other_seed = ovsa::io::load_samples_expecting(&path, &Metadata::new(ArtifactKind::Samples, 10).with_seed(6));
+    let other_dimension = ovsa::io::load_samples_expecting(&path, &Metadata::new(ArtifactKind::Samples, 20));
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(matching.unwrap().1, samples);
+    for result in [other_encoder, other_seed, other_dimension] {
+        assert!(matches!(result, Err(OVSAError::IncompatibleArtifact(_))));
+    }
+}
+
+#[test]
+fn test_memory_and_classifier_loaders_check_expected_metadata() {
+    let path = env::temp_dir().join(format!("ovsa-expecting-classifier-{}.json", std::process::id()));
+    let mut classifier = CentroidClassifier::new(10).unwrap();
+    classifier.fit(&[(ovsa::binary::from_indices(10, &[1, 2]).unwrap(), "a".to_string())]).unwrap();
+    ovsa::io::save_classifier_with_metadata(&path, &classifier, Metadata::new(ArtifactKind::Classifier, 10).with_encoder_parameter("levels", 8)).unwrap();
+    let matching = ovsa::io::load_classifier_expecting(&path, &Metadata::new(ArtifactKind::Classifier, 10).with_encoder_parameter("levels", 8));
+    let mismatching = ovsa::io::load_classifier_expecting(&path, &Metadata::new(ArtifactKind::Classifier, 10).with_encoder_parameter("levels", 16));
+
+    fs::write(&path, r#"{"dimension": 10, "vectors": [{"label": "a", "indices": [1, 4]}]}"#).unwrap();
+    let legacy = ovsa::io::load_memory_expecting(&path, &Metadata::new(ArtifactKind::Memory, 10).with_seed(1));
+    let legacy_other_dimension = ovsa::io::load_memory_expecting(&path, &Metadata::new(ArtifactKind::Memory, 100));
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(matching.unwrap().prototypes().get("a"), classifier.prototypes().get("a"));
+    assert!(matches!(mismatching, Err(OVSAError::IncompatibleArtifact(_))));
+    assert_eq!(legacy.unwrap().len(), 1);
+    assert!(matches!(legacy_other_dimension, Err(OVSAError::IncompatibleArtifact(_))));
+}
+
+#[test]
+fn test_version_1_files_are_migrated() {
+    let path = env::temp_dir().join(format!("ovsa-version-1-{}.json", std::process::id()));
+    fs::write(&path, r#"{"dimension": 10, "classes": [{"label": "a", "n": 1, "counts": [0, 1, 1, 0, 0, 0, 0, 0, 0, 0]}]}"#).unwrap();
+    assert_eq!(ovsa::io::format_version(&path).unwrap(), 1);
+    let classifier = ovsa::io::load_classifier(&path).unwrap();
+    assert_eq!(classifier.predict(&ovsa::binary::from_indices(10, &[1, 2]).unwrap()).unwrap().unwrap().0, "a");
+
+    assert_eq!(ovsa::io::migrate_file(&path).unwrap(), 1);
+    let version = ovsa::io::format_version(&path).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let migrated = ovsa::io::load_classifier(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(version, ovsa::io::FORMAT_VERSION);
+    assert_eq!(json["classes"][0]["counts"], serde_json::json!([[1, 1], [2, 1]]));
+    assert_eq!(migrated.prototypes().get("a"), classifier.prototypes().get("a"));
+}
+
+#[test]
+fn test_migrate_rejects_newer_versions() {
+    let json = serde_json::json!({"format_version": ovsa::io::FORMAT_VERSION + 1, "dimension": 10, "vectors": []});
+    assert!(matches!(ovsa::io::migrate(json), Err(OVSAError::IncompatibleArtifact(_))));
+    let json = serde_json::json!({"format_version": "two", "dimension": 10, "vectors": []});
+    assert!(matches!(ovsa::io::migrate(json), Err(OVSAError::InvalidFormat(_))));
+}
+
+#[test]
+fn test_version_0_files_are_rejected() {
+    let json = serde_json::json!({"format_version": 0, "dimension": 10, "vectors": []});
+    assert!(matches!(ovsa::io::migrate(json), Err(OVSAError::InvalidFormat(_))));
+
+    let path = env::temp_dir().join(format!("ovsa-version-0-{}.json", std::process::id()));
+    fs::write(&path, r#"{"format_version": 0, "dimension": 10, "classes": []}"#).unwrap();
+    let results = (ovsa::io::load_classifier(&path).err(), ovsa::io::load_memory(&path).err(), ovsa::io::format_version(&path).err());
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(results, (Some(OVSAError::InvalidFormat(_)), Some(OVSAError::InvalidFormat(_)), Some(OVSAError::InvalidFormat(_)))));
+}
+
+#[test]
+fn test_saved_files_have_current_version() {
+    let path = env::temp_dir().join(format!("ovsa-current-version-{}.json", std::process::id()));
+    ovsa::io::save_memory(&path, &ovsa::memory::ItemMemory::new(10).unwrap()).unwrap();
+    let version = ovsa::io::format_version(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(version, ovsa::io::FORMAT_VERSION);
+}
+
+#[test]
+fn test_classifier_counts_are_stored_sparsely() {
+    let path = env::temp_dir().join(format!("ovsa-sparse-counts-{}.json", std::process::id()));
+    let mut classifier = CentroidClassifier::new(1000).unwrap();
+    classifier.fit(&[(ovsa::binary::from_indices(1000, &[3, 700]).unwrap(), "a".to_string())]).unwrap();
+    ovsa::io::save_classifier(&path, &classifier).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let loaded = ovsa::io::load_classifier(&path).unwrap();
+
+    fs::write(&path, r#"{"format_version": 2, "dimension": 10, "classes": [{"label": "a", "n": 1, "counts": [[12, 1]]}]}"#).unwrap();
+    let out_of_range = ovsa::io::load_classifier(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(json["classes"][0]["counts"], serde_json::json!([[3, 1], [700, 1]]));
+    assert_eq!(loaded.counts("a"), classifier.counts("a"));
+    assert!(matches!(out_of_range, Err(OVSAError::InvalidFormat(_))));
+}
+
+#[test]
+fn test_failed_save_keeps_the_previous_file() {
+    let directory = env::temp_dir().join(format!("ovsa-atomic-save-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("memory.json");
+    let mut memory = ovsa::memory::ItemMemory::new(10).unwrap();
+    memory.insert("a", ovsa::binary::from_indices(10, &[1, 2]).unwrap()).unwrap();
+    ovsa::io::save_memory(&path, &memory).unwrap();
+
+    // a directory in place of the temporary file makes the next save fail before the target is touched
+    fs::create_dir(directory.join(".memory.json.tmp")).unwrap();
+    memory.insert("b", ovsa::binary::from_indices(10, &[3, 4]).unwrap()).unwrap();
+    let failed = ovsa::io::save_memory(&path, &memory);
+    let loaded = ovsa::io::load_memory(&path).unwrap();
+    let files = fs::read_dir(&directory).unwrap().count();
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert!(matches!(failed, Err(OVSAError::Io(_))));
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(files, 2);
+}
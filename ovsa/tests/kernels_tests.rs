@@ -0,0 +1,56 @@
+#![cfg(feature = "sparse")]
+
+use rand::{Rng, SeedableRng};
+use ovsa::binary::kernels::{self, Isa};
+use ovsa::rng::OvsaRng;
+
+
+// a single test, since the instruction set is selected for the whole process
+#[test]
+fn test_kernels_agree_on_every_supported_isa() {
+    let mut rng = OvsaRng::seed_from_u64(14);
+    let dimension: usize = 1000;
+    let n_words = dimension.div_ceil(64);
+    let last_mask = (1u64 << (dimension % 64)) - 1;
+    let random_words = |rng: &mut OvsaRng| -> Vec<u64> {
+        let mut words: Vec<u64> = (0..n_words).map(|_| rng.random()).collect();
+        words[n_words - 1] &= last_mask;
+        words
+    };
+    let (a, b) = (random_words(&mut rng), random_words(&mut rng));
+
+    let popcount: usize = a.iter().map(|word| word.count_ones() as usize).sum();
+    let distance: usize = a.iter().zip(&b).map(|(a, b)| (a ^ b).count_ones() as usize).sum();
+    let overlap: usize = a.iter().zip(&b).map(|(a, b)| (a & b).count_ones() as usize).sum();
+    let xor: Vec<u64> = a.iter().zip(&b).map(|(a, b)| a ^ b).collect();
+    let counts: Vec<u32> = (0..dimension).map(|index| ((a[index / 64] >> (index % 64)) & 1) as u32 + ((b[index / 64] >> (index % 64)) & 1) as u32).collect();
+
+    assert!(kernels::detected_isa().is_supported());
+    for isa in Isa::ALL {
+        if !isa.is_supported() {
+            assert!(kernels::set_isa(Some(isa)).is_err());
+            continue;
+        }
+        kernels::set_isa(Some(isa)).unwrap();
+        assert_eq!(kernels::isa(), isa);
+
+        assert_eq!(kernels::popcount(&a), popcount, "{isa:?}");
+        assert_eq!(kernels::xor_popcount(&a, &b), distance, "{isa:?}");
+        assert_eq!(kernels::and_popcount(&a, &b), overlap, "{isa:?}");
+        let mut bound = a.clone();
+        kernels::xor_into(&mut bound, &b);
+        assert_eq!(bound, xor, "{isa:?}");
+        let mut accumulated = vec![0u32; dimension];
+        kernels::accumulate(&mut accumulated, &a);
+        kernels::accumulate(&mut accumulated, &b);
+        assert_eq!(accumulated, counts, "{isa:?}");
+
+        // the packed matrix runs on the selected kernels
+        let vector = ovsa::binary::matrix::unpack(dimension, &a);
+        let matrix = ovsa::binary::matrix::HvMatrix::from_rows(dimension, [&vector, &vector]).unwrap();
+        assert_eq!(matrix.popcounts(), vec![popcount; 2]);
+    }
+
+    kernels::set_isa(None).unwrap();
+    assert_eq!(kernels::isa(), kernels::detected_isa());
+}
@@ -0,0 +1,853 @@
+#![cfg(feature = "sparse")]
+
+use std::env;
+use std::fs;
+use rand::SeedableRng;
+use ovsa::learn::CentroidClassifier;
+use ovsa::rng::OvsaRng;
+
+
+fn noisy(vector: &sprs::CsVec<i8>, rng: &mut OvsaRng) -> sprs::CsVec<i8> {
+    let noise = ovsa::binary::sparse_random_with_rng(vector.dim(), vector.dim() / 10, rng).unwrap();
+    ovsa::binary::xor(vector, &noise).unwrap()
+}
+
+
+#[test]
+fn test_centroid_classifier() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let a = ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap();
+    let b = ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap();
+
+    let samples: Vec<_> = (0..5).flat_map(|_| [(noisy(&a, &mut rng), "a".to_string()), (noisy(&b, &mut rng), "b".to_string())]).collect();
+    let mut classifier = CentroidClassifier::new(1000).unwrap();
+    classifier.fit_with_rng(&samples, &mut rng).unwrap();
+
+    assert_eq!(classifier.counts("a").unwrap().1, 5);
+    assert_eq!(classifier.predict(&noisy(&a, &mut rng)).unwrap().unwrap().0, "a");
+    assert_eq!(classifier.predict(&noisy(&b, &mut rng)).unwrap().unwrap().0, "b");
+}
+
+#[test]
+fn test_classifier_stochastic_hardening() {
+    use ovsa::hypervector::Hardening;
+
+    let mut rng = OvsaRng::seed_from_u64(4);
+    let a = ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap();
+    let b = ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap();
+
+    let samples: Vec<_> = (0..3).flat_map(|_| [(noisy(&a, &mut rng), "a".to_string()), (noisy(&b, &mut rng), "b".to_string())]).collect();
+    let mut classifier = CentroidClassifier::new(1000).unwrap().with_hardening(Hardening::Stochastic);
+    assert_eq!(CentroidClassifier::new(1000).unwrap().hardening(), Hardening::Majority);
+    classifier.fit_with_rng(&samples, &mut rng).unwrap();
+
+    assert_eq!(classifier.predict(&noisy(&a, &mut rng)).unwrap().unwrap().0, "a");
+    assert_eq!(classifier.predict(&noisy(&b, &mut rng)).unwrap().unwrap().0, "b");
+    assert_eq!(classifier.reproject(500, 7).unwrap().hardening(), Hardening::Stochastic);
+}
+
+#[test]
+fn test_classifier_round_trip() {
+    let mut rng = OvsaRng::seed_from_u64(4);
+    let samples: Vec<_> = (0..3).map(|i| (ovsa::binary::sparse_random_with_rng(100, 50, &mut rng).unwrap(), i.to_string())).collect();
+    let mut classifier = CentroidClassifier::new(100).unwrap();
+    classifier.fit(&samples).unwrap();
+
+    let path = env::temp_dir().join(format!("ovsa-classifier-{}.json", std::process::id()));
+    ovsa::io::save_classifier(&path, &classifier).unwrap();
+    let loaded = ovsa::io::load_classifier(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    for (label, prototype) in classifier.prototypes().iter() {
+        assert_eq!(loaded.prototypes().get(label).unwrap(), prototype);
+    }
+}
+
+#[test]
+fn test_classifier_memory_usage() {
+    let mut classifier = CentroidClassifier::new(100).unwrap();
+    classifier.fit(&[(ovsa::binary::from_indices(100, &[1, 2]).unwrap(), "a".to_string())]).unwrap();
+    let usage = classifier.memory_usage();
+    assert_eq!(usage.n_vectors, 1);
+    assert!(usage.auxiliary_bytes >= 100 * 4);
+}
+
+#[test]
+fn test_classifier_file_is_not_a_memory() {
+    let mut classifier = CentroidClassifier::new(10).unwrap();
+    classifier.fit(&[(ovsa::binary::from_indices(10, &[1, 2]).unwrap(), "a".to_string())]).unwrap();
+    let path = env::temp_dir().join(format!("ovsa-classifier-kind-{}.json", std::process::id()));
+    ovsa::io::save_classifier(&path, &classifier).unwrap();
+    let result = ovsa::io::load_memory(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::IncompatibleArtifact(_))));
+}
+
+#[test]
+fn test_fit_reports_progress_and_stops_early() {
+    use std::ops::ControlFlow;
+
+    let samples: Vec<_> = (0..4).map(|i| (ovsa::binary::from_indices(10, &[i, i + 1]).unwrap(), (i % 2).to_string())).collect();
+    let mut reported = Vec::new();
+    let mut classifier = CentroidClassifier::new(10).unwrap();
+    classifier.fit_with_progress(&samples, |progress| {
+        reported.push((progress.completed, progress.total));
+        ControlFlow::Continue(())
+    }).unwrap();
+    assert_eq!(reported, vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
+
+    let mut stopped = CentroidClassifier::new(10).unwrap();
+    let result = stopped.fit_with_progress(&samples, |progress| if progress.completed == 1 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) });
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::Cancelled)));
+    // the samples seen before stopping are kept
+    assert_eq!(stopped.counts("0").unwrap().1, 1);
+    assert!(stopped.counts("1").is_none());
+    assert_eq!(stopped.prototypes().len(), 1);
+}
+
+#[test]
+fn test_callbacks_may_use_the_global_rng() {
+    use std::ops::ControlFlow;
+
+    let samples: Vec<_> = (0..6).map(|i| (ovsa::binary::from_indices(10, &[i, i + 1]).unwrap(), (i % 2).to_string())).collect();
+    let mut classifier = CentroidClassifier::new(10).unwrap();
+    classifier.fit_with_progress(&samples, |_| {
+        ovsa::binary::sparse_random(10, 2).unwrap();
+        ControlFlow::Continue(())
+    }).unwrap();
+    assert_eq!(classifier.prototypes().len(), 2);
+
+    let evaluations = ovsa::learn::evaluation::cross_validate(&samples, 2, |train| {
+        let mut classifier = CentroidClassifier::new(10)?;
+        classifier.fit(train)?;
+        Ok(classifier)
+    }).unwrap();
+    assert_eq!(evaluations.len(), 2);
+}
+
+#[test]
+fn test_cancellation_token_stops_training() {
+    use std::ops::ControlFlow;
+
+    let samples: Vec<_> = (0..4).map(|i| (ovsa::binary::from_indices(10, &[i]).unwrap(), i.to_string())).collect();
+    let token = ovsa::progress::CancellationToken::new();
+    let mut classifier = CentroidClassifier::new(10).unwrap();
+    classifier.fit_with_progress(&samples, token.callback()).unwrap();
+    assert_eq!(classifier.prototypes().len(), 4);
+
+    // cancelling from within the guarded callback stands in for another thread cancelling
+    let mut seen = 0;
+    let canceller = token.clone();
+    let mut stopped = CentroidClassifier::new(10).unwrap();
+    let result = stopped.fit_with_progress(&samples, token.guard(|_| {
+        seen += 1;
+        canceller.cancel();
+        ControlFlow::Continue(())
+    }));
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::Cancelled)));
+    assert_eq!(seen, 1);
+    assert_eq!(stopped.prototypes().len(), 2);
+    assert!(token.check().is_err());
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_parallel_fit_is_bit_identical_to_serial() {
+    let mut rng = OvsaRng::seed_from_u64(13);
+    // an even number of samples per class makes prototype ties, which both paths must break identically
+    let samples: Vec<_> = (0..40).map(|i| (ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap(), (i % 5).to_string())).collect();
+
+    let mut serial = CentroidClassifier::new(1000).unwrap();
+    serial.fit_with_rng(&samples, &mut OvsaRng::seed_from_u64(14)).unwrap();
+    let mut parallel = CentroidClassifier::new(1000).unwrap();
+    parallel.par_fit_with_rng(&samples, &mut OvsaRng::seed_from_u64(14)).unwrap();
+
+    for (label, prototype) in serial.prototypes().iter() {
+        assert_eq!(parallel.prototypes().get(label).unwrap(), prototype);
+        assert_eq!(parallel.counts(label), serial.counts(label));
+    }
+}
+
+#[test]
+fn test_predict_batch_matches_predict() {
+    let mut rng = OvsaRng::seed_from_u64(22);
+    let samples: Vec<_> = (0..30).map(|i| (ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap(), (i % 3).to_string())).collect();
+    let mut classifier = CentroidClassifier::new(1000).unwrap();
+    classifier.fit_with_rng(&samples, &mut rng).unwrap();
+
+    let vectors: Vec<_> = samples.iter().map(|(vector, _)| vector.clone()).collect();
+    let predictions = classifier.predict_batch(&vectors).unwrap();
+    for (vector, prediction) in vectors.iter().zip(predictions) {
+        assert_eq!(prediction, classifier.predict(vector).unwrap());
+    }
+}
+
+#[test]
+fn test_refine_codebook_widens_margin() {
+    use ovsa::encode::{LevelEncoder, levels_with_rng, record};
+    use ovsa::learn::refine::{RefineParams, refine_codebook_with_rng};
+    use ovsa::memory::ItemMemory;
+
+    let mut rng = OvsaRng::seed_from_u64(21);
+    let levels = LevelEncoder::new(0.0, 1.0, levels_with_rng(256, 64, 8, &mut rng).unwrap()).unwrap();
+    let mut codebook = ItemMemory::new(256).unwrap();
+    for position in 0..3 {
+        codebook.symbol_with_rng(&format!("feature:{}", position), 128, &mut rng).unwrap();
+    }
+    let encode = |codebook: &ItemMemory, values: &Vec<f64>| {
+        let roles: Vec<_> = (0..values.len()).map(|position| codebook.get(&format!("feature:{}", position)).unwrap()).collect();
+        let fields: Vec<_> = roles.into_iter().zip(values).map(|(role, &value)| (role, levels.encode(value))).collect();
+        record(&fields)
+    };
+
+    let sample = |class: usize, rng: &mut OvsaRng| -> (Vec<f64>, String) {
+        use rand::Rng;
+        let values = (0..3).map(|feature| ((class + feature) % 3) as f64 / 2.0 + rng.random_range(-0.2..0.2)).collect();
+        (values, format!("class{}", class))
+    };
+    let train: Vec<_> = (0..30).map(|i| sample(i % 3, &mut rng)).collect();
+    let validation: Vec<_> = (0..15).map(|i| sample(i % 3, &mut rng)).collect();
+    let before = codebook.get("feature:0").unwrap().clone();
+
+    let params = RefineParams { iterations: 30, swaps: 4 };
+    let report = refine_codebook_with_rng(&mut codebook, &train, &validation, encode, params, &mut rng).unwrap();
+    assert!(report.accepted > 0, "{:?}", report);
+    assert!(report.final_margin > report.initial_margin);
+    assert_eq!(codebook.get("feature:0").unwrap().nnz(), before.nnz());
+    assert_eq!(codebook.len(), 3);
+}
+
+#[test]
+fn test_reprojected_classifier_classifies_reprojected_vectors() {
+    let mut rng = OvsaRng::seed_from_u64(12);
+    let centers: Vec<_> = (0..3).map(|_| ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap()).collect();
+    let samples: Vec<_> = (0..30).map(|i| (noisy(&centers[i % 3], &mut rng), format!("class{}", i % 3))).collect();
+    let mut classifier = CentroidClassifier::new(2000).unwrap();
+    classifier.fit_with_rng(&samples, &mut rng).unwrap();
+
+    let small = classifier.reproject(500, 4).unwrap();
+    assert_eq!(small.dimension(), 500);
+    for (i, center) in centers.iter().enumerate() {
+        let query = ovsa::binary::reproject(&noisy(center, &mut rng), 500, 4).unwrap();
+        assert_eq!(small.predict(&query).unwrap().unwrap().0, format!("class{}", i));
+    }
+}
+
+#[test]
+fn test_explain_ranks_discriminative_feature_first() {
+    use rand::Rng;
+    use ovsa::encode::{LevelEncoder, features_with_roles, levels_with_rng};
+    use ovsa::memory::ItemMemory;
+
+    let dimension = 2000;
+    let mut rng = OvsaRng::seed_from_u64(8);
+    let levels = LevelEncoder::new(0.0, 1.0, levels_with_rng(dimension, dimension / 2, 8, &mut rng).unwrap()).unwrap();
+    let mut codebook = ItemMemory::new(dimension).unwrap();
+    // feature 0 separates the classes, features 1 to 3 are noise
+    let mut draw = |label: &str, rng: &mut OvsaRng| {
+        let first = if label == "high" { 0.95 } else { 0.05 };
+        let values = [first, rng.random::<f64>(), rng.random::<f64>(), rng.random::<f64>()];
+        features_with_roles(&values, &levels, &mut codebook, dimension / 2).unwrap()
+    };
+
+    let mut samples = Vec::new();
+    for i in 0..40 {
+        let label = if i % 2 == 0 { "high" } else { "low" };
+        samples.push((draw(label, &mut rng).vector, label.to_string()));
+    }
+    let mut classifier = CentroidClassifier::new(dimension).unwrap();
+    classifier.fit_with_rng(&samples, &mut rng).unwrap();
+
+    let record = draw("high", &mut rng);
+    assert_eq!(record.fields.len(), 4);
+    let explanation = classifier.explain(&record).unwrap().unwrap();
+    assert_eq!(explanation.label, "high");
+    assert_eq!(explanation.runner_up.as_deref(), Some("low"));
+    assert_eq!(explanation.contributions[0].role, "feature:0");
+    assert!(explanation.contributions[0].margin() > 0.1);
+    assert!(explanation.contributions.windows(2).all(|pair| pair[0].alignment >= pair[1].alignment));
+
+    let untrained = CentroidClassifier::new(dimension).unwrap();
+    assert_eq!(untrained.explain(&record).unwrap(), None);
+}
+
+fn four_classes(rng: &mut OvsaRng) -> (CentroidClassifier, ovsa::learn::Samples) {
+    let dimension = 2048;
+    let bases: Vec<_> = (0..4).map(|_| ovsa::binary::sparse_random_with_rng(dimension, dimension / 2, rng).unwrap()).collect();
+    let mut samples = Vec::new();
+    for _ in 0..10 {
+        for (class, base) in bases.iter().enumerate() {
+            samples.push((noisy(base, rng), format!("class{}", class)));
+        }
+    }
+    let mut classifier = CentroidClassifier::new(dimension).unwrap();
+    classifier.fit_with_rng(&samples[..20], rng).unwrap();
+    (classifier, samples[20..].to_vec())
+}
+
+#[test]
+fn test_compress_prunes_to_budget() {
+    use ovsa::learn::compress::CompressParams;
+
+    let mut rng = OvsaRng::seed_from_u64(12);
+    let (classifier, validation) = four_classes(&mut rng);
+    let (full, report) = classifier.compress(usize::MAX, &validation, CompressParams::default()).unwrap();
+    assert_eq!(report.n_kept, 2048);
+    assert!(report.compressed_bytes < report.original_bytes);
+    assert_eq!(report.compressed_accuracy, report.original_accuracy);
+
+    let target = full.memory_usage().total_bytes() - 128;
+    let (compressed, report) = classifier.compress(target, &validation, CompressParams::default()).unwrap();
+    assert!(report.compressed_bytes <= target);
+    assert!(report.n_kept < 2048 && report.n_kept >= 64);
+    assert_eq!(report.n_merged, 0);
+    assert_eq!(report.original_accuracy, 1.0);
+    assert!(report.compressed_accuracy > 0.9);
+    assert_eq!(compressed.mask().unwrap().n_kept(), report.n_kept);
+    assert!(full.mask().is_none());
+    assert_eq!(compressed.members("class0").unwrap(), &["class0".to_string()]);
+    assert!(compressed.predict(&validation[0].0).unwrap().is_some());
+}
+
+#[test]
+fn test_compress_merges_classes_when_allowed() {
+    use ovsa::learn::compress::CompressParams;
+
+    let mut rng = OvsaRng::seed_from_u64(12);
+    let (classifier, validation) = four_classes(&mut rng);
+    let smallest = classifier.compress(usize::MAX, &validation, CompressParams { min_dimension: 2048, merge: false }).unwrap().0;
+    let target = smallest.memory_usage().total_bytes() * 3 / 4;
+
+    let params = CompressParams { min_dimension: 2048, merge: true };
+    assert!(matches!(classifier.compress(target, &validation, CompressParams { merge: false, ..params }), Err(ovsa::errors::OVSAError::BudgetExceeded)));
+    let (compressed, report) = classifier.compress(target, &validation, params).unwrap();
+    assert!(report.n_merged > 0);
+    assert_eq!(compressed.prototypes().len(), 4 - report.n_merged);
+    // merged classes count as correct for all their members
+    assert_eq!(report.compressed_accuracy, 1.0);
+    let merged = compressed.prototypes().labels().iter().find(|label| label.contains('+')).unwrap();
+    assert!(compressed.members(merged).unwrap().len() > 1);
+}
+
+#[test]
+fn test_qmemory_update_and_read() {
+    use ovsa::learn::QMemory;
+    let mut rng = OvsaRng::seed_from_u64(40);
+    let mut memory = QMemory::new_with_rng(4000, 2000, 0.0, 1.0, 11, &mut rng).unwrap();
+    let state = ovsa::binary::sparse_random_with_rng(4000, 2000, &mut rng).unwrap();
+    let other = ovsa::binary::sparse_random_with_rng(4000, 2000, &mut rng).unwrap();
+
+    assert_eq!(memory.value(&state, "up").unwrap(), None);
+    assert_eq!(memory.update_with_rng(&state, "up", 0.8, 1.0, &mut rng).unwrap(), 0.8);
+    assert_eq!(memory.update_with_rng(&state, "down", 0.2, 1.0, &mut rng).unwrap(), 0.2);
+    assert_eq!(memory.update_with_rng(&state, "up", 0.4, 0.5, &mut rng).unwrap(), 0.6);
+    assert_eq!(memory.len(), 2);
+    assert_eq!(memory.value(&state, "up").unwrap(), Some(0.6));
+    assert_eq!(memory.value(&state, "down").unwrap(), Some(0.2));
+    assert_eq!(memory.value(&other, "up").unwrap(), None);
+    assert_eq!(memory.greedy_action(&state, &["up", "down", "left"]).unwrap(), Some(("up".to_string(), 0.6)));
+    assert!(memory.update_with_rng(&state, "up", 0.4, 0.0, &mut rng).is_err());
+}
+
+#[test]
+fn test_qmemory_learns_chain() {
+    use rand::Rng;
+    use ovsa::learn::QMemory;
+    use ovsa::learn::qmemory::Transition;
+    let mut rng = OvsaRng::seed_from_u64(41);
+    let states: Vec<_> = (0..4).map(|_| ovsa::binary::sparse_random_with_rng(4000, 2000, &mut rng).unwrap()).collect();
+    let mut memory = QMemory::new_with_rng(4000, 2000, 0.0, 1.0, 21, &mut rng).unwrap();
+    let actions = ["left", "right"];
+
+    // a chain of four states explored at random, the reward waiting at the right end
+    for _ in 0..30 {
+        let mut position = 0usize;
+        for _ in 0..20 {
+            let action = actions[rng.random_range(0..2)];
+            let next = if action == "right" { position + 1 } else { position.saturating_sub(1) };
+            let done = next == 3;
+            let transition = Transition { state: &states[position], action, reward: if done { 1.0 } else { 0.0 }, next_state: (!done).then(|| &states[next]) };
+            memory.q_learning_step_with_rng(&transition, &actions, 0.9, 1.0, &mut rng).unwrap();
+            if done {
+                break;
+            }
+            position = next;
+        }
+    }
+
+    for state in &states[..3] {
+        assert_eq!(memory.greedy_action(state, &actions).unwrap().unwrap().0, "right");
+    }
+    let value = memory.value(&states[0], "right").unwrap().unwrap();
+    assert!((value - 0.8).abs() < 1e-9, "{value}");
+}
+
+#[test]
+fn test_anomaly_detector() {
+    use ovsa::learn::AnomalyDetector;
+    let mut rng = OvsaRng::seed_from_u64(42);
+    let modes: Vec<_> = (0..2).map(|_| ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap()).collect();
+    let normal: Vec<_> = (0..40).map(|i| noisy(&modes[i % 2], &mut rng)).collect();
+    let validation: Vec<_> = (0..20).map(|i| noisy(&modes[i % 2], &mut rng)).collect();
+    let anomalies: Vec<_> = (0..10).map(|_| ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap()).collect();
+
+    let mut detector = AnomalyDetector::fit_with_rng(&normal, 2, &mut rng).unwrap();
+    assert_eq!(detector.prototypes().len(), 2);
+    assert!(detector.is_anomaly(&anomalies[0]).is_err());
+    let threshold = detector.calibrate(&validation, 0.05).unwrap();
+    assert_eq!(detector.threshold(), Some(threshold));
+
+    let flagged = validation.iter().filter(|vector| detector.is_anomaly(vector).unwrap()).count();
+    assert!(flagged <= 1, "{flagged}");
+    assert!(anomalies.iter().all(|vector| detector.is_anomaly(vector).unwrap()));
+
+    // a single prototype of two modes is a poor description of either
+    let single = AnomalyDetector::fit_with_rng(&normal, 1, &mut rng).unwrap();
+    assert!(single.score(&validation[0]).unwrap() > detector.score(&validation[0]).unwrap());
+    assert!(AnomalyDetector::fit_with_rng(&normal[..1], 2, &mut rng).is_err());
+    assert!(matches!(AnomalyDetector::fit_with_rng(&normal, 0, &mut rng), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+    assert!(matches!(single.is_anomaly(&validation[0]), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+}
+
+#[test]
+fn test_anomaly_detector_absorbs_novelty() {
+    use ovsa::learn::AnomalyDetector;
+    use ovsa::memory::Recognition;
+    let mut rng = OvsaRng::seed_from_u64(43);
+    let mode = ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap();
+    let normal: Vec<_> = (0..20).map(|_| noisy(&mode, &mut rng)).collect();
+    let mut detector = AnomalyDetector::fit_with_rng(&normal, 1, &mut rng).unwrap();
+    detector.calibrate(&normal, 0.0).unwrap();
+
+    let novelty = ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap();
+    assert!(matches!(detector.absorb(&novelty).unwrap(), Recognition::Novel { ref label, .. } if label == "normal1"));
+    assert!(!detector.is_anomaly(&noisy(&novelty, &mut rng)).unwrap());
+    assert!(matches!(detector.absorb(&noisy(&mode, &mut rng)).unwrap(), Recognition::Known { ref label, .. } if label == "normal0"));
+}
+
+#[test]
+fn test_bag_classifier() {
+    use ovsa::learn::{BagClassifier, Pooling};
+    let mut rng = OvsaRng::seed_from_u64(45);
+    let keys: Vec<_> = (0..2).map(|_| ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap()).collect();
+    let labels = ["a", "b"];
+    // a bag holds a noisy copy of its class key among seven unrelated instances
+    let bag = |class: usize, rng: &mut OvsaRng| -> (Vec<sprs::CsVec<i8>>, String) {
+        let mut instances = vec![noisy(&keys[class], rng)];
+        instances.extend((0..7).map(|_| ovsa::binary::sparse_random_with_rng(2000, 1000, rng).unwrap()));
+        (instances, labels[class].to_string())
+    };
+    let train: Vec<_> = (0..20).map(|i| bag(i % 2, &mut rng)).collect();
+    let test: Vec<_> = (0..40).map(|i| bag(i % 2, &mut rng)).collect();
+
+    assert!(BagClassifier::new(2000, Pooling::Attention { temperature: 0.0 }).is_err());
+    for pooling in [Pooling::Majority, Pooling::Attention { temperature: 0.02 }] {
+        let mut classifier = BagClassifier::new(2000, pooling).unwrap();
+        assert_eq!(classifier.weights(&train[0].0).unwrap(), vec![0.125; 8]);
+        assert!(classifier.pool(&[]).is_err());
+        classifier.fit_with_rng(&train, &mut rng).unwrap();
+        classifier.retrain_with_rng(&train, 5, &mut rng).unwrap();
+        let correct = test.iter().filter(|(bag, label)| classifier.predict_with_rng(bag, &mut rng).unwrap().unwrap().0 == *label).count();
+        assert!(correct >= 38, "{pooling:?} {correct}");
+        assert_eq!(classifier.pool_with_rng(&test[0].0[..1], &mut rng).unwrap(), test[0].0[0]);
+
+        // attention singles out the key instance of a bag
+        let weights = classifier.weights(&test[0].0).unwrap();
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        match pooling {
+            Pooling::Majority => assert_eq!(weights, vec![0.125; 8]),
+            Pooling::Attention { .. } => assert!(weights[0] > 0.9, "{weights:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_drift_monitor() {
+    use ovsa::learn::DriftMonitor;
+    let mut rng = OvsaRng::seed_from_u64(44);
+    let a = ovsa::binary::sparse_random_with_rng(2000, 200, &mut rng).unwrap();
+    let b = ovsa::binary::sparse_random_with_rng(2000, 200, &mut rng).unwrap();
+    let train: Vec<_> = (0..10).flat_map(|_| [(noisy(&a, &mut rng), "a".to_string()), (noisy(&b, &mut rng), "b".to_string())]).collect();
+    let reference: Vec<_> = (0..200).map(|i| noisy(if i % 2 == 0 { &a } else { &b }, &mut rng)).collect();
+    let mut classifier = CentroidClassifier::new(2000).unwrap();
+    classifier.fit_with_rng(&train, &mut rng).unwrap();
+
+    assert!(DriftMonitor::new(&CentroidClassifier::new(2000).unwrap(), &reference, 10).is_err());
+    assert!(matches!(DriftMonitor::new(&classifier, &reference, 0), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+    let mut monitor = DriftMonitor::new(&classifier, &reference, 10).unwrap();
+    assert!(monitor.status().is_none());
+    assert!(monitor.prototype_baseline().mean > 0.5);
+    assert!(monitor.observe(&ovsa::binary::sparse_random_with_rng(1000, 100, &mut rng).unwrap()).is_err());
+
+    for i in 0..30 {
+        let status = monitor.observe(&noisy(if i % 2 == 0 { &a } else { &b }, &mut rng)).unwrap();
+        assert_eq!(status.n_observed, i + 1);
+        assert!(!status.drift, "{status:?}");
+    }
+
+    // a new kind of input lowers both statistics, but drift is only flagged once the window is full of it
+    let c = ovsa::binary::sparse_random_with_rng(2000, 200, &mut rng).unwrap();
+    monitor.reset();
+    let statuses: Vec<_> = (0..10).map(|_| monitor.observe(&noisy(&c, &mut rng)).unwrap()).collect();
+    assert!(statuses[..9].iter().all(|status| !status.drift));
+    let last = statuses[9];
+    assert!(last.drift && last.prototype_z < -3.0 && last.reference_z < -3.0, "{last:?}");
+}
+
+#[test]
+fn test_evaluate_confusion_and_metrics() {
+    use ovsa::learn::evaluation::Classifier;
+
+    // predicts "a" for vectors with an active first entry, "b" otherwise, and nothing for empty vectors
+    struct FirstEntry;
+    impl Classifier for FirstEntry {
+        fn predict(&self, vector: &sprs::CsVec<i8>) -> Result<Option<(String, f64)>, ovsa::errors::OVSAError> {
+            Ok(vector.indices().first().map(|&index| (if index == 0 { "a" } else { "b" }.to_string(), 1.0)))
+        }
+    }
+
+    let vector = |indices: &[usize]| ovsa::binary::from_indices(8, indices).unwrap();
+    let test_set = vec![
+        (vector(&[0, 3]), "a".to_string()),
+        (vector(&[0]), "a".to_string()),
+        (vector(&[2]), "a".to_string()),
+        (sprs::CsVec::empty(8), "a".to_string()),
+        (vector(&[1]), "b".to_string()),
+        (vector(&[0, 1]), "c".to_string()),
+    ];
+    let evaluation = ovsa::learn::evaluate(&FirstEntry, &test_set).unwrap();
+
+    assert_eq!(evaluation.labels, ["a", "b", "c"]);
+    assert_eq!(evaluation.confusion, vec![vec![2, 1, 0], vec![0, 1, 0], vec![1, 0, 0]]);
+    assert_eq!(evaluation.unpredicted, [1, 0, 0]);
+    assert_eq!(evaluation.n_samples(), 6);
+    assert!((evaluation.accuracy() - 0.5).abs() < 1e-12);
+    assert_eq!(evaluation.count("c", "a"), 1);
+
+    let a = evaluation.class_metrics("a").unwrap();
+    assert!((a.precision - 2.0 / 3.0).abs() < 1e-12 && (a.recall - 0.5).abs() < 1e-12 && a.support == 4);
+    assert!((a.f1 - 4.0 / 7.0).abs() < 1e-12);
+    let c = evaluation.class_metrics("c").unwrap();
+    assert_eq!((c.precision, c.recall, c.f1), (0.0, 0.0, 0.0));
+    assert!(evaluation.class_metrics("d").is_none());
+    assert!(evaluation.to_string().starts_with("accuracy 0.5000 over 6 samples"));
+}
+
+#[test]
+fn test_splits_are_stratified() {
+    let samples: Vec<(usize, String)> = (0..30).map(|i| (i, if i < 20 { "a" } else { "b" }.to_string())).collect();
+    let mut rng = OvsaRng::seed_from_u64(9);
+
+    let (train, test) = ovsa::learn::evaluation::train_test_split_with_rng(&samples, 0.3, &mut rng).unwrap();
+    assert_eq!((train.len(), test.len()), (21, 9));
+    assert_eq!(test.iter().filter(|(_, label)| label == "a").count(), 6);
+    let mut all: Vec<usize> = train.iter().chain(&test).map(|(i, _)| *i).collect();
+    all.sort_unstable();
+    assert_eq!(all, (0..30).collect::<Vec<_>>());
+    assert!(ovsa::learn::train_test_split(&samples, 1.0).is_err());
+
+    let folds = ovsa::learn::evaluation::k_fold_with_rng(&samples, 4, &mut rng).unwrap();
+    let mut tested: Vec<usize> = folds.iter().flat_map(|(_, test)| test.iter().map(|(i, _)| *i)).collect();
+    tested.sort_unstable();
+    assert_eq!(tested, (0..30).collect::<Vec<_>>());
+    for (train, test) in &folds {
+        assert!(test.len() == 7 || test.len() == 8);
+        assert_eq!(train.len() + test.len(), 30);
+        let n_a = test.iter().filter(|(_, label)| label == "a").count();
+        assert!(n_a == 5 || n_a == 6, "{}", n_a);
+    }
+    assert!(ovsa::learn::k_fold(&samples, 1).is_err());
+    assert!(ovsa::learn::k_fold(&samples, 31).is_err());
+}
+
+#[test]
+fn test_cross_validate_centroid_classifier() {
+    let mut rng = OvsaRng::seed_from_u64(10);
+    let a = ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap();
+    let b = ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap();
+    let samples: Vec<_> = (0..6).flat_map(|_| [(noisy(&a, &mut rng), "a".to_string()), (noisy(&b, &mut rng), "b".to_string())]).collect();
+
+    let evaluations = ovsa::learn::evaluation::cross_validate_with_rng(&samples, 3, |train| {
+        let mut classifier = CentroidClassifier::new(1000)?;
+        classifier.fit(train)?;
+        Ok(classifier)
+    }, &mut rng).unwrap();
+    assert_eq!(evaluations.len(), 3);
+    assert!(evaluations.iter().all(|evaluation| evaluation.n_samples() == 4 && evaluation.accuracy() == 1.0));
+}
+
+#[test]
+fn test_retrain_corrects_training_errors() {
+    let mut rng = OvsaRng::seed_from_u64(11);
+    let a = ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap();
+    let b = ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap();
+    // a third class halfway between the others is often mistaken for them by the plain centroids
+    let mut counts = vec![0u32; 1000];
+    for vector in [&a, &b, &noisy(&b, &mut rng)] {
+        vector.indices().iter().for_each(|&index| counts[index] += 1);
+    }
+    let between = ovsa::binary::majority_with_rng(&counts, 3, &mut rng);
+    let samples: Vec<_> = (0..6)
+        .flat_map(|_| [(noisy(&a, &mut rng), "a".to_string()), (noisy(&b, &mut rng), "b".to_string()), (noisy(&between, &mut rng), "ab".to_string())])
+        .collect();
+
+    let mut classifier = CentroidClassifier::new(1000).unwrap();
+    classifier.fit_with_rng(&samples, &mut rng).unwrap();
+    let corrections = classifier.retrain_with_rng(&samples, 10, &mut rng).unwrap();
+    assert!(!corrections.is_empty() && corrections.len() <= 10);
+    let accuracy = ovsa::learn::evaluate(&classifier, &samples).unwrap().accuracy();
+    assert!(accuracy >= 1.0 - corrections[0] as f64 / samples.len() as f64, "{:?} {}", corrections, accuracy);
+    assert!(classifier.retrain_with_rng(&[(ovsa::binary::from_indices(10, &[1]).unwrap(), "a".to_string())], 1, &mut rng).is_err());
+}
+
+#[test]
+fn test_sweep() {
+    use ovsa::learn::SweepGrid;
+
+    let mut rng = OvsaRng::seed_from_u64(12);
+    let mut sample = |label: &str, center: f64| {
+        let features: Vec<f64> = (0..4).map(|_| center + rand::Rng::random_range(&mut rng, -1.0..1.0)).collect();
+        (features, label.to_string())
+    };
+    let train: Vec<_> = (0..20).flat_map(|_| [sample("low", 2.0), sample("high", 8.0)]).collect();
+    let test: Vec<_> = (0..10).flat_map(|_| [sample("low", 2.0), sample("high", 8.0)]).collect();
+
+    let grid = SweepGrid { dimensions: vec![200, 1000], densities: vec![0.5], levels: vec![4, 16], epochs: vec![2, 0] };
+    let table = ovsa::learn::sweep(&train, &test, &grid, 3).unwrap();
+    let settings: Vec<(usize, usize, usize)> = table.rows.iter().map(|row| (row.dimension, row.levels, row.epochs)).collect();
+    assert_eq!(settings, [(200, 4, 0), (200, 4, 2), (200, 16, 0), (200, 16, 2), (1000, 4, 0), (1000, 4, 2), (1000, 16, 0), (1000, 16, 2)]);
+    assert!(table.best().unwrap().accuracy > 0.9);
+    assert_eq!(ovsa::learn::sweep(&train, &test, &grid, 3).unwrap(), table);
+    assert!(table.to_string().starts_with("dimension\tdensity\tlevels\tepochs\taccuracy\tmacro_f1\n200\t0.5\t4\t0\t"));
+
+    assert!(ovsa::learn::sweep(&train, &test, &SweepGrid { densities: vec![1.5], ..grid.clone() }, 3).is_err());
+    assert!(ovsa::learn::sweep(&train, &test, &SweepGrid { epochs: vec![], ..grid.clone() }, 3).is_err());
+    assert!(ovsa::learn::sweep(&[], &test, &grid, 3).is_err());
+}
+
+
+#[test]
+fn test_stratified_batches() {
+    let samples: Vec<((), String)> = (0..30).map(|index| ((), if index < 20 { "a" } else { "b" }.to_string())).collect();
+    let batches = ovsa::learn::online::stratified_batches_with_rng(&samples, 6, &mut OvsaRng::seed_from_u64(1)).unwrap();
+    assert_eq!(batches.len(), 5);
+    let mut seen: Vec<usize> = batches.iter().flatten().copied().collect();
+    seen.sort_unstable();
+    assert_eq!(seen, (0..30).collect::<Vec<_>>());
+    // every batch holds the classes in the overall proportion of 2 to 1
+    for batch in &batches {
+        let n_a = batch.iter().filter(|&&index| index < 20).count();
+        assert!((3..=5).contains(&n_a), "{:?}", batches);
+    }
+    assert!(ovsa::learn::online::stratified_batches(&samples, 0).is_err());
+}
+
+
+#[test]
+fn test_train_online() {
+    use ovsa::learn::TrainingParams;
+
+    let mut rng = OvsaRng::seed_from_u64(8);
+    let centers: Vec<_> = (0..3).map(|_| ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).collect();
+    let samples = |n: usize, rng: &mut OvsaRng| -> Vec<_> {
+        (0..n).flat_map(|_| centers.iter().enumerate().map(|(class, center)| (noisy(center, rng), class.to_string())).collect::<Vec<_>>()).collect()
+    };
+    let (train, validation) = (samples(10, &mut rng), samples(5, &mut rng));
+
+    let params = TrainingParams { batch_size: 6, epochs: 10, patience: 2, min_delta: 0.0 };
+    let mut classifier = CentroidClassifier::new(1000).unwrap();
+    let report = ovsa::learn::online::train_online_with_rng(&mut classifier, &train, &validation, &params, &mut rng).unwrap();
+    assert_eq!(report.epochs[0].updates, train.len());
+    assert!(report.best_accuracy() > 0.9);
+    assert!(report.epochs.len() <= report.best_epoch + params.patience + 1);
+    assert_eq!(report.stopped_early, report.epochs.len() < params.epochs);
+    // the classifier of the best epoch is kept
+    assert_eq!(ovsa::learn::evaluate(&classifier, &validation).unwrap().accuracy(), report.best_accuracy());
+
+    let mut classifier = CentroidClassifier::new(1000).unwrap();
+    assert!(ovsa::learn::train_online(&mut classifier, &train, &[], &params).is_err());
+    assert!(ovsa::learn::train_online(&mut classifier, &train, &validation, &TrainingParams { epochs: 0, ..params }).is_err());
+    assert!(ovsa::learn::train_online(&mut CentroidClassifier::new(500).unwrap(), &train, &validation, &params).is_err());
+}
+
+
+#[test]
+fn test_learning_rate_schedules() {
+    use ovsa::learn::LearningRate;
+
+    assert_eq!(LearningRate::Constant(0.1).at(7), 0.1);
+    let step = LearningRate::Step { initial: 1.0, factor: 0.5, every: 2 };
+    assert_eq!([step.at(0), step.at(1), step.at(2), step.at(5)], [1.0, 1.0, 0.5, 0.25]);
+    assert_eq!(LearningRate::InverseTime { initial: 1.0, decay: 1.0 }.at(3), 0.25);
+
+    assert!(ovsa::learn::OnlineHDClassifier::new(100, LearningRate::Constant(0.0)).is_err());
+    assert!(ovsa::learn::OnlineHDClassifier::new(100, LearningRate::Step { initial: 1.0, factor: 0.5, every: 0 }).is_err());
+    assert!(ovsa::learn::OnlineHDClassifier::new(0, LearningRate::default()).is_err());
+}
+
+
+#[test]
+fn test_onlinehd_classifier() {
+    use ovsa::learn::{LearningRate, OnlineHDClassifier};
+
+    let mut rng = OvsaRng::seed_from_u64(9);
+    let centers: Vec<_> = (0..3).map(|_| ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).collect();
+    let samples = |n: usize, rng: &mut OvsaRng| -> Vec<_> {
+        (0..n).flat_map(|_| centers.iter().enumerate().map(|(class, center)| (noisy(center, rng), class.to_string())).collect::<Vec<_>>()).collect()
+    };
+    let (train, test) = (samples(10, &mut rng), samples(5, &mut rng));
+
+    let mut classifier = OnlineHDClassifier::new(1000, LearningRate::Step { initial: 0.5, factor: 0.5, every: 1 }).unwrap();
+    let mistakes = classifier.fit(&train, 3).unwrap();
+    assert_eq!(mistakes.len(), 3);
+    assert_eq!(classifier.labels(), ["0", "1", "2"]);
+    assert!(ovsa::learn::evaluate(&classifier, &test).unwrap().accuracy() > 0.9);
+
+    // a sample the class already recognizes moves the accumulator less than a new one
+    let mut fresh = OnlineHDClassifier::new(1000, LearningRate::Constant(1.0)).unwrap();
+    fresh.update(&centers[0], "0", 1.0).unwrap();
+    let before = fresh.accumulator("0").unwrap()[centers[0].indices()[0]];
+    assert_eq!(before, 1.0);
+    assert!(!fresh.update(&centers[0], "0", 1.0).unwrap());
+    assert!((fresh.accumulator("0").unwrap()[centers[0].indices()[0]] - before).abs() < 1e-9);
+    // a misclassified sample is subtracted from the winning class
+    assert!(fresh.update(&centers[1], "1", 1.0).unwrap());
+    assert!(fresh.accumulator("0").unwrap().iter().any(|&value| value < 1.0));
+    assert!(fresh.predict(&centers[1]).unwrap().unwrap().0 == "1");
+
+    assert!(classifier.fit(&[(ovsa::binary::sparse_random_with_rng(500, 10, &mut rng).unwrap(), "0".to_string())], 1).is_err());
+}
+
+
+#[test]
+fn test_ensemble() {
+    use ovsa::learn::{Combination, Ensemble};
+
+    let mut rng = OvsaRng::seed_from_u64(10);
+    let centers: Vec<_> = (0..3).map(|_| ovsa::binary::sparse_random_with_rng(2000, 1000, &mut rng).unwrap()).collect();
+    let samples = |n: usize, rng: &mut OvsaRng| -> Vec<_> {
+        (0..n).flat_map(|_| centers.iter().enumerate().map(|(class, center)| (noisy(center, rng), class.to_string())).collect::<Vec<_>>()).collect()
+    };
+    let (train, test) = (samples(5, &mut rng), samples(5, &mut rng));
+
+    let mut ensemble = Ensemble::new(2000, 5, 500, 3).unwrap();
+    assert_eq!(ensemble.predict(&test[0].0).unwrap(), None);
+    ensemble.fit(&train).unwrap();
+    assert_eq!(ensemble.members().len(), 5);
+    assert!(ensemble.members().iter().all(|member| member.dimension() == 500 && member.prototypes().len() == 3));
+    // the members see different dimensions
+    assert_ne!(ensemble.members()[0].prototypes().get("0"), ensemble.members()[1].prototypes().get("0"));
+    assert!(ovsa::learn::evaluate(&ensemble, &test).unwrap().accuracy() > 0.9);
+
+    let (label, score) = ensemble.predict(&test[0].0).unwrap().unwrap();
+    assert_eq!(label, test[0].1);
+    assert!(score > 0.0 && score <= 1.0);
+    let voting = ensemble.clone().with_combination(Combination::Vote);
+    assert_eq!(voting.predict(&test[0].0).unwrap().unwrap(), (label, 1.0));
+
+    // training is reproducible
+    let mut again = Ensemble::new(2000, 5, 500, 3).unwrap();
+    again.fit(&train).unwrap();
+    assert_eq!(again.members()[4].prototypes().get("2"), ensemble.members()[4].prototypes().get("2"));
+
+    assert!(matches!(Ensemble::new(2000, 0, 500, 3), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+    assert!(ensemble.predict(&ovsa::binary::sparse_random_with_rng(1000, 10, &mut rng).unwrap()).is_err());
+}
+
+
+#[test]
+fn test_merge_classes() {
+    let mut rng = OvsaRng::seed_from_u64(11);
+    let centers: Vec<_> = (0..3).map(|_| ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).collect();
+    let samples: Vec<_> = (0..5).flat_map(|_| centers.iter().zip(["a", "b", "c"]).map(|(center, label)| (noisy(center, &mut rng), label.to_string())).collect::<Vec<_>>()).collect();
+    let mut classifier = CentroidClassifier::new(1000).unwrap();
+    classifier.fit_with_rng(&samples, &mut rng).unwrap();
+
+    // merging gives the class trained on the vectors of both
+    let merged_samples: Vec<_> = samples.iter().map(|(vector, label)| (vector.clone(), if label == "b" { "a".to_string() } else { label.clone() })).collect();
+    let mut expected = CentroidClassifier::new(1000).unwrap();
+    expected.fit(&merged_samples).unwrap();
+    classifier.merge_classes_with_rng("a", "b", "ab", &mut rng).unwrap();
+    assert_eq!(classifier.prototypes().labels(), ["c", "ab"]);
+    assert_eq!(classifier.counts("ab").unwrap(), expected.counts("a").unwrap());
+    assert_eq!(classifier.predict(&noisy(&centers[1], &mut rng)).unwrap().unwrap().0, "ab");
+
+    assert!(classifier.merge_classes("ab", "ab", "x").is_err());
+    assert!(classifier.merge_classes("ab", "missing", "x").is_err());
+    classifier.fit(&[(centers[0].clone(), "d".to_string())]).unwrap();
+    assert!(classifier.merge_classes("ab", "c", "d").is_err());
+    classifier.merge_classes("ab", "c", "c").unwrap();
+    assert_eq!(classifier.prototypes().labels(), ["d", "c"]);
+}
+
+
+#[test]
+fn test_split_class() {
+    let mut rng = OvsaRng::seed_from_u64(12);
+    let modes: Vec<_> = (0..2).map(|_| ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).collect();
+    let vectors: Vec<_> = (0..6).flat_map(|_| modes.iter().map(|mode| noisy(mode, &mut rng)).collect::<Vec<_>>()).collect();
+    let samples: Vec<_> = vectors.iter().map(|vector| (vector.clone(), "x".to_string())).collect();
+    let mut classifier = CentroidClassifier::new(1000).unwrap();
+    classifier.fit_with_rng(&samples, &mut rng).unwrap();
+
+    let labels = classifier.split_class_with_rng("x", &vectors, 2, &mut rng).unwrap();
+    assert_eq!(labels, ["x/0", "x/1"]);
+    assert!(classifier.counts("x").is_none());
+    assert_eq!(labels.iter().map(|label| classifier.counts(label).unwrap().1).sum::<usize>(), 12);
+    // every mode lands in its own sub-class
+    let first = classifier.predict(&noisy(&modes[0], &mut rng)).unwrap().unwrap().0;
+    let second = classifier.predict(&noisy(&modes[1], &mut rng)).unwrap().unwrap().0;
+    assert_ne!(first, second);
+    assert_eq!(classifier.prototypes().labels_in("x").count(), 2);
+
+    assert!(classifier.split_class("x", &vectors, 2).is_err());
+    assert!(classifier.split_class("x/0", &vectors, 1).is_err());
+    assert!(classifier.split_class("x/0", &vectors[..1], 2).is_err());
+}
+
+
+#[test]
+fn test_calibration() {
+    use ovsa::learn::{Calibration, CalibrationMethod};
+
+    let mut rng = OvsaRng::seed_from_u64(13);
+    let centers: Vec<_> = (0..3).map(|_| ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).collect();
+    // heavy noise, so that some held-out samples are misclassified
+    let sample = |class: usize, rng: &mut OvsaRng| {
+        let noise = ovsa::binary::sparse_random_with_rng(1000, 450, rng).unwrap();
+        (ovsa::binary::xor(&centers[class], &noise).unwrap(), class.to_string())
+    };
+    let train: Vec<_> = (0..30).map(|index| sample(index % 3, &mut rng)).collect();
+    let held_out: Vec<_> = (0..60).map(|index| sample(index % 3, &mut rng)).collect();
+    let mut classifier = CentroidClassifier::new(1000).unwrap();
+    classifier.fit_with_rng(&train, &mut rng).unwrap();
+    assert!(matches!(classifier.predict_proba(&held_out[0].0), Err(ovsa::errors::OVSAError::InvalidArgument(_))));
+
+    for method in [CalibrationMethod::Temperature, CalibrationMethod::Platt] {
+        let calibration = classifier.calibrate(&held_out, method).unwrap();
+        assert_eq!(classifier.calibration(), Some(calibration));
+
+        let probabilities = classifier.predict_proba(&held_out[0].0).unwrap();
+        assert_eq!(probabilities.len(), 3);
+        assert!((probabilities.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(probabilities.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        let (label, probability) = classifier.predict_calibrated(&held_out[0].0).unwrap().unwrap();
+        assert_eq!(label, classifier.predict(&held_out[0].0).unwrap().unwrap().0);
+
+        // the mean confidence matches the accuracy on the calibration set
+        let accuracy = ovsa::learn::evaluate(&classifier, &held_out).unwrap().accuracy();
+        let confidence = held_out.iter().map(|(vector, _)| classifier.predict_calibrated(vector).unwrap().unwrap().1).sum::<f64>() / held_out.len() as f64;
+        assert!((confidence - accuracy).abs() < 0.15, "{:?}: confidence {} for accuracy {}", method, confidence, accuracy);
+        assert!(probability > 0.0 && probability < 1.0);
+    }
+
+    assert_eq!(Calibration::Temperature { temperature: 1.0 }.probabilities(&[0.0, 0.0]), [0.5, 0.5]);
+    assert!(classifier.calibrate(&[(held_out[0].0.clone(), "unknown".to_string())], CalibrationMethod::Platt).is_err());
+    assert!(classifier.calibrate(&[], CalibrationMethod::Platt).is_err());
+
+    // the calibration is saved with the model
+    let path = env::temp_dir().join(format!("ovsa-calibrated-{}.json", std::process::id()));
+    ovsa::io::save_classifier(&path, &classifier).unwrap();
+    assert_eq!(ovsa::io::load_classifier(&path).unwrap().calibration(), classifier.calibration());
+    fs::remove_file(path).unwrap();
+}
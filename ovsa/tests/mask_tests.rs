@@ -0,0 +1,215 @@
+#![cfg(feature = "sparse")]
+
+#[cfg(feature = "dense")]
+use ndarray::array;
+use rand::{Rng, SeedableRng};
+use ovsa::analysis::dimension_scores;
+use ovsa::binary::from_indices;
+use ovsa::learn::CentroidClassifier;
+use ovsa::mask::DimensionMask;
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_zero_and_project() {
+    let mask = DimensionMask::new(8, vec![6, 1, 3, 3]).unwrap();
+    assert_eq!(mask.kept(), &[1, 3, 6]);
+    assert_eq!(mask.n_kept(), 3);
+
+    let vec = from_indices(8, &[0, 3, 6, 7]).unwrap();
+    assert_eq!(mask.zero(&vec).unwrap().indices(), &[3, 6]);
+    let projected = mask.project(&vec).unwrap();
+    assert_eq!(projected.dim(), 3);
+    assert_eq!(projected.indices(), &[1, 2]);
+
+    assert!(mask.project(&from_indices(9, &[0]).unwrap()).is_err());
+    assert!(DimensionMask::new(8, vec![8]).is_err());
+    assert!(DimensionMask::new(8, vec![]).is_err());
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_zero_and_project_dense() {
+    let mask = DimensionMask::new(8, vec![6, 1, 3]).unwrap();
+    let dense = array![0.0f32, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+    assert_eq!(mask.zero_dense(&dense).unwrap(), array![0.0f32, 1.0, 0.0, 3.0, 0.0, 0.0, 6.0, 0.0]);
+    assert_eq!(mask.project_dense(&dense).unwrap(), array![1.0f32, 3.0, 6.0]);
+    assert!(mask.project_dense(&array![0.0f32, 1.0]).is_err());
+}
+
+
+#[test]
+fn test_from_scores_keeps_the_best_dimensions() {
+    let mask = DimensionMask::from_scores(&[0.5, 2.0, 0.5, 1.0], 3).unwrap();
+    assert_eq!(mask.kept(), &[0, 1, 3]);
+    assert!(DimensionMask::from_scores(&[1.0], 2).is_err());
+}
+
+
+#[test]
+fn test_discriminative_dimensions_survive_masking() {
+    // dimensions 0..20 tell the classes apart, the remaining ones are noise
+    let mut rng = OvsaRng::seed_from_u64(8);
+    let mut samples = Vec::new();
+    for i in 0..40 {
+        let class = i % 2;
+        let mut indices: Vec<usize> = (0..20).filter(|index| index % 2 == class).collect();
+        indices.extend((20..200).filter(|_| rng.random_bool(0.5)));
+        samples.push((from_indices(200, &indices).unwrap(), format!("class{}", class)));
+    }
+    let mut classifier = CentroidClassifier::new(200).unwrap();
+    classifier.fit_with_rng(&samples, &mut rng).unwrap();
+
+    let scores = dimension_scores(&classifier).unwrap();
+    let mask = DimensionMask::from_scores(&scores, 20).unwrap();
+    assert_eq!(mask.kept(), (0..20).collect::<Vec<_>>().as_slice());
+
+    let masked = classifier.masked(&mask).unwrap();
+    assert_eq!(masked.dimension(), 20);
+    for (vector, label) in &samples {
+        let (predicted, similarity) = masked.predict(&mask.project(vector).unwrap()).unwrap().unwrap();
+        assert_eq!(&predicted, label);
+        assert_eq!(similarity, 1.0);
+    }
+    assert_eq!(mask.project_memory(classifier.prototypes()).unwrap().len(), 2);
+    assert!(dimension_scores(&CentroidClassifier::new(10).unwrap()).is_err());
+}
+
+
+#[test]
+fn test_weighted_similarity_matches_projection() {
+    let mut rng = OvsaRng::seed_from_u64(5);
+    let a = ovsa::binary::sparse_random_with_rng(300, 150, &mut rng).unwrap();
+    let b = ovsa::binary::sparse_random_with_rng(300, 150, &mut rng).unwrap();
+    let mask = DimensionMask::new(300, (0..300).filter(|_| rng.random_bool(0.3)).collect()).unwrap();
+
+    let weighted = ovsa::binary::weighted_similarity(&a, &b, &mask.weights()).unwrap();
+    let projected = ovsa::binary::similarity(&mask.project(&a).unwrap(), &mask.project(&b).unwrap()).unwrap();
+    assert!((weighted - projected).abs() < 1e-12);
+    assert_eq!(ovsa::binary::weighted_similarity(&a, &b, &[2.0; 300]).unwrap(), ovsa::binary::similarity(&a, &b).unwrap());
+
+    assert!(ovsa::binary::weighted_similarity(&a, &b, &[1.0; 299]).is_err());
+    assert!(ovsa::binary::weighted_similarity(&a, &b, &[0.0; 300]).is_err());
+    let mut negative = vec![1.0; 300];
+    negative[3] = -1.0;
+    assert!(ovsa::binary::weighted_similarity(&a, &b, &negative).is_err());
+}
+
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_dense_weighted_similarity() {
+    let a = array![1.0f32, 0.0, 1.0, 1.0];
+    let b = array![1.0f32, 1.0, -1.0, 1.0];
+    let mask = DimensionMask::new(4, vec![0, 3]).unwrap();
+    assert!((ovsa::dense::weighted_similarity(&a, &b, &mask.weights()).unwrap() - 1.0).abs() < 1e-6);
+    let uniform = ovsa::dense::weighted_similarity(&a, &b, &[0.5; 4]).unwrap();
+    assert!((uniform - ovsa::dense::similarity(&a, &b)).abs() < 1e-6);
+}
+
+
+#[test]
+fn test_segment_weights_emphasize_a_field() {
+    let weights = ovsa::mask::segment_weights(10, &[(0..4, 3.0), (2..3, 0.0)], 1.0).unwrap();
+    assert_eq!(weights, vec![3.0, 3.0, 0.0, 3.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]);
+    assert!(ovsa::mask::segment_weights(10, &[(5..11, 1.0)], 1.0).is_err());
+    assert!(ovsa::mask::segment_weights(4, &[(0..4, 0.0)], 1.0).is_err());
+
+    // two vectors differing only in the second field: ignoring that field makes them identical
+    let a = from_indices(10, &[0, 5]).unwrap();
+    let b = from_indices(10, &[0, 6, 7]).unwrap();
+    let first_field = ovsa::mask::segment_weights(10, &[(0..5, 1.0)], 0.0).unwrap();
+    assert_eq!(ovsa::binary::weighted_similarity(&a, &b, &first_field).unwrap(), 1.0);
+}
+
+
+#[test]
+fn test_weighted_batch_kernel_and_query() {
+    use ovsa::hypervector::Hypervector;
+    use ovsa::memory::ItemMemory;
+
+    let mut rng = OvsaRng::seed_from_u64(6);
+    let vectors: Vec<_> = (0..5).map(|_| ovsa::binary::sparse_random_with_rng(640, 320, &mut rng).unwrap()).collect();
+    let weights: Vec<f64> = (0..640).map(|_| rng.random_range(0.0..2.0)).collect();
+    let queries = &vectors[..2];
+    let batch = <sprs::CsVec<i8> as Hypervector>::weighted_similarities(queries, &vectors.iter().collect::<Vec<_>>(), &weights).unwrap();
+    for (query, row) in queries.iter().zip(&batch) {
+        for (vector, similarity) in vectors.iter().zip(row) {
+            assert!((similarity - ovsa::binary::weighted_similarity(query, vector, &weights).unwrap()).abs() < 1e-9);
+        }
+    }
+
+    let mut memory = ItemMemory::new(640).unwrap();
+    for (index, vector) in vectors.iter().enumerate() {
+        memory.insert(&format!("v{}", index), vector.clone()).unwrap();
+    }
+    let matches = memory.query_weighted(&vectors[3], 2, &weights).unwrap();
+    assert_eq!(matches[0], ("v3".to_string(), 1.0));
+    assert!(memory.query_weighted(&vectors[3], 2, &weights[1..]).is_err());
+}
+
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_dense_weighted_batch_kernel() {
+    use ovsa::hypervector::Hypervector;
+
+    let mut rng = OvsaRng::seed_from_u64(7);
+    let vectors: Vec<_> = (0..4).map(|_| ovsa::dense::random_uniform_with_rng(64, -1.0, 1.0, &mut rng).unwrap()).collect();
+    let weights: Vec<f64> = (0..64).map(|_| rng.random_range(0.0..2.0)).collect();
+    let batch = <ndarray::Array1<f32> as Hypervector>::weighted_similarities(&vectors[..1], &vectors.iter().collect::<Vec<_>>(), &weights).unwrap();
+    for (vector, similarity) in vectors.iter().zip(&batch[0]) {
+        assert!((similarity - ovsa::dense::weighted_similarity(&vectors[0], vector, &weights).unwrap() as f64).abs() < 1e-5);
+    }
+}
+
+
+#[test]
+fn test_binary_similarity_range() {
+    use ovsa::hypervector::Hypervector;
+
+    let a = from_indices(200, &[3, 40, 60, 61, 150]).unwrap();
+    let b = from_indices(200, &[3, 41, 60, 150, 199]).unwrap();
+    // within 40..80 the vectors differ at 40, 41 and 61
+    assert!((ovsa::binary::similarity_range(&a, &b, 40..80).unwrap() - (1.0 - 3.0 / 40.0)).abs() < 1e-12);
+    assert_eq!(ovsa::binary::similarity_range(&a, &b, 0..200).unwrap(), ovsa::binary::similarity(&a, &b).unwrap());
+    assert_eq!(ovsa::binary::similarity_range(&a, &b, 100..199).unwrap(), 1.0);
+    // the trait default over range weights agrees with the in-place comparison
+    let weights: Vec<f64> = (0..200).map(|index| if (40..80).contains(&index) { 1.0 } else { 0.0 }).collect();
+    assert_eq!(a.similarity_range(&b, 40..80).unwrap(), a.weighted_similarity(&b, &weights).unwrap());
+
+    assert!(ovsa::binary::similarity_range(&a, &b, 150..201).is_err());
+    assert!(ovsa::binary::similarity_range(&a, &b, 50..50).is_err());
+    assert!(ovsa::binary::similarity_range(&a, &from_indices(100, &[3]).unwrap(), 0..10).is_err());
+
+    let mut memory = ovsa::memory::ItemMemory::new(200).unwrap();
+    memory.insert("a", a.clone()).unwrap();
+    memory.insert("b", b.clone()).unwrap();
+    let query = from_indices(200, &[41, 99]).unwrap();
+    assert_eq!(memory.query_range(&query, 1, 0..100).unwrap()[0].0, "b");
+    let batch = <sprs::CsVec<i8> as Hypervector>::similarities_range(std::slice::from_ref(&query), &[&a, &b], 0..100).unwrap();
+    assert_eq!(batch[0], vec![query.similarity_range(&a, 0..100).unwrap(), query.similarity_range(&b, 0..100).unwrap()]);
+}
+
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_dense_similarity_range() {
+    use ovsa::hypervector::Hypervector;
+
+    let a = array![1.0f32, 0.0, 1.0, 2.0, -1.0, 5.0];
+    let b = array![1.0f32, 0.0, 2.0, 4.0, -2.0, -3.0];
+    assert!((ovsa::dense::similarity_range(&a, &b, 2..5).unwrap() - 1.0).abs() < 1e-6);
+    assert!((ovsa::dense::similarity_range(&a, &b, 0..6).unwrap() - ovsa::dense::similarity(&a, &b)).abs() < 1e-6);
+    assert!(ovsa::dense::similarity_range(&a, &b, 4..7).is_err());
+
+    let mut rng = OvsaRng::seed_from_u64(8);
+    let vectors: Vec<_> = (0..4).map(|_| ovsa::dense::random_uniform_with_rng(64, -1.0, 1.0, &mut rng).unwrap()).collect();
+    let batch = <ndarray::Array1<f32> as Hypervector>::similarities_range(&vectors[..2], &vectors.iter().collect::<Vec<_>>(), 10..30).unwrap();
+    for (query, row) in vectors.iter().zip(&batch) {
+        for (vector, similarity) in vectors.iter().zip(row) {
+            assert!((similarity - query.similarity_range(vector, 10..30).unwrap()).abs() < 1e-5);
+        }
+    }
+    assert!(<ndarray::Array1<f32> as Hypervector>::similarities_range(&vectors[..1], &[&vectors[1]], 60..65).is_err());
+}
@@ -0,0 +1,146 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::binary::matrix::{HvMatrix, pack, unpack};
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+fn random_rows(n_rows: usize, dimension: usize, seed: u64) -> Vec<sprs::CsVec<i8>> {
+    let mut rng = OvsaRng::seed_from_u64(seed);
+    (0..n_rows).map(|_| ovsa::binary::sparse_random_with_rng(dimension, dimension / 2, &mut rng).unwrap()).collect()
+}
+
+
+#[test]
+fn test_pack_round_trip() {
+    let vector = ovsa::binary::from_indices(130, &[0, 63, 64, 129]).unwrap();
+    let words = pack(&vector);
+    assert_eq!(words, vec![1 | 1 << 63, 1, 1 << 1]);
+    assert_eq!(unpack(130, &words), vector);
+
+    let rows = random_rows(3, 200, 1);
+    let matrix = HvMatrix::from_rows(200, &rows).unwrap();
+    assert_eq!((matrix.len(), matrix.words_per_row()), (3, 4));
+    for (i, row) in rows.iter().enumerate() {
+        assert_eq!(matrix.get(i), *row);
+        assert_eq!(matrix.row(i), pack(row).as_slice());
+    }
+    assert_eq!(matrix.popcounts(), vec![100; 3]);
+}
+
+#[test]
+fn test_row_operations_match_sparse_operations() {
+    let rows = random_rows(5, 1000, 2);
+    let matrix = HvMatrix::from_rows(1000, &rows).unwrap();
+    let other = &random_rows(1, 1000, 3)[0];
+
+    let distances = matrix.hamming_distances(other).unwrap();
+    let bound = matrix.xor_rows(other).unwrap();
+    for (i, row) in rows.iter().enumerate() {
+        assert_eq!(distances[i], ovsa::binary::hamming_distance(row, other));
+        assert_eq!(bound.get(i), ovsa::binary::xor(row, other).unwrap());
+    }
+
+    // an odd number of rows has no ties
+    assert_eq!(matrix.majority().unwrap(), ovsa::binary::consensus_sum(&rows).unwrap());
+
+    let block = matrix.hamming_block(&HvMatrix::from_rows(1000, [other]).unwrap()).unwrap();
+    assert_eq!(block.iter().map(|row| row[0]).collect::<Vec<_>>(), distances);
+
+    // the word-level variants of the default-less core agree with the sparse ones
+    assert_eq!(matrix.hamming_distances_words(&pack(other)).unwrap(), distances);
+    assert_eq!(matrix.xor_rows_words(&pack(other)).unwrap(), bound);
+}
+
+#[test]
+fn test_dimension_mismatches_are_rejected() {
+    let mut matrix = HvMatrix::new(100).unwrap();
+    assert!(matrix.push(&ovsa::binary::from_indices(99, &[1]).unwrap()).is_err());
+    assert!(matrix.push_words(&[0; 3]).is_err());
+    assert!(matrix.hamming_distances_words(&[0; 3]).is_err());
+    assert!(matrix.xor_rows_words(&[0; 1]).is_err());
+    assert!(matrix.majority().is_err());
+    assert!(HvMatrix::new(0).is_err());
+
+    // bits at or above the dimension would be counted by the row operations
+    assert!(matrix.push_words(&[0, 1 << 36]).is_err());
+    assert!(matrix.hamming_distances_words(&[0, 1 << 36]).is_err());
+    assert!(ovsa::binary::matrix::cyclic_shift_words(100, &[0, 1 << 36], 1).is_err());
+    matrix.push_words(&[0, 1 << 35]).unwrap();
+    assert_eq!(matrix.popcounts(), vec![1]);
+}
+
+#[test]
+fn test_memory_to_matrix() {
+    let rows = random_rows(4, 300, 4);
+    let mut memory = ItemMemory::new(300).unwrap();
+    for (i, row) in rows.iter().enumerate() {
+        memory.insert(&i.to_string(), row.clone()).unwrap();
+    }
+    let matrix = memory.to_matrix();
+    assert_eq!(matrix.len(), 4);
+    assert_eq!(matrix.get(2), rows[2]);
+}
+
+
+#[test]
+fn test_progressive_top_k_matches_exact_ranking() {
+    let rows = random_rows(300, 4096, 11);
+    let matrix = HvMatrix::from_rows(4096, &rows).unwrap();
+    let mut rng = OvsaRng::seed_from_u64(12);
+    let noise = ovsa::binary::sparse_random_with_rng(4096, 600, &mut rng).unwrap();
+    let query = ovsa::binary::xor(&rows[42], &noise).unwrap();
+
+    let exact = matrix.top_k_progressive(&query, 3, 1.0).unwrap();
+    let distances = matrix.hamming_distances(&query).unwrap();
+    let mut expected: Vec<usize> = (0..rows.len()).collect();
+    expected.sort_by_key(|&row| (distances[row], row));
+    assert_eq!(exact.iter().map(|(row, _)| *row).collect::<Vec<_>>(), expected[..3]);
+    assert!(exact[0].1.is_exact());
+    assert_eq!(exact[0].1.estimate, 1.0 - distances[42] as f64 / 4096.0);
+
+    // with statistical bounds, the clear winner is decided after a prefix of the words
+    let fast = matrix.top_k_progressive(&query, 1, 0.999).unwrap();
+    assert_eq!(fast[0].0, 42);
+    assert!(fast[0].1.n_words < matrix.words_per_row(), "{:?}", fast[0].1);
+    assert!(fast[0].1.lower <= fast[0].1.estimate && fast[0].1.estimate <= fast[0].1.upper);
+
+    assert!(matrix.top_k_progressive(&query, 1, 0.0).is_err());
+    assert!(matrix.top_k_progressive(&rows[0], 0, 0.9).unwrap().is_empty());
+}
+
+
+#[test]
+fn test_memory_progressive_cleanup() {
+    let rows = random_rows(50, 1000, 13);
+    let mut memory = ItemMemory::new(1000).unwrap();
+    for (index, row) in rows.iter().enumerate() {
+        memory.insert(&format!("item{}", index), row.clone()).unwrap();
+    }
+    let matrix = memory.to_matrix();
+
+    let (label, bounds) = memory.cleanup_progressive(&matrix, &rows[7], 0.99).unwrap().unwrap();
+    assert_eq!(label, "item7");
+    assert_eq!(bounds.estimate, 1.0);
+    let matches = memory.query_progressive(&matrix, &rows[7], 2, 1.0).unwrap();
+    assert_eq!(matches[0].0, "item7");
+    assert_eq!(matches[1].0, memory.query(&rows[7], 2).unwrap()[1].0);
+
+    assert!(memory.cleanup_progressive(&HvMatrix::new(1000).unwrap(), &rows[7], 0.99).is_err());
+}
+
+#[test]
+fn test_cyclic_shift_words_matches_sparse_shift() {
+    use ovsa::binary::matrix::cyclic_shift_words;
+    for (seed, dimension) in [3, 63, 64, 65, 128, 200, 1000].into_iter().enumerate() {
+        let vector = random_rows(1, dimension, seed as u64).remove(0);
+        let words = pack(&vector);
+        for shift in [0, 1, -1, 63, 64, 65, -130, 999, dimension as isize] {
+            let shifted = cyclic_shift_words(dimension, &words, shift).unwrap();
+            assert_eq!(unpack(dimension, &shifted), ovsa::binary::cyclic_shift(&vector, shift), "{dimension} {shift}");
+            assert_eq!(shifted, pack(&ovsa::binary::cyclic_shift(&vector, shift)));
+        }
+    }
+    assert!(cyclic_shift_words(100, &[0], 1).is_err());
+}
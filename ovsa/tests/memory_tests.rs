@@ -0,0 +1,356 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::memory::ItemMemory;
+use ovsa::memory::sharded::ShardedItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_item_memory_cleanup() {
+    let dimension = 1000;
+    let mut rng = OvsaRng::seed_from_u64(1);
+    let mut memory = ItemMemory::new(dimension).unwrap();
+    for label in ["a", "b", "c"] {
+        memory.insert(label, ovsa::binary::sparse_random_with_rng(dimension, 50, &mut rng).unwrap()).unwrap();
+    }
+
+    let query = memory.get("b").unwrap().clone();
+    let (label, similarity) = memory.cleanup(&query).unwrap().unwrap();
+    assert_eq!(label, "b");
+    assert_eq!(similarity, 1.0);
+}
+
+#[test]
+fn test_item_memory_insert_replaces() {
+    let mut memory = ItemMemory::new(10).unwrap();
+    memory.insert("a", ovsa::binary::from_indices(10, &[1]).unwrap()).unwrap();
+    memory.insert("a", ovsa::binary::from_indices(10, &[2]).unwrap()).unwrap();
+    assert_eq!(memory.len(), 1);
+    assert_eq!(memory.get("a").unwrap()[2], 1);
+}
+
+#[test]
+fn test_item_memory_remove() {
+    use ovsa::memory::DuplicatePolicy;
+
+    let mut memory = ItemMemory::new(10).unwrap();
+    for (label, index) in [("a", 1), ("b", 4), ("c", 7)] {
+        memory.insert(label, ovsa::binary::from_indices(10, &[index, index + 1]).unwrap()).unwrap();
+    }
+    memory.insert_checked("b2", ovsa::binary::from_indices(10, &[4, 5]).unwrap(), 0.9, DuplicatePolicy::Merge).unwrap();
+    memory.insert_checked("c2", ovsa::binary::from_indices(10, &[7, 8]).unwrap(), 0.9, DuplicatePolicy::Merge).unwrap();
+
+    // removing an alias keeps the entry it points to
+    assert!(memory.remove("c2"));
+    assert!(memory.get("c2").is_none() && memory.get("c").is_some());
+    // removing an entry removes its aliases and keeps the later entries reachable
+    assert!(memory.remove("b"));
+    assert_eq!(memory.labels(), ["a", "c"]);
+    assert!(memory.get("b2").is_none());
+    assert_eq!(memory.get("c").unwrap().indices(), &[7, 8]);
+    assert_eq!(memory.cleanup(&ovsa::binary::from_indices(10, &[7, 8]).unwrap()).unwrap().unwrap().0, "c");
+    assert!(!memory.remove("b"));
+}
+
+#[test]
+fn test_item_memory_dimension_mismatch() {
+    let mut memory = ItemMemory::new(10).unwrap();
+    let result = memory.insert("a", ovsa::binary::from_indices(20, &[1]).unwrap());
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::VectorSizeMismatch)));
+}
+
+#[test]
+fn test_sharded_query_matches_single_memory() {
+    let dimension = 1000;
+    let mut rng = OvsaRng::seed_from_u64(2);
+    let mut memory = ItemMemory::new(dimension).unwrap();
+    let mut sharded = ShardedItemMemory::new(dimension, 4).unwrap();
+    for i in 0..40 {
+        let vector = ovsa::binary::sparse_random_with_rng(dimension, 100, &mut rng).unwrap();
+        memory.insert(&i.to_string(), vector.clone()).unwrap();
+        sharded.insert(&i.to_string(), vector).unwrap();
+    }
+    assert_eq!(sharded.len(), 40);
+
+    let query = ovsa::binary::sparse_random_with_rng(dimension, 100, &mut rng).unwrap();
+    assert_eq!(sharded.query(&query, 5).unwrap(), memory.query(&query, 5).unwrap());
+}
+
+#[test]
+fn test_memory_usage() {
+    let mut memory = ItemMemory::new(1000).unwrap();
+    memory.insert("ab", ovsa::binary::from_indices(1000, &[1, 2, 3]).unwrap()).unwrap();
+    memory.insert("cd", ovsa::binary::from_indices(1000, &[4]).unwrap()).unwrap();
+
+    let usage = memory.memory_usage();
+    let inline = std::mem::size_of::<sprs::CsVec<i8>>();
+    assert_eq!(usage.n_vectors, 2);
+    assert_eq!(usage.vector_bytes, 2 * inline + 4 * 9);
+    assert_eq!(usage.label_bytes, 2 * (4 + 2 * std::mem::size_of::<String>()));
+    assert_eq!(usage.bytes_per_vector(), inline + 18);
+    assert_eq!(usage.total_bytes(), usage.vector_bytes + usage.label_bytes + usage.auxiliary_bytes);
+
+    #[cfg(feature = "dense")]
+    {
+        let dense: ItemMemory<ndarray::Array1<f32>> = ItemMemory::new(1000).unwrap();
+        assert_eq!(dense.memory_usage().bytes_per_vector(), 0);
+    }
+}
+
+#[test]
+fn test_query_view_and_cleanup_ref() {
+    let dimension = 1000;
+    let mut rng = OvsaRng::seed_from_u64(9);
+    let mut memory = ItemMemory::new(dimension).unwrap();
+    for label in ["a", "b", "c"] {
+        memory.insert(label, ovsa::binary::sparse_random_with_rng(dimension, 500, &mut rng).unwrap()).unwrap();
+    }
+    let query = ovsa::binary::sparse_random_with_rng(dimension, 500, &mut rng).unwrap();
+
+    assert_eq!(memory.query_view(query.view(), 3).unwrap(), memory.query(&query, 3).unwrap());
+    assert_eq!(memory.query_view(memory.get_view("b").unwrap(), 1).unwrap()[0].0, "b");
+
+    let (label, vector, similarity) = memory.cleanup_ref(memory.get("c").unwrap()).unwrap().unwrap();
+    assert_eq!(label, "c");
+    assert!(std::ptr::eq(vector, memory.get("c").unwrap()));
+    assert_eq!(similarity, 1.0);
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_dense_query_view() {
+    let mut memory: ItemMemory<ndarray::Array1<f32>> = ItemMemory::new(3).unwrap();
+    memory.insert("x", ndarray::array![1.0, 0.0, 0.0]).unwrap();
+    memory.insert("y", ndarray::array![0.0, 1.0, 0.0]).unwrap();
+
+    let rows = ndarray::array![[0.1f32, 0.9, 0.0], [2.0, 0.0, 0.1]];
+    assert_eq!(memory.query_view(rows.row(0), 1).unwrap()[0].0, "y");
+    assert_eq!(memory.query_view(rows.row(1), 1).unwrap()[0].0, "x");
+}
+
+#[test]
+fn test_insert_checked_policies() {
+    use ovsa::memory::{DuplicatePolicy, InsertOutcome};
+
+    let mut memory = ItemMemory::new(10).unwrap();
+    memory.insert("a", ovsa::binary::from_indices(10, &[1, 2, 3]).unwrap()).unwrap();
+    memory.insert("b", ovsa::binary::from_indices(10, &[6, 7, 8]).unwrap()).unwrap();
+    let near_a = ovsa::binary::from_indices(10, &[1, 2, 4]).unwrap();
+
+    let report = memory.insert_checked("c", near_a.clone(), 0.7, DuplicatePolicy::Reject).unwrap();
+    assert_eq!(report.outcome, InsertOutcome::Rejected);
+    assert_eq!(report.collisions, vec![("a".to_string(), 0.8)]);
+    assert!(memory.get("c").is_none());
+
+    let report = memory.insert_checked("c", near_a.clone(), 0.7, DuplicatePolicy::Merge).unwrap();
+    assert_eq!(report.outcome, InsertOutcome::Merged("a".to_string()));
+    assert_eq!(memory.get("c"), memory.get("a"));
+    assert_eq!(memory.len(), 2);
+
+    // storing under a merged label must not overwrite the entry it aliases
+    memory.insert("c", near_a.clone()).unwrap();
+    assert_eq!(memory.len(), 3);
+    assert_eq!(memory.get("a").unwrap().indices(), &[1, 2, 3]);
+
+    let report = memory.insert_checked("d", near_a, 0.9, DuplicatePolicy::Allow).unwrap();
+    assert_eq!(report.outcome, InsertOutcome::Inserted);
+    assert_eq!(report.collisions, vec![("c".to_string(), 1.0)]);
+    assert_eq!(memory.len(), 4);
+}
+
+#[test]
+fn test_insert_checked_replacement_is_not_a_collision() {
+    use ovsa::memory::{DuplicatePolicy, InsertOutcome};
+
+    let mut memory = ItemMemory::new(10).unwrap();
+    memory.insert("a", ovsa::binary::from_indices(10, &[1, 2, 3]).unwrap()).unwrap();
+    let report = memory.insert_checked("a", ovsa::binary::from_indices(10, &[1, 2, 3]).unwrap(), 0.5, DuplicatePolicy::Reject).unwrap();
+    assert_eq!(report.outcome, InsertOutcome::Inserted);
+    assert!(report.collisions.is_empty());
+}
+
+#[test]
+fn test_concurrent_memory_reads_while_writing() {
+    use std::sync::Arc;
+    use ovsa::memory::concurrent::ConcurrentItemMemory;
+
+    let memory = Arc::new(ConcurrentItemMemory::new(1000).unwrap());
+    let symbol = memory.symbol("a", 500).unwrap();
+    let snapshot = memory.snapshot();
+
+    let handles: Vec<_> = (0..4).map(|thread| {
+        let memory = Arc::clone(&memory);
+        let symbol = symbol.clone();
+        std::thread::spawn(move || {
+            for i in 0..10 {
+                memory.symbol(&format!("{thread}:{i}"), 500).unwrap();
+                assert_eq!(memory.cleanup(&symbol).unwrap().unwrap().0, "a");
+            }
+        })
+    }).collect();
+    handles.into_iter().for_each(|handle| handle.join().unwrap());
+
+    assert_eq!(memory.len(), 41);
+    assert_eq!(memory.symbol("a", 500).unwrap(), symbol);
+    // a snapshot is not affected by later writes
+    assert_eq!(snapshot.len(), 1);
+}
+
+#[test]
+fn test_concurrent_memory_failed_update_leaves_memory_unchanged() {
+    use ovsa::memory::concurrent::ConcurrentItemMemory;
+
+    let memory = ConcurrentItemMemory::new(10).unwrap();
+    memory.insert("a", ovsa::binary::from_indices(10, &[1]).unwrap()).unwrap();
+    let result: Result<(), &str> = memory.try_update(|memory| {
+        memory.insert("b", ovsa::binary::from_indices(10, &[2]).unwrap()).unwrap();
+        memory.remove("a");
+        Err("persisting failed")
+    });
+
+    assert_eq!(result, Err("persisting failed"));
+    assert_eq!(memory.len(), 1);
+    assert!(memory.get("a").is_some() && memory.get("b").is_none());
+    assert!(memory.try_update(|memory| memory.insert("b", ovsa::binary::from_indices(10, &[2]).unwrap())).is_ok());
+    assert_eq!(memory.len(), 2);
+}
+
+#[test]
+fn test_snapshot_diff_and_merge() {
+    use ovsa::memory::MergePolicy;
+
+    let mut main = ItemMemory::new(10).unwrap();
+    main.insert("a", ovsa::binary::from_indices(10, &[1]).unwrap()).unwrap();
+    main.insert("b", ovsa::binary::from_indices(10, &[2]).unwrap()).unwrap();
+
+    let mut branch = main.snapshot();
+    branch.insert("c", ovsa::binary::from_indices(10, &[3]).unwrap()).unwrap();
+    branch.insert("b", ovsa::binary::from_indices(10, &[4]).unwrap()).unwrap();
+    assert_eq!(main.len(), 2);
+    assert_eq!(main.get("b").unwrap().indices(), &[2]);
+
+    let diff = branch.diff(&main);
+    assert_eq!(diff.added, vec!["c".to_string()]);
+    assert_eq!(diff.changed, vec!["b".to_string()]);
+    assert!(diff.removed.is_empty());
+    assert_eq!(main.diff(&branch).removed, vec!["c".to_string()]);
+    assert!(main.diff(&main.snapshot()).is_empty());
+
+    let conflicts = main.merge(&branch, MergePolicy::KeepOurs).unwrap();
+    assert_eq!(conflicts, vec!["b".to_string()]);
+    assert_eq!(main.labels(), &["a", "b", "c"]);
+    assert_eq!(main.get("b").unwrap().indices(), &[2]);
+
+    main.merge(&branch, MergePolicy::TakeTheirs).unwrap();
+    assert_eq!(main.get("b").unwrap().indices(), &[4]);
+    assert!(main.diff(&branch).is_empty());
+}
+
+#[test]
+fn test_namespaced_queries() {
+    use ovsa::memory::scoped;
+
+    let mut memory = ItemMemory::new(10).unwrap();
+    memory.insert(&scoped("colors", "red"), ovsa::binary::from_indices(10, &[1, 2]).unwrap()).unwrap();
+    memory.insert(&scoped("shapes", "square"), ovsa::binary::from_indices(10, &[1, 2, 3]).unwrap()).unwrap();
+    memory.insert("colors/warm/orange", ovsa::binary::from_indices(10, &[5, 6]).unwrap()).unwrap();
+    memory.insert("colorset", ovsa::binary::from_indices(10, &[1, 2, 3]).unwrap()).unwrap();
+
+    assert_eq!(memory.labels_in("colors").collect::<Vec<_>>(), vec!["colors/red", "colors/warm/orange"]);
+
+    let query = ovsa::binary::from_indices(10, &[1, 2, 3]).unwrap();
+    assert_eq!(memory.cleanup(&query).unwrap().unwrap().1, 1.0);
+    let (label, similarity) = memory.cleanup_in("colors", &query).unwrap().unwrap();
+    assert_eq!(label, "colors/red");
+    assert_eq!(similarity, 0.9);
+    assert_eq!(memory.query_in("colors", &query, 5).unwrap().len(), 2);
+    assert!(memory.cleanup_in("sizes", &query).unwrap().is_none());
+
+    let colors = memory.namespace("colors");
+    assert_eq!(colors.labels(), &["red", "warm/orange"]);
+    assert_eq!(colors.get("red"), memory.get("colors/red"));
+}
+
+#[test]
+fn test_bulk_building() {
+    let labels = ["a", "b", "a", "c"];
+    let memory = ItemMemory::build_from_labels_with_rng(100, &labels, 10, &mut OvsaRng::seed_from_u64(14)).unwrap();
+    assert_eq!(memory.labels(), &["a", "b", "c"]);
+    assert!(memory.iter().all(|(_, vector)| vector.nnz() == 10));
+    let again = ItemMemory::build_from_labels_with_rng(100, &labels, 10, &mut OvsaRng::seed_from_u64(14)).unwrap();
+    assert_eq!(again.get("c"), memory.get("c"));
+
+    let mut copy = ItemMemory::new(100).unwrap();
+    copy.extend_from_iter(memory.iter().map(|(label, vector)| (label, vector.clone()))).unwrap();
+    assert_eq!(copy.labels(), memory.labels());
+    let result = copy.extend_from_iter([("d", ovsa::binary::from_indices(50, &[1]).unwrap())]);
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::VectorSizeMismatch)));
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_parallel_bulk_building_is_deterministic() {
+    let labels: Vec<String> = (0..100).map(|i| format!("symbol{i}")).collect();
+    let labels: Vec<&str> = labels.iter().map(|label| label.as_str()).collect();
+    let memory = ItemMemory::par_build_from_labels(1000, &labels, 50, 3).unwrap();
+    assert_eq!(memory.len(), 100);
+    assert_eq!(memory.get("symbol7"), ItemMemory::par_build_from_labels(1000, &labels, 50, 3).unwrap().get("symbol7"));
+    assert_ne!(memory.get("symbol7"), memory.get("symbol8"));
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_parallel_bulk_building_matches_serial() {
+    let labels = ["a", "b", "a", "c"];
+    let parallel = ItemMemory::par_build_from_labels(1000, &labels, 50, 4).unwrap();
+    let serial = ItemMemory::build_from_labels_seeded(1000, &labels, 50, 4).unwrap();
+    assert_eq!(parallel.labels(), serial.labels());
+    for label in ["a", "b", "c"] {
+        assert_eq!(parallel.get(label), serial.get(label));
+    }
+}
+
+#[test]
+fn test_query_batch_matches_single_queries() {
+    let mut rng = OvsaRng::seed_from_u64(21);
+    let mut memory = ItemMemory::new(500).unwrap();
+    // more entries and queries than one tile, with duplicated vectors to exercise the tie ordering
+    for i in 0..600 {
+        memory.insert(&format!("{:03}", i), ovsa::binary::sparse_random_with_rng(500, 50, &mut rng).unwrap()).unwrap();
+    }
+    let duplicate = memory.get("100").unwrap().clone();
+    memory.insert("duplicate", duplicate).unwrap();
+    let queries: Vec<_> = (0..70).map(|i| memory.get(&format!("{:03}", i * 7)).unwrap().clone()).collect();
+
+    let batch = memory.query_batch(&queries, 5).unwrap();
+    assert_eq!(batch.len(), queries.len());
+    for (query, matches) in queries.iter().zip(&batch) {
+        assert_eq!(*matches, memory.query(query, 5).unwrap());
+    }
+    assert_eq!(memory.query_batch(&queries[..1], usize::MAX).unwrap()[0].len(), memory.len());
+    assert!(memory.query_batch(&[ovsa::binary::from_indices(10, &[1]).unwrap()], 1).is_err());
+}
+
+#[test]
+fn test_recognize_or_insert_grows_memory() {
+    use ovsa::memory::Recognition;
+    let mut rng = OvsaRng::seed_from_u64(50);
+    let mut memory = ItemMemory::new(1000).unwrap();
+    memory.insert("unknown0", ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap()).unwrap();
+    let novel = ovsa::binary::sparse_random_with_rng(1000, 500, &mut rng).unwrap();
+
+    let first = memory.recognize_or_insert(&novel, 0.8, "unknown").unwrap();
+    let Recognition::Novel { label, closest } = first else { panic!("{first:?}") };
+    assert_eq!(label, "unknown1");
+    assert_eq!(closest.unwrap().0, "unknown0");
+    assert_eq!(memory.len(), 2);
+
+    let noise = ovsa::binary::sparse_random_with_rng(1000, 50, &mut rng).unwrap();
+    let again = memory.recognize_or_insert(&ovsa::binary::xor(&novel, &noise).unwrap(), 0.8, "unknown").unwrap();
+    assert!(matches!(again, Recognition::Known { ref label, similarity } if label == "unknown1" && similarity > 0.8), "{again:?}");
+    assert_eq!(memory.len(), 2);
+
+    let mut empty = ItemMemory::new(1000).unwrap();
+    assert!(matches!(empty.recognize_or_insert(&novel, 0.8, "c").unwrap(), Recognition::Novel { ref label, closest: None } if label == "c0"));
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "async")]
+
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use rand::SeedableRng;
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+
+#[test]
+fn test_spawn() {
+    assert_eq!(block_on(ovsa::nonblocking::spawn(|| 1 + 2)), 3);
+}
+
+#[test]
+fn test_map_batch() {
+    let encoded = block_on(ovsa::nonblocking::map_batch(vec![vec![1usize], vec![2, 3]], |indices| ovsa::binary::from_indices(10, indices))).unwrap();
+    assert_eq!(encoded.len(), 2);
+    assert_eq!(encoded[1].nnz(), 2);
+}
+
+#[test]
+fn test_query_batch() {
+    let dimension = 1000;
+    let mut rng = OvsaRng::seed_from_u64(5);
+    let mut memory = ItemMemory::new(dimension).unwrap();
+    for label in ["a", "b", "c"] {
+        memory.insert(label, ovsa::binary::sparse_random_with_rng(dimension, 50, &mut rng).unwrap()).unwrap();
+    }
+    let queries = vec![memory.get("c").unwrap().clone(), memory.get("a").unwrap().clone()];
+
+    let results = block_on(ovsa::nonblocking::query_batch(Arc::new(memory), queries, 1)).unwrap();
+    assert_eq!(results[0][0].0, "c");
+    assert_eq!(results[1][0].0, "a");
+}
+
+#[test]
+fn test_cancelled_batches_stop() {
+    let token = ovsa::progress::CancellationToken::new();
+    let memory = Arc::new(ItemMemory::new(10).unwrap());
+    let queries = vec![ovsa::binary::from_indices(10, &[1]).unwrap(); 4];
+    assert!(block_on(ovsa::nonblocking::query_batch_cancellable(Arc::clone(&memory), queries.clone(), 1, token.clone())).is_ok());
+
+    token.cancel();
+    let result = block_on(ovsa::nonblocking::query_batch_cancellable(memory, queries, 1, token));
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::Cancelled)));
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "sparse")]
+
+use ovsa::pipelines::LanguageId;
+
+
+fn corpus() -> Vec<(String, String)> {
+    [
+        ("the quick brown fox jumps over the lazy dog while the cat is sleeping in the sun", "en"),
+        ("it was the best of times and it was the worst of times for everyone in the city", "en"),
+        ("der schnelle braune fuchs springt über den faulen hund während die katze schläft", "de"),
+        ("es war einmal ein könig der hatte drei töchter und lebte in einem großen schloss", "de"),
+        ("el rápido zorro marrón salta sobre el perro perezoso mientras el gato duerme", "es"),
+        ("había una vez un rey que tenía tres hijas y vivía en un gran castillo junto al mar", "es"),
+    ].iter().map(|(text, language)| (text.to_string(), language.to_string())).collect()
+}
+
+
+#[test]
+fn test_language_id() {
+    ovsa::rng::set_global_seed(12);
+    let mut language_id = LanguageId::new(10000, 3).unwrap();
+    language_id.train(&corpus()).unwrap();
+    assert_eq!(language_id.classifier().prototypes().len(), 3);
+
+    assert_eq!(language_id.identify("the dog was sleeping in the city").unwrap().unwrap().0, "en");
+    assert_eq!(language_id.identify("die katze und der hund schlafen").unwrap().unwrap().0, "de");
+    assert_eq!(language_id.identify("el perro y el gato duermen en el castillo").unwrap().unwrap().0, "es");
+}
+
+#[test]
+fn test_language_id_untrained() {
+    let mut language_id = LanguageId::new(1000, 3).unwrap();
+    assert!(language_id.identify("hello").unwrap().is_none());
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_sparse_random_with_rng_reproducible() {
+    let mut rng1 = OvsaRng::seed_from_u64(42);
+    let mut rng2 = OvsaRng::seed_from_u64(42);
+    let vec1 = ovsa::binary::sparse_random_with_rng(1000, 50, &mut rng1).unwrap();
+    let vec2 = ovsa::binary::sparse_random_with_rng(1000, 50, &mut rng2).unwrap();
+    assert_eq!(vec1, vec2);
+}
+
+#[test]
+fn test_consensus_sum_with_rng_reproducible() {
+    let dimension = 1000;
+    let vec1 = ovsa::binary::from_indices(dimension, &(0..500).collect::<Vec<usize>>()).unwrap();
+    let vec2 = ovsa::binary::from_indices(dimension, &(500..1000).collect::<Vec<usize>>()).unwrap();
+    let vectors = [vec1, vec2];
+
+    // every index is a tie, so the result depends entirely on the generator
+    let consensus1 = ovsa::binary::consensus_sum_with_rng(&vectors, &mut OvsaRng::seed_from_u64(7)).unwrap();
+    let consensus2 = ovsa::binary::consensus_sum_with_rng(&vectors, &mut OvsaRng::seed_from_u64(7)).unwrap();
+    assert_eq!(consensus1, consensus2);
+}
+
+#[test]
+#[cfg(feature = "dense")]
+fn test_random_uniform_with_rng_reproducible() {
+    let vec1 = ovsa::dense::random_uniform_with_rng(100, -1.0, 1.0, &mut OvsaRng::seed_from_u64(3)).unwrap();
+    let vec2 = ovsa::dense::random_uniform_with_rng(100, -1.0, 1.0, &mut OvsaRng::seed_from_u64(3)).unwrap();
+    assert_eq!(vec1, vec2);
+}
+
+#[test]
+fn test_global_seed() {
+    ovsa::rng::set_global_seed(11);
+    let vec1 = ovsa::binary::sparse_random(1000, 50).unwrap();
+    ovsa::rng::set_global_seed(11);
+    let vec2 = ovsa::binary::sparse_random(1000, 50).unwrap();
+    assert_eq!(vec1, vec2);
+}
+
+#[test]
+fn test_pcg64_matches_reference() {
+    use rand::RngCore;
+    use ovsa::rng::algorithms::Pcg64;
+
+    // the first outputs of the reference implementation's pcg64 demo
+    let mut rng = Pcg64::new(42, 54);
+    let expected = [0x86b1da1d72062b68, 0x1304aa46c9853d39, 0xa3670e9e0dd50358, 0xf9090e529a7dae00, 0xc85b9fd837996f2c, 0x606121f8e3919196];
+    for value in expected {
+        assert_eq!(rng.next_u64(), value);
+    }
+}
+
+#[test]
+fn test_xoshiro_matches_reference() {
+    use rand::RngCore;
+    use ovsa::rng::algorithms::Xoshiro256PlusPlus;
+
+    let mut seed = [0u8; 32];
+    for (i, word) in [1u64, 2, 3, 4].iter().enumerate() {
+        seed[8 * i..8 * (i + 1)].copy_from_slice(&word.to_le_bytes());
+    }
+    let mut rng = Xoshiro256PlusPlus::from_seed(seed);
+    assert_eq!(rng.next_u64(), 41943041);
+    assert_eq!(rng.next_u64(), 58720359);
+    assert_eq!(rng.next_u64(), 3588806011781223);
+
+    // the all-zero state would only ever produce zeros
+    let mut zero = Xoshiro256PlusPlus::from_seed([0; 32]);
+    assert_ne!(zero.next_u64(), 0);
+}
+
+#[test]
+fn test_explicit_algorithm_is_reproducible() {
+    use ovsa::rng::algorithms::{Pcg64, Xoshiro256PlusPlus};
+
+    let vec1 = ovsa::binary::sparse_random_with_rng(1000, 50, &mut Pcg64::seed_from_u64(5)).unwrap();
+    let vec2 = ovsa::binary::sparse_random_with_rng(1000, 50, &mut Pcg64::seed_from_u64(5)).unwrap();
+    let vec3 = ovsa::binary::sparse_random_with_rng(1000, 50, &mut Xoshiro256PlusPlus::seed_from_u64(5)).unwrap();
+    assert_eq!(vec1, vec2);
+    assert_ne!(vec1, vec3);
+    assert!(["chacha12", "xoshiro256++", "pcg64"].contains(&OvsaRng::algorithm()));
+}
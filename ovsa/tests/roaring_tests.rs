@@ -0,0 +1,67 @@
+#![cfg(feature = "roaring")]
+
+use rand::SeedableRng;
+use ovsa::binary::roaring::RoaringBinary;
+use ovsa::hypervector::Hypervector;
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+#[test]
+fn test_checked_conversions() {
+    let mut rng = OvsaRng::seed_from_u64(1);
+    let sparse = ovsa::binary::sparse_random_with_rng(200000, 20000, &mut rng).unwrap();
+    let roaring = RoaringBinary::try_from(&sparse).unwrap();
+    assert_eq!((roaring.dim(), roaring.nnz()), (200000, 20000));
+    assert!(roaring.indices().eq(sparse.indices().iter().copied()));
+    assert_eq!(roaring.to_sparse(), sparse);
+    assert_eq!(sprs::CsVec::from(&roaring), sparse);
+    // at 10% density the three full chunks are 8 KiB bitsets and the last, 3392 dimensions long, an array
+    let last = roaring.indices().filter(|&index| index >= 3 * 65536).count();
+    assert_eq!(roaring.heap_bytes(), 3 * 8192 + 2 * last);
+    assert!(roaring.heap_bytes() * 4 < sparse.heap_bytes());
+
+    let built = RoaringBinary::from_indices(10, &[7, 2, 7, 0]).unwrap();
+    assert!(built.indices().eq([0, 2, 7]));
+    assert!(built.contains(2) && !built.contains(3) && !built.contains(usize::MAX));
+    assert_eq!(RoaringBinary::from_bitmap(10, built.bitmap().clone()).unwrap(), built);
+    assert!(RoaringBinary::from_indices(10, &[10]).is_err());
+    assert!(RoaringBinary::from_bitmap(7, built.bitmap().clone()).is_err());
+}
+
+#[test]
+fn test_set_operations_match_sparse() {
+    let mut rng = OvsaRng::seed_from_u64(2);
+    let vectors: Vec<_> = [10, 3000, 40000].iter().map(|&n_active| ovsa::binary::sparse_random_with_rng(100000, n_active, &mut rng).unwrap()).collect();
+    let weights: Vec<f64> = (0..100000).map(|index| (index % 7) as f64).collect();
+    for a in &vectors {
+        for b in &vectors {
+            let (roaring_a, roaring_b) = (RoaringBinary::try_from(a).unwrap(), RoaringBinary::try_from(b).unwrap());
+            let overlap = a.indices().iter().filter(|index| b.indices().binary_search(index).is_ok()).count();
+            assert_eq!(roaring_a.overlap(&roaring_b).unwrap(), overlap);
+            assert_eq!(roaring_a.intersection(&roaring_b).unwrap().nnz(), overlap);
+            assert_eq!(roaring_a.union(&roaring_b).unwrap().nnz(), a.nnz() + b.nnz() - overlap);
+            assert_eq!(roaring_a.xor(&roaring_b).unwrap().to_sparse(), ovsa::binary::xor(a, b).unwrap());
+            assert_eq!(roaring_a.hamming_distance(&roaring_b).unwrap(), ovsa::binary::hamming_distance(a, b));
+            assert_eq!(roaring_a.similarity(&roaring_b).unwrap(), a.similarity(b).unwrap());
+            assert!((roaring_a.weighted_similarity(&roaring_b, &weights).unwrap() - a.weighted_similarity(b, &weights).unwrap()).abs() < 1e-9);
+        }
+    }
+    let other = RoaringBinary::from_indices(100, &[1]).unwrap();
+    assert!(RoaringBinary::try_from(&vectors[0]).unwrap().xor(&other).is_err());
+}
+
+#[test]
+fn test_roaring_memory() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let mut memory = ItemMemory::new(70000).unwrap();
+    for i in 0..30 {
+        memory.insert(&format!("symbol{}", i), ovsa::binary::sparse_random_with_rng(70000, 7000, &mut rng).unwrap()).unwrap();
+    }
+    let query = ovsa::binary::sparse_random_with_rng(70000, 7000, &mut rng).unwrap();
+
+    let roaring = memory.roaring().unwrap();
+    assert_eq!(roaring.query(&RoaringBinary::try_from(&query).unwrap(), 5).unwrap(), memory.query(&query, 5).unwrap());
+    assert!(roaring.memory_usage().vector_bytes * 2 < memory.memory_usage().vector_bytes);
+    assert_eq!(roaring.to_sparse().get("symbol7"), memory.get("symbol7"));
+}
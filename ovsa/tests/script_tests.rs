@@ -0,0 +1,82 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+use ovsa::script::{Interpreter, Outcome};
+
+
+fn interpreter() -> Interpreter {
+    let labels = ["a", "b", "c", "new york"];
+    let memory = ItemMemory::build_from_labels_seeded(4000, &labels, 2000, 1).unwrap();
+    Interpreter::new(memory).with_top_k(2)
+}
+
+
+#[test]
+fn test_expressions_match_the_algebra() {
+    let mut interpreter = interpreter();
+    let mut rng = OvsaRng::seed_from_u64(2);
+    let (a, b) = (interpreter.get("a").unwrap().clone(), interpreter.get("b").unwrap().clone());
+
+    assert_eq!(interpreter.execute_with_rng("x = bind(a, shift(b, 1))", &mut rng).unwrap(), Outcome::Assigned("x".to_string()));
+    let expected = ovsa::binary::xor(&a, &ovsa::binary::cyclic_shift(&b, 1)).unwrap();
+    assert_eq!(interpreter.get("x").unwrap(), &expected);
+
+    // unbinding recovers the other operand exactly
+    assert_eq!(interpreter.execute_with_rng("shift(unbind(x, a), -1) ~ b", &mut rng).unwrap(), Outcome::Similarity(1.0));
+    let Outcome::Matches(matches) = interpreter.execute_with_rng("bundle(a, \"new york\", random())  # a set", &mut rng).unwrap() else {
+        panic!("a bare expression is a query");
+    };
+    let mut labels: Vec<&str> = matches.iter().map(|(label, _)| label.as_str()).collect();
+    labels.sort();
+    assert_eq!(labels, ["a", "new york"]);
+
+    // variables shadow symbols
+    interpreter.execute_with_rng("a = c", &mut rng).unwrap();
+    assert_eq!(interpreter.execute_with_rng("a ~ c", &mut rng).unwrap(), Outcome::Similarity(1.0));
+    assert_eq!(interpreter.execute_with_rng("   # nothing", &mut rng).unwrap(), Outcome::Nothing);
+}
+
+
+#[test]
+fn test_nested_bundles_are_majorities_of_majorities() {
+    let mut interpreter = interpreter();
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let vectors: Vec<_> = ["a", "b", "c", "new york"].iter().map(|label| interpreter.get(label).unwrap().clone()).collect();
+    let shifted = ovsa::binary::cyclic_shift(&vectors[0], 1);
+
+    // odd numbers of terms never tie, so the result does not depend on the generator
+    interpreter.execute_with_rng("x = bundle(bundle(a, b, c), \"new york\", shift(a, 1))", &mut rng).unwrap();
+    let inner = ovsa::binary::consensus_sum(&vectors[..3]).unwrap();
+    let expected = ovsa::binary::consensus_sum(&[inner, vectors[3].clone(), shifted.clone()]).unwrap();
+    assert_eq!(interpreter.get("x").unwrap(), &expected);
+
+    // an index active in a, b and c but neither other term is active in the flat five-way majority only
+    let mut flat = vectors.clone();
+    flat.push(shifted);
+    assert_ne!(interpreter.get("x").unwrap(), &ovsa::binary::consensus_sum(&flat).unwrap());
+}
+
+#[test]
+fn test_run_reports_the_failing_line() {
+    let mut interpreter = interpreter();
+    let outcomes = interpreter.run("x = bind(a, b)\n\nx ~ bind(b, a)\n").unwrap();
+    assert_eq!(outcomes, [Outcome::Assigned("x".to_string()), Outcome::Nothing, Outcome::Similarity(1.0)]);
+    assert_eq!(outcomes[2].to_string(), "1.0000");
+
+    for (script, message) in [
+        ("x = a\ny = bind(x, d)", "line 2: unknown name d"),
+        ("bind(a)", "line 1: wrong arguments for bind: at least two vectors"),
+        ("shift(a, b)", "line 1: wrong arguments for shift: shift(vector, integer)"),
+        ("a ~ b c", "line 1: unexpected Name(\"c\") after the statement"),
+        ("frobnicate(a, b)", "line 1: unknown function frobnicate"),
+        ("bind(a, b", "line 1: expected ',' or ')' in the arguments of bind"),
+        ("x =", "line 1: unexpected end of line"),
+    ] {
+        match interpreter.run(script) {
+            Err(ovsa::errors::OVSAError::InvalidFormat(error)) => assert_eq!(error, message),
+            other => panic!("{:?} for {:?}", other, script),
+        }
+    }
+}
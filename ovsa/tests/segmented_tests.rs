@@ -0,0 +1,93 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::binary::from_indices;
+use ovsa::binary::segmented::SegmentedHV;
+use ovsa::hypervector::Hypervector;
+use ovsa::memory::ItemMemory;
+use ovsa::rng::OvsaRng;
+
+
+const LAYOUT: [(&str, usize); 2] = [("color", 200), ("shape", 400)];
+
+
+#[test]
+fn test_flat_round_trip() {
+    let flat = from_indices(600, &[0, 199, 200, 599]).unwrap();
+    let segmented = SegmentedHV::from_flat(&flat, &LAYOUT).unwrap();
+    assert_eq!(segmented.segment("color").unwrap().indices(), &[0, 199]);
+    assert_eq!(segmented.segment("shape").unwrap().indices(), &[0, 399]);
+    assert_eq!(segmented.to_flat(), flat);
+    assert_eq!(segmented.layout(), LAYOUT.to_vec());
+    assert_eq!(segmented.range("shape"), Some(200..600));
+    assert_eq!(segmented.dimension(), 600);
+
+    assert!(SegmentedHV::from_flat(&flat, &LAYOUT[..1]).is_err());
+    assert!(SegmentedHV::zeros(&[("a", 10), ("a", 10)]).is_err());
+    assert!(SegmentedHV::zeros(&[("a", 0)]).is_err());
+}
+
+
+#[test]
+fn test_segments_do_not_interfere() {
+    let mut rng = OvsaRng::seed_from_u64(1);
+    let red = ovsa::binary::sparse_random_with_rng(200, 100, &mut rng).unwrap();
+    let blue = ovsa::binary::sparse_random_with_rng(200, 100, &mut rng).unwrap();
+    let square = ovsa::binary::sparse_random_with_rng(400, 200, &mut rng).unwrap();
+
+    let mut red_square = SegmentedHV::zeros(&LAYOUT).unwrap();
+    red_square.set_segment("color", red.clone()).unwrap();
+    red_square.set_segment("shape", square.clone()).unwrap();
+    let mut blue_square = red_square.clone();
+    blue_square.set_segment("color", blue).unwrap();
+
+    // changing the color leaves the shape segment identical
+    assert_eq!(red_square.segment_similarity(&blue_square, "shape").unwrap(), 1.0);
+    let similarities = red_square.segment_similarities(&blue_square).unwrap();
+    assert_eq!(similarities[0].0, "color");
+    assert!(similarities[0].1 < 0.7);
+    let flat = ovsa::binary::similarity(&red_square.to_flat(), &blue_square.to_flat()).unwrap();
+    assert!((red_square.similarity(&blue_square).unwrap() - flat).abs() < 1e-12);
+
+    // emphasizing the shape makes the two objects more alike
+    let weights = red_square.segment_weights(&[("shape", 4.0)]).unwrap();
+    assert!(red_square.weighted_similarity(&blue_square, &weights).unwrap() > flat);
+    assert!(red_square.segment_weights(&[("size", 1.0)]).is_err());
+    assert!(red_square.set_segment("shape", red).is_err());
+}
+
+
+#[test]
+fn test_segment_wise_bind_and_bundle() {
+    let mut rng = OvsaRng::seed_from_u64(2);
+    let vectors: Vec<SegmentedHV> = (0..3).map(|_| SegmentedHV::random_with_rng(&LAYOUT, 0.5, &mut rng).unwrap()).collect();
+    assert_eq!(vectors[0].segment("shape").unwrap().nnz(), 200);
+
+    let bound = vectors[0].bind(&vectors[1]).unwrap();
+    assert_eq!(bound.bind(&vectors[1]).unwrap(), vectors[0]);
+    let key = ovsa::binary::sparse_random_with_rng(200, 100, &mut rng).unwrap();
+    let recolored = vectors[0].bind_segment("color", &key).unwrap();
+    assert_eq!(recolored.segment("shape"), vectors[0].segment("shape"));
+    assert_eq!(recolored.bind_segment("color", &key).unwrap(), vectors[0]);
+
+    let bundle = SegmentedHV::bundle_with_rng(&vectors, &mut rng).unwrap();
+    for vector in &vectors {
+        assert!(bundle.similarity(vector).unwrap() > 0.7);
+    }
+    let other = SegmentedHV::zeros(&[("shape", 400), ("color", 200)]).unwrap();
+    assert!(vectors[0].bind(&other).is_err());
+    assert!(SegmentedHV::bundle(&[]).is_err());
+    assert!(SegmentedHV::random(&LAYOUT, 0.0).is_err());
+}
+
+
+#[test]
+fn test_segmented_item_memory() {
+    let mut rng = OvsaRng::seed_from_u64(3);
+    let mut memory: ItemMemory<SegmentedHV> = ItemMemory::new(600).unwrap();
+    let vectors: Vec<SegmentedHV> = (0..4).map(|_| SegmentedHV::random_with_rng(&LAYOUT, 0.5, &mut rng).unwrap()).collect();
+    for (index, vector) in vectors.iter().enumerate() {
+        memory.insert(&format!("object{}", index), vector.clone()).unwrap();
+    }
+    assert_eq!(memory.cleanup(&vectors[2]).unwrap().unwrap(), ("object2".to_string(), 1.0));
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "sparse")]
+
+use ovsa::sketch::{SimHash, from_fingerprints, simhash64, to_fingerprints};
+
+
+#[test]
+fn test_simhash_approximates_angle() {
+    let simhash = SimHash::new(4096, 7).unwrap();
+    let a: Vec<f64> = (0..50).map(|i| (i as f64 * 0.37).sin()).collect();
+    let b: Vec<f64> = a.iter().enumerate().map(|(i, value)| value + 0.5 * (i as f64 * 1.3).cos()).collect();
+
+    let dot: f64 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+    let norms = a.iter().map(|x| x * x).sum::<f64>().sqrt() * b.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let expected = 1.0 - (dot / norms).acos() / std::f64::consts::PI;
+
+    let similarity = ovsa::binary::similarity(&simhash.encode_dense(&a).unwrap(), &simhash.encode_dense(&b).unwrap()).unwrap();
+    assert!((similarity - expected).abs() < 0.05, "{similarity} {expected}");
+}
+
+#[test]
+fn test_simhash_weighted_tokens_near_duplicates() {
+    let simhash = SimHash::new(256, 1).unwrap();
+    let original = simhash.encode_weighted(&[("the", 1.0), ("quick", 1.0), ("brown", 1.0), ("fox", 1.0), ("jumps", 1.0)]).unwrap();
+    let edited = simhash.encode_weighted(&[("the", 1.0), ("quick", 1.0), ("brown", 1.0), ("fox", 1.0), ("leaps", 1.0)]).unwrap();
+    let other = simhash.encode_weighted(&[("lorem", 1.0), ("ipsum", 1.0), ("dolor", 1.0)]).unwrap();
+    assert!(ovsa::binary::similarity(&original, &edited).unwrap() > ovsa::binary::similarity(&original, &other).unwrap());
+}
+
+#[test]
+fn test_simhash64_matches_classic_fingerprint() {
+    // a single feature with positive weight reproduces its own hash
+    let hash = 0xdead_beef_0123_4567u64;
+    let vector = simhash64(&[(hash, 2.0)]);
+    assert_eq!(to_fingerprints(&vector), vec![hash]);
+    assert_eq!(from_fingerprints(64, &[hash]).unwrap(), vector);
+    assert!(from_fingerprints(65, &[hash]).is_err());
+}
+
+#[test]
+fn test_minhash_estimates_jaccard() {
+    use ovsa::sketch::MinHash;
+
+    let minhash = MinHash::new(512, 16, 3).unwrap();
+    let a: Vec<String> = (0..100).map(|i| format!("item{i}")).collect();
+    let b: Vec<String> = (50..150).map(|i| format!("item{i}")).collect();
+    let expected = 50.0 / 150.0;
+
+    let (sa, sb) = (minhash.encode(&a).unwrap(), minhash.encode(&b).unwrap());
+    assert_eq!(sa.nnz(), 512);
+    assert_eq!(sa.dim(), minhash.dimension());
+    let estimate = minhash.estimate_jaccard(&sa, &sb).unwrap();
+    assert!((estimate - expected).abs() < 0.06, "{estimate}");
+    assert_eq!(minhash.estimate_jaccard(&sa, &sa).unwrap(), 1.0);
+
+    // duplicates and order do not matter
+    let mut shuffled = a.clone();
+    shuffled.reverse();
+    shuffled.push(a[0].clone());
+    assert_eq!(minhash.encode(&shuffled).unwrap(), sa);
+
+    assert!(matches!(MinHash::new(0, 16, 3), Err(ovsa::errors::OVSAError::ZeroDimension)));
+    assert!(matches!(MinHash::new(512, 1, 3), Err(ovsa::errors::OVSAError::InvalidArgument(message)) if message.contains("bucket_size")));
+}
+
+#[test]
+fn test_shingles() {
+    assert_eq!(ovsa::sketch::shingles("abcd", 3), vec!["abc", "bcd"]);
+    assert_eq!(ovsa::sketch::shingles("ab", 3), vec!["ab"]);
+    assert!(ovsa::sketch::shingles("", 3).is_empty());
+}
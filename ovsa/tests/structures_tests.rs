@@ -0,0 +1,82 @@
+#![cfg(feature = "sparse")]
+
+use rand::SeedableRng;
+use ovsa::rng::OvsaRng;
+use ovsa::structures::HdMultiset;
+
+
+#[test]
+fn test_multiset_estimates_counts() {
+    let mut rng = OvsaRng::seed_from_u64(6);
+    let elements: Vec<_> = (0..20).map(|_| ovsa::binary::sparse_random_with_rng(10000, 100, &mut rng).unwrap()).collect();
+    let mut multiset = HdMultiset::new(10000).unwrap();
+    for (count, element) in elements.iter().enumerate() {
+        multiset.insert_n(element, count).unwrap();
+    }
+    assert_eq!(multiset.total(), 190);
+
+    let noise = multiset.noise_std(100);
+    for (count, element) in elements.iter().enumerate() {
+        let estimate = multiset.estimate(element).unwrap();
+        assert!((estimate - count as f64).abs() < 4.0 * noise, "{count} {estimate}");
+    }
+
+    let absent = ovsa::binary::sparse_random_with_rng(10000, 100, &mut rng).unwrap();
+    assert!(multiset.estimate(&absent).unwrap().abs() < 4.0 * noise);
+}
+
+#[test]
+fn test_multiset_calibration_corrects_bias() {
+    let mut rng = OvsaRng::seed_from_u64(6);
+    let elements: Vec<_> = (0..5).map(|_| ovsa::binary::sparse_random_with_rng(1000, 100, &mut rng).unwrap()).collect();
+    let mut multiset = HdMultiset::new(1000).unwrap();
+    for (count, element) in elements.iter().enumerate() {
+        multiset.insert_n(element, 10 * count).unwrap();
+    }
+
+    let known: Vec<_> = elements.iter().enumerate().map(|(count, element)| (element, 10 * count)).collect();
+    let calibration = multiset.calibrate(&known).unwrap();
+    assert!((calibration.scale - 1.0).abs() < 0.2);
+    let error: f64 = known.iter().map(|&(element, count)| (multiset.estimate(element).unwrap() - count as f64).abs()).sum();
+    assert!(error / 5.0 < 2.0);
+}
+
+#[test]
+fn test_multiset_remove() {
+    let element = ovsa::binary::from_indices(100, &[1, 5, 9]).unwrap();
+    let mut multiset = HdMultiset::new(100).unwrap();
+    multiset.insert_n(&element, 2).unwrap();
+    multiset.remove(&element).unwrap();
+    assert_eq!(multiset.total(), 1);
+    multiset.remove(&element).unwrap();
+    assert!(multiset.remove(&element).is_err());
+}
+
+#[test]
+fn test_multiset_noise_with_repeated_elements() {
+    let mut rng = OvsaRng::seed_from_u64(7);
+    let elements: Vec<_> = (0..4).map(|_| ovsa::binary::sparse_random_with_rng(10000, 100, &mut rng).unwrap()).collect();
+    let mut repeated = HdMultiset::new(10000).unwrap();
+    let mut distinct = HdMultiset::new(10000).unwrap();
+    for element in &elements {
+        repeated.insert_n(element, 10).unwrap();
+        distinct.insert(element).unwrap();
+    }
+    // the same density with ten times the multiplicities has ten times the crosstalk
+    assert!((repeated.noise_std(100) / distinct.noise_std(100) - 10.0).abs() < 1e-9);
+    let before = repeated.clone();
+    repeated.insert_n(&elements[0], 0).unwrap();
+    assert_eq!(repeated.noise_std(100), before.noise_std(100));
+}
+
+#[test]
+fn test_multiset_counter_overflow() {
+    let element = ovsa::binary::from_indices(100, &[1, 5, 9]).unwrap();
+    let mut multiset = HdMultiset::new(100).unwrap();
+    multiset.insert_n(&element, u32::MAX as usize).unwrap();
+    assert!(matches!(multiset.insert(&element), Err(ovsa::errors::OVSAError::CounterOverflow)));
+    assert_eq!(multiset.counts()[1], u32::MAX);
+    assert_eq!(multiset.total(), u32::MAX as usize);
+    #[cfg(target_pointer_width = "64")]
+    assert!(HdMultiset::new(100).unwrap().insert_n(&element, u32::MAX as usize + 1).is_err());
+}
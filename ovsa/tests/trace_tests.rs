@@ -0,0 +1,61 @@
+#![cfg(feature = "trace")]
+
+use std::sync::{Arc, Mutex};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+
+/// Records the names of the opened spans and the number of `finished` events.
+#[derive(Default, Clone)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<String>>>,
+    finished: Arc<Mutex<usize>>,
+}
+
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span.metadata().name().to_string());
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        if event.metadata().fields().field("elapsed_us").is_some() {
+            *self.finished.lock().unwrap() += 1;
+        }
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+
+#[test]
+fn test_expensive_operations_are_traced() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let a = ovsa::binary::from_indices(10, &[1, 2]).unwrap();
+        let b = ovsa::binary::from_indices(10, &[2, 3]).unwrap();
+        ovsa::binary::consensus_sum(&[a.clone(), b.clone(), a.clone()]).unwrap();
+
+        let mut classifier = ovsa::learn::CentroidClassifier::new(10).unwrap();
+        classifier.fit(&[(a.clone(), "a".to_string()), (b, "b".to_string())]).unwrap();
+        classifier.predict(&a).unwrap();
+    });
+
+    let spans = recorder.spans.lock().unwrap().clone();
+    for name in ["consensus_sum", "fit", "cleanup"] {
+        assert!(spans.iter().any(|span| span == name), "{:?}", spans);
+    }
+    assert_eq!(*recorder.finished.lock().unwrap(), spans.len());
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "dense")]
+
+use rand::SeedableRng;
+use ovsa::rng::OvsaRng;
+use ovsa::vfa::{FractionalPowerEncoder, Kernel, VectorFunction, bandwidth_for_similarity, median_bandwidth};
+
+
+#[test]
+fn test_fpe_kernel_decays_with_distance() {
+    let mut rng = OvsaRng::seed_from_u64(9);
+    let encoder = FractionalPowerEncoder::new_with_rng(4000, 1, 1.0, &mut rng).unwrap();
+    assert!((encoder.kernel(&[0.5], &[0.5]).unwrap() - 1.0).abs() < 1e-4);
+    let near = encoder.kernel(&[0.0], &[0.5]).unwrap();
+    let far = encoder.kernel(&[0.0], &[3.0]).unwrap();
+    // a Gaussian kernel of bandwidth 1: exp(-0.125) and exp(-4.5)
+    assert!((near - 0.88).abs() < 0.05, "{near}");
+    assert!(far.abs() < 0.05, "{far}");
+}
+
+#[test]
+fn test_vector_function_fits_sine() {
+    let mut rng = OvsaRng::seed_from_u64(9);
+    let encoder = FractionalPowerEncoder::new_with_rng(4000, 1, 0.5, &mut rng).unwrap();
+    let mut function = VectorFunction::new(encoder);
+    let samples: Vec<(Vec<f64>, f64)> = (0..40).map(|i| {
+        let x = i as f64 * 0.15;
+        (vec![x], x.sin())
+    }).collect();
+
+    let error = function.fit(&samples, 20, 0.5).unwrap();
+    assert!(error < 0.05, "{error}");
+    // between the samples
+    let value = function.evaluate(&[1.0]).unwrap();
+    assert!((value - 1f64.sin()).abs() < 0.1, "{value}");
+}
+
+#[test]
+fn test_fpe_rejects_wrong_point_size() {
+    let mut rng = OvsaRng::seed_from_u64(9);
+    let encoder = FractionalPowerEncoder::new_with_rng(10, 2, 1.0, &mut rng).unwrap();
+    assert!(matches!(encoder.encode(&[1.0]), Err(ovsa::errors::OVSAError::VectorSizeMismatch)));
+    assert!(FractionalPowerEncoder::new_with_rng(11, 2, 1.0, &mut rng).is_err());
+}
+
+#[test]
+fn test_fit_reports_epoch_losses() {
+    use std::ops::ControlFlow;
+
+    let mut rng = OvsaRng::seed_from_u64(11);
+    let encoder = FractionalPowerEncoder::new_with_rng(2000, 1, 0.5, &mut rng).unwrap();
+    let samples: Vec<(Vec<f64>, f64)> = (0..20).map(|i| (vec![i as f64 / 5.0], (i as f64 / 5.0).sin())).collect();
+
+    let mut losses = Vec::new();
+    let mut function = VectorFunction::new(encoder);
+    let result = function.fit_with_progress(&samples, 10, 0.5, |progress| {
+        assert_eq!(progress.stage, "epoch");
+        losses.push(progress.loss.unwrap());
+        if progress.completed == 5 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+    });
+    assert!(matches!(result, Err(ovsa::errors::OVSAError::Cancelled)));
+    assert_eq!(losses.len(), 5);
+    assert!(losses[4] < losses[0], "{:?}", losses);
+}
+
+#[test]
+fn test_laplacian_kernel_encoder() {
+    let mut rng = OvsaRng::seed_from_u64(9);
+    let encoder = FractionalPowerEncoder::with_kernel_and_rng(8000, 2, Kernel::Laplacian, 1.0, &mut rng).unwrap();
+    assert_eq!(encoder.kernel_type(), Kernel::Laplacian);
+    assert_eq!(encoder.bandwidth(), 1.0);
+    for (a, b) in [([0.0, 0.0], [0.1, 0.2]), ([0.0, 0.0], [0.5, -0.5]), ([1.0, 1.0], [2.0, 2.0])] {
+        let expected = Kernel::Laplacian.evaluate(&a, &b, 1.0).unwrap();
+        let estimate = encoder.kernel(&a, &b).unwrap();
+        assert!((estimate - expected).abs() < 0.05, "{estimate} vs {expected}");
+    }
+    // the Laplacian kernel is sharper at the origin and heavier in the tails than the Gaussian
+    assert!(Kernel::Laplacian.evaluate(&[0.0], &[0.1], 1.0).unwrap() < Kernel::Gaussian.evaluate(&[0.0], &[0.1], 1.0).unwrap());
+    assert!(Kernel::Laplacian.evaluate(&[0.0], &[3.0], 1.0).unwrap() > Kernel::Gaussian.evaluate(&[0.0], &[3.0], 1.0).unwrap());
+}
+
+#[test]
+fn test_bandwidth_helpers() {
+    for kernel in [Kernel::Gaussian, Kernel::Laplacian] {
+        let bandwidth = bandwidth_for_similarity(kernel, 2.0, 0.5).unwrap();
+        assert!((kernel.evaluate(&[0.0], &[2.0], bandwidth).unwrap() - 0.5).abs() < 1e-12);
+        assert!(bandwidth_for_similarity(kernel, 2.0, 1.0).is_err());
+    }
+
+    let points = vec![vec![0.0, 0.0], vec![3.0, 4.0], vec![0.0, 1.0]];
+    // Euclidean distances 5, 1 and sqrt(18); L1 distances 7, 1 and 6
+    assert_eq!(median_bandwidth(&points, Kernel::Gaussian).unwrap(), 18f64.sqrt());
+    assert_eq!(median_bandwidth(&points, Kernel::Laplacian).unwrap(), 6.0);
+    assert!(median_bandwidth(&points[..1], Kernel::Gaussian).is_err());
+    assert!(median_bandwidth(&[vec![1.0], vec![1.0]], Kernel::Gaussian).is_err());
+}